@@ -55,6 +55,7 @@ interface RawConversation {
   current_node?: string;
   workspace_id?: string;
   workspace_title?: string;
+  is_archived?: boolean;
 }
 
 const adapter: Adapter = {
@@ -80,7 +81,12 @@ const adapter: Adapter = {
   },
 
   async parse(path: string, opts?: ParseOptions): Promise<Conversation[]> {
-    const files = await findConversationFiles(path, { shallowOnly: false });
+    // Source options: `account` picks one export folder when several sit
+    // side by side; `include_archived = false` skips archived chats.
+    const account = typeof opts?.options?.account === 'string' ? opts.options.account : undefined;
+    const includeArchived = opts?.options?.include_archived !== false;
+    const root = account ? join(path, account) : path;
+    const files = await findConversationFiles(root, { shallowOnly: false });
     if (files.length === 0) {
       return [];
     }
@@ -106,6 +112,9 @@ const adapter: Adapter = {
         if (!entry || typeof entry !== 'object' || !entry.mapping) {
           continue;
         }
+        if (!includeArchived && entry.is_archived) {
+          continue;
+        }
 
         const conv = parseConversation(entry, opts);
         if (!conv) {
@@ -162,7 +171,7 @@ function sortConversations(conversations: Conversation[]): Conversation[] {
 }
 
 function parseConversation(entry: RawConversation, opts?: ParseOptions): Conversation | null {
-  const messages = extractMessages(entry.mapping);
+  const messages = extractMessages(entry.mapping, entry.current_node);
 
   if (messages.length === 0) {
     return null;
@@ -202,47 +211,101 @@ function parseConversation(entry: RawConversation, opts?: ParseOptions): Convers
   return conversation;
 }
 
-function extractMessages(mapping?: ConversationMap): Message[] {
+/**
+ * Flatten the mapping tree depth-first so parents precede children, visiting
+ * the child that leads to `currentNode` last. The active branch therefore
+ * ends at the last message. When the tree actually branches (regenerated
+ * answers, edited prompts) every message records `parentIdx`, so alternate
+ * branches are kept instead of interleaved by time.
+ */
+function extractMessages(mapping?: ConversationMap, currentNode?: string): Message[] {
   if (!mapping) return [];
 
-  const messages: Message[] = [];
+  const activePath = new Set<string>();
+  for (let id = currentNode; id && mapping[id] && !activePath.has(id); ) {
+    activePath.add(id);
+    id = mapping[id].parent ?? undefined;
+  }
 
-  for (const node of Object.values(mapping)) {
-    const msg = node.message;
-    if (!msg || !msg.author?.role) {
-      continue;
+  // Children from parent links; some exports omit or truncate `children`.
+  const childrenOf = new Map<string, string[]>();
+  for (const [id, node] of Object.entries(mapping)) {
+    if (node.parent && mapping[node.parent]) {
+      const siblings = childrenOf.get(node.parent) ?? [];
+      siblings.push(id);
+      childrenOf.set(node.parent, siblings);
     }
+  }
 
-    const content = extractContent(msg.content);
-    if (!content) {
-      continue;
-    }
+  const nodeTime = (id: string) => mapping[id]?.message?.create_time ?? 0;
+  const orderChildren = (ids: string[]) =>
+    ids
+      .filter((id) => mapping[id])
+      .sort((a, b) => {
+        const aActive = activePath.has(a) ? 1 : 0;
+        const bActive = activePath.has(b) ? 1 : 0;
+        return aActive - bActive || nodeTime(a) - nodeTime(b);
+      });
+
+  const roots = orderChildren(
+    Object.keys(mapping).filter((id) => {
+      const parent = mapping[id].parent;
+      return !parent || !mapping[parent];
+    }),
+  );
 
-    const createdAt = msg.create_time ? Math.floor(msg.create_time * 1000) : undefined;
+  const messages: Message[] = [];
+  const visited = new Set<string>();
+  // Stack of [node id, index of the nearest kept ancestor]; pushed in
+  // reverse so children are visited in order.
+  const stack: Array<[string, number | undefined]> = roots
+    .reverse()
+    .map((id) => [id, undefined]);
+
+  while (stack.length > 0) {
+    const [id, parentIdx] = stack.pop()!;
+    if (visited.has(id)) continue;
+    visited.add(id);
+
+    const node = mapping[id];
+    let idx = parentIdx;
+    const message = node.message ? toMessage(node, node.message) : null;
+    if (message) {
+      messages.push({ ...message, parentIdx });
+      idx = messages.length - 1;
+    }
+    const children = orderChildren(childrenOf.get(id) ?? []);
+    for (let i = children.length - 1; i >= 0; i--) {
+      stack.push([children[i], idx]);
+    }
+  }
 
-    messages.push({
-      role: mapRole(msg.author.role),
-      content,
-      parts: textOnlyParts(content),
-      createdAt,
-      model: extractModel(msg.metadata),
-      metadata: {
-        id: msg.id,
-        nodeId: node.id,
-        parentId: node.parent ?? undefined,
-        contentType: msg.content?.content_type,
-      },
-    });
+  const linear = messages.every((msg, i) => msg.parentIdx === (i === 0 ? undefined : i - 1));
+  if (linear) {
+    for (const msg of messages) delete msg.parentIdx;
   }
+  return messages;
+}
 
-  messages.sort((a, b) => {
-    const aTime = a.createdAt ?? 0;
-    const bTime = b.createdAt ?? 0;
-    if (aTime !== bTime) return aTime - bTime;
-    return a.content.localeCompare(b.content);
-  });
+function toMessage(node: ConversationNode, msg: ConversationMessage): Message | null {
+  if (!msg.author?.role) return null;
 
-  return messages;
+  const content = extractContent(msg.content);
+  if (!content) return null;
+
+  return {
+    role: mapRole(msg.author.role),
+    content,
+    parts: textOnlyParts(content),
+    createdAt: msg.create_time ? Math.floor(msg.create_time * 1000) : undefined,
+    model: extractModel(msg.metadata),
+    metadata: {
+      id: msg.id,
+      nodeId: node.id,
+      parentId: node.parent ?? undefined,
+      contentType: msg.content?.content_type,
+    },
+  };
 }
 
 function extractContent(content: ConversationMessage['content']): string {
@@ -23,6 +23,7 @@ import {
   toolResultPart,
   textOnlyParts,
   isUnderCanonicalRoot,
+  unchangedFileCheck,
 } from '../types/index.ts';
 import { findFirstRealUserMessage, formatFrumTitle } from '../types/first-message.ts';
 
@@ -105,8 +106,10 @@ const adapter: Adapter = {
     if (files.length === 0) return [];
 
     const conversations: Conversation[] = [];
+    const isUnchanged = unchangedFileCheck(opts);
 
     for (const filePath of files) {
+      if (isUnchanged(filePath)) continue;
       const raw = await readFile(filePath, 'utf-8');
       const lines = raw.split(/\r?\n/).filter(line => line.trim().length > 0);
       if (lines.length === 0) continue;
@@ -48,6 +48,7 @@ import {
   toolCallPart,
   toolResultPart,
   isUnderAnyCanonicalRoot,
+  unchangedFileCheck,
 } from '../types/index.ts';
 import { isSystemContext } from '../types/first-message.ts';
 
@@ -136,8 +137,10 @@ const adapter: Adapter = {
 
     const threadIndex = loadThreadIndex(path);
     const conversations: Conversation[] = [];
+    const isUnchanged = unchangedFileCheck(opts);
 
     for (const filePath of files) {
+      if (isUnchanged(filePath)) continue;
       const conv = await parseRolloutFile(filePath, threadIndex, opts);
       if (conv) {
         conversations.push(conv);
@@ -61,6 +61,13 @@ export interface Message {
   toolCalls?: ToolCall[];
   attachments?: Attachment[];
   metadata?: Record<string, unknown>;
+  /**
+   * Index (in the conversation's `messages`) of the message this one
+   * continues from. Set it on every message of a conversation that branches
+   * (regenerations, edited prompts) and list the active branch's leaf last;
+   * leave it unset for linear conversations.
+   */
+  parentIdx?: number;
 }
 
 /** A tool call within a message */
@@ -91,6 +98,8 @@ export interface ParseOptions {
   includeAttachments?: boolean;
   cursor?: unknown;       // Adapter-defined incremental cursor
   batchSize?: number;     // Max conversations per batch
+  options?: Record<string, unknown>; // Per-source options (`hstry source set-option`)
+  unchangedFiles?: string[]; // Files unchanged since the last completed sync
 }
 
 /** Export formats supported by adapters */
@@ -212,6 +221,19 @@ export function isUnderAnyCanonicalRoot(candidate: string, roots: string[]): boo
   return roots.some(root => isUnderCanonicalRoot(candidate, root));
 }
 
+/**
+ * Returns a check for files the host found unchanged since the last
+ * completed sync (`opts.unchangedFiles`). Their conversations are already
+ * stored, so file-per-session adapters can skip reading them. Paths are
+ * compared exactly after collapsing repeated slashes, like
+ * `isUnderCanonicalRoot`.
+ */
+export function unchangedFileCheck(opts?: ParseOptions): (filePath: string) => boolean {
+  const collapse = (path: string) => path.replace(/\/{2,}/g, '/');
+  const files = new Set((opts?.unchangedFiles ?? []).map(collapse));
+  return filePath => files.has(collapse(filePath));
+}
+
 /** Adapter metadata */
 export interface AdapterInfo {
   name: string;
@@ -234,6 +256,13 @@ export interface Adapter {
   /** Optional: parse a batch with cursor/backpressure */
   parseStream?(path: string, opts?: ParseOptions): Promise<ParseStreamResult>;
 
+  /**
+   * Optional: yield conversations one at a time. Preferred for large sources:
+   * each one is written to hstry as soon as it is yielded, so neither side
+   * holds the whole source in memory.
+   */
+  parseIter?(path: string, opts?: ParseOptions): AsyncIterable<Conversation>;
+
   /** Optional: supports incremental sync */
   supportsIncremental?: boolean;
   
@@ -256,6 +285,7 @@ export type AdapterRequest =
   | { method: 'detect'; params: { path: string } }
   | { method: 'parse'; params: { path: string; opts?: ParseOptions } }
   | { method: 'parseStream'; params: { path: string; opts?: ParseOptions } }
+  | { method: 'parseNdjson'; params: { path: string; opts?: ParseOptions } }
   | { method: 'export'; params: { conversations: Conversation[]; opts: ExportOptions } };
 
 /** Response to hstry runtime */
@@ -307,15 +337,170 @@ function sanitizeJson(obj: unknown): unknown {
   return obj;
 }
 
+/** One line of a `parseNdjson` response. */
+export type NdjsonLine =
+  | { conversation: Conversation }
+  | { cursor: unknown }
+  | { done: true }
+  | { error: string };
+
+/** Write one NDJSON line, waiting for stdout to drain when it is full. */
+async function writeLine(line: NdjsonLine): Promise<void> {
+  await writeText(JSON.stringify(sanitizeJson(line)) + '\n');
+}
+
+async function writeText(text: string): Promise<void> {
+  if (!process.stdout.write(text)) {
+    await new Promise<void>((resolve) => process.stdout.once('drain', () => resolve()));
+  }
+}
+
+/**
+ * Stream conversations as NDJSON: one `{conversation}` line each, a
+ * `{cursor}` line after every completed `parseStream` batch, then `{done}`.
+ * Uses `parseIter` when the adapter has it, otherwise drives `parseStream`
+ * or `parse` in-process.
+ */
+async function streamConversations(adapter: Adapter, path: string, opts?: ParseOptions): Promise<void> {
+  if (adapter.parseIter) {
+    for await (const conversation of adapter.parseIter(path, opts)) {
+      await writeLine({ conversation });
+    }
+  } else if (adapter.parseStream) {
+    let cursor = opts?.cursor;
+    for (;;) {
+      const batch = await adapter.parseStream(path, { ...opts, cursor });
+      for (const conversation of batch.conversations) {
+        await writeLine({ conversation });
+      }
+      if (batch.cursor !== undefined) {
+        cursor = batch.cursor;
+        await writeLine({ cursor });
+      }
+      // Without a new cursor another call would return the same batch.
+      if (batch.done || batch.cursor === undefined) {
+        break;
+      }
+    }
+  } else {
+    for (const conversation of await adapter.parse(path, opts)) {
+      await writeLine({ conversation });
+    }
+  }
+  await writeLine({ done: true });
+}
+
+/** Answer a single-response request (everything but `parseNdjson`). */
+async function handleRequest(adapter: Adapter, request: AdapterRequest): Promise<AdapterResponse> {
+  switch (request.method) {
+    case 'info':
+      return adapter.info();
+    case 'detect':
+      return await adapter.detect(request.params.path);
+    case 'parse':
+      return await adapter.parse(request.params.path, request.params.opts);
+    case 'parseStream':
+      if (!adapter.parseStream) {
+        return { error: 'Adapter does not support parseStream' };
+      }
+      return await adapter.parseStream(request.params.path, request.params.opts);
+    case 'export':
+      if (!adapter.export) {
+        return { error: 'Adapter does not support export' };
+      }
+      return await adapter.export(request.params.conversations, request.params.opts);
+    default:
+      return { error: `Unknown method: ${(request as any).method}` };
+  }
+}
+
+function formatLogArg(arg: unknown): string {
+  if (typeof arg === 'string') return arg;
+  if (arg instanceof Error) return arg.stack ?? String(arg);
+  try {
+    return JSON.stringify(arg);
+  } catch {
+    return String(arg);
+  }
+}
+
+/**
+ * Worker mode (`HSTRY_WORKER=1`): serve JSON-RPC requests, one per line on
+ * stdin, until stdin closes. Each reply carries the console output of its
+ * request in `stderr`, so hstry can still log runs per request. Requests
+ * are handled one at a time.
+ */
+async function serveWorker(adapter: Adapter): Promise<void> {
+  let output: string[] = [];
+  const capture = (...args: unknown[]) => {
+    output.push(args.map(formatLogArg).join(' '));
+  };
+  console.log = capture;
+  console.error = capture;
+  console.warn = capture;
+  console.info = capture;
+  console.debug = capture;
+
+  const serve = async (line: string) => {
+    let id: unknown = null;
+    let reply: Record<string, unknown>;
+    try {
+      const request = JSON.parse(line) as AdapterRequest & { id?: unknown };
+      id = request.id ?? null;
+      const result = await handleRequest(adapter, request);
+      reply = { jsonrpc: '2.0', id, result: sanitizeJson(result) ?? null };
+    } catch (err) {
+      capture(err instanceof Error && err.stack ? err.stack : String(err));
+      reply = { jsonrpc: '2.0', id, error: { code: -32603, message: String(err) } };
+    }
+    reply.stderr = output.join('\n');
+    output = [];
+    await writeText(JSON.stringify(reply) + '\n');
+  };
+
+  // Decode as a stream so characters split across chunks survive.
+  const decoder = new TextDecoder();
+  let pending = '';
+  for await (const chunk of process.stdin) {
+    pending += decoder.decode(chunk, { stream: true });
+    let newline: number;
+    while ((newline = pending.indexOf('\n')) !== -1) {
+      const line = pending.slice(0, newline);
+      pending = pending.slice(newline + 1);
+      if (line.trim()) {
+        await serve(line);
+      }
+    }
+  }
+}
+
 /** 
  * Main entry point for adapters.
  * Handles the request/response protocol with the Rust runtime.
  */
 export function runAdapter(adapter: Adapter): void {
+  const worker = process.env.HSTRY_WORKER === '1' ||
+                 (typeof Bun !== 'undefined' && Bun?.env?.HSTRY_WORKER === '1') ||
+                 (typeof Deno !== 'undefined' && Deno?.env?.get?.('HSTRY_WORKER') === '1');
+  if (worker) {
+    serveWorker(adapter).catch((err) => {
+      process.stderr.write(`${err instanceof Error && err.stack ? err.stack : String(err)}\n`);
+      process.exit(1);
+    });
+    return;
+  }
+
   const useStdin = process.env.HSTRY_REQUEST_STDIN === '1' || 
                    (typeof Bun !== 'undefined' && Bun?.env?.HSTRY_REQUEST_STDIN === '1') ||
                    (typeof Deno !== 'undefined' && Deno?.env?.get?.('HSTRY_REQUEST_STDIN') === '1');
 
+  // stdout carries the JSON response; send adapter console output to stderr,
+  // where hstry captures it into the per-run adapter log.
+  const respond = console.log.bind(console);
+  console.log = console.error.bind(console);
+  console.info = console.error.bind(console);
+  console.debug = console.error.bind(console);
+
   (async () => {
     try {
       let requestJson: string;
@@ -335,45 +520,24 @@ export function runAdapter(adapter: Adapter): void {
       }
 
       const request: AdapterRequest = JSON.parse(requestJson);
-      let response: AdapterResponse;
-
-      switch (request.method) {
-        case 'info':
-          response = adapter.info();
-          break;
-        case 'detect':
-          response = await adapter.detect(request.params.path);
-          break;
-        case 'parse':
-          response = await adapter.parse(request.params.path, request.params.opts);
-          break;
-        case 'parseStream':
-          if (!adapter.parseStream) {
-            response = { error: 'Adapter does not support parseStream' };
-            break;
-          }
-          response = await adapter.parseStream(request.params.path, request.params.opts);
-          break;
-        case 'export':
-          if (!adapter.export) {
-            response = { error: 'Adapter does not support export' };
-            break;
-          }
-          response = await adapter.export(request.params.conversations, request.params.opts);
-          break;
-        default:
-          response = { error: `Unknown method: ${(request as any).method}` };
+
+      if (request.method === 'parseNdjson') {
+        await streamConversations(adapter, request.params.path, request.params.opts);
+        return;
       }
 
+      const response = await handleRequest(adapter, request);
+
       // Sanitize any unpaired surrogates that may exist in strings parsed from
       // source files (e.g., OpenCode parts containing mathematical Unicode
       // symbols stored as JSON surrogate pairs). Without this, JSON.stringify
       // can produce WTF-8 bytes that are invalid UTF-8, causing the Rust
       // runtime to reject the output.
       const sanitized = sanitizeJson(response);
-      console.log(JSON.stringify(sanitized));
+      respond(JSON.stringify(sanitized));
     } catch (err) {
-      console.log(JSON.stringify({ error: String(err) }));
+      console.error(err instanceof Error && err.stack ? err.stack : String(err));
+      respond(JSON.stringify({ error: String(err) }));
       process.exit(1);
     }
   })();
@@ -4,26 +4,49 @@ use std::path::PathBuf;
 use std::sync::Arc;
 
 use anyhow::Result;
-use axum::extract::{DefaultBodyLimit, Query, State};
-use axum::http::{HeaderMap, StatusCode};
+use axum::body::Body;
+use axum::extract::{DefaultBodyLimit, Extension, Path, Query, Request, State};
+use axum::http::{HeaderMap, Method, StatusCode, header};
+use axum::middleware::{self, Next};
+use axum::response::Response;
 use axum::routing::{get, post};
+use axum::serve::Listener;
 use axum::{Json, Router};
-use chrono::Utc;
+use chrono::{SecondsFormat, Utc};
 use clap::{Args, Parser};
 use log::info;
 use serde::{Deserialize, Serialize};
 use tower_http::cors::{Any, CorsLayer};
 use tower_http::trace::TraceLayer;
 
-use hstry_core::db::{SearchMode, SearchOptions};
-use hstry_core::ingest::ingest_batch;
-use hstry_core::models::Source;
-use hstry_core::parsed::ParsedConversation;
-use hstry_core::{Config, Database};
+use hstry_core::api_token::{self, ApiTokenScope};
+use hstry_core::db::{
+    ActivityStats, ListConversationsOptions, MessageRange, SearchMode, SearchOptions, SourceStats,
+};
+use hstry_core::ingest::{append_messages, ingest_batch};
+use hstry_core::ingest_buffer::{BufferedWrite, IngestBuffer};
+use hstry_core::models::{Conversation, Message, SearchHit, Source};
+use hstry_core::parsed::{ParsedConversation, ParsedMessage};
+use hstry_core::remote::{self, PushBatch, SyncResult};
+use hstry_core::search_groups::{self, ConversationHits};
+use hstry_core::{Config, Database, DatabaseOptions};
+
+use crate::listen::{ListenAddr, TlsListener};
+
+mod listen;
 
 /// Ingest payloads carry full conversation histories; allow generous bodies.
 const INGEST_BODY_LIMIT: usize = 64 * 1024 * 1024;
 
+/// Page size for `/conversations` when the client does not pass `limit`.
+const DEFAULT_PAGE_SIZE: i64 = 50;
+
+/// Upper bound on `/conversations` page size.
+const MAX_PAGE_SIZE: i64 = 500;
+
+/// Window (days) for the activity block in `/stats`.
+const STATS_ACTIVITY_DAYS: i64 = 30;
+
 fn main() {
     if let Err(err) = try_main() {
         let _ = writeln!(io::stderr(), "{err:?}");
@@ -42,7 +65,17 @@ async fn try_main() -> Result<()> {
         .unwrap_or_else(Config::default_config_path);
     let config = Config::ensure_at(&config_path)?;
 
-    let db = Database::open(&config.database).await?;
+    let db = Database::open_with(
+        &config.database.path,
+        &DatabaseOptions::from_config(&config, true)?,
+    )
+    .await?;
+    if config.sensitive.api {
+        db.follow_unlock_session(&config.database.path);
+    }
+    if let Err(err) = db.prune_access_log(config.access_log.max_age_days).await {
+        log::warn!("failed to prune the access log: {err}");
+    }
 
     let ingest_token = cli
         .common
@@ -50,17 +83,43 @@ async fn try_main() -> Result<()> {
         .clone()
         .or_else(|| std::env::var("HSTRY_API_TOKEN").ok())
         .filter(|t| !t.is_empty());
-    let has_token = ingest_token.is_some();
+    let has_token = ingest_token.is_some() || db.has_api_tokens().await?;
     if !has_token {
         info!(
-            "No ingest token configured (set --token or HSTRY_API_TOKEN); /ingest accepts any loopback client"
+            "No API token configured (run `hstry api token create` or set --token); /ingest accepts any loopback client"
+        );
+    }
+
+    let listen_addr =
+        cli.common.listen.clone().unwrap_or_else(|| {
+            ListenAddr::Tcp(SocketAddr::from(([127, 0, 0, 1], cli.common.port)))
+        });
+    let require_auth = matches!(&listen_addr, ListenAddr::Tcp(addr) if !addr.ip().is_loopback());
+    if require_auth && !has_token {
+        anyhow::bail!(
+            "Refusing to listen on {listen_addr} without a token (run `hstry api token create` or set --token)"
         );
     }
 
+    let db = Arc::new(db);
+    let ingest_buffer = if config.storage.ingest_buffer.enabled {
+        let buffer = Arc::new(IngestBuffer::open(
+            Arc::clone(&db),
+            &config.storage.ingest_buffer,
+            config.ingest_buffer_journal_path(),
+        )?);
+        buffer.spawn_flusher(&config.storage.ingest_buffer);
+        Some(buffer)
+    } else {
+        None
+    };
+
     let state = AppState {
         config: Arc::new(config),
-        db: Arc::new(db),
+        db,
         ingest_token: Arc::new(ingest_token),
+        ingest_buffer: ingest_buffer.clone(),
+        require_auth,
     };
 
     let cors = CorsLayer::new()
@@ -73,33 +132,108 @@ async fn try_main() -> Result<()> {
         .route("/health", get(health))
         .route("/config", get(get_config))
         .route("/search", get(search))
-        .route("/sources", post(register_source))
+        .route("/sources", get(list_sources).post(register_source))
+        .route("/conversations", get(list_conversations))
+        .route(
+            "/conversations/{id}",
+            get(get_conversation).delete(delete_conversation),
+        )
+        .route(
+            "/conversations/{id}/messages",
+            get(get_conversation_messages),
+        )
+        .route("/stats", get(stats))
+        .route("/counts", get(counts))
         .route(
             "/ingest",
             post(ingest).layer(DefaultBodyLimit::max(INGEST_BODY_LIMIT)),
         )
+        .route(
+            "/ingest/messages",
+            post(ingest_messages).layer(DefaultBodyLimit::max(INGEST_BODY_LIMIT)),
+        )
+        .route("/sync/export", get(sync_export))
+        .route(
+            "/sync/import",
+            post(sync_import).layer(DefaultBodyLimit::max(INGEST_BODY_LIMIT)),
+        )
+        .layer(middleware::from_fn_with_state(state.clone(), authorize))
         .layer(cors)
         .layer(TraceLayer::new_for_http())
         .with_state(state);
 
-    let addr = SocketAddr::from(([127, 0, 0, 1], cli.common.port));
-    info!("Starting API server on {addr}");
+    let tls = match (&cli.common.tls_cert, &cli.common.tls_key) {
+        (Some(_), _) if matches!(listen_addr, ListenAddr::Unix(_)) => {
+            anyhow::bail!("--tls-cert/--tls-key cannot be used with a Unix socket")
+        }
+        (Some(cert), Some(key)) => Some(listen::tls_acceptor(cert, key)?),
+        _ => None,
+    };
+    info!("Starting API server on {listen_addr}");
+
+    let url = match (&listen_addr, &tls) {
+        (ListenAddr::Tcp(addr), None) => format!("http://{addr}"),
+        (ListenAddr::Tcp(addr), Some(_)) => format!("https://{addr}"),
+        (ListenAddr::Unix(_), _) => listen_addr.to_string(),
+    };
+    let auth = match (&listen_addr, has_token) {
+        (_, true) => "token required",
+        (ListenAddr::Tcp(_), false) => "open on loopback",
+        (ListenAddr::Unix(_), false) => "open to the socket's owner",
+    };
+    match listen_addr {
+        ListenAddr::Tcp(addr) => {
+            let listener = tokio::net::TcpListener::bind(addr).await?;
+            print_banner(&url, auth);
+            match tls {
+                Some(acceptor) => serve(TlsListener::new(listener, acceptor)?, app).await?,
+                None => serve(listener, app).await?,
+            }
+        }
+        #[cfg(unix)]
+        ListenAddr::Unix(path) => {
+            let listener = listen::bind_unix(&path)?;
+            print_banner(&url, auth);
+            serve(listener, app).await?;
+            let _ = std::fs::remove_file(&path);
+        }
+        #[cfg(not(unix))]
+        ListenAddr::Unix(_) => {
+            anyhow::bail!("Unix domain sockets are not supported on this platform")
+        }
+    }
+
+    if let Some(buffer) = ingest_buffer {
+        let outcome = buffer.flush().await?;
+        info!(
+            "Flushed {} buffered ingest writes on shutdown",
+            outcome.writes
+        );
+    }
+
+    Ok(())
+}
 
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-    // Unconditional banner: env_logger is silent without RUST_LOG, which makes
-    // a healthy server look hung. Print one line so the user sees it is up.
+/// Unconditional banner: env_logger is silent without RUST_LOG, which makes
+/// a healthy server look hung. Print one line so the user sees it is up.
+fn print_banner(url: &str, auth: &str) {
     let _ = writeln!(
         io::stderr(),
-        "hstry-api listening on http://{addr}  (ingest auth: {}, set RUST_LOG=info,tower_http=debug for request logs)",
-        if has_token {
-            "token required"
-        } else {
-            "open on loopback"
-        }
+        "hstry-api listening on {url}  (auth: {auth}, set RUST_LOG=info,tower_http=debug for request logs)"
     );
-    axum::serve(listener, app).await?;
+}
 
-    Ok(())
+/// Serve `app` on `listener` until Ctrl-C.
+async fn serve<L>(listener: L, app: Router) -> io::Result<()>
+where
+    L: Listener,
+    L::Addr: std::fmt::Debug,
+{
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await
 }
 
 #[derive(Debug, Parser)]
@@ -119,7 +253,22 @@ struct CommonOpts {
     #[arg(short, long, default_value = "3000")]
     port: u16,
 
-    /// Bearer token required for /ingest (falls back to HSTRY_API_TOKEN)
+    /// Address to listen on instead of 127.0.0.1:<port>: `host:port`, or
+    /// `unix:<path>` for a Unix domain socket. TCP addresses other than
+    /// loopback require a token
+    #[arg(long, value_name = "ADDR")]
+    listen: Option<ListenAddr>,
+
+    /// PEM certificate chain; serves HTTPS instead of HTTP
+    #[arg(long, value_name = "PATH", requires = "tls_key")]
+    tls_cert: Option<PathBuf>,
+
+    /// PEM private key for --tls-cert
+    #[arg(long, value_name = "PATH", requires = "tls_cert")]
+    tls_key: Option<PathBuf>,
+
+    /// Bearer token with the `ingest` scope, alongside tokens from `hstry api
+    /// token create` (falls back to HSTRY_API_TOKEN)
     #[arg(long, value_name = "TOKEN")]
     token: Option<String>,
 }
@@ -129,6 +278,12 @@ struct AppState {
     config: Arc<Config>,
     db: Arc<Database>,
     ingest_token: Arc<Option<String>>,
+    /// Set when `[storage.ingest_buffer]` is enabled: ingest requests are
+    /// queued and committed in batches instead of one transaction each.
+    ingest_buffer: Option<Arc<IngestBuffer>>,
+    /// Set when listening beyond loopback: every request needs a token, even
+    /// after the last managed token is revoked.
+    require_auth: bool,
 }
 
 #[derive(Serialize)]
@@ -165,9 +320,9 @@ struct SearchQuery {
     source: Option<String>,
     workspace: Option<String>,
     mode: Option<String>,
-    /// ISO 8601 timestamp: only messages after this time
+    /// Only messages after this time (ISO 8601 or relative: "2 weeks ago", "3d")
     after: Option<String>,
-    /// ISO 8601 timestamp: only messages before this time
+    /// Only messages before this time
     before: Option<String>,
     /// Filter by message role
     role: Option<String>,
@@ -177,29 +332,52 @@ struct SearchQuery {
     harness: Option<String>,
     /// Filter by conversation tag
     tag: Option<String>,
+    /// Only starred conversations
+    #[serde(default)]
+    starred: bool,
+    /// Comma-separated records to embed in each hit (`conversation`)
+    expand: Option<String>,
+    /// Messages to include before and after each hit
+    context: Option<usize>,
+    /// `conversation` returns one entry per conversation instead of per hit
+    group_by: Option<String>,
+}
+
+/// `/search` response: hits, or conversations with `group_by=conversation`.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum SearchResponse {
+    Hits(Vec<SearchHit>),
+    Conversations(Vec<ConversationHits>),
 }
 
 async fn search(
     State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
     Query(params): Query<SearchQuery>,
-) -> Result<Json<Vec<hstry_core::models::SearchHit>>, StatusCode> {
+) -> Result<Json<SearchResponse>, StatusCode> {
     let mode = match params.mode.as_deref() {
         Some("auto") | None => SearchMode::Auto,
         Some("natural" | "natural_language") => SearchMode::NaturalLanguage,
         Some("code") => SearchMode::Code,
         _ => return Err(StatusCode::BAD_REQUEST),
     };
+    let mut expand_conversation = false;
+    for field in params.expand.as_deref().unwrap_or_default().split(',') {
+        match field.trim() {
+            "" => {}
+            "conversation" => expand_conversation = true,
+            _ => return Err(StatusCode::BAD_REQUEST),
+        }
+    }
+    let group_by_conversation = match params.group_by.as_deref() {
+        None | Some("" | "message") => false,
+        Some("conversation") => true,
+        Some(_) => return Err(StatusCode::BAD_REQUEST),
+    };
 
-    let after = params
-        .after
-        .as_deref()
-        .and_then(|s| dateparser::parse(s).ok())
-        .map(|dt| dt.with_timezone(&chrono::Utc));
-    let before = params
-        .before
-        .as_deref()
-        .and_then(|s| dateparser::parse(s).ok())
-        .map(|dt| dt.with_timezone(&chrono::Utc));
+    let after = parse_timestamp(params.after.as_deref())?;
+    let before = parse_timestamp(params.before.as_deref())?;
 
     let source = params.source.clone();
     let workspace = params.workspace.clone();
@@ -208,14 +386,19 @@ async fn search(
     let harness = params.harness.clone();
     let tag = params.tag.clone();
 
-    let results = state
+    let mut results = state
         .db
         .search(
             &params.query,
             SearchOptions {
                 source_id: source,
                 workspace,
-                limit: params.limit,
+                // Grouping folds hits together; fetch enough to fill the page.
+                limit: if group_by_conversation {
+                    params.limit.map(|limit| limit.saturating_mul(4))
+                } else {
+                    params.limit
+                },
                 offset: params.offset,
                 mode,
                 after,
@@ -224,12 +407,46 @@ async fn search(
                 model,
                 harness,
                 tag,
+                starred: params.starred,
             },
         )
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if expand_conversation {
+        state
+            .db
+            .expand_search_hits(&mut results)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+    if let Some(messages) = params.context.filter(|&n| n > 0) {
+        state
+            .db
+            .add_search_context(&mut results, messages)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
 
-    Ok(Json(results))
+    let response = if group_by_conversation {
+        let mut groups = search_groups::group_by_conversation(results);
+        if let Some(limit) = params.limit {
+            groups.truncate(usize::try_from(limit.max(0)).unwrap_or(usize::MAX));
+        }
+        SearchResponse::Conversations(groups)
+    } else {
+        SearchResponse::Hits(results)
+    };
+
+    let mut ids: Vec<_> = match &response {
+        SearchResponse::Hits(hits) => hits.iter().map(|hit| hit.conversation_id).collect(),
+        SearchResponse::Conversations(groups) => {
+            groups.iter().map(|group| group.conversation_id).collect()
+        }
+    };
+    ids.sort_unstable();
+    ids.dedup();
+    log_access(&state, &caller, "search", Some(&params.query), &ids).await;
+    Ok(Json(response))
 }
 
 #[derive(Debug, Deserialize)]
@@ -250,6 +467,37 @@ struct IngestResponse {
     created: usize,
     updated: usize,
     messages: usize,
+    /// The write was queued in the ingest buffer and commits on the next
+    /// flush; `created`/`updated` are not known yet.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    buffered: bool,
+}
+
+impl IngestResponse {
+    fn buffered(source: &str, conversations: usize, messages: usize) -> (StatusCode, Json<Self>) {
+        (
+            StatusCode::ACCEPTED,
+            Json(Self {
+                source: source.to_string(),
+                conversations,
+                created: 0,
+                updated: 0,
+                messages,
+                buffered: true,
+            }),
+        )
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IngestMessagesRequest {
+    source: String,
+    /// External id of a conversation previously pushed via `/ingest`.
+    external_id: String,
+    /// Position of the first message; defaults to appending after the last.
+    start_idx: Option<i32>,
+    messages: Vec<ParsedMessage>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -267,17 +515,100 @@ struct RegisterSourceResponse {
     created: bool,
 }
 
-fn authorize_ingest(state: &AppState, headers: &HeaderMap) -> Result<(), StatusCode> {
-    if let Some(expected) = state.ingest_token.as_ref() {
-        let provided = headers
-            .get("authorization")
-            .and_then(|value| value.to_str().ok())
-            .and_then(|value| value.strip_prefix("Bearer "));
-        if provided != Some(expected.as_str()) {
-            return Err(StatusCode::UNAUTHORIZED);
+/// Bearer token from an `Authorization` header.
+/// Who made a request: the name of the API token it carried, `"--token"`
+/// for the shared secret, or `None` for open reads.
+#[derive(Debug, Clone, Default)]
+struct Caller(Option<String>);
+
+/// Record a read in the access log when `[access_log]` is enabled. A
+/// failure to record is logged; the read itself still succeeds.
+async fn log_access(
+    state: &AppState,
+    caller: &Caller,
+    action: &str,
+    query: Option<&str>,
+    conversation_ids: &[uuid::Uuid],
+) {
+    if !state.config.access_log.enabled {
+        return;
+    }
+    if let Err(err) = state
+        .db
+        .record_access("api", caller.0.as_deref(), action, query, conversation_ids)
+        .await
+    {
+        log::warn!("failed to record {action} in the access log: {err}");
+    }
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+}
+
+/// Check the request's bearer token. `GET` requests need the `read` scope,
+/// anything else `ingest`. Until a token has been created with `hstry api
+/// token create`, reads stay open on loopback and writes only need `--token`
+/// when one is set; `/` and `/health` are always open.
+async fn authorize(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    let path = request.uri().path();
+    if path == "/" || path == "/health" {
+        return Ok(next.run(request).await);
+    }
+    let needed = if request.method() == Method::GET || request.method() == Method::HEAD {
+        ApiTokenScope::Read
+    } else {
+        ApiTokenScope::Ingest
+    };
+    let provided = bearer_token(request.headers());
+    if let (Some(provided), Some(expected)) = (provided, state.ingest_token.as_deref())
+        && provided == expected
+    {
+        request
+            .extensions_mut()
+            .insert(Caller(Some("--token".to_string())));
+        return Ok(next.run(request).await);
+    }
+
+    if let Some(provided) = provided {
+        let token = state
+            .db
+            .find_api_token(&api_token::hash_token(provided))
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        if let Some(token) = token {
+            if !token.scope.allows(needed) {
+                return Err(StatusCode::FORBIDDEN);
+            }
+            if let Err(err) = state.db.touch_api_token(token.id).await {
+                log::warn!("failed to record use of API token {}: {err}", token.id);
+            }
+            request.extensions_mut().insert(Caller(Some(token.name)));
+            return Ok(next.run(request).await);
         }
     }
-    Ok(())
+
+    let managed = state
+        .db
+        .has_api_tokens()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let open = !state.require_auth
+        && !managed
+        && (needed == ApiTokenScope::Read || state.ingest_token.is_none());
+    if open {
+        request.extensions_mut().insert(Caller::default());
+        Ok(next.run(request).await)
+    } else {
+        Err(StatusCode::UNAUTHORIZED)
+    }
 }
 
 fn valid_source_id(source_id: &str) -> bool {
@@ -289,10 +620,8 @@ fn valid_source_id(source_id: &str) -> bool {
 
 async fn register_source(
     State(state): State<AppState>,
-    headers: HeaderMap,
     Json(req): Json<RegisterSourceRequest>,
 ) -> Result<Json<RegisterSourceResponse>, StatusCode> {
-    authorize_ingest(&state, &headers)?;
     let source_id = req.source.trim();
     let adapter = req.adapter.trim();
     if !valid_source_id(source_id) || adapter.is_empty() {
@@ -327,16 +656,30 @@ async fn register_source(
 
 async fn ingest(
     State(state): State<AppState>,
-    headers: HeaderMap,
     Json(req): Json<IngestRequest>,
-) -> Result<Json<IngestResponse>, StatusCode> {
-    authorize_ingest(&state, &headers)?;
-
+) -> Result<(StatusCode, Json<IngestResponse>), StatusCode> {
     let source_id = req.source.trim();
     if !valid_source_id(source_id) {
         return Err(StatusCode::BAD_REQUEST);
     }
 
+    if let Some(buffer) = &state.ingest_buffer {
+        let conversations = req.conversations.len();
+        let messages = req.conversations.iter().map(|c| c.messages.len()).sum();
+        buffer
+            .push(BufferedWrite::Conversations {
+                source: source_id.to_string(),
+                adapter: req.adapter,
+                conversations: req.conversations,
+            })
+            .await
+            .map_err(|err| {
+                log::error!("failed to buffer ingest for source '{source_id}': {err:?}");
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+        return Ok(IngestResponse::buffered(source_id, conversations, messages));
+    }
+
     let mut source = state
         .db
         .get_source(source_id)
@@ -372,11 +715,464 @@ async fn ingest(
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    Ok(Json(IngestResponse {
-        source: source_id.to_string(),
-        conversations: outcome.conversations,
-        created: outcome.created,
-        updated: outcome.updated,
-        messages: outcome.messages,
+    Ok((
+        StatusCode::OK,
+        Json(IngestResponse {
+            source: source_id.to_string(),
+            conversations: outcome.conversations,
+            created: outcome.created,
+            updated: outcome.updated,
+            messages: outcome.messages,
+            buffered: false,
+        }),
+    ))
+}
+
+fn parse_timestamp(value: Option<&str>) -> Result<Option<chrono::DateTime<Utc>>, StatusCode> {
+    value
+        .map(|s| hstry_core::dates::parse_date_filter(s).map_err(|_| StatusCode::BAD_REQUEST))
+        .transpose()
+}
+
+/// Resolve a conversation by UUID, external id, platform id, or readable id.
+/// Look up a conversation by UUID or reference. Sensitive conversations are
+/// not found unless `[sensitive] api` is set.
+async fn resolve_conversation(state: &AppState, id: &str) -> Result<Conversation, StatusCode> {
+    let db = &state.db;
+    let mut conv = None;
+    if let Ok(uuid) = uuid::Uuid::parse_str(id) {
+        conv = db
+            .get_conversation(uuid)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+    if conv.is_none() {
+        conv = db
+            .get_conversation_by_reference(None, Some(id), Some(id), None, None)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+    let conv = conv.ok_or(StatusCode::NOT_FOUND)?;
+    if !state.config.sensitive.api
+        && db
+            .is_sensitive(conv.id)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(conv)
+}
+
+#[derive(Debug, Deserialize)]
+struct ListConversationsQuery {
+    source: Option<String>,
+    workspace: Option<String>,
+    /// Only conversations created after this time (ISO 8601 or relative)
+    after: Option<String>,
+    /// Only conversations created before this time
+    before: Option<String>,
+    /// Only starred conversations
+    #[serde(default)]
+    starred: bool,
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ConversationListItem {
+    #[serde(flatten)]
+    conversation: Conversation,
+    first_user_message: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ConversationListResponse {
+    conversations: Vec<ConversationListItem>,
+    limit: i64,
+    offset: i64,
+    /// Offset of the next page, absent on the last page.
+    next_offset: Option<i64>,
+}
+
+async fn list_conversations(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Query(params): Query<ListConversationsQuery>,
+) -> Result<Json<ConversationListResponse>, StatusCode> {
+    let limit = params.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    let offset = params.offset.unwrap_or(0);
+    if !(1..=MAX_PAGE_SIZE).contains(&limit) || offset < 0 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let filters = [
+        ("source", &params.source),
+        ("workspace", &params.workspace),
+        ("after", &params.after),
+        ("before", &params.before),
+    ]
+    .into_iter()
+    .filter_map(|(key, value)| value.as_ref().map(|value| format!("{key}={value}")))
+    .collect::<Vec<_>>()
+    .join(" ");
+
+    let summaries = state
+        .db
+        .list_conversation_summaries(ListConversationsOptions {
+            source_id: params.source,
+            workspace: params.workspace,
+            after: parse_timestamp(params.after.as_deref())?,
+            before: parse_timestamp(params.before.as_deref())?,
+            ingested_after: None,
+            model: None,
+            provider: None,
+            harness: None,
+            role: None,
+            exclude_sensitive: !state.config.sensitive.api,
+            starred: params.starred,
+            limit: Some(limit),
+            offset: Some(offset),
+        })
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let ids: Vec<_> = summaries
+        .iter()
+        .map(|summary| summary.conversation.id)
+        .collect();
+    let filters = (!filters.is_empty()).then_some(filters.as_str());
+    log_access(&state, &caller, "list", filters, &ids).await;
+
+    let page_len = i64::try_from(summaries.len()).unwrap_or(i64::MAX);
+    let next_offset = (page_len == limit).then_some(offset + limit);
+    let conversations = summaries
+        .into_iter()
+        .map(|summary| {
+            let mut conversation = summary.conversation;
+            conversation.message_count = summary.message_count;
+            ConversationListItem {
+                conversation,
+                first_user_message: summary.first_user_message,
+            }
+        })
+        .collect();
+
+    Ok(Json(ConversationListResponse {
+        conversations,
+        limit,
+        offset,
+        next_offset,
+    }))
+}
+
+#[derive(Debug, Serialize)]
+struct ConversationResponse {
+    #[serde(flatten)]
+    conversation: Conversation,
+    messages: Vec<Message>,
+}
+
+async fn get_conversation(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<String>,
+) -> Result<Json<ConversationResponse>, StatusCode> {
+    let conversation = resolve_conversation(&state, &id).await?;
+    let messages = state
+        .db
+        .get_messages(conversation.id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    log_access(&state, &caller, "conversation", None, &[conversation.id]).await;
+    Ok(Json(ConversationResponse {
+        conversation,
+        messages,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+struct MessagesQuery {
+    /// Only messages with `idx >= from_idx`.
+    from_idx: Option<i32>,
+    /// Only messages with `idx < to_idx`.
+    to_idx: Option<i32>,
+    offset: Option<i64>,
+    /// Omit to return every matching message.
+    limit: Option<i64>,
+}
+
+async fn get_conversation_messages(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<String>,
+    Query(params): Query<MessagesQuery>,
+) -> Result<Json<Vec<Message>>, StatusCode> {
+    if params
+        .limit
+        .is_some_and(|limit| !(1..=MAX_PAGE_SIZE).contains(&limit))
+        || params.offset.is_some_and(|offset| offset < 0)
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let conversation = resolve_conversation(&state, &id).await?;
+    let messages = state
+        .db
+        .get_messages_range(
+            conversation.id,
+            MessageRange {
+                from_idx: params.from_idx,
+                to_idx: params.to_idx,
+                offset: params.offset,
+                limit: params.limit,
+            },
+        )
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    log_access(&state, &caller, "messages", None, &[conversation.id]).await;
+    Ok(Json(messages))
+}
+
+async fn delete_conversation(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    let conversation = resolve_conversation(&state, &id).await?;
+    state
+        .db
+        .trash_conversations(&[conversation.id])
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_sources(State(state): State<AppState>) -> Result<Json<Vec<Source>>, StatusCode> {
+    let sources = state
+        .db
+        .list_sources()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(sources))
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StatsResponse {
+    sources: usize,
+    conversations: i64,
+    messages: i64,
+    per_source: Vec<SourceStats>,
+    activity: ActivityStats,
+}
+
+async fn stats(State(state): State<AppState>) -> Result<Json<StatsResponse>, StatusCode> {
+    let db = &state.db;
+    let internal = |_| StatusCode::INTERNAL_SERVER_ERROR;
+    Ok(Json(StatsResponse {
+        sources: db.list_sources().await.map_err(internal)?.len(),
+        conversations: db.count_conversations().await.map_err(internal)?,
+        messages: db.count_messages().await.map_err(internal)?,
+        per_source: db.get_source_stats().await.map_err(internal)?,
+        activity: db
+            .get_activity_stats(STATS_ACTIVITY_DAYS)
+            .await
+            .map_err(internal)?,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+struct CountsQuery {
+    /// Last time the client looked; enables `unread` and `hasNew`.
+    since: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SourceCount {
+    source_id: String,
+    conversations: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CountsResponse {
+    conversations: i64,
+    per_source: Vec<SourceCount>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unread: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    has_new: Option<bool>,
+}
+
+/// Cheap aggregates for UI badges, without loading conversation rows.
+async fn counts(
+    State(state): State<AppState>,
+    Query(params): Query<CountsQuery>,
+) -> Result<Json<CountsResponse>, StatusCode> {
+    let db = &state.db;
+    let internal = |_| StatusCode::INTERNAL_SERVER_ERROR;
+    let since = parse_timestamp(params.since.as_deref())?;
+    let per_source: Vec<SourceCount> = db
+        .count_conversations_by_source()
+        .await
+        .map_err(internal)?
+        .into_iter()
+        .map(|(source_id, conversations)| SourceCount {
+            source_id,
+            conversations,
+        })
+        .collect();
+    let (unread, has_new) = match since {
+        Some(since) => {
+            let unread = db.count_unread_since(since).await.map_err(internal)?;
+            (Some(unread), Some(unread > 0))
+        }
+        None => (None, None),
+    };
+    Ok(Json(CountsResponse {
+        conversations: per_source.iter().map(|s| s.conversations).sum(),
+        per_source,
+        unread,
+        has_new,
     }))
 }
+
+/// Append messages to a conversation already known to hstry, so live-capture
+/// clients can stream new turns without re-posting the full history.
+async fn ingest_messages(
+    State(state): State<AppState>,
+    Json(req): Json<IngestMessagesRequest>,
+) -> Result<(StatusCode, Json<IngestResponse>), StatusCode> {
+    let source_id = req.source.trim();
+    if !valid_source_id(source_id) || req.start_idx.is_some_and(|idx| idx < 0) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    if let Some(buffer) = &state.ingest_buffer {
+        let messages = req.messages.len();
+        buffer
+            .push(BufferedWrite::Messages {
+                source: source_id.to_string(),
+                external_id: req.external_id,
+                start_idx: req.start_idx,
+                messages: req.messages,
+            })
+            .await
+            .map_err(|err| {
+                log::error!("failed to buffer messages for source '{source_id}': {err:?}");
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+        return Ok(IngestResponse::buffered(source_id, 1, messages));
+    }
+
+    let outcome = append_messages(
+        &state.db,
+        source_id,
+        &req.external_id,
+        req.messages,
+        req.start_idx,
+    )
+    .await
+    .map_err(|err| {
+        log::error!("message ingest failed for source '{source_id}': {err:?}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    state
+        .db
+        .rebuild_conversation_summaries(&outcome.affected_conversation_ids)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok((
+        StatusCode::OK,
+        Json(IngestResponse {
+            source: source_id.to_string(),
+            conversations: outcome.conversations,
+            created: outcome.created,
+            updated: outcome.updated,
+            messages: outcome.messages,
+            buffered: false,
+        }),
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+struct SyncExportQuery {
+    /// Only conversations changed since this time (inclusive).
+    since: Option<String>,
+}
+
+/// Delta export for `type = "http"` remotes: this server's clock on the
+/// first line, then one conversation per line in the `hstry export --format
+/// jsonl` shape, streamed as it is read.
+async fn sync_export(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Query(params): Query<SyncExportQuery>,
+) -> Result<Response, StatusCode> {
+    // Taken before reading so nothing written during the export is skipped
+    // by the client's next pull.
+    let started_at = Utc::now();
+    let since = parse_timestamp(params.since.as_deref())?;
+    let ids = remote::delta_conversation_ids(&state.db, since)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let query = params
+        .since
+        .as_deref()
+        .map(|since| format!("since {since}"));
+    log_access(&state, &caller, "sync_export", query.as_deref(), &ids).await;
+
+    let (tx, rx) = tokio::sync::mpsc::channel::<io::Result<String>>(16);
+    let db = Arc::clone(&state.db);
+    tokio::spawn(async move {
+        let header = format!(
+            "{}\n",
+            started_at.to_rfc3339_opts(SecondsFormat::Secs, true)
+        );
+        if tx.send(Ok(header)).await.is_err() {
+            return;
+        }
+        for id in ids {
+            let line = match remote::export_conversation(&db, id).await {
+                Ok(Some(conversation)) => serde_json::to_string(&conversation)
+                    .map(|json| json + "\n")
+                    .map_err(io::Error::other),
+                Ok(None) => continue,
+                Err(err) => Err(io::Error::other(err.to_string())),
+            };
+            let failed = line.is_err();
+            // Abort the body on failure so the client does not advance its
+            // cursor past a truncated export.
+            if tx.send(line).await.is_err() || failed {
+                return;
+            }
+        }
+    });
+
+    Response::builder()
+        .header(header::CONTENT_TYPE, "application/x-ndjson")
+        .body(Body::from_stream(
+            tokio_stream::wrappers::ReceiverStream::new(rx),
+        ))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Receive a batch pushed by an `http` remote. Sources are stored under
+/// `local:` and diverged conversations settled with the batch's strategy.
+async fn sync_import(
+    State(state): State<AppState>,
+    Json(batch): Json<PushBatch>,
+) -> Result<Json<SyncResult>, StatusCode> {
+    let result = remote::apply_push_batch(&state.db, batch)
+        .await
+        .map_err(|err| {
+            log::error!("sync import failed: {err:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    Ok(Json(result))
+}
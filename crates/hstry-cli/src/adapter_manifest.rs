@@ -1,28 +1,69 @@
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
 use anyhow::Result;
+use hstry_runtime::runner::{RuntimeKind, adapter_version, versioned_adapter_dir};
 use serde::{Deserialize, Serialize};
 
 pub const ADAPTER_PROTOCOL_VERSION: &str = "1";
 
+/// Lockfile in the adapter root recording installed and snapshotted versions.
+pub const ADAPTER_LOCK_FILE: &str = "adapters.lock.json";
+
+/// Per-adapter manifest, next to `adapter.ts`. Schema:
+/// `docs/schemas/adapter-manifest-v1.json`.
+pub const ADAPTER_SPEC_FILE: &str = "adapter.json";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AdapterManifest {
     pub hstry_version: String,
     pub protocol_version: String,
+    /// Runtimes an adapter works on, for adapters that need specific ones
+    /// (e.g. `"codex": ["bun", "node"]` for its SQLite bindings).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub runtimes: BTreeMap<String, Vec<String>>,
 }
 
 pub fn expected_hstry_version() -> String {
     env!("CARGO_PKG_VERSION").to_string()
 }
 
-pub fn validate_adapter_manifest(adapter_paths: &[PathBuf]) -> Result<AdapterManifest> {
-    if std::env::var("HSTRY_ALLOW_UNPINNED_ADAPTERS")
+/// Whether `HSTRY_ALLOW_UNPINNED_ADAPTERS` turns off manifest version checks.
+pub fn unpinned_allowed() -> bool {
+    std::env::var("HSTRY_ALLOW_UNPINNED_ADAPTERS")
         .map(|value| matches!(value.as_str(), "1" | "true" | "yes"))
         .unwrap_or(false)
-    {
+}
+
+/// Fail unless `manifest` was written for this hstry version and adapter
+/// protocol.
+pub fn ensure_compatible(manifest: &AdapterManifest) -> Result<()> {
+    let expected_version_value = expected_hstry_version();
+    let expected_version = normalize_version(&expected_version_value);
+    let manifest_version = normalize_version(&manifest.hstry_version);
+    if manifest_version != expected_version {
+        anyhow::bail!(
+            "Adapter version mismatch (expected hstry {}, found {})",
+            expected_version,
+            manifest_version
+        );
+    }
+    if manifest.protocol_version != ADAPTER_PROTOCOL_VERSION {
+        anyhow::bail!(
+            "Adapter protocol mismatch (expected {}, found {})",
+            ADAPTER_PROTOCOL_VERSION,
+            manifest.protocol_version
+        );
+    }
+    Ok(())
+}
+
+pub fn validate_adapter_manifest(adapter_paths: &[PathBuf]) -> Result<AdapterManifest> {
+    if unpinned_allowed() {
         return Ok(AdapterManifest {
             hstry_version: expected_hstry_version(),
             protocol_version: ADAPTER_PROTOCOL_VERSION.to_string(),
+            runtimes: runtime_requirements(adapter_paths),
         });
     }
 
@@ -39,35 +80,33 @@ pub fn validate_adapter_manifest(adapter_paths: &[PathBuf]) -> Result<AdapterMan
         );
     }
 
-    let expected_version_value = expected_hstry_version();
-    let expected_version = normalize_version(&expected_version_value);
-    let expected_protocol = ADAPTER_PROTOCOL_VERSION;
-
     for manifest in manifests {
-        let manifest_version = normalize_version(&manifest.hstry_version);
-        if manifest_version != expected_version {
-            anyhow::bail!(
-                "Adapter version mismatch (expected hstry {}, found {}). Run 'hstry adapters update'.",
-                expected_version,
-                manifest_version
-            );
-        }
-        if manifest.protocol_version != expected_protocol {
-            anyhow::bail!(
-                "Adapter protocol mismatch (expected {}, found {}). Run 'hstry adapters update'.",
-                expected_protocol,
-                manifest.protocol_version
-            );
-        }
+        ensure_compatible(&manifest)
+            .map_err(|err| anyhow::anyhow!("{err}. Run 'hstry adapters update'."))?;
     }
 
     Ok(AdapterManifest {
         hstry_version: expected_hstry_version(),
-        protocol_version: expected_protocol.to_string(),
+        protocol_version: ADAPTER_PROTOCOL_VERSION.to_string(),
+        runtimes: runtime_requirements(adapter_paths),
     })
 }
 
-fn read_manifest(adapter_path: &Path) -> Result<Option<AdapterManifest>> {
+/// Runtime requirements per adapter, taken from the first adapter path whose
+/// manifest lists the adapter.
+pub fn runtime_requirements(adapter_paths: &[PathBuf]) -> BTreeMap<String, Vec<String>> {
+    let mut runtimes = BTreeMap::new();
+    for base_path in adapter_paths {
+        if let Ok(Some(manifest)) = read_manifest(base_path) {
+            for (name, kinds) in manifest.runtimes {
+                runtimes.entry(name).or_insert(kinds);
+            }
+        }
+    }
+    runtimes
+}
+
+pub fn read_manifest(adapter_path: &Path) -> Result<Option<AdapterManifest>> {
     let manifest_path = adapter_path.join(".hstry-adapters.json");
     if !manifest_path.exists() {
         return Ok(None);
@@ -80,3 +119,327 @@ fn read_manifest(adapter_path: &Path) -> Result<Option<AdapterManifest>> {
 fn normalize_version(version: &str) -> &str {
     version.strip_prefix('v').unwrap_or(version)
 }
+
+/// What an adapter declares about itself in [`ADAPTER_SPEC_FILE`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AdapterSpec {
+    /// Must match the adapter's directory name.
+    pub name: String,
+    /// Must match the `version` in `adapter.ts`.
+    pub version: String,
+    pub capabilities: Vec<Capability>,
+    /// Oldest version of each runtime the adapter runs on (`"node": "22.6.0"`).
+    /// Runtimes not listed have no minimum.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub min_runtime: BTreeMap<String, String>,
+}
+
+/// Protocol methods an adapter implements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Capability {
+    Detect,
+    Parse,
+    Export,
+}
+
+impl Capability {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Detect => "detect",
+            Self::Parse => "parse",
+            Self::Export => "export",
+        }
+    }
+}
+
+pub fn read_spec(adapter_dir: &Path) -> Result<Option<AdapterSpec>> {
+    let path = adapter_dir.join(ADAPTER_SPEC_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let spec = serde_json::from_str(&std::fs::read_to_string(&path)?)
+        .map_err(|err| anyhow::anyhow!("Invalid {}: {err}", path.display()))?;
+    Ok(Some(spec))
+}
+
+/// Read and check the manifest of the adapter in `adapter_dir`. Adapters
+/// without one are accepted (`Ok(None)`) so older third-party adapters keep
+/// working.
+pub fn validate_spec(adapter_dir: &Path) -> Result<Option<AdapterSpec>> {
+    let Some(spec) = read_spec(adapter_dir)? else {
+        return Ok(None);
+    };
+    let dir_name = adapter_dir
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    if spec.name != dir_name {
+        anyhow::bail!(
+            "Adapter manifest names '{}' but the adapter directory is '{dir_name}'",
+            spec.name
+        );
+    }
+    if parse_version(&spec.version).is_none() {
+        anyhow::bail!(
+            "Adapter '{}' has an invalid version '{}'",
+            spec.name,
+            spec.version
+        );
+    }
+    if let Some(code_version) = adapter_version(&adapter_dir.join("adapter.ts"))
+        && code_version != spec.version
+    {
+        anyhow::bail!(
+            "Adapter '{}' manifest says version {} but adapter.ts says {code_version}",
+            spec.name,
+            spec.version
+        );
+    }
+    for required in [Capability::Detect, Capability::Parse] {
+        if !spec.capabilities.contains(&required) {
+            anyhow::bail!(
+                "Adapter '{}' must declare the '{}' capability",
+                spec.name,
+                required.as_str()
+            );
+        }
+    }
+    for (runtime, version) in &spec.min_runtime {
+        if RuntimeKind::parse(runtime).is_none() {
+            anyhow::bail!(
+                "Adapter '{}' names an unknown runtime '{runtime}' (expected bun, deno or node)",
+                spec.name
+            );
+        }
+        if parse_version(version).is_none() {
+            anyhow::bail!(
+                "Adapter '{}' has an invalid minimum {runtime} version '{version}'",
+                spec.name
+            );
+        }
+    }
+    Ok(Some(spec))
+}
+
+/// Check the manifest of every adapter under `root` (each directory with an
+/// `adapter.ts`). Returns how many adapters had one.
+pub fn validate_specs_in(root: &Path) -> Result<usize> {
+    let mut validated = 0;
+    for entry in std::fs::read_dir(root)? {
+        let path = entry?.path();
+        if path.join("adapter.ts").exists() && validate_spec(&path)?.is_some() {
+            validated += 1;
+        }
+    }
+    Ok(validated)
+}
+
+/// `1.2.3`, `v1.2` or `1.2.3-beta.1` as numeric components; the pre-release
+/// or build suffix is ignored.
+fn parse_version(version: &str) -> Option<Vec<u64>> {
+    let core = normalize_version(version.trim())
+        .split(['-', '+'])
+        .next()
+        .unwrap_or_default();
+    core.split('.')
+        .map(|part| part.parse().ok())
+        .collect::<Option<Vec<u64>>>()
+        .filter(|parts| !parts.is_empty())
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AdapterLock {
+    #[serde(default)]
+    pub adapters: BTreeMap<String, LockedAdapter>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LockedAdapter {
+    /// Version installed at `<root>/<name>`.
+    pub version: String,
+    /// Where that version came from (`git:<url>@<ref>`, `archive:<url>`,
+    /// `local:<path>`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// Versions kept as snapshots that sources can pin to.
+    #[serde(default)]
+    pub versions: Vec<String>,
+}
+
+pub fn read_lock(adapter_root: &Path) -> Result<AdapterLock> {
+    let path = adapter_root.join(ADAPTER_LOCK_FILE);
+    if !path.exists() {
+        return Ok(AdapterLock::default());
+    }
+    Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+}
+
+fn write_lock(adapter_root: &Path, lock: &AdapterLock) -> Result<()> {
+    std::fs::write(
+        adapter_root.join(ADAPTER_LOCK_FILE),
+        serde_json::to_string_pretty(lock)?,
+    )?;
+    Ok(())
+}
+
+/// Keep a copy of the adapter currently installed at `<root>/<name>` under
+/// its version so sources can stay pinned to it across updates, and record
+/// it in the lockfile. An existing snapshot of the same version is left
+/// untouched. Returns the version.
+pub fn snapshot_installed(adapter_root: &Path, name: &str, source: Option<&str>) -> Result<String> {
+    let adapter_dir = adapter_root.join(name);
+    let version = adapter_version(&adapter_dir.join("adapter.ts")).ok_or_else(|| {
+        anyhow::anyhow!("Could not determine the installed version of adapter '{name}'")
+    })?;
+
+    let snapshot = versioned_adapter_dir(adapter_root, name, &version);
+    if !snapshot.exists() {
+        crate::copy_dir_recursive(&adapter_dir, &snapshot.join(name))?;
+        let types = adapter_root.join("types");
+        if types.is_dir() {
+            crate::copy_dir_recursive(&types, &snapshot.join("types"))?;
+        }
+    }
+
+    let mut lock = read_lock(adapter_root)?;
+    let entry = lock.adapters.entry(name.to_string()).or_default();
+    entry.version.clone_from(&version);
+    if let Some(source) = source {
+        entry.source = Some(source.to_string());
+    }
+    if !entry.versions.contains(&version) {
+        entry.versions.push(version.clone());
+        entry.versions.sort();
+    }
+    write_lock(adapter_root, &lock)?;
+    Ok(version)
+}
+
+/// Whether `<root>/<name>` already holds the version of the adapter in
+/// `src_dir`, installed from `source`, so `hstry adapters update` can skip
+/// it unless forced.
+pub fn is_current(
+    lock: &AdapterLock,
+    adapter_root: &Path,
+    name: &str,
+    src_dir: &Path,
+    source: &str,
+) -> bool {
+    let Some(locked) = lock.adapters.get(name) else {
+        return false;
+    };
+    let Some(version) = adapter_version(&src_dir.join("adapter.ts")) else {
+        return false;
+    };
+    locked.source.as_deref() == Some(source)
+        && locked.version == version
+        && adapter_version(&adapter_root.join(name).join("adapter.ts")) == Some(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_keeps_each_version_and_records_it() {
+        let root = tempfile::TempDir::new().expect("tempdir");
+        let adapter = root.path().join("codex");
+        std::fs::create_dir_all(&adapter).expect("create dir");
+        std::fs::create_dir_all(root.path().join("types")).expect("create dir");
+        std::fs::write(root.path().join("types/index.ts"), "// types").expect("write");
+
+        std::fs::write(adapter.join("adapter.ts"), "({ version: '1.0.0' })").expect("write");
+        snapshot_installed(root.path(), "codex", Some("local:/repo")).expect("snapshot");
+        std::fs::write(adapter.join("adapter.ts"), "({ version: '1.1.0' })").expect("write");
+        snapshot_installed(root.path(), "codex", None).expect("snapshot");
+
+        let old = versioned_adapter_dir(root.path(), "codex", "1.0.0");
+        assert_eq!(
+            std::fs::read_to_string(old.join("codex/adapter.ts")).expect("read"),
+            "({ version: '1.0.0' })"
+        );
+        assert!(old.join("types/index.ts").exists());
+
+        let lock = read_lock(root.path()).expect("lock");
+        let entry = &lock.adapters["codex"];
+        assert_eq!(entry.version, "1.1.0");
+        assert_eq!(entry.versions, vec!["1.0.0", "1.1.0"]);
+        assert_eq!(entry.source.as_deref(), Some("local:/repo"));
+    }
+
+    #[test]
+    fn installed_adapter_is_current_for_same_version_and_source() {
+        let root_dir = tempfile::TempDir::new().expect("tempdir");
+        let src_dir = tempfile::TempDir::new().expect("tempdir");
+        let (root, src) = (root_dir.path(), src_dir.path().join("codex"));
+        std::fs::create_dir_all(root.join("codex")).expect("create dir");
+        std::fs::create_dir_all(&src).expect("create dir");
+        std::fs::write(root.join("codex/adapter.ts"), "({ version: '1.0.0' })").expect("write");
+        std::fs::write(src.join("adapter.ts"), "({ version: '1.0.0' })").expect("write");
+        snapshot_installed(root, "codex", Some("git:repo@v1")).expect("snapshot");
+
+        let lock = read_lock(root).expect("lock");
+        assert!(is_current(&lock, root, "codex", &src, "git:repo@v1"));
+        assert!(!is_current(&lock, root, "codex", &src, "git:repo@v2"));
+
+        std::fs::write(src.join("adapter.ts"), "({ version: '1.1.0' })").expect("write");
+        assert!(!is_current(&lock, root, "codex", &src, "git:repo@v1"));
+    }
+
+    #[test]
+    fn spec_must_match_the_adapter_it_describes() {
+        let root = tempfile::TempDir::new().expect("tempdir");
+        let dir = root.path().join("codex");
+        std::fs::create_dir_all(&dir).expect("create dir");
+        std::fs::write(dir.join("adapter.ts"), "({ version: '1.1.0' })").expect("write");
+        assert!(validate_spec(&dir).expect("no manifest").is_none());
+
+        let write_spec = |json: &str| std::fs::write(dir.join(ADAPTER_SPEC_FILE), json);
+        write_spec(
+            r#"{"name": "codex", "version": "1.1.0",
+                "capabilities": ["detect", "parse", "export"],
+                "min_runtime": {"node": "22.6.0"}}"#,
+        )
+        .expect("write");
+        let spec = validate_spec(&dir).expect("valid").expect("manifest");
+        assert_eq!(spec.capabilities.len(), 3);
+        assert_eq!(validate_specs_in(root.path()).expect("valid"), 1);
+
+        for invalid in [
+            r#"{"name": "codex", "version": "1.0.0", "capabilities": ["detect", "parse"]}"#,
+            r#"{"name": "gemini", "version": "1.1.0", "capabilities": ["detect", "parse"]}"#,
+            r#"{"name": "codex", "version": "1.1.0", "capabilities": ["parse"]}"#,
+            r#"{"name": "codex", "version": "1.1.0", "capabilities": ["detect", "parse", "sync"]}"#,
+            r#"{"name": "codex", "version": "1.1.0", "capabilities": ["detect", "parse"],
+                "min_runtime": {"python": "3.12"}}"#,
+        ] {
+            write_spec(invalid).expect("write");
+            assert!(validate_spec(&dir).is_err(), "accepted {invalid}");
+        }
+    }
+
+    #[test]
+    fn runtime_requirements_prefer_earlier_adapter_paths() {
+        let first = tempfile::TempDir::new().expect("tempdir");
+        let second = tempfile::TempDir::new().expect("tempdir");
+        std::fs::write(
+            first.path().join(".hstry-adapters.json"),
+            r#"{"hstry_version": "0.1.0", "protocol_version": "1",
+                "runtimes": {"codex": ["node"]}}"#,
+        )
+        .expect("write");
+        std::fs::write(
+            second.path().join(".hstry-adapters.json"),
+            r#"{"hstry_version": "0.1.0", "protocol_version": "1",
+                "runtimes": {"codex": ["bun"], "opencode": ["bun", "node"]}}"#,
+        )
+        .expect("write");
+
+        let runtimes =
+            runtime_requirements(&[first.path().to_path_buf(), second.path().to_path_buf()]);
+        assert_eq!(runtimes["codex"], vec!["node"]);
+        assert_eq!(runtimes["opencode"], vec!["bun", "node"]);
+    }
+}
@@ -0,0 +1,253 @@
+//! Failure alerts for the background service.
+//!
+//! The service counts consecutive failed syncs per source. Crossing the
+//! `[service.notifications]` thresholds queues an alert; queued alerts are
+//! sent as one digest per sync cycle to the configured desktop, webhook and
+//! email channels.
+
+use std::io::Write as _;
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result};
+use hstry_core::config::NotificationConfig;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertKind {
+    /// The source reached `after_failures` consecutive failures.
+    Failing,
+    /// The source reached `escalate_after` consecutive failures.
+    Escalated,
+    /// A reported source synced successfully again.
+    Recovered,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceAlert {
+    pub source_id: String,
+    pub adapter: String,
+    pub kind: AlertKind,
+    /// Consecutive failures so far, or before recovery.
+    pub failures: u32,
+    pub error: Option<String>,
+}
+
+/// Alert to raise after a source's `failures`-th consecutive failed sync.
+pub fn failure_alert(config: &NotificationConfig, failures: u32) -> Option<AlertKind> {
+    if !config.enabled {
+        return None;
+    }
+    if config.escalate_after > config.after_failures && failures == config.escalate_after {
+        Some(AlertKind::Escalated)
+    } else if failures == config.after_failures.max(1) {
+        Some(AlertKind::Failing)
+    } else {
+        None
+    }
+}
+
+/// Whether a successful sync after `previous_failures` failures is worth a
+/// recovery notice, i.e. the failures had been reported.
+pub fn should_report_recovery(config: &NotificationConfig, previous_failures: u32) -> bool {
+    config.enabled && config.recovery && previous_failures >= config.after_failures.max(1)
+}
+
+fn digest_title(alerts: &[SourceAlert]) -> String {
+    let failing = alerts
+        .iter()
+        .filter(|alert| alert.kind != AlertKind::Recovered)
+        .count();
+    let recovered = alerts.len() - failing;
+    match (failing, recovered) {
+        (0, 1) => format!("hstry: {} is syncing again", alerts[0].source_id),
+        (0, n) => format!("hstry: {n} sources are syncing again"),
+        (1, 0) => format!("hstry: {} keeps failing to sync", alerts[0].source_id),
+        (n, 0) => format!("hstry: {n} sources keep failing to sync"),
+        (f, r) => format!("hstry: {f} sources failing, {r} recovered"),
+    }
+}
+
+fn digest_body(alerts: &[SourceAlert]) -> String {
+    let mut body = String::new();
+    for alert in alerts {
+        let line = match alert.kind {
+            AlertKind::Failing => format!(
+                "{} ({}): {} failed syncs in a row",
+                alert.source_id, alert.adapter, alert.failures
+            ),
+            AlertKind::Escalated => format!(
+                "{} ({}): still failing after {} syncs",
+                alert.source_id, alert.adapter, alert.failures
+            ),
+            AlertKind::Recovered => format!(
+                "{} ({}): recovered after {} failed syncs",
+                alert.source_id, alert.adapter, alert.failures
+            ),
+        };
+        body.push_str(&line);
+        if let Some(error) = &alert.error {
+            body.push_str(&format!("\n  {error}"));
+        }
+        body.push('\n');
+    }
+    body
+}
+
+/// Send one digest for `alerts` to every configured channel. A failing
+/// channel is logged and does not stop the others.
+pub async fn send_digest(config: &NotificationConfig, alerts: &[SourceAlert]) {
+    if alerts.is_empty() {
+        return;
+    }
+    let title = digest_title(alerts);
+    let body = digest_body(alerts);
+
+    if config.desktop
+        && let Err(err) = send_desktop(&title, &body)
+    {
+        tracing::warn!("failed to show desktop notification: {err}");
+    }
+    if let Some(url) = config.webhook_url.as_deref().filter(|url| !url.is_empty())
+        && let Err(err) = send_webhook(url, &title, alerts).await
+    {
+        tracing::warn!("failed to send alert webhook to {url}: {err}");
+    }
+    if let Some(to) = config.email_to.as_deref().filter(|to| !to.is_empty())
+        && let Err(err) = send_email(&config.sendmail_command, to, &title, &body)
+    {
+        tracing::warn!("failed to mail alert to {to}: {err}");
+    }
+}
+
+fn send_desktop(title: &str, body: &str) -> Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        let script = format!(
+            "display notification {} with title {}",
+            applescript_string(body),
+            applescript_string(title)
+        );
+        let mut command = Command::new("osascript");
+        command.args(["-e", &script]);
+        command
+    } else {
+        let mut command = Command::new("notify-send");
+        command.args(["--app-name=hstry", title, body]);
+        command
+    };
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .context("failed to run the desktop notifier")?;
+    Ok(())
+}
+
+fn applescript_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+async fn send_webhook(url: &str, title: &str, alerts: &[SourceAlert]) -> Result<()> {
+    let payload = serde_json::json!({
+        "title": title,
+        "alerts": alerts,
+        "sent_at": chrono::Utc::now(),
+    });
+    reqwest::Client::new()
+        .post(url)
+        .timeout(std::time::Duration::from_secs(10))
+        .json(&payload)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+fn send_email(sendmail_command: &str, to: &str, subject: &str, body: &str) -> Result<()> {
+    let argv = shlex::split(sendmail_command)
+        .filter(|argv| !argv.is_empty())
+        .with_context(|| format!("invalid sendmail_command: {sendmail_command}"))?;
+    let mut child = Command::new(&argv[0])
+        .args(&argv[1..])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .with_context(|| format!("failed to run {}", argv[0]))?;
+    let message = format!("To: {to}\nSubject: {subject}\n\n{body}");
+    child
+        .stdin
+        .take()
+        .context("sendmail stdin unavailable")?
+        .write_all(message.as_bytes())?;
+    let status = child.wait()?;
+    if !status.success() {
+        anyhow::bail!("{} exited with {status}", argv[0]);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> NotificationConfig {
+        NotificationConfig {
+            enabled: true,
+            after_failures: 3,
+            escalate_after: 10,
+            ..NotificationConfig::default()
+        }
+    }
+
+    #[test]
+    fn failure_alerts_fire_once_per_threshold() {
+        let config = config();
+        let alerts: Vec<_> = (1..=25)
+            .filter_map(|failures| failure_alert(&config, failures).map(|kind| (failures, kind)))
+            .collect();
+        assert_eq!(
+            alerts,
+            vec![(3, AlertKind::Failing), (10, AlertKind::Escalated)]
+        );
+
+        let disabled = NotificationConfig::default();
+        assert_eq!(failure_alert(&disabled, 3), None);
+    }
+
+    #[test]
+    fn recovery_is_reported_only_after_an_alert() {
+        let config = config();
+        assert!(!should_report_recovery(&config, 2));
+        assert!(should_report_recovery(&config, 3));
+
+        let quiet = NotificationConfig {
+            recovery: false,
+            ..config
+        };
+        assert!(!should_report_recovery(&quiet, 12));
+    }
+
+    #[test]
+    fn digest_title_summarises_mixed_alerts() {
+        let alert = |id: &str, kind| SourceAlert {
+            source_id: id.to_string(),
+            adapter: "codex".to_string(),
+            kind,
+            failures: 3,
+            error: None,
+        };
+        assert_eq!(
+            digest_title(&[alert("a", AlertKind::Failing)]),
+            "hstry: a keeps failing to sync"
+        );
+        assert_eq!(
+            digest_title(&[
+                alert("a", AlertKind::Failing),
+                alert("b", AlertKind::Escalated),
+                alert("c", AlertKind::Recovered),
+            ]),
+            "hstry: 2 sources failing, 1 recovered"
+        );
+    }
+}
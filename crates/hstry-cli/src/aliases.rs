@@ -0,0 +1,137 @@
+//! User-defined command shortcuts from the `[aliases]` config section.
+//!
+//! Like git aliases, `week = "list --after 7d"` makes `hstry week --limit 5`
+//! run `hstry list --after 7d --limit 5`. Built-in subcommands always win,
+//! and an alias may expand to another alias.
+
+use std::collections::{BTreeMap, HashSet};
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use anyhow::{Result, bail};
+
+/// The `--config` path given on the command line, if any. Aliases live in
+/// the config, so it has to be found before clap parses anything.
+pub fn config_arg(args: &[OsString]) -> Option<PathBuf> {
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        let arg = arg.to_string_lossy();
+        if arg == "--" {
+            break;
+        }
+        if arg == "--config" {
+            return iter.next().map(PathBuf::from);
+        }
+        if let Some(path) = arg.strip_prefix("--config=") {
+            return Some(PathBuf::from(path));
+        }
+    }
+    None
+}
+
+/// Replace an alias in the subcommand position of `args` with the command
+/// line it stands for. Arguments that follow the alias are kept after it.
+pub fn expand(
+    mut args: Vec<OsString>,
+    aliases: &BTreeMap<String, String>,
+    is_builtin: impl Fn(&str) -> bool,
+) -> Result<Vec<OsString>> {
+    let mut seen = HashSet::new();
+    while let Some(pos) = command_position(&args) {
+        let Some(name) = args[pos].to_str().map(str::to_string) else {
+            break;
+        };
+        if is_builtin(&name) {
+            break;
+        }
+        let Some(expansion) = aliases.get(&name) else {
+            break;
+        };
+        if !seen.insert(name.clone()) {
+            bail!("Alias '{name}' expands to itself");
+        }
+        let Some(words) = shlex::split(expansion) else {
+            bail!("Alias '{name}' has unbalanced quotes: {expansion}");
+        };
+        if words.is_empty() {
+            bail!("Alias '{name}' is empty");
+        }
+        args.splice(pos..=pos, words.into_iter().map(OsString::from));
+    }
+    Ok(args)
+}
+
+/// Index of the first argument that is not a global option.
+fn command_position(args: &[OsString]) -> Option<usize> {
+    let mut i = 1;
+    while let Some(arg) = args.get(i) {
+        let arg = arg.to_string_lossy();
+        if arg == "--" {
+            return None;
+        }
+        if !arg.starts_with('-') {
+            return Some(i);
+        }
+        i += if arg == "--config" { 2 } else { 1 };
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<OsString> {
+        line.split_whitespace().map(OsString::from).collect()
+    }
+
+    fn aliases(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| ((*k).to_string(), (*v).to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn expands_alias_after_global_options_and_keeps_trailing_args() {
+        let table = aliases(&[
+            ("week", "list --after 7d"),
+            ("wk", "week"),
+            ("find", "search 'two words'"),
+        ]);
+        let builtin = |name: &str| matches!(name, "list" | "search");
+
+        assert_eq!(
+            expand(
+                args("hstry --config c.toml -v wk --limit 5"),
+                &table,
+                builtin
+            )
+            .expect("expand"),
+            args("hstry --config c.toml -v list --after 7d --limit 5")
+        );
+        let quoted = expand(args("hstry find"), &table, builtin).expect("expand");
+        assert_eq!(quoted[2], OsString::from("two words"));
+        assert_eq!(
+            expand(args("hstry list --json"), &table, builtin).expect("expand"),
+            args("hstry list --json")
+        );
+        assert_eq!(
+            config_arg(&args("hstry --json --config=/tmp/c.toml week")),
+            Some(PathBuf::from("/tmp/c.toml"))
+        );
+    }
+
+    #[test]
+    fn builtins_win_and_loops_are_rejected() {
+        let table = aliases(&[("list", "search x"), ("a", "b"), ("b", "a")]);
+        let builtin = |name: &str| name == "list";
+
+        assert_eq!(
+            expand(args("hstry list"), &table, builtin).expect("expand"),
+            args("hstry list")
+        );
+        let err = expand(args("hstry a"), &table, builtin).expect_err("loop");
+        assert!(err.to_string().contains("expands to itself"));
+    }
+}
@@ -0,0 +1,625 @@
+//! Native import of the official ChatGPT data export (`chatgpt-export.zip`).
+//!
+//! The archive holds `conversations.json` plus the uploaded and generated
+//! files each conversation references. Every conversation is a tree in
+//! `mapping`: regenerating a reply or editing a prompt starts a new branch.
+//!
+//! The branch ending at `current_node` (what ChatGPT shows) becomes the
+//! conversation itself. Every other leaf becomes a child conversation with
+//! `fork_type = "fork"` that stores only the messages after the point where
+//! it diverged, so shared history is indexed once.
+//!
+//! Image and file parts that point at files in the archive become
+//! [`Part::Image`] / [`Part::Attachment`] parts with an attachment reference;
+//! the bytes are written to the attachments table after ingest.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use hstry_core::parsed::{ParsedConversation, ParsedMessage};
+use hstry_core::parts::{MediaSource, Part};
+use serde::Deserialize;
+use serde_json::Value;
+use zip::ZipArchive;
+
+const CONVERSATIONS_JSON: &str = "conversations.json";
+
+/// Conversations parsed from an export archive, plus the archive files to
+/// store as attachments once they are ingested.
+#[derive(Debug, Default)]
+pub struct ChatGptExport {
+    pub conversations: Vec<ParsedConversation>,
+    pub assets: Vec<PendingAsset>,
+}
+
+impl ChatGptExport {
+    /// Number of branch conversations (everything that is not a main thread).
+    pub fn branch_count(&self) -> usize {
+        self.conversations
+            .iter()
+            .filter(|c| c.parent_external_id.is_some())
+            .count()
+    }
+}
+
+/// A file in the archive referenced by a message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingAsset {
+    /// External id of the conversation holding the message.
+    pub external_id: String,
+    /// Index of the message within that conversation.
+    pub message_idx: usize,
+    /// Attachment id used in the message parts (the ChatGPT file id).
+    pub attachment_id: String,
+    /// Path of the file inside the archive.
+    pub entry: String,
+    pub mime_type: String,
+    pub filename: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawConversation {
+    id: Option<String>,
+    conversation_id: Option<String>,
+    title: Option<String>,
+    create_time: Option<f64>,
+    update_time: Option<f64>,
+    #[serde(default)]
+    mapping: HashMap<String, RawNode>,
+    current_node: Option<String>,
+    workspace_id: Option<String>,
+    workspace_title: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawNode {
+    parent: Option<String>,
+    #[serde(default)]
+    children: Vec<String>,
+    message: Option<RawMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawMessage {
+    author: Option<RawAuthor>,
+    create_time: Option<f64>,
+    content: Option<RawContent>,
+    metadata: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawAuthor {
+    role: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawContent {
+    text: Option<String>,
+    parts: Option<Vec<Value>>,
+}
+
+/// True when `path` is a zip archive containing `conversations.json`.
+pub fn is_chatgpt_export(path: &Path) -> bool {
+    let is_zip = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"));
+    if !is_zip || !path.is_file() {
+        return false;
+    }
+    File::open(path)
+        .ok()
+        .and_then(|file| ZipArchive::new(file).ok())
+        .is_some_and(|archive| conversations_entry(&archive).is_some())
+}
+
+/// Read and parse the export archive at `path`.
+pub fn read_export(path: &Path) -> Result<ChatGptExport> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut archive = ZipArchive::new(file)
+        .with_context(|| format!("{} is not a zip archive", path.display()))?;
+    let entry = conversations_entry(&archive)
+        .with_context(|| format!("No {CONVERSATIONS_JSON} in {}", path.display()))?;
+
+    let mut raw = String::new();
+    archive.by_name(&entry)?.read_to_string(&mut raw)?;
+    let conversations: Vec<RawConversation> =
+        serde_json::from_str(&raw).with_context(|| format!("Failed to parse {entry}"))?;
+
+    let files: Vec<String> = archive
+        .file_names()
+        .filter(|name| !name.ends_with('/'))
+        .map(str::to_string)
+        .collect();
+    Ok(parse_conversations(conversations, &files))
+}
+
+/// Read one file from the archive.
+pub fn read_entry(archive: &mut ZipArchive<File>, entry: &str) -> Result<Vec<u8>> {
+    let mut file = archive.by_name(entry)?;
+    let mut data = Vec::with_capacity(usize::try_from(file.size()).unwrap_or_default());
+    file.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+fn conversations_entry(archive: &ZipArchive<File>) -> Option<String> {
+    archive
+        .file_names()
+        .filter(|name| {
+            *name == CONVERSATIONS_JSON || name.ends_with(&format!("/{CONVERSATIONS_JSON}"))
+        })
+        .min_by_key(|name| name.len())
+        .map(str::to_string)
+}
+
+fn parse_conversations(raw: Vec<RawConversation>, files: &[String]) -> ChatGptExport {
+    let assets = AssetIndex::new(files);
+    let mut export = ChatGptExport::default();
+    for conv in raw {
+        parse_tree(&conv, &assets, &mut export);
+    }
+    export
+}
+
+/// A message node converted to the ingest shape, with its archive files.
+struct Converted {
+    message: ParsedMessage,
+    assets: Vec<(String, String, String, Option<String>)>,
+}
+
+/// A stored thread: the node path from the root and where its stored
+/// messages start.
+struct Thread {
+    external_id: String,
+    path: Vec<String>,
+    start: usize,
+}
+
+fn parse_tree(conv: &RawConversation, assets: &AssetIndex, export: &mut ChatGptExport) {
+    let Some(conv_id) = conv.id.clone().or_else(|| conv.conversation_id.clone()) else {
+        return;
+    };
+
+    let mut converted: HashMap<&str, Converted> = HashMap::new();
+    for (node_id, node) in &conv.mapping {
+        if let Some(msg) = node
+            .message
+            .as_ref()
+            .and_then(|m| convert_message(m, assets))
+        {
+            converted.insert(node_id.as_str(), msg);
+        }
+    }
+    if converted.is_empty() {
+        return;
+    }
+
+    // Main thread first, then the other leaves oldest first so each branch
+    // can hang off whichever earlier thread it shares the most history with.
+    let mut leaves: Vec<&str> = conv
+        .mapping
+        .iter()
+        .filter(|(_, node)| node.children.is_empty())
+        .map(|(id, _)| id.as_str())
+        .collect();
+    leaves.sort_by(|a, b| {
+        leaf_time(conv, a)
+            .total_cmp(&leaf_time(conv, b))
+            .then_with(|| a.cmp(b))
+    });
+    let main_leaf = conv
+        .current_node
+        .as_deref()
+        .filter(|id| conv.mapping.contains_key(*id))
+        .or_else(|| leaves.last().copied());
+    let Some(main_leaf) = main_leaf else {
+        return;
+    };
+    leaves.retain(|id| *id != main_leaf);
+
+    let title = conv.title.clone().filter(|t| !t.trim().is_empty());
+    let mut threads: Vec<Thread> = Vec::new();
+
+    for leaf in std::iter::once(main_leaf).chain(leaves) {
+        let path = node_path(conv, leaf);
+        // On ties prefer the earliest thread, which owns the shared history.
+        let parent = threads
+            .iter()
+            .rev()
+            .map(|t| (t, common_prefix(&t.path, &path)))
+            .max_by_key(|(_, common)| *common);
+
+        let (external_id, start, parent_ref) = match parent {
+            None => (conv_id.clone(), 0, None),
+            Some((thread, common)) => {
+                let idx = thread.path[thread.start..common.max(thread.start)]
+                    .iter()
+                    .filter(|id| converted.contains_key(id.as_str()))
+                    .count();
+                (
+                    format!("{conv_id}#{leaf}"),
+                    common,
+                    Some((thread.external_id.clone(), idx)),
+                )
+            }
+        };
+
+        let mut messages = Vec::new();
+        for node_id in &path[start..] {
+            let Some(msg) = converted.get(node_id.as_str()) else {
+                continue;
+            };
+            for (attachment_id, entry, mime_type, filename) in &msg.assets {
+                export.assets.push(PendingAsset {
+                    external_id: external_id.clone(),
+                    message_idx: messages.len(),
+                    attachment_id: attachment_id.clone(),
+                    entry: entry.clone(),
+                    mime_type: mime_type.clone(),
+                    filename: filename.clone(),
+                });
+            }
+            messages.push(msg.message.clone());
+        }
+        if messages.is_empty() {
+            continue;
+        }
+
+        let created_at = if parent_ref.is_some() {
+            messages.first().and_then(|m| m.created_at)
+        } else {
+            None
+        }
+        .or_else(|| conv.create_time.map(secs_to_millis))
+        .or_else(|| messages.iter().filter_map(|m| m.created_at).min())
+        .unwrap_or_default();
+        let updated_at = if parent_ref.is_some() {
+            messages.iter().filter_map(|m| m.created_at).max()
+        } else {
+            conv.update_time.map(secs_to_millis)
+        };
+
+        export.conversations.push(ParsedConversation {
+            external_id: Some(external_id.clone()),
+            readable_id: None,
+            title: title.clone(),
+            created_at,
+            updated_at,
+            model: messages.iter().find_map(|m| m.model.clone()),
+            provider: Some("openai".to_string()),
+            workspace: conv
+                .workspace_title
+                .clone()
+                .or_else(|| conv.workspace_id.clone()),
+            tokens_in: None,
+            tokens_out: None,
+            cost_usd: None,
+            messages,
+            metadata: Some(serde_json::json!({
+                "currentNode": conv.current_node,
+                "leafNode": leaf,
+                "workspaceId": conv.workspace_id,
+                "workspaceTitle": conv.workspace_title,
+            })),
+            version: None,
+            message_count: None,
+            parent_external_id: parent_ref.as_ref().map(|(id, _)| id.clone()),
+            parent_message_idx: parent_ref
+                .as_ref()
+                .and_then(|(_, idx)| i32::try_from(*idx).ok()),
+            fork_type: parent_ref.as_ref().map(|_| "fork".to_string()),
+            source_id: None,
+        });
+        threads.push(Thread {
+            external_id,
+            path,
+            start,
+        });
+    }
+}
+
+/// Node ids from the root down to `leaf`.
+fn node_path(conv: &RawConversation, leaf: &str) -> Vec<String> {
+    let mut path = Vec::new();
+    let mut seen = HashSet::new();
+    let mut current = Some(leaf.to_string());
+    while let Some(id) = current {
+        if !seen.insert(id.clone()) {
+            break;
+        }
+        current = conv.mapping.get(&id).and_then(|node| node.parent.clone());
+        path.push(id);
+    }
+    path.reverse();
+    path
+}
+
+fn common_prefix(a: &[String], b: &[String]) -> usize {
+    a.iter().zip(b).take_while(|(x, y)| x == y).count()
+}
+
+fn leaf_time(conv: &RawConversation, id: &str) -> f64 {
+    conv.mapping
+        .get(id)
+        .and_then(|node| node.message.as_ref())
+        .and_then(|msg| msg.create_time)
+        .unwrap_or_default()
+}
+
+fn secs_to_millis(secs: f64) -> i64 {
+    (secs * 1000.0) as i64
+}
+
+fn convert_message(msg: &RawMessage, assets: &AssetIndex) -> Option<Converted> {
+    let role = msg.author.as_ref()?.role.as_deref()?;
+    let metadata = msg.metadata.as_ref();
+    if metadata
+        .and_then(|m| m.get("is_visually_hidden_from_conversation"))
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
+    {
+        return None;
+    }
+
+    let mut texts = Vec::new();
+    let mut parts = Vec::new();
+    let mut files = Vec::new();
+
+    if let Some(content) = &msg.content {
+        if let Some(text) = content
+            .text
+            .as_deref()
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+        {
+            texts.push(text.to_string());
+        }
+        for part in content.parts.iter().flatten() {
+            match part {
+                Value::String(s) if !s.trim().is_empty() => texts.push(s.trim().to_string()),
+                Value::Object(obj) => {
+                    if let Some(text) = obj
+                        .get("text")
+                        .and_then(Value::as_str)
+                        .map(str::trim)
+                        .filter(|t| !t.is_empty())
+                    {
+                        texts.push(text.to_string());
+                    } else if let Some(pointer) = obj.get("asset_pointer").and_then(Value::as_str)
+                        && let Some((file_id, entry)) = assets.find(pointer)
+                    {
+                        let mime = mime_for(entry);
+                        parts.push(Part::Image {
+                            id: format!("part_{file_id}"),
+                            source: MediaSource::attachment_ref(file_id, Some(mime.clone())),
+                            alt: None,
+                        });
+                        files.push((file_id.to_string(), entry.to_string(), mime, None));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Uploaded files are listed in metadata rather than in the content parts.
+    for attachment in metadata
+        .and_then(|m| m.get("attachments"))
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        let Some((file_id, entry)) = attachment
+            .get("id")
+            .and_then(Value::as_str)
+            .and_then(|id| assets.find(id))
+        else {
+            continue;
+        };
+        if files.iter().any(|(id, ..)| id == file_id) {
+            continue;
+        }
+        let name = attachment
+            .get("name")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        let mime = attachment
+            .get("mime_type")
+            .and_then(Value::as_str)
+            .map_or_else(|| mime_for(entry), str::to_string);
+        let source = MediaSource::attachment_ref(file_id, Some(mime.clone()));
+        parts.push(if mime.starts_with("image/") {
+            Part::Image {
+                id: format!("part_{file_id}"),
+                source,
+                alt: name.clone(),
+            }
+        } else {
+            Part::Attachment {
+                id: format!("part_{file_id}"),
+                source,
+                filename: name.clone(),
+                size_bytes: None,
+            }
+        });
+        files.push((file_id.to_string(), entry.to_string(), mime, name));
+    }
+
+    let mut content = texts.join("\n");
+    if content.is_empty() {
+        if files.is_empty() {
+            return None;
+        }
+        content = files
+            .iter()
+            .map(|(_, _, mime, name)| match name {
+                Some(name) => format!("[file: {name}]"),
+                None if mime.starts_with("image/") => "[image]".to_string(),
+                None => "[file]".to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+    } else {
+        parts.insert(0, Part::text(content.clone()));
+    }
+
+    let model = metadata.and_then(|m| {
+        ["model_slug", "model", "model_name"]
+            .iter()
+            .find_map(|key| m.get(*key).and_then(Value::as_str))
+            .map(str::to_string)
+    });
+
+    Some(Converted {
+        message: ParsedMessage {
+            role: map_role(role).to_string(),
+            content,
+            created_at: msg.create_time.map(secs_to_millis),
+            model,
+            tokens: None,
+            cost_usd: None,
+            parts: serde_json::to_value(&parts).ok(),
+            tool_calls: None,
+            metadata: None,
+            parent_idx: None,
+        },
+        assets: files,
+    })
+}
+
+fn map_role(role: &str) -> &'static str {
+    match role.to_ascii_lowercase().as_str() {
+        "user" | "human" => "user",
+        "system" => "system",
+        "tool" | "function" => "tool",
+        _ => "assistant",
+    }
+}
+
+/// Archive files keyed by the ChatGPT file id their name starts with.
+struct AssetIndex<'a> {
+    files: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> AssetIndex<'a> {
+    fn new(files: &'a [String]) -> Self {
+        let files = files
+            .iter()
+            .filter(|f| !f.ends_with(".json") && !f.ends_with(".html"))
+            .map(|f| (f.rsplit('/').next().unwrap_or(f), f.as_str()))
+            .collect();
+        Self { files }
+    }
+
+    /// Resolve an asset pointer (`file-service://file-…`, `sediment://file_…`)
+    /// or bare file id to `(file_id, archive entry)`.
+    fn find<'p>(&self, pointer: &'p str) -> Option<(&'p str, &'a str)> {
+        let file_id = pointer.rsplit("://").next().unwrap_or(pointer);
+        if file_id.is_empty() {
+            return None;
+        }
+        self.files
+            .iter()
+            .find(|(name, _)| name.starts_with(file_id))
+            .map(|(_, entry)| (file_id, *entry))
+    }
+}
+
+fn mime_for(entry: &str) -> String {
+    let ext = entry
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_ascii_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "pdf" => "application/pdf",
+        "txt" | "md" => "text/plain",
+        "csv" => "text/csv",
+        "json" => "application/json",
+        "wav" => "audio/wav",
+        "mp3" => "audio/mpeg",
+        _ => "application/octet-stream",
+    }
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tree_splits_into_main_thread_and_branches_with_assets() {
+        let raw: Vec<RawConversation> = serde_json::from_value(serde_json::json!([{
+            "id": "c1",
+            "title": "Photos",
+            "create_time": 1.0,
+            "current_node": "n5",
+            "mapping": {
+                "root": { "parent": null, "children": ["n1"], "message": null },
+                "n1": { "parent": "root", "children": ["n2", "n3"], "message": {
+                    "author": { "role": "user" }, "create_time": 1.0,
+                    "content": { "parts": ["hi"] } } },
+                "n2": { "parent": "n1", "children": [], "message": {
+                    "author": { "role": "assistant" }, "create_time": 2.0,
+                    "content": { "parts": ["first"] } } },
+                "n3": { "parent": "n1", "children": ["n4"], "message": {
+                    "author": { "role": "assistant" }, "create_time": 3.0,
+                    "content": { "parts": ["second"] },
+                    "metadata": { "model_slug": "gpt-4o" } } },
+                "n4": { "parent": "n3", "children": ["n5"], "message": {
+                    "author": { "role": "user" }, "create_time": 4.0,
+                    "content": { "parts": [
+                        { "content_type": "image_asset_pointer",
+                          "asset_pointer": "file-service://file-abc" },
+                        "look"
+                    ] } } },
+                "n5": { "parent": "n4", "children": [], "message": {
+                    "author": { "role": "assistant" }, "create_time": 5.0,
+                    "content": { "parts": ["nice"] } } }
+            }
+        }]))
+        .expect("raw export");
+        let files = vec![
+            "conversations.json".to_string(),
+            "file-abc-photo.png".to_string(),
+        ];
+
+        let export = parse_conversations(raw, &files);
+        assert_eq!(export.conversations.len(), 2);
+
+        let main = &export.conversations[0];
+        assert_eq!(main.external_id.as_deref(), Some("c1"));
+        let contents: Vec<&str> = main.messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, ["hi", "second", "look", "nice"]);
+        assert_eq!(main.model.as_deref(), Some("gpt-4o"));
+
+        let branch = &export.conversations[1];
+        assert_eq!(branch.external_id.as_deref(), Some("c1#n2"));
+        assert_eq!(branch.parent_external_id.as_deref(), Some("c1"));
+        assert_eq!(branch.parent_message_idx, Some(1));
+        assert_eq!(branch.fork_type.as_deref(), Some("fork"));
+        assert_eq!(branch.messages.len(), 1);
+        assert_eq!(branch.messages[0].content, "first");
+
+        assert_eq!(
+            export.assets,
+            vec![PendingAsset {
+                external_id: "c1".to_string(),
+                message_idx: 2,
+                attachment_id: "file-abc".to_string(),
+                entry: "file-abc-photo.png".to_string(),
+                mime_type: "image/png".to_string(),
+                filename: None,
+            }]
+        );
+        let parts = main.messages[2].parts.as_ref().expect("parts");
+        assert_eq!(parts[1]["type"], "image");
+        assert_eq!(parts[1]["attachmentId"], "file-abc");
+    }
+}
@@ -0,0 +1,556 @@
+//! Portable archives (`hstry export --format hstry-archive`): a
+//! zstd-compressed tar for moving history between machines without copying
+//! the SQLite files.
+//!
+//! Entries, in this order:
+//! - `manifest.json`: format name and version, export time and counts
+//! - `sources.jsonl`: the sources of the exported conversations
+//! - `conversations.jsonl`: one conversation per line, in the shape of
+//!   `hstry export --format jsonl`, forks after the conversation they
+//!   branch from
+//! - `attachments.jsonl`: the conversation and message of each attachment
+//! - `attachments/<n>`: the content of line `n` of `attachments.jsonl`
+//!
+//! Conversations keep their source and external id, so importing an archive
+//! again, or into the database it came from, updates them in place.
+//! Sensitive conversations are left out.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, Write};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use hstry_core::Database;
+use hstry_core::cancel::CancelToken;
+use hstry_core::export;
+use hstry_core::models::{Conversation, Source};
+use hstry_core::parsed::ParsedConversation;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::IMPORT_BATCH_SIZE;
+
+pub const FORMAT: &str = "hstry-archive";
+
+/// Layout version written by [`write_archive`]; newer archives are refused.
+pub const VERSION: u32 = 1;
+
+const MANIFEST: &str = "manifest.json";
+const SOURCES: &str = "sources.jsonl";
+const CONVERSATIONS: &str = "conversations.jsonl";
+const ATTACHMENTS: &str = "attachments.jsonl";
+
+type Reader = tar::Archive<zstd::Decoder<'static, BufReader<File>>>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    /// Version of hstry that wrote the archive.
+    pub hstry_version: String,
+    pub sources: usize,
+    pub conversations: usize,
+    pub messages: usize,
+    pub attachments: usize,
+}
+
+/// A line of `attachments.jsonl`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct AttachmentEntry {
+    /// Archive entry holding the content.
+    file: String,
+    id: String,
+    source_id: String,
+    external_id: String,
+    /// Position of the message in its conversation.
+    message_idx: usize,
+    name: Option<String>,
+    mime_type: Option<String>,
+}
+
+/// Outcome of [`write_archive`].
+#[derive(Debug, Serialize)]
+pub struct ArchiveExport {
+    #[serde(flatten)]
+    pub manifest: Manifest,
+    /// Sensitive conversations left out.
+    pub skipped_sensitive: usize,
+}
+
+/// Outcome of [`import_archive`].
+#[derive(Debug, Default, Serialize)]
+pub struct ArchiveImport {
+    /// Sources the archive added to this database.
+    pub sources_added: usize,
+    pub conversations: usize,
+    pub messages: usize,
+    /// Attachments written; ones already stored are skipped.
+    pub attachments: usize,
+    pub dry_run: bool,
+}
+
+/// Write `conversations` to a new archive at `output`.
+pub async fn write_archive(
+    db: &Database,
+    conversations: &[Conversation],
+    output: &Path,
+) -> Result<ArchiveExport> {
+    let parents: HashMap<String, Option<&str>> = conversations
+        .iter()
+        .map(|c| (c.id.to_string(), c.parent_conversation_id.as_deref()))
+        .collect();
+    let external_ids: HashMap<String, String> = conversations
+        .iter()
+        .map(|c| {
+            let external_id = c.external_id.clone().unwrap_or_else(|| c.id.to_string());
+            (c.id.to_string(), external_id)
+        })
+        .collect();
+    let mut ordered: Vec<&Conversation> = conversations.iter().collect();
+    ordered.sort_by_cached_key(|c| (fork_depth(&parents, c), c.source_id.clone()));
+
+    // The manifest comes first and holds the counts, so conversations are
+    // spooled to a temp file until all of them are read.
+    let mut lines = tempfile::tempfile()?;
+    let mut attachments = Vec::new();
+    let mut source_ids = BTreeSet::new();
+    let mut written = 0usize;
+    let mut messages = 0usize;
+    let mut skipped_sensitive = 0usize;
+    for conv in ordered {
+        if db.is_sensitive(conv.id).await? {
+            skipped_sensitive += 1;
+            continue;
+        }
+        let stored = db.get_messages(conv.id).await?;
+        let positions: HashMap<Uuid, usize> =
+            stored.iter().enumerate().map(|(i, m)| (m.id, i)).collect();
+        let external_id = external_ids[&conv.id.to_string()].clone();
+        let mut parsed = export::to_parsed(conv, stored);
+        parsed.external_id = Some(external_id.clone());
+        parsed.parent_external_id = conv
+            .parent_conversation_id
+            .as_ref()
+            .and_then(|id| external_ids.get(id))
+            .cloned();
+        serde_json::to_writer(&mut lines, &parsed)?;
+        lines.write_all(b"\n")?;
+        written += 1;
+        messages += parsed.messages.len();
+
+        for info in db.list_conversation_attachments(conv.id).await? {
+            let Some(&message_idx) = positions.get(&info.message_id) else {
+                continue;
+            };
+            attachments.push(AttachmentEntry {
+                file: format!("attachments/{}", attachments.len()),
+                id: info.id,
+                source_id: conv.source_id.clone(),
+                external_id: external_id.clone(),
+                message_idx,
+                name: info.name,
+                mime_type: info.mime_type,
+            });
+        }
+        source_ids.insert(conv.source_id.clone());
+    }
+    let mut sources = Vec::with_capacity(source_ids.len());
+    for id in &source_ids {
+        sources.extend(db.get_source(id).await?);
+    }
+
+    let manifest = Manifest {
+        format: FORMAT.to_string(),
+        version: VERSION,
+        exported_at: Utc::now(),
+        hstry_version: env!("CARGO_PKG_VERSION").to_string(),
+        sources: sources.len(),
+        conversations: written,
+        messages,
+        attachments: attachments.len(),
+    };
+    let file =
+        File::create(output).with_context(|| format!("Failed to create {}", output.display()))?;
+    let mut tar = tar::Builder::new(zstd::Encoder::new(file, 0)?);
+    append(&mut tar, MANIFEST, &serde_json::to_vec_pretty(&manifest)?)?;
+    append(&mut tar, SOURCES, &to_jsonl(&sources)?)?;
+    lines.rewind()?;
+    let mut header = entry_header(lines.metadata()?.len());
+    tar.append_data(&mut header, CONVERSATIONS, &mut lines)?;
+    append(&mut tar, ATTACHMENTS, &to_jsonl(&attachments)?)?;
+    for entry in &attachments {
+        if let Some((_, data)) = db.get_attachment(&entry.id).await? {
+            append(&mut tar, &entry.file, &data)?;
+        }
+    }
+    tar.into_inner()?.finish()?.sync_all()?;
+
+    Ok(ArchiveExport {
+        manifest,
+        skipped_sensitive,
+    })
+}
+
+/// Forks above `conv` that are in the same export.
+fn fork_depth(parents: &HashMap<String, Option<&str>>, conv: &Conversation) -> usize {
+    let mut depth = 0;
+    let mut parent = conv.parent_conversation_id.as_deref();
+    while let Some(id) = parent {
+        if depth > parents.len() {
+            break;
+        }
+        depth += 1;
+        parent = parents.get(id).copied().flatten();
+    }
+    depth
+}
+
+fn entry_header(size: u64) -> tar::Header {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(size);
+    header.set_mode(0o644);
+    header.set_mtime(u64::try_from(Utc::now().timestamp()).unwrap_or_default());
+    header
+}
+
+fn append<W: Write>(tar: &mut tar::Builder<W>, path: &str, data: &[u8]) -> Result<()> {
+    let mut header = entry_header(data.len() as u64);
+    tar.append_data(&mut header, path, data)?;
+    Ok(())
+}
+
+fn to_jsonl<T: Serialize>(items: &[T]) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    for item in items {
+        serde_json::to_writer(&mut out, item)?;
+        out.push(b'\n');
+    }
+    Ok(out)
+}
+
+fn open(path: &Path) -> Result<Reader> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    Ok(tar::Archive::new(zstd::Decoder::new(file)?))
+}
+
+/// Check the first entry of an archive is a manifest this build can read.
+fn parse_manifest(mut entry: tar::Entry<'_, impl Read>) -> Result<Manifest> {
+    if entry.path()?.to_str() != Some(MANIFEST) {
+        anyhow::bail!("not an {FORMAT} (no {MANIFEST})");
+    }
+    let mut raw = Vec::new();
+    entry.read_to_end(&mut raw)?;
+    let manifest: Manifest =
+        serde_json::from_slice(&raw).with_context(|| format!("Failed to parse {MANIFEST}"))?;
+    if manifest.format != FORMAT {
+        anyhow::bail!("not an {FORMAT} (format is {})", manifest.format);
+    }
+    if manifest.version > VERSION {
+        anyhow::bail!(
+            "archive version {} was written by a newer hstry; this build reads up to version {VERSION}",
+            manifest.version
+        );
+    }
+    Ok(manifest)
+}
+
+/// The manifest of the archive at `path`.
+pub fn read_manifest(path: &Path) -> Result<Manifest> {
+    let mut archive = open(path)?;
+    let entry = archive
+        .entries()?
+        .next()
+        .with_context(|| format!("{} is empty", path.display()))??;
+    parse_manifest(entry)
+}
+
+/// True when `path` is an archive written by [`write_archive`].
+pub fn is_archive(path: &Path) -> bool {
+    path.is_file() && read_manifest(path).is_ok()
+}
+
+/// Import the archive at `path`. Sources missing from this database are
+/// added; `dry_run` only counts what would be imported. Stops between
+/// batches when `cancel` fires.
+pub async fn import_archive(
+    db: &Database,
+    path: &Path,
+    dry_run: bool,
+    cancel: &CancelToken,
+) -> Result<ArchiveImport> {
+    let mut archive = open(path)?;
+    let mut entries = archive.entries()?;
+    let first = entries
+        .next()
+        .with_context(|| format!("{} is empty", path.display()))??;
+    parse_manifest(first).with_context(|| format!("Failed to read {}", path.display()))?;
+
+    let mut result = ArchiveImport {
+        dry_run,
+        ..ArchiveImport::default()
+    };
+    let mut attachments: HashMap<String, AttachmentEntry> = HashMap::new();
+    let mut message_ids: HashMap<(String, String), Vec<Uuid>> = HashMap::new();
+    for entry in entries {
+        let mut entry = entry?;
+        let name = entry.path()?.to_string_lossy().into_owned();
+        match name.as_str() {
+            SOURCES => {
+                for line in BufReader::new(entry).lines() {
+                    let source: Source = serde_json::from_str(&line?)
+                        .with_context(|| format!("Failed to parse {SOURCES}"))?;
+                    if db.get_source(&source.id).await?.is_some() {
+                        continue;
+                    }
+                    result.sources_added += 1;
+                    if !dry_run {
+                        db.upsert_source(&Source {
+                            last_sync_at: None,
+                            ..source
+                        })
+                        .await?;
+                    }
+                }
+            }
+            CONVERSATIONS => {
+                import_conversations(db, BufReader::new(entry), dry_run, cancel, &mut result)
+                    .await?;
+            }
+            ATTACHMENTS => {
+                for line in BufReader::new(entry).lines() {
+                    let attachment: AttachmentEntry = serde_json::from_str(&line?)
+                        .with_context(|| format!("Failed to parse {ATTACHMENTS}"))?;
+                    attachments.insert(attachment.file.clone(), attachment);
+                }
+            }
+            _ => {
+                // Entries added by later versions are skipped.
+                let Some(attachment) = attachments.get(&name) else {
+                    continue;
+                };
+                if db.get_attachment(&attachment.id).await?.is_some() {
+                    continue;
+                }
+                if dry_run {
+                    result.attachments += 1;
+                    continue;
+                }
+                let key = (attachment.source_id.clone(), attachment.external_id.clone());
+                if !message_ids.contains_key(&key) {
+                    let ids = match db
+                        .get_conversation_id(&attachment.source_id, &attachment.external_id)
+                        .await?
+                    {
+                        Some(conv_id) => db
+                            .get_messages(conv_id)
+                            .await?
+                            .into_iter()
+                            .map(|m| m.id)
+                            .collect(),
+                        None => Vec::new(),
+                    };
+                    message_ids.insert(key.clone(), ids);
+                }
+                let Some(message_id) = message_ids[&key].get(attachment.message_idx) else {
+                    continue;
+                };
+                let mut data = Vec::new();
+                entry.read_to_end(&mut data)?;
+                db.insert_attachment(
+                    &attachment.id,
+                    *message_id,
+                    attachment
+                        .mime_type
+                        .as_deref()
+                        .unwrap_or("application/octet-stream"),
+                    attachment.name.as_deref(),
+                    &data,
+                )
+                .await?;
+                result.attachments += 1;
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Ingest `conversations.jsonl` in batches of one source each.
+async fn import_conversations(
+    db: &Database,
+    lines: impl BufRead,
+    dry_run: bool,
+    cancel: &CancelToken,
+    result: &mut ArchiveImport,
+) -> Result<()> {
+    let mut affected = Vec::new();
+    let mut batch: Vec<ParsedConversation> = Vec::new();
+    let mut batch_source = String::new();
+    let mut lines = lines.lines();
+    loop {
+        let next = match lines.next() {
+            Some(line) => {
+                let conv: ParsedConversation = serde_json::from_str(&line?)
+                    .with_context(|| format!("Failed to parse {CONVERSATIONS}"))?;
+                let source_id = conv
+                    .source_id
+                    .clone()
+                    .with_context(|| format!("A conversation in {CONVERSATIONS} has no source"))?;
+                result.conversations += 1;
+                Some((source_id, conv))
+            }
+            None => None,
+        };
+        let flush = match &next {
+            Some((source_id, _)) => *source_id != batch_source || batch.len() >= IMPORT_BATCH_SIZE,
+            None => true,
+        };
+        if flush && !batch.is_empty() {
+            let pending = std::mem::take(&mut batch);
+            if dry_run {
+                result.messages += pending.iter().map(|c| c.messages.len()).sum::<usize>();
+            } else {
+                let ingested = cancel
+                    .run(hstry_core::ingest::ingest_batch(db, &batch_source, pending))
+                    .await;
+                let Ok(outcome) = ingested else {
+                    return cancelled(db, &affected).await;
+                };
+                let outcome = outcome?;
+                result.messages += outcome.messages;
+                affected.extend(outcome.affected_conversation_ids);
+                if cancel.run(db.pace_writes(outcome.bytes)).await.is_err() {
+                    return cancelled(db, &affected).await;
+                }
+            }
+        }
+        let Some((source_id, conv)) = next else {
+            break;
+        };
+        batch_source = source_id;
+        batch.push(conv);
+    }
+    if !affected.is_empty() {
+        db.rebuild_conversation_summaries(&affected).await?;
+    }
+    Ok(())
+}
+
+async fn cancelled(db: &Database, affected: &[Uuid]) -> Result<()> {
+    if !affected.is_empty() {
+        db.rebuild_conversation_summaries(affected).await?;
+    }
+    anyhow::bail!("Import cancelled; re-run to finish")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hstry_core::db::ListConversationsOptions;
+
+    fn conversation(external_id: &str, parent: Option<&str>) -> ParsedConversation {
+        serde_json::from_value(serde_json::json!({
+            "externalId": external_id,
+            "title": format!("Conversation {external_id}"),
+            "createdAt": 1_760_000_000_000i64,
+            "parentExternalId": parent,
+            "parentMessageIdx": parent.map(|_| 0),
+            "messages": [
+                {"role": "user", "content": "Plot the latency"},
+                {"role": "assistant", "content": "Here is the chart"},
+            ],
+        }))
+        .expect("conversation")
+    }
+
+    #[tokio::test]
+    async fn archives_round_trip_forks_and_attachments() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let db = Database::open(&dir.path().join("from.db"))
+            .await
+            .expect("open db");
+        db.upsert_source(&Source {
+            id: "codex".to_string(),
+            adapter: "codex".to_string(),
+            path: Some("~/.codex/sessions".to_string()),
+            last_sync_at: Some(Utc::now()),
+            config: serde_json::json!({}),
+        })
+        .await
+        .expect("source");
+        let batch = vec![
+            conversation("fork", Some("root")),
+            conversation("root", None),
+        ];
+        hstry_core::ingest::ingest_batch(&db, "codex", batch)
+            .await
+            .expect("ingest");
+        let root = db
+            .get_conversation_id("codex", "root")
+            .await
+            .expect("lookup")
+            .expect("root");
+        let reply = db.get_messages(root).await.expect("messages")[1].id;
+        db.insert_attachment("chart", reply, "image/png", Some("chart.png"), b"png")
+            .await
+            .expect("attachment");
+
+        let conversations = db
+            .list_conversations(ListConversationsOptions::default())
+            .await
+            .expect("list");
+        let path = dir.path().join("history.tar.zst");
+        let written = write_archive(&db, &conversations, &path)
+            .await
+            .expect("export");
+        assert_eq!(written.manifest.conversations, 2);
+        assert_eq!(written.manifest.messages, 4);
+        assert_eq!(written.manifest.attachments, 1);
+        assert!(is_archive(&path));
+        assert!(!is_archive(&dir.path().join("from.db")));
+
+        let other = Database::open(&dir.path().join("to.db"))
+            .await
+            .expect("open db");
+        let cancel = CancelToken::new();
+        let imported = import_archive(&other, &path, false, &cancel)
+            .await
+            .expect("import");
+        assert_eq!(imported.sources_added, 1);
+        assert_eq!(imported.conversations, 2);
+        assert_eq!(imported.attachments, 1);
+
+        let source = other.get_source("codex").await.expect("source");
+        assert!(source.is_some_and(|s| s.last_sync_at.is_none()));
+        let root = other
+            .get_conversation_id("codex", "root")
+            .await
+            .expect("lookup")
+            .expect("root");
+        let fork = other
+            .get_conversation_id("codex", "fork")
+            .await
+            .expect("lookup")
+            .expect("fork");
+        let fork = other
+            .get_conversation(fork)
+            .await
+            .expect("get")
+            .expect("fork");
+        assert_eq!(fork.parent_conversation_id, Some(root.to_string()));
+        let (mime, data) = other
+            .get_attachment("chart")
+            .await
+            .expect("attachment")
+            .expect("stored");
+        assert_eq!((mime.as_str(), data.as_slice()), ("image/png", &b"png"[..]));
+
+        let again = import_archive(&other, &path, false, &cancel)
+            .await
+            .expect("re-import");
+        assert_eq!((again.sources_added, again.attachments), (0, 0));
+        assert_eq!(other.count_conversations().await.expect("count"), 2);
+    }
+}
@@ -1,19 +1,40 @@
 #![allow(clippy::print_stdout, clippy::print_stderr)]
 //! hstry CLI - Universal AI chat history
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 use std::process::{Command as ProcessCommand, Stdio};
 use std::sync::Arc;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
 use futures::stream::{self, StreamExt};
-use hstry_core::config::{AdapterRepo, AdapterRepoSource};
+use hstry_core::agent_search::is_system_context;
+use hstry_core::api_token::{self, ApiTokenScope};
+use hstry_core::branches;
+use hstry_core::bundle::ConfigBundle;
+use hstry_core::cancel::CancelToken;
+use hstry_core::config::{
+    AdapterRepo, AdapterRepoSource, ConflictStrategy, RemoteTransport, RetentionAction,
+};
+use hstry_core::dates::parse_date_filter;
+use hstry_core::db::{ListToolCallsOptions, MessageRange, SavedSnapshot, SearchIndexStatus};
+use hstry_core::export::{self, ExportFormat};
+use hstry_core::graph::{self, GraphConversation, GraphFormat, GraphOptions};
+use hstry_core::html_export;
 use hstry_core::models::{Conversation, Message, MessageRole, SearchHit, Source};
-use hstry_core::{Config, Database};
-use hstry_runtime::{AdapterRunner, ExportConversation, ExportOptions, ParsedMessage, Runtime};
+use hstry_core::parsed::ParsedConversation;
+use hstry_core::revisions::{MessageRevision, RevisionKind};
+use hstry_core::saved_search::{SavedFilters, SavedSearch};
+use hstry_core::sensitive;
+use hstry_core::share;
+use hstry_core::tool_calls;
+use hstry_core::{Config, Database, tr};
+use hstry_runtime::{
+    AdapterRunner, ExportConversation, ExportOptions, ExportResult, ParsedMessage, Runtime,
+    RuntimeKind,
+};
 
 /// Apply storage feature flags from `config` to a freshly opened `Database`.
 /// Centralised so every entry point honours the trx-aa3m / trx-z42c contracts.
@@ -22,7 +43,74 @@ fn apply_storage_config(db: &Database, config: &Config) {
     db.set_indexer_outbox_enabled(config.storage.indexer_outbox.enabled);
 }
 
+/// Throttle writes through `db` and lower the process priority as `[nice]`
+/// says, when `nice` is set or `[nice] enabled` is.
+fn apply_nice_mode(db: &Database, config: &Config, nice: bool) {
+    if !(nice || config.nice.enabled) {
+        return;
+    }
+    db.set_throttle(Some(hstry_core::throttle::Throttle::new(&config.nice)));
+    if config.nice.low_priority {
+        hstry_core::throttle::lower_priority();
+    }
+}
+
+/// Build the adapter runner for `config`: the `js_runtime` priority list,
+/// the runtimes adapter manifests require, and `[[adapters]]` overrides.
+///
+/// A missing runtime is not an error here; it surfaces only when an adapter
+/// is actually run, so commands that merely may need one keep working on
+/// machines without bun, deno, or node.
+fn adapter_runner(config: &Config) -> Result<AdapterRunner> {
+    let preference = RuntimeKind::parse_list(&config.js_runtime).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Unknown js_runtime '{}'. Use auto, bun, deno, node, or a list like \"bun,node\".",
+            config.js_runtime
+        )
+    })?;
+    let mut runner = match Runtime::parse(&config.js_runtime) {
+        Some(runtime) => AdapterRunner::new(runtime, config.adapter_paths.clone())
+            .with_runtime_preference(preference),
+        None => AdapterRunner::without_runtime(preference, config.adapter_paths.clone()),
+    }
+    .with_log_dir(config.adapter_log_dir())
+    .with_worker_idle_timeout(std::time::Duration::from_secs(
+        config.adapter_worker_idle_secs,
+    ));
+
+    for (name, names) in adapter_manifest::runtime_requirements(&config.adapter_paths) {
+        let kinds: Vec<RuntimeKind> = names
+            .iter()
+            .filter_map(|name| RuntimeKind::parse(name))
+            .collect();
+        runner = runner.with_adapter_runtimes(&name, kinds);
+    }
+    for adapter in &config.adapters {
+        if let Some(secs) = adapter.timeout_secs {
+            runner =
+                runner.with_adapter_timeout(&adapter.name, std::time::Duration::from_secs(secs));
+        }
+        let Some(runtime) = &adapter.runtime else {
+            continue;
+        };
+        let kinds = RuntimeKind::parse_list(runtime)
+            .filter(|kinds| !kinds.is_empty())
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Unknown runtime '{runtime}' for adapter '{}'. Use bun, deno, node, or a list like \"bun,node\".",
+                    adapter.name
+                )
+            })?;
+        runner = runner.with_adapter_runtimes(&adapter.name, kinds);
+    }
+    Ok(runner)
+}
+
 mod adapter_manifest;
+mod alerts;
+mod aliases;
+mod chatgpt_zip;
+mod hstry_archive;
 use serde::{Serialize, de::DeserializeOwned};
 
 mod pretty;
@@ -32,6 +120,7 @@ mod sync;
 #[derive(Debug, serde::Deserialize)]
 struct SyncInput {
     source: Option<String>,
+    group: Option<String>,
     parallel: Option<usize>,
 }
 
@@ -44,6 +133,10 @@ struct SearchInput {
     mode: Option<SearchModeArg>,
     scope: Option<SearchScopeArg>,
     remotes: Option<Vec<String>>,
+    rerank: Option<bool>,
+    expand: Option<Vec<SearchExpandArg>>,
+    context: Option<usize>,
+    group_by: Option<SearchGroupArg>,
 }
 
 #[derive(Debug, serde::Deserialize)]
@@ -58,6 +151,10 @@ struct ListInput {
 #[derive(Debug, serde::Deserialize)]
 struct ShowInput {
     id: String,
+    page: Option<usize>,
+    page_size: Option<usize>,
+    branches: Option<bool>,
+    history: Option<bool>,
 }
 
 #[derive(Debug, serde::Deserialize)]
@@ -104,6 +201,9 @@ struct StatsSummary {
     messages: i64,
     per_source: Vec<hstry_core::db::SourceStats>,
     activity: hstry_core::db::ActivityStats,
+    llm_usage: Vec<hstry_core::db::LlmUsageSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detailed: Option<hstry_core::db::DetailedStats>,
 }
 
 #[derive(Debug, serde::Serialize)]
@@ -119,6 +219,26 @@ struct AdapterStatus {
     name: String,
     enabled: bool,
 }
+
+#[derive(Debug, serde::Serialize)]
+struct AdapterDetails {
+    name: String,
+    display_name: Option<String>,
+    version: Option<String>,
+    path: PathBuf,
+    enabled: bool,
+    /// `None` when the adapter has no `adapter.json`.
+    capabilities: Option<Vec<adapter_manifest::Capability>>,
+    min_runtime: BTreeMap<String, String>,
+    runtimes: Vec<String>,
+    default_paths: Vec<String>,
+    /// Repository the installed version came from, from the lockfile.
+    source: Option<String>,
+    /// Versions kept for pinned sources.
+    versions: Vec<String>,
+    /// Why the adapter's `info` call failed, if it did.
+    info_error: Option<String>,
+}
 #[derive(Debug, Parser)]
 #[command(
     name = "hstry",
@@ -152,18 +272,36 @@ enum Command {
         #[arg(long)]
         source: Option<String>,
 
+        /// Only sync the sources of a `[groups]` entry
+        #[arg(long, conflicts_with = "source")]
+        group: Option<String>,
+
         /// Max number of sources to sync in parallel
         #[arg(long)]
         parallel: Option<usize>,
 
+        /// Print progress as JSON lines while syncing; the last line is the
+        /// usual JSON result
+        #[arg(long)]
+        stream: bool,
+
         /// Read JSON input from file or "-" for stdin
         #[arg(long)]
         input: Option<PathBuf>,
+
+        /// Throttle writes and run at low priority (see `[nice]`)
+        #[arg(long)]
+        nice: bool,
+
+        /// Reparse everything, ignoring stored cursors and unchanged files
+        #[arg(long)]
+        full: bool,
     },
 
     /// Import chat history from a file or directory with auto-detection
     Import {
-        /// Path to file or directory to import
+        /// Path to file or directory to import, or "-" to read an export
+        /// from stdin
         path: PathBuf,
 
         /// Force a specific adapter (skip auto-detection)
@@ -177,12 +315,17 @@ enum Command {
         /// Only show what would be imported (don't write to database)
         #[arg(long)]
         dry_run: bool,
+
+        /// Throttle writes and run at low priority (see `[nice]`)
+        #[arg(long)]
+        nice: bool,
     },
 
     /// Search across chat history
     Search {
         /// Search query
-        query: String,
+        #[arg(required_unless_present_any = ["saved", "input"])]
+        query: Option<String>,
 
         /// Maximum results
         #[arg(short, long, default_value = "20")]
@@ -244,20 +387,87 @@ enum Command {
         #[arg(long)]
         tag: Option<String>,
 
+        /// Only search starred conversations
+        #[arg(long)]
+        starred: bool,
+
         /// Show each session only once with occurrence count
         #[arg(short, long)]
         compact: bool,
 
+        /// Also search conversations moved to the archive database
+        #[arg(long)]
+        include_archived: bool,
+
+        /// Re-rank the best hits with the `[search.rerank]` model
+        #[arg(long)]
+        rerank: bool,
+
+        /// Embed related records in each JSON hit (`conversation`)
+        #[arg(long, value_enum, value_delimiter = ',')]
+        expand: Vec<SearchExpandArg>,
+
+        /// Show N messages before and after each hit
+        #[arg(short = 'C', long, value_name = "N", default_value_t = 0)]
+        context: usize,
+
+        /// Return one entry per conversation, ranked by all of its hits
+        #[arg(long, value_enum, conflicts_with_all = ["compact", "context"])]
+        group_by: Option<SearchGroupArg>,
+
+        /// Save the query and its filters under this name, then search
+        #[arg(long, value_name = "NAME")]
+        save: Option<String>,
+
+        /// Run the search saved under this name; filters given here are
+        /// added to the saved ones
+        #[arg(long, value_name = "NAME", conflicts_with_all = ["query", "save"])]
+        saved: Option<String>,
+
         /// Read JSON input from file or "-" for stdin
         #[arg(long)]
         input: Option<PathBuf>,
     },
 
-    /// Build or refresh the search index
+    /// Answer a question from your history with the `[ask]` model
+    Ask {
+        /// Question in plain language
+        question: String,
+
+        /// Only use conversations from this source
+        #[arg(long)]
+        source: Option<String>,
+
+        /// Only use conversations whose workspace contains this value
+        #[arg(long)]
+        workspace: Option<String>,
+
+        /// Only use messages after this date (ISO 8601 or relative)
+        #[arg(long)]
+        after: Option<String>,
+
+        /// Only use messages before this date (ISO 8601 or relative)
+        #[arg(long)]
+        before: Option<String>,
+
+        /// Messages given to the model (default: `[ask] passages`)
+        #[arg(long)]
+        passages: Option<usize>,
+    },
+
+    /// Build, inspect or compact the search index
+    #[command(args_conflicts_with_subcommands = true)]
     Index {
+        #[command(subcommand)]
+        command: Option<IndexCommand>,
+
         /// Rebuild the index from scratch
         #[arg(long)]
         rebuild: bool,
+
+        /// Run at low priority (see `[nice]`)
+        #[arg(long)]
+        nice: bool,
     },
 
     /// List conversations
@@ -299,18 +509,92 @@ enum Command {
         /// hidden by default. Their content is always searchable regardless.
         #[arg(short, long)]
         all: bool,
+
+        /// Also list conversations moved to the archive database
+        #[arg(long, conflicts_with = "peek")]
+        include_archived: bool,
+
+        /// Only list conversations ingested since the profile's last-seen mark
+        #[arg(long)]
+        new: bool,
+
+        /// Only list starred conversations
+        #[arg(long)]
+        starred: bool,
+
+        /// Mark everything as read for the active profile after listing
+        #[arg(long)]
+        mark_read: bool,
     },
 
     /// Show a conversation
     Show {
-        /// Conversation ID, unique prefix, or external ID
-        id: String,
+        /// Conversation ID, unique prefix, or external ID (default: pick one
+        /// with fzf)
+        id: Option<String>,
+
+        /// Show only this page of messages (1-based)
+        #[arg(long)]
+        page: Option<usize>,
+
+        /// Messages per page when using --page
+        #[arg(long, default_value = "50")]
+        page_size: usize,
+
+        /// Also show alternate branches (regenerated answers, edited prompts)
+        #[arg(long, conflicts_with = "page")]
+        branches: bool,
+
+        /// Also show earlier versions of messages that a later sync found
+        /// edited or regenerated
+        #[arg(long)]
+        history: bool,
 
         /// Read JSON input from file or "-" for stdin
         #[arg(long)]
         input: Option<PathBuf>,
     },
 
+    /// Pick a conversation with fzf, then show or export it
+    ///
+    /// Matches on source, date, workspace, title and first prompt, with a
+    /// preview of each candidate. `hstry show` without an ID opens the same
+    /// picker.
+    Pick {
+        /// Filter by source
+        #[arg(long)]
+        source: Option<String>,
+
+        /// Filter by workspace
+        #[arg(long)]
+        workspace: Option<String>,
+
+        /// Only offer conversations after this date/time (natural language)
+        #[arg(long)]
+        after: Option<String>,
+
+        /// Only offer conversations before this date/time (natural language)
+        #[arg(long)]
+        before: Option<String>,
+
+        /// Maximum conversations offered, newest first
+        #[arg(short, long, default_value_t = PICK_LIMIT)]
+        limit: i64,
+
+        /// Export the chosen conversation in this format (see `hstry export`)
+        /// instead of showing it
+        #[arg(short, long)]
+        format: Option<String>,
+
+        /// Output path for --format
+        #[arg(short, long, requires = "format")]
+        output: Option<PathBuf>,
+
+        /// Only print the chosen conversation's ID
+        #[arg(long, conflicts_with = "format")]
+        id_only: bool,
+    },
+
     /// Show a token-efficient peek bundle for a single conversation
     Peek {
         /// Conversation ID, unique prefix, or external ID
@@ -321,18 +605,161 @@ enum Command {
         chars: Option<usize>,
     },
 
-    /// Remove one conversation and its related data
+    /// Write a workspace history pack (AGENTS-HISTORY.md) for coding agents
+    Pack {
+        /// Workspace path (default: current directory)
+        #[arg(long)]
+        workspace: Option<String>,
+
+        /// Output file, or a directory to write AGENTS-HISTORY.md into
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Conversations to include in full
+        #[arg(long, default_value = "10")]
+        top: usize,
+
+        /// Rewrite the pack even if no conversation changed
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// List decisions found in conversations ("we decided to…", "let's use X")
+    Decisions {
+        /// Only decisions from workspaces containing this text
+        #[arg(long)]
+        workspace: Option<String>,
+
+        /// Show at most N decisions, newest first
+        #[arg(long, default_value = "50")]
+        limit: usize,
+    },
+
+    /// Star a conversation, or unstar it with --remove
+    Star {
+        /// Conversation UUID, unique prefix, or external ID
+        id: String,
+
+        /// Unstar the conversation
+        #[arg(long)]
+        remove: bool,
+    },
+
+    /// Move one conversation to the trash
     Remove {
         /// Conversation UUID, unique prefix, or external ID
         id: String,
 
-        /// Delete without prompting; otherwise only preview the removal
+        /// Remove without prompting; otherwise only preview the removal
         #[arg(long)]
         yes: bool,
 
         /// Preview the removal without changing the database
         #[arg(long, conflicts_with = "yes")]
         dry_run: bool,
+
+        /// Delete the conversation and its related data instead of trashing it
+        #[arg(long)]
+        permanent: bool,
+    },
+
+    /// List, restore or empty removed conversations
+    Trash {
+        #[command(subcommand)]
+        command: TrashCommand,
+    },
+
+    /// Save point-in-time copies of conversations and roll them back
+    Snapshot {
+        #[command(subcommand)]
+        command: SnapshotCommand,
+    },
+
+    /// Move conversations into the cold-storage archive database
+    Archive {
+        /// Conversation UUIDs, unique prefixes, or external IDs
+        ids: Vec<String>,
+
+        /// Select all conversations from this source
+        #[arg(long)]
+        source: Option<String>,
+
+        /// Select conversations whose workspace contains this value
+        #[arg(long)]
+        workspace: Option<String>,
+
+        /// Select conversations created before this date (ISO 8601 or relative)
+        #[arg(long)]
+        before: Option<String>,
+
+        /// Select conversations with this tag
+        #[arg(long)]
+        tag: Option<String>,
+
+        /// Move without prompting; otherwise only preview the selection
+        #[arg(long)]
+        yes: bool,
+
+        /// Preview the selection without changing either database
+        #[arg(long, conflicts_with = "yes")]
+        dry_run: bool,
+    },
+
+    /// Move archived conversations back into the main database
+    Unarchive {
+        /// Conversation UUIDs, unique prefixes, or external IDs
+        ids: Vec<String>,
+
+        /// Select all conversations from this source
+        #[arg(long)]
+        source: Option<String>,
+
+        /// Select conversations whose workspace contains this value
+        #[arg(long)]
+        workspace: Option<String>,
+
+        /// Select conversations created before this date (ISO 8601 or relative)
+        #[arg(long)]
+        before: Option<String>,
+
+        /// Select conversations with this tag
+        #[arg(long)]
+        tag: Option<String>,
+
+        /// Move without prompting; otherwise only preview the selection
+        #[arg(long)]
+        yes: bool,
+
+        /// Preview the selection without changing either database
+        #[arg(long, conflicts_with = "yes")]
+        dry_run: bool,
+    },
+
+    /// Remove conversations that fall outside the `[retention]` policy
+    Prune {
+        /// Override `retention.max_age_days` (0 disables)
+        #[arg(long)]
+        max_age_days: Option<u32>,
+
+        /// Override `retention.max_conversations_per_source` (0 disables)
+        #[arg(long)]
+        max_per_source: Option<u32>,
+
+        /// Override `retention.exclude_tags` (repeatable)
+        #[arg(long = "exclude-tag")]
+        exclude_tags: Vec<String>,
+
+        /// Prune starred conversations too
+        #[arg(long)]
+        include_starred: bool,
+
+        /// Apply the policy; otherwise only preview what would be pruned
+        #[arg(long)]
+        yes: bool,
+
+        /// Preview the pruning without changing the database
+        #[arg(long, conflicts_with = "yes")]
+        dry_run: bool,
     },
 
     /// Manage sources
@@ -361,7 +788,9 @@ enum Command {
 
     /// Export conversations to another format
     Export {
-        /// Target format (pi, opencode, codex, claude-code, markdown, json)
+        /// Target format: markdown, json, jsonl, html, graphml, dot,
+        /// daily-notes or hstry-archive (built in), or an adapter format such
+        /// as pi, opencode, codex or claude-code
         #[arg(short, long)]
         format: String,
 
@@ -381,17 +810,48 @@ enum Command {
         #[arg(long, short = 'r', value_enum)]
         role: Vec<SearchRoleArg>,
 
-        /// Output path (file for single-output exports, directory for multi-file exports)
+        /// Only export conversations added or changed since this time
+        /// (ISO 8601 or relative: "2d", "1w")
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Only rewrite conversations changed since the last incremental
+        /// export to the same output directory and format
+        #[arg(long, conflicts_with = "since")]
+        incremental: bool,
+
+        /// Output path (file for single-output exports, directory for multi-file
+        /// exports and html; html defaults to ./site)
         #[arg(short, long)]
         output: Option<PathBuf>,
 
-        /// Export one file per conversation for markdown/json
+        /// Export one file per conversation for markdown/json/jsonl
         #[arg(long)]
         session_files: bool,
 
         /// Pretty print JSON output
         #[arg(long)]
         pretty: bool,
+
+        /// Minimum similarity (0-1) for "similar" edges in graphml/dot
+        /// exports; 0 leaves them out
+        #[arg(long, default_value_t = 0.3)]
+        min_similarity: f32,
+
+        /// Obsidian vault or Logseq graph for daily-notes exports (default:
+        /// `[daily_notes].vault`)
+        #[arg(long)]
+        vault: Option<PathBuf>,
+
+        /// Day summarized by daily-notes exports: today, yesterday or
+        /// YYYY-MM-DD
+        #[arg(long, default_value = "today")]
+        day: String,
+
+        /// Only keep user and assistant text: drop reasoning, tool calls and
+        /// metadata, shorten file paths and apply the `[redact]` rules
+        #[arg(long)]
+        share_safe: bool,
     },
 
     /// Resume a conversation in a coding agent
@@ -443,7 +903,30 @@ enum Command {
     },
 
     /// Show database statistics
-    Stats,
+    Stats {
+        /// Add token and cost totals broken down by source, model, month and
+        /// workspace
+        #[arg(long)]
+        detailed: bool,
+    },
+
+    /// Report spend, estimating missing costs from `[pricing]`
+    Cost {
+        #[command(subcommand)]
+        command: CostCommand,
+    },
+
+    /// Estimate token counts the adapters did not record
+    Tokens {
+        #[command(subcommand)]
+        command: TokensCommand,
+    },
+
+    /// Analyze tool calls recorded in conversations
+    Tools {
+        #[command(subcommand)]
+        command: ToolsCommand,
+    },
 
     /// Deduplicate conversations in the database
     Dedup {
@@ -454,6 +937,28 @@ enum Command {
         /// Filter by source
         #[arg(long)]
         source: Option<String>,
+
+        /// Also remove starred duplicates
+        #[arg(long)]
+        include_starred: bool,
+
+        /// How duplicates are matched within a source
+        #[arg(long, value_enum, default_value = "exact")]
+        strategy: DedupStrategyArg,
+
+        /// Minimum similarity (0-1) for fuzzy duplicates
+        #[arg(long, default_value_t = 0.85)]
+        threshold: f32,
+
+        /// Walk the duplicate pairs and choose which conversation to remove
+        #[arg(short, long, conflicts_with = "dry_run")]
+        interactive: bool,
+    },
+
+    /// Link copies of a conversation imported through different sources
+    Link {
+        #[command(subcommand)]
+        command: LinkCommand,
     },
 
     /// Integrate with mmry
@@ -474,6 +979,42 @@ enum Command {
         command: WebCommand,
     },
 
+    /// Manage access to the hstry-api server
+    Api {
+        #[command(subcommand)]
+        command: ApiCommand,
+    },
+
+    /// Encrypt and unlock conversations of sensitive sources and workspaces
+    Sensitive {
+        #[command(subcommand)]
+        command: SensitiveCommand,
+    },
+
+    /// Audit what hstry-api and hstry-mcp handed out
+    Access {
+        #[command(subcommand)]
+        command: AccessCommand,
+    },
+
+    /// List and delete searches saved with `search --save`
+    Searches {
+        #[command(subcommand)]
+        command: SearchesCommand,
+    },
+
+    /// Manage the workspaces conversations are stored under
+    Workspace {
+        #[command(subcommand)]
+        command: WorkspaceCommand,
+    },
+
+    /// Give untitled conversations a title
+    Title {
+        #[command(subcommand)]
+        command: TitleCommand,
+    },
+
     /// Show or manage configuration
     Config {
         #[command(subcommand)]
@@ -526,6 +1067,18 @@ enum Command {
         #[arg(long)]
         repair: bool,
     },
+
+    /// Fix conversations a source stored in the wrong shape
+    Repair {
+        #[command(subcommand)]
+        command: RepairCommand,
+    },
+
+    /// Show where disk space goes and reclaim it
+    Db {
+        #[command(subcommand)]
+        command: DbCommand,
+    },
 }
 
 #[derive(Debug, Subcommand)]
@@ -538,6 +1091,24 @@ enum ConfigCommand {
 
     /// Open config in editor
     Edit,
+
+    /// Write config, adapter repos and the tag list to one file for setting
+    /// up another machine (conversations are not included)
+    ExportBundle {
+        /// Output file (stdout when omitted)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Apply a bundle written by `config export-bundle`
+    ImportBundle {
+        /// Bundle file
+        path: PathBuf,
+
+        /// Apply without confirmation (the current config is kept as .bak)
+        #[arg(long)]
+        yes: bool,
+    },
 }
 
 #[derive(Debug, Subcommand)]
@@ -583,18 +1154,394 @@ enum WebCommand {
 }
 
 #[derive(Debug, Subcommand)]
-enum RemoteCommand {
-    /// List configured remotes
-    List,
+enum IndexCommand {
+    /// Show segments, indexed messages, size and staleness of the index
+    Status,
+
+    /// Merge index segments so searches read fewer of them
+    Optimize,
+}
+
+#[derive(Debug, Subcommand)]
+enum ApiCommand {
+    /// Manage bearer tokens accepted by hstry-api
+    Token {
+        #[command(subcommand)]
+        command: ApiTokenCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum SensitiveCommand {
+    /// Create the key sensitive messages are encrypted with, then encrypt
+    /// the matching conversations already stored
+    Init,
+
+    /// Make sensitive messages readable for a while
+    Unlock {
+        /// Minutes to stay unlocked (default: `[sensitive] unlock_minutes`)
+        #[arg(long)]
+        minutes: Option<u32>,
+    },
+
+    /// Make sensitive messages unreadable again
+    Lock,
+
+    /// Encrypt conversations that became sensitive after they were stored
+    Seal,
+
+    /// Show the key, lock state and number of sensitive conversations
+    Status,
+}
+
+#[derive(Debug, Subcommand)]
+enum RepairCommand {
+    /// Split conversations that hold several sessions (e.g. a terminal
+    /// capture logging a whole day as one conversation)
+    Segment {
+        /// Source whose conversations are split
+        #[arg(long)]
+        source: String,
+
+        /// A user message this many minutes after the previous message
+        /// starts a new session (0 disables)
+        #[arg(long, value_name = "MINUTES", default_value_t = 30)]
+        gap: u32,
+
+        /// A user message matching this regex starts a new session
+        /// (repeatable)
+        #[arg(long, value_name = "REGEX")]
+        pattern: Vec<String>,
+
+        /// Sessions shorter than this stay with the session before them
+        #[arg(long, default_value_t = 2)]
+        min_messages: usize,
+
+        /// Show the sessions that would be split off without changing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum AccessCommand {
+    /// Show the searches and conversation reads served, newest first
+    Log {
+        /// Only reads over `api` or `mcp`
+        #[arg(long, value_parser = ["api", "mcp"])]
+        channel: Option<String>,
+
+        /// Only reads by this API token name or MCP scope
+        #[arg(long)]
+        caller: Option<String>,
+
+        /// Only reads that returned this conversation (UUID, prefix or
+        /// readable id)
+        #[arg(long)]
+        conversation: Option<String>,
+
+        /// Only reads after this time (e.g. "2024-01-01", "7d", "yesterday")
+        #[arg(long)]
+        after: Option<String>,
+
+        /// Maximum number of entries
+        #[arg(short = 'n', long, default_value = "50")]
+        limit: i64,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum SearchesCommand {
+    /// Show the saved searches with their queries and filters
+    List,
+
+    /// Delete a saved search
+    Delete {
+        /// Name the search was saved under
+        name: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum WorkspaceCommand {
+    /// Show workspaces with their names, git remotes, aliases and
+    /// conversation counts
+    List,
+
+    /// Give a workspace a display name shown instead of its path
+    Rename {
+        /// Workspace path
+        path: String,
+        /// New display name; omit with `--clear`
+        #[arg(required_unless_present = "clear")]
+        name: Option<String>,
+        /// Remove the display name
+        #[arg(long, conflicts_with = "name")]
+        clear: bool,
+    },
+
+    /// Move the conversations of one workspace into another and keep
+    /// storing the first one's conversations there
+    Merge {
+        /// Workspace to merge away
+        from: String,
+        /// Workspace to merge into
+        into: String,
+    },
+
+    /// Apply the `[workspace_paths]` rules to workspaces stored before
+    Normalize {
+        /// Show what would change without writing it
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum TitleCommand {
+    /// Derive titles from the first user message, or ask the `[titles]`
+    /// model with `--llm`
+    Generate {
+        /// Only conversations from this source
+        #[arg(long)]
+        source: Option<String>,
+
+        /// Only conversations whose workspace contains this value
+        #[arg(long)]
+        workspace: Option<String>,
+
+        /// Title at most N conversations, newest first
+        #[arg(short, long)]
+        limit: Option<usize>,
+
+        /// Ask the `[titles]` model instead of using the first user message
+        #[arg(long)]
+        llm: bool,
+
+        /// Also replace titles the adapter provided
+        #[arg(long)]
+        force: bool,
+
+        /// Show the titles without storing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum CostCommand {
+    /// Recorded and estimated spend per month, model or workspace
+    Report {
+        /// How to group conversations
+        #[arg(long, value_enum, default_value = "month")]
+        by: CostGroupArg,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum TrashCommand {
+    /// List conversations in the trash, most recently removed first
+    List {
+        /// Maximum number of conversations
+        #[arg(short, long)]
+        limit: Option<i64>,
+    },
+
+    /// Move conversations back out of the trash
+    Restore {
+        /// Conversation UUIDs, unique prefixes, or external IDs
+        #[arg(required_unless_present = "all")]
+        ids: Vec<String>,
+
+        /// Restore everything in the trash
+        #[arg(long, conflicts_with = "ids")]
+        all: bool,
+    },
+
+    /// Delete conversations in the trash for good
+    Empty {
+        /// Only those removed more than this many days ago
+        #[arg(long)]
+        older_than: Option<u32>,
+
+        /// Delete without prompting; otherwise only preview
+        #[arg(long)]
+        yes: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum SnapshotCommand {
+    /// Save a copy of a conversation as it is now
+    Create {
+        /// Conversation UUID, unique prefix, or external ID
+        id: String,
+    },
+
+    /// List saved snapshots, newest first
+    List {
+        /// Only snapshots of this conversation
+        id: Option<String>,
+
+        /// Maximum number of snapshots
+        #[arg(short, long, default_value = "20")]
+        limit: i64,
+    },
+
+    /// Roll a conversation back to a snapshot, recreating it if it was
+    /// deleted; its current state is saved as a snapshot first
+    Restore {
+        /// Snapshot ID or unique prefix
+        snapshot: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum DbCommand {
+    /// Show the size of the database, its tables, indexes and attachments
+    Size,
+
+    /// Give the space freed by removes and prunes back to the file system
+    Vacuum,
+
+    /// Merge the history of another hstry database, e.g. an old laptop's,
+    /// the way `hstry remote sync` merges a remote's
+    Merge {
+        /// Path to the other database file
+        path: PathBuf,
+
+        /// Prefix for the merged sources, which become `<namespace>:<source>`
+        /// (default: the file name without its extension)
+        #[arg(long)]
+        namespace: Option<String>,
+
+        /// How to settle conversations changed in both databases
+        #[arg(long, value_enum, default_value = "last-writer-wins")]
+        strategy: ConflictStrategyArg,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum TokensCommand {
+    /// Count tokens for messages without a count and store them as estimates
+    Backfill {
+        /// Only messages from this source
+        #[arg(long)]
+        source: Option<String>,
+
+        /// estimate (about four characters per token), o200k_base or
+        /// cl100k_base (the last two need the `tiktoken` feature); defaults
+        /// to the most accurate one available
+        #[arg(long)]
+        tokenizer: Option<String>,
+
+        /// Also recount messages estimated before, e.g. with another tokenizer
+        #[arg(long)]
+        reestimate: bool,
+
+        /// Count without storing
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum LinkCommand {
+    /// Find copies by shared external or platform id and by message text,
+    /// and record them as related
+    Detect {
+        /// Minimum similarity (0-1) for linking by message text
+        #[arg(long, default_value_t = hstry_core::links::DEFAULT_THRESHOLD)]
+        threshold: f32,
+
+        /// Find copies without recording them
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum CostGroupArg {
+    Month,
+    Model,
+    Workspace,
+}
+
+impl From<CostGroupArg> for hstry_core::db::StatsGroup {
+    fn from(value: CostGroupArg) -> Self {
+        match value {
+            CostGroupArg::Month => Self::Month,
+            CostGroupArg::Model => Self::Model,
+            CostGroupArg::Workspace => Self::Workspace,
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+enum ApiTokenCommand {
+    /// Create a token and print it (it cannot be shown again)
+    Create {
+        /// Name to recognise the token by
+        name: String,
+
+        /// What the token may do
+        #[arg(long, value_enum, default_value = "read")]
+        scope: ApiTokenScopeArg,
+    },
+
+    /// List tokens
+    List {
+        /// Include revoked tokens
+        #[arg(long)]
+        all: bool,
+    },
+
+    /// Revoke a token by id or name
+    Revoke {
+        /// Token id or name
+        token: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum ApiTokenScopeArg {
+    /// Search and read conversations
+    Read,
+    /// Read, plus ingest, deletes and remote sync pushes
+    Ingest,
+}
+
+impl From<ApiTokenScopeArg> for ApiTokenScope {
+    fn from(value: ApiTokenScopeArg) -> Self {
+        match value {
+            ApiTokenScopeArg::Read => ApiTokenScope::Read,
+            ApiTokenScopeArg::Ingest => ApiTokenScope::Ingest,
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+enum RemoteCommand {
+    /// List configured remotes
+    List,
 
     /// Add a remote host
     Add {
         /// Unique name for this remote
         name: String,
 
-        /// SSH host (e.g., "user@hostname" or SSH config alias)
+        /// SSH host (e.g., "user@hostname" or SSH config alias), or the
+        /// peer's hstry-api URL with --type http
         host: String,
 
+        /// How to reach the remote
+        #[arg(long = "type", value_enum, default_value = "ssh")]
+        transport: RemoteTransportArg,
+
+        /// Environment variable holding the bearer token for an http remote
+        #[arg(long)]
+        token_env: Option<String>,
+
         /// Path to hstry database on remote
         #[arg(long)]
         database_path: Option<String>,
@@ -636,12 +1583,65 @@ enum RemoteCommand {
         /// Sync direction
         #[arg(short, long, value_enum, default_value = "pull")]
         direction: SyncDirectionArg,
+
+        /// Pull only conversations changed since the last delta sync by
+        /// streaming `hstry export --since` over SSH instead of copying the
+        /// whole remote database (http remotes always sync deltas)
+        #[arg(long)]
+        delta: bool,
+
+        /// Keep pulling deltas every N seconds until interrupted
+        #[arg(long, value_name = "SECONDS", requires = "delta")]
+        watch: Option<u64>,
+
+        /// How to settle conversations changed on both sides (default: the
+        /// remote's `conflict_strategy`)
+        #[arg(long, value_enum)]
+        strategy: Option<ConflictStrategyArg>,
+
+        /// Shorthand for --strategy prefer-local, prefer-remote or
+        /// last-writer-wins
+        #[arg(long, value_enum, conflicts_with = "strategy")]
+        prefer: Option<ConflictPreferArg>,
+
+        /// Keep both copies of each conflict, then show how they differ and
+        /// ask which to keep
+        #[arg(short, long, conflicts_with_all = ["strategy", "prefer", "watch"])]
+        interactive: bool,
+    },
+
+    /// List conversations that changed on both sides of a sync
+    Conflicts {
+        /// Only conflicts with this remote
+        #[arg(short, long)]
+        remote: Option<String>,
+
+        /// Include conflicts already settled by the conflict strategy
+        #[arg(long, conflicts_with = "resolve")]
+        all: bool,
+
+        /// Walk open conflicts and choose which copy to keep
+        #[arg(long)]
+        resolve: bool,
+
+        /// Resolve every open conflict this way instead of asking
+        #[arg(long, value_enum, requires = "resolve")]
+        prefer: Option<ConflictPreferArg>,
     },
 
     /// Show remote cache status
     Status,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+enum DedupStrategyArg {
+    /// Identical messages
+    Exact,
+    /// Mostly the same text (MinHash similarity of the messages)
+    Fuzzy,
+}
+
 #[derive(Debug, Clone, Copy, clap::ValueEnum)]
 enum SyncDirectionArg {
     /// Pull from remote to local
@@ -652,6 +1652,66 @@ enum SyncDirectionArg {
     Bidirectional,
 }
 
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum RemoteTransportArg {
+    /// Run hstry on the remote over SSH
+    Ssh,
+    /// Talk to the remote's hstry-api server over HTTP(S)
+    Http,
+}
+
+impl From<RemoteTransportArg> for RemoteTransport {
+    fn from(value: RemoteTransportArg) -> Self {
+        match value {
+            RemoteTransportArg::Ssh => RemoteTransport::Ssh,
+            RemoteTransportArg::Http => RemoteTransport::Http,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum ConflictStrategyArg {
+    /// Keep the copy with the newer updated_at
+    LastWriterWins,
+    /// Keep the copy on this machine
+    PreferLocal,
+    /// Keep the copy from the remote
+    PreferRemote,
+    /// Keep both copies and leave the conflict open for review
+    Duplicate,
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum ConflictPreferArg {
+    /// Keep the copy on this machine
+    Local,
+    /// Keep the copy from the remote
+    Remote,
+    /// Keep the copy with the newer updated_at
+    Newest,
+}
+
+impl From<ConflictPreferArg> for ConflictStrategy {
+    fn from(value: ConflictPreferArg) -> Self {
+        match value {
+            ConflictPreferArg::Local => ConflictStrategy::PreferLocal,
+            ConflictPreferArg::Remote => ConflictStrategy::PreferRemote,
+            ConflictPreferArg::Newest => ConflictStrategy::LastWriterWins,
+        }
+    }
+}
+
+impl From<ConflictStrategyArg> for ConflictStrategy {
+    fn from(value: ConflictStrategyArg) -> Self {
+        match value {
+            ConflictStrategyArg::LastWriterWins => ConflictStrategy::LastWriterWins,
+            ConflictStrategyArg::PreferLocal => ConflictStrategy::PreferLocal,
+            ConflictStrategyArg::PreferRemote => ConflictStrategy::PreferRemote,
+            ConflictStrategyArg::Duplicate => ConflictStrategy::Duplicate,
+        }
+    }
+}
+
 impl From<SyncDirectionArg> for hstry_core::remote::SyncDirection {
     fn from(value: SyncDirectionArg) -> Self {
         match value {
@@ -695,6 +1755,33 @@ enum SourceCommand {
         input: Option<PathBuf>,
     },
 
+    /// Set adapter options for a source (passed to the adapter on every sync)
+    #[command(name = "set-option")]
+    SetOption {
+        /// Source ID
+        id: String,
+
+        /// Options as key=value; JSON values keep their type, `key=` removes
+        #[arg(required = true)]
+        options: Vec<String>,
+    },
+
+    /// Pin a source to an adapter version so adapter updates don't change
+    /// how it is parsed
+    #[command(name = "pin-adapter")]
+    PinAdapter {
+        /// Source ID
+        id: String,
+
+        /// Version to pin (defaults to the installed version)
+        #[arg(value_name = "VERSION")]
+        adapter_version: Option<String>,
+
+        /// Remove the pin and follow the installed adapter again
+        #[arg(long, conflicts_with = "adapter_version")]
+        clear: bool,
+    },
+
     /// Clean up duplicate sources (same adapter/path with different IDs)
     Cleanup {
         /// Remove duplicate sources automatically
@@ -708,6 +1795,12 @@ enum AdapterCommand {
     /// List available adapters
     List,
 
+    /// Show an adapter's version, capabilities, default paths and source
+    Info {
+        /// Adapter name
+        name: String,
+    },
+
     /// Add an adapter directory to the config
     Add {
         /// Path to the adapter directory
@@ -758,16 +1851,83 @@ enum AdapterCommand {
         #[command(subcommand)]
         command: AdapterRepoCommand,
     },
+
+    /// Show per-run adapter logs (stderr and exit status)
+    Logs {
+        /// Only show logs for this adapter
+        adapter: Option<String>,
+
+        /// Print the newest matching log instead of listing
+        #[arg(long)]
+        last: bool,
+
+        /// Maximum logs to list
+        #[arg(short, long, default_value = "20")]
+        limit: usize,
+    },
 }
 
 #[derive(Debug, Subcommand)]
-enum AdapterRepoCommand {
-    /// List configured adapter repositories
-    List,
+enum ToolsCommand {
+    /// List recent tool calls, newest first
+    List {
+        /// Only calls to this tool
+        #[arg(long)]
+        name: Option<String>,
 
-    /// Add a git repository (GitHub, GitLab, Gitea, self-hosted, etc.)
-    AddGit {
-        /// Repository name (e.g., "community")
+        /// Filter by source
+        #[arg(long)]
+        source: Option<String>,
+
+        /// Filter by workspace (substring match)
+        #[arg(long)]
+        workspace: Option<String>,
+
+        /// Only failed calls
+        #[arg(long)]
+        errors: bool,
+
+        /// Only conversations created after this date (e.g. "7d" or "2024-01-01")
+        #[arg(long)]
+        after: Option<String>,
+
+        /// Maximum calls to list
+        #[arg(short, long, default_value = "50")]
+        limit: i64,
+    },
+
+    /// Show which tools are used most
+    Stats {
+        /// Filter by source
+        #[arg(long)]
+        source: Option<String>,
+
+        /// Filter by workspace (substring match)
+        #[arg(long)]
+        workspace: Option<String>,
+
+        /// Only conversations created after this date (e.g. "7d" or "2024-01-01")
+        #[arg(long)]
+        after: Option<String>,
+
+        /// Maximum tools to show
+        #[arg(short, long, default_value = "20")]
+        limit: i64,
+    },
+
+    /// Rebuild tool calls from stored messages (for history synced before
+    /// tool calls were recorded)
+    Backfill,
+}
+
+#[derive(Debug, Subcommand)]
+enum AdapterRepoCommand {
+    /// List configured adapter repositories
+    List,
+
+    /// Add a git repository (GitHub, GitLab, Gitea, self-hosted, etc.)
+    AddGit {
+        /// Repository name (e.g., "community")
         name: String,
 
         /// Git repository URL (HTTPS or SSH)
@@ -787,7 +1947,7 @@ enum AdapterRepoCommand {
         /// Repository name
         name: String,
 
-        /// URL to the archive (.tar.gz, .zip, .tgz)
+        /// URL to the archive (.zip, .tar.gz, .tgz, .tar.zst, .tar) or file:// path
         url: String,
 
         /// Path within archive where adapters are located
@@ -917,9 +2077,33 @@ fn default_log_filter(verbose: u8) -> &'static str {
     }
 }
 
+/// Parse the command line after expanding `[aliases]` from the config.
+fn parse_cli() -> Result<Cli> {
+    use clap::CommandFactory;
+
+    let args: Vec<std::ffi::OsString> = std::env::args_os().collect();
+    let config_path = aliases::config_arg(&args).unwrap_or_else(Config::default_config_path);
+    // A broken config is reported by the regular load once parsing is done.
+    let table = if config_path.exists() {
+        Config::load_from_path(&config_path)
+            .map(|config| config.aliases)
+            .unwrap_or_default()
+    } else {
+        BTreeMap::new()
+    };
+    if table.is_empty() {
+        return Ok(Cli::parse_from(args));
+    }
+    let command = Cli::command();
+    let args = aliases::expand(args, &table, |name| {
+        name == "help" || command.find_subcommand(name).is_some()
+    })?;
+    Ok(Cli::parse_from(args))
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
-    let cli = Cli::parse();
+    let cli = parse_cli()?;
 
     // Initialize logging
     let filter = tracing_subscriber::EnvFilter::try_from_default_env()
@@ -933,6 +2117,7 @@ async fn main() -> Result<()> {
     // Load config
     let config_path = cli.config.unwrap_or_else(Config::default_config_path);
     let config = Config::ensure_at(&config_path)?;
+    hstry_core::i18n::init(config.locale.as_deref());
 
     match cli.command {
         Command::Search {
@@ -952,11 +2137,55 @@ async fn main() -> Result<()> {
             model,
             harness_filter,
             tag,
+            starred,
             compact,
+            include_archived,
+            rerank,
+            expand,
+            context,
+            group_by,
+            save,
+            saved,
             input,
         } => {
             let input = read_input::<SearchInput>(input)?;
-            let query = input.as_ref().map_or(query, |v| v.query.clone());
+            let mut query = input.as_ref().map(|v| v.query.clone()).or(query);
+            let mut filters = SavedFilters::default();
+            if let Some(name) = &saved {
+                let db = Database::open_config(&config).await?;
+                let saved = db
+                    .get_saved_search(name)
+                    .await?
+                    .ok_or_else(|| anyhow::anyhow!("No saved search named '{name}'"))?;
+                query = Some(saved.query);
+                filters = saved.filters;
+            }
+            let query = query.ok_or_else(|| anyhow::anyhow!("A search query is required"))?;
+            let source = source.or(filters.source);
+            let workspace = workspace.or(filters.workspace);
+            let mode = match (mode, filters.mode.as_deref()) {
+                (SearchModeArg::Auto, Some(saved_mode)) => {
+                    clap::ValueEnum::from_str(saved_mode, true).map_err(anyhow::Error::msg)?
+                }
+                (mode, _) => mode,
+            };
+            let role = if role.is_empty() {
+                filters
+                    .roles
+                    .iter()
+                    .map(|r| clap::ValueEnum::from_str(r, true))
+                    .collect::<std::result::Result<Vec<SearchRoleArg>, _>>()
+                    .map_err(anyhow::Error::msg)?
+            } else {
+                role
+            };
+            let no_tools = no_tools || filters.no_tools;
+            let after = after.or(filters.after);
+            let before = before.or(filters.before);
+            let model = model.or(filters.model);
+            let harness_filter = harness_filter.or(filters.harness);
+            let tag = tag.or(filters.tag);
+            let starred = starred || filters.starred;
             let limit = input.as_ref().and_then(|v| v.limit).unwrap_or(limit);
             let source = input.as_ref().and_then(|v| v.source.clone()).or(source);
             let workspace = input
@@ -969,6 +2198,42 @@ async fn main() -> Result<()> {
                 .as_ref()
                 .and_then(|v| v.remotes.clone())
                 .unwrap_or(remote);
+            let rerank = input.as_ref().and_then(|v| v.rerank).unwrap_or(rerank);
+            let expand = input
+                .as_ref()
+                .and_then(|v| v.expand.clone())
+                .unwrap_or(expand);
+            let context = input.as_ref().and_then(|v| v.context).unwrap_or(context);
+            let group_by = input.as_ref().and_then(|v| v.group_by).or(group_by);
+            if let Some(name) = save {
+                let db = Database::open_config(&config).await?;
+                let search = SavedSearch {
+                    name,
+                    query: query.clone(),
+                    filters: SavedFilters {
+                        source: source.clone(),
+                        workspace: workspace.clone(),
+                        mode: match mode {
+                            SearchModeArg::Auto => None,
+                            mode => clap::ValueEnum::to_possible_value(&mode)
+                                .map(|value| value.get_name().to_string()),
+                        },
+                        roles: role.iter().map(ToString::to_string).collect(),
+                        after: after.clone(),
+                        before: before.clone(),
+                        model: model.clone(),
+                        harness: harness_filter.clone(),
+                        tag: tag.clone(),
+                        no_tools,
+                        starred,
+                    },
+                    created_at: chrono::Utc::now(),
+                };
+                db.save_search(&search).await?;
+                if !cli.json {
+                    eprintln!("Saved search '{}'", search.name);
+                }
+            }
             cmd_search_fast(
                 &config,
                 &query,
@@ -987,48 +2252,120 @@ async fn main() -> Result<()> {
                 model,
                 harness_filter,
                 tag,
+                starred,
                 compact,
+                include_archived,
+                rerank,
+                &expand,
+                context,
+                group_by,
                 cli.json,
             )
             .await
         }
         Command::Sync {
             source,
+            group,
             parallel,
+            stream,
             input,
+            nice,
+            full,
         } => {
-            let db = Database::open(&config.database).await?;
+            let db = Database::open_config(&config).await?;
             apply_storage_config(&db, &config);
-            let runtime = Runtime::parse(&config.js_runtime).ok_or_else(|| {
-                anyhow::anyhow!("No JavaScript runtime found. Install bun, deno, or node.")
-            })?;
-            let runner = AdapterRunner::new(runtime, config.adapter_paths.clone());
+            apply_nice_mode(&db, &config, nice);
+            let runner = adapter_runner(&config)?;
             let input = read_input::<SyncInput>(input)?;
             let source = input.as_ref().and_then(|v| v.source.clone()).or(source);
+            let group = input.as_ref().and_then(|v| v.group.clone()).or(group);
             let parallel = input.and_then(|v| v.parallel).or(parallel);
-            cmd_sync(&db, &runner, &config, source, parallel, cli.json).await
+            cmd_sync(
+                &db, &runner, &config, source, group, parallel, full, stream, cli.json,
+            )
+            .await
         }
         Command::Import {
             path,
             adapter,
             source_id,
             dry_run,
+            nice,
         } => {
-            let db = Database::open(&config.database).await?;
+            let db = Database::open_config(&config).await?;
             apply_storage_config(&db, &config);
-            let runtime = Runtime::parse(&config.js_runtime).ok_or_else(|| {
-                anyhow::anyhow!("No JavaScript runtime found. Install bun, deno, or node.")
-            })?;
-            let runner = AdapterRunner::new(runtime, config.adapter_paths.clone());
+            apply_nice_mode(&db, &config, nice);
+            // Piped exports are spooled to a temp file that lives until the
+            // import is done.
+            let stdin = if path.as_os_str() == "-" {
+                Some(spool_stdin()?)
+            } else {
+                None
+            };
+            let path = stdin.as_ref().map_or(path, |spooled| spooled.to_path_buf());
+            // The official ChatGPT export zip is read natively, so it needs
+            // neither a JS runtime nor an unpacked directory.
+            let expanded = Config::expand_path(&path.to_string_lossy());
+            if adapter.is_none() && hstry_archive::is_archive(&expanded) {
+                if source_id.is_some() {
+                    anyhow::bail!(
+                        "--source-id does not apply to {} imports, which keep their sources",
+                        hstry_archive::FORMAT
+                    );
+                }
+                return cmd_import_archive(&db, &expanded, dry_run, cli.json).await;
+            }
+            if adapter.as_deref().is_none_or(|name| name == "chatgpt")
+                && chatgpt_zip::is_chatgpt_export(&expanded)
+            {
+                return cmd_import_chatgpt_zip(&db, &expanded, source_id, dry_run, cli.json).await;
+            }
+            let runner = adapter_runner(&config)?;
             cmd_import(
-                &db, &runner, &config, path, adapter, source_id, dry_run, cli.json,
+                &db,
+                &runner,
+                &config,
+                path,
+                stdin.is_some(),
+                adapter,
+                source_id,
+                dry_run,
+                cli.json,
             )
             .await
         }
-        Command::Index { rebuild } => {
-            let db = Database::open(&config.database).await?;
+        Command::Ask {
+            question,
+            source,
+            workspace,
+            after,
+            before,
+            passages,
+        } => {
+            let db = Database::open_config(&config).await?;
+            apply_storage_config(&db, &config);
+            let opts = hstry_core::db::SearchOptions {
+                source_id: source,
+                workspace: workspace.map(|value| format!("%{value}%")),
+                after: after.as_deref().map(parse_date_filter).transpose()?,
+                before: before.as_deref().map(parse_date_filter).transpose()?,
+                ..Default::default()
+            };
+            cmd_ask(&db, &config, &question, &opts, passages, cli.json).await
+        }
+        Command::Index {
+            command,
+            rebuild,
+            nice,
+        } => {
+            let db = Database::open_config(&config).await?;
             apply_storage_config(&db, &config);
-            cmd_index(&config, &db, rebuild, cli.json).await
+            apply_nice_mode(&db, &config, nice);
+            match command {
+                Some(IndexCommand::Status) => cmd_index_status(&db, cli.json).await,
+                Some(IndexCommand::Optimize) => cmd_index_optimize(&db, cli.json).await,
+                None => cmd_index(&config, &db, rebuild, cli.json).await,
+            }
         }
         Command::List {
             source,
@@ -1040,8 +2377,12 @@ async fn main() -> Result<()> {
             peek,
             peek_chars,
             all,
+            include_archived,
+            new,
+            starred,
+            mark_read,
         } => {
-            let db = Database::open(&config.database).await?;
+            let db = Database::open_config(&config).await?;
             apply_storage_config(&db, &config);
             let input = read_input::<ListInput>(input)?;
             let source = input.as_ref().and_then(|v| v.source.clone()).or(source);
@@ -1054,50 +2395,270 @@ async fn main() -> Result<()> {
             let before = input.as_ref().and_then(|v| v.before.clone()).or(before);
             let after_dt = after.as_deref().map(parse_date_filter).transpose()?;
             let before_dt = before.as_deref().map(parse_date_filter).transpose()?;
-            if peek {
+            let profile = config.active_profile();
+            let last_seen = db.last_seen_or_init(&profile).await?;
+            let result = if peek {
                 cmd_list_peek(
-                    &db, source, workspace, limit, after_dt, before_dt, peek_chars,
+                    &db, source, workspace, limit, after_dt, before_dt, starred, peek_chars,
                 )
                 .await
             } else {
+                let archive = if include_archived && config.archive_path().exists() {
+                    Some(Database::open_archive(&config).await?)
+                } else {
+                    None
+                };
                 cmd_list(
-                    &db, source, workspace, limit, after_dt, before_dt, all, cli.json,
+                    &db,
+                    archive.as_ref(),
+                    source,
+                    workspace,
+                    limit,
+                    after_dt,
+                    before_dt,
+                    last_seen,
+                    new,
+                    starred,
+                    all,
+                    &config.badges,
+                    cli.json,
                 )
                 .await
+            };
+            if result.is_ok() && mark_read {
+                db.mark_all_read(&profile).await?;
             }
+            result
         }
-        Command::Show { id, input } => {
-            let db = Database::open(&config.database).await?;
+        Command::Show {
+            id,
+            page,
+            page_size,
+            branches,
+            history,
+            input,
+        } => {
+            let db = Database::open_config(&config).await?;
             apply_storage_config(&db, &config);
             let input = read_input::<ShowInput>(input)?;
-            let id = input.as_ref().map_or(id, |v| v.id.clone());
-            cmd_show(&db, &id, cli.json).await
+            let (id, page, page_size, branches, history) = match input {
+                Some(v) => (
+                    Some(v.id),
+                    v.page.or(page),
+                    v.page_size.unwrap_or(page_size),
+                    v.branches.unwrap_or(branches),
+                    v.history.unwrap_or(history),
+                ),
+                None => (id, page, page_size, branches, history),
+            };
+            let id = match id {
+                Some(id) => id,
+                None => {
+                    let options = hstry_core::db::ListConversationsOptions {
+                        limit: Some(PICK_LIMIT),
+                        ..Default::default()
+                    };
+                    match fzf_pick_conversation(&db, options, "Show conversation> ").await? {
+                        Some(id) => id.to_string(),
+                        None => return Ok(()),
+                    }
+                }
+            };
+            cmd_show(&db, &id, page, page_size, branches, history, cli.json).await
+        }
+        Command::Pick {
+            source,
+            workspace,
+            after,
+            before,
+            limit,
+            format,
+            output,
+            id_only,
+        } => {
+            let db = Database::open_config(&config).await?;
+            apply_storage_config(&db, &config);
+            let options = hstry_core::db::ListConversationsOptions {
+                source_id: source,
+                workspace: workspace.map(|v| format!("%{v}%")),
+                after: after.as_deref().map(parse_date_filter).transpose()?,
+                before: before.as_deref().map(parse_date_filter).transpose()?,
+                limit: Some(limit),
+                ..Default::default()
+            };
+            let prompt = if format.is_some() {
+                "Export conversation> "
+            } else {
+                "Show conversation> "
+            };
+            let Some(id) = fzf_pick_conversation(&db, options, prompt).await? else {
+                return Ok(());
+            };
+            if id_only {
+                if cli.json {
+                    return emit_json(JsonResponse {
+                        ok: true,
+                        result: Some(serde_json::json!({ "id": id })),
+                        error: None,
+                    });
+                }
+                println!("{id}");
+                return Ok(());
+            }
+            match format {
+                Some(format) => {
+                    let runner = adapter_runner(&config)?;
+                    cmd_export(
+                        &db,
+                        &runner,
+                        &format,
+                        &id.to_string(),
+                        None,
+                        None,
+                        Vec::new(),
+                        None,
+                        false,
+                        output,
+                        false,
+                        false,
+                        0.0,
+                        None,
+                        cli.json,
+                    )
+                    .await
+                }
+                None => cmd_show(&db, &id.to_string(), None, 50, false, false, cli.json).await,
+            }
         }
         Command::Peek { id, chars } => {
-            let db = Database::open(&config.database).await?;
+            let db = Database::open_config(&config).await?;
             apply_storage_config(&db, &config);
             cmd_peek(&db, &id, chars, cli.json).await
         }
-        Command::Remove { id, yes, dry_run } => {
-            let db = Database::open(&config.database).await?;
+        Command::Pack {
+            workspace,
+            output,
+            top,
+            force,
+        } => {
+            let db = Database::open_config(&config).await?;
+            apply_storage_config(&db, &config);
+            cmd_pack(&db, workspace, output, top, force, cli.json).await
+        }
+        Command::Decisions { workspace, limit } => {
+            let db = Database::open_config(&config).await?;
+            apply_storage_config(&db, &config);
+            cmd_decisions(&db, workspace, limit, cli.json).await
+        }
+        Command::Star { id, remove } => {
+            let db = Database::open_config(&config).await?;
+            apply_storage_config(&db, &config);
+            cmd_star(&db, &id, !remove, cli.json).await
+        }
+        Command::Remove {
+            id,
+            yes,
+            dry_run,
+            permanent,
+        } => {
+            let db = Database::open_config(&config).await?;
+            apply_storage_config(&db, &config);
+            cmd_remove(&db, &id, yes, dry_run, permanent, cli.json).await
+        }
+        Command::Trash { command } => {
+            let db = Database::open_config(&config).await?;
+            apply_storage_config(&db, &config);
+            match command {
+                TrashCommand::List { limit } => cmd_trash_list(&db, limit, cli.json).await,
+                TrashCommand::Restore { ids, all } => {
+                    cmd_trash_restore(&db, &ids, all, cli.json).await
+                }
+                TrashCommand::Empty { older_than, yes } => {
+                    cmd_trash_empty(&db, older_than, yes, cli.json).await
+                }
+            }
+        }
+        Command::Snapshot { command } => {
+            let db = Database::open_config(&config).await?;
+            apply_storage_config(&db, &config);
+            match command {
+                SnapshotCommand::Create { id } => cmd_snapshot_create(&db, &id, cli.json).await,
+                SnapshotCommand::List { id, limit } => {
+                    cmd_snapshot_list(&db, id.as_deref(), limit, cli.json).await
+                }
+                SnapshotCommand::Restore { snapshot } => {
+                    cmd_snapshot_restore(&db, &snapshot, cli.json).await
+                }
+            }
+        }
+        Command::Archive {
+            ids,
+            source,
+            workspace,
+            before,
+            tag,
+            yes,
+            dry_run,
+        } => {
+            let selection = TransferSelection {
+                ids,
+                source,
+                workspace,
+                before: before.as_deref().map(parse_date_filter).transpose()?,
+                tag,
+            };
+            cmd_archive(&config, &selection, false, yes, dry_run, cli.json).await
+        }
+        Command::Unarchive {
+            ids,
+            source,
+            workspace,
+            before,
+            tag,
+            yes,
+            dry_run,
+        } => {
+            let selection = TransferSelection {
+                ids,
+                source,
+                workspace,
+                before: before.as_deref().map(parse_date_filter).transpose()?,
+                tag,
+            };
+            cmd_archive(&config, &selection, true, yes, dry_run, cli.json).await
+        }
+        Command::Prune {
+            max_age_days,
+            max_per_source,
+            exclude_tags,
+            include_starred,
+            yes,
+            dry_run,
+        } => {
+            let db = Database::open_config(&config).await?;
             apply_storage_config(&db, &config);
-            cmd_remove(&db, &id, yes, dry_run, cli.json).await
+            let mut policy = config.retention.clone();
+            if let Some(days) = max_age_days {
+                policy.max_age_days = days;
+            }
+            if let Some(max) = max_per_source {
+                policy.max_conversations_per_source = max;
+            }
+            if !exclude_tags.is_empty() {
+                policy.exclude_tags = exclude_tags;
+            }
+            policy.include_starred |= include_starred;
+            cmd_prune(&db, &config, &policy, yes, dry_run, cli.json).await
         }
         Command::Source { command } => {
-            let db = Database::open(&config.database).await?;
+            let db = Database::open_config(&config).await?;
             apply_storage_config(&db, &config);
-            let runtime = Runtime::parse(&config.js_runtime).ok_or_else(|| {
-                anyhow::anyhow!("No JavaScript runtime found. Install bun, deno, or node.")
-            })?;
-            let runner = AdapterRunner::new(runtime, config.adapter_paths.clone());
-            cmd_source(&db, &runner, command, cli.json).await
+            let runner = adapter_runner(&config)?;
+            cmd_source(&db, &runner, &config, &config_path, command, cli.json).await
         }
         Command::Adapters { command } => {
-            let runtime = Runtime::parse(&config.js_runtime).ok_or_else(|| {
-                anyhow::anyhow!("No JavaScript runtime found. Install bun, deno, or node.")
-            })?;
-            let runner = AdapterRunner::new(runtime, config.adapter_paths.clone());
-            cmd_adapters(&runner, &config, &config_path, command, cli.json)
+            let runner = adapter_runner(&config)?;
+            cmd_adapters(&runner, &config, &config_path, command, cli.json).await
         }
         Command::Service { command } => match command {
             ServiceCommand::Status => {
@@ -1128,19 +2689,13 @@ async fn main() -> Result<()> {
             }
         },
         Command::Scan => {
-            let runtime = Runtime::parse(&config.js_runtime).ok_or_else(|| {
-                anyhow::anyhow!("No JavaScript runtime found. Install bun, deno, or node.")
-            })?;
-            let runner = AdapterRunner::new(runtime, config.adapter_paths.clone());
+            let runner = adapter_runner(&config)?;
             cmd_scan(&runner, &config, cli.json).await
         }
         Command::Quickstart => {
-            let db = Database::open(&config.database).await?;
+            let db = Database::open_config(&config).await?;
             apply_storage_config(&db, &config);
-            let runtime = Runtime::parse(&config.js_runtime).ok_or_else(|| {
-                anyhow::anyhow!("No JavaScript runtime found. Install bun, deno, or node.")
-            })?;
-            let runner = AdapterRunner::new(runtime, config.adapter_paths.clone());
+            let runner = adapter_runner(&config)?;
             cmd_quickstart(&db, &runner, &config, &config_path, cli.json).await
         }
         Command::Export {
@@ -1149,16 +2704,37 @@ async fn main() -> Result<()> {
             source,
             workspace,
             role,
+            since,
+            incremental,
             output,
             session_files,
             pretty,
+            min_similarity,
+            vault,
+            day,
+            share_safe,
         } => {
-            let db = Database::open(&config.database).await?;
+            let db = Database::open_config(&config).await?;
             apply_storage_config(&db, &config);
-            let runtime = Runtime::parse(&config.js_runtime).ok_or_else(|| {
-                anyhow::anyhow!("No JavaScript runtime found. Install bun, deno, or node.")
-            })?;
-            let runner = AdapterRunner::new(runtime, config.adapter_paths.clone());
+            let redactor = share_safe
+                .then(|| share::Redactor::new(&config.redact))
+                .transpose()?;
+            if format == "daily-notes" {
+                if incremental {
+                    anyhow::bail!("--incremental does not apply to daily-notes exports");
+                }
+                return cmd_export_daily_notes(
+                    &db,
+                    &config.daily_notes,
+                    vault,
+                    parse_note_day(&day)?,
+                    source,
+                    workspace,
+                    cli.json,
+                )
+                .await;
+            }
+            let runner = adapter_runner(&config)?;
             cmd_export(
                 &db,
                 &runner,
@@ -1167,9 +2743,13 @@ async fn main() -> Result<()> {
                 source,
                 workspace,
                 role,
+                since.as_deref().map(parse_date_filter).transpose()?,
+                incremental,
                 output,
                 session_files,
                 pretty,
+                min_similarity,
+                redactor.as_ref(),
                 cli.json,
             )
             .await
@@ -1186,44 +2766,159 @@ async fn main() -> Result<()> {
             dry_run,
             pick,
         } => {
-            let db = Database::open(&config.database).await?;
+            let db = Database::open_config(&config).await?;
             apply_storage_config(&db, &config);
-            let runtime = Runtime::parse(&config.js_runtime).ok_or_else(|| {
-                anyhow::anyhow!("No JavaScript runtime found. Install bun, deno, or node.")
-            })?;
-            let runner = AdapterRunner::new(runtime, config.adapter_paths.clone());
+            let runner = adapter_runner(&config)?;
             cmd_resume(
                 &db, &runner, &config, id, search, agent, source, workspace, after, before, limit,
                 dry_run, pick, cli.json,
             )
             .await
         }
-        Command::Stats => {
-            let db = Database::open(&config.database).await?;
+        Command::Stats { detailed } => {
+            let db = Database::open_config(&config).await?;
+            apply_storage_config(&db, &config);
+            cmd_stats(&db, detailed, cli.json).await
+        }
+        Command::Cost { command } => {
+            let db = Database::open_config(&config).await?;
             apply_storage_config(&db, &config);
-            cmd_stats(&db, cli.json).await
+            match command {
+                CostCommand::Report { by } => {
+                    cmd_cost_report(&db, &config.pricing, by, cli.json).await
+                }
+            }
+        }
+        Command::Db { command } => {
+            let db = Database::open_config(&config).await?;
+            apply_storage_config(&db, &config);
+            match command {
+                DbCommand::Size => cmd_db_size(&db, &config, cli.json).await,
+                DbCommand::Vacuum => cmd_db_vacuum(&db, &config, cli.json).await,
+                DbCommand::Merge {
+                    path,
+                    namespace,
+                    strategy,
+                } => cmd_db_merge(&db, &config, &path, namespace, strategy.into(), cli.json).await,
+            }
+        }
+        Command::Tokens { command } => {
+            let db = Database::open_config(&config).await?;
+            apply_storage_config(&db, &config);
+            match command {
+                TokensCommand::Backfill {
+                    source,
+                    tokenizer,
+                    reestimate,
+                    dry_run,
+                } => {
+                    cmd_tokens_backfill(
+                        &db,
+                        source.as_deref(),
+                        tokenizer.as_deref(),
+                        reestimate,
+                        dry_run,
+                        cli.json,
+                    )
+                    .await
+                }
+            }
+        }
+        Command::Tools { command } => {
+            let db = Database::open_config(&config).await?;
+            apply_storage_config(&db, &config);
+            cmd_tools(&db, command, cli.json).await
+        }
+        Command::Dedup {
+            dry_run,
+            source,
+            include_starred,
+            strategy,
+            threshold,
+            interactive,
+        } => {
+            let db = Database::open_config(&config).await?;
+            apply_storage_config(&db, &config);
+            cmd_dedup(
+                &db,
+                dry_run,
+                source,
+                include_starred,
+                strategy,
+                threshold,
+                interactive,
+                cli.json,
+            )
+            .await
         }
-        Command::Dedup { dry_run, source } => {
-            let db = Database::open(&config.database).await?;
+        Command::Link { command } => {
+            let db = Database::open_config(&config).await?;
             apply_storage_config(&db, &config);
-            cmd_dedup(&db, dry_run, source, cli.json).await
+            match command {
+                LinkCommand::Detect { threshold, dry_run } => {
+                    cmd_link_detect(&db, threshold, dry_run, cli.json).await
+                }
+            }
         }
         Command::Mmry { command } => {
-            let db = Database::open(&config.database).await?;
+            let db = Database::open_config(&config).await?;
             apply_storage_config(&db, &config);
             cmd_mmry(&db, command, cli.json).await
         }
         Command::Remote { command } => {
-            let db = Database::open(&config.database).await?;
+            let db = Database::open_config(&config).await?;
             apply_storage_config(&db, &config);
             cmd_remote(&db, &config, &config_path, command, cli.json).await
         }
         Command::Web { command } => {
-            let db = Database::open(&config.database).await?;
+            let db = Database::open_config(&config).await?;
             apply_storage_config(&db, &config);
             cmd_web(&db, &config, &config_path, command, cli.json).await
         }
-        Command::Config { command } => cmd_config(&config, &config_path, command, cli.json),
+        Command::Api { command } => {
+            let db = Database::open_config(&config).await?;
+            apply_storage_config(&db, &config);
+            cmd_api(&db, command, cli.json).await
+        }
+        Command::Sensitive { command } => {
+            let db = Database::open_config(&config).await?;
+            cmd_sensitive(&config, &db, command, cli.json).await
+        }
+        Command::Access { command } => {
+            let db = Database::open_config(&config).await?;
+            cmd_access(&db, command, cli.json).await
+        }
+        Command::Searches { command } => {
+            let db = Database::open_config(&config).await?;
+            cmd_searches(&db, command, cli.json).await
+        }
+        Command::Workspace { command } => {
+            let db = Database::open_config(&config).await?;
+            cmd_workspace(&db, command, cli.json).await
+        }
+        Command::Title { command } => {
+            let db = Database::open_config(&config).await?;
+            apply_storage_config(&db, &config);
+            match command {
+                TitleCommand::Generate {
+                    source,
+                    workspace,
+                    limit,
+                    llm,
+                    force,
+                    dry_run,
+                } => {
+                    let opts = hstry_core::db::ListConversationsOptions {
+                        source_id: source,
+                        workspace: workspace.map(|value| format!("%{value}%")),
+                        ..Default::default()
+                    };
+                    cmd_title_generate(&db, &config, opts, limit, llm, force, dry_run, cli.json)
+                        .await
+                }
+            }
+        }
+        Command::Config { command } => cmd_config(&config, &config_path, command, cli.json).await,
         Command::Reseed {
             source,
             dedup,
@@ -1232,12 +2927,10 @@ async fn main() -> Result<()> {
             dry_run,
             drop_source,
         } => {
-            let db = Database::open(&config.database).await?;
+            let db = Database::open_config(&config).await?;
             apply_storage_config(&db, &config);
-            let runtime = Runtime::parse(&config.js_runtime).ok_or_else(|| {
-                anyhow::anyhow!("No JavaScript runtime found. Install bun, deno, or node.")
-            })?;
-            let runner = AdapterRunner::new(runtime, config.adapter_paths.clone());
+            let runner = adapter_runner(&config)?;
+            let cancel = CancelToken::install_ctrl_c();
             cmd_reseed(
                 &db,
                 &runner,
@@ -1247,18 +2940,22 @@ async fn main() -> Result<()> {
                 !no_bulk,
                 dry_run,
                 drop_source,
+                &cancel,
                 cli.json,
             )
             .await
         }
         Command::Verify { source, repair } => {
-            let db = Database::open(&config.database).await?;
+            let db = Database::open_config(&config).await?;
             apply_storage_config(&db, &config);
-            let runtime = Runtime::parse(&config.js_runtime).ok_or_else(|| {
-                anyhow::anyhow!("No JavaScript runtime found. Install bun, deno, or node.")
-            })?;
-            let runner = AdapterRunner::new(runtime, config.adapter_paths.clone());
-            cmd_verify(&db, &runner, source, repair, cli.json).await
+            let runner = adapter_runner(&config)?;
+            let cancel = CancelToken::install_ctrl_c();
+            cmd_verify(&db, &runner, source, repair, &cancel, cli.json).await
+        }
+        Command::Repair { command } => {
+            let db = Database::open_config(&config).await?;
+            apply_storage_config(&db, &config);
+            cmd_repair(&db, command, cli.json).await
         }
     }
 }
@@ -1342,32 +3039,208 @@ async fn ensure_config_sources(
             )
             .map_err(|e| anyhow::anyhow!("config source '{id}' rejected: {e}", id = source.id))?,
         };
+        // Options in the config file are authoritative for config sources.
+        let mut entry = entry;
+        replace_source_options(&mut entry, &source.options);
+        replace_source_filter(&mut entry, &source.filter)?;
+        set_source_adapter_version(&mut entry, source.adapter_version.as_deref());
         db.upsert_source(&entry).await?;
     }
     Ok(())
 }
 
-fn default_sync_parallelism() -> usize {
-    std::thread::available_parallelism()
-        .map(|value| value.get().min(4))
-        .unwrap_or(4)
+/// Store `options` under the `options` key of a source's stored config,
+/// removing the key when there are none.
+fn replace_source_options(source: &mut Source, options: &BTreeMap<String, serde_json::Value>) {
+    let mut config = match source.config.take() {
+        serde_json::Value::Object(config) => config,
+        _ => serde_json::Map::new(),
+    };
+    if options.is_empty() {
+        config.remove("options");
+    } else {
+        config.insert(
+            "options".to_string(),
+            serde_json::Value::Object(options.clone().into_iter().collect()),
+        );
+    }
+    source.config = serde_json::Value::Object(config);
 }
 
-/// Per-source result captured during a concurrent sync so the final summary can
-/// be rendered in a stable, grouped order instead of interleaved line-by-line.
-enum SyncOutcome {
-    Synced {
-        id: String,
-        adapter: String,
-        conversations: usize,
-        messages: usize,
-    },
-    UpToDate,
-    Failed {
-        id: String,
+/// Store `filter` under the `filter` key of a source's stored config,
+/// removing the key when it keeps everything.
+fn replace_source_filter(
+    source: &mut Source,
+    filter: &hstry_core::config::SourceFilterConfig,
+) -> Result<()> {
+    hstry_core::source_filter::SourceFilter::new(filter)
+        .map_err(|e| anyhow::anyhow!("Invalid filter for source '{}': {e}", source.id))?;
+    let mut config = match source.config.take() {
+        serde_json::Value::Object(config) => config,
+        _ => serde_json::Map::new(),
+    };
+    if filter.is_empty() {
+        config.remove(hstry_core::source_filter::FILTER_KEY);
+    } else {
+        config.insert(
+            hstry_core::source_filter::FILTER_KEY.to_string(),
+            serde_json::to_value(filter)?,
+        );
+    }
+    source.config = serde_json::Value::Object(config);
+    Ok(())
+}
+
+/// Resolve the version to pin `adapter` to, snapshotting the installed
+/// adapter when that is the version asked for.
+fn pin_adapter_version(
+    runner: &AdapterRunner,
+    config: &Config,
+    adapter: &str,
+    version: Option<&str>,
+) -> Result<String> {
+    if let Some(version) = version
+        && runner.find_adapter_version(adapter, version).is_some()
+    {
+        return Ok(version.to_string());
+    }
+
+    let installed = runner
+        .find_adapter(adapter)
+        .ok_or_else(|| anyhow::anyhow!("Adapter '{adapter}' not found"))?;
+    let root = installed
+        .parent()
+        .and_then(Path::parent)
+        .ok_or_else(|| anyhow::anyhow!("Adapter '{adapter}' has no adapter root"))?;
+    let current = hstry_runtime::runner::adapter_version(&installed);
+    if version.is_none() || version == current.as_deref() {
+        return adapter_manifest::snapshot_installed(root, adapter, None);
+    }
+
+    let available = config
+        .adapter_paths
+        .iter()
+        .filter_map(|path| adapter_manifest::read_lock(path).ok())
+        .filter_map(|lock| lock.adapters.get(adapter).cloned())
+        .flat_map(|entry| entry.versions)
+        .collect::<Vec<_>>();
+    anyhow::bail!(
+        "Adapter '{adapter}' {version} is not installed (available: {available})",
+        version = version.unwrap_or_default(),
+        available = if available.is_empty() {
+            current.unwrap_or_else(|| "none".to_string())
+        } else {
+            available.join(", ")
+        }
+    );
+}
+
+/// Pin a source to an adapter version (`None` unpins).
+fn set_source_adapter_version(source: &mut Source, version: Option<&str>) {
+    let mut config = match source.config.take() {
+        serde_json::Value::Object(config) => config,
+        _ => serde_json::Map::new(),
+    };
+    match version {
+        Some(version) => {
+            config.insert("adapter_version".to_string(), version.into());
+        }
+        None => {
+            config.remove("adapter_version");
+        }
+    }
+    source.config = serde_json::Value::Object(config);
+}
+
+/// Parse `key=value` for `source set-option`. Values that are valid JSON
+/// (`true`, `3`, `["a"]`) keep their type; anything else is a string. An
+/// empty value (`key=`) means "remove".
+fn parse_source_option(raw: &str) -> Result<(String, Option<serde_json::Value>)> {
+    let (key, value) = raw
+        .split_once('=')
+        .ok_or_else(|| anyhow::anyhow!("Expected key=value, got '{raw}'"))?;
+    let key = key.trim();
+    if key.is_empty() {
+        anyhow::bail!("Option key cannot be empty in '{raw}'");
+    }
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok((key.to_string(), None));
+    }
+    let value = serde_json::from_str(value)
+        .unwrap_or_else(|_| serde_json::Value::String(value.to_string()));
+    Ok((key.to_string(), Some(value)))
+}
+
+fn default_sync_parallelism() -> usize {
+    std::thread::available_parallelism()
+        .map(|value| value.get().min(4))
+        .unwrap_or(4)
+}
+
+/// Per-source result captured during a concurrent sync so the final summary can
+/// be rendered in a stable, grouped order instead of interleaved line-by-line.
+enum SyncOutcome {
+    Synced {
+        id: String,
+        adapter: String,
+        conversations: usize,
+        messages: usize,
+        /// Conversations the source's filter left out.
+        filtered: usize,
+    },
+    UpToDate,
+    Failed {
+        id: String,
         adapter: String,
         message: String,
     },
+    /// Interrupted by Ctrl-C; committed batches and the cursor were kept.
+    Cancelled {
+        id: String,
+        adapter: String,
+    },
+}
+
+/// How `sync_sources` reports while it runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SyncOutput {
+    /// Nothing; the caller reports the returned stats.
+    Quiet,
+    /// Progress bars per source, then the grouped summary.
+    Human,
+    /// One JSON [`SyncEvent`] per line on stdout.
+    Events,
+}
+
+impl SyncOutput {
+    fn human_unless(json: bool) -> Self {
+        if json { Self::Quiet } else { Self::Human }
+    }
+}
+
+/// Progress event printed by `hstry sync --stream`.
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum SyncEvent<'a> {
+    Progress {
+        source_id: &'a str,
+        #[serde(flatten)]
+        progress: sync::SyncProgress,
+    },
+    Failed {
+        source_id: &'a str,
+        message: &'a str,
+    },
+    Cancelled {
+        source_id: &'a str,
+    },
+}
+
+fn emit_json_line<T: serde::Serialize>(value: T) -> Result<()> {
+    let line = serde_json::to_string(&value)?;
+    println!("{line}");
+    Ok(())
 }
 
 async fn sync_sources(
@@ -1375,10 +3248,21 @@ async fn sync_sources(
     runner: &AdapterRunner,
     config: &Config,
     source_filter: Option<String>,
+    group_filter: Option<&str>,
     parallel: Option<usize>,
-    print: bool,
+    full: bool,
+    output: SyncOutput,
+    cancel: Option<&CancelToken>,
 ) -> Result<Vec<sync::SyncStats>> {
+    let print = output == SyncOutput::Human;
     adapter_manifest::validate_adapter_manifest(&config.adapter_paths)?;
+    let group = group_filter
+        .map(|name| {
+            config.groups.get(name).ok_or_else(|| {
+                anyhow::anyhow!("Unknown source group '{name}' (see [groups] in config)")
+            })
+        })
+        .transpose()?;
 
     // Ensure sources from config are in the database
     ensure_config_sources(db, runner, config).await?;
@@ -1387,7 +3271,7 @@ async fn sync_sources(
 
     if sources.is_empty() {
         if print {
-            println!("No sources configured. Use 'hstry source add <path>' to add a source.");
+            println!("{}", tr!("cli-no-sources"));
         }
         return Ok(Vec::new());
     }
@@ -1400,12 +3284,23 @@ async fn sync_sources(
         {
             continue;
         }
+        if let Some(group) = group
+            && !group.sources.contains(&source.id)
+        {
+            continue;
+        }
 
         if !config.adapter_enabled(&source.adapter) {
             disabled += 1;
             continue;
         }
 
+        // Path-less sources are fed by push (API ingest) or one-off archive
+        // imports; there is nothing on disk to re-read.
+        if source.path.is_none() {
+            continue;
+        }
+
         sources_to_sync.push(source);
     }
 
@@ -1419,6 +3314,16 @@ async fn sync_sources(
         return Ok(Vec::new());
     }
 
+    // Higher-priority groups start first; the sort is stable, so sources
+    // keep their order within a priority.
+    sources_to_sync.sort_by_key(|source| {
+        std::cmp::Reverse(
+            config
+                .source_group(&source.id)
+                .map_or(0, |(_, group)| group.priority),
+        )
+    });
+
     let total = sources_to_sync.len();
     let parallelism = parallel.unwrap_or_else(default_sync_parallelism).max(1);
     let parallelism = parallelism.min(total.max(1));
@@ -1426,31 +3331,96 @@ async fn sync_sources(
     let outcomes = Arc::new(tokio::sync::Mutex::new(Vec::new()));
     let done = Arc::new(std::sync::atomic::AtomicUsize::new(0));
 
-    let pb = if print {
-        let bar = indicatif::ProgressBar::new_spinner();
+    // One overall line plus a spinner per running source, showing how many
+    // conversations the adapter has produced and how many were written.
+    let bars = if print {
+        let multi = indicatif::MultiProgress::new();
+        let bar = multi.add(indicatif::ProgressBar::new_spinner());
         bar.set_style(
             indicatif::ProgressStyle::with_template("{spinner:.cyan} {msg}")
                 .unwrap_or_else(|_| indicatif::ProgressStyle::default_spinner()),
         );
         bar.enable_steady_tick(std::time::Duration::from_millis(120));
         bar.set_message(format!("Syncing 0/{total} sources..."));
-        Some(bar)
+        Some((multi, bar))
     } else {
         None
     };
+    let source_style =
+        indicatif::ProgressStyle::with_template("  {spinner:.dim} {prefix:.bold} {msg:.dim}")
+            .unwrap_or_else(|_| indicatif::ProgressStyle::default_spinner());
 
     stream::iter(sources_to_sync)
         .for_each_concurrent(parallelism, |mut source| {
             let stats = Arc::clone(&stats);
             let outcomes = Arc::clone(&outcomes);
             let done = Arc::clone(&done);
-            let pb = pb.clone();
+            let bars = bars.clone();
+            let source_style = source_style.clone();
             async move {
-                let outcome = sync_one_source(db, runner, &mut source, &stats).await;
+                let source_bar = bars.as_ref().map(|(multi, _)| {
+                    let bar = multi.add(indicatif::ProgressBar::new_spinner());
+                    bar.set_style(source_style);
+                    bar.set_prefix(source.id.clone());
+                    bar.enable_steady_tick(std::time::Duration::from_millis(120));
+                    bar
+                });
+                let source_id = source.id.clone();
+                let report: Option<Box<dyn Fn(sync::SyncProgress) + Send + Sync>> = match output {
+                    SyncOutput::Quiet => None,
+                    SyncOutput::Human => source_bar.clone().map(|bar| {
+                        Box::new(move |progress: sync::SyncProgress| {
+                            bar.set_message(format!(
+                                "{} parsed, {} written",
+                                progress.parsed, progress.conversations
+                            ));
+                        }) as Box<dyn Fn(sync::SyncProgress) + Send + Sync>
+                    }),
+                    // Per-conversation updates would flood the stream; their
+                    // count rides along on the next `written` event.
+                    SyncOutput::Events => Some(Box::new(move |progress: sync::SyncProgress| {
+                        if progress.phase != sync::SyncPhase::Parsed {
+                            let _ = emit_json_line(SyncEvent::Progress {
+                                source_id: &source_id,
+                                progress,
+                            });
+                        }
+                    })),
+                };
+
+                let outcome = sync_one_source(
+                    db,
+                    runner,
+                    &mut source,
+                    full,
+                    &stats,
+                    report.as_deref(),
+                    cancel,
+                )
+                .await;
+
+                if output == SyncOutput::Events {
+                    let event = match &outcome {
+                        SyncOutcome::Failed { id, message, .. } => Some(SyncEvent::Failed {
+                            source_id: id,
+                            message,
+                        }),
+                        SyncOutcome::Cancelled { id, .. } => {
+                            Some(SyncEvent::Cancelled { source_id: id })
+                        }
+                        _ => None,
+                    };
+                    if let Some(event) = event {
+                        let _ = emit_json_line(event);
+                    }
+                }
+                if let Some(bar) = source_bar {
+                    bar.finish_and_clear();
+                }
 
                 let mut outcomes = outcomes.lock().await;
                 outcomes.push(outcome);
-                if let Some(bar) = pb.as_ref() {
+                if let Some((_, bar)) = bars.as_ref() {
                     let n = done.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1;
                     bar.set_message(format!("Syncing {n}/{total} sources..."));
                 }
@@ -1458,36 +3428,55 @@ async fn sync_sources(
         })
         .await;
 
-    if let Some(bar) = pb {
+    if let Some((_, bar)) = bars {
         bar.finish_and_clear();
     }
 
+    let stats = stats.lock().await.clone();
+    for source_stats in &stats {
+        sync::apply_group_tags(db, config, source_stats).await?;
+    }
+    if sync::auto_index(db, config, &stats).await? && print {
+        println!("Rebuilt the out-of-date search index.");
+    }
+
     if print {
         let outcomes = outcomes.lock().await;
         print_sync_summary(&outcomes, disabled);
     }
 
-    Ok(stats.lock().await.clone())
+    if cancel.is_some_and(CancelToken::is_cancelled) {
+        return Err(hstry_core::Error::Cancelled.into());
+    }
+
+    Ok(stats)
 }
 
 /// Sync a single source, resetting a stale cursor when the source has no
-/// surviving conversations. Pushes successful stats onto the shared accumulator
-/// and returns the outcome for the end-of-run summary.
+/// surviving conversations or `full` asks for a complete reparse. Pushes
+/// successful stats onto the shared accumulator and returns the outcome for
+/// the end-of-run summary.
 async fn sync_one_source(
     db: &Database,
     runner: &AdapterRunner,
     source: &mut Source,
+    full: bool,
     stats: &Arc<tokio::sync::Mutex<Vec<sync::SyncStats>>>,
+    progress: Option<sync::ProgressCallback<'_>>,
+    cancel: Option<&CancelToken>,
 ) -> SyncOutcome {
-    if source.last_sync_at.is_some() {
+    if cancel.is_some_and(CancelToken::is_cancelled) {
+        return SyncOutcome::Cancelled {
+            id: source.id.clone(),
+            adapter: source.adapter.clone(),
+        };
+    }
+
+    if full {
+        sync::forget_sync_state(source);
+    } else if source.last_sync_at.is_some() {
         match db.count_source_data(&source.id).await {
-            Ok((0, _)) => {
-                source.last_sync_at = None;
-                if let serde_json::Value::Object(mut config) = source.config.clone() {
-                    config.remove("cursor");
-                    source.config = serde_json::Value::Object(config);
-                }
-            }
+            Ok((0, _)) => sync::forget_sync_state(source),
             Ok(_) => {}
             Err(err) => {
                 return SyncOutcome::Failed {
@@ -1499,7 +3488,7 @@ async fn sync_one_source(
         }
     }
 
-    match sync::sync_source(db, runner, source).await {
+    match sync::sync_source_with_progress(db, runner, source, progress, cancel).await {
         Ok(result) => {
             if result.conversations > 0 {
                 let outcome = SyncOutcome::Synced {
@@ -1507,6 +3496,7 @@ async fn sync_one_source(
                     adapter: source.adapter.clone(),
                     conversations: result.conversations,
                     messages: result.messages,
+                    filtered: result.filtered,
                 };
                 stats.lock().await.push(result);
                 outcome
@@ -1515,6 +3505,17 @@ async fn sync_one_source(
                 SyncOutcome::UpToDate
             }
         }
+        Err(err)
+            if matches!(
+                err.downcast_ref::<hstry_core::Error>(),
+                Some(hstry_core::Error::Cancelled)
+            ) =>
+        {
+            SyncOutcome::Cancelled {
+                id: source.id.clone(),
+                adapter: source.adapter.clone(),
+            }
+        }
         Err(err) => SyncOutcome::Failed {
             id: source.id.clone(),
             adapter: source.adapter.clone(),
@@ -1531,6 +3532,7 @@ fn print_sync_summary(outcomes: &[SyncOutcome], disabled: usize) {
     let mut synced = 0usize;
     let mut total_conversations = 0usize;
     let mut failed = 0usize;
+    let mut cancelled = 0usize;
 
     for outcome in outcomes {
         if let SyncOutcome::Failed {
@@ -1555,21 +3557,31 @@ fn print_sync_summary(outcomes: &[SyncOutcome], disabled: usize) {
             adapter,
             conversations,
             messages,
+            filtered,
         } = outcome
         {
             synced += 1;
             total_conversations += conversations;
+            let mut detail = format!("{conversations} conversations, {messages} messages");
+            if *filtered > 0 {
+                detail.push_str(&format!(", {filtered} filtered out"));
+            }
             println!(
                 "{} {} {}",
                 console::style("✓").green().bold(),
                 console::style(format!("{id} ({adapter})")).bold(),
-                console::style(format!(
-                    "{conversations} conversations, {messages} messages"
-                ))
-                .dim()
+                console::style(detail).dim()
             );
         } else if matches!(outcome, SyncOutcome::UpToDate) {
             up_to_date += 1;
+        } else if let SyncOutcome::Cancelled { id, adapter } = outcome {
+            cancelled += 1;
+            println!(
+                "{} {} {}",
+                console::style("-").yellow().bold(),
+                console::style(format!("{id} ({adapter})")).bold(),
+                console::style("cancelled").yellow()
+            );
         }
     }
 
@@ -1580,6 +3592,9 @@ fn print_sync_summary(outcomes: &[SyncOutcome], disabled: usize) {
     if disabled > 0 {
         notes.push(format!("{disabled} disabled"));
     }
+    if cancelled > 0 {
+        notes.push(format!("{cancelled} cancelled, will resume on next sync"));
+    }
     if !notes.is_empty() {
         println!("{}", console::style(notes.join(", ")).dim());
     }
@@ -1599,16 +3614,36 @@ async fn cmd_sync(
     runner: &AdapterRunner,
     config: &Config,
     source_filter: Option<String>,
+    group_filter: Option<String>,
     parallel: Option<usize>,
+    full: bool,
+    stream: bool,
     json: bool,
 ) -> Result<()> {
-    let stats = sync_sources(db, runner, config, source_filter, parallel, !json).await?;
-
-    if json {
+    let cancel = CancelToken::install_ctrl_c();
+    let output = if stream {
+        SyncOutput::Events
+    } else {
+        SyncOutput::human_unless(json)
+    };
+    let stats = sync_sources(
+        db,
+        runner,
+        config,
+        source_filter,
+        group_filter.as_deref(),
+        parallel,
+        full,
+        output,
+        Some(&cancel),
+    )
+    .await?;
+
+    if json || stream {
         let total_sources = stats.len();
         let total_conversations = stats.iter().map(|s| s.conversations).sum();
         let total_messages = stats.iter().map(|s| s.messages).sum();
-        return emit_json(JsonResponse {
+        let response = JsonResponse {
             ok: true,
             result: Some(SyncSummary {
                 sources: stats,
@@ -1617,12 +3652,23 @@ async fn cmd_sync(
                 total_messages,
             }),
             error: None,
-        });
+        };
+        return if stream {
+            emit_json_line(response)
+        } else {
+            emit_json(response)
+        };
     }
 
     Ok(())
 }
 
+/// Conversations written per transaction by `hstry import`.
+const IMPORT_BATCH_SIZE: usize = 200;
+
+/// `host` value attached to search hits that come from the archive database.
+const ARCHIVE_HOST: &str = "archive";
+
 #[derive(Debug, Serialize)]
 struct ImportResult {
     adapter: String,
@@ -1633,17 +3679,77 @@ struct ImportResult {
     dry_run: bool,
 }
 
+/// Result of importing a ChatGPT export zip.
+#[derive(Debug, Serialize)]
+struct ChatGptZipImportResult {
+    adapter: &'static str,
+    source_id: String,
+    conversations: usize,
+    branches: usize,
+    messages: usize,
+    attachments: usize,
+    dry_run: bool,
+}
+
 #[derive(Debug, Serialize)]
 struct DetectionResult {
     adapter: String,
     confidence: f32,
 }
 
+/// Bytes of piped input inspected to pick its file extension.
+const STDIN_SNIFF_BYTES: u64 = 1024 * 1024;
+
+/// Copy stdin to a temporary file for `hstry import -`, named so adapters
+/// recognise its format. Removed when the returned path is dropped.
+fn spool_stdin() -> Result<tempfile::TempPath> {
+    use std::io::Read as _;
+
+    let mut stdin = std::io::stdin().lock();
+    let mut head = Vec::new();
+    stdin
+        .by_ref()
+        .take(STDIN_SNIFF_BYTES)
+        .read_to_end(&mut head)?;
+    if head.is_empty() {
+        anyhow::bail!("Nothing to import: stdin is empty");
+    }
+    let mut file = tempfile::Builder::new()
+        .prefix("hstry-import-")
+        .suffix(stdin_suffix(&head))
+        .tempfile()?;
+    file.write_all(&head)?;
+    std::io::copy(&mut stdin, &mut file)?;
+    file.flush()?;
+    Ok(file.into_temp_path())
+}
+
+/// File extension for piped input starting with `head`: `.zip` for
+/// archives, `.jsonl` when the first line is a complete JSON object with more
+/// lines after it, `.json` otherwise.
+fn stdin_suffix(head: &[u8]) -> &'static str {
+    if head.starts_with(b"PK\x03\x04") {
+        return ".zip";
+    }
+    let text = String::from_utf8_lossy(head);
+    let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+    match (lines.next(), lines.next()) {
+        (Some(first), Some(_))
+            if first.starts_with('{')
+                && serde_json::from_str::<serde_json::Value>(first).is_ok() =>
+        {
+            ".jsonl"
+        }
+        _ => ".json",
+    }
+}
+
 async fn cmd_import(
     db: &Database,
     runner: &AdapterRunner,
     config: &Config,
     path: PathBuf,
+    from_stdin: bool,
     adapter: Option<String>,
     source_id: Option<String>,
     dry_run: bool,
@@ -1680,7 +3786,11 @@ async fn cmd_import(
     } else {
         // Auto-detect adapter
         if !json {
-            println!("Detecting format for {path}...", path = expanded.display());
+            if from_stdin {
+                println!("Detecting format of stdin...");
+            } else {
+                println!("Detecting format for {path}...", path = expanded.display());
+            }
         }
 
         let mut best_match: Option<(String, f32)> = None;
@@ -1759,6 +3869,8 @@ async fn cmd_import(
     let normalized_source_path = expanded.to_string_lossy().trim_end_matches('/').to_string();
     let source_id = if let Some(source_id) = source_id {
         source_id
+    } else if from_stdin {
+        adapter_name.clone()
     } else if let Some(existing) = db
         .get_source_by_adapter_path(&adapter_name, &normalized_source_path)
         .await?
@@ -1776,6 +3888,8 @@ async fn cmd_import(
         include_attachments: true,
         cursor: None,
         batch_size: None,
+        options: None,
+        unchanged_files: None,
     };
 
     let conversations = runner
@@ -1797,7 +3911,7 @@ async fn cmd_import(
                 error: None,
             });
         }
-        println!("No conversations found.");
+        println!("{}", tr!("cli-no-conversations"));
         return Ok(());
     }
 
@@ -1832,17 +3946,30 @@ async fn cmd_import(
     // import cannot create spurious sources (e.g. a path inside another
     // harness's tree, an individual file, a duplicate of an existing
     // source). Idempotent for an already-registered source.
-    let canonical_roots = resolve_canonical_roots(runner).await;
-    let existing_sources = db.list_sources().await?;
-    let source = match hstry_core::source_registry::validate_new_source(
-        &adapter_name,
-        &normalized_source_path,
-        source_id.clone(),
-        serde_json::json!({}),
-        &canonical_roots,
-        &existing_sources,
-        |p| p.is_dir(),
-    ) {
+    // Piped input has no path to sync from later, so it lands in a
+    // path-less source like the ChatGPT zip import.
+    let validated = if from_stdin {
+        Ok(db.get_source(&source_id).await?.unwrap_or_else(|| Source {
+            id: source_id.clone(),
+            adapter: adapter_name.clone(),
+            path: None,
+            last_sync_at: None,
+            config: serde_json::json!({}),
+        }))
+    } else {
+        let canonical_roots = resolve_canonical_roots(runner).await;
+        let existing_sources = db.list_sources().await?;
+        hstry_core::source_registry::validate_new_source(
+            &adapter_name,
+            &normalized_source_path,
+            source_id.clone(),
+            serde_json::json!({}),
+            &canonical_roots,
+            &existing_sources,
+            |p| p.is_dir(),
+        )
+    };
+    let source = match validated {
         Ok(s) => s,
         Err(e) => {
             if json {
@@ -1862,91 +3989,59 @@ async fn cmd_import(
         println!("Importing {conv_count} conversations...");
     }
 
+    // Write in fixed-size batches, one transaction each, so Ctrl-C rolls
+    // back at most the in-flight batch. Stable message ids make re-running
+    // the import after a cancel idempotent for the batches that landed.
+    let cancel = CancelToken::install_ctrl_c();
     let mut imported_convs = 0usize;
     let mut imported_msgs = 0usize;
-
-    for conv in conversations {
-        let mut conv_id = uuid::Uuid::new_v4();
-        if let Some(external_id) = conv.external_id.as_deref()
-            && let Some(existing) = db.get_conversation_id(&source_id, external_id).await?
+    let mut affected: Vec<uuid::Uuid> = Vec::new();
+    let mut pending = conversations.into_iter().peekable();
+    let mut interrupted = false;
+
+    while pending.peek().is_some() {
+        let batch: Vec<_> = pending.by_ref().take(IMPORT_BATCH_SIZE).collect();
+        let outcome = match cancel
+            .run(hstry_core::ingest::ingest_batch(db, &source_id, batch))
+            .await
         {
-            conv_id = existing;
-        }
-
-        let hstry_conv = hstry_core::models::Conversation {
-            id: conv_id,
-            source_id: source_id.clone(),
-            external_id: conv.external_id,
-            readable_id: conv.readable_id,
-            platform_id: None,
-            title: conv.title,
-            created_at: chrono::DateTime::from_timestamp_millis(conv.created_at)
-                .unwrap_or_default()
-                .with_timezone(&chrono::Utc),
-            updated_at: conv.updated_at.and_then(|ts| {
-                chrono::DateTime::from_timestamp_millis(ts).map(|dt| dt.with_timezone(&chrono::Utc))
-            }),
-            model: conv.model,
-            provider: conv.provider,
-            workspace: conv.workspace,
-            tokens_in: conv.tokens_in,
-            tokens_out: conv.tokens_out,
-            cost_usd: conv.cost_usd,
-            metadata: conv
-                .metadata
-                .map(|m| serde_json::to_value(m).unwrap_or_default())
-                .unwrap_or_default(),
-            harness: None,
-            version: 0,
-            message_count: 0,
-            parent_conversation_id: None,
-            parent_message_idx: conv.parent_message_idx,
-            fork_type: conv.fork_type,
+            Ok(outcome) => outcome?,
+            Err(_) => {
+                interrupted = true;
+                break;
+            }
         };
+        imported_convs += outcome.conversations;
+        imported_msgs += outcome.messages;
+        affected.extend(outcome.affected_conversation_ids);
+        if cancel.run(db.pace_writes(outcome.bytes)).await.is_err() {
+            interrupted = true;
+            break;
+        }
+    }
 
-        db.upsert_conversation(&hstry_conv).await?;
+    if !affected.is_empty() {
+        db.rebuild_conversation_summaries(&affected).await?;
+    }
 
-        for (idx, msg) in conv.messages.iter().enumerate() {
-            let Ok(idx) = i32::try_from(idx) else {
-                continue;
-            };
-            let parts_json = msg.parts.clone().unwrap_or_else(|| serde_json::json!([]));
-            let role_str = msg.role.as_str();
-            // Stable, content-addressable id (trx-hjjw.4) so re-imports of
-            // the same source produce idempotent rows.
-            let stable_id = hstry_core::stable_message_id(
-                &source_id,
-                hstry_conv.external_id.as_deref(),
-                idx,
-                role_str,
-                &msg.content,
-                None,
-            );
-            let hstry_msg = hstry_core::models::Message {
-                id: stable_id,
-                conversation_id: hstry_conv.id,
-                idx,
-                role: hstry_core::models::MessageRole::from(role_str),
-                content: msg.content.clone(),
-                parts_json,
-                created_at: msg.created_at.and_then(|ts| {
-                    chrono::DateTime::from_timestamp_millis(ts)
-                        .map(|dt| dt.with_timezone(&chrono::Utc))
+    if interrupted {
+        if json {
+            return emit_json(JsonResponse::<ImportResult> {
+                ok: false,
+                result: Some(ImportResult {
+                    adapter: adapter_name,
+                    confidence,
+                    source_id,
+                    conversations: imported_convs,
+                    messages: imported_msgs,
+                    dry_run: false,
                 }),
-                model: msg.model.clone(),
-                tokens: msg.tokens,
-                cost_usd: msg.cost_usd,
-                metadata: serde_json::Value::Object(serde_json::Map::default()),
-                sender: None,
-                provider: None,
-                harness: None,
-                client_id: None,
-            };
-            db.insert_message(&hstry_msg).await?;
-            imported_msgs += 1;
+                error: Some("Import cancelled".to_string()),
+            });
         }
-
-        imported_convs += 1;
+        anyhow::bail!(
+            "Import cancelled after {imported_convs} of {conv_count} conversations; re-run to finish"
+        );
     }
 
     // Update source last_sync_at
@@ -1975,65 +4070,280 @@ async fn cmd_import(
     Ok(())
 }
 
-async fn cmd_search_fast(
-    config: &Config,
-    query: &str,
-    limit: i64,
-    source: Option<String>,
-    workspace: Option<String>,
-    mode: SearchModeArg,
-    scope: SearchScopeArg,
-    remotes: Vec<String>,
-    roles: Vec<SearchRoleArg>,
-    no_tools: bool,
-    dedup: bool,
-    include_system: bool,
-    after: Option<String>,
-    before: Option<String>,
-    model: Option<String>,
-    harness_filter: Option<String>,
-    tag: Option<String>,
-    compact: bool,
-    json: bool,
-) -> Result<()> {
-    // Parse date strings into DateTime<Utc>
-    let after_dt = after.as_deref().map(parse_date_filter).transpose()?;
-    let before_dt = before.as_deref().map(parse_date_filter).transpose()?;
-
-    // Push single role filter to DB level for efficiency
-    let db_role = if roles.len() == 1 {
-        Some(roles[0].to_string())
+/// Import an archive written by `hstry export --format hstry-archive`.
+async fn cmd_import_archive(db: &Database, path: &Path, dry_run: bool, json: bool) -> Result<()> {
+    let cancel = CancelToken::install_ctrl_c();
+    let result = hstry_archive::import_archive(db, path, dry_run, &cancel).await?;
+    if json {
+        return emit_json(JsonResponse {
+            ok: true,
+            result: Some(result),
+            error: None,
+        });
+    }
+    let verb = if dry_run {
+        "Dry run: would import"
     } else {
-        None
+        "Imported"
     };
+    println!(
+        "{verb} {} conversations ({} messages, {} new attachments, {} new sources) from {}",
+        result.conversations,
+        result.messages,
+        result.attachments,
+        result.sources_added,
+        path.display()
+    );
+    Ok(())
+}
 
-    // Request more results than needed if we're filtering, to ensure we get enough after filtering
-    let has_filters = !include_system || !roles.is_empty() || no_tools || dedup;
-    let fetch_limit = if has_filters { limit * 4 } else { limit };
-    let opts = hstry_core::db::SearchOptions {
-        source_id: source,
-        workspace,
-        limit: Some(fetch_limit),
-        offset: None,
-        mode: mode.into(),
-        after: after_dt,
-        before: before_dt,
-        role: db_role,
-        model,
-        harness: harness_filter,
-        tag,
+/// Import an official ChatGPT export zip (`hstry import chatgpt-export.zip`).
+///
+/// The source is registered without a path: the archive is a one-off
+/// snapshot, so `hstry sync` has nothing to re-read. Re-importing a newer
+/// export updates conversations in place.
+async fn cmd_import_chatgpt_zip(
+    db: &Database,
+    path: &Path,
+    source_id: Option<String>,
+    dry_run: bool,
+    json: bool,
+) -> Result<()> {
+    let export = chatgpt_zip::read_export(path)?;
+    let source_id = source_id.unwrap_or_else(|| "chatgpt".to_string());
+    let mut result = ChatGptZipImportResult {
+        adapter: "chatgpt",
+        source_id: source_id.clone(),
+        conversations: export.conversations.len(),
+        branches: export.branch_count(),
+        messages: export.conversations.iter().map(|c| c.messages.len()).sum(),
+        attachments: export.assets.len(),
+        dry_run,
     };
-    let mut messages = Vec::new();
-
-    if scope != SearchScopeArg::Remote {
-        let service_expected = std::env::var("HSTRY_NO_SERVICE").is_err()
-            && config.service.enabled
-            && config.service.search_api;
 
-        let local = if service_expected {
-            if let Some(results) = hstry_core::service::try_service_search(query, &opts).await? {
-                results
-            } else {
+    if dry_run || export.conversations.is_empty() {
+        if json {
+            return emit_json(JsonResponse {
+                ok: true,
+                result: Some(result),
+                error: None,
+            });
+        }
+        if export.conversations.is_empty() {
+            println!("{}", tr!("cli-no-conversations"));
+            return Ok(());
+        }
+        println!(
+            "Dry run: would import {} conversations ({} branches, {} messages, {} attachments)",
+            result.conversations, result.branches, result.messages, result.attachments
+        );
+        for conv in export
+            .conversations
+            .iter()
+            .filter(|c| c.parent_external_id.is_none())
+        {
+            let title = conv.title.as_deref().unwrap_or("Untitled");
+            let msg_cnt = conv.messages.len();
+            println!("  - {title} ({msg_cnt} messages)");
+        }
+        return Ok(());
+    }
+
+    let mut source = db.get_source(&source_id).await?.unwrap_or_else(|| Source {
+        id: source_id.clone(),
+        adapter: "chatgpt".to_string(),
+        path: None,
+        last_sync_at: None,
+        config: serde_json::json!({}),
+    });
+    db.upsert_source(&source).await?;
+
+    if !json {
+        println!("Importing {} conversations...", result.conversations);
+    }
+
+    // Branches follow their parent thread in `conversations`, so the parent
+    // is always written by the time ingest links a branch to it.
+    let conversations = export.conversations;
+    let cancel = CancelToken::install_ctrl_c();
+    let mut affected: Vec<uuid::Uuid> = Vec::new();
+    let mut imported_msgs = 0usize;
+    for batch in conversations.chunks(IMPORT_BATCH_SIZE) {
+        let Ok(outcome) = cancel
+            .run(hstry_core::ingest::ingest_batch(
+                db,
+                &source_id,
+                batch.to_vec(),
+            ))
+            .await
+        else {
+            if !affected.is_empty() {
+                db.rebuild_conversation_summaries(&affected).await?;
+            }
+            anyhow::bail!("Import cancelled; re-run to finish");
+        };
+        let outcome = outcome?;
+        imported_msgs += outcome.messages;
+        affected.extend(outcome.affected_conversation_ids);
+        if cancel.run(db.pace_writes(outcome.bytes)).await.is_err() {
+            db.rebuild_conversation_summaries(&affected).await?;
+            anyhow::bail!("Import cancelled; re-run to finish");
+        }
+    }
+    if !affected.is_empty() {
+        db.rebuild_conversation_summaries(&affected).await?;
+    }
+
+    result.messages = imported_msgs;
+    result.attachments = store_chatgpt_assets(db, path, &source_id, &export.assets).await?;
+
+    source.last_sync_at = Some(chrono::Utc::now());
+    db.upsert_source(&source).await?;
+
+    if json {
+        return emit_json(JsonResponse {
+            ok: true,
+            result: Some(result),
+            error: None,
+        });
+    }
+    println!(
+        "Imported {} conversations ({} branches, {} messages, {} new attachments) into source '{source_id}'",
+        result.conversations, result.branches, result.messages, result.attachments
+    );
+    Ok(())
+}
+
+/// Copy archive files referenced by imported messages into the attachments
+/// table. Files already stored by an earlier import are skipped. Returns the
+/// number of attachments written.
+async fn store_chatgpt_assets(
+    db: &Database,
+    path: &Path,
+    source_id: &str,
+    assets: &[chatgpt_zip::PendingAsset],
+) -> Result<usize> {
+    if assets.is_empty() {
+        return Ok(0);
+    }
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(path)?)?;
+    let mut message_ids: HashMap<&str, Vec<uuid::Uuid>> = HashMap::new();
+    let mut stored = 0usize;
+
+    for asset in assets {
+        if db.get_attachment(&asset.attachment_id).await?.is_some() {
+            continue;
+        }
+        if !message_ids.contains_key(asset.external_id.as_str()) {
+            let ids = match db
+                .get_conversation_id(source_id, &asset.external_id)
+                .await?
+            {
+                Some(conv_id) => db
+                    .get_messages(conv_id)
+                    .await?
+                    .into_iter()
+                    .map(|m| m.id)
+                    .collect(),
+                None => Vec::new(),
+            };
+            message_ids.insert(&asset.external_id, ids);
+        }
+        let Some(message_id) = message_ids
+            .get(asset.external_id.as_str())
+            .and_then(|ids| ids.get(asset.message_idx))
+        else {
+            continue;
+        };
+        let data = chatgpt_zip::read_entry(&mut archive, &asset.entry)?;
+        db.insert_attachment(
+            &asset.attachment_id,
+            *message_id,
+            &asset.mime_type,
+            asset.filename.as_deref(),
+            &data,
+        )
+        .await?;
+        stored += 1;
+    }
+    Ok(stored)
+}
+
+async fn cmd_search_fast(
+    config: &Config,
+    query: &str,
+    limit: i64,
+    source: Option<String>,
+    workspace: Option<String>,
+    mode: SearchModeArg,
+    scope: SearchScopeArg,
+    remotes: Vec<String>,
+    roles: Vec<SearchRoleArg>,
+    no_tools: bool,
+    dedup: bool,
+    include_system: bool,
+    after: Option<String>,
+    before: Option<String>,
+    model: Option<String>,
+    harness_filter: Option<String>,
+    tag: Option<String>,
+    starred: bool,
+    compact: bool,
+    include_archived: bool,
+    rerank: bool,
+    expand: &[SearchExpandArg],
+    context: usize,
+    group_by: Option<SearchGroupArg>,
+    json: bool,
+) -> Result<()> {
+    // Parse date strings into DateTime<Utc>
+    let after_dt = after.as_deref().map(parse_date_filter).transpose()?;
+    let before_dt = before.as_deref().map(parse_date_filter).transpose()?;
+
+    // Push single role filter to DB level for efficiency
+    let db_role = if roles.len() == 1 {
+        Some(roles[0].to_string())
+    } else {
+        None
+    };
+
+    // Request more results than needed if we're filtering, to ensure we get enough after filtering
+    let has_filters =
+        !include_system || !roles.is_empty() || no_tools || dedup || group_by.is_some();
+    let mut fetch_limit = if has_filters { limit * 4 } else { limit };
+    let reranker = if rerank || config.search.rerank.enabled {
+        let reranker =
+            hstry_core::rerank::Reranker::from_config(&config.search.rerank, &config.llm)?;
+        fetch_limit = fetch_limit.max(i64::try_from(reranker.candidates()).unwrap_or(i64::MAX));
+        Some(reranker)
+    } else {
+        None
+    };
+    let opts = hstry_core::db::SearchOptions {
+        source_id: source,
+        workspace,
+        limit: Some(fetch_limit),
+        offset: None,
+        mode: mode.into(),
+        after: after_dt,
+        before: before_dt,
+        role: db_role,
+        model,
+        harness: harness_filter,
+        tag,
+        starred,
+    };
+    let mut messages = Vec::new();
+
+    if scope != SearchScopeArg::Remote {
+        let service_expected = std::env::var("HSTRY_NO_SERVICE").is_err()
+            && config.service.enabled
+            && config.service.search_api;
+
+        let local = if service_expected {
+            if let Some(results) = hstry_core::service::try_service_search(query, &opts).await? {
+                results
+            } else {
                 anyhow::bail!(
                     "Search service unavailable. Run `hstry service start` or set HSTRY_NO_SERVICE=1 to use local search."
                 );
@@ -2041,11 +4351,20 @@ async fn cmd_search_fast(
         } else if let Some(results) = try_api_search(query, &opts, mode).await? {
             results
         } else {
-            let db = Database::open(&config.database).await?;
+            let db = Database::open_config(config).await?;
             apply_storage_config(&db, config);
             db.search(query, opts.clone()).await?
         };
         messages.extend(local);
+
+        if include_archived && config.archive_path().exists() {
+            let archive = Database::open_archive(config).await?;
+            let archived = archive.search(query, opts.clone()).await?;
+            messages.extend(archived.into_iter().map(|mut hit| {
+                hit.host = Some(ARCHIVE_HOST.to_string());
+                hit
+            }));
+        }
     }
 
     if scope != SearchScopeArg::Local {
@@ -2092,6 +4411,17 @@ async fn cmd_search_fast(
         });
     }
 
+    // Re-score the best hits; scores are the model's from here on. A
+    // failing endpoint leaves the full-text order in place.
+    if let Some(reranker) = &reranker
+        && let Err(err) = reranker.rerank(query, &mut messages).await
+    {
+        tracing::warn!("Re-ranking failed, keeping full-text order: {err}");
+    }
+    if let Some(reranker) = &reranker {
+        record_llm_usage(config, reranker.take_usage()).await;
+    }
+
     // Deduplicate by external_id (real session identifier) if requested
     if dedup {
         let mut seen = std::collections::HashSet::new();
@@ -2105,6 +4435,31 @@ async fn cmd_search_fast(
         });
     }
 
+    if group_by == Some(SearchGroupArg::Conversation) {
+        let mut groups = hstry_core::search_groups::group_by_conversation(messages);
+        groups.truncate(usize::try_from(limit.max(0)).unwrap_or(usize::MAX));
+        if json {
+            if expand.contains(&SearchExpandArg::Conversation) {
+                let mut hits: Vec<SearchHit> = groups
+                    .iter_mut()
+                    .flat_map(|group| std::mem::take(&mut group.hits))
+                    .collect();
+                expand_conversations(config, &mut hits).await?;
+                let mut hits = hits.into_iter();
+                for group in &mut groups {
+                    group.hits = hits.by_ref().take(group.hit_count).collect();
+                }
+            }
+            return emit_json(JsonResponse {
+                ok: true,
+                result: Some(groups),
+                error: None,
+            });
+        }
+        pretty::print_conversation_hits(&groups, &config.badges);
+        return Ok(());
+    }
+
     // Group by external_id if compact mode is enabled
     if compact {
         use std::collections::BTreeMap;
@@ -2148,8 +4503,14 @@ async fn cmd_search_fast(
     // Apply the original limit after filtering
     let truncate_to = usize::try_from(limit.max(0)).unwrap_or(usize::MAX);
     messages.truncate(truncate_to);
+    if context > 0 {
+        add_search_context(config, &mut messages, context).await?;
+    }
 
     if json {
+        if expand.contains(&SearchExpandArg::Conversation) {
+            expand_conversations(config, &mut messages).await?;
+        }
         return emit_json(JsonResponse {
             ok: true,
             result: Some(messages),
@@ -2157,38 +4518,151 @@ async fn cmd_search_fast(
         });
     }
 
-    if compact {
-        pretty::print_search_results_compact(&messages);
+    if context > 0 {
+        pretty::print_search_results_with_context(&messages);
+    } else if compact {
+        pretty::print_search_results_compact(&messages, &config.badges);
     } else {
-        pretty::print_search_results(&messages);
+        pretty::print_search_results(&messages, &config.badges);
     }
     Ok(())
 }
 
-/// Detect if content is system context (AGENTS.md, etc.) that should be hidden by default.
-fn is_system_context(content: &str) -> bool {
-    // Strong markers - if any of these are present, it's system context
-    let strong_markers = [
-        "# AGENTS.md",
-        "# Agent Configuration",
-        "<available_skills>",
-        "Guidance for coding agents",
-        "<SYSTEM_PROMPT>",
-        "</SYSTEM_PROMPT>",
-    ];
+async fn cmd_ask(
+    db: &Database,
+    config: &Config,
+    question: &str,
+    opts: &hstry_core::db::SearchOptions,
+    passages: Option<usize>,
+    json: bool,
+) -> Result<()> {
+    use hstry_core::ask;
+
+    let mut ask_config = config.ask.clone();
+    if let Some(passages) = passages {
+        ask_config.passages = passages;
+    }
+    let endpoint = hstry_core::llm::Endpoint::for_feature(
+        &config.llm,
+        "ask",
+        hstry_core::llm::SectionEndpoint {
+            section: "[ask]",
+            url: ask_config.endpoint.as_deref(),
+            model: ask_config.model.as_deref(),
+            api_key_env: ask_config.api_key_env.as_deref(),
+            timeout_secs: ask_config.timeout_secs,
+        },
+    )?;
+
+    let mut hits = ask::retrieve(db, question, opts, 50.max(ask_config.passages * 4)).await?;
+    if config.search.rerank.enabled {
+        let reranker =
+            hstry_core::rerank::Reranker::from_config(&config.search.rerank, &config.llm)?;
+        if let Err(err) = reranker.rerank(question, &mut hits).await {
+            tracing::warn!("Re-ranking failed, keeping full-text order: {err}");
+        }
+        record_llm_usage(config, reranker.take_usage()).await;
+    }
+    let context = ask::passages(&hits, &ask_config);
+    if context.is_empty() {
+        anyhow::bail!("No messages match the question; try other words or fewer filters");
+    }
+    let answer = ask::ask(&endpoint, question, context).await;
+    record_llm_usage(config, endpoint.take_usage()).await;
+    let answer = answer?;
 
-    for marker in &strong_markers {
-        if content.contains(marker) {
-            return true;
+    if json {
+        return emit_json(JsonResponse {
+            ok: true,
+            result: Some(answer),
+            error: None,
+        });
+    }
+    println!("{}", answer.answer);
+    if !answer.citations.is_empty() {
+        println!("\nSources:");
+        for citation in &answer.citations {
+            let id = citation
+                .readable_id
+                .clone()
+                .unwrap_or_else(|| citation.conversation_id.to_string());
+            println!(
+                "  [{}] {id} #{}  {} ({}, {})",
+                citation.n,
+                citation.message_idx,
+                citation.title.as_deref().unwrap_or("Untitled"),
+                citation.date.format("%Y-%m-%d"),
+                citation.source
+            );
         }
     }
+    Ok(())
+}
+
+/// Store model request usage for `hstry stats`. Failing to record it never
+/// fails the command.
+async fn record_llm_usage(config: &Config, usage: Vec<hstry_core::llm::LlmUsage>) {
+    if usage.is_empty() {
+        return;
+    }
+    let result = match Database::open_config(config).await {
+        Ok(db) => db.record_llm_usage(&usage).await,
+        Err(err) => Err(err),
+    };
+    if let Err(err) = result {
+        tracing::warn!("Failed to record LLM usage: {err}");
+    }
+}
 
-    // Check for AGENTS.md file path pattern
-    if content.contains("AGENTS.md") && content.contains("instructions") {
-        return true;
+/// Attach conversations to local and archived hits. Remote hits keep only
+/// the metadata already on the hit.
+async fn expand_conversations(config: &Config, hits: &mut [SearchHit]) -> Result<()> {
+    if hits.iter().any(|hit| hit.host.is_none()) {
+        let db = Database::open_config(config).await?;
+        db.expand_search_hits(hits.iter_mut().filter(|hit| hit.host.is_none()))
+            .await?;
+    }
+    if hits
+        .iter()
+        .any(|hit| hit.host.as_deref() == Some(ARCHIVE_HOST))
+    {
+        let archive = Database::open_archive(config).await?;
+        archive
+            .expand_search_hits(
+                hits.iter_mut()
+                    .filter(|hit| hit.host.as_deref() == Some(ARCHIVE_HOST)),
+            )
+            .await?;
     }
+    Ok(())
+}
 
-    false
+/// Attach the messages around local and archived hits. Remote hits are
+/// shown without context.
+async fn add_search_context(
+    config: &Config,
+    hits: &mut [SearchHit],
+    messages: usize,
+) -> Result<()> {
+    if hits.iter().any(|hit| hit.host.is_none()) {
+        let db = Database::open_config(config).await?;
+        db.add_search_context(hits.iter_mut().filter(|hit| hit.host.is_none()), messages)
+            .await?;
+    }
+    if hits
+        .iter()
+        .any(|hit| hit.host.as_deref() == Some(ARCHIVE_HOST))
+    {
+        let archive = Database::open_archive(config).await?;
+        archive
+            .add_search_context(
+                hits.iter_mut()
+                    .filter(|hit| hit.host.as_deref() == Some(ARCHIVE_HOST)),
+                messages,
+            )
+            .await?;
+    }
+    Ok(())
 }
 
 /// Detect a resume/compaction continuation fragment whose first user message is
@@ -2235,7 +4709,11 @@ async fn try_api_search(
     };
 
     let client = reqwest::Client::new();
-    let Ok(response) = client.get(url).query(&query_params).send().await else {
+    let mut request = client.get(url).query(&query_params);
+    if let Ok(token) = std::env::var("HSTRY_API_TOKEN") {
+        request = request.bearer_auth(token);
+    }
+    let Ok(response) = request.send().await else {
         return Ok(None);
     };
 
@@ -2278,6 +4756,22 @@ enum SearchScopeArg {
     All,
 }
 
+#[derive(
+    Debug, Clone, Copy, clap::ValueEnum, serde::Deserialize, serde::Serialize, PartialEq, Eq,
+)]
+#[serde(rename_all = "lowercase")]
+enum SearchExpandArg {
+    Conversation,
+}
+
+#[derive(
+    Debug, Clone, Copy, clap::ValueEnum, serde::Deserialize, serde::Serialize, PartialEq, Eq,
+)]
+#[serde(rename_all = "lowercase")]
+enum SearchGroupArg {
+    Conversation,
+}
+
 #[derive(
     Debug, Clone, Copy, clap::ValueEnum, serde::Deserialize, serde::Serialize, PartialEq, Eq,
 )]
@@ -2378,12 +4872,17 @@ fn expanded_list_limit(limit: i64) -> i64 {
 
 async fn cmd_list(
     db: &Database,
+    archive: Option<&Database>,
     source: Option<String>,
     workspace: Option<String>,
     limit: i64,
     after: Option<chrono::DateTime<chrono::Utc>>,
     before: Option<chrono::DateTime<chrono::Utc>>,
+    last_seen: chrono::DateTime<chrono::Utc>,
+    only_new: bool,
+    starred: bool,
     include_all: bool,
+    badges: &hstry_core::config::SourceBadges,
     json: bool,
 ) -> Result<()> {
     let dedup_across_sources = source.is_none();
@@ -2393,14 +4892,39 @@ async fn cmd_list(
         workspace,
         after,
         before,
+        ingested_after: only_new.then_some(last_seen),
+        model: None,
+        provider: None,
+        harness: None,
+        role: None,
+        exclude_sensitive: false,
+        starred,
         limit: Some(if dedup_across_sources {
             expanded_list_limit(limit)
         } else {
             limit
         }),
+        offset: None,
     };
 
-    let mut fetched = db.list_conversation_previews(opts).await?;
+    let mut fetched = if let Some(archive) = archive {
+        let mut merged = db.list_conversation_previews(opts.clone()).await?;
+        merged.extend(archive.list_conversation_previews(opts).await?);
+        merged.sort_by_key(|preview| {
+            std::cmp::Reverse(
+                preview
+                    .conversation
+                    .updated_at
+                    .unwrap_or(preview.conversation.created_at),
+            )
+        });
+        if !dedup_across_sources && limit > 0 {
+            merged.truncate(limit as usize);
+        }
+        merged
+    } else {
+        db.list_conversation_previews(opts).await?
+    };
     if !include_all {
         fetched.retain(|preview| !is_continuation_fragment(preview.first_user_message.as_deref()));
     }
@@ -2423,6 +4947,12 @@ async fn cmd_list(
         });
     }
 
+    let new_ids: HashSet<uuid::Uuid> = db
+        .new_conversation_ids(last_seen)
+        .await?
+        .into_iter()
+        .collect();
+    let starred_ids = db.starred_conversation_ids().await?;
     let display = previews
         .into_iter()
         .map(|preview| {
@@ -2431,9 +4961,12 @@ async fn cmd_list(
                 preview.first_user_message.as_deref(),
             );
             pretty::ConversationDisplay {
+                is_new: new_ids.contains(&preview.conversation.id),
+                is_starred: starred_ids.contains(&preview.conversation.id),
                 id: preview.conversation.id,
                 source_id: preview.conversation.source_id,
                 workspace: preview.conversation.workspace,
+                workspace_name: preview.workspace_name,
                 created_at: preview.conversation.created_at,
                 title,
                 readable_id: preview.conversation.readable_id,
@@ -2441,7 +4974,7 @@ async fn cmd_list(
         })
         .collect::<Vec<_>>();
 
-    pretty::print_conversations(&display);
+    pretty::print_conversations(&display, badges);
 
     Ok(())
 }
@@ -2453,6 +4986,7 @@ async fn cmd_list_peek(
     limit: i64,
     after: Option<chrono::DateTime<chrono::Utc>>,
     before: Option<chrono::DateTime<chrono::Utc>>,
+    starred: bool,
     last_assistant_chars: Option<usize>,
 ) -> Result<()> {
     let dedup_across_sources = source.is_none();
@@ -2462,11 +4996,19 @@ async fn cmd_list_peek(
         workspace: workspace_filter,
         after,
         before,
+        ingested_after: None,
+        model: None,
+        provider: None,
+        harness: None,
+        role: None,
+        exclude_sensitive: false,
+        starred,
         limit: Some(if dedup_across_sources {
             expanded_list_limit(limit)
         } else {
             limit
         }),
+        offset: None,
     };
 
     let previews = if dedup_across_sources {
@@ -2520,30 +5062,135 @@ async fn cmd_peek(db: &Database, id: &str, chars: Option<usize>, json: bool) ->
     Ok(())
 }
 
-fn print_peek_text(b: &hstry_core::peek::PeekBundle) {
-    println!("{} [{}]", b.id, b.source);
-    if let Some(m) = &b.model {
-        println!("Model: {m}");
+async fn cmd_pack(
+    db: &Database,
+    workspace: Option<String>,
+    output: Option<PathBuf>,
+    top: usize,
+    force: bool,
+    json: bool,
+) -> Result<()> {
+    use hstry_core::db::ListConversationsOptions;
+    use hstry_core::pack::{PackConfig, PackHeader, PackInput};
+
+    let workspace = match workspace {
+        Some(workspace) => workspace,
+        None => std::env::current_dir()?.to_string_lossy().into_owned(),
+    };
+    let mut output = output.unwrap_or_else(|| PathBuf::from("AGENTS-HISTORY.md"));
+    if output.is_dir() {
+        output.push("AGENTS-HISTORY.md");
     }
-    println!(
-        "Created: {} ({} min, {} msgs)",
-        b.created_at.format("%Y-%m-%d %H:%M"),
-        b.duration_min,
-        b.message_count
-    );
-    println!(
-        "Counts: user={}, assistant={}, tool_calls={}",
-        b.counts.user, b.counts.assistant, b.counts.tool_calls
-    );
-    if !b.tools.is_empty() {
-        let tools: Vec<String> = b
-            .tools
-            .iter()
-            .map(|(name, count)| format!("{name}={count}"))
-            .collect();
-        println!("Tools: {}", tools.join(", "));
+
+    let summaries = db
+        .list_conversation_summaries(ListConversationsOptions {
+            workspace: Some(workspace.clone()),
+            exclude_sensitive: true,
+            ..Default::default()
+        })
+        .await?;
+    if summaries.is_empty() {
+        anyhow::bail!("No conversations found for workspace {workspace}");
     }
-    if !b.files_touched.is_empty() {
+
+    // Keep the existing pack when it covers the same conversations and none
+    // of them changed since it was generated.
+    let existing = std::fs::read_to_string(&output)
+        .ok()
+        .and_then(|pack| PackHeader::parse(&pack));
+    let mut up_to_date = false;
+    if !force
+        && let Some(header) = existing
+        && header.workspace == workspace
+        && header.conversations == summaries.len()
+    {
+        let changed: HashSet<uuid::Uuid> = db
+            .changed_conversation_ids(header.generated_at)
+            .await?
+            .into_iter()
+            .collect();
+        up_to_date = !summaries
+            .iter()
+            .any(|summary| changed.contains(&summary.conversation.id));
+    }
+
+    if !up_to_date {
+        let config = PackConfig {
+            top_conversations: top,
+            ..PackConfig::default()
+        };
+        let now = chrono::Utc::now();
+        let mut messages = HashMap::new();
+        for id in hstry_core::pack::top_conversations(&summaries, now, config.top_conversations) {
+            messages.insert(id, db.get_messages(id).await?);
+        }
+        hstry_core::decisions::analyze(db, Some(&workspace)).await?;
+        // Decisions recorded before their conversation became sensitive stay
+        // out along with it.
+        let packed: HashSet<uuid::Uuid> = summaries.iter().map(|s| s.conversation.id).collect();
+        let mut decisions = db.list_decisions(Some(&workspace), None).await?;
+        decisions.retain(|decision| packed.contains(&decision.conversation_id));
+        let pack = hstry_core::pack::render_pack(
+            &PackInput {
+                workspace: &workspace,
+                summaries: &summaries,
+                messages: &messages,
+                decisions: &decisions,
+                generated_at: now,
+            },
+            &config,
+        );
+        std::fs::write(&output, pack)?;
+    }
+
+    if json {
+        return emit_json(JsonResponse {
+            ok: true,
+            result: Some(serde_json::json!({
+                "output": output,
+                "workspace": workspace,
+                "conversations": summaries.len(),
+                "written": !up_to_date,
+            })),
+            error: None,
+        });
+    }
+    if up_to_date {
+        println!("{} is up to date", output.display());
+    } else {
+        println!(
+            "Wrote {} ({} conversations)",
+            output.display(),
+            summaries.len()
+        );
+    }
+    Ok(())
+}
+
+fn print_peek_text(b: &hstry_core::peek::PeekBundle) {
+    println!("{} [{}]", b.id, b.source);
+    if let Some(m) = &b.model {
+        println!("Model: {m}");
+    }
+    println!(
+        "Created: {} ({} min, {} msgs)",
+        b.created_at.format("%Y-%m-%d %H:%M"),
+        b.duration_min,
+        b.message_count
+    );
+    println!(
+        "Counts: user={}, assistant={}, tool_calls={}",
+        b.counts.user, b.counts.assistant, b.counts.tool_calls
+    );
+    if !b.tools.is_empty() {
+        let tools: Vec<String> = b
+            .tools
+            .iter()
+            .map(|(name, count)| format!("{name}={count}"))
+            .collect();
+        println!("Tools: {}", tools.join(", "));
+    }
+    if !b.files_touched.is_empty() {
         println!("Files touched:");
         for f in &b.files_touched {
             println!("  {f}");
@@ -2568,25 +5215,83 @@ fn print_peek_text(b: &hstry_core::peek::PeekBundle) {
     }
 }
 
-async fn cmd_show(db: &Database, id: &str, json: bool) -> Result<()> {
+async fn cmd_show(
+    db: &Database,
+    id: &str,
+    page: Option<usize>,
+    page_size: usize,
+    show_branches: bool,
+    show_history: bool,
+    json: bool,
+) -> Result<()> {
     let conv = resolve_conversation_by_id(db, id).await?;
+    db.refresh_summary_if_stale(conv.id).await?;
 
-    let messages = db.get_messages(conv.id).await?;
+    let (messages, page_info) = match page {
+        Some(page) => {
+            if page == 0 || page_size == 0 {
+                anyhow::bail!("--page and --page-size must be at least 1");
+            }
+            let total =
+                usize::try_from(db.count_messages_for_conversation(conv.id).await?).unwrap_or(0);
+            let offset = (page - 1).saturating_mul(page_size);
+            let messages = db
+                .get_messages_range(
+                    conv.id,
+                    MessageRange {
+                        offset: i64::try_from(offset).ok(),
+                        limit: i64::try_from(page_size).ok(),
+                        ..Default::default()
+                    },
+                )
+                .await?;
+            let pages = total.div_ceil(page_size).max(1);
+            (messages, Some((page, pages, offset, total)))
+        }
+        None => (db.get_messages(conv.id).await?, None),
+    };
+
+    // Branched conversations show the active thread unless --branches asks
+    // for everything. Paged output stays in stored order.
+    let all_branches = if page_info.is_none() && branches::is_tree(&messages) {
+        branches::branches(&messages)
+    } else {
+        Vec::new()
+    };
+    let (messages, alternates) = if all_branches.is_empty() || (show_branches && json) {
+        (messages, Vec::new())
+    } else {
+        let by_idx: HashMap<i32, Message> = messages.into_iter().map(|m| (m.idx, m)).collect();
+        let pick = |idxs: &[i32]| -> Vec<Message> {
+            idxs.iter().filter_map(|i| by_idx.get(i).cloned()).collect()
+        };
+        let alternates: Vec<(usize, Vec<Message>)> = all_branches[1..]
+            .iter()
+            .map(|branch| (branch.shared, pick(&branch.path[branch.shared..])))
+            .collect();
+        (pick(&all_branches[0].path), alternates)
+    };
+    let revisions = db.message_revisions(conv.id).await?;
     if json {
+        let mut tool_calls = tool_calls::by_message(db.get_tool_calls(conv.id).await?);
         let details = hstry_core::models::ConversationWithMessages {
             conversation: conv,
             messages: messages
                 .into_iter()
                 .map(|message| hstry_core::models::MessageWithExtras {
+                    tool_calls: tool_calls.remove(&message.id).unwrap_or_default(),
                     message,
-                    tool_calls: Vec::new(),
                     attachments: Vec::new(),
                 })
                 .collect(),
         };
+        let mut result = serde_json::to_value(details)?;
+        if show_history {
+            result["revisions"] = serde_json::to_value(revisions)?;
+        }
         return emit_json(JsonResponse {
             ok: true,
-            result: Some(details),
+            result: Some(result),
             error: None,
         });
     }
@@ -2598,12 +5303,87 @@ async fn cmd_show(db: &Database, id: &str, json: bool) -> Result<()> {
     if let Some(ws) = &conv.workspace {
         println!("Workspace: {ws}");
     }
+    for related in db.related_conversations(conv.id).await? {
+        let id = related
+            .readable_id
+            .unwrap_or_else(|| related.id.to_string());
+        println!(
+            "Also imported from {source}: {id} ({reason})",
+            source = related.source_id,
+            reason = related.reason.replace('_', " ")
+        );
+    }
+    if let Some((page, pages, offset, total)) = page_info {
+        let shown = messages.len();
+        if shown == 0 {
+            println!("Page {page}/{pages} (no messages, {total} total)");
+        } else {
+            println!(
+                "Page {page}/{pages} (messages {first}-{last} of {total})",
+                first = offset + 1,
+                last = offset + shown
+            );
+        }
+    }
     println!();
 
+    let mut earlier: HashMap<i32, Vec<&MessageRevision>> = HashMap::new();
+    for revision in &revisions {
+        earlier.entry(revision.idx).or_default().push(revision);
+    }
     for msg in messages {
         println!("--- {role} ---", role = msg.role);
         println!("{content}", content = msg.content);
         println!();
+        if !show_history {
+            continue;
+        }
+        for revision in earlier.get(&msg.idx).into_iter().flatten() {
+            let verb = match revision.kind {
+                RevisionKind::Edit => "edited",
+                RevisionKind::Regenerate => "regenerated",
+            };
+            println!(
+                "~~~ earlier version, {verb} {date} ~~~",
+                date = revision.replaced_at.format("%Y-%m-%d %H:%M")
+            );
+            println!("{content}", content = revision.content);
+            println!();
+        }
+    }
+    if !show_history && !revisions.is_empty() {
+        println!(
+            "({count} earlier message version{s} kept; use --history to show)",
+            count = revisions.len(),
+            s = if revisions.len() == 1 { "" } else { "s" }
+        );
+    }
+
+    if show_branches {
+        for (n, (shared, branch)) in alternates.iter().enumerate() {
+            let from = if *shared == 0 {
+                "at the start".to_string()
+            } else {
+                format!("after message {shared}")
+            };
+            println!(
+                "=== Branch {n} of {total}, {from} ===",
+                n = n + 2,
+                total = alternates.len() + 1
+            );
+            println!();
+            for msg in branch {
+                println!("--- {role} ---", role = msg.role);
+                println!("{content}", content = msg.content);
+                println!();
+            }
+        }
+    } else if !alternates.is_empty() {
+        println!(
+            "({count} alternate branch{es} hidden; use --branches to show)",
+            count = alternates.len(),
+            es = if alternates.len() == 1 { "" } else { "es" }
+        );
     }
 
     Ok(())
@@ -2612,6 +5392,8 @@ async fn cmd_show(db: &Database, id: &str, json: bool) -> Result<()> {
 async fn cmd_source(
     db: &Database,
     runner: &AdapterRunner,
+    config: &Config,
+    config_path: &Path,
     command: SourceCommand,
     json: bool,
 ) -> Result<()> {
@@ -2712,8 +5494,14 @@ async fn cmd_source(
             } else {
                 for source in sources {
                     let path = source.path.as_deref().unwrap_or("-");
+                    let pin = source
+                        .config
+                        .get("adapter_version")
+                        .and_then(serde_json::Value::as_str)
+                        .map(|version| format!("@{version}"))
+                        .unwrap_or_default();
                     println!(
-                        "{id} | {adapter} | {path}",
+                        "{id} | {adapter}{pin} | {path}",
                         id = source.id,
                         adapter = source.adapter
                     );
@@ -2733,6 +5521,99 @@ async fn cmd_source(
             }
             println!("Removed source: {id}");
         }
+        SourceCommand::SetOption { id, options } => {
+            let Some(mut source) = db.get_source(&id).await? else {
+                anyhow::bail!("Source '{id}' not found");
+            };
+            let mut current: BTreeMap<String, serde_json::Value> = source
+                .config
+                .get("options")
+                .and_then(serde_json::Value::as_object)
+                .map(|map| map.clone().into_iter().collect())
+                .unwrap_or_default();
+            for raw in &options {
+                match parse_source_option(raw)? {
+                    (key, Some(value)) => current.insert(key, value),
+                    (key, None) => current.remove(&key),
+                };
+            }
+            replace_source_options(&mut source, &current);
+            db.upsert_source(&source).await?;
+
+            // Config-file sources are re-applied from the file on every sync,
+            // so the file has to carry the change too.
+            if config.sources.iter().any(|s| s.id == id) {
+                let mut config = config.clone();
+                if let Some(entry) = config.sources.iter_mut().find(|s| s.id == id) {
+                    entry.options = current.clone();
+                }
+                config.save_to_path(config_path)?;
+            }
+
+            if json {
+                return emit_json(JsonResponse {
+                    ok: true,
+                    result: Some(serde_json::json!({ "id": id, "options": current })),
+                    error: None,
+                });
+            }
+            if current.is_empty() {
+                println!("Source '{id}' has no options");
+            } else {
+                println!("Options for '{id}':");
+                for (key, value) in &current {
+                    println!("  {key} = {value}");
+                }
+            }
+        }
+        SourceCommand::PinAdapter {
+            id,
+            adapter_version,
+            clear,
+        } => {
+            let Some(mut source) = db.get_source(&id).await? else {
+                anyhow::bail!("Source '{id}' not found");
+            };
+            let pinned = if clear {
+                None
+            } else {
+                Some(pin_adapter_version(
+                    runner,
+                    config,
+                    &source.adapter,
+                    adapter_version.as_deref(),
+                )?)
+            };
+            set_source_adapter_version(&mut source, pinned.as_deref());
+            db.upsert_source(&source).await?;
+
+            if config.sources.iter().any(|s| s.id == id) {
+                let mut config = config.clone();
+                if let Some(entry) = config.sources.iter_mut().find(|s| s.id == id) {
+                    entry.adapter_version.clone_from(&pinned);
+                }
+                config.save_to_path(config_path)?;
+            }
+
+            if json {
+                return emit_json(JsonResponse {
+                    ok: true,
+                    result: Some(serde_json::json!({
+                        "id": id,
+                        "adapter": source.adapter,
+                        "adapter_version": pinned,
+                    })),
+                    error: None,
+                });
+            }
+            match pinned {
+                Some(version) => println!(
+                    "Pinned source '{id}' to {adapter} {version}",
+                    adapter = source.adapter
+                ),
+                None => println!("Source '{id}' now follows the installed adapter"),
+            }
+        }
         SourceCommand::Cleanup { auto_remove } => {
             let sources = db.list_sources().await?;
 
@@ -2860,7 +5741,7 @@ async fn cmd_source(
     Ok(())
 }
 
-fn cmd_adapters(
+async fn cmd_adapters(
     runner: &AdapterRunner,
     config: &Config,
     config_path: &Path,
@@ -2899,6 +5780,17 @@ fn cmd_adapters(
                 }
             }
         }
+        AdapterCommand::Info { name } => {
+            let details = adapter_details(runner, &config, &name).await?;
+            if json {
+                return emit_json(JsonResponse {
+                    ok: true,
+                    result: Some(details),
+                    error: None,
+                });
+            }
+            print_adapter_details(&details);
+        }
         AdapterCommand::Add { path, input } => {
             let input = read_input::<AdapterAddInput>(input)?;
             let path = input
@@ -2906,6 +5798,9 @@ fn cmd_adapters(
                 .map(|v| PathBuf::from(&v.path))
                 .unwrap_or(path);
             let expanded = Config::expand_path(&path.to_string_lossy());
+            if expanded.is_dir() {
+                adapter_manifest::validate_specs_in(&expanded)?;
+            }
             if !config.adapter_paths.contains(&expanded) {
                 config.adapter_paths.push(expanded);
                 config.save_to_path(config_path)?;
@@ -2966,6 +5861,54 @@ fn cmd_adapters(
             }
             println!("Disabled adapter: {name}");
         }
+        AdapterCommand::Logs {
+            adapter,
+            last,
+            limit,
+        } => {
+            let log_dir = config.adapter_log_dir();
+            let mut logs = hstry_runtime::logs::list_logs(&log_dir, adapter.as_deref());
+            if last {
+                let Some(log) = logs.first() else {
+                    anyhow::bail!("No adapter logs in {}", log_dir.display());
+                };
+                let content = std::fs::read_to_string(&log.path)?;
+                if json {
+                    return emit_json(JsonResponse {
+                        ok: true,
+                        result: Some(serde_json::json!({ "log": log, "content": content })),
+                        error: None,
+                    });
+                }
+                println!("{}", log.path.display());
+                print!("{content}");
+                return Ok(());
+            }
+            logs.truncate(limit);
+            if json {
+                return emit_json(JsonResponse {
+                    ok: true,
+                    result: Some(logs),
+                    error: None,
+                });
+            }
+            if logs.is_empty() {
+                println!("No adapter logs in {}", log_dir.display());
+            }
+            for log in logs {
+                let at = chrono::DateTime::from_timestamp_millis(log.timestamp_ms)
+                    .map(|dt| dt.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S"))
+                    .map(|dt| dt.to_string())
+                    .unwrap_or_default();
+                println!(
+                    "{at}  {adapter:<14} {method:<12} {size:>7}  {path}",
+                    adapter = log.adapter,
+                    method = log.method,
+                    size = format_bytes(log.size_bytes),
+                    path = log.path.display()
+                );
+            }
+        }
         AdapterCommand::Update {
             adapter,
             repo,
@@ -3038,7 +5981,7 @@ fn cmd_adapters(
 
             for repo in &repos_to_update {
                 let repo_result =
-                    update_repo_adapters(repo, &adapter_root, adapter.as_deref(), force)?;
+                    update_repo_adapters(repo, &adapter_root, adapter.as_deref(), force).await?;
                 updated_repos.push(repo_result);
             }
 
@@ -3058,9 +6001,10 @@ fn cmd_adapters(
             println!("Updated adapters in {}", adapter_root.display());
             for repo_result in &updated_repos {
                 println!(
-                    "  {name}: {count} adapters",
+                    "  {name}: {count} adapters updated, {current} up to date",
                     name = repo_result.name,
-                    count = repo_result.adapters.len()
+                    count = repo_result.adapters.len(),
+                    current = repo_result.up_to_date.len()
                 );
             }
         }
@@ -3072,10 +6016,100 @@ fn cmd_adapters(
     Ok(())
 }
 
+async fn adapter_details(
+    runner: &AdapterRunner,
+    config: &Config,
+    name: &str,
+) -> Result<AdapterDetails> {
+    let Some(path) = runner.find_adapter(name) else {
+        anyhow::bail!("Adapter not found: {name}. Run 'hstry adapters list'.");
+    };
+    let adapter_dir = path.parent().unwrap_or(&path);
+    let spec = adapter_manifest::read_spec(adapter_dir)?;
+    let (info, info_error) = match runner.get_info(&path).await {
+        Ok(info) => (Some(info), None),
+        Err(err) => (None, Some(err.to_string())),
+    };
+    let locked = adapter_dir
+        .parent()
+        .and_then(|root| adapter_manifest::read_lock(root).ok())
+        .and_then(|mut lock| lock.adapters.remove(name))
+        .unwrap_or_default();
+    let runtimes = adapter_manifest::runtime_requirements(&config.adapter_paths)
+        .remove(name)
+        .unwrap_or_default();
+
+    Ok(AdapterDetails {
+        name: name.to_string(),
+        display_name: info.as_ref().map(|info| info.display_name.clone()),
+        version: info
+            .as_ref()
+            .map(|info| info.version.clone())
+            .or_else(|| spec.as_ref().map(|spec| spec.version.clone()))
+            .or_else(|| hstry_runtime::runner::adapter_version(&path)),
+        enabled: config.adapter_enabled(name),
+        capabilities: spec.as_ref().map(|spec| spec.capabilities.clone()),
+        min_runtime: spec.map(|spec| spec.min_runtime).unwrap_or_default(),
+        runtimes,
+        default_paths: info.map(|info| info.default_paths).unwrap_or_default(),
+        source: locked.source,
+        versions: locked.versions,
+        info_error,
+        path,
+    })
+}
+
+fn print_adapter_details(details: &AdapterDetails) {
+    match &details.display_name {
+        Some(display_name) => println!("{} ({display_name})", details.name),
+        None => println!("{}", details.name),
+    }
+    let field = |label: &str, value: &str| println!("  {label:<14} {value}");
+    field("Version:", details.version.as_deref().unwrap_or("unknown"));
+    field("Path:", &details.path.display().to_string());
+    field("Enabled:", if details.enabled { "yes" } else { "no" });
+    let capabilities = details.capabilities.as_ref().map_or_else(
+        || "unknown (no adapter.json)".to_string(),
+        |capabilities| {
+            capabilities
+                .iter()
+                .map(|capability| capability.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        },
+    );
+    field("Capabilities:", &capabilities);
+    if !details.min_runtime.is_empty() {
+        let min_runtime = details
+            .min_runtime
+            .iter()
+            .map(|(runtime, version)| format!("{runtime} {version}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        field("Min runtime:", &min_runtime);
+    }
+    if !details.runtimes.is_empty() {
+        field("Runtimes:", &details.runtimes.join(", "));
+    }
+    for (i, default_path) in details.default_paths.iter().enumerate() {
+        field(if i == 0 { "Default paths:" } else { "" }, default_path);
+    }
+    field("Source:", details.source.as_deref().unwrap_or("unknown"));
+    if !details.versions.is_empty() {
+        field("Versions:", &details.versions.join(", "));
+    }
+    if let Some(err) = &details.info_error {
+        field("Info failed:", err);
+    }
+}
+
 #[derive(Debug, Serialize)]
 struct RepoUpdateResult {
     name: String,
     adapters: Vec<String>,
+    /// Adapters skipped because the same version from the same source is
+    /// already installed.
+    up_to_date: Vec<String>,
     source: String,
 }
 
@@ -3091,15 +6125,16 @@ fn adapter_root_dir(config: &Config) -> Result<PathBuf> {
     Ok(config_dir.join("adapters"))
 }
 
-fn update_repo_adapters(
+async fn update_repo_adapters(
     repo: &AdapterRepo,
     adapter_root: &Path,
     filter: Option<&str>,
     force: bool,
 ) -> Result<RepoUpdateResult> {
-    match &repo.source {
+    // Keeps a clone or extracted archive alive until the adapters are copied.
+    let temp_dir = tempfile::tempdir()?;
+    let (src_root, source_label) = match &repo.source {
         AdapterRepoSource::Git { url, git_ref, path } => {
-            let temp_dir = tempfile::tempdir()?;
             let target = temp_dir.path();
 
             let mut cmd = ProcessCommand::new("git");
@@ -3117,51 +6152,111 @@ fn update_repo_adapters(
                 anyhow::bail!("Failed to clone adapters repo: {stderr}");
             }
 
-            let src_root = target.join(path);
-            let source_label = format!("git:{url}@{git_ref}");
-            let adapters = copy_adapters_from(&src_root, adapter_root, filter, force)?;
-
-            Ok(RepoUpdateResult {
-                name: repo.name.clone(),
-                adapters,
-                source: source_label,
-            })
-        }
-        AdapterRepoSource::Local { path } => {
-            let src_root = PathBuf::from(path);
-            let source_label = format!("local:{path}");
-            let adapters = copy_adapters_from(&src_root, adapter_root, filter, force)?;
-
-            Ok(RepoUpdateResult {
-                name: repo.name.clone(),
-                adapters,
-                source: source_label,
-            })
+            (target.join(path), format!("git:{url}@{git_ref}"))
         }
-        AdapterRepoSource::Archive { url, .. } => {
-            anyhow::bail!("Archive adapter repositories are not supported yet: {url}");
+        AdapterRepoSource::Archive { url, path } => {
+            let bytes = download_archive(url).await?;
+            extract_archive(url, &bytes, temp_dir.path())?;
+            (
+                archive_source_root(temp_dir.path(), path),
+                format!("archive:{url}"),
+            )
         }
+        AdapterRepoSource::Local { path } => (PathBuf::from(path), format!("local:{path}")),
+    };
+
+    let (adapters, up_to_date) =
+        copy_adapters_from(&src_root, adapter_root, filter, force, &source_label)?;
+
+    Ok(RepoUpdateResult {
+        name: repo.name.clone(),
+        adapters,
+        up_to_date,
+        source: source_label,
+    })
+}
+
+async fn download_archive(url: &str) -> Result<Vec<u8>> {
+    if let Some(path) = url.strip_prefix("file://") {
+        return std::fs::read(path)
+            .with_context(|| format!("Failed to read adapter archive {path}"));
+    }
+    let response = reqwest::get(url)
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .with_context(|| format!("Failed to download adapter archive {url}"))?;
+    Ok(response.bytes().await?.to_vec())
+}
+
+/// Unpack an adapter archive into `dest`, picking the format from the URL.
+/// Both extractors refuse entries that would land outside `dest`.
+fn extract_archive(url: &str, bytes: &[u8], dest: &Path) -> Result<()> {
+    let name = url
+        .split(['?', '#'])
+        .next()
+        .unwrap_or(url)
+        .to_ascii_lowercase();
+    if name.ends_with(".zip") {
+        zip::ZipArchive::new(std::io::Cursor::new(bytes))?.extract(dest)?;
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        tar::Archive::new(flate2::read::GzDecoder::new(bytes)).unpack(dest)?;
+    } else if name.ends_with(".tar.zst") || name.ends_with(".tzst") {
+        tar::Archive::new(zstd::Decoder::new(bytes)?).unpack(dest)?;
+    } else if name.ends_with(".tar") {
+        tar::Archive::new(bytes).unpack(dest)?;
+    } else {
+        anyhow::bail!(
+            "Unsupported adapter archive format: {url} (expected .zip, .tar.gz, .tgz, .tar.zst or .tar)"
+        );
+    }
+    Ok(())
+}
+
+/// Where `path` is in an extracted archive. Archives from code hosts wrap
+/// everything in one top-level directory (`hstry-0.5.0/adapters`), so
+/// `path` is looked up inside it when it is not at the top.
+fn archive_source_root(extracted: &Path, path: &str) -> PathBuf {
+    let direct = extracted.join(path);
+    if direct.exists() {
+        return direct;
+    }
+    let mut dirs = std::fs::read_dir(extracted)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|entry| entry.is_dir());
+    match (dirs.next(), dirs.next()) {
+        (Some(only), None) => only.join(path),
+        _ => direct,
     }
 }
 
+/// Copy adapters from `src_root` into `dest_root` and record them in the
+/// lockfile. Returns the adapters copied and those skipped as up to date.
 fn copy_adapters_from(
     src_root: &Path,
     dest_root: &Path,
     filter: Option<&str>,
     force: bool,
-) -> Result<Vec<String>> {
+    source_label: &str,
+) -> Result<(Vec<String>, Vec<String>)> {
     let mut adapters = Vec::new();
 
     if !src_root.exists() {
         anyhow::bail!("Adapter source path not found: {}", src_root.display());
     }
 
-    let manifest_path = src_root.join(".hstry-adapters.json");
-    if !manifest_path.exists() {
+    let Some(src_manifest) = adapter_manifest::read_manifest(src_root)? else {
         anyhow::bail!(
             "Adapter manifest missing at {}. Ensure the repo matches the hstry version.",
-            manifest_path.display()
+            src_root.join(".hstry-adapters.json").display()
         );
+    };
+    if !adapter_manifest::unpinned_allowed() {
+        adapter_manifest::ensure_compatible(&src_manifest).with_context(|| {
+            format!("Adapters from {source_label} do not match this hstry version")
+        })?;
     }
 
     let mut items = Vec::new();
@@ -3180,6 +6275,48 @@ fn copy_adapters_from(
         }
     }
 
+    let mut up_to_date = Vec::new();
+    if !force {
+        let lock = adapter_manifest::read_lock(dest_root).unwrap_or_default();
+        items.retain(|item| {
+            let current = item != "types"
+                && adapter_manifest::is_current(
+                    &lock,
+                    dest_root,
+                    item,
+                    &src_root.join(item),
+                    source_label,
+                );
+            if current {
+                up_to_date.push(item.clone());
+            }
+            !current
+        });
+        if items.is_empty() {
+            return Ok((adapters, up_to_date));
+        }
+    }
+
+    for item in &items {
+        let src_dir = src_root.join(item);
+        if item == "types" || !src_dir.join("adapter.ts").exists() {
+            continue;
+        }
+        let spec = adapter_manifest::validate_spec(&src_dir)
+            .with_context(|| format!("Adapter '{item}' from {source_label} is invalid"))?;
+        if spec.is_none() {
+            tracing::warn!("Adapter '{item}' from {source_label} has no adapter.json");
+        }
+    }
+
+    // Snapshot what is installed now (before `types` is replaced) so sources
+    // pinned to the current version keep working after the update.
+    for item in &items {
+        if item != "types" && dest_root.join(item).join("adapter.ts").exists() {
+            let _ = adapter_manifest::snapshot_installed(dest_root, item, None);
+        }
+    }
+
     let mut entries_to_copy = Vec::new();
     if !items.iter().any(|item| item == "types") {
         entries_to_copy.push("types".to_string());
@@ -3206,14 +6343,38 @@ fn copy_adapters_from(
         }
     }
 
+    for adapter in &adapters {
+        if let Err(err) =
+            adapter_manifest::snapshot_installed(dest_root, adapter, Some(source_label))
+        {
+            tracing::warn!("Not recording a version for adapter '{adapter}': {err}");
+        }
+    }
+
+    // Carry runtime requirements over for the adapters just copied; keep
+    // the rest from the existing manifest.
+    let mut runtimes = adapter_manifest::read_manifest(dest_root)
+        .ok()
+        .flatten()
+        .map(|manifest| manifest.runtimes)
+        .unwrap_or_default();
+    let src_runtimes = src_manifest.runtimes;
+    for adapter in &adapters {
+        match src_runtimes.get(adapter) {
+            Some(kinds) => runtimes.insert(adapter.clone(), kinds.clone()),
+            None => runtimes.remove(adapter),
+        };
+    }
+
     let dest_manifest = dest_root.join(".hstry-adapters.json");
     let manifest = adapter_manifest::AdapterManifest {
         hstry_version: adapter_manifest::expected_hstry_version(),
         protocol_version: adapter_manifest::ADAPTER_PROTOCOL_VERSION.to_string(),
+        runtimes,
     };
     std::fs::write(&dest_manifest, serde_json::to_string_pretty(&manifest)?)?;
 
-    Ok(adapters)
+    Ok((adapters, up_to_date))
 }
 
 fn copy_dir_recursive(src: &Path, dest: &Path) -> Result<()> {
@@ -3416,10 +6577,7 @@ async fn cmd_web_sync(
 
     let mut results = Vec::new();
 
-    let runtime = Runtime::parse(&config.js_runtime).ok_or_else(|| {
-        anyhow::anyhow!("No JavaScript runtime found. Install bun, deno, or node.")
-    })?;
-    let runner = AdapterRunner::new(runtime, config.adapter_paths.clone());
+    let runner = adapter_runner(&config)?;
 
     for provider in providers {
         let storage_state = web_sessions_dir()?.join(format!("{provider}.json"));
@@ -3456,13 +6614,26 @@ async fn cmd_web_sync(
                 adapter: adapter.to_string(),
                 path: export_path.to_string_lossy().to_string(),
                 auto_sync: true,
+                options: BTreeMap::new(),
+                adapter_version: None,
+                filter: Default::default(),
             });
             config.save_to_path(config_path)?;
         }
 
         ensure_config_sources(db, &runner, &config).await?;
-        let stats =
-            sync_sources(db, &runner, &config, Some(source_id.clone()), None, !json).await?;
+        let stats = sync_sources(
+            db,
+            &runner,
+            &config,
+            Some(source_id.clone()),
+            None,
+            None,
+            false,
+            SyncOutput::human_unless(json),
+            None,
+        )
+        .await?;
 
         let summary = SyncSummary {
             total_sources: stats.len(),
@@ -3696,7 +6867,7 @@ async fn cmd_quickstart(
     let mut config = config.clone();
 
     if adapter_manifest::validate_adapter_manifest(&config.adapter_paths).is_err() {
-        ensure_adapter_updates(&mut config, config_path, json)?;
+        ensure_adapter_updates(&mut config, config_path, json).await?;
     }
 
     let hits = scan_hits(runner, &config).await?;
@@ -3772,6 +6943,9 @@ async fn cmd_quickstart(
             adapter: hit.adapter.clone(),
             path: hit.path.clone(),
             auto_sync: true,
+            options: BTreeMap::new(),
+            adapter_version: None,
+            filter: Default::default(),
         });
 
         sources_added.push(serde_json::json!({
@@ -3787,7 +6961,18 @@ async fn cmd_quickstart(
 
     ensure_config_sources(db, runner, &config).await?;
 
-    let stats = sync_sources(db, runner, &config, None, None, !json).await?;
+    let stats = sync_sources(
+        db,
+        runner,
+        &config,
+        None,
+        None,
+        None,
+        false,
+        SyncOutput::human_unless(json),
+        None,
+    )
+    .await?;
     let sync_summary = SyncSummary {
         total_sources: stats.len(),
         total_conversations: stats.iter().map(|s| s.conversations).sum(),
@@ -3829,7 +7014,7 @@ fn generate_source_id(config: &Config, adapter: &str) -> String {
     candidate
 }
 
-fn ensure_adapter_updates(config: &mut Config, config_path: &Path, json: bool) -> Result<()> {
+async fn ensure_adapter_updates(config: &mut Config, config_path: &Path, json: bool) -> Result<()> {
     let expected_ref = format!("v{}", env!("CARGO_PKG_VERSION"));
     let mut repos_to_update: Vec<_> = config
         .adapter_repos
@@ -3870,7 +7055,7 @@ fn ensure_adapter_updates(config: &mut Config, config_path: &Path, json: bool) -
 
     let mut updated_repos = Vec::new();
     for repo in &repos_to_update {
-        let repo_result = update_repo_adapters(repo, &adapter_root, None, false)?;
+        let repo_result = update_repo_adapters(repo, &adapter_root, None, false).await?;
         updated_repos.push(repo_result);
     }
 
@@ -3880,9 +7065,10 @@ fn ensure_adapter_updates(config: &mut Config, config_path: &Path, json: bool) -
         println!("Updated adapters in {}", adapter_root.display());
         for repo_result in &updated_repos {
             println!(
-                "  {name}: {count} adapters",
+                "  {name}: {count} adapters updated, {current} up to date",
                 name = repo_result.name,
-                count = repo_result.adapters.len()
+                count = repo_result.adapters.len(),
+                current = repo_result.up_to_date.len()
             );
         }
     }
@@ -4196,6 +7382,8 @@ fn upsert_adapter_config(config: &mut Config, name: &str, enabled: bool) {
         config.adapters.push(hstry_core::config::AdapterConfig {
             name: name.to_string(),
             enabled,
+            runtime: None,
+            timeout_secs: None,
         });
     }
 }
@@ -4262,7 +7450,13 @@ mod tests {
     use std::io::Write;
 
     #[test]
-    fn default_log_filter_suppresses_sqlx_slow_query_warnings_without_verbose() {
+    fn cli_definition_is_valid() {
+        use clap::CommandFactory;
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn default_log_filter_suppresses_sqlx_slow_query_warnings_without_verbose() {
         assert_eq!(
             default_log_filter(0),
             "warn,hstry_cli=info,hstry_core=info,sqlx=error"
@@ -4274,6 +7468,30 @@ mod tests {
         assert_eq!(default_log_filter(2), "trace");
     }
 
+    #[test]
+    fn sync_progress_events_are_flat_json_lines() {
+        let event = SyncEvent::Progress {
+            source_id: "codex",
+            progress: sync::SyncProgress {
+                phase: sync::SyncPhase::Written,
+                parsed: 250,
+                conversations: 200,
+                messages: 1800,
+            },
+        };
+        assert_eq!(
+            serde_json::to_value(&event).expect("serialize"),
+            serde_json::json!({
+                "event": "progress",
+                "source_id": "codex",
+                "phase": "written",
+                "parsed": 250,
+                "conversations": 200,
+                "messages": 1800,
+            })
+        );
+    }
+
     #[test]
     fn read_input_reads_json_file() {
         let mut file =
@@ -4324,30 +7542,71 @@ mod tests {
     }
 
     #[test]
-    fn parse_date_filter_accepts_relative_durations() {
-        let now = chrono::Utc::now();
-        let cases = [
-            ("2", chrono::Duration::days(2)),
-            ("1w", chrono::Duration::weeks(1)),
-            ("2d", chrono::Duration::days(2)),
-            ("3h", chrono::Duration::hours(3)),
-            ("30m", chrono::Duration::minutes(30)),
-            ("2mo", chrono::Duration::days(60)),
-            ("1y", chrono::Duration::days(365)),
-            ("1 week", chrono::Duration::weeks(1)),
-            ("2 days", chrono::Duration::days(2)),
-            ("3 hours ago", chrono::Duration::hours(3)),
+    fn piped_input_is_named_after_its_format() {
+        assert_eq!(stdin_suffix(b"PK\x03\x04rest"), ".zip");
+        assert_eq!(stdin_suffix(b"[{\"title\": \"a\"}]"), ".json");
+        assert_eq!(stdin_suffix(b"{\n  \"title\": \"a\"\n}\n"), ".json");
+        assert_eq!(stdin_suffix(b"{\"a\": 1}\n{\"a\": 2}\n"), ".jsonl");
+        assert_eq!(stdin_suffix(b"{\"a\": 1}\n"), ".json");
+    }
+
+    #[test]
+    fn fuzzy_duplicates_are_only_paired_with_a_similar_kept_conversation() {
+        use hstry_core::near_duplicates::{self, Signature};
+
+        // A ~ B and B ~ C, but A and C share too little.
+        let text = |words: std::ops::Range<usize>| {
+            words
+                .map(|i| format!("word{i}"))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        let candidate = |words, messages| DedupCandidate {
+            conversation: Conversation {
+                id: uuid::Uuid::new_v4(),
+                source_id: "codex".to_string(),
+                external_id: None,
+                readable_id: None,
+                platform_id: None,
+                title: None,
+                created_at: chrono::Utc::now(),
+                updated_at: None,
+                model: None,
+                provider: None,
+                workspace: None,
+                tokens_in: None,
+                tokens_out: None,
+                cost_usd: None,
+                metadata: serde_json::json!({}),
+                harness: None,
+                version: 0,
+                message_count: 0,
+                parent_conversation_id: None,
+                parent_message_idx: None,
+                fork_type: None,
+            },
+            messages,
+            signature: Signature::new(&text(words)),
+        };
+        let candidates = vec![
+            candidate(0..100, 3),
+            candidate(20..120, 2),
+            candidate(40..140, 1),
         ];
-        for (input, expected) in cases {
-            let parsed =
-                parse_date_filter(input).unwrap_or_else(|err| panic!("parse '{input}': {err}"));
-            // Bare numbers snap to start-of-day, so allow a full day of slack.
-            let delta = ((now - expected) - parsed).num_seconds().abs();
-            assert!(
-                delta <= 86_400,
-                "input '{input}' parsed too far from expected (delta {delta}s)"
-            );
-        }
+        let threshold = 0.55;
+        let signatures: Vec<_> = candidates.iter().map(|c| c.signature.clone()).collect();
+        let pairs = near_duplicates::similar_pairs(&signatures, threshold);
+        let chain: Vec<_> = pairs.iter().map(|&(a, b, _)| (a, b)).collect();
+        assert_eq!(chain, vec![(0, 1), (1, 2)]);
+        let groups = near_duplicates::clusters(candidates.len(), chain);
+        assert_eq!(groups, vec![vec![0, 1, 2]]);
+
+        let (found, pairs) = dedup_pairs(&candidates, groups, &HashSet::new(), threshold);
+        assert_eq!(found, 1);
+        assert_eq!(pairs.len(), 1);
+        assert_eq!(pairs[0].keep, candidates[0].conversation.id);
+        assert_eq!(pairs[0].duplicate, candidates[1].conversation.id);
+        assert!(pairs[0].similarity >= threshold);
     }
 }
 
@@ -4359,27 +7618,46 @@ async fn cmd_export(
     source_filter: Option<String>,
     workspace_filter: Option<String>,
     role_filter: Vec<SearchRoleArg>,
+    since: Option<chrono::DateTime<chrono::Utc>>,
+    incremental: bool,
     output: Option<PathBuf>,
     session_files: bool,
     pretty: bool,
+    min_similarity: f32,
+    redactor: Option<&share::Redactor>,
     json_output: bool,
 ) -> Result<()> {
     use hstry_core::db::ListConversationsOptions;
     use std::fs;
 
-    // Find the adapter for the target format
-    // For universal formats (markdown, json), use any available adapter
-    let adapter_path = if format == "markdown" || format == "json" {
-        // Try to use the first available adapter that supports export
-        let adapters = runner.list_adapters();
-        adapters
-            .into_iter()
-            .find_map(|name| runner.find_adapter(&name))
-            .ok_or_else(|| anyhow::anyhow!("No adapters available for export"))?
+    // Incremental exports rewrite some files in a directory of one file per
+    // conversation; anything else would lose the unchanged conversations.
+    let native = format.parse::<ExportFormat>().ok();
+    let incremental_target = if incremental {
+        if json_output {
+            anyhow::bail!("--incremental writes files and cannot be combined with --json");
+        }
+        if format == "html"
+            || format == hstry_archive::FORMAT
+            || GraphFormat::from_name(format).is_some()
+        {
+            anyhow::bail!(
+                "--incremental works with markdown, json, jsonl and adapter formats that write one file per conversation"
+            );
+        }
+        if native.is_some() && !session_files {
+            anyhow::bail!("--incremental needs --session-files for {format} exports");
+        }
+        let dir = output.clone().unwrap_or_else(|| PathBuf::from("."));
+        fs::create_dir_all(&dir)?;
+        Some(fs::canonicalize(&dir)?.to_string_lossy().into_owned())
     } else {
-        runner
-            .find_adapter(format)
-            .ok_or_else(|| anyhow::anyhow!("No adapter found for format '{format}'"))?
+        None
+    };
+    let started_at = chrono::Utc::now();
+    let since = match &incremental_target {
+        Some(target) => db.export_mark(target, format).await?,
+        None => since,
     };
 
     // Load conversations from database
@@ -4391,7 +7669,15 @@ async fn cmd_export(
             workspace: workspace_filter.clone(),
             after: None,
             before: None,
+            ingested_after: None,
+            model: None,
+            provider: None,
+            harness: None,
+            role: None,
+            exclude_sensitive: false,
+            starred: false,
             limit: None,
+            offset: None,
         })
         .await?
     } else {
@@ -4405,6 +7691,19 @@ async fn cmd_export(
         }
         convs
     };
+    let conversations = if let Some(since) = since {
+        let changed: HashSet<uuid::Uuid> = db
+            .changed_conversation_ids(since)
+            .await?
+            .into_iter()
+            .collect();
+        conversations
+            .into_iter()
+            .filter(|conv| changed.contains(&conv.id))
+            .collect()
+    } else {
+        conversations
+    };
 
     if conversations.is_empty() {
         if json_output {
@@ -4414,29 +7713,149 @@ async fn cmd_export(
                 error: Some("No conversations found".to_string()),
             });
         }
-        println!("No conversations found");
+        if let Some(target) = &incremental_target {
+            println!("No conversations changed since the last export to {target}");
+            return Ok(());
+        }
+        // stderr, so an empty `export --format jsonl` stream stays empty.
+        eprintln!("No conversations found");
+        return Ok(());
+    }
+
+    if redactor.is_some()
+        && (format == "html"
+            || format == hstry_archive::FORMAT
+            || GraphFormat::from_name(format).is_some())
+    {
+        anyhow::bail!("--share-safe works with markdown, json, jsonl and adapter formats");
+    }
+
+    if format == hstry_archive::FORMAT {
+        if !role_filter.is_empty() {
+            anyhow::bail!("--role does not apply to {format} exports, which keep every message");
+        }
+        let output = output.unwrap_or_else(|| {
+            PathBuf::from(format!(
+                "hstry-{}.tar.zst",
+                chrono::Local::now().format("%Y%m%d")
+            ))
+        });
+        let written = hstry_archive::write_archive(db, &conversations, &output).await?;
+        if json_output {
+            return emit_json(JsonResponse {
+                ok: true,
+                result: Some(serde_json::json!({
+                    "format": format,
+                    "output": output,
+                    "archive": written,
+                })),
+                error: None,
+            });
+        }
+        let manifest = &written.manifest;
+        println!(
+            "Exported {} conversations ({} messages, {} attachments) to {}",
+            manifest.conversations,
+            manifest.messages,
+            manifest.attachments,
+            output.display()
+        );
+        if written.skipped_sensitive > 0 {
+            println!(
+                "Skipped {} sensitive conversation(s).",
+                written.skipped_sensitive
+            );
+        }
+        return Ok(());
+    }
+
+    if format == "html" {
+        let output_dir = output.unwrap_or_else(|| PathBuf::from("site"));
+        let mut site = Vec::with_capacity(conversations.len());
+        for conversation in conversations {
+            let messages = db
+                .get_messages(conversation.id)
+                .await?
+                .into_iter()
+                .filter(|m| role_matches(&role_filter, &m.role))
+                .collect();
+            site.push(html_export::SiteConversation {
+                conversation,
+                messages,
+            });
+        }
+        let pages = html_export::write_site(&output_dir, &site)?;
+        if json_output {
+            return emit_json(JsonResponse {
+                ok: true,
+                result: Some(serde_json::json!({
+                    "format": "html",
+                    "output": output_dir,
+                    "conversations": pages,
+                })),
+                error: None,
+            });
+        }
+        println!(
+            "Exported {pages} conversations to {}",
+            output_dir.join("index.html").display()
+        );
+        return Ok(());
+    }
+
+    if let Some(graph_format) = GraphFormat::from_name(format) {
+        let options = GraphOptions {
+            min_similarity,
+            ..GraphOptions::default()
+        };
+        return export_graph(
+            db,
+            graph_format,
+            conversations,
+            &options,
+            output,
+            json_output,
+        )
+        .await;
+    }
+
+    if let Some(native) = native {
+        export_native(
+            db,
+            native,
+            &conversations,
+            &role_filter,
+            output,
+            session_files,
+            incremental,
+            pretty,
+            redactor,
+            json_output,
+        )
+        .await?;
+        if let Some(target) = &incremental_target {
+            db.set_export_mark(target, format, started_at).await?;
+        }
         return Ok(());
     }
 
+    let adapter_path = runner
+        .find_adapter(format)
+        .ok_or_else(|| anyhow::anyhow!("No adapter found for format '{format}'"))?;
+
     // Convert to export format
     let mut export_convs = Vec::new();
     for conv in &conversations {
-        let messages = db.get_messages(conv.id).await?;
+        // Adapter formats are linear; export the active branch.
+        let messages = branches::active_thread(db.get_messages(conv.id).await?);
+        let tool_calls = tool_calls::by_message(db.get_tool_calls(conv.id).await?);
         let parsed_messages: Vec<ParsedMessage> = messages
             .into_iter()
-            .filter(|m| {
-                // Filter by role if specified
-                if role_filter.is_empty() {
-                    return true;
-                }
-                role_filter.iter().any(|r| match r {
-                    SearchRoleArg::User => m.role == MessageRole::User,
-                    SearchRoleArg::Assistant => m.role == MessageRole::Assistant,
-                    SearchRoleArg::System => m.role == MessageRole::System,
-                    SearchRoleArg::Tool => m.role == MessageRole::Tool,
-                })
-            })
+            .filter(|m| role_matches(&role_filter, &m.role))
             .map(|m| ParsedMessage {
+                tool_calls: tool_calls
+                    .get(&m.id)
+                    .map(|calls| calls.iter().map(tool_calls::to_parsed).collect()),
                 role: m.role.to_string(),
                 content: m.content,
                 created_at: m.created_at.map(|dt| dt.timestamp_millis()),
@@ -4444,12 +7863,12 @@ async fn cmd_export(
                 tokens: m.tokens,
                 cost_usd: m.cost_usd,
                 parts: Some(m.parts_json),
-                tool_calls: None, // TODO: load from tool_calls table
                 metadata: Some(m.metadata),
+                parent_idx: None,
             })
             .collect();
 
-        export_convs.push(ExportConversation {
+        let mut export_conv = ExportConversation {
             external_id: conv.external_id.clone(),
             readable_id: conv.readable_id.clone(),
             title: conv.title.clone(),
@@ -4465,54 +7884,16 @@ async fn cmd_export(
             metadata: Some(conv.metadata.clone()),
             version: Some(u64::try_from(conv.version).unwrap_or(0)),
             message_count: Some(u32::try_from(conv.message_count).unwrap_or(0)),
-        });
-    }
-
-    if !json_output && session_files && (format == "markdown" || format == "json") {
-        let output_dir = output.unwrap_or_else(|| PathBuf::from("."));
-        fs::create_dir_all(&output_dir)?;
-
-        let mut written = 0usize;
-        for (index, conv) in export_convs.iter().enumerate() {
-            let single_result = runner
-                .export(
-                    &adapter_path,
-                    vec![conv.clone()],
-                    ExportOptions {
-                        format: format.to_string(),
-                        pretty: Some(pretty),
-                        include_tools: Some(true),
-                        include_attachments: Some(true),
-                    },
-                )
-                .await?;
-
-            if let Some(content) = &single_result.content {
-                let filename = build_session_export_filename(conv, index, format);
-                fs::write(output_dir.join(filename), content)?;
-                written += 1;
-                continue;
-            }
-
-            if let Some(files) = &single_result.files {
-                for file in files {
-                    let file_path = output_dir.join(&file.path);
-                    if let Some(parent) = file_path.parent() {
-                        fs::create_dir_all(parent)?;
-                    }
-                    fs::write(&file_path, &file.content)?;
-                    written += 1;
-                }
-            }
+        };
+        if let Some(redactor) = redactor {
+            export_conv.messages =
+                share::share_safe_messages(std::mem::take(&mut export_conv.messages), redactor);
+            export_conv.title = export_conv.title.as_deref().map(|t| redactor.redact(t));
+            export_conv.workspace = export_conv.workspace.as_deref().map(share::workspace_name);
+            export_conv.metadata = None;
+            export_conv.message_count = u32::try_from(export_conv.messages.len()).ok();
         }
-
-        println!(
-            "Exported {} conversations to {} files in {}",
-            conversations.len(),
-            written,
-            output_dir.display()
-        );
-        return Ok(());
+        export_convs.push(export_conv);
     }
 
     let opts = ExportOptions {
@@ -4523,6 +7904,9 @@ async fn cmd_export(
     };
 
     let result = runner.export(&adapter_path, export_convs, opts).await?;
+    if incremental_target.is_some() && result.files.is_none() {
+        anyhow::bail!("--incremental needs a format that writes one file per conversation");
+    }
 
     if json_output {
         return emit_json(JsonResponse {
@@ -4553,6 +7937,9 @@ async fn cmd_export(
             }
             fs::write(&file_path, &file.content)?;
         }
+        if let Some(target) = &incremental_target {
+            db.set_export_mark(target, format, started_at).await?;
+        }
         println!(
             "Exported {} conversations to {} files in {}",
             conversations.len(),
@@ -4564,13 +7951,249 @@ async fn cmd_export(
     Ok(())
 }
 
-fn build_session_export_filename(conv: &ExportConversation, index: usize, format: &str) -> String {
-    let ext = match format {
-        "markdown" => "md",
-        "json" => "json",
-        _ => "txt",
+/// True when `role` passes the `--role` filter (an empty filter keeps all).
+fn role_matches(filter: &[SearchRoleArg], role: &MessageRole) -> bool {
+    filter.is_empty()
+        || filter.iter().any(|r| match r {
+            SearchRoleArg::User => *role == MessageRole::User,
+            SearchRoleArg::Assistant => *role == MessageRole::Assistant,
+            SearchRoleArg::System => *role == MessageRole::System,
+            SearchRoleArg::Tool => *role == MessageRole::Tool,
+        })
+}
+
+/// Export markdown, JSON or JSONL without going through an adapter.
+async fn export_native(
+    db: &Database,
+    format: ExportFormat,
+    conversations: &[Conversation],
+    role_filter: &[SearchRoleArg],
+    output: Option<PathBuf>,
+    session_files: bool,
+    stable_names: bool,
+    pretty: bool,
+    redactor: Option<&share::Redactor>,
+    json_output: bool,
+) -> Result<()> {
+    // Share-safe exports keep one thread, since dropped messages would leave
+    // branch links dangling.
+    let to_parsed = |conv: &Conversation, messages: Vec<Message>| {
+        let Some(redactor) = redactor else {
+            return export::to_parsed(conv, messages);
+        };
+        let mut parsed = export::to_parsed(conv, branches::active_thread(messages));
+        share::share_safe(&mut parsed, redactor);
+        parsed
     };
 
+    // JSONL on stdout is streamed a conversation at a time, which keeps
+    // memory flat and lets `remote sync --delta` ingest while it downloads.
+    if format == ExportFormat::Jsonl && output.is_none() && !session_files && !json_output {
+        let mut stdout = std::io::stdout().lock();
+        for conv in conversations {
+            let messages = db
+                .get_messages(conv.id)
+                .await?
+                .into_iter()
+                .filter(|m| role_matches(role_filter, &m.role))
+                .collect();
+            let line = serde_json::to_string(&to_parsed(conv, messages))?;
+            writeln!(stdout, "{line}")?;
+        }
+        return Ok(());
+    }
+
+    let mut parsed = Vec::with_capacity(conversations.len());
+    for conv in conversations {
+        let messages = db
+            .get_messages(conv.id)
+            .await?
+            .into_iter()
+            .filter(|m| role_matches(role_filter, &m.role))
+            .collect();
+        parsed.push(to_parsed(conv, messages));
+    }
+
+    if !json_output && session_files {
+        let output_dir = output.unwrap_or_else(|| PathBuf::from("."));
+        std::fs::create_dir_all(&output_dir)?;
+        for (index, conv) in parsed.iter().enumerate() {
+            let content = export::render(format, std::slice::from_ref(conv), pretty)?;
+            let filename = if stable_names {
+                stable_session_export_filename(conv, conversations[index].id, format.extension())
+            } else {
+                build_session_export_filename(conv, index, format.extension())
+            };
+            std::fs::write(output_dir.join(filename), content)?;
+        }
+        println!(
+            "Exported {} conversations to {} files in {}",
+            parsed.len(),
+            parsed.len(),
+            output_dir.display()
+        );
+        return Ok(());
+    }
+
+    let content = export::render(format, &parsed, pretty)?;
+    if json_output {
+        return emit_json(JsonResponse {
+            ok: true,
+            result: Some(ExportResult {
+                format: format.as_str().to_string(),
+                content: Some(content),
+                files: None,
+                mime_type: Some(format.mime_type().to_string()),
+                metadata: None,
+            }),
+            error: None,
+        });
+    }
+
+    if let Some(output_path) = output {
+        std::fs::write(&output_path, content)?;
+        println!(
+            "Exported {} conversations to {}",
+            parsed.len(),
+            output_path.display()
+        );
+    } else {
+        print!("{content}");
+    }
+    Ok(())
+}
+
+/// Summarize the conversations of `day` into its daily note
+/// (`--format daily-notes`).
+async fn cmd_export_daily_notes(
+    db: &Database,
+    config: &hstry_core::config::DailyNotesConfig,
+    vault: Option<PathBuf>,
+    day: chrono::NaiveDate,
+    source_filter: Option<String>,
+    workspace_filter: Option<String>,
+    json_output: bool,
+) -> Result<()> {
+    let vault = vault.or_else(|| config.vault.clone()).ok_or_else(|| {
+        anyhow::anyhow!("No vault given: pass --vault or set [daily_notes].vault")
+    })?;
+    if !vault.is_dir() {
+        anyhow::bail!("Vault {} is not a directory", vault.display());
+    }
+    let workspace_filter = workspace_filter.map(|value| format!("%{value}%"));
+    let note = hstry_core::daily_notes::write_day(
+        db,
+        config,
+        &vault,
+        day,
+        source_filter,
+        workspace_filter,
+    )
+    .await?;
+
+    if json_output {
+        return emit_json(JsonResponse {
+            ok: true,
+            result: Some(note),
+            error: None,
+        });
+    }
+    if note.conversations == 0 {
+        println!("No conversations on {day}");
+    } else {
+        println!(
+            "Summarized {} conversations into {}",
+            note.conversations,
+            note.path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Day argument of daily-notes exports, in local time.
+fn parse_note_day(value: &str) -> Result<chrono::NaiveDate> {
+    let today = chrono::Local::now().date_naive();
+    match value.trim().to_lowercase().as_str() {
+        "today" => Ok(today),
+        "yesterday" => today
+            .pred_opt()
+            .ok_or_else(|| anyhow::anyhow!("Could not compute yesterday")),
+        other => chrono::NaiveDate::parse_from_str(other, "%Y-%m-%d").map_err(|_| {
+            anyhow::anyhow!("Invalid day '{value}' (expected today, yesterday or YYYY-MM-DD)")
+        }),
+    }
+}
+
+/// Write the relationship graph of `conversations` (`--format graphml|dot`).
+async fn export_graph(
+    db: &Database,
+    format: GraphFormat,
+    conversations: Vec<Conversation>,
+    options: &GraphOptions,
+    output: Option<PathBuf>,
+    json_output: bool,
+) -> Result<()> {
+    let mut entries = Vec::with_capacity(conversations.len());
+    for conversation in conversations {
+        let mut files = Vec::new();
+        for call in db.get_tool_calls(conversation.id).await? {
+            hstry_core::peek::touched_paths(
+                &call.tool_name.to_lowercase(),
+                call.input.as_ref(),
+                &mut files,
+            );
+        }
+        let text = db
+            .get_messages(conversation.id)
+            .await?
+            .into_iter()
+            .filter(|m| m.role == MessageRole::User && !is_system_context(&m.content))
+            .map(|m| m.content)
+            .collect::<Vec<_>>()
+            .join("\n");
+        entries.push(GraphConversation {
+            conversation,
+            files,
+            text,
+        });
+    }
+    let graph = graph::build(&entries, options);
+    let content = graph.render(format);
+
+    if json_output {
+        let mut result = serde_json::json!({
+            "format": format.as_str(),
+            "nodes": graph.nodes.len(),
+            "edges": graph.edges.len(),
+        });
+        if let Some(output_path) = output {
+            std::fs::write(&output_path, content)?;
+            result["output"] = serde_json::json!(output_path);
+        } else {
+            result["content"] = content.into();
+        }
+        return emit_json(JsonResponse {
+            ok: true,
+            result: Some(result),
+            error: None,
+        });
+    }
+    if let Some(output_path) = output {
+        std::fs::write(&output_path, content)?;
+        println!(
+            "Exported a graph of {} conversations ({} nodes, {} edges) to {}",
+            entries.len(),
+            graph.nodes.len(),
+            graph.edges.len(),
+            output_path.display()
+        );
+    } else {
+        print!("{content}");
+    }
+    Ok(())
+}
+
+fn build_session_export_filename(conv: &ParsedConversation, index: usize, ext: &str) -> String {
     let stem = conv
         .readable_id
         .as_deref()
@@ -4583,6 +8206,19 @@ fn build_session_export_filename(conv: &ExportConversation, index: usize, format
     format!("{:03}_{stem}.{ext}", index + 1)
 }
 
+/// Session file name without the position prefix, so an incremental export
+/// rewrites the same file on every run.
+fn stable_session_export_filename(conv: &ParsedConversation, id: uuid::Uuid, ext: &str) -> String {
+    let stem = conv
+        .readable_id
+        .as_deref()
+        .or(conv.external_id.as_deref())
+        .map(sanitize_filename_segment)
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| id.to_string());
+    format!("{stem}.{ext}")
+}
+
 fn sanitize_filename_segment(input: &str) -> String {
     let mut out = String::with_capacity(input.len());
     let mut last_was_sep = false;
@@ -4600,81 +8236,92 @@ fn sanitize_filename_segment(input: &str) -> String {
     out.trim_matches('-').to_string()
 }
 
-/// Parse a natural-language or ISO date string into a `DateTime<Utc>`.
-///
-/// Supports:
-/// - ISO dates: "2026-03-01", "2026-03-01T10:00:00Z"
-/// - Relative: "today", "yesterday", "N days ago", "N weeks ago", "N months ago"
-/// - Without "ago": "1 week", "2 days", "3 hours"
-/// - Compact: "1w", "2d", "3h", "30m", "2mo", "1y"
-/// - Bare number: "2" (interpreted as N days ago)
-/// - Named: "last week", "last month"
-fn unit_to_duration(n: i64, unit: &str) -> Option<chrono::Duration> {
-    match unit.trim_end_matches('s') {
-        "m" | "min" | "minute" => Some(chrono::Duration::minutes(n)),
-        "h" | "hr" | "hour" => Some(chrono::Duration::hours(n)),
-        "d" | "day" => Some(chrono::Duration::days(n)),
-        "w" | "wk" | "week" => Some(chrono::Duration::weeks(n)),
-        "mo" | "mon" | "month" => Some(chrono::Duration::days(n * 30)),
-        "y" | "yr" | "year" => Some(chrono::Duration::days(n * 365)),
-        _ => None,
-    }
-}
-
-fn parse_date_filter(s: &str) -> Result<chrono::DateTime<chrono::Utc>> {
-    let lower = s.trim().to_lowercase();
-
-    // Handle relative dates that dateparser doesn't support
-    let now = chrono::Utc::now();
-    let start_of_day =
-        |dt: chrono::DateTime<chrono::Utc>| -> Result<chrono::DateTime<chrono::Utc>> {
-            dt.date_naive()
-                .and_hms_opt(0, 0, 0)
-                .map(|value| value.and_utc())
-                .ok_or_else(|| anyhow::anyhow!("Could not compute start-of-day timestamp"))
-        };
-
-    if lower == "today" {
-        return start_of_day(now);
-    }
-    if lower == "yesterday" {
-        return start_of_day(now - chrono::Duration::days(1));
-    }
-    if lower == "last week" {
-        return start_of_day(now - chrono::Duration::weeks(1));
-    }
-    if lower == "last month" {
-        return start_of_day(now - chrono::Duration::days(30));
-    }
+/// Conversations offered by `hstry pick` and `hstry show` without an ID.
+const PICK_LIMIT: i64 = 500;
 
-    // "N days/weeks/months ago" or just "N days/weeks" (with or without "ago")
-    let relative = lower.strip_suffix(" ago").unwrap_or(&lower);
-    let parts: Vec<&str> = relative.split_whitespace().collect();
-    if parts.len() == 2
-        && let Ok(n) = parts[0].parse::<i64>()
-        && let Some(d) = unit_to_duration(n, parts[1])
-    {
-        return Ok(now - d);
-    }
+/// Let the user pick one of the conversations matching `options` with fzf,
+/// previewing each with `hstry show`. Returns `None` when the picker is
+/// cancelled.
+async fn fzf_pick_conversation(
+    db: &Database,
+    options: hstry_core::db::ListConversationsOptions,
+    prompt: &str,
+) -> Result<Option<uuid::Uuid>> {
+    use std::fmt::Write as _;
+    use std::io::Write as _;
+    use std::process::{Command, Stdio};
 
-    // Compact durations like "1w", "2d", "3h", "30m", "2mo", "1y"
-    if let Some(idx) = lower.find(|c: char| c.is_alphabetic())
-        && idx > 0
-        && let Ok(n) = lower[..idx].parse::<i64>()
-        && let Some(d) = unit_to_duration(n, &lower[idx..])
-    {
-        return Ok(now - d);
+    let fzf = which::which("fzf")
+        .map_err(|_| anyhow::anyhow!("fzf not found in PATH; install it to use the picker"))?;
+    let conversations = db.list_conversation_summaries(options).await?;
+    if conversations.is_empty() {
+        anyhow::bail!("No conversations found. Try adjusting filters.");
     }
 
-    // Bare number → N days ago
-    if let Ok(n) = lower.parse::<i64>() {
-        return start_of_day(now - chrono::Duration::days(n));
+    // One "<display>\t<id>" line per conversation: fzf matches on and shows
+    // the display field and hands the ID to the preview.
+    let mut lines = String::new();
+    for cs in &conversations {
+        let conv = &cs.conversation;
+        let title = display_title_for_list(conv.title.as_deref(), cs.first_user_message.as_deref());
+        let workspace = conv.workspace.as_deref().unwrap_or("");
+        let ws_short = workspace
+            .strip_prefix("/Users/")
+            .and_then(|s| s.split_once('/').map(|(_, rest)| format!("~/{rest}")))
+            .unwrap_or_else(|| workspace.to_string());
+        let _ = write!(
+            lines,
+            "[{}] {}  {}  {}",
+            conv.source_id,
+            conv.created_at.format("%Y-%m-%d %H:%M"),
+            ws_short,
+            truncate_title(&title, 80)
+        );
+        // Titled conversations also match on their first prompt.
+        if let Some(first) = cs.first_user_message.as_deref()
+            && first != title
+        {
+            let _ = write!(lines, "  — {}", truncate_title(first, 80));
+        }
+        let _ = writeln!(lines, "\t{}", conv.id);
+    }
+
+    let exe = std::env::current_exe().unwrap_or_else(|_| PathBuf::from("hstry"));
+    let exe = exe.to_string_lossy();
+    let preview = format!(
+        "--preview={} show {{2}} 2>/dev/null",
+        shlex::try_quote(&exe).unwrap_or_else(|_| "hstry".into())
+    );
+    let mut child = Command::new(fzf)
+        .args([
+            "--height=80%",
+            "--reverse",
+            "--inline-info",
+            "--bind=ctrl-z:ignore",
+            "--delimiter=\t",
+            "--with-nth=1",
+            "--preview-window=down:60%",
+            &preview,
+            &format!("--prompt={prompt}"),
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // fzf closes its input early when the user picks before reading it all.
+        let _ = stdin.write_all(lines.as_bytes());
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        // User cancelled (ESC or ctrl-c)
+        return Ok(None);
     }
 
-    // Fall back to dateparser for ISO dates and other formats
-    dateparser::parse(s)
-        .map(|dt| dt.with_timezone(&chrono::Utc))
-        .map_err(|e| anyhow::anyhow!("Could not parse date '{s}': {e}"))
+    let selected = String::from_utf8_lossy(&output.stdout);
+    let Some((_, id)) = selected.trim_end().rsplit_once('\t') else {
+        return Ok(None);
+    };
+    Ok(Some(uuid::Uuid::parse_str(id)?))
 }
 
 /// Run interactive fzf picker to select a conversation
@@ -4692,96 +8339,20 @@ async fn run_fzf_picker(
     json_output: bool,
 ) -> Result<()> {
     use hstry_core::db::ListConversationsOptions;
-    use std::process::{Command, Stdio};
 
-    // Fetch conversations with filters
     let workspace_filter_like = workspace_filter.as_ref().map(|v| format!("%{v}%"));
-    let conversations = db
-        .list_conversation_summaries(ListConversationsOptions {
-            source_id: source_filter,
-            workspace: workspace_filter_like,
-            after,
-            before,
-            limit: Some(limit),
-        })
-        .await?;
-
-    if conversations.is_empty() {
-        anyhow::bail!("No conversations found. Try adjusting filters.");
-    }
-
-    // Build fzf input lines
-    let mut lines: Vec<String> = Vec::new();
-    let mut id_map: std::collections::HashMap<String, uuid::Uuid> =
-        std::collections::HashMap::new();
-
-    for cs in &conversations {
-        let title = cs
-            .conversation
-            .title
-            .as_deref()
-            .or(cs.first_user_message.as_deref())
-            .unwrap_or("(untitled)");
-        let source = &cs.conversation.source_id;
-        let date = cs.conversation.created_at.format("%Y-%m-%d %H:%M");
-        let workspace = cs.conversation.workspace.as_deref().unwrap_or("");
-        let ws_short = workspace
-            .strip_prefix("/Users/")
-            .and_then(|s| s.split_once('/').map(|(_, rest)| format!("~/{rest}")))
-            .unwrap_or_else(|| workspace.to_string());
-        let id_short = cs.conversation.id.to_string()[..8].to_string();
-
-        // Format: "[source] date  workspace  title  (id)"
-        let line = format!(
-            "[{}] {}  {}  {}  ({})",
-            source, date, ws_short, title, id_short
-        );
-        id_map.insert(line.clone(), cs.conversation.id);
-        lines.push(line);
-    }
-
-    // Write to temp file for fzf
-    let temp_dir = std::env::temp_dir();
-    let temp_file = temp_dir.join(format!("hstry_picker_{}", std::process::id()));
-    std::fs::write(&temp_file, lines.join("\n"))?;
-
-    // Open temp file for stdin redirection
-    let temp_file_handle = std::fs::File::open(&temp_file)?;
-
-    // Build fzf command with preview - read from stdin
-    let fzf_output = Command::new("fzf")
-        .args([
-            "--height=80%",
-            "--reverse",
-            "--inline-info",
-            "--bind=ctrl-z:ignore",
-            "--preview-window=down:60%",
-            r#"--preview=echo {} | grep -oP '\([0-9a-f]{8}\)' | tr -d '()' | xargs -I {} hstry show {} 2>/dev/null | head -20"#,
-            "--prompt=Resume conversation> ",
-        ])
-        .stdin(Stdio::from(temp_file_handle))
-        .stdout(Stdio::piped())
-        .output()?;
-
-    // Clean up temp file
-    let _ = std::fs::remove_file(&temp_file);
-
-    if !fzf_output.status.success() {
-        // User cancelled (ESC or ctrl-c)
-        return Ok(());
-    }
-
-    // Parse selected line
-    let selected = String::from_utf8_lossy(&fzf_output.stdout);
-    let selected = selected.trim();
-
-    if selected.is_empty() {
+    let options = ListConversationsOptions {
+        source_id: source_filter,
+        workspace: workspace_filter_like,
+        after,
+        before,
+        limit: Some(limit),
+        ..Default::default()
+    };
+    let Some(selected_id) = fzf_pick_conversation(db, options, "Resume conversation> ").await?
+    else {
         return Ok(());
-    }
-
-    let selected_id = id_map
-        .get(selected)
-        .ok_or_else(|| anyhow::anyhow!("Could not find selected conversation"))?;
+    };
 
     // Dispatch through the same path as an explicitly supplied ID so picker
     // selection performs the conversion/direct resume and launches the agent.
@@ -4874,7 +8445,15 @@ async fn cmd_resume(
                 workspace: workspace_filter_like.clone(),
                 after,
                 before,
+                ingested_after: None,
+                model: None,
+                provider: None,
+                harness: None,
+                role: None,
+                exclude_sensitive: false,
+                starred: false,
                 limit: Some(limit),
+                offset: None,
             })
             .await?;
 
@@ -4997,7 +8576,15 @@ async fn cmd_resume(
                 workspace: workspace_filter_like,
                 after,
                 before,
+                ingested_after: None,
+                model: None,
+                provider: None,
+                harness: None,
+                role: None,
+                exclude_sensitive: false,
+                starred: false,
                 limit: Some(limit),
+                offset: None,
             })
             .await?;
 
@@ -5135,8 +8722,9 @@ async fn cmd_resume(
         )
     })?;
 
-    // Load messages and build export conversation
-    let messages = db.get_messages(conversation.id).await?;
+    // Load messages and build export conversation (agents resume the
+    // active branch)
+    let messages = branches::active_thread(db.get_messages(conversation.id).await?);
     let parsed_messages: Vec<ParsedMessage> = messages
         .into_iter()
         .map(|m| ParsedMessage {
@@ -5149,6 +8737,7 @@ async fn cmd_resume(
             parts: Some(m.parts_json),
             tool_calls: None,
             metadata: Some(m.metadata),
+            parent_idx: None,
         })
         .collect();
 
@@ -5236,48 +8825,80 @@ async fn cmd_resume(
 }
 
 /// Resolve a conversation by UUID, partial UUID, or external_id.
+/// Candidates listed when a conversation reference is ambiguous.
+const AMBIGUOUS_CANDIDATES: usize = 10;
+
+/// Resolve a conversation from its UUID, readable ID, external or platform
+/// ID, or an unambiguous prefix of one of them.
 async fn resolve_conversation_by_id(db: &Database, id_str: &str) -> Result<Conversation> {
-    // Try full UUID first
-    if let Ok(uuid) = uuid::Uuid::parse_str(id_str)
-        && let Some(conv) = db.get_conversation(uuid).await?
+    let id_str = id_str.trim();
+    if let Some(conv) = db
+        .get_conversation_by_reference(None, Some(id_str), Some(id_str), Some(id_str), None)
+        .await?
     {
         return Ok(conv);
     }
 
-    // Try partial UUID match or external_id match
-    let all = db
-        .list_conversations(hstry_core::db::ListConversationsOptions {
-            limit: None,
-            ..Default::default()
-        })
+    let limit = i64::try_from(AMBIGUOUS_CANDIDATES + 1).unwrap_or(i64::MAX);
+    let mut matches = db.find_conversations_by_prefix(id_str, limit).await?;
+    match matches.len() {
+        0 => anyhow::bail!("No conversation found matching '{id_str}'"),
+        1 => Ok(matches.remove(0)),
+        n => {
+            let mut message = if n > AMBIGUOUS_CANDIDATES {
+                format!(
+                    "Ambiguous ID '{id_str}': matched more than {AMBIGUOUS_CANDIDATES} conversations. Use a longer prefix."
+                )
+            } else {
+                format!("Ambiguous ID '{id_str}': matched {n} conversations:")
+            };
+            for conv in matches.iter().take(AMBIGUOUS_CANDIDATES) {
+                let readable = conv.readable_id.as_deref().unwrap_or("-");
+                let title = truncate_title(conv.title.as_deref().unwrap_or("Untitled"), 60);
+                message.push_str(&format!(
+                    "\n  {}  {readable}  {}  [{}] {title}",
+                    &conv.id.to_string()[..8],
+                    conv.created_at.format("%Y-%m-%d"),
+                    conv.source_id,
+                ));
+            }
+            anyhow::bail!(message)
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct StarResult {
+    id: uuid::Uuid,
+    title: Option<String>,
+    starred: bool,
+}
+
+async fn cmd_star(db: &Database, id: &str, starred: bool, json_output: bool) -> Result<()> {
+    let conversation = resolve_conversation_by_id(db, id).await?;
+    db.set_conversation_starred(conversation.id, starred)
         .await?;
 
-    let matches: Vec<_> = all
-        .into_iter()
-        .filter(|c| {
-            let id_match = c.id.to_string().starts_with(id_str);
-            let ext_match = c
-                .external_id
-                .as_ref()
-                .is_some_and(|e| e.starts_with(id_str) || e == id_str);
-            let readable_match = c
-                .readable_id
-                .as_deref()
-                .is_some_and(|r| r == id_str || r.starts_with(&format!("{id_str}-")));
-            id_match || ext_match || readable_match
-        })
-        .collect();
+    let result = StarResult {
+        id: conversation.id,
+        title: conversation.title,
+        starred,
+    };
+    if json_output {
+        return emit_json(JsonResponse {
+            ok: true,
+            result: Some(result),
+            error: None,
+        });
+    }
 
-    match matches.len() {
-        0 => anyhow::bail!("No conversation found matching '{id_str}'"),
-        1 => matches
-            .into_iter()
-            .next()
-            .ok_or_else(|| anyhow::anyhow!("No conversation found matching '{id_str}'")),
-        n => anyhow::bail!(
-            "Ambiguous ID '{id_str}': matched {n} conversations. Use a longer prefix."
-        ),
+    let title = result.title.as_deref().unwrap_or("Untitled conversation");
+    if starred {
+        println!("Starred '{title}' ({})", result.id);
+    } else {
+        println!("Unstarred '{title}' ({})", result.id);
     }
+    Ok(())
 }
 
 #[derive(Debug, Serialize)]
@@ -5288,6 +8909,8 @@ struct RemoveConversationResult {
     title: Option<String>,
     messages: i64,
     removed: bool,
+    /// Deleted for good rather than moved to the trash.
+    permanent: bool,
 }
 
 async fn cmd_remove(
@@ -5295,14 +8918,26 @@ async fn cmd_remove(
     id: &str,
     yes: bool,
     dry_run: bool,
+    permanent: bool,
     json_output: bool,
 ) -> Result<()> {
     let conversation = resolve_conversation_by_id(db, id).await?;
     let messages = db.count_messages_for_conversation(conversation.id).await?;
+    let trashed_at = db.trashed_at(conversation.id).await?;
+    if trashed_at.is_some() && !permanent {
+        anyhow::bail!(
+            "'{}' is already in the trash; add --permanent to delete it",
+            conversation.id
+        );
+    }
     let should_remove = yes && !dry_run;
 
     if should_remove {
-        db.delete_conversations_batch(&[conversation.id]).await?;
+        if permanent {
+            db.delete_conversations_batch(&[conversation.id]).await?;
+        } else {
+            db.trash_conversations(&[conversation.id]).await?;
+        }
     }
 
     let result = RemoveConversationResult {
@@ -5312,6 +8947,7 @@ async fn cmd_remove(
         title: conversation.title,
         messages,
         removed: should_remove,
+        permanent,
     };
 
     if json_output {
@@ -5323,127 +8959,612 @@ async fn cmd_remove(
     }
 
     let title = result.title.as_deref().unwrap_or("Untitled conversation");
-    if should_remove {
+    if should_remove && permanent {
         println!(
-            "Removed '{title}' ({id}) and {messages} message(s)",
+            "Deleted '{title}' ({id}) and {messages} message(s)",
             id = result.id
         );
+    } else if should_remove {
+        println!("Moved '{title}' ({id}) to the trash", id = result.id);
+        println!("Run `hstry trash restore {}` to bring it back.", result.id);
     } else {
-        println!("Would remove:");
+        if permanent {
+            println!("Would delete permanently:");
+        } else {
+            println!("Would move to the trash:");
+        }
         println!("  Title:    {title}");
         println!("  ID:       {}", result.id);
         println!("  Source:   {}", result.source_id);
         println!("  Messages: {messages}");
         println!();
-        println!("Run `hstry remove {} --yes` to confirm.", result.id);
+        let flag = if permanent { " --permanent" } else { "" };
+        println!("Run `hstry remove {}{flag} --yes` to confirm.", result.id);
     }
 
     Ok(())
 }
 
-/// Build the launch command string by replacing placeholders.
-fn build_resume_command(
-    agent_config: &hstry_core::config::AgentResumeConfig,
-    session_path: &Path,
-    conversation: &Conversation,
-) -> String {
-    let id_string = conversation.id.to_string();
-    let session_id = conversation.external_id.as_deref().unwrap_or(&id_string);
-
-    agent_config
-        .command
-        .replace("{session_path}", &session_path.display().to_string())
-        .replace("{session_id}", session_id)
-        .replace(
-            "{workspace}",
-            conversation.workspace.as_deref().unwrap_or("."),
-        )
+#[derive(Debug, Serialize)]
+struct TrashEntry {
+    #[serde(flatten)]
+    conversation: ConversationBrief,
+    deleted_at: chrono::DateTime<chrono::Utc>,
 }
 
-/// Place exported session files into the agent's native session directory.
-///
-/// Adapters may include a `root` prefix in their file paths (e.g., `sessions/...`).
-/// Since `session_dir` already points to the target directory, we strip the root prefix.
-fn place_exported_session(
-    result: &hstry_runtime::ExportResult,
-    session_dir: &Path,
-    conversation: &Conversation,
-    dry_run: bool,
-) -> Result<Vec<PathBuf>> {
-    use std::fs;
-
-    // Extract root prefix from metadata (e.g., "sessions/", "project/")
-    let root_prefix = result
-        .metadata
-        .as_ref()
-        .and_then(|m| m.get("root"))
-        .and_then(|v| v.as_str())
-        .unwrap_or("");
-
-    let mut placed = Vec::new();
-
-    if let Some(ref content) = result.content {
-        // Single-file export (e.g., JSON, markdown)
-        let id_string = conversation.id.to_string();
-        let session_id = conversation.external_id.as_deref().unwrap_or(&id_string);
-        let ext = match result.format.as_str() {
-            "markdown" => "md",
-            "json" => "json",
-            _ => "jsonl",
-        };
-        let filename = format!("{session_id}.{ext}");
-        let target = session_dir.join(&filename);
-
-        if dry_run {
-            placed.push(target);
-        } else {
-            if let Some(parent) = target.parent() {
-                fs::create_dir_all(parent)?;
-            }
-            fs::write(&target, content)?;
-            placed.push(target);
-        }
+async fn cmd_trash_list(db: &Database, limit: Option<i64>, json_output: bool) -> Result<()> {
+    let entries: Vec<TrashEntry> = db
+        .list_trash(limit)
+        .await?
+        .into_iter()
+        .map(|trashed| TrashEntry {
+            conversation: trashed.conversation.into(),
+            deleted_at: trashed.deleted_at,
+        })
+        .collect();
+    if json_output {
+        return emit_json(JsonResponse {
+            ok: true,
+            result: Some(entries),
+            error: None,
+        });
     }
 
-    if let Some(ref files) = result.files {
-        for file in files {
-            // Strip the root prefix from the file path
-            let relative = file.path.strip_prefix(root_prefix).unwrap_or(&file.path);
-            let target = session_dir.join(relative);
-
-            if dry_run {
-                placed.push(target);
-            } else {
-                if let Some(parent) = target.parent() {
-                    fs::create_dir_all(parent)?;
-                }
-                fs::write(&target, &file.content)?;
-                placed.push(target);
-            }
-        }
+    if entries.is_empty() {
+        println!("The trash is empty.");
+        return Ok(());
     }
-
-    Ok(placed)
-}
-
-/// Launch an agent process in the given workspace directory.
-fn launch_agent(command_str: &str, workspace: &str, json_output: bool) -> Result<()> {
-    let parts: Vec<&str> = command_str.split_whitespace().collect();
-    if parts.is_empty() {
-        anyhow::bail!("Empty resume command");
+    println!("{:<36}  {:<10}  {:<12}  TITLE", "ID", "REMOVED", "SOURCE");
+    for entry in &entries {
+        let conversation = &entry.conversation;
+        println!(
+            "{}  {}  {:<12}  {}",
+            conversation.id,
+            entry.deleted_at.format("%Y-%m-%d"),
+            conversation.source_id,
+            truncate_title(
+                conversation
+                    .title
+                    .as_deref()
+                    .unwrap_or("Untitled conversation"),
+                60
+            )
+        );
     }
+    Ok(())
+}
 
-    let program = parts[0];
-    let args = &parts[1..];
-
+async fn cmd_trash_restore(
+    db: &Database,
+    ids: &[String],
+    all: bool,
+    json_output: bool,
+) -> Result<()> {
+    let ids: Vec<uuid::Uuid> = if all {
+        db.list_trash(None)
+            .await?
+            .into_iter()
+            .map(|trashed| trashed.conversation.id)
+            .collect()
+    } else {
+        let mut resolved = Vec::with_capacity(ids.len());
+        for id in ids {
+            resolved.push(resolve_conversation_by_id(db, id).await?.id);
+        }
+        resolved
+    };
+    let restored = db.restore_conversations(&ids).await?;
     if json_output {
         return emit_json(JsonResponse {
             ok: true,
-            result: Some(&serde_json::json!({
-                "launched": true,
-                "command": command_str,
-                "workspace": workspace,
-            })),
+            result: Some(serde_json::json!({ "restored": restored })),
+            error: None,
+        });
+    }
+    println!("Restored {restored} conversation(s) from the trash.");
+    if restored < ids.len() {
+        println!("{} of them were not in the trash.", ids.len() - restored);
+    }
+    Ok(())
+}
+
+async fn cmd_trash_empty(
+    db: &Database,
+    older_than: Option<u32>,
+    yes: bool,
+    json_output: bool,
+) -> Result<()> {
+    let before =
+        older_than.map(|days| chrono::Utc::now() - chrono::Duration::days(i64::from(days)));
+    let purged = if yes {
+        db.purge_trash(before).await?
+    } else {
+        db.list_trash(None)
+            .await?
+            .iter()
+            .filter(|trashed| before.is_none_or(|before| trashed.deleted_at < before))
+            .count()
+    };
+    if json_output {
+        return emit_json(JsonResponse {
+            ok: true,
+            result: Some(serde_json::json!({ "purged": purged, "dry_run": !yes })),
+            error: None,
+        });
+    }
+    if yes {
+        println!("Deleted {purged} conversation(s) from the trash for good.");
+    } else {
+        println!("Would delete {purged} conversation(s) from the trash for good.");
+        println!("Re-run with --yes to confirm.");
+    }
+    Ok(())
+}
+
+async fn cmd_snapshot_create(db: &Database, id: &str, json_output: bool) -> Result<()> {
+    let conversation = resolve_conversation_by_id(db, id).await?;
+    let Some(snapshot) = db.create_snapshot(conversation.id, "manual").await? else {
+        anyhow::bail!("Sensitive conversations are not snapshotted; their text would be unsealed");
+    };
+    if json_output {
+        return emit_json(JsonResponse {
+            ok: true,
+            result: Some(snapshot),
+            error: None,
+        });
+    }
+    println!(
+        "Saved snapshot {} of \"{}\" ({} messages).",
+        snapshot.id,
+        truncate_title(
+            snapshot.title.as_deref().unwrap_or("Untitled conversation"),
+            60
+        ),
+        snapshot.message_count
+    );
+    Ok(())
+}
+
+async fn cmd_snapshot_list(
+    db: &Database,
+    id: Option<&str>,
+    limit: i64,
+    json_output: bool,
+) -> Result<()> {
+    let conversation_id = match id {
+        Some(id) => Some(resolve_conversation_by_id(db, id).await?.id),
+        None => None,
+    };
+    let snapshots = db.list_snapshots(conversation_id, Some(limit)).await?;
+    if json_output {
+        return emit_json(JsonResponse {
+            ok: true,
+            result: Some(snapshots),
+            error: None,
+        });
+    }
+
+    if snapshots.is_empty() {
+        println!("No snapshots saved.");
+        return Ok(());
+    }
+    println!(
+        "{:<36}  {:<16}  {:<8}  {:>8}  TITLE",
+        "ID", "TAKEN", "REASON", "MESSAGES"
+    );
+    for snapshot in &snapshots {
+        println!(
+            "{}  {}  {:<8}  {:>8}  {}",
+            snapshot.id,
+            snapshot.created_at.format("%Y-%m-%d %H:%M"),
+            snapshot.reason,
+            snapshot.message_count,
+            truncate_title(
+                snapshot.title.as_deref().unwrap_or("Untitled conversation"),
+                50
+            )
+        );
+    }
+    Ok(())
+}
+
+async fn cmd_snapshot_restore(db: &Database, prefix: &str, json_output: bool) -> Result<()> {
+    let mut matches: Vec<SavedSnapshot> = db
+        .list_snapshots(None, None)
+        .await?
+        .into_iter()
+        .filter(|snapshot| snapshot.id.to_string().starts_with(prefix))
+        .collect();
+    let snapshot = match matches.len() {
+        0 => anyhow::bail!("No snapshot matches '{prefix}'"),
+        1 => matches.remove(0),
+        n => anyhow::bail!("'{prefix}' matches {n} snapshots; give more of the ID"),
+    };
+    let conversation_id = db.restore_snapshot(snapshot.id).await?;
+    if json_output {
+        return emit_json(JsonResponse {
+            ok: true,
+            result: Some(serde_json::json!({
+                "snapshot": snapshot.id,
+                "conversation_id": conversation_id,
+                "messages": snapshot.message_count,
+            })),
+            error: None,
+        });
+    }
+    println!(
+        "Restored {conversation_id} to its snapshot from {} ({} messages).",
+        snapshot.created_at.format("%Y-%m-%d %H:%M"),
+        snapshot.message_count
+    );
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+struct ConversationBrief {
+    id: uuid::Uuid,
+    source_id: String,
+    title: Option<String>,
+    last_active: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<Conversation> for ConversationBrief {
+    fn from(c: Conversation) -> Self {
+        Self {
+            id: c.id,
+            last_active: c.updated_at.unwrap_or(c.created_at),
+            source_id: c.source_id,
+            title: c.title,
+        }
+    }
+}
+
+fn print_conversation_briefs(conversations: &[ConversationBrief]) {
+    for conversation in conversations {
+        println!(
+            "  {}  {}  {:<12}  {}",
+            conversation.id,
+            conversation.last_active.format("%Y-%m-%d"),
+            conversation.source_id,
+            conversation
+                .title
+                .as_deref()
+                .unwrap_or("Untitled conversation")
+        );
+    }
+}
+
+/// Conversations selected by `hstry archive` / `hstry unarchive`.
+struct TransferSelection {
+    ids: Vec<String>,
+    source: Option<String>,
+    workspace: Option<String>,
+    before: Option<chrono::DateTime<chrono::Utc>>,
+    tag: Option<String>,
+}
+
+impl TransferSelection {
+    fn has_filters(&self) -> bool {
+        self.source.is_some()
+            || self.workspace.is_some()
+            || self.before.is_some()
+            || self.tag.is_some()
+    }
+
+    async fn resolve(&self, db: &Database) -> Result<Vec<Conversation>> {
+        let mut selected = Vec::new();
+        for id in &self.ids {
+            selected.push(resolve_conversation_by_id(db, id).await?);
+        }
+
+        if self.has_filters() {
+            let mut matches = db
+                .list_conversations(hstry_core::db::ListConversationsOptions {
+                    source_id: self.source.clone(),
+                    workspace: self.workspace.as_ref().map(|value| format!("%{value}%")),
+                    before: self.before,
+                    ..Default::default()
+                })
+                .await?;
+            if let Some(tag) = &self.tag {
+                let tagged: HashSet<uuid::Uuid> = db
+                    .find_conversations_by_tag(tag)
+                    .await?
+                    .into_iter()
+                    .map(|c| c.id)
+                    .collect();
+                matches.retain(|c| tagged.contains(&c.id));
+            }
+            selected.extend(matches);
+        }
+
+        let mut seen = HashSet::new();
+        selected.retain(|c| seen.insert(c.id));
+        Ok(selected)
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ArchiveResult {
+    archive: PathBuf,
+    conversations: Vec<ConversationBrief>,
+    moved: usize,
+}
+
+/// Move conversations between the main database and the archive database.
+/// `unarchive` flips the direction.
+async fn cmd_archive(
+    config: &Config,
+    selection: &TransferSelection,
+    unarchive: bool,
+    yes: bool,
+    dry_run: bool,
+    json_output: bool,
+) -> Result<()> {
+    if selection.ids.is_empty() && !selection.has_filters() {
+        anyhow::bail!(
+            "Pass conversation IDs or at least one of --source, --workspace, --before, --tag"
+        );
+    }
+
+    let archive_path = config.archive_path();
+    if unarchive && !archive_path.exists() {
+        anyhow::bail!("No archive database at {}", archive_path.display());
+    }
+
+    let db = Database::open_config(config).await?;
+    apply_storage_config(&db, config);
+    let archive = if unarchive || (yes && !dry_run) {
+        Some(Database::open_archive(config).await?)
+    } else {
+        None
+    };
+
+    let from = if unarchive {
+        archive.as_ref().unwrap_or(&db)
+    } else {
+        &db
+    };
+    let conversations = selection.resolve(from).await?;
+    let ids: Vec<uuid::Uuid> = conversations.iter().map(|c| c.id).collect();
+
+    let should_move = yes && !dry_run;
+    let moved = match (&archive, should_move) {
+        (Some(archive), true) if unarchive => archive.transfer_conversations(&db, &ids).await?,
+        (Some(archive), true) => db.transfer_conversations(archive, &ids).await?,
+        _ => 0,
+    };
+
+    let result = ArchiveResult {
+        archive: archive_path,
+        conversations: conversations.into_iter().map(Into::into).collect(),
+        moved,
+    };
+
+    if json_output {
+        return emit_json(JsonResponse {
+            ok: true,
+            result: Some(result),
+            error: None,
+        });
+    }
+
+    let verb = if unarchive { "unarchive" } else { "archive" };
+    if result.conversations.is_empty() {
+        println!("No matching conversations to {verb}.");
+        return Ok(());
+    }
+
+    if should_move {
+        if unarchive {
+            println!(
+                "Restored {} conversation(s) from {}",
+                result.moved,
+                result.archive.display()
+            );
+        } else {
+            println!(
+                "Archived {} conversation(s) to {}",
+                result.moved,
+                result.archive.display()
+            );
+        }
+        return Ok(());
+    }
+
+    println!(
+        "Would {verb} {} conversation(s):",
+        result.conversations.len()
+    );
+    print_conversation_briefs(&result.conversations);
+    println!();
+    println!("Re-run with --yes to confirm.");
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+struct PruneResult {
+    action: RetentionAction,
+    candidates: Vec<ConversationBrief>,
+    pruned: usize,
+}
+
+async fn cmd_prune(
+    db: &Database,
+    config: &Config,
+    policy: &hstry_core::config::RetentionConfig,
+    yes: bool,
+    dry_run: bool,
+    json_output: bool,
+) -> Result<()> {
+    let scopes = config.retention_scopes(policy);
+    if !scopes.iter().any(|scope| scope.has_limits()) {
+        anyhow::bail!(
+            "No retention limits configured; set [retention] max_age_days or \
+             max_conversations_per_source, or pass --max-age-days/--max-per-source"
+        );
+    }
+
+    let mut conversations = Vec::new();
+    for scope in &scopes {
+        conversations.extend(
+            db.find_retention_candidates_in(scope, &policy.exclude_tags)
+                .await?,
+        );
+    }
+    conversations.sort_by_key(|c| c.updated_at.unwrap_or(c.created_at));
+    let should_prune = yes && !dry_run;
+    let pruned = if should_prune {
+        let ids: Vec<uuid::Uuid> = conversations.iter().map(|c| c.id).collect();
+        match policy.action {
+            RetentionAction::Delete => db.delete_conversations_batch(&ids).await?,
+            RetentionAction::Archive => {
+                let archive = Database::open_archive(config).await?;
+                db.transfer_conversations(&archive, &ids).await?
+            }
+        }
+    } else {
+        0
+    };
+
+    let result = PruneResult {
+        action: policy.action,
+        candidates: conversations.into_iter().map(Into::into).collect(),
+        pruned,
+    };
+
+    if json_output {
+        return emit_json(JsonResponse {
+            ok: true,
+            result: Some(result),
+            error: None,
+        });
+    }
+
+    if result.candidates.is_empty() {
+        println!("No conversations outside the retention policy.");
+        return Ok(());
+    }
+
+    if should_prune {
+        let verb = match result.action {
+            RetentionAction::Delete => "Pruned",
+            RetentionAction::Archive => "Archived",
+        };
+        println!("{verb} {} conversation(s).", result.pruned);
+        return Ok(());
+    }
+
+    println!("Would prune {} conversation(s):", result.candidates.len());
+    print_conversation_briefs(&result.candidates);
+    println!();
+    println!("Run `hstry prune --yes` to confirm.");
+    Ok(())
+}
+
+/// Build the launch command string by replacing placeholders.
+fn build_resume_command(
+    agent_config: &hstry_core::config::AgentResumeConfig,
+    session_path: &Path,
+    conversation: &Conversation,
+) -> String {
+    let id_string = conversation.id.to_string();
+    let session_id = conversation.external_id.as_deref().unwrap_or(&id_string);
+
+    agent_config
+        .command
+        .replace("{session_path}", &session_path.display().to_string())
+        .replace("{session_id}", session_id)
+        .replace(
+            "{workspace}",
+            conversation.workspace.as_deref().unwrap_or("."),
+        )
+}
+
+/// Place exported session files into the agent's native session directory.
+///
+/// Adapters may include a `root` prefix in their file paths (e.g., `sessions/...`).
+/// Since `session_dir` already points to the target directory, we strip the root prefix.
+fn place_exported_session(
+    result: &hstry_runtime::ExportResult,
+    session_dir: &Path,
+    conversation: &Conversation,
+    dry_run: bool,
+) -> Result<Vec<PathBuf>> {
+    use std::fs;
+
+    // Extract root prefix from metadata (e.g., "sessions/", "project/")
+    let root_prefix = result
+        .metadata
+        .as_ref()
+        .and_then(|m| m.get("root"))
+        .and_then(|v| v.as_str())
+        .unwrap_or("");
+
+    let mut placed = Vec::new();
+
+    if let Some(ref content) = result.content {
+        // Single-file export (e.g., JSON, markdown)
+        let id_string = conversation.id.to_string();
+        let session_id = conversation.external_id.as_deref().unwrap_or(&id_string);
+        let ext = match result.format.as_str() {
+            "markdown" => "md",
+            "json" => "json",
+            _ => "jsonl",
+        };
+        let filename = format!("{session_id}.{ext}");
+        let target = session_dir.join(&filename);
+
+        if dry_run {
+            placed.push(target);
+        } else {
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&target, content)?;
+            placed.push(target);
+        }
+    }
+
+    if let Some(ref files) = result.files {
+        for file in files {
+            // Strip the root prefix from the file path
+            let relative = file.path.strip_prefix(root_prefix).unwrap_or(&file.path);
+            let target = session_dir.join(relative);
+
+            if dry_run {
+                placed.push(target);
+            } else {
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(&target, &file.content)?;
+                placed.push(target);
+            }
+        }
+    }
+
+    Ok(placed)
+}
+
+/// Launch an agent process in the given workspace directory.
+fn launch_agent(command_str: &str, workspace: &str, json_output: bool) -> Result<()> {
+    let parts: Vec<&str> = command_str.split_whitespace().collect();
+    if parts.is_empty() {
+        anyhow::bail!("Empty resume command");
+    }
+
+    let program = parts[0];
+    let args = &parts[1..];
+
+    if json_output {
+        return emit_json(JsonResponse {
+            ok: true,
+            result: Some(&serde_json::json!({
+                "launched": true,
+                "command": command_str,
+                "workspace": workspace,
+            })),
             error: None,
         });
     }
@@ -5451,154 +9572,1440 @@ fn launch_agent(command_str: &str, workspace: &str, json_output: bool) -> Result
     eprintln!("Launching: {command_str}");
     eprintln!("Workspace: {workspace}");
 
-    let status = ProcessCommand::new(program)
-        .args(args)
-        .current_dir(workspace)
-        .stdin(Stdio::inherit())
-        .stdout(Stdio::inherit())
-        .stderr(Stdio::inherit())
-        .status()
-        .map_err(|e| anyhow::anyhow!("Failed to launch '{program}': {e}"))?;
+    let status = ProcessCommand::new(program)
+        .args(args)
+        .current_dir(workspace)
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .status()
+        .map_err(|e| anyhow::anyhow!("Failed to launch '{program}': {e}"))?;
+
+    if !status.success() {
+        anyhow::bail!("Agent exited with status: {status}");
+    }
+
+    Ok(())
+}
+
+async fn cmd_index(_config: &Config, db: &Database, rebuild: bool, json: bool) -> Result<()> {
+    let total = if rebuild {
+        // The FTS rebuild is a single statement; cancelling drops it before
+        // commit and leaves the previous index intact.
+        let cancel = CancelToken::install_ctrl_c();
+        cancel.run(db.rebuild_search_fts()).await??
+    } else {
+        0
+    };
+
+    if json {
+        return emit_json(JsonResponse {
+            ok: true,
+            result: Some(serde_json::json!({
+                "indexed": total,
+                "rebuild": rebuild,
+                "backend": "sqlite-fts5",
+            })),
+            error: None,
+        });
+    }
+
+    if rebuild {
+        println!("Rebuilt SQLite FTS search index ({total} messages).");
+    } else {
+        println!("Search uses SQLite FTS5 inside the database and stays up to date via triggers;");
+        println!("a copy of the database carries its index, so no rebuild is needed.");
+    }
+
+    Ok(())
+}
+
+async fn cmd_index_status(db: &Database, json: bool) -> Result<()> {
+    let status = db.search_index_status().await?;
+    let stale = status.is_stale();
+
+    if json {
+        return emit_json(JsonResponse {
+            ok: true,
+            result: Some(serde_json::json!({
+                "messages": status.messages,
+                "tokenizer": status.tokenizer,
+                "stale": stale,
+                "tables": status.tables,
+            })),
+            error: None,
+        });
+    }
+
+    println!("Messages: {}", status.messages);
+    if let Some(tokenizer) = status.tokenizer {
+        println!("Tokenizer: {}", tokenizer.fts5_spec());
+    }
+    println!(
+        "  {:<18} {:>8} {:>10} {:>12}",
+        "TABLE", "SEGMENTS", "MESSAGES", "SIZE"
+    );
+    for table in &status.tables {
+        println!(
+            "  {:<18} {:>8} {:>10} {:>12}",
+            table.table,
+            table.segments,
+            table.documents,
+            format_bytes(u64::try_from(table.size_bytes).unwrap_or(0))
+        );
+    }
+    if stale {
+        println!("The index is out of date; run `hstry index --rebuild`.");
+    } else if status.tables.iter().any(|table| table.segments > 1) {
+        println!("Run `hstry index optimize` to merge segments.");
+    }
+
+    Ok(())
+}
+
+async fn cmd_index_optimize(db: &Database, json: bool) -> Result<()> {
+    let segments = |status: &SearchIndexStatus| -> i64 {
+        status.tables.iter().map(|table| table.segments).sum()
+    };
+    let before = segments(&db.search_index_status().await?);
+    // Like a rebuild, optimize is one statement per table; cancelling
+    // leaves the unmerged index intact.
+    let cancel = CancelToken::install_ctrl_c();
+    cancel.run(db.optimize_search_fts()).await??;
+    let after = segments(&db.search_index_status().await?);
+
+    if json {
+        return emit_json(JsonResponse {
+            ok: true,
+            result: Some(serde_json::json!({
+                "segments_before": before,
+                "segments_after": after,
+            })),
+            error: None,
+        });
+    }
+
+    println!("Merged {before} index segments into {after}.");
+    Ok(())
+}
+
+/// Models and workspaces listed by `hstry stats --detailed`.
+const STATS_TOP: i64 = 10;
+
+async fn cmd_stats(db: &Database, detailed: bool, json: bool) -> Result<()> {
+    let sources = db.list_sources().await?;
+    let conv_count = db.count_conversations().await?;
+    let msg_count = db.count_messages().await?;
+    let sources_count = i64::try_from(sources.len()).unwrap_or(i64::MAX);
+    let per_source = db.get_source_stats().await?;
+    let activity = db.get_activity_stats(30).await?;
+    let llm_usage = db.llm_usage_summary().await?;
+    let detailed = if detailed {
+        Some(db.detailed_stats(STATS_TOP).await?)
+    } else {
+        None
+    };
+
+    if json {
+        return emit_json(JsonResponse {
+            ok: true,
+            result: Some(StatsSummary {
+                sources: sources_count,
+                conversations: conv_count,
+                messages: msg_count,
+                per_source,
+                activity,
+                llm_usage,
+                detailed,
+            }),
+            error: None,
+        });
+    }
+
+    // Header
+    println!("\x1b[1mDatabase Statistics\x1b[0m");
+    println!();
+
+    // Totals
+    println!("\x1b[1;34mTotals\x1b[0m");
+    println!("  Sources:       {sources_count}");
+    println!("  Conversations: {conv_count}");
+    println!("  Messages:      {msg_count}");
+    println!();
+
+    // Activity
+    println!("\x1b[1;34mActivity\x1b[0m");
+    println!("  Today:      {:>6} conversations", activity.today);
+    println!("  This week:  {:>6} conversations", activity.week);
+    println!("  This month: {:>6} conversations", activity.month);
+    println!();
+
+    // Per-source stats
+    if !per_source.is_empty() {
+        println!("\x1b[1;34mPer Source\x1b[0m");
+        println!(
+            "  {:<15} {:<12} {:>8} {:>10} {:>12}",
+            "SOURCE", "ADAPTER", "CONVS", "MSGS", "LAST SYNC"
+        );
+        println!("  {}", "-".repeat(60));
+        for stats in &per_source {
+            let last_sync = stats
+                .last_sync_at
+                .map(pretty::relative_time_short)
+                .unwrap_or_else(|| "never".to_string());
+            println!(
+                "  {:<15} {:<12} {:>8} {:>10} {:>12}",
+                truncate_title(&stats.source_id, 15),
+                truncate_title(&stats.adapter, 12),
+                stats.conversations,
+                stats.messages,
+                last_sync
+            );
+        }
+        println!();
+    }
+
+    // Model requests made by hstry itself
+    if !llm_usage.is_empty() {
+        println!("\x1b[1;34mLLM Usage\x1b[0m");
+        println!(
+            "  {:<10} {:<22} {:>8} {:>10} {:>10} {:>10}",
+            "FEATURE", "MODEL", "REQUESTS", "TOKENS IN", "TOKENS OUT", "COST"
+        );
+        println!("  {}", "-".repeat(75));
+        for usage in &llm_usage {
+            let model = usage.model.as_deref().unwrap_or(&usage.provider);
+            println!(
+                "  {:<10} {:<22} {:>8} {:>10} {:>10} {:>10}",
+                truncate_title(&usage.feature, 10),
+                truncate_title(model, 22),
+                usage.requests,
+                usage.tokens_in,
+                usage.tokens_out,
+                format!("${:.4}", usage.cost_usd)
+            );
+        }
+        println!();
+    }
+
+    // Date range
+    let oldest = per_source.iter().filter_map(|s| s.oldest).min();
+    let newest = per_source.iter().filter_map(|s| s.newest).max();
+    if let (Some(oldest), Some(newest)) = (oldest, newest) {
+        println!("\x1b[1;34mDate Range\x1b[0m");
+        println!("  Oldest: {}", oldest.format("%Y-%m-%d"));
+        println!("  Newest: {}", newest.format("%Y-%m-%d"));
+    }
+
+    if let Some(detailed) = detailed {
+        println!();
+        println!("\x1b[1;34mTokens & Cost\x1b[0m");
+        println!("  Tokens in:    {:>12}", detailed.totals.tokens_in);
+        println!("  Tokens out:   {:>12}", detailed.totals.tokens_out);
+        println!(
+            "  Cost:         {:>12}",
+            format!("${:.2}", detailed.totals.cost_usd)
+        );
+        println!("  Avg messages: {:>12.1}", detailed.avg_messages);
+        println!();
+        print_stats_breakdown("By Source", "SOURCE", &detailed.by_source);
+        print_stats_breakdown("By Model", "MODEL", &detailed.by_model);
+        print_stats_breakdown("By Month", "MONTH", &detailed.by_month);
+        print_stats_breakdown("Busiest Workspaces", "WORKSPACE", &detailed.top_workspaces);
+    }
+
+    Ok(())
+}
+
+async fn cmd_cost_report(
+    db: &Database,
+    pricing: &hstry_core::config::Pricing,
+    by: CostGroupArg,
+    json: bool,
+) -> Result<()> {
+    let report = hstry_core::cost::report(db, pricing, by.into()).await?;
+    if json {
+        return emit_json(JsonResponse {
+            ok: true,
+            result: Some(report),
+            error: None,
+        });
+    }
+
+    let label = match by {
+        CostGroupArg::Month => "MONTH",
+        CostGroupArg::Model => "MODEL",
+        CostGroupArg::Workspace => "WORKSPACE",
+    };
+    println!(
+        "{:<28} {:>7} {:>12} {:>12} {:>10} {:>10} {:>10}",
+        label, "CONVS", "TOKENS IN", "TOKENS OUT", "RECORDED", "ESTIMATED", "TOTAL"
+    );
+    println!("{}", "-".repeat(95));
+    let print_line = |key: &str, line: &hstry_core::cost::CostLine| {
+        println!(
+            "{:<28} {:>7} {:>12} {:>12} {:>10} {:>10} {:>10}",
+            truncate_title(key, 28),
+            line.conversations,
+            line.tokens_in,
+            line.tokens_out,
+            format!("${:.2}", line.recorded_usd),
+            format!("${:.2}", line.estimated_usd),
+            format!("${:.2}", line.total_usd())
+        );
+    };
+    for line in &report.lines {
+        print_line(line.key.as_deref().unwrap_or("(unknown)"), line);
+    }
+    println!("{}", "-".repeat(95));
+    print_line("Total", &report.total);
+
+    if report.total.unpriced > 0 {
+        println!();
+        println!(
+            "{} conversation(s) have tokens but no recorded cost or price.",
+            report.total.unpriced
+        );
+        if !report.unpriced_models.is_empty() {
+            println!(
+                "Add [pricing] entries for: {}",
+                report.unpriced_models.join(", ")
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Bytes of the database file and of its write-ahead log.
+fn database_file_sizes(path: &Path) -> (u64, u64) {
+    let len = |path: &Path| std::fs::metadata(path).map_or(0, |meta| meta.len());
+    let mut wal = path.as_os_str().to_owned();
+    wal.push("-wal");
+    (len(path), len(Path::new(&wal)))
+}
+
+async fn cmd_db_size(db: &Database, config: &Config, json: bool) -> Result<()> {
+    let path = &config.database.path;
+    let (file_bytes, wal_bytes) = database_file_sizes(path);
+    let archive_path = config.archive_path();
+    let archive_bytes = std::fs::metadata(&archive_path).ok().map(|meta| meta.len());
+    let size = db.database_size().await?;
+    if json {
+        return emit_json(JsonResponse {
+            ok: true,
+            result: Some(serde_json::json!({
+                "path": path,
+                "file_bytes": file_bytes,
+                "wal_bytes": wal_bytes,
+                "archive_bytes": archive_bytes,
+                "size": size,
+            })),
+            error: None,
+        });
+    }
+
+    let bytes = |bytes: i64| format_bytes(u64::try_from(bytes).unwrap_or(0));
+    println!(
+        "Database:    {} ({})",
+        path.display(),
+        format_bytes(file_bytes)
+    );
+    println!("WAL:         {}", format_bytes(wal_bytes));
+    println!(
+        "Attachments: {} ({})",
+        size.attachments,
+        bytes(size.attachment_bytes)
+    );
+    if let Some(archive_bytes) = archive_bytes {
+        println!(
+            "Archive:     {} ({})",
+            archive_path.display(),
+            format_bytes(archive_bytes)
+        );
+    }
+    println!("Free:        {}", bytes(size.free_bytes));
+
+    if !size.tables.is_empty() {
+        println!();
+        println!(
+            "  {:<28} {:>12} {:>12} {:>12}",
+            "TABLE", "DATA", "INDEXES", "TOTAL"
+        );
+        for table in &size.tables {
+            println!(
+                "  {:<28} {:>12} {:>12} {:>12}",
+                truncate_title(&table.name, 28),
+                bytes(table.table_bytes),
+                bytes(table.index_bytes),
+                bytes(table.table_bytes + table.index_bytes)
+            );
+        }
+    }
+    if size.free_bytes > 0 || wal_bytes > 0 {
+        println!();
+        println!("Run `hstry db vacuum` to reclaim free pages and the WAL.");
+    }
+    Ok(())
+}
+
+async fn cmd_db_vacuum(db: &Database, config: &Config, json: bool) -> Result<()> {
+    let path = &config.database.path;
+    let (file_before, wal_before) = database_file_sizes(path);
+    let before = file_before + wal_before;
+    if !json {
+        println!("Vacuuming {} ({})...", path.display(), format_bytes(before));
+    }
+    db.vacuum().await?;
+    let (file_after, wal_after) = database_file_sizes(path);
+    let after = file_after + wal_after;
+    let freed = before.saturating_sub(after);
+    if json {
+        return emit_json(JsonResponse {
+            ok: true,
+            result: Some(serde_json::json!({
+                "bytes_before": before,
+                "bytes_after": after,
+                "bytes_freed": freed,
+            })),
+            error: None,
+        });
+    }
+    println!(
+        "Vacuumed: {} -> {} (freed {}).",
+        format_bytes(before),
+        format_bytes(after),
+        format_bytes(freed)
+    );
+    Ok(())
+}
+
+/// `hstry db merge`: merge another database with the dedup and conflict
+/// handling of remote sync, its sources namespaced like a remote's.
+async fn cmd_db_merge(
+    db: &Database,
+    config: &Config,
+    path: &Path,
+    namespace: Option<String>,
+    strategy: ConflictStrategy,
+    json: bool,
+) -> Result<()> {
+    use hstry_core::remote;
+
+    let path = Config::expand_path(&path.to_string_lossy());
+    if !path.is_file() {
+        anyhow::bail!("No database at {}", path.display());
+    }
+    if std::fs::canonicalize(&path)? == std::fs::canonicalize(&config.database.path)? {
+        anyhow::bail!("{} is the database being merged into", path.display());
+    }
+    let namespace = match namespace {
+        Some(namespace) => namespace,
+        None => path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+    };
+    if namespace.is_empty() || namespace.contains(':') {
+        anyhow::bail!("Namespace '{namespace}' must be non-empty and contain no ':'");
+    }
+    // An encrypted database can only be read with the configured key, so it
+    // has to have been encrypted with the same one.
+    let encrypted = !hstry_core::db::is_plain_sqlite(&path)?;
+    let key = if encrypted {
+        let Some(key) = config.database.encryption.resolve_key()? else {
+            anyhow::bail!(
+                "{} is encrypted or not an SQLite database; configure [database.encryption] \
+                 with its key to merge it",
+                path.display()
+            );
+        };
+        Some(key)
+    } else {
+        None
+    };
+    let source_opts = hstry_core::db::DatabaseOptions {
+        key,
+        ..Default::default()
+    };
+
+    // Opening a database migrates it, so the merge reads a copy and leaves
+    // the other file as it was.
+    let copy_dir = tempfile::tempdir()?;
+    let copy = copy_dir.path().join("merge.db");
+    std::fs::copy(&path, &copy)?;
+    let wal = PathBuf::from(format!("{}-wal", path.display()));
+    if wal.is_file() {
+        std::fs::copy(&wal, copy_dir.path().join("merge.db-wal"))?;
+    }
+    if !json {
+        println!("Merging {} as '{namespace}'...", path.display());
+    }
+    let mut result = remote::merge_databases_with(
+        db,
+        &copy,
+        &source_opts,
+        &namespace,
+        strategy,
+        remote::MergeOrigin::Remote,
+    )
+    .await
+    .with_context(|| {
+        if encrypted {
+            format!(
+                "Could not merge {}; is it encrypted with the key in [database.encryption]?",
+                path.display()
+            )
+        } else {
+            format!("Could not merge {}", path.display())
+        }
+    })?;
+    for conflict in &mut result.conflicts {
+        conflict.id = db.record_remote_conflict(conflict).await?;
+    }
+
+    if json {
+        return emit_json(JsonResponse {
+            ok: true,
+            result: Some(result),
+            error: None,
+        });
+    }
+    println!(
+        "Added {} conversations, updated {}, {} messages, {} new sources",
+        result.conversations_added,
+        result.conversations_updated,
+        result.messages_added,
+        result.sources_added
+    );
+    if !result.conflicts.is_empty() {
+        let open = result
+            .conflicts
+            .iter()
+            .filter(|c| c.resolved_at.is_none())
+            .count();
+        println!(
+            "{} conflicts settled by {}",
+            result.conflicts.len(),
+            strategy.as_str()
+        );
+        if open > 0 {
+            println!(
+                "{open} kept as duplicates; review with `hstry remote conflicts --remote {namespace}`"
+            );
+        }
+    }
+    Ok(())
+}
+
+async fn cmd_tokens_backfill(
+    db: &Database,
+    source: Option<&str>,
+    tokenizer: Option<&str>,
+    reestimate: bool,
+    dry_run: bool,
+    json: bool,
+) -> Result<()> {
+    let tokenizer = hstry_core::tokens::Tokenizer::new(
+        tokenizer.unwrap_or(hstry_core::tokens::Tokenizer::default_name()),
+    )?;
+    let cancel = CancelToken::install_ctrl_c();
+    let stats =
+        hstry_core::tokens::backfill(db, &tokenizer, source, reestimate, dry_run, &cancel).await?;
+    if json {
+        return emit_json(JsonResponse {
+            ok: true,
+            result: Some(serde_json::json!({
+                "dry_run": dry_run,
+                "stats": stats,
+            })),
+            error: None,
+        });
+    }
 
-    if !status.success() {
-        anyhow::bail!("Agent exited with status: {status}");
+    let verb = if dry_run { "Would count" } else { "Counted" };
+    println!(
+        "{verb} {} tokens in {} message(s) with {}.",
+        stats.tokens, stats.messages, stats.tokenizer
+    );
+    if stats.locked > 0 {
+        println!(
+            "Skipped {} sealed message(s); run `hstry sensitive unlock` to count them.",
+            stats.locked
+        );
+    }
+    if cancel.is_cancelled() {
+        println!("Interrupted; run again to continue.");
+    }
+    Ok(())
+}
+
+fn print_stats_breakdown(heading: &str, label: &str, rows: &[hstry_core::db::StatsBreakdown]) {
+    if rows.is_empty() {
+        return;
     }
+    println!("\x1b[1;34m{heading}\x1b[0m");
+    println!(
+        "  {:<28} {:>7} {:>9} {:>7} {:>12} {:>12} {:>10}",
+        label, "CONVS", "MSGS", "AVG", "TOKENS IN", "TOKENS OUT", "COST"
+    );
+    println!("  {}", "-".repeat(91));
+    for row in rows {
+        let key = row.key.as_deref().unwrap_or("(unknown)");
+        println!(
+            "  {:<28} {:>7} {:>9} {:>7.1} {:>12} {:>12} {:>10}",
+            truncate_title(key, 28),
+            row.conversations,
+            row.messages,
+            row.avg_messages(),
+            row.tokens_in,
+            row.tokens_out,
+            format!("${:.2}", row.cost_usd)
+        );
+    }
+    println!();
+}
 
+async fn cmd_decisions(
+    db: &Database,
+    workspace: Option<String>,
+    limit: usize,
+    json: bool,
+) -> Result<()> {
+    let workspace = workspace.map(|value| format!("%{value}%"));
+    hstry_core::decisions::analyze(db, workspace.as_deref()).await?;
+    let decisions = db.list_decisions(workspace.as_deref(), Some(limit)).await?;
+    if json {
+        return emit_json(JsonResponse {
+            ok: true,
+            result: Some(decisions),
+            error: None,
+        });
+    }
+    if decisions.is_empty() {
+        println!("{}", tr!("cli-no-decisions"));
+        return Ok(());
+    }
+    for decision in &decisions {
+        println!(
+            "{}  {}",
+            decision.decided_at.format("%Y-%m-%d"),
+            decision.text
+        );
+        let title = decision.title.as_deref().unwrap_or("Untitled");
+        match decision.workspace.as_deref() {
+            Some(workspace) => println!("            {title} · {workspace}"),
+            None => println!("            {title}"),
+        }
+    }
     Ok(())
 }
 
-async fn cmd_index(_config: &Config, db: &Database, rebuild: bool, json: bool) -> Result<()> {
-    let total = if rebuild {
-        db.rebuild_search_fts().await?
+/// Conversations listed under an access log entry; more are summarized.
+const ACCESS_LOG_TITLES: usize = 5;
+
+async fn cmd_searches(db: &Database, command: SearchesCommand, json: bool) -> Result<()> {
+    match command {
+        SearchesCommand::List => {
+            let searches = db.list_saved_searches().await?;
+            if json {
+                return emit_json(JsonResponse {
+                    ok: true,
+                    result: Some(searches),
+                    error: None,
+                });
+            }
+            if searches.is_empty() {
+                println!("{}", tr!("cli-no-saved-searches"));
+                return Ok(());
+            }
+            for search in &searches {
+                let filters = serde_json::to_value(&search.filters)?;
+                let filters = filters
+                    .as_object()
+                    .into_iter()
+                    .flatten()
+                    .map(|(key, value)| match value {
+                        serde_json::Value::String(value) => format!("{key}={value}"),
+                        serde_json::Value::Array(values) => {
+                            let values: Vec<&str> =
+                                values.iter().filter_map(|v| v.as_str()).collect();
+                            format!("{key}={}", values.join(","))
+                        }
+                        value => format!("{key}={value}"),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                println!("{:<20} \"{}\"  {filters}", search.name, search.query);
+            }
+            Ok(())
+        }
+        SearchesCommand::Delete { name } => {
+            if !db.delete_saved_search(&name).await? {
+                anyhow::bail!("No saved search named '{name}'");
+            }
+            if json {
+                return emit_json(JsonResponse {
+                    ok: true,
+                    result: Some(serde_json::json!({ "deleted": name })),
+                    error: None,
+                });
+            }
+            println!("{}", tr!("cli-deleted-saved-search", name = name.as_str()));
+            Ok(())
+        }
+    }
+}
+
+async fn cmd_workspace(db: &Database, command: WorkspaceCommand, json: bool) -> Result<()> {
+    // Paths are looked up the way conversations store them.
+    let stored_path = |path: &str| {
+        let expanded = hstry_core::Config::expand_path(path);
+        db.normalize_workspace(Some(&expanded.to_string_lossy()))
+            .unwrap_or_default()
+    };
+    match command {
+        WorkspaceCommand::List => cmd_workspace_list(db, json).await,
+        WorkspaceCommand::Rename { path, name, clear } => {
+            let path = stored_path(&path);
+            let name = if clear { None } else { name };
+            db.rename_workspace(&path, name.as_deref()).await?;
+            if json {
+                return emit_json(JsonResponse {
+                    ok: true,
+                    result: Some(serde_json::json!({ "path": path, "display_name": name })),
+                    error: None,
+                });
+            }
+            match name {
+                Some(name) => println!("{path} is now shown as {name}."),
+                None => println!("Cleared the display name of {path}."),
+            }
+            Ok(())
+        }
+        WorkspaceCommand::Merge { from, into } => {
+            let (from, into) = (stored_path(&from), stored_path(&into));
+            let moved = db.merge_workspaces(&from, &into).await?;
+            if json {
+                return emit_json(JsonResponse {
+                    ok: true,
+                    result: Some(serde_json::json!({
+                        "from": from,
+                        "into": into,
+                        "conversations": moved,
+                    })),
+                    error: None,
+                });
+            }
+            println!("Moved {moved} conversation(s) from {from} into {into}.");
+            Ok(())
+        }
+        WorkspaceCommand::Normalize { dry_run } => cmd_workspace_normalize(db, dry_run, json).await,
+    }
+}
+
+/// A title written, or previewed, by `hstry title generate`.
+#[derive(Debug, Serialize)]
+struct GeneratedTitle {
+    id: uuid::Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    previous: Option<String>,
+    title: String,
+}
+
+/// `hstry title generate`. Conversations with a title are only retitled
+/// with `force`; a failed model request falls back to the heuristic title.
+async fn cmd_title_generate(
+    db: &Database,
+    config: &Config,
+    opts: hstry_core::db::ListConversationsOptions,
+    limit: Option<usize>,
+    llm: bool,
+    force: bool,
+    dry_run: bool,
+    json: bool,
+) -> Result<()> {
+    use hstry_core::titles;
+
+    let titles_config = &config.titles;
+    let endpoint = if llm {
+        Some(hstry_core::llm::Endpoint::for_feature(
+            &config.llm,
+            "titles",
+            hstry_core::llm::SectionEndpoint {
+                section: "[titles]",
+                url: titles_config.endpoint.as_deref(),
+                model: titles_config.model.as_deref(),
+                api_key_env: titles_config.api_key_env.as_deref(),
+                timeout_secs: titles_config.timeout_secs,
+            },
+        )?)
     } else {
-        0
+        None
     };
 
+    // Sensitive conversations are skipped: their messages would reach the
+    // title model, and while locked only the placeholder text is readable.
+    let mut conversations = db
+        .list_conversations(hstry_core::db::ListConversationsOptions {
+            exclude_sensitive: true,
+            ..opts
+        })
+        .await?;
+    conversations.retain(|conv| force || conv.title.as_deref().is_none_or(|t| t.trim().is_empty()));
+    if let Some(limit) = limit {
+        conversations.truncate(limit);
+    }
+
+    let cancel = CancelToken::install_ctrl_c();
+    let mut generated = Vec::new();
+    let mut skipped = 0usize;
+    for conv in conversations {
+        if cancel.is_cancelled() {
+            break;
+        }
+        let messages = db
+            .get_messages_range(
+                conv.id,
+                MessageRange {
+                    limit: Some(titles::OPENING_MESSAGES),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        let mut title = None;
+        if let Some(endpoint) = &endpoint {
+            match titles::generate(endpoint, &messages, titles_config).await {
+                Ok(reply) => title = reply,
+                Err(err) => tracing::warn!("Title model failed for {}: {err}", conv.id),
+            }
+        }
+        let Some(title) = title.or_else(|| titles::heuristic(&messages, titles_config.max_chars))
+        else {
+            skipped += 1;
+            continue;
+        };
+        let previous = conv.title.filter(|t| !t.trim().is_empty());
+        if previous.as_deref() == Some(title.as_str()) {
+            continue;
+        }
+        if !dry_run {
+            db.set_generated_title(conv.id, &title, previous.is_some())
+                .await?;
+        }
+        generated.push(GeneratedTitle {
+            id: conv.id,
+            previous,
+            title,
+        });
+    }
+    if let Some(endpoint) = &endpoint {
+        record_llm_usage(config, endpoint.take_usage()).await;
+    }
+
     if json {
         return emit_json(JsonResponse {
             ok: true,
             result: Some(serde_json::json!({
-                "indexed": total,
-                "rebuild": rebuild,
-                "backend": "sqlite-fts5",
+                "dry_run": dry_run,
+                "titles": generated,
+                "skipped": skipped,
             })),
             error: None,
         });
     }
+    for entry in &generated {
+        let id = entry.id.to_string();
+        match &entry.previous {
+            Some(previous) => println!("{}  {previous} -> {}", &id[..8], entry.title),
+            None => println!("{}  {}", &id[..8], entry.title),
+        }
+    }
+    let verb = if dry_run { "Would title" } else { "Titled" };
+    println!("{verb} {} conversation(s).", generated.len());
+    if skipped > 0 {
+        println!("Skipped {skipped} without a usable user message.");
+    }
+    Ok(())
+}
 
-    if rebuild {
-        println!("Rebuilt SQLite FTS search index ({total} messages).");
+/// `hstry workspace normalize`.
+async fn cmd_workspace_normalize(db: &Database, dry_run: bool, json: bool) -> Result<()> {
+    let changes = db.normalize_stored_workspaces(!dry_run).await?;
+    if json {
+        return emit_json(JsonResponse {
+            ok: true,
+            result: Some(serde_json::json!({ "dry_run": dry_run, "changes": changes })),
+            error: None,
+        });
+    }
+    if changes.is_empty() {
+        println!("All workspaces are normalized.");
+        return Ok(());
+    }
+    for change in &changes {
+        println!(
+            "{} -> {}  ({} conversations)",
+            change.from, change.to, change.conversations
+        );
+    }
+    let conversations: i64 = changes.iter().map(|change| change.conversations).sum();
+    if dry_run {
+        println!(
+            "Would move {conversations} conversation(s) across {} workspace(s).",
+            changes.len()
+        );
     } else {
-        println!("Search uses SQLite FTS5 and stays up to date via triggers.");
+        println!(
+            "Moved {conversations} conversation(s) across {} workspace(s).",
+            changes.len()
+        );
     }
+    Ok(())
+}
 
+/// `hstry workspace list`. Git remotes are looked up for workspaces that
+/// exist on this machine and have none recorded yet.
+async fn cmd_workspace_list(db: &Database, json: bool) -> Result<()> {
+    let mut workspaces = db.list_workspaces().await?;
+    for workspace in &mut workspaces {
+        if workspace.git_remote.is_none()
+            && let Some(remote) =
+                hstry_core::workspaces::git_remote(std::path::Path::new(&workspace.path))
+        {
+            db.set_workspace_git_remote(&workspace.path, &remote)
+                .await?;
+            workspace.git_remote = Some(remote);
+        }
+    }
+    if json {
+        return emit_json(JsonResponse {
+            ok: true,
+            result: Some(workspaces),
+            error: None,
+        });
+    }
+    if workspaces.is_empty() {
+        println!("No workspaces yet.");
+        return Ok(());
+    }
+    for workspace in &workspaces {
+        println!(
+            "{:<24} {:>6}  {}",
+            workspace.label(),
+            workspace.conversations,
+            workspace.path
+        );
+        if let Some(remote) = &workspace.git_remote {
+            println!("{:<24} {:>6}  remote: {remote}", "", "");
+        }
+        if !workspace.aliases.is_empty() {
+            println!(
+                "{:<24} {:>6}  aliases: {}",
+                "",
+                "",
+                workspace.aliases.join(", ")
+            );
+        }
+    }
     Ok(())
 }
 
-async fn cmd_stats(db: &Database, json: bool) -> Result<()> {
-    let sources = db.list_sources().await?;
-    let conv_count = db.count_conversations().await?;
-    let msg_count = db.count_messages().await?;
-    let sources_count = i64::try_from(sources.len()).unwrap_or(i64::MAX);
-    let per_source = db.get_source_stats().await?;
-    let activity = db.get_activity_stats(30).await?;
+async fn cmd_access(db: &Database, command: AccessCommand, json: bool) -> Result<()> {
+    let AccessCommand::Log {
+        channel,
+        caller,
+        conversation,
+        after,
+        limit,
+    } = command;
+    let conversation_id = match conversation {
+        Some(reference) => Some(resolve_conversation_by_id(db, &reference).await?.id),
+        None => None,
+    };
+    let entries = db
+        .list_access_log(&hstry_core::db::AccessLogOptions {
+            channel,
+            caller,
+            conversation_id,
+            after: after.as_deref().map(parse_date_filter).transpose()?,
+            limit: Some(limit),
+        })
+        .await?;
+    if json {
+        return emit_json(JsonResponse {
+            ok: true,
+            result: Some(entries),
+            error: None,
+        });
+    }
+    if entries.is_empty() {
+        println!("No reads recorded.");
+        return Ok(());
+    }
+
+    let mut titles: HashMap<uuid::Uuid, String> = HashMap::new();
+    for entry in &entries {
+        let caller = entry.caller.as_deref().unwrap_or("-");
+        let query = entry
+            .query
+            .as_deref()
+            .map(|query| format!(" \"{}\"", truncate_title(query, 40)))
+            .unwrap_or_default();
+        println!(
+            "{}  {:<3}  {:<16} {}{query}  ({} conversations)",
+            entry
+                .accessed_at
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M:%S"),
+            entry.channel,
+            truncate_title(caller, 16),
+            entry.action,
+            entry.conversation_ids.len(),
+        );
+        if entry.conversation_ids.len() > ACCESS_LOG_TITLES {
+            continue;
+        }
+        for id in &entry.conversation_ids {
+            if !titles.contains_key(id) {
+                let title = match db.get_conversation(*id).await? {
+                    Some(conv) => format!(
+                        "{}  {}",
+                        conv.readable_id.unwrap_or_else(|| id.to_string()),
+                        truncate_title(conv.title.as_deref().unwrap_or("Untitled"), 60)
+                    ),
+                    None => format!("{id}  (deleted)"),
+                };
+                titles.insert(*id, title);
+            }
+            println!("    {}", titles[id]);
+        }
+    }
+    Ok(())
+}
+
+/// The sensitive-key passphrase from `[sensitive] passphrase_env`, or read
+/// from the terminal (twice when `confirm`).
+fn sensitive_passphrase(config: &Config, confirm: bool) -> Result<String> {
+    let env = &config.sensitive.passphrase_env;
+    if let Ok(passphrase) = std::env::var(env)
+        && !passphrase.is_empty()
+    {
+        return Ok(passphrase);
+    }
+    let term = console::Term::stderr();
+    if !term.is_term() {
+        anyhow::bail!("No terminal to read the passphrase from; set {env}");
+    }
+    term.write_str("Passphrase: ")?;
+    let passphrase = term.read_secure_line()?;
+    if confirm {
+        term.write_str("Repeat passphrase: ")?;
+        if term.read_secure_line()? != passphrase {
+            anyhow::bail!("The passphrases do not match");
+        }
+    }
+    Ok(passphrase)
+}
 
+async fn cmd_sensitive(
+    config: &Config,
+    db: &Database,
+    command: SensitiveCommand,
+    json: bool,
+) -> Result<()> {
+    let db_path = &config.database.path;
+    if !matches!(command, SensitiveCommand::Init | SensitiveCommand::Status)
+        && !db.has_sensitive_key()
+    {
+        anyhow::bail!("No sensitive key yet; create one with `hstry sensitive init`");
+    }
+    let result = match command {
+        SensitiveCommand::Init => {
+            if db.has_sensitive_key() {
+                anyhow::bail!("This database already has a sensitive key");
+            }
+            let key = sensitive::generate_key(&sensitive_passphrase(config, true)?)?;
+            db.create_sensitive_key(&key).await?;
+            let sealed = db.seal_sensitive_conversations().await?;
+            if !json {
+                println!("Created the sensitive key.");
+                if sealed > 0 {
+                    println!("Encrypted {sealed} sensitive conversation(s).");
+                }
+                if config.sensitive.is_empty() {
+                    println!(
+                        "Mark sources or workspaces as sensitive under [sensitive] in the config."
+                    );
+                }
+            }
+            serde_json::json!({ "sealed": sealed })
+        }
+        SensitiveCommand::Unlock { minutes } => {
+            let wrapped = db.wrapped_sensitive_identity().await?.unwrap_or_default();
+            let identity =
+                sensitive::unwrap_identity(&wrapped, &sensitive_passphrase(config, false)?)?;
+            let minutes = minutes.unwrap_or(config.sensitive.unlock_minutes);
+            let until = chrono::Utc::now() + chrono::Duration::minutes(i64::from(minutes));
+            sensitive::write_session(db_path, &identity, until)?;
+            if !json {
+                println!(
+                    "Sensitive conversations are readable until {}.",
+                    until.with_timezone(&chrono::Local).format("%H:%M")
+                );
+            }
+            serde_json::json!({ "unlocked_until": until })
+        }
+        SensitiveCommand::Lock => {
+            let was_unlocked = sensitive::clear_session(db_path)?;
+            if !json {
+                println!(
+                    "{}",
+                    if was_unlocked {
+                        "Locked sensitive conversations."
+                    } else {
+                        "Sensitive conversations were already locked."
+                    }
+                );
+            }
+            serde_json::json!({ "was_unlocked": was_unlocked })
+        }
+        SensitiveCommand::Seal => {
+            let sealed = db.seal_sensitive_conversations().await?;
+            if !json {
+                println!("Encrypted {sealed} sensitive conversation(s).");
+            }
+            serde_json::json!({ "sealed": sealed })
+        }
+        SensitiveCommand::Status => {
+            let conversations = db.sensitive_conversation_ids().await?.len();
+            let unlocked_until = sensitive::read_session(db_path).map(|(_, until)| until);
+            if !json {
+                if !db.has_sensitive_key() {
+                    println!("No sensitive key; create one with `hstry sensitive init`.");
+                } else if let Some(until) = unlocked_until {
+                    println!(
+                        "Unlocked until {}.",
+                        until.with_timezone(&chrono::Local).format("%H:%M")
+                    );
+                } else {
+                    println!("Locked.");
+                }
+                println!("Sensitive conversations: {conversations}");
+                for source in &config.sensitive.sources {
+                    println!("  source     {source}");
+                }
+                for workspace in &config.sensitive.workspaces {
+                    println!("  workspace  {workspace}");
+                }
+            }
+            serde_json::json!({
+                "has_key": db.has_sensitive_key(),
+                "unlocked_until": unlocked_until,
+                "conversations": conversations,
+                "sources": config.sensitive.sources,
+                "workspaces": config.sensitive.workspaces,
+            })
+        }
+    };
     if json {
-        return emit_json(JsonResponse {
+        emit_json(JsonResponse {
             ok: true,
-            result: Some(StatsSummary {
-                sources: sources_count,
-                conversations: conv_count,
-                messages: msg_count,
-                per_source,
-                activity,
-            }),
+            result: Some(result),
             error: None,
-        });
+        })?;
     }
+    Ok(())
+}
 
-    // Header
-    println!("\x1b[1mDatabase Statistics\x1b[0m");
-    println!();
-
-    // Totals
-    println!("\x1b[1;34mTotals\x1b[0m");
-    println!("  Sources:       {sources_count}");
-    println!("  Conversations: {conv_count}");
-    println!("  Messages:      {msg_count}");
-    println!();
-
-    // Activity
-    println!("\x1b[1;34mActivity\x1b[0m");
-    println!("  Today:      {:>6} conversations", activity.today);
-    println!("  This week:  {:>6} conversations", activity.week);
-    println!("  This month: {:>6} conversations", activity.month);
-    println!();
-
-    // Per-source stats
-    if !per_source.is_empty() {
-        println!("\x1b[1;34mPer Source\x1b[0m");
-        println!(
-            "  {:<15} {:<12} {:>8} {:>10} {:>12}",
-            "SOURCE", "ADAPTER", "CONVS", "MSGS", "LAST SYNC"
-        );
-        println!("  {}", "-".repeat(60));
-        for stats in &per_source {
-            let last_sync = stats
-                .last_sync_at
-                .map(pretty::relative_time_short)
-                .unwrap_or_else(|| "never".to_string());
+async fn cmd_api(db: &Database, command: ApiCommand, json: bool) -> Result<()> {
+    let ApiCommand::Token { command } = command;
+    match command {
+        ApiTokenCommand::Create { name, scope } => {
+            let name = name.trim();
+            if name.is_empty() {
+                anyhow::bail!("Token name must not be empty");
+            }
+            if db
+                .list_api_tokens(false)
+                .await?
+                .iter()
+                .any(|token| token.name == name)
+            {
+                anyhow::bail!("A token named '{name}' already exists");
+            }
+            let secret = api_token::generate_token();
+            let token = db
+                .create_api_token(
+                    name,
+                    scope.into(),
+                    &api_token::hash_token(&secret),
+                    api_token::display_prefix(&secret),
+                )
+                .await?;
+            if json {
+                return emit_json(JsonResponse {
+                    ok: true,
+                    result: Some(serde_json::json!({ "token": token, "secret": secret })),
+                    error: None,
+                });
+            }
             println!(
-                "  {:<15} {:<12} {:>8} {:>10} {:>12}",
-                truncate_title(&stats.source_id, 15),
-                truncate_title(&stats.adapter, 12),
-                stats.conversations,
-                stats.messages,
-                last_sync
+                "Created {scope} token #{id} '{name}':",
+                scope = token.scope.as_str(),
+                id = token.id,
+                name = token.name,
             );
+            println!();
+            println!("    {secret}");
+            println!();
+            println!("Store it now; it cannot be shown again.");
+        }
+        ApiTokenCommand::List { all } => {
+            let tokens = db.list_api_tokens(all).await?;
+            if json {
+                return emit_json(JsonResponse {
+                    ok: true,
+                    result: Some(tokens),
+                    error: None,
+                });
+            }
+            if tokens.is_empty() {
+                println!("No API tokens. Create one with `hstry api token create <name>`.");
+                return Ok(());
+            }
+            for token in &tokens {
+                let last_used = token.last_used_at.map_or_else(
+                    || "never".to_string(),
+                    |at| at.format("%Y-%m-%d %H:%M").to_string(),
+                );
+                let revoked = token
+                    .revoked_at
+                    .map(|at| format!(", revoked {}", at.format("%Y-%m-%d %H:%M")))
+                    .unwrap_or_default();
+                println!(
+                    "#{id} {name} [{scope}] {prefix}... created {created}, last used {last_used}{revoked}",
+                    id = token.id,
+                    name = token.name,
+                    scope = token.scope.as_str(),
+                    prefix = token.prefix,
+                    created = token.created_at.format("%Y-%m-%d %H:%M"),
+                );
+            }
+        }
+        ApiTokenCommand::Revoke { token } => {
+            let revoked = db.revoke_api_token(&token).await?;
+            if revoked == 0 {
+                anyhow::bail!("No active API token '{token}'");
+            }
+            if json {
+                return emit_json(JsonResponse {
+                    ok: true,
+                    result: Some(serde_json::json!({ "revoked": revoked })),
+                    error: None,
+                });
+            }
+            println!("Revoked API token '{token}'.");
         }
-        println!();
     }
+    Ok(())
+}
 
-    // Date range
-    let oldest = per_source.iter().filter_map(|s| s.oldest).min();
-    let newest = per_source.iter().filter_map(|s| s.newest).max();
-    if let (Some(oldest), Some(newest)) = (oldest, newest) {
-        println!("\x1b[1;34mDate Range\x1b[0m");
-        println!("  Oldest: {}", oldest.format("%Y-%m-%d"));
-        println!("  Newest: {}", newest.format("%Y-%m-%d"));
+async fn cmd_tools(db: &Database, command: ToolsCommand, json: bool) -> Result<()> {
+    match command {
+        ToolsCommand::List {
+            name,
+            source,
+            workspace,
+            errors,
+            after,
+            limit,
+        } => {
+            let calls = db
+                .list_tool_calls(&ListToolCallsOptions {
+                    tool_name: name,
+                    source_id: source,
+                    workspace,
+                    status: errors.then(|| "error".to_string()),
+                    after: after.as_deref().map(parse_date_filter).transpose()?,
+                    limit: Some(limit),
+                })
+                .await?;
+            if json {
+                return emit_json(JsonResponse {
+                    ok: true,
+                    result: Some(calls),
+                    error: None,
+                });
+            }
+            if calls.is_empty() {
+                println!(
+                    "No tool calls found. Run `hstry tools backfill` if you synced before tool calls were recorded."
+                );
+                return Ok(());
+            }
+            for entry in calls {
+                let call = &entry.tool_call;
+                let at = entry
+                    .created_at
+                    .map(pretty::relative_time_short)
+                    .unwrap_or_else(|| "-".to_string());
+                let status = call.status.as_ref().map_or("-", |s| s.as_str());
+                let input = call
+                    .input
+                    .as_ref()
+                    .map(|input| truncate_title(&input.to_string(), 60))
+                    .unwrap_or_default();
+                println!(
+                    "{at:>8}  {name:<20} {status:<8} {conv}  {input}",
+                    name = truncate_title(&call.tool_name, 20),
+                    conv = &entry.conversation_id.to_string()[..8],
+                );
+            }
+        }
+        ToolsCommand::Stats {
+            source,
+            workspace,
+            after,
+            limit,
+        } => {
+            let usage = db
+                .tool_usage_stats(&ListToolCallsOptions {
+                    source_id: source,
+                    workspace,
+                    after: after.as_deref().map(parse_date_filter).transpose()?,
+                    limit: Some(limit),
+                    ..Default::default()
+                })
+                .await?;
+            if json {
+                return emit_json(JsonResponse {
+                    ok: true,
+                    result: Some(usage),
+                    error: None,
+                });
+            }
+            if usage.is_empty() {
+                println!(
+                    "No tool calls found. Run `hstry tools backfill` if you synced before tool calls were recorded."
+                );
+                return Ok(());
+            }
+            println!(
+                "  {:<24} {:>8} {:>8} {:>8} {:>10}",
+                "TOOL", "CALLS", "ERRORS", "CONVS", "AVG MS"
+            );
+            println!("  {}", "-".repeat(62));
+            for tool in &usage {
+                let avg = tool
+                    .avg_duration_ms
+                    .map(|ms| format!("{ms:.0}"))
+                    .unwrap_or_else(|| "-".to_string());
+                println!(
+                    "  {:<24} {:>8} {:>8} {:>8} {:>10}",
+                    truncate_title(&tool.tool_name, 24),
+                    tool.calls,
+                    tool.errors,
+                    tool.conversations,
+                    avg
+                );
+            }
+        }
+        ToolsCommand::Backfill => {
+            let stored = db.rebuild_tool_calls().await?;
+            if json {
+                return emit_json(JsonResponse {
+                    ok: true,
+                    result: Some(serde_json::json!({ "tool_calls": stored })),
+                    error: None,
+                });
+            }
+            println!("Recorded {stored} tool calls");
+        }
     }
-
     Ok(())
 }
 
 #[derive(Debug, Serialize)]
 struct DedupResult {
+    strategy: DedupStrategyArg,
     duplicates_found: usize,
     conversations_removed: usize,
     messages_removed: usize,
     dry_run: bool,
+    /// Duplicates that may be removed, each with the conversation it
+    /// duplicates.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pairs: Vec<DedupPair>,
+}
+
+#[derive(Debug, Serialize)]
+struct DedupPair {
+    keep: uuid::Uuid,
+    duplicate: uuid::Uuid,
+    similarity: f32,
+}
+
+/// A conversation scanned by `hstry dedup`.
+struct DedupCandidate {
+    conversation: Conversation,
+    messages: usize,
+    /// MinHash of the message text, for fuzzy matching.
+    signature: Option<hstry_core::near_duplicates::Signature>,
+}
+
+impl DedupCandidate {
+    fn updated(&self) -> chrono::DateTime<chrono::Utc> {
+        let conv = &self.conversation;
+        conv.updated_at.unwrap_or(conv.created_at)
+    }
 }
 
 async fn cmd_dedup(
     db: &Database,
     dry_run: bool,
     source_filter: Option<String>,
+    include_starred: bool,
+    strategy: DedupStrategyArg,
+    threshold: f32,
+    interactive: bool,
     json: bool,
 ) -> Result<()> {
     use std::collections::HashMap;
     use std::collections::hash_map::DefaultHasher;
     use std::hash::{Hash, Hasher};
 
+    use hstry_core::near_duplicates::{self, Signature};
+
+    if !(threshold > 0.0 && threshold <= 1.0) {
+        anyhow::bail!("--threshold must be above 0 and at most 1");
+    }
+    if interactive && json {
+        anyhow::bail!("--interactive asks on the terminal; drop --json");
+    }
+
     let opts = hstry_core::db::ListConversationsOptions {
         source_id: source_filter,
         workspace: None,
         after: None,
         before: None,
+        ingested_after: None,
+        model: None,
+        provider: None,
+        harness: None,
+        role: None,
+        exclude_sensitive: false,
+        starred: false,
         limit: None,
+        offset: None,
     };
 
     let conversations = db.list_conversations(opts).await?;
+    let starred = if include_starred {
+        HashSet::new()
+    } else {
+        db.starred_conversation_ids().await?
+    };
+    let cancel = CancelToken::install_ctrl_c();
 
     if !json {
         println!(
@@ -5607,48 +11014,79 @@ async fn cmd_dedup(
         );
     }
 
-    // Group conversations by a hash of their full content
-    let mut groups: HashMap<u64, Vec<Conversation>> = HashMap::new();
-
+    // Group conversations by a hash of their full content, or by fuzzy
+    // similarity within a source
+    let mut candidates: Vec<DedupCandidate> = Vec::with_capacity(conversations.len());
+    let mut exact: HashMap<u64, Vec<usize>> = HashMap::new();
     for conv in conversations {
+        cancel.check()?;
         let messages = db.get_messages(conv.id).await?;
 
-        // Hash all message content for accurate dedup
-        let mut hasher = DefaultHasher::new();
-        conv.source_id.hash(&mut hasher);
-        for msg in &messages {
-            msg.role.to_string().hash(&mut hasher);
-            msg.content.hash(&mut hasher);
-        }
-        let hash = hasher.finish();
-
-        groups.entry(hash).or_default().push(conv);
+        let signature = if strategy == DedupStrategyArg::Fuzzy {
+            Signature::of_messages(&messages)
+        } else {
+            // Hash all message content for accurate dedup
+            let mut hasher = DefaultHasher::new();
+            conv.source_id.hash(&mut hasher);
+            for msg in &messages {
+                msg.role.to_string().hash(&mut hasher);
+                msg.content.hash(&mut hasher);
+            }
+            exact
+                .entry(hasher.finish())
+                .or_default()
+                .push(candidates.len());
+            None
+        };
+        candidates.push(DedupCandidate {
+            conversation: conv,
+            messages: messages.len(),
+            signature,
+        });
     }
 
-    // Find groups with duplicates
-    let mut duplicates_found = 0usize;
-    let mut to_remove: Vec<uuid::Uuid> = Vec::new();
-
-    for (_key, mut convs) in groups {
-        if convs.len() > 1 {
-            duplicates_found += convs.len() - 1;
-            // Sort by updated_at descending, keep the most recent
-            convs.sort_by(|a, b| {
-                let a_time = a.updated_at.unwrap_or(a.created_at);
-                let b_time = b.updated_at.unwrap_or(b.created_at);
-                b_time.cmp(&a_time)
+    let groups: Vec<Vec<usize>> = if strategy == DedupStrategyArg::Fuzzy {
+        let signatures: Vec<Option<Signature>> =
+            candidates.iter().map(|c| c.signature.clone()).collect();
+        let same_source = near_duplicates::similar_pairs(&signatures, threshold)
+            .into_iter()
+            .map(|(a, b, _)| (a, b))
+            .filter(|&(a, b)| {
+                candidates[a].conversation.source_id == candidates[b].conversation.source_id
             });
-            // Keep first (most recent), mark rest for removal
-            for conv in convs.into_iter().skip(1) {
-                to_remove.push(conv.id);
-            }
-        }
-    }
+        near_duplicates::clusters(candidates.len(), same_source)
+    } else {
+        exact.into_values().collect()
+    };
 
-    if !json && !to_remove.is_empty() {
+    let (duplicates_found, pairs) = dedup_pairs(&candidates, groups, &starred, threshold);
+
+    if !json && !pairs.is_empty() {
         println!("Found {} duplicate conversations", duplicates_found);
     }
 
+    let by_id: HashMap<uuid::Uuid, &DedupCandidate> =
+        candidates.iter().map(|c| (c.conversation.id, c)).collect();
+    let to_remove: Vec<uuid::Uuid> = if interactive {
+        review_duplicates(&pairs, &by_id)?
+    } else {
+        pairs.iter().map(|pair| pair.duplicate).collect()
+    };
+    if strategy == DedupStrategyArg::Fuzzy && dry_run && !json {
+        let title = |id: &uuid::Uuid| {
+            let title = by_id[id].conversation.title.as_deref();
+            truncate_title(title.unwrap_or("(untitled)"), 40)
+        };
+        for pair in &pairs {
+            println!(
+                "  {:>3.0}%  {}  duplicates  {}",
+                pair.similarity * 100.0,
+                title(&pair.duplicate),
+                title(&pair.keep)
+            );
+        }
+    }
+
     // Count messages that will be removed (use lightweight count query)
     let mut messages_removed = 0usize;
     for conv_id in &to_remove {
@@ -5658,40 +11096,178 @@ async fn cmd_dedup(
     }
 
     if !dry_run && !to_remove.is_empty() {
-        // Batch delete all duplicates in a single transaction
-        db.delete_conversations_batch(&to_remove).await?;
+        db.snapshot_conversations(&to_remove, "dedup").await?;
+        // Batch delete all duplicates in a single transaction; a cancel
+        // before commit rolls the whole batch back.
+        cancel
+            .run(db.delete_conversations_batch(&to_remove))
+            .await??;
     }
 
     let result = DedupResult {
+        strategy,
         duplicates_found,
         conversations_removed: to_remove.len(),
         messages_removed,
         dry_run,
+        pairs,
+    };
+
+    if json {
+        return emit_json(JsonResponse {
+            ok: true,
+            result: Some(result),
+            error: None,
+        });
+    }
+
+    if result.pairs.is_empty() {
+        println!("No duplicates found.");
+    } else if to_remove.is_empty() {
+        println!("Kept all conversations.");
+    } else if dry_run {
+        println!(
+            "Would remove {} conversations ({} messages)",
+            result.conversations_removed, result.messages_removed
+        );
+        println!("Run without --dry-run to actually remove them.");
+    } else {
+        println!(
+            "Removed {} duplicate conversations ({} messages)",
+            result.conversations_removed, result.messages_removed
+        );
+        println!("Each was saved as a snapshot first; see `hstry snapshot list`.");
+    }
+
+    Ok(())
+}
+
+/// The duplicate pairs in `groups`, most similar first, and how many
+/// duplicates were found including starred ones, which are never paired
+/// for removal. Each group keeps its starred conversation first, then the
+/// longest and the most recently updated. Fuzzy groups are joined
+/// transitively, so members less than `threshold` similar to the kept
+/// conversation are left out.
+fn dedup_pairs(
+    candidates: &[DedupCandidate],
+    groups: Vec<Vec<usize>>,
+    starred: &HashSet<uuid::Uuid>,
+    threshold: f32,
+) -> (usize, Vec<DedupPair>) {
+    let mut duplicates_found = 0usize;
+    let mut pairs: Vec<DedupPair> = Vec::new();
+    for mut group in groups {
+        if group.len() < 2 {
+            continue;
+        }
+        group.sort_by(|&a, &b| {
+            let (a, b) = (&candidates[a], &candidates[b]);
+            starred
+                .contains(&b.conversation.id)
+                .cmp(&starred.contains(&a.conversation.id))
+                .then(b.messages.cmp(&a.messages))
+                .then(b.updated().cmp(&a.updated()))
+        });
+        let keep = &candidates[group[0]];
+        for &i in &group[1..] {
+            let duplicate = &candidates[i];
+            let similarity = match (&keep.signature, &duplicate.signature) {
+                (Some(a), Some(b)) => a.similarity(b),
+                _ => 1.0,
+            };
+            if similarity < threshold {
+                continue;
+            }
+            duplicates_found += 1;
+            if starred.contains(&duplicate.conversation.id) {
+                continue;
+            }
+            pairs.push(DedupPair {
+                keep: keep.conversation.id,
+                duplicate: duplicate.conversation.id,
+                similarity,
+            });
+        }
+    }
+    pairs.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+    (duplicates_found, pairs)
+}
+
+/// Ask which conversation of each duplicate pair to remove. Pairs touching
+/// a conversation already chosen for removal are skipped.
+fn review_duplicates(
+    pairs: &[DedupPair],
+    by_id: &HashMap<uuid::Uuid, &DedupCandidate>,
+) -> Result<Vec<uuid::Uuid>> {
+    let describe = |label: &str, id: &uuid::Uuid| {
+        let candidate = by_id[id];
+        let title = candidate.conversation.title.as_deref();
+        eprintln!(
+            "  {label}: {} — {} messages, updated {}",
+            truncate_title(title.unwrap_or("(untitled)"), 50),
+            candidate.messages,
+            candidate.updated().format("%Y-%m-%d %H:%M")
+        );
     };
 
+    let mut removed: Vec<uuid::Uuid> = Vec::new();
+    for (i, pair) in pairs.iter().enumerate() {
+        if removed.contains(&pair.keep) || removed.contains(&pair.duplicate) {
+            continue;
+        }
+        eprintln!();
+        eprintln!(
+            "[{}/{}] {:.0}% similar, source {}",
+            i + 1,
+            pairs.len(),
+            pair.similarity * 100.0,
+            by_id[&pair.keep].conversation.source_id
+        );
+        describe("original ", &pair.keep);
+        describe("duplicate", &pair.duplicate);
+
+        loop {
+            eprint!("Remove the [d]uplicate, the [o]riginal, [k]eep both or [q]uit? ");
+            std::io::stderr().flush()?;
+            let mut input = String::new();
+            let read = std::io::stdin().read_line(&mut input)?;
+            match input.trim().to_lowercase().as_str() {
+                "d" | "duplicate" => removed.push(pair.duplicate),
+                "o" | "original" => removed.push(pair.keep),
+                "k" | "keep" => {}
+                "q" | "quit" => return Ok(removed),
+                _ if read == 0 => return Ok(removed),
+                _ => continue,
+            }
+            break;
+        }
+    }
+    Ok(removed)
+}
+
+async fn cmd_link_detect(db: &Database, threshold: f32, dry_run: bool, json: bool) -> Result<()> {
+    if !(threshold > 0.0 && threshold <= 1.0) {
+        anyhow::bail!("--threshold must be above 0 and at most 1");
+    }
+    let cancel = CancelToken::install_ctrl_c();
+    let stats = hstry_core::links::detect(db, threshold, dry_run, &cancel).await?;
     if json {
         return emit_json(JsonResponse {
             ok: true,
-            result: Some(result),
+            result: Some(serde_json::json!({
+                "dry_run": dry_run,
+                "stats": stats,
+            })),
             error: None,
         });
     }
 
-    if to_remove.is_empty() {
-        println!("No duplicates found.");
-    } else if dry_run {
-        println!(
-            "Would remove {} conversations ({} messages)",
-            result.conversations_removed, result.messages_removed
-        );
-        println!("Run without --dry-run to actually remove them.");
-    } else {
-        println!(
-            "Removed {} duplicate conversations ({} messages)",
-            result.conversations_removed, result.messages_removed
-        );
-    }
-
+    println!(
+        "Scanned {} conversations: {} copies by external id, {} by platform id, {} by content.",
+        stats.scanned, stats.external_id, stats.platform_id, stats.content
+    );
+    let verb = if dry_run { "Would link" } else { "Linked" };
+    println!("{verb} {} new pair(s).", stats.added);
     Ok(())
 }
 
@@ -5721,6 +11297,7 @@ async fn cmd_reseed(
     bulk_mode: bool,
     dry_run: bool,
     drop_source: bool,
+    cancel: &CancelToken,
     json: bool,
 ) -> Result<()> {
     let Some(source) = db.get_source(source_id).await? else {
@@ -5826,17 +11403,33 @@ async fn cmd_reseed(
         Some(bar)
     };
 
-    let stats = if let Some(bar) = pb.as_ref() {
-        let cb_box: Box<dyn Fn(usize, usize) + Send + Sync> =
-            Box::new(move |convs: usize, msgs: usize| {
+    let imported = if let Some(bar) = pb.as_ref() {
+        let cb_box: Box<dyn Fn(sync::SyncProgress) + Send + Sync> =
+            Box::new(move |progress: sync::SyncProgress| {
                 bar.set_message(format!(
-                    "Importing... {convs} conversations / {msgs} messages"
+                    "Importing... {} parsed, {} conversations / {} messages written",
+                    progress.parsed, progress.conversations, progress.messages
                 ));
             });
         let cb_ref: sync::ProgressCallback<'_> = cb_box.as_ref();
-        sync::sync_source_with_progress(db, runner, &reimport_source, Some(cb_ref)).await?
+        sync::sync_source_with_progress(db, runner, &reimport_source, Some(cb_ref), Some(cancel))
+            .await
     } else {
-        sync::sync_source_with_progress(db, runner, &reimport_source, None).await?
+        sync::sync_source_with_progress(db, runner, &reimport_source, None, Some(cancel)).await
+    };
+    let stats = match imported {
+        Ok(stats) => stats,
+        Err(err) => {
+            // Never leave the database without its indexes: a cancelled or
+            // failed bulk import must still restore them before bailing.
+            if let Some(bar) = pb {
+                bar.abandon_with_message("Import interrupted");
+            }
+            if bulk_mode {
+                db.end_bulk_reseed().await?;
+            }
+            return Err(err);
+        }
     };
     if let Some(bar) = pb {
         bar.finish_with_message(format!(
@@ -5846,7 +11439,7 @@ async fn cmd_reseed(
     }
 
     let mut deduped = 0i64;
-    if do_dedup {
+    if do_dedup && !cancel.is_cancelled() {
         let pb = if json {
             None
         } else {
@@ -5872,7 +11465,7 @@ async fn cmd_reseed(
     }
 
     let mut indexed = 0usize;
-    if do_index {
+    if do_index && !cancel.is_cancelled() {
         let pb = if json {
             None
         } else {
@@ -5931,11 +11524,90 @@ struct VerifyResult {
     total_repaired: usize,
 }
 
+async fn cmd_repair(db: &Database, command: RepairCommand, json_output: bool) -> Result<()> {
+    match command {
+        RepairCommand::Segment {
+            source,
+            gap,
+            pattern,
+            min_messages,
+            dry_run,
+        } => {
+            if db.get_source(&source).await?.is_none() {
+                anyhow::bail!("Source not found: {source}");
+            }
+            if gap == 0 && pattern.is_empty() {
+                anyhow::bail!("Nothing to split on: pass --gap or --pattern");
+            }
+            let gap = (gap > 0).then(|| chrono::Duration::minutes(i64::from(gap)));
+            let rules = hstry_core::segment::SegmentRules::new(gap, &pattern, min_messages)?;
+            let plans = hstry_core::segment::segment_source(db, &source, &rules, dry_run).await?;
+
+            if json_output {
+                return emit_json(JsonResponse {
+                    ok: true,
+                    result: Some(plans),
+                    error: None,
+                });
+            }
+            if plans.is_empty() {
+                println!("No conversation in {source} holds more than one session.");
+                return Ok(());
+            }
+            for plan in &plans {
+                let name = plan
+                    .readable_id
+                    .clone()
+                    .unwrap_or_else(|| plan.conversation_id.to_string());
+                println!(
+                    "{name}  {}  ({} sessions)",
+                    plan.title.as_deref().unwrap_or("Untitled"),
+                    plan.sessions.len()
+                );
+                for (n, session) in plan.sessions.iter().enumerate() {
+                    let time = |at: Option<chrono::DateTime<chrono::Utc>>| {
+                        at.map_or_else(
+                            || "?".to_string(),
+                            |at| {
+                                at.with_timezone(&chrono::Local)
+                                    .format("%m-%d %H:%M")
+                                    .to_string()
+                            },
+                        )
+                    };
+                    println!(
+                        "  {}. {} – {}  {:>4} messages  {}",
+                        n + 1,
+                        time(session.started_at),
+                        time(session.ended_at),
+                        session.messages,
+                        session.first_prompt.as_deref().unwrap_or("")
+                    );
+                }
+            }
+            let sessions: usize = plans.iter().map(|plan| plan.sessions.len()).sum();
+            if dry_run {
+                println!(
+                    "\nWould split {} conversation(s) into {sessions} sessions. Run without --dry-run to apply.",
+                    plans.len()
+                );
+            } else {
+                println!(
+                    "\nSplit {} conversation(s) into {sessions} sessions.",
+                    plans.len()
+                );
+            }
+            Ok(())
+        }
+    }
+}
+
 async fn cmd_verify(
     db: &Database,
     runner: &AdapterRunner,
     source_filter: Option<String>,
     repair: bool,
+    cancel: &CancelToken,
     json: bool,
 ) -> Result<()> {
     let mut targets: Vec<Source> = Vec::new();
@@ -5956,7 +11628,8 @@ async fn cmd_verify(
     };
 
     for source in &targets {
-        let Some(adapter_path) = runner.find_adapter(&source.adapter) else {
+        cancel.check()?;
+        let Ok(adapter_path) = sync::source_adapter_path(runner, source) else {
             continue;
         };
         let Some(path) = source.path.as_ref() else {
@@ -5974,6 +11647,8 @@ async fn cmd_verify(
                     include_attachments: false,
                     cursor: None,
                     batch_size: None,
+                    options: source.config.get("options").cloned(),
+                    unchanged_files: None,
                 },
             )
             .await
@@ -5997,9 +11672,11 @@ async fn cmd_verify(
         let mut repaired = false;
         if drifted && repair {
             // Reseed the drifted source. Use the same defaults as `cmd_reseed`.
-            cmd_reseed(db, runner, &source.id, true, true, true, false, false, true)
-                .await
-                .ok();
+            cmd_reseed(
+                db, runner, &source.id, true, true, true, false, false, cancel, true,
+            )
+            .await
+            .ok();
             repaired = true;
             report.total_repaired += 1;
         }
@@ -6041,7 +11718,7 @@ async fn cmd_verify(
 // Config Commands
 // =============================================================================
 
-fn cmd_config(
+async fn cmd_config(
     config: &Config,
     config_path: &Path,
     command: Option<ConfigCommand>,
@@ -6117,6 +11794,98 @@ fn cmd_config(
                 anyhow::bail!("Editor exited with non-zero status");
             }
         }
+
+        ConfigCommand::ExportBundle { output } => {
+            let db = Database::open_config(config).await?;
+            let bundle = ConfigBundle::new(config, db.list_tag_names().await?);
+            let content = bundle.to_toml()?;
+            match &output {
+                Some(path) => std::fs::write(path, &content)?,
+                None if !json => print!("{content}"),
+                None => {}
+            }
+            if json {
+                return emit_json(JsonResponse {
+                    ok: true,
+                    result: Some(serde_json::json!({
+                        "output": output,
+                        "bundle": bundle,
+                    })),
+                    error: None,
+                });
+            }
+            if let Some(path) = output {
+                eprintln!(
+                    "Wrote config bundle ({} sources, {} adapter repos, {} tags) to {}",
+                    bundle.config.sources.len(),
+                    bundle.config.adapter_repos.len(),
+                    bundle.tags.len(),
+                    path.display()
+                );
+            }
+        }
+
+        ConfigCommand::ImportBundle { path, yes } => {
+            let bundle = ConfigBundle::from_toml(&std::fs::read_to_string(&path)?)?;
+            let summary = format!(
+                "{} sources, {} adapter repos, {} remotes, {} tags (exported {})",
+                bundle.config.sources.len(),
+                bundle.config.adapter_repos.len(),
+                bundle.config.remotes.len(),
+                bundle.tags.len(),
+                bundle.exported_at.format("%Y-%m-%d %H:%M UTC"),
+            );
+            if !yes {
+                if json {
+                    return emit_json(JsonResponse {
+                        ok: true,
+                        result: Some(serde_json::json!({
+                            "applied": false,
+                            "configPath": config_path,
+                            "bundle": bundle,
+                        })),
+                        error: None,
+                    });
+                }
+                println!("Bundle contains {summary}.");
+                println!(
+                    "Re-run with --yes to replace {} (the current file is kept as .bak).",
+                    config_path.display()
+                );
+                return Ok(());
+            }
+
+            let backup = config_path.with_extension("toml.bak");
+            if config_path.exists() {
+                std::fs::copy(config_path, &backup)?;
+            }
+            // Save the portable form; `~` is expanded whenever it is loaded.
+            bundle.config.save_to_path(config_path)?;
+            let new_config = bundle.config_for_this_machine();
+            let db = Database::open_config(&new_config).await?;
+            let tags_created = db.create_tags(&bundle.tags).await?;
+
+            if json {
+                return emit_json(JsonResponse {
+                    ok: true,
+                    result: Some(serde_json::json!({
+                        "applied": true,
+                        "configPath": config_path,
+                        "backup": backup.exists().then_some(&backup),
+                        "tagsCreated": tags_created,
+                    })),
+                    error: None,
+                });
+            }
+            println!("Imported {summary}.");
+            println!("Wrote {}", config_path.display());
+            if backup.exists() {
+                println!("Previous config saved to {}", backup.display());
+            }
+            if tags_created > 0 {
+                println!("Created {tags_created} tags");
+            }
+        }
     }
 
     Ok(())
@@ -6136,6 +11905,7 @@ struct RemoteStatus {
     cache_path: Option<String>,
     cache_size_bytes: Option<u64>,
     cache_modified: Option<String>,
+    last_delta: Option<String>,
 }
 
 #[derive(Debug, serde::Serialize)]
@@ -6195,6 +11965,8 @@ async fn cmd_remote(
                         },
                         cache_size_bytes: cache_size,
                         cache_modified,
+                        last_delta: remote::read_delta_cursor(&r.name)
+                            .map(|at| at.format("%Y-%m-%d %H:%M:%S").to_string()),
                     }
                 })
                 .collect();
@@ -6234,6 +12006,8 @@ async fn cmd_remote(
         RemoteCommand::Add {
             name,
             host,
+            transport,
+            token_env,
             database_path,
             port,
             identity_file,
@@ -6250,13 +12024,22 @@ async fn cmd_remote(
                 anyhow::bail!("Remote '{name}' already exists");
             }
 
+            let transport: RemoteTransport = transport.into();
+            if transport == RemoteTransport::Http
+                && !(host.starts_with("http://") || host.starts_with("https://"))
+            {
+                anyhow::bail!("http remotes take the API's base URL, e.g. https://nas:3000");
+            }
             let remote = RemoteConfig {
                 name: name.clone(),
+                transport,
                 host: host.clone(),
+                token_env,
                 database_path,
                 port,
                 identity_file,
                 enabled: true,
+                conflict_strategy: ConflictStrategy::default(),
             };
 
             let mut config = config.clone();
@@ -6314,9 +12097,17 @@ async fn cmd_remote(
                 .find(|r| r.name == name)
                 .ok_or_else(|| anyhow::anyhow!("Remote '{name}' not found"))?;
 
-            let transport = remote::SshTransport::from_config(remote_config);
+            let tested = match remote_config.transport {
+                RemoteTransport::Ssh => {
+                    remote::SshTransport::from_config(remote_config).test_connection()
+                }
+                RemoteTransport::Http => match remote::HttpTransport::from_config(remote_config) {
+                    Ok(transport) => transport.test_connection().await,
+                    Err(e) => Err(e),
+                },
+            };
 
-            match transport.test_connection() {
+            match tested {
                 Ok(()) => {
                     if json {
                         return emit_json(JsonResponse {
@@ -6406,12 +12197,36 @@ async fn cmd_remote(
         RemoteCommand::Sync {
             remote: remote_name,
             direction,
+            delta,
+            watch,
+            strategy,
+            prefer,
+            interactive,
         } => {
-            let remotes_to_sync: Vec<_> = if let Some(ref name) = remote_name {
-                config.remotes.iter().filter(|r| &r.name == name).collect()
+            if interactive && json {
+                anyhow::bail!("--interactive cannot be combined with --json");
+            }
+            let strategy: Option<ConflictStrategy> = if interactive {
+                Some(ConflictStrategy::Duplicate)
             } else {
-                config.remotes.iter().filter(|r| r.enabled).collect()
+                prefer.map(Into::into).or(strategy.map(Into::into))
             };
+            let remotes_to_sync: Vec<RemoteConfig> = config
+                .remotes
+                .iter()
+                .filter(|r| {
+                    remote_name
+                        .as_ref()
+                        .map_or(r.enabled, |name| &r.name == name)
+                })
+                .cloned()
+                .map(|mut r| {
+                    if let Some(strategy) = strategy {
+                        r.conflict_strategy = strategy;
+                    }
+                    r
+                })
+                .collect();
 
             if remotes_to_sync.is_empty() {
                 if json {
@@ -6426,82 +12241,135 @@ async fn cmd_remote(
             }
 
             let direction: hstry_core::remote::SyncDirection = direction.into();
-            let mut results = Vec::new();
-            let mut total_convs_added = 0usize;
-            let mut total_convs_updated = 0usize;
-            let mut total_msgs_added = 0usize;
+            if delta && direction == hstry_core::remote::SyncDirection::Push {
+                anyhow::bail!(
+                    "--delta only applies to pulls; use --direction pull or bidirectional"
+                );
+            }
 
-            for remote_config in remotes_to_sync {
-                if !json {
-                    let name = &remote_config.name;
-                    println!("Syncing with {name} ({direction})...");
+            let Some(interval) = watch else {
+                let summary =
+                    remote_sync_round(db, config, &remotes_to_sync, direction, delta, json).await?;
+                if interactive {
+                    let open: Vec<_> = summary
+                        .results
+                        .iter()
+                        .flat_map(|result| &result.conflicts)
+                        .filter(|conflict| conflict.resolved_at.is_none())
+                        .cloned()
+                        .collect();
+                    resolve_conflicts_interactively(db, &open).await?;
+                }
+                if json {
+                    return emit_json(JsonResponse {
+                        ok: true,
+                        result: Some(summary),
+                        error: None,
+                    });
                 }
+                return Ok(());
+            };
 
-                let result = match direction {
-                    hstry_core::remote::SyncDirection::Pull => {
-                        remote::sync_from_remote(db, remote_config)
-                            .await
-                            .map(|(_, sync)| sync)
-                    }
-                    hstry_core::remote::SyncDirection::Push => {
-                        remote::sync_to_remote(&config.database, remote_config).await
-                    }
-                    hstry_core::remote::SyncDirection::Bidirectional => {
-                        // Pull first, then push
-                        let pull_result = remote::sync_from_remote(db, remote_config).await;
-                        match pull_result {
-                            Ok((_, mut sync)) => {
-                                if let Ok(push_sync) =
-                                    remote::sync_to_remote(&config.database, remote_config).await
-                                {
-                                    sync.conversations_added += push_sync.conversations_added;
-                                    sync.conversations_updated += push_sync.conversations_updated;
-                                    sync.messages_added += push_sync.messages_added;
-                                    sync.direction =
-                                        hstry_core::remote::SyncDirection::Bidirectional;
-                                }
-                                Ok(sync)
-                            }
-                            Err(e) => Err(e),
-                        }
-                    }
-                };
+            // One compact JSON summary per round so watchers can tail it.
+            let interval = std::time::Duration::from_secs(interval.max(1));
+            loop {
+                let summary =
+                    remote_sync_round(db, config, &remotes_to_sync, direction, delta, json).await?;
+                if json {
+                    emit_json_line(&summary)?;
+                }
+                tokio::select! {
+                    () = tokio::time::sleep(interval) => {}
+                    _ = tokio::signal::ctrl_c() => return Ok(()),
+                }
+            }
+        }
 
-                match result {
-                    Ok(sync_result) => {
-                        if !json {
-                            let added = sync_result.conversations_added;
-                            let updated = sync_result.conversations_updated;
-                            let messages = sync_result.messages_added;
-                            println!(
-                                "  Added {added} conversations, updated {updated}, {messages} messages"
-                            );
-                        }
-                        total_convs_added += sync_result.conversations_added;
-                        total_convs_updated += sync_result.conversations_updated;
-                        total_msgs_added += sync_result.messages_added;
-                        results.push(sync_result);
+        RemoteCommand::Conflicts {
+            remote: remote_name,
+            all,
+            resolve,
+            prefer,
+        } => {
+            let conflicts = db
+                .list_remote_conflicts(remote_name.as_deref(), all)
+                .await?;
+            if resolve {
+                let Some(prefer) = prefer else {
+                    if json {
+                        anyhow::bail!("--resolve without --prefer is interactive; drop --json");
                     }
-                    Err(e) => {
-                        if !json {
-                            eprintln!("  Error: {e}");
+                    resolve_conflicts_interactively(db, &conflicts).await?;
+                    return Ok(());
+                };
+                let mut resolved = Vec::new();
+                for conflict in conflicts {
+                    let choice = match prefer {
+                        ConflictPreferArg::Local => remote::ConflictChoice::Local,
+                        ConflictPreferArg::Remote => remote::ConflictChoice::Remote,
+                        ConflictPreferArg::Newest => remote::ConflictChoice::newest(&conflict),
+                    };
+                    match remote::resolve_conflict(db, &conflict, choice).await {
+                        Ok(()) => resolved.push(conflict.id),
+                        Err(e) => {
+                            if !json {
+                                eprintln!("  #{}: {e}", conflict.id);
+                            }
                         }
                     }
                 }
+                if json {
+                    return emit_json(JsonResponse {
+                        ok: true,
+                        result: Some(serde_json::json!({ "resolved": resolved })),
+                        error: None,
+                    });
+                }
+                println!("Resolved {} conflicts.", resolved.len());
+                return Ok(());
             }
-
             if json {
                 return emit_json(JsonResponse {
                     ok: true,
-                    result: Some(RemoteSyncSummary {
-                        results,
-                        total_conversations_added: total_convs_added,
-                        total_conversations_updated: total_convs_updated,
-                        total_messages_added: total_msgs_added,
-                    }),
+                    result: Some(conflicts),
                     error: None,
                 });
             }
+            if conflicts.is_empty() {
+                println!("No {}conflicts.", if all { "" } else { "open " });
+                return Ok(());
+            }
+            for conflict in &conflicts {
+                let state = if conflict.resolved_at.is_some() {
+                    conflict.resolution.as_str()
+                } else {
+                    "open"
+                };
+                let title = truncate_title(conflict.title.as_deref().unwrap_or("(untitled)"), 50);
+                println!(
+                    "#{id} [{state}] {remote} {source}/{external} {title}",
+                    id = conflict.id,
+                    remote = conflict.remote,
+                    source = conflict.source_id,
+                    external = conflict.external_id,
+                );
+                let when = |at: Option<chrono::DateTime<chrono::Utc>>| {
+                    at.map_or_else(
+                        || "-".to_string(),
+                        |at| at.format("%Y-%m-%d %H:%M").to_string(),
+                    )
+                };
+                println!(
+                    "    local updated {local}, remote updated {remote}, detected {detected} ({strategy})",
+                    local = when(conflict.local_updated_at),
+                    remote = when(conflict.remote_updated_at),
+                    detected = conflict.detected_at.format("%Y-%m-%d %H:%M"),
+                    strategy = conflict.strategy,
+                );
+                if let Some(id) = conflict.duplicate_conversation_id {
+                    println!("    duplicate copy: {id}");
+                }
+            }
         }
 
         RemoteCommand::Status => {
@@ -6536,6 +12404,8 @@ async fn cmd_remote(
                     },
                     cache_size_bytes: cache_size,
                     cache_modified,
+                    last_delta: remote::read_delta_cursor(&remote.name)
+                        .map(|at| at.format("%Y-%m-%d %H:%M:%S").to_string()),
                 });
             }
 
@@ -6575,6 +12445,9 @@ async fn cmd_remote(
                     } else {
                         println!("  Cached: no");
                     }
+                    if let Some(ref at) = s.last_delta {
+                        println!("  Last delta sync: {at} (remote time)");
+                    }
                     println!();
                 }
             }
@@ -6584,6 +12457,203 @@ async fn cmd_remote(
     Ok(())
 }
 
+/// Sync each remote once, reporting progress unless `json` is set.
+/// Failures are reported per remote and do not stop the others.
+async fn remote_sync_round(
+    db: &Database,
+    config: &Config,
+    remotes: &[hstry_core::config::RemoteConfig],
+    direction: hstry_core::remote::SyncDirection,
+    delta: bool,
+    json: bool,
+) -> Result<RemoteSyncSummary> {
+    use hstry_core::remote::{self, SyncDirection};
+
+    let pull = async |remote_config| remote::pull(db, remote_config, delta).await;
+    let db_opts = hstry_core::db::DatabaseOptions::from_config(config, false)?;
+    let push = async |remote_config| {
+        remote::push(db, &config.database.path, &db_opts, remote_config).await
+    };
+
+    let mut summary = RemoteSyncSummary {
+        results: Vec::new(),
+        total_conversations_added: 0,
+        total_conversations_updated: 0,
+        total_messages_added: 0,
+    };
+
+    for remote_config in remotes {
+        if !json {
+            let name = &remote_config.name;
+            let mode = if delta || remote_config.transport == RemoteTransport::Http {
+                ", delta"
+            } else {
+                ""
+            };
+            println!("Syncing with {name} ({direction}{mode})...");
+        }
+
+        let result = match direction {
+            SyncDirection::Pull => pull(remote_config).await,
+            SyncDirection::Push => push(remote_config).await,
+            SyncDirection::Bidirectional => {
+                // Pull first, then push
+                match pull(remote_config).await {
+                    Ok(mut sync) => {
+                        if let Ok(push_sync) = push(remote_config).await {
+                            sync.conversations_added += push_sync.conversations_added;
+                            sync.conversations_updated += push_sync.conversations_updated;
+                            sync.messages_added += push_sync.messages_added;
+                            sync.direction = SyncDirection::Bidirectional;
+                        }
+                        Ok(sync)
+                    }
+                    Err(e) => Err(e),
+                }
+            }
+        };
+
+        match result {
+            Ok(mut sync_result) => {
+                for conflict in &mut sync_result.conflicts {
+                    conflict.id = db.record_remote_conflict(conflict).await?;
+                }
+                if !json {
+                    let added = sync_result.conversations_added;
+                    let updated = sync_result.conversations_updated;
+                    let messages = sync_result.messages_added;
+                    let transferred = sync_result
+                        .bytes_transferred
+                        .map(|bytes| format!(" ({} transferred)", format_bytes(bytes)))
+                        .unwrap_or_default();
+                    println!(
+                        "  Added {added} conversations, updated {updated}, {messages} messages{transferred}"
+                    );
+                    print_conflict_summary(&sync_result.conflicts, remote_config);
+                }
+                summary.total_conversations_added += sync_result.conversations_added;
+                summary.total_conversations_updated += sync_result.conversations_updated;
+                summary.total_messages_added += sync_result.messages_added;
+                summary.results.push(sync_result);
+            }
+            Err(e) => {
+                if !json {
+                    eprintln!("  Error: {e}");
+                }
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Show how each conflict's copies differ and ask which to keep.
+async fn resolve_conflicts_interactively(
+    db: &Database,
+    conflicts: &[hstry_core::db::RemoteConflict],
+) -> Result<()> {
+    use hstry_core::remote::{self, ConflictChoice};
+
+    if conflicts.is_empty() {
+        eprintln!("No open conflicts.");
+        return Ok(());
+    }
+    let when = |at: Option<chrono::DateTime<chrono::Utc>>| {
+        at.map_or_else(
+            || "-".to_string(),
+            |at| at.format("%Y-%m-%d %H:%M").to_string(),
+        )
+    };
+    let snippet = |change: &Option<(String, String)>| {
+        change.as_ref().map_or_else(
+            || "(nothing)".to_string(),
+            |(role, content)| format!("{role}: {}", truncate_title(content, 70)),
+        )
+    };
+
+    let mut resolved = 0usize;
+    for (i, conflict) in conflicts.iter().enumerate() {
+        let Some(diff) = remote::conflict_diff(db, conflict).await? else {
+            eprintln!(
+                "#{} {}: the duplicate lives on {}; resolve it there.",
+                conflict.id, conflict.external_id, conflict.remote
+            );
+            continue;
+        };
+        let title = conflict.title.as_deref().unwrap_or("(untitled)");
+        eprintln!();
+        eprintln!(
+            "[{}/{}] #{} {} — {}",
+            i + 1,
+            conflicts.len(),
+            conflict.id,
+            conflict.source_id,
+            truncate_title(title, 60)
+        );
+        eprintln!(
+            "  local:  {} messages, updated {}",
+            diff.local_messages,
+            when(conflict.local_updated_at)
+        );
+        eprintln!(
+            "  remote: {} messages, updated {}",
+            diff.remote_messages,
+            when(conflict.remote_updated_at)
+        );
+        eprintln!("  shared: first {} messages", diff.shared_messages);
+        eprintln!("  local  then  {}", snippet(&diff.local_first_change));
+        eprintln!("  remote then  {}", snippet(&diff.remote_first_change));
+
+        let choice = loop {
+            eprint!("Keep [l]ocal, [r]emote, [b]oth, [n]ewest, [s]kip, [q]uit? ");
+            std::io::stderr().flush()?;
+            let mut input = String::new();
+            let read = std::io::stdin().read_line(&mut input)?;
+            match input.trim().to_lowercase().as_str() {
+                "l" | "local" => break Some(ConflictChoice::Local),
+                "r" | "remote" => break Some(ConflictChoice::Remote),
+                "b" | "both" => break Some(ConflictChoice::Both),
+                "n" | "newest" => break Some(ConflictChoice::newest(conflict)),
+                "s" | "skip" => break None,
+                _ if read == 0 => {
+                    eprintln!();
+                    eprintln!("Resolved {resolved} conflicts.");
+                    return Ok(());
+                }
+                "q" | "quit" => {
+                    eprintln!("Resolved {resolved} conflicts.");
+                    return Ok(());
+                }
+                _ => {}
+            }
+        };
+        if let Some(choice) = choice {
+            remote::resolve_conflict(db, conflict, choice).await?;
+            resolved += 1;
+        }
+    }
+    eprintln!("Resolved {resolved} conflicts.");
+    Ok(())
+}
+
+fn print_conflict_summary(
+    conflicts: &[hstry_core::db::RemoteConflict],
+    remote_config: &hstry_core::config::RemoteConfig,
+) {
+    if conflicts.is_empty() {
+        return;
+    }
+    let open = conflicts.iter().filter(|c| c.resolved_at.is_none()).count();
+    let strategy = remote_config.conflict_strategy.as_str();
+    println!("  {} conflicts settled by {strategy}", conflicts.len());
+    if open > 0 {
+        let name = &remote_config.name;
+        println!(
+            "  {open} kept as duplicates; review with `hstry remote conflicts --remote {name}`"
+        );
+    }
+}
+
 fn format_bytes(bytes: u64) -> String {
     const KB: u64 = 1024;
     const MB: u64 = KB * 1024;
@@ -6700,7 +12770,15 @@ async fn cmd_mmry_extract(
             workspace: workspace.clone(),
             after,
             before: None,
+            ingested_after: None,
+            model: None,
+            provider: None,
+            harness: None,
+            role: None,
+            exclude_sensitive: false,
+            starred: false,
             limit,
+            offset: None,
         })
         .await?;
 
@@ -1,7 +1,10 @@
 //! Pretty terminal output formatting for hstry CLI.
 
 use chrono::{DateTime, Utc};
-use hstry_core::models::SearchHit;
+use console::Alignment;
+use hstry_core::config::{BadgeColor, SourceBadges};
+use hstry_core::models::{MessageRole, SearchHit};
+use hstry_core::search_groups::ConversationHits;
 use std::io::{self, IsTerminal};
 use std::path::Path;
 
@@ -11,11 +14,16 @@ pub struct ConversationDisplay {
     pub id: uuid::Uuid,
     pub source_id: String,
     pub workspace: Option<String>,
+    /// Display name of the workspace, shown instead of its directory name.
+    pub workspace_name: Option<String>,
     pub created_at: DateTime<Utc>,
     pub title: String,
     /// Human-readable id (adjective-noun) when available; shown in the id
     /// column in preference to the UUID prefix.
     pub readable_id: Option<String>,
+    /// Ingested since the profile's last-seen mark.
+    pub is_new: bool,
+    pub is_starred: bool,
 }
 
 /// Format a short relative time string.
@@ -73,10 +81,14 @@ fn single_line(value: &str) -> String {
     value.split_whitespace().collect::<Vec<_>>().join(" ")
 }
 
-fn display_workspace(workspace: Option<&str>) -> String {
-    workspace
-        .and_then(|value| Path::new(value).file_name())
-        .and_then(|value| value.to_str())
+/// The workspace's display name, else its directory name.
+fn display_workspace(workspace: Option<&str>, name: Option<&str>) -> String {
+    name.filter(|name| !name.is_empty())
+        .or_else(|| {
+            workspace
+                .and_then(|value| Path::new(value).file_name())
+                .and_then(|value| value.to_str())
+        })
         .filter(|value| !value.is_empty())
         .unwrap_or("-")
         .to_string()
@@ -99,7 +111,27 @@ fn truncate(value: &str, width: usize) -> String {
     format!("{}…", value.chars().take(width - 1).collect::<String>())
 }
 
-fn print_rows(rows: &[(String, String, String, String, String)], empty: &str) {
+fn badge_style(color: BadgeColor) -> console::Style {
+    let style = console::Style::new();
+    match color {
+        BadgeColor::Red => style.red(),
+        BadgeColor::Green => style.green(),
+        BadgeColor::Yellow => style.yellow(),
+        BadgeColor::Blue => style.blue(),
+        BadgeColor::Magenta => style.magenta(),
+        BadgeColor::Cyan => style.cyan(),
+        BadgeColor::White => style.white(),
+        BadgeColor::Gray => style.color256(8),
+    }
+}
+
+/// Rows are `(title, workspace, source id, age, id)`. The source id is shown
+/// through [`display_source`], with its configured badge on a terminal.
+fn print_rows(
+    rows: &[(String, String, String, String, String)],
+    empty: &str,
+    badges: &SourceBadges,
+) {
     if rows.is_empty() {
         println!("{empty}");
         return;
@@ -107,11 +139,25 @@ fn print_rows(rows: &[(String, String, String, String, String)], empty: &str) {
 
     if !io::stdout().is_terminal() {
         for (title, workspace, source, age, id) in rows {
+            let source = display_source(source);
             println!("{title}\t{workspace}\t{source}\t{age}\t{id}");
         }
         return;
     }
 
+    let sources = rows
+        .iter()
+        .map(|row| {
+            let badge = badges.lookup(&row.2, None);
+            let name = display_source(&row.2);
+            let label = match badge.and_then(|badge| badge.icon.as_deref()) {
+                Some(icon) => format!("{icon} {name}"),
+                None => name.to_string(),
+            };
+            (label, badge.and_then(|badge| badge.color))
+        })
+        .collect::<Vec<_>>();
+
     let terminal_width = console::Term::stdout().size().1 as usize;
     let workspace_width = rows
         .iter()
@@ -119,9 +165,9 @@ fn print_rows(rows: &[(String, String, String, String, String)], empty: &str) {
         .max()
         .unwrap_or(9)
         .clamp(9, 18);
-    let source_width = rows
+    let source_width = sources
         .iter()
-        .map(|row| row.2.chars().count())
+        .map(|(label, _)| console::measure_text_width(label))
         .max()
         .unwrap_or(6)
         .clamp(6, 16);
@@ -150,12 +196,16 @@ fn print_rows(rows: &[(String, String, String, String, String)], empty: &str) {
         "{:<title_width$}  {:<workspace_width$}  {:<source_width$}  {:>age_width$}  {:<id_width$}",
         "TITLE", "WORKSPACE", "SOURCE", "AGE", "ID"
     );
-    for (title, workspace, source, age, id) in rows {
+    for ((title, workspace, _, age, id), (label, color)) in rows.iter().zip(&sources) {
+        let source = console::pad_str(label, source_width, Alignment::Left, Some("…"));
+        let source = match color {
+            Some(color) => badge_style(*color).apply_to(source).to_string(),
+            None => source.into_owned(),
+        };
         println!(
-            "{:<title_width$}  {:<workspace_width$}  {:<source_width$}  {:>age_width$}  {:<id_width$}",
+            "{:<title_width$}  {:<workspace_width$}  {source}  {:>age_width$}  {:<id_width$}",
             truncate(title, title_width),
             truncate(workspace, workspace_width),
-            truncate(source, source_width),
             age,
             truncate(id, id_width),
         );
@@ -163,7 +213,7 @@ fn print_rows(rows: &[(String, String, String, String, String)], empty: &str) {
 }
 
 /// Print search results in a compact format.
-pub fn print_search_results(hits: &[SearchHit]) {
+pub fn print_search_results(hits: &[SearchHit], badges: &SourceBadges) {
     let rows = hits
         .iter()
         .map(|hit| {
@@ -175,18 +225,18 @@ pub fn print_search_results(hits: &[SearchHit]) {
             let snippet = single_line(&clean_snippet(&hit.snippet));
             (
                 format!("{title} \u{2014} {snippet}"),
-                display_workspace(hit.workspace.as_deref()),
-                display_source(&hit.source_adapter).to_string(),
+                display_workspace(hit.workspace.as_deref(), hit.workspace_name.as_deref()),
+                hit.source_adapter.clone(),
                 relative_time_short(hit.conv_created_at),
                 id,
             )
         })
         .collect::<Vec<_>>();
-    print_rows(&rows, "No results found.");
+    print_rows(&rows, &hstry_core::tr!("cli-no-results"), badges);
 }
 
 /// Print conversations in a nice table format.
-pub fn print_conversations(conversations: &[ConversationDisplay]) {
+pub fn print_conversations(conversations: &[ConversationDisplay], badges: &SourceBadges) {
     let rows = conversations
         .iter()
         .map(|conversation| {
@@ -194,20 +244,31 @@ pub fn print_conversations(conversations: &[ConversationDisplay]) {
                 .readable_id
                 .clone()
                 .unwrap_or_else(|| conversation.id.to_string()[..8].to_string());
+            let mut title = single_line(&conversation.title);
+            if conversation.is_starred {
+                title = format!("* {title}");
+            }
             (
-                single_line(&conversation.title),
-                display_workspace(conversation.workspace.as_deref()),
-                display_source(&conversation.source_id).to_string(),
+                if conversation.is_new {
+                    format!("NEW {title}")
+                } else {
+                    title
+                },
+                display_workspace(
+                    conversation.workspace.as_deref(),
+                    conversation.workspace_name.as_deref(),
+                ),
+                conversation.source_id.clone(),
                 relative_time_short(conversation.created_at),
                 id,
             )
         })
         .collect::<Vec<_>>();
-    print_rows(&rows, "No conversations found.");
+    print_rows(&rows, "No conversations found.", badges);
 }
 
 /// Print compact search results (one per session with occurrence count).
-pub fn print_search_results_compact(hits: &[SearchHit]) {
+pub fn print_search_results_compact(hits: &[SearchHit], badges: &SourceBadges) {
     let rows = hits
         .iter()
         .map(|hit| {
@@ -225,15 +286,77 @@ pub fn print_search_results_compact(hits: &[SearchHit]) {
             };
             (
                 lead,
-                display_workspace(hit.workspace.as_deref()),
-                display_source(&hit.source_adapter).to_string(),
+                display_workspace(hit.workspace.as_deref(), hit.workspace_name.as_deref()),
+                hit.source_adapter.clone(),
+                relative_time_short(hit.conv_created_at),
+                id,
+            )
+        })
+        .collect::<Vec<_>>();
+    print_rows(&rows, &hstry_core::tr!("cli-no-results"), badges);
+}
+/// Print search results grouped by conversation, with each one's hit count.
+pub fn print_conversation_hits(groups: &[ConversationHits], badges: &SourceBadges) {
+    let rows = groups
+        .iter()
+        .map(|group| {
+            let hit = group.best();
+            let id = hit
+                .readable_id
+                .clone()
+                .unwrap_or_else(|| hit.conversation_id.to_string()[..8].to_string());
+            let title = single_line(hit.title.as_deref().unwrap_or("(untitled)"));
+            let snippet = single_line(&clean_snippet(&hit.snippet));
+            let noun = if group.hit_count == 1 { "hit" } else { "hits" };
+            (
+                format!("{title} ({} {noun}) \u{2014} {snippet}", group.hit_count),
+                display_workspace(hit.workspace.as_deref(), hit.workspace_name.as_deref()),
+                hit.source_adapter.clone(),
                 relative_time_short(hit.conv_created_at),
                 id,
             )
         })
         .collect::<Vec<_>>();
-    print_rows(&rows, "No results found.");
+    print_rows(&rows, &hstry_core::tr!("cli-no-results"), badges);
+}
+
+/// Print each hit with the messages around it (`search --context N`). The
+/// hit is marked with `>`.
+pub fn print_search_results_with_context(hits: &[SearchHit]) {
+    if hits.is_empty() {
+        println!("{}", hstry_core::tr!("cli-no-results"));
+        return;
+    }
+    for (i, hit) in hits.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        let id = hit
+            .readable_id
+            .clone()
+            .unwrap_or_else(|| hit.conversation_id.to_string()[..8].to_string());
+        let title = single_line(hit.title.as_deref().unwrap_or("(untitled)"));
+        println!(
+            "{id}  {title}  ({}, {})",
+            display_source(&hit.source_id),
+            relative_time_short(hit.conv_created_at)
+        );
+        let context = hit.context.as_ref();
+        for message in context.map(|c| c.before.as_slice()).unwrap_or_default() {
+            print_context_line(' ', message.idx, &message.role, &message.content);
+        }
+        print_context_line('>', hit.message_idx, &hit.role, &hit.content);
+        for message in context.map(|c| c.after.as_slice()).unwrap_or_default() {
+            print_context_line(' ', message.idx, &message.role, &message.content);
+        }
+    }
+}
+
+fn print_context_line(marker: char, idx: i32, role: &MessageRole, content: &str) {
+    let content = truncate(&single_line(content), 160);
+    println!("{marker} #{idx:<4} {:<9} {content}", role.to_string());
 }
+
 #[cfg(test)]
 mod tests {
     use super::*;
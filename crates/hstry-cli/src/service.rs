@@ -9,6 +9,7 @@ use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use anyhow::{Context, Result};
+use chrono::Timelike;
 use notify::{RecommendedWatcher, RecursiveMode, Watcher};
 use tokio::sync::mpsc;
 use tokio::time::{Duration, Instant, interval};
@@ -19,8 +20,9 @@ use walkdir::WalkDir;
 
 use crate::ServiceCommand;
 use crate::adapter_manifest;
+use crate::alerts::{self, AlertKind, SourceAlert};
 use crate::sync;
-use hstry_core::config::ServiceTransport;
+use hstry_core::config::{QosClass, RetentionAction, ServiceTransport};
 use hstry_core::models::Source;
 use hstry_core::service::{
     MAX_MESSAGE_SIZE, ReadService, ReadServiceServer, SearchService, SearchServiceServer,
@@ -28,11 +30,14 @@ use hstry_core::service::{
     conversation_to_proto, hit_to_proto, message_event_to_proto, message_from_proto,
     message_to_proto, search_request_to_opts,
 };
-use hstry_core::{Config, Database};
-use hstry_runtime::{AdapterRunner, Runtime};
+use hstry_core::{Config, Database, DatabaseOptions};
+use hstry_runtime::AdapterRunner;
 
 const DETECT_THRESHOLD: f32 = 0.5;
 
+/// Stale conversation summary caches rebuilt per sync cycle.
+const SUMMARY_REFRESH_BATCH: i64 = 500;
+
 #[derive(Clone)]
 struct ServerState {
     db: Arc<Database>,
@@ -580,11 +585,19 @@ impl ReadService for ServerState {
                 },
                 after: None,
                 before: None,
+                ingested_after: None,
+                model: None,
+                provider: None,
+                harness: None,
+                role: None,
+                exclude_sensitive: false,
+                starred: false,
                 limit: if request.limit > 0 {
                     Some(request.limit)
                 } else {
                     None
                 },
+                offset: None,
             })
             .await
             .map_err(|e| tonic::Status::internal(format!("Failed to list conversations: {e}")))?;
@@ -713,7 +726,7 @@ fn stop_service() -> Result<()> {
     Ok(())
 }
 
-fn is_process_running(pid: u32) -> bool {
+pub(crate) fn is_process_running(pid: u32) -> bool {
     use nix::sys::signal::kill;
     use nix::unistd::Pid;
     i32::try_from(pid)
@@ -1041,6 +1054,12 @@ struct ServiceState {
     metrics: Arc<tokio::sync::Mutex<ServiceMetrics>>,
     /// Last time message_events compaction ran (trx-jtxf).
     last_events_compaction: Instant,
+    /// Last time the `[retention]` policy was enforced.
+    last_retention_run: Instant,
+    /// Day whose `[daily_notes]` section was last written.
+    daily_notes_day: Option<chrono::NaiveDate>,
+    /// Failure alerts raised during the current cycle, sent as one digest.
+    pending_alerts: Vec<SourceAlert>,
 }
 
 /// Per-process counters surfaced through structured logs (trx-z42c.8).
@@ -1051,6 +1070,7 @@ struct ServiceMetrics {
     syncs_skipped: u64,
     syncs_failed: u64,
     events_compacted_total: i64,
+    conversations_pruned_total: usize,
     per_source: HashMap<String, SourceMetrics>,
 }
 
@@ -1069,13 +1089,17 @@ impl ServiceState {
         let config = Config::ensure_at(config_path)?;
         let config_mtime = config_path.metadata().and_then(|m| m.modified()).ok();
 
-        let db = Arc::new(Database::open(&config.database).await?);
+        let db = Arc::new(
+            Database::open_with(
+                &config.database.path,
+                &DatabaseOptions::from_config(&config, true)?,
+            )
+            .await?,
+        );
         crate::apply_storage_config(&db, &config);
-        let runtime = Runtime::parse(&config.js_runtime).ok_or_else(|| {
-            anyhow::anyhow!("No JavaScript runtime found. Install bun, deno, or node.")
-        })?;
+        crate::apply_nice_mode(&db, &config, background_qos(&config));
         adapter_manifest::validate_adapter_manifest(&config.adapter_paths)?;
-        let runner = AdapterRunner::new(runtime, config.adapter_paths.clone());
+        let runner = crate::adapter_runner(&config)?;
 
         let enabled_adapters = enabled_adapters(&config, &runner);
         let auto_sync_by_id = auto_sync_map(&config);
@@ -1105,6 +1129,9 @@ impl ServiceState {
             sync_semaphore,
             metrics: Arc::new(tokio::sync::Mutex::new(ServiceMetrics::default())),
             last_events_compaction: Instant::now() - Duration::from_secs(86_400),
+            last_retention_run: Instant::now() - Duration::from_secs(86_400),
+            daily_notes_day: None,
+            pending_alerts: Vec::new(),
         };
 
         // NOTE: refresh_watches() is called separately by the caller after
@@ -1122,14 +1149,12 @@ impl ServiceState {
         }
 
         let config = Config::load_from_path(&self.config_path)?;
-        let runtime = Runtime::parse(&config.js_runtime).ok_or_else(|| {
-            anyhow::anyhow!("No JavaScript runtime found. Install bun, deno, or node.")
-        })?;
+        let runner = crate::adapter_runner(&config)?;
         adapter_manifest::validate_adapter_manifest(&config.adapter_paths)?;
 
-        let db = Arc::new(Database::open(&config.database).await?);
+        let db = Arc::new(Database::open_config(&config).await?);
         crate::apply_storage_config(&db, &config);
-        let runner = AdapterRunner::new(runtime, config.adapter_paths.clone());
+        crate::apply_nice_mode(&db, &config, background_qos(&config));
 
         self.config = config;
         self.config_mtime = mtime;
@@ -1209,6 +1234,7 @@ impl ServiceState {
             stats.sources_synced, stats.sources_skipped_unchanged
         );
         self.last_event_sync = Instant::now();
+        self.flush_alerts().await;
         Ok(())
     }
 
@@ -1220,6 +1246,27 @@ impl ServiceState {
         let stats = self.sync_existing_sources().await?;
         self.sync_remotes_if_due().await?;
         self.maybe_compact_message_events().await?;
+        self.maybe_enforce_retention().await?;
+        self.purge_expired_trash().await;
+        self.maybe_write_daily_notes().await;
+        // Readers only look a few seconds back; a day covers a TUI left
+        // suspended overnight.
+        if let Err(err) = self
+            .db
+            .prune_change_log(chrono::Utc::now() - chrono::Duration::days(1))
+            .await
+        {
+            tracing::warn!(target: "hstry::sync", "failed to prune change log: {err}");
+        }
+        match self.db.refresh_stale_summaries(SUMMARY_REFRESH_BATCH).await {
+            Ok(0) => {}
+            Ok(refreshed) => {
+                tracing::debug!(target: "hstry::sync", "refreshed {refreshed} stale conversation summaries");
+            }
+            Err(err) => {
+                tracing::warn!(target: "hstry::sync", "failed to refresh conversation summaries: {err}");
+            }
+        }
         let outbox_depth = self.db.indexer_outbox_depth().await.unwrap_or(0);
         let metrics = self.metrics.lock().await;
         tracing::info!(
@@ -1236,9 +1283,17 @@ impl ServiceState {
             "sync_cycle reason=audit sources_synced={} sources_skipped_unchanged={} outbox_depth={}",
             stats.sources_synced, stats.sources_skipped_unchanged, outbox_depth
         );
+        drop(metrics);
+        self.flush_alerts().await;
         Ok(())
     }
 
+    /// Send the alerts queued during this cycle as a single digest.
+    async fn flush_alerts(&mut self) {
+        let alerts = std::mem::take(&mut self.pending_alerts);
+        alerts::send_digest(&self.config.service.notifications, &alerts).await;
+    }
+
     /// Run the message_events compaction at most once per
     /// `compaction_interval_secs` (trx-jtxf).
     async fn maybe_compact_message_events(&mut self) -> Result<()> {
@@ -1276,6 +1331,98 @@ impl ServiceState {
         Ok(())
     }
 
+    /// Apply the `[retention]` policy at most once per `interval_secs`.
+    async fn maybe_enforce_retention(&mut self) -> Result<()> {
+        let retention = &self.config.retention;
+        let scopes = self.config.retention_scopes(retention);
+        if !retention.enabled || !scopes.iter().any(|scope| scope.has_limits()) {
+            return Ok(());
+        }
+        if self.last_retention_run.elapsed() < Duration::from_secs(retention.interval_secs.max(60))
+        {
+            return Ok(());
+        }
+        self.last_retention_run = Instant::now();
+        let mut candidates = Vec::new();
+        for scope in &scopes {
+            candidates.extend(
+                self.db
+                    .find_retention_candidates_in(scope, &retention.exclude_tags)
+                    .await?,
+            );
+        }
+        if candidates.is_empty() {
+            return Ok(());
+        }
+        let ids: Vec<uuid::Uuid> = candidates.iter().map(|c| c.id).collect();
+        let action = retention.action;
+        let pruned = match action {
+            RetentionAction::Delete => self.db.delete_conversations_batch(&ids).await?,
+            RetentionAction::Archive => {
+                let archive = Database::open_archive(&self.config).await?;
+                let moved = self.db.transfer_conversations(&archive, &ids).await?;
+                archive.close().await;
+                moved
+            }
+        };
+        let mut metrics = self.metrics.lock().await;
+        metrics.conversations_pruned_total += pruned;
+        tracing::info!(
+            target: "hstry::storage",
+            conversations_pruned = pruned,
+            action = ?action,
+            "enforced_retention"
+        );
+        Ok(())
+    }
+
+    /// Delete conversations that have been in the trash longer than
+    /// `[trash].purge_after_days`.
+    async fn purge_expired_trash(&mut self) {
+        let days = self.config.trash.purge_after_days;
+        if days == 0 {
+            return;
+        }
+        let before = chrono::Utc::now() - chrono::Duration::days(i64::from(days));
+        match self.db.purge_trash(Some(before)).await {
+            Ok(0) => {}
+            Ok(purged) => {
+                tracing::info!(target: "hstry::storage", conversations_purged = purged, "purged_trash");
+            }
+            Err(err) => {
+                tracing::warn!(target: "hstry::storage", "failed to purge trash: {err}");
+            }
+        }
+    }
+
+    /// Once a day, after `[daily_notes].run_at_hour`, summarize the previous
+    /// day into its daily note.
+    async fn maybe_write_daily_notes(&mut self) {
+        let config = &self.config.daily_notes;
+        let Some(vault) = config.vault.as_deref().filter(|_| config.enabled) else {
+            return;
+        };
+        let now = chrono::Local::now();
+        let Some(day) = now.date_naive().pred_opt() else {
+            return;
+        };
+        if now.hour() < config.run_at_hour || self.daily_notes_day >= Some(day) {
+            return;
+        }
+        self.daily_notes_day = Some(day);
+        match hstry_core::daily_notes::write_day(&self.db, config, vault, day, None, None).await {
+            Ok(note) => tracing::info!(
+                target: "hstry::storage",
+                conversations = note.conversations,
+                path = %note.path.display(),
+                "wrote_daily_notes"
+            ),
+            Err(err) => {
+                tracing::warn!(target: "hstry::storage", "failed to write daily notes: {err}");
+            }
+        }
+    }
+
     async fn sync_remotes_if_due(&mut self) -> Result<()> {
         if !self.config.sync.auto_sync {
             return Ok(());
@@ -1306,18 +1453,22 @@ impl ServiceState {
             self.config.remotes.iter().filter(|r| r.enabled).collect()
         };
 
+        let db_opts = hstry_core::db::DatabaseOptions::from_config(&self.config, false)?;
         for remote_config in remotes {
             let _ = match direction {
                 hstry_core::remote::SyncDirection::Pull => {
-                    hstry_core::remote::sync_from_remote(&self.db, remote_config)
-                        .await
-                        .map(|_| ())
-                }
-                hstry_core::remote::SyncDirection::Push => {
-                    hstry_core::remote::sync_to_remote(&self.config.database, remote_config)
+                    hstry_core::remote::pull(&self.db, remote_config, false)
                         .await
                         .map(|_| ())
                 }
+                hstry_core::remote::SyncDirection::Push => hstry_core::remote::push(
+                    &self.db,
+                    &self.config.database.path,
+                    &db_opts,
+                    remote_config,
+                )
+                .await
+                .map(|_| ()),
                 hstry_core::remote::SyncDirection::Bidirectional => Ok(()),
             };
         }
@@ -1705,7 +1856,38 @@ impl ServiceState {
         };
         match outcome_result {
             Ok(result) => {
-                self.source_backoff.remove(&source.id);
+                if let Some((failures, _)) = self.source_backoff.remove(&source.id)
+                    && alerts::should_report_recovery(&self.config.service.notifications, failures)
+                {
+                    self.pending_alerts.push(SourceAlert {
+                        source_id: source.id.clone(),
+                        adapter: source.adapter.clone(),
+                        kind: AlertKind::Recovered,
+                        failures,
+                        error: None,
+                    });
+                }
+                if let Err(err) = sync::apply_group_tags(&self.db, &self.config, &result).await {
+                    tracing::warn!(
+                        target: "hstry::sync",
+                        source = %source.id,
+                        "failed to apply group tags: {err}"
+                    );
+                }
+                match sync::auto_index(&self.db, &self.config, std::slice::from_ref(&result)).await
+                {
+                    Ok(true) => tracing::info!(
+                        target: "hstry::sync",
+                        source = %source.id,
+                        "rebuilt the out-of-date search index"
+                    ),
+                    Ok(false) => {}
+                    Err(err) => tracing::warn!(
+                        target: "hstry::sync",
+                        source = %source.id,
+                        "failed to update the search index: {err}"
+                    ),
+                }
                 // Adaptive cadence: tighten on activity, back off on idle.
                 let scheduler = &self.config.service.scheduler;
                 let prev_interval = self
@@ -1794,6 +1976,17 @@ impl ServiceState {
                 self.source_backoff
                     .insert(source.id.clone(), (failures, retry_after));
                 eprintln!("  Will retry in {backoff_secs}s (failure #{failures})",);
+                if let Some(kind) =
+                    alerts::failure_alert(&self.config.service.notifications, failures)
+                {
+                    self.pending_alerts.push(SourceAlert {
+                        source_id: source.id.clone(),
+                        adapter: source.adapter.clone(),
+                        kind,
+                        failures,
+                        error: Some(err.to_string()),
+                    });
+                }
                 Ok(SourceSyncOutcome::Skipped)
             }
         }
@@ -1944,6 +2137,12 @@ fn enabled_adapters(config: &Config, runner: &AdapterRunner) -> HashSet<String>
     enabled
 }
 
+/// The service syncs in nice mode unless `service.resources.qos` is
+/// `interactive`.
+fn background_qos(config: &Config) -> bool {
+    config.service.resources.qos == QosClass::Background
+}
+
 fn auto_sync_map(config: &Config) -> HashMap<String, bool> {
     let mut map = HashMap::new();
     for source in &config.sources {
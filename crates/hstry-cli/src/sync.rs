@@ -1,145 +1,590 @@
 //! Sync helpers shared between CLI and service.
 
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
 use anyhow::{Context, Result};
 use chrono::Utc;
-use hstry_core::{Database, ingest::ingest_batch, models::Source};
+use futures::StreamExt;
+use hstry_core::ingest_queue::{IngestTotals, QueuedBatch, ingest_queue, write_with_retry};
+use hstry_core::parsed::ParsedConversation;
+use hstry_core::source_files::{self, FileManifest};
+use hstry_core::source_filter::SourceFilter;
+use hstry_core::{Config, Database, cancel::CancelToken, models::Source};
 use hstry_runtime::{
-    AdapterRunner,
+    AdapterRunner, ConversationStream, StreamEvent,
     runner::{ParseOptions, ParseStreamResult},
 };
+use tokio::sync::mpsc;
 
 /// Number of conversations to buffer per streaming batch. Larger batches
 /// trade memory for fewer transaction commits and let the bulk-insert path
 /// pack more rows per multi-row INSERT statement.
 const DEFAULT_BATCH_SIZE: usize = 200;
 
+/// How often a running sync refreshes its source lock.
+const SYNC_LOCK_HEARTBEAT: Duration = Duration::from_secs(30);
+
+/// A source lock whose heartbeat is older than this is taken over.
+const SYNC_LOCK_STALE_AFTER: chrono::Duration = chrono::Duration::minutes(5);
+
+/// Another process is already syncing the source.
+#[derive(Debug)]
+pub struct SourceLocked(pub hstry_core::db::SyncLock);
+
+impl std::fmt::Display for SourceLocked {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let lock = &self.0;
+        write!(
+            f,
+            "'{}' is already being synced by pid {} on {} (since {})",
+            lock.source_id,
+            lock.pid,
+            lock.host,
+            lock.acquired_at.format("%Y-%m-%d %H:%M:%S")
+        )
+    }
+}
+
+impl std::error::Error for SourceLocked {}
+
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct SyncStats {
     pub source_id: String,
     pub conversations: usize,
     pub messages: usize,
+    /// Conversations parsed but left out by the source's filter.
+    pub filtered: usize,
+    /// Conversations created or updated by the sync.
+    #[serde(skip)]
+    pub conversation_ids: Vec<uuid::Uuid>,
+}
+
+/// Where a sync is when it reports progress.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncPhase {
+    /// The adapter was started and is discovering conversations.
+    Started,
+    /// Conversations arrived from the adapter and await writing.
+    Parsed,
+    /// A batch was committed.
+    Written,
+    /// The sync completed and the cursor was stored.
+    Finished,
+}
+
+/// Running totals for one source's sync.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct SyncProgress {
+    pub phase: SyncPhase,
+    /// Conversations received from the adapter so far.
+    pub parsed: usize,
+    /// Conversations and messages committed so far.
+    pub conversations: usize,
+    pub messages: usize,
 }
 
-/// Optional progress callback invoked once per committed batch.
+/// Optional progress callback.
 ///
-/// Arguments are the running total of conversations and messages written so
-/// far. Callers wire this to whatever UI they prefer (indicatif bar, plain
-/// stdout, structured logs). Returning is fast and infallible by contract —
-/// the callback must not block on I/O.
-pub type ProgressCallback<'a> = &'a (dyn Fn(usize, usize) + Send + Sync);
+/// Invoked when the adapter starts, for every conversation it emits, after
+/// every committed batch and once when the sync finishes. Callers wire this
+/// to whatever UI they prefer (indicatif bar, JSON events, structured logs).
+/// Returning is fast and infallible by contract — the callback must not
+/// block on slow I/O.
+pub type ProgressCallback<'a> = &'a (dyn Fn(SyncProgress) + Send + Sync);
 
 pub async fn sync_source(
     db: &Database,
     runner: &AdapterRunner,
     source: &Source,
 ) -> Result<SyncStats> {
-    sync_source_with_progress(db, runner, source, None).await
+    sync_source_with_progress(db, runner, source, None, None).await
+}
+
+/// Tag the conversations a sync wrote with the `tags` of the source's group.
+pub async fn apply_group_tags(db: &Database, config: &Config, stats: &SyncStats) -> Result<()> {
+    let Some((_, group)) = config.source_group(&stats.source_id) else {
+        return Ok(());
+    };
+    for tag in &group.tags {
+        db.add_tag_to_conversations(&stats.conversation_ids, tag)
+            .await?;
+    }
+    Ok(())
+}
+
+/// The adapter script used for `source`: its pinned `adapter_version`
+/// snapshot when set, otherwise the installed adapter.
+pub fn source_adapter_path(runner: &AdapterRunner, source: &Source) -> Result<PathBuf> {
+    match source
+        .config
+        .get("adapter_version")
+        .and_then(serde_json::Value::as_str)
+    {
+        Some(version) => runner
+            .find_adapter_version(&source.adapter, version)
+            .with_context(|| {
+                format!(
+                    "Source '{id}' is pinned to {adapter} {version}, which is not installed. \
+                     Run 'hstry source pin-adapter {id} --clear' to follow the installed adapter.",
+                    id = source.id,
+                    adapter = source.adapter
+                )
+            }),
+        None => runner
+            .find_adapter(&source.adapter)
+            .with_context(|| format!("Adapter '{}' not found", source.adapter)),
+    }
+}
+
+/// Catch the search index up after a sync that wrote messages, when
+/// `[search] auto_index` is on. Returns whether the index was rebuilt.
+pub async fn auto_index(db: &Database, config: &Config, stats: &[SyncStats]) -> Result<bool> {
+    if !config.search.auto_index || stats.iter().all(|stats| stats.messages == 0) {
+        return Ok(false);
+    }
+    Ok(db.catch_up_search_fts().await?)
 }
 
+/// Sync `source`, optionally reporting progress and honouring `cancel`.
+///
+/// Conversations are streamed from the adapter (`parseNdjson`) and written
+/// in batches of [`DEFAULT_BATCH_SIZE`], so memory stays bounded however big
+/// the source is; adapters without streaming fall back to `parseStream`
+/// batches, then to a single `parse`. Each batch is written in its own
+/// transaction. When `cancel` fires mid-batch the transaction is rolled back;
+/// batches already committed are kept and the cursor reached so far is
+/// persisted (without bumping `last_sync_at`), so the next sync resumes where
+/// this one stopped.
+///
+/// Only one process syncs a source at a time: the source's lock is taken
+/// first (failing with [`SourceLocked`] while someone else holds it) and its
+/// heartbeat refreshed until the sync ends.
 pub async fn sync_source_with_progress(
     db: &Database,
     runner: &AdapterRunner,
     source: &Source,
     progress: Option<ProgressCallback<'_>>,
+    cancel: Option<&CancelToken>,
+) -> Result<SyncStats> {
+    let pid = std::process::id();
+    let host = nix::unistd::gethostname()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    lock_source(db, &source.id, pid, &host).await?;
+
+    let sync = sync_source_locked(db, runner, source, progress, cancel);
+    tokio::pin!(sync);
+    let mut heartbeat = tokio::time::interval(SYNC_LOCK_HEARTBEAT);
+    heartbeat.tick().await;
+    let result = loop {
+        tokio::select! {
+            result = &mut sync => break result,
+            _ = heartbeat.tick() => {
+                if let Err(err) = db.heartbeat_source_sync(&source.id, pid, &host).await {
+                    tracing::warn!(source = %source.id, "sync lock heartbeat failed: {err}");
+                }
+            }
+        }
+    };
+
+    db.unlock_source_sync(&source.id, pid, &host).await?;
+    result
+}
+
+/// Take the sync lock for `source_id`, breaking it when the holder is a
+/// process on this host that no longer exists.
+async fn lock_source(db: &Database, source_id: &str, pid: u32, host: &str) -> Result<()> {
+    let stale_before = Utc::now() - SYNC_LOCK_STALE_AFTER;
+    let Some(holder) = db
+        .try_lock_source_sync(source_id, pid, host, stale_before)
+        .await?
+    else {
+        return Ok(());
+    };
+    if holder.host == host && !crate::service::is_process_running(holder.pid) {
+        db.break_sync_lock(&holder).await?;
+        if let Some(holder) = db
+            .try_lock_source_sync(source_id, pid, host, stale_before)
+            .await?
+        {
+            return Err(SourceLocked(holder).into());
+        }
+        return Ok(());
+    }
+    Err(SourceLocked(holder).into())
+}
+
+async fn sync_source_locked(
+    db: &Database,
+    runner: &AdapterRunner,
+    source: &Source,
+    progress: Option<ProgressCallback<'_>>,
+    cancel: Option<&CancelToken>,
 ) -> Result<SyncStats> {
-    let adapter_path = runner
-        .find_adapter(&source.adapter)
-        .with_context(|| format!("Adapter '{}' not found", source.adapter))?;
+    let adapter_path = source_adapter_path(runner, source)?;
 
     let path = source
         .path
         .as_ref()
         .with_context(|| format!("No path configured for source '{}'", source.id))?;
 
-    let mut new_count = 0usize;
-    let mut message_count = 0usize;
-    let mut affected_conversation_ids: Vec<uuid::Uuid> = Vec::new();
-
-    let mut cursor = source.config.get("cursor").cloned();
+    let filter = SourceFilter::from_source_config(&source.config)
+        .with_context(|| format!("Invalid filter for source '{}'", source.id))?;
+    let mut run = SyncRun {
+        db,
+        source,
+        root: PathBuf::from(path),
+        filter,
+        progress,
+        cancel,
+        cursor: source.config.get("cursor").cloned(),
+        parsed: AtomicUsize::new(0),
+        conversations: AtomicUsize::new(0),
+        messages: AtomicUsize::new(0),
+        filtered: AtomicUsize::new(0),
+        affected_conversation_ids: Vec::new(),
+    };
+    let options = source.config.get("options").cloned();
+    let files = scan_source_files(source, path).await;
+    let unchanged_files = files
+        .as_ref()
+        .map(|scan| scan.unchanged.clone())
+        .filter(|unchanged| !unchanged.is_empty());
+    let parse_opts = |cursor: Option<serde_json::Value>, batch_size: Option<usize>| ParseOptions {
+        since: source.last_sync_at.map(|dt| dt.timestamp_millis()),
+        limit: None,
+        include_tools: true,
+        include_attachments: true,
+        cursor,
+        batch_size,
+        options: options.clone(),
+        unchanged_files: unchanged_files.clone(),
+    };
 
-    let mut parsed_stream = runner
-        .parse_stream(
+    run.report(SyncPhase::Started);
+    if let Some(stream) = runner
+        .parse_ndjson(
             &adapter_path,
             path,
-            ParseOptions {
-                since: source.last_sync_at.map(|dt| dt.timestamp_millis()),
-                limit: None,
-                include_tools: true,
-                include_attachments: true,
-                cursor: cursor.clone(),
-                batch_size: Some(DEFAULT_BATCH_SIZE),
-            },
+            parse_opts(run.cursor.clone(), Some(DEFAULT_BATCH_SIZE)),
         )
-        .await?;
-
-    if parsed_stream.is_none() {
-        let conversations = runner
-            .parse(
+        .await?
+    {
+        run.consume_stream(stream).await?;
+    } else {
+        let mut parsed_stream = runner
+            .parse_stream(
                 &adapter_path,
                 path,
-                ParseOptions {
-                    since: source.last_sync_at.map(|dt| dt.timestamp_millis()),
-                    limit: None,
-                    include_tools: true,
-                    include_attachments: true,
-                    cursor: None,
-                    batch_size: None,
-                },
+                parse_opts(run.cursor.clone(), Some(DEFAULT_BATCH_SIZE)),
             )
             .await?;
-        parsed_stream = Some(ParseStreamResult {
-            conversations,
-            cursor: None,
-            done: Some(true),
-        });
+
+        if parsed_stream.is_none() {
+            let conversations = runner
+                .parse(&adapter_path, path, parse_opts(None, None))
+                .await?;
+            parsed_stream = Some(ParseStreamResult {
+                conversations,
+                cursor: None,
+                done: Some(true),
+            });
+        }
+
+        while let Some(batch) = parsed_stream.take() {
+            run.parsed
+                .fetch_add(batch.conversations.len(), Ordering::Relaxed);
+            run.report(SyncPhase::Parsed);
+            let conversations = run.apply_filter(batch.conversations);
+            run.write_batch(conversations, batch.cursor).await?;
+            if batch.done.unwrap_or(false) {
+                break;
+            }
+            parsed_stream = runner
+                .parse_stream(
+                    &adapter_path,
+                    path,
+                    parse_opts(run.cursor.clone(), Some(DEFAULT_BATCH_SIZE)),
+                )
+                .await?;
+        }
     }
 
-    while let Some(batch) = parsed_stream.take() {
-        if let Some(next_cursor) = batch.cursor.clone() {
-            cursor = Some(next_cursor);
+    // Rebuild summary caches for all affected conversations in one pass
+    if !run.affected_conversation_ids.is_empty() {
+        db.rebuild_conversation_summaries(&run.affected_conversation_ids)
+            .await?;
+    }
+
+    let mut updated = with_cursor(source, run.cursor.take());
+    if let Some(scan) = files {
+        updated = with_manifest(&updated, scan.manifest);
+    }
+    updated.last_sync_at = Some(Utc::now());
+    db.upsert_source(&updated).await?;
+    run.report(SyncPhase::Finished);
+
+    Ok(SyncStats {
+        source_id: source.id.clone(),
+        conversations: run.conversations.into_inner(),
+        messages: run.messages.into_inner(),
+        filtered: run.filtered.into_inner(),
+        conversation_ids: run.affected_conversation_ids,
+    })
+}
+
+/// Progress of one sync: what has been committed and the cursor covering it.
+///
+/// The counters are atomic because the parsing and writing halves of a
+/// streamed sync update them concurrently.
+struct SyncRun<'a> {
+    db: &'a Database,
+    source: &'a Source,
+    /// The source path, which file filters are relative to.
+    root: PathBuf,
+    filter: SourceFilter,
+    progress: Option<ProgressCallback<'a>>,
+    cancel: Option<&'a CancelToken>,
+    cursor: Option<serde_json::Value>,
+    parsed: AtomicUsize,
+    conversations: AtomicUsize,
+    messages: AtomicUsize,
+    filtered: AtomicUsize,
+    affected_conversation_ids: Vec<uuid::Uuid>,
+}
+
+impl SyncRun<'_> {
+    fn report(&self, phase: SyncPhase) {
+        if let Some(cb) = self.progress {
+            cb(SyncProgress {
+                phase,
+                parsed: self.parsed.load(Ordering::Relaxed),
+                conversations: self.conversations.load(Ordering::Relaxed),
+                messages: self.messages.load(Ordering::Relaxed),
+            });
+        }
+    }
+
+    /// Drop the conversations the source's filter leaves out.
+    fn apply_filter(&self, mut conversations: Vec<ParsedConversation>) -> Vec<ParsedConversation> {
+        if !self.filter.is_empty() {
+            let before = conversations.len();
+            conversations.retain(|conversation| self.filter.keeps(&self.root, conversation));
+            self.filtered
+                .fetch_add(before - conversations.len(), Ordering::Relaxed);
         }
+        conversations
+    }
 
-        let outcome = ingest_batch(db, &source.id, batch.conversations).await?;
-        new_count += outcome.conversations;
-        message_count += outcome.messages;
-        affected_conversation_ids.extend(outcome.affected_conversation_ids);
+    /// Write streamed conversations through an ingest queue, so the adapter
+    /// keeps parsing while earlier batches commit. When the stream, a write
+    /// or `cancel` stops the sync, what was committed is kept and the cursor
+    /// of the last committed batch stored, so the next sync resumes there.
+    async fn consume_stream(&mut self, stream: ConversationStream) -> Result<()> {
+        let (sender, writer) = ingest_queue(self.db);
+        let mut totals = IngestTotals::default();
+        let result = {
+            let run = &*self;
+            let (conversations, messages) = (
+                run.conversations.load(Ordering::Relaxed),
+                run.messages.load(Ordering::Relaxed),
+            );
+            let write = writer.run(run.db, &run.source.id, &mut totals, |totals| {
+                run.conversations
+                    .store(conversations + totals.conversations, Ordering::Relaxed);
+                run.messages
+                    .store(messages + totals.messages, Ordering::Relaxed);
+                run.report(SyncPhase::Written);
+            });
+            let both = async { tokio::try_join!(run.queue_stream(stream, sender), write) };
+            let finished = match run.cancel {
+                Some(token) => token.run(both).await.map_err(anyhow::Error::from),
+                None => Ok(both.await),
+            };
+            finished.and_then(|result| result.map(|_| ()))
+        };
 
-        if outcome.conversations > 0
-            && let Some(cb) = progress
-        {
-            cb(new_count, message_count);
+        if totals.cursor.is_some() {
+            self.cursor = totals.cursor;
         }
+        self.affected_conversation_ids
+            .extend(totals.affected_conversation_ids);
+        if let Err(err) = result {
+            self.save_partial_progress().await?;
+            return Err(err);
+        }
+        Ok(())
+    }
 
-        let done = batch.done.unwrap_or(false);
-        if done {
-            break;
+    /// Batch `stream` onto `sender`. A checkpoint closes the pending batch,
+    /// so the stored cursor never runs ahead of what was committed.
+    async fn queue_stream(
+        &self,
+        mut stream: ConversationStream,
+        sender: mpsc::Sender<QueuedBatch>,
+    ) -> Result<()> {
+        let mut batch = Vec::with_capacity(DEFAULT_BATCH_SIZE);
+        while let Some(event) = stream.next().await.transpose()? {
+            let cursor = match event {
+                StreamEvent::Conversation(conversation) => {
+                    self.parsed.fetch_add(1, Ordering::Relaxed);
+                    self.report(SyncPhase::Parsed);
+                    if !self.filter.keeps(&self.root, &conversation) {
+                        self.filtered.fetch_add(1, Ordering::Relaxed);
+                        continue;
+                    }
+                    batch.push(*conversation);
+                    if batch.len() < DEFAULT_BATCH_SIZE {
+                        continue;
+                    }
+                    None
+                }
+                StreamEvent::Checkpoint(cursor) => Some(cursor),
+            };
+            let conversations =
+                std::mem::replace(&mut batch, Vec::with_capacity(DEFAULT_BATCH_SIZE));
+            if sender
+                .send(QueuedBatch {
+                    conversations,
+                    cursor,
+                })
+                .await
+                .is_err()
+            {
+                // The writer stopped; its error is the one reported.
+                return Ok(());
+            }
+        }
+        if !batch.is_empty() {
+            let _ = sender
+                .send(QueuedBatch {
+                    conversations: batch,
+                    cursor: None,
+                })
+                .await;
         }
+        Ok(())
+    }
 
-        parsed_stream = runner
-            .parse_stream(
-                &adapter_path,
-                path,
-                ParseOptions {
-                    since: source.last_sync_at.map(|dt| dt.timestamp_millis()),
-                    limit: None,
-                    include_tools: true,
-                    include_attachments: true,
-                    cursor: cursor.clone(),
-                    batch_size: Some(DEFAULT_BATCH_SIZE),
+    /// Commit `conversations` in one transaction, then advance the cursor
+    /// and wait as nice mode asks.
+    async fn write_batch(
+        &mut self,
+        conversations: Vec<ParsedConversation>,
+        cursor: Option<serde_json::Value>,
+    ) -> Result<()> {
+        let mut written = None;
+        if !conversations.is_empty() {
+            let write = write_with_retry(
+                self.db,
+                &self.source.id,
+                conversations,
+                self.db.ingest_queue_config(),
+            );
+            let outcome = match self.cancel {
+                Some(token) => match token.run(write).await {
+                    Ok(outcome) => outcome?,
+                    Err(err) => {
+                        self.save_partial_progress().await?;
+                        return Err(err.into());
+                    }
                 },
-            )
-            .await?;
-        if parsed_stream.is_none() {
-            break;
+                None => write.await?,
+            };
+            self.conversations
+                .fetch_add(outcome.conversations, Ordering::Relaxed);
+            self.messages.fetch_add(outcome.messages, Ordering::Relaxed);
+            self.affected_conversation_ids
+                .extend(outcome.affected_conversation_ids);
+            written = Some(outcome.bytes);
+            self.report(SyncPhase::Written);
+        }
+        if let Some(cursor) = cursor {
+            self.cursor = Some(cursor);
+        }
+        if let Some(bytes) = written {
+            let pace = self.db.pace_writes(bytes);
+            match self.cancel {
+                Some(token) => {
+                    if let Err(err) = token.run(pace).await {
+                        self.save_partial_progress().await?;
+                        return Err(err.into());
+                    }
+                }
+                None => pace.await,
+            }
         }
+        Ok(())
     }
 
-    // Rebuild summary caches for all affected conversations in one pass
-    if !affected_conversation_ids.is_empty() {
-        db.rebuild_conversation_summaries(&affected_conversation_ids)
-            .await?;
+    /// Record what a cancelled sync committed: refresh summaries for the
+    /// batches that landed and store the last committed cursor.
+    /// `last_sync_at` is left untouched so the `since` filter does not skip
+    /// the unsynced remainder.
+    async fn save_partial_progress(&self) -> Result<()> {
+        if !self.affected_conversation_ids.is_empty() {
+            self.db
+                .rebuild_conversation_summaries(&self.affected_conversation_ids)
+                .await?;
+        }
+        if self.cursor.is_some() {
+            self.db
+                .upsert_source(&with_cursor(self.source, self.cursor.clone()))
+                .await?;
+        }
+        Ok(())
     }
+}
+
+/// Compare the files under `path` with the manifest of the last completed
+/// sync. `None` when they cannot be listed, e.g. because the path is gone;
+/// the adapter then reads everything as before.
+async fn scan_source_files(source: &Source, path: &str) -> Option<source_files::FileScan> {
+    let previous = source_files::stored_manifest(&source.config);
+    let path = PathBuf::from(path);
+    let scan = tokio::task::spawn_blocking(move || source_files::scan(&path, &previous)).await;
+    match scan {
+        Ok(Ok(scan)) => Some(scan),
+        Ok(Err(err)) => {
+            tracing::debug!(source = %source.id, "could not list source files: {err}");
+            None
+        }
+        Err(err) => {
+            tracing::warn!(source = %source.id, "source file scan failed: {err}");
+            None
+        }
+    }
+}
 
+/// Copy of `source` with its file manifest replaced by `manifest`.
+fn with_manifest(source: &Source, manifest: FileManifest) -> Source {
+    let mut updated = source.clone();
+    let mut config = match updated.config {
+        serde_json::Value::Object(config) => config,
+        _ => serde_json::Map::new(),
+    };
+    if let Ok(manifest) = serde_json::to_value(manifest) {
+        config.insert(source_files::MANIFEST_KEY.to_string(), manifest);
+    }
+    updated.config = serde_json::Value::Object(config);
+    updated
+}
+
+/// Forget what earlier syncs of `source` covered, so the next sync reparses
+/// everything: `last_sync_at`, the adapter cursor and the file manifest.
+pub fn forget_sync_state(source: &mut Source) {
+    source.last_sync_at = None;
+    if let serde_json::Value::Object(config) = &mut source.config {
+        config.remove("cursor");
+        config.remove(source_files::MANIFEST_KEY);
+    }
+}
+
+/// Copy of `source` with its stored cursor replaced by `cursor`.
+fn with_cursor(source: &Source, cursor: Option<serde_json::Value>) -> Source {
     let mut updated = source.clone();
     if let serde_json::Value::Object(mut config) = updated.config.clone() {
         if let Some(cursor) = cursor {
@@ -153,12 +598,5 @@ pub async fn sync_source_with_progress(
         config.insert("cursor".to_string(), cursor);
         updated.config = serde_json::Value::Object(config);
     }
-    updated.last_sync_at = Some(Utc::now());
-    db.upsert_source(&updated).await?;
-
-    Ok(SyncStats {
-        source_id: source.id.clone(),
-        conversations: new_count,
-        messages: message_count,
-    })
+    updated
 }
@@ -0,0 +1,145 @@
+//! `hstry db merge` against a throwaway config and databases.
+
+use std::path::{Path, PathBuf};
+use std::process::Output;
+
+use chrono::Utc;
+use hstry_core::Database;
+use hstry_core::models::{Conversation, Source};
+use tempfile::TempDir;
+use uuid::Uuid;
+
+/// A config in `dir` whose database is `main.db`, plus the path of that
+/// database.
+fn setup(dir: &TempDir) -> (PathBuf, PathBuf) {
+    let database = dir.path().join("main.db");
+    let config = dir.path().join("config.toml");
+    std::fs::write(
+        &config,
+        format!("database = {:?}\n", database.display().to_string()),
+    )
+    .expect("write config");
+    (config, database)
+}
+
+fn hstry(dir: &TempDir, config: &Path, args: &[&str]) -> Output {
+    std::process::Command::new(env!("CARGO_BIN_EXE_hstry"))
+        .arg("--config")
+        .arg(config)
+        .args(args)
+        .env("HOME", dir.path())
+        .env("XDG_CONFIG_HOME", dir.path().join("config"))
+        .env("XDG_DATA_HOME", dir.path().join("data"))
+        .env("XDG_STATE_HOME", dir.path().join("state"))
+        .output()
+        .expect("run hstry")
+}
+
+/// A database at `path` with one conversation of source `codex`.
+async fn other_database(path: &Path) {
+    let db = Database::open(path).await.expect("open other db");
+    db.upsert_source(&Source {
+        id: "codex".to_string(),
+        adapter: "codex".to_string(),
+        path: None,
+        last_sync_at: None,
+        config: serde_json::json!({}),
+    })
+    .await
+    .expect("upsert source");
+    db.upsert_conversation(&Conversation {
+        id: Uuid::new_v4(),
+        source_id: "codex".to_string(),
+        external_id: Some("session-1".to_string()),
+        readable_id: None,
+        platform_id: None,
+        title: Some("From the old laptop".to_string()),
+        created_at: Utc::now(),
+        updated_at: None,
+        model: None,
+        provider: None,
+        workspace: None,
+        tokens_in: None,
+        tokens_out: None,
+        cost_usd: None,
+        metadata: serde_json::json!({}),
+        harness: None,
+        version: 0,
+        message_count: 0,
+        parent_conversation_id: None,
+        parent_message_idx: None,
+        fork_type: None,
+    })
+    .await
+    .expect("upsert conversation");
+    db.close().await;
+}
+
+#[tokio::test]
+async fn merges_sources_under_a_namespace() {
+    let dir = TempDir::new().expect("tempdir");
+    let (config, database) = setup(&dir);
+    let other = dir.path().join("laptop.db");
+    other_database(&other).await;
+
+    let output = hstry(&dir, &config, &["db", "merge", &other.to_string_lossy()]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let output = hstry(
+        &dir,
+        &config,
+        &[
+            "db",
+            "merge",
+            &other.to_string_lossy(),
+            "--namespace",
+            "old",
+        ],
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let db = Database::open(&database).await.expect("open main db");
+    for source in ["laptop:codex", "old:codex"] {
+        let id = db
+            .get_conversation_id(source, "session-1")
+            .await
+            .expect("lookup");
+        assert!(id.is_some(), "{source}");
+    }
+    assert!(db.get_source("codex").await.expect("lookup").is_none());
+}
+
+#[test]
+fn refuses_the_database_being_merged_into_and_unreadable_ones() {
+    let dir = TempDir::new().expect("tempdir");
+    let (config, database) = setup(&dir);
+
+    // Any command creates the configured database.
+    assert!(hstry(&dir, &config, &["db", "size"]).status.success());
+    let output = hstry(&dir, &config, &["db", "merge", &database.to_string_lossy()]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("is the database being merged into"),
+        "{stderr}"
+    );
+
+    // Without a configured key an encrypted database cannot be read.
+    let encrypted = dir.path().join("encrypted.db");
+    std::fs::write(&encrypted, [0x5a_u8; 4096]).expect("write");
+    let output = hstry(
+        &dir,
+        &config,
+        &["db", "merge", &encrypted.to_string_lossy()],
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("[database.encryption]"), "{stderr}");
+}
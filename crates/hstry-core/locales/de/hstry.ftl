@@ -0,0 +1,32 @@
+# German messages for the hstry CLI and TUI.
+
+## CLI
+
+cli-no-conversations = Keine Unterhaltungen gefunden.
+cli-no-results = Keine Treffer gefunden.
+cli-no-sources = Keine Quellen konfiguriert. Mit 'hstry source add <pfad>' eine Quelle hinzufügen.
+cli-no-decisions = Keine Entscheidungen gefunden.
+cli-no-saved-searches = Keine gespeicherten Suchen. Mit `hstry search <anfrage> --save <name>` eine speichern.
+cli-deleted-saved-search = Gespeicherte Suche '{ $name }' gelöscht
+
+## TUI
+
+tui-help-hint = ? für Hilfe, q zum Beenden
+tui-all-conversations = Alle Unterhaltungen
+tui-view = Ansicht: { $view }
+tui-view-sources = Quellen
+tui-view-workspaces = Arbeitsbereiche
+tui-view-dates = Daten
+tui-view-saved = Gespeichert
+tui-sorted-by = Sortiert nach: { $order }
+tui-sort-date-desc = Datum (neueste zuerst)
+tui-sort-date-asc = Datum (älteste zuerst)
+tui-sort-title-asc = Titel (A-Z)
+tui-sort-title-desc = Titel (Z-A)
+tui-sort-source = Quelle
+tui-found-results = { $count } Treffer gefunden ({ $scope })
+tui-search-error = Suchfehler: { $error }
+tui-no-conversation = Keine Unterhaltung ausgewählt
+tui-loading-conversation = Unterhaltung wird geladen...
+tui-data-refreshed = Daten aktualisiert
+tui-search-cleared = Suchergebnisse gelöscht
@@ -0,0 +1,36 @@
+# English messages for the hstry CLI and TUI. Every other locale falls back
+# to these for messages it does not translate.
+
+## CLI
+
+cli-no-conversations = No conversations found.
+cli-no-results = No results found.
+cli-no-sources = No sources configured. Use 'hstry source add <path>' to add a source.
+cli-no-decisions = No decisions found.
+cli-no-saved-searches = No saved searches. Save one with `hstry search <query> --save <name>`.
+cli-deleted-saved-search = Deleted saved search '{ $name }'
+
+## TUI
+
+tui-help-hint = Press ? for help, q to quit
+tui-all-conversations = All Conversations
+tui-view = View: { $view }
+tui-view-sources = Sources
+tui-view-workspaces = Workspaces
+tui-view-dates = Dates
+tui-view-saved = Saved
+tui-sorted-by = Sorted by: { $order }
+tui-sort-date-desc = Date (newest first)
+tui-sort-date-asc = Date (oldest first)
+tui-sort-title-asc = Title (A-Z)
+tui-sort-title-desc = Title (Z-A)
+tui-sort-source = Source
+tui-found-results = Found { $count } { $count ->
+        [one] result
+       *[other] results
+    } ({ $scope })
+tui-search-error = Search error: { $error }
+tui-no-conversation = No conversation selected
+tui-loading-conversation = Loading conversation...
+tui-data-refreshed = Data refreshed
+tui-search-cleared = Cleared search results
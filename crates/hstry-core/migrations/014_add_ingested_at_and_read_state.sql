@@ -0,0 +1,26 @@
+-- Track when each conversation first landed in this database and a per-profile
+-- "last seen" watermark, so clients can flag conversations ingested since the
+-- user's previous session as new.
+--
+-- ingested_at is stamped by a trigger rather than by every insert path
+-- (adapters, remote sync, archive transfers). Existing rows are back-filled
+-- from created_at, which is the best available approximation.
+
+ALTER TABLE conversations ADD COLUMN ingested_at INTEGER;
+
+UPDATE conversations SET ingested_at = created_at WHERE ingested_at IS NULL;
+
+CREATE INDEX IF NOT EXISTS idx_conversations_ingested_at ON conversations(ingested_at);
+
+CREATE TRIGGER IF NOT EXISTS conversations_stamp_ingested_at
+AFTER INSERT ON conversations
+WHEN NEW.ingested_at IS NULL
+BEGIN
+    UPDATE conversations SET ingested_at = CAST(strftime('%s', 'now') AS INTEGER)
+    WHERE id = NEW.id;
+END;
+
+CREATE TABLE IF NOT EXISTS read_state (
+    profile TEXT PRIMARY KEY,
+    last_seen_at INTEGER NOT NULL
+);
@@ -0,0 +1,4 @@
+-- `hstry tools list/stats` filter and group tool calls by name. The table
+-- was only indexed by message_id, which made those full scans.
+
+CREATE INDEX IF NOT EXISTS idx_tool_calls_name ON tool_calls(tool_name);
@@ -0,0 +1,8 @@
+-- Message-level branches: regenerated answers and edited prompts form a tree
+-- within one conversation. parent_message_id points at the message a row
+-- continues from; NULL everywhere means a plain linear conversation.
+
+ALTER TABLE messages ADD COLUMN parent_message_id TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_messages_parent ON messages(parent_message_id)
+WHERE parent_message_id IS NOT NULL;
@@ -0,0 +1,28 @@
+-- Stamp conversations whenever their content changes so `hstry export --since`
+-- (and remote delta sync on top of it) can ship only what is new or updated.
+--
+-- ingested_at only records the first insert; changed_at also moves when a
+-- re-sync bumps the version or appends messages. Existing rows are back-filled
+-- from ingested_at.
+
+ALTER TABLE conversations ADD COLUMN changed_at INTEGER;
+
+UPDATE conversations SET changed_at = COALESCE(ingested_at, created_at) WHERE changed_at IS NULL;
+
+CREATE INDEX IF NOT EXISTS idx_conversations_changed_at ON conversations(changed_at);
+
+CREATE TRIGGER IF NOT EXISTS conversations_stamp_changed_at_insert
+AFTER INSERT ON conversations
+WHEN NEW.changed_at IS NULL
+BEGIN
+    UPDATE conversations SET changed_at = CAST(strftime('%s', 'now') AS INTEGER)
+    WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS conversations_stamp_changed_at_update
+AFTER UPDATE OF version, message_count ON conversations
+WHEN NEW.version IS NOT OLD.version OR NEW.message_count IS NOT OLD.message_count
+BEGIN
+    UPDATE conversations SET changed_at = CAST(strftime('%s', 'now') AS INTEGER)
+    WHERE id = NEW.id;
+END;
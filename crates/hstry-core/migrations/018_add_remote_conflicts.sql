@@ -0,0 +1,23 @@
+-- Conversations that changed on both sides of a remote sync. Conflicts
+-- settled automatically by the remote's strategy are stored resolved; the
+-- duplicate strategy keeps both copies and leaves the row open for review
+-- with `hstry remote conflicts`.
+
+CREATE TABLE IF NOT EXISTS remote_conflicts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    remote TEXT NOT NULL,
+    source_id TEXT NOT NULL,
+    external_id TEXT NOT NULL,
+    title TEXT,
+    local_conversation_id TEXT,
+    duplicate_conversation_id TEXT,
+    local_updated_at INTEGER,
+    remote_updated_at INTEGER,
+    strategy TEXT NOT NULL,
+    resolution TEXT NOT NULL,
+    detected_at INTEGER NOT NULL,
+    resolved_at INTEGER
+);
+
+CREATE INDEX IF NOT EXISTS idx_remote_conflicts_open
+ON remote_conflicts(remote, detected_at) WHERE resolved_at IS NULL;
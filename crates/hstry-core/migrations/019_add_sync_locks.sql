@@ -0,0 +1,12 @@
+-- Advisory per-source sync locks, so the service and a manual `hstry sync`
+-- never ingest the same source at once. Holders refresh heartbeat_at while
+-- they run; a lock whose heartbeat has gone quiet, or whose process is gone,
+-- may be taken over.
+
+CREATE TABLE IF NOT EXISTS sync_locks (
+    source_id TEXT PRIMARY KEY,
+    pid INTEGER NOT NULL,
+    host TEXT NOT NULL,
+    acquired_at INTEGER NOT NULL,
+    heartbeat_at INTEGER NOT NULL
+);
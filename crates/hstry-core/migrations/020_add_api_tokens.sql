@@ -0,0 +1,14 @@
+-- Bearer tokens for hstry-api, managed with `hstry api token`. Only the
+-- SHA-256 of each token is stored; the prefix is kept so `list` can show
+-- which token is which.
+
+CREATE TABLE IF NOT EXISTS api_tokens (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    prefix TEXT NOT NULL,
+    scope TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    last_used_at INTEGER,
+    revoked_at INTEGER
+);
@@ -0,0 +1,56 @@
+-- Append-only log of conversation and source changes. Readers such as the
+-- TUI remember the last seq they saw and poll for newer rows, so data
+-- ingested by the background service shows up without a manual refresh.
+--
+-- Rows are written by triggers, so every writer (service, CLI, API) is
+-- covered. The service prunes entries older than a day.
+
+CREATE TABLE IF NOT EXISTS change_log (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    -- 'conversation' or 'source'
+    entity TEXT NOT NULL,
+    entity_id TEXT NOT NULL,
+    -- 'insert', 'update' or 'delete'
+    kind TEXT NOT NULL,
+    changed_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_change_log_changed_at ON change_log(changed_at);
+
+CREATE TRIGGER IF NOT EXISTS change_log_conversation_insert
+AFTER INSERT ON conversations
+BEGIN
+    INSERT INTO change_log (entity, entity_id, kind, changed_at)
+    VALUES ('conversation', NEW.id, 'insert', CAST(strftime('%s', 'now') AS INTEGER));
+END;
+
+CREATE TRIGGER IF NOT EXISTS change_log_conversation_update
+AFTER UPDATE OF version, message_count, title ON conversations
+WHEN NEW.version IS NOT OLD.version
+    OR NEW.message_count IS NOT OLD.message_count
+    OR NEW.title IS NOT OLD.title
+BEGIN
+    INSERT INTO change_log (entity, entity_id, kind, changed_at)
+    VALUES ('conversation', NEW.id, 'update', CAST(strftime('%s', 'now') AS INTEGER));
+END;
+
+CREATE TRIGGER IF NOT EXISTS change_log_conversation_delete
+AFTER DELETE ON conversations
+BEGIN
+    INSERT INTO change_log (entity, entity_id, kind, changed_at)
+    VALUES ('conversation', OLD.id, 'delete', CAST(strftime('%s', 'now') AS INTEGER));
+END;
+
+CREATE TRIGGER IF NOT EXISTS change_log_source_insert
+AFTER INSERT ON sources
+BEGIN
+    INSERT INTO change_log (entity, entity_id, kind, changed_at)
+    VALUES ('source', NEW.id, 'insert', CAST(strftime('%s', 'now') AS INTEGER));
+END;
+
+CREATE TRIGGER IF NOT EXISTS change_log_source_delete
+AFTER DELETE ON sources
+BEGIN
+    INSERT INTO change_log (entity, entity_id, kind, changed_at)
+    VALUES ('source', OLD.id, 'delete', CAST(strftime('%s', 'now') AS INTEGER));
+END;
@@ -0,0 +1,17 @@
+-- One row per request to a model endpoint (re-ranking, `hstry ask`, ...),
+-- so `hstry stats` can report tokens and cost per feature and provider.
+-- `provider` is the `[llm.providers]` name, or the URL of an endpoint set
+-- directly in a feature's own section.
+
+CREATE TABLE IF NOT EXISTS llm_usage (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    created_at INTEGER NOT NULL,
+    feature TEXT NOT NULL,
+    provider TEXT NOT NULL,
+    model TEXT,
+    tokens_in INTEGER NOT NULL DEFAULT 0,
+    tokens_out INTEGER NOT NULL DEFAULT 0,
+    cost_usd REAL NOT NULL DEFAULT 0
+);
+
+CREATE INDEX IF NOT EXISTS idx_llm_usage_created_at ON llm_usage(created_at);
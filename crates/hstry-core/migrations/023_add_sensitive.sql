@@ -0,0 +1,21 @@
+-- Sensitive conversations: their messages keep content = '' and
+-- parts_json = '[]' and store both encrypted in `sealed` instead, so the
+-- FTS triggers only ever index the empty string. `sensitive` marks the
+-- conversation even after `[sensitive]` stops matching it.
+--
+-- sensitive_key holds the single age key pair messages are sealed to; the
+-- identity is wrapped with the user's passphrase.
+
+ALTER TABLE messages ADD COLUMN sealed BLOB;
+
+ALTER TABLE conversations ADD COLUMN sensitive INTEGER NOT NULL DEFAULT 0;
+
+CREATE INDEX IF NOT EXISTS idx_conversations_sensitive ON conversations(sensitive)
+WHERE sensitive = 1;
+
+CREATE TABLE IF NOT EXISTS sensitive_key (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    recipient TEXT NOT NULL,
+    identity BLOB NOT NULL,
+    created_at INTEGER NOT NULL
+);
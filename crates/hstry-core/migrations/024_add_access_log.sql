@@ -0,0 +1,15 @@
+-- Reads served over hstry-api and hstry-mcp, for `hstry access log`.
+-- `caller` is the API token name or MCP scope (NULL for open reads), and
+-- `conversation_ids` a JSON array of the conversations the read returned.
+
+CREATE TABLE IF NOT EXISTS access_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    accessed_at INTEGER NOT NULL,
+    channel TEXT NOT NULL,
+    caller TEXT,
+    action TEXT NOT NULL,
+    query TEXT,
+    conversation_ids TEXT NOT NULL DEFAULT '[]'
+);
+
+CREATE INDEX IF NOT EXISTS idx_access_log_accessed_at ON access_log(accessed_at);
@@ -0,0 +1,11 @@
+-- When each export target was last written by `hstry export --incremental`.
+-- `target` is the absolute output directory; the next incremental run to the
+-- same target and format only rewrites conversations changed since
+-- `exported_at`.
+
+CREATE TABLE IF NOT EXISTS export_state (
+    target TEXT NOT NULL,
+    format TEXT NOT NULL,
+    exported_at INTEGER NOT NULL,
+    PRIMARY KEY (target, format)
+);
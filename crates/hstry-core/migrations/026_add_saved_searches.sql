@@ -0,0 +1,9 @@
+-- Named searches from `hstry search --save`. `filters` is a JSON object of
+-- the search flags, with dates kept as typed so relative ones stay relative.
+
+CREATE TABLE IF NOT EXISTS saved_searches (
+    name TEXT PRIMARY KEY,
+    query TEXT NOT NULL,
+    filters TEXT NOT NULL DEFAULT '{}',
+    created_at INTEGER NOT NULL
+);
@@ -0,0 +1,20 @@
+-- Decision statements found in conversations by `hstry decisions`.
+-- decision_scans records when each conversation was last scanned, so only
+-- new or changed conversations are scanned again.
+
+CREATE TABLE IF NOT EXISTS decisions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    conversation_id TEXT NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+    message_id TEXT NOT NULL,
+    workspace TEXT,
+    text TEXT NOT NULL,
+    decided_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_decisions_workspace ON decisions(workspace, decided_at);
+CREATE INDEX IF NOT EXISTS idx_decisions_conversation ON decisions(conversation_id);
+
+CREATE TABLE IF NOT EXISTS decision_scans (
+    conversation_id TEXT PRIMARY KEY REFERENCES conversations(id) ON DELETE CASCADE,
+    scanned_at INTEGER NOT NULL
+);
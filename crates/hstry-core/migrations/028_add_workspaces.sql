@@ -0,0 +1,12 @@
+-- Metadata for the workspaces conversations are stored under, managed by
+-- `hstry workspace`. `aliases` is a JSON array of other paths (e.g. ones
+-- merged into this workspace); conversations reported under an alias are
+-- stored under `path`.
+
+CREATE TABLE IF NOT EXISTS workspaces (
+    path TEXT PRIMARY KEY,
+    display_name TEXT,
+    git_remote TEXT,
+    aliases TEXT NOT NULL DEFAULT '[]',
+    updated_at INTEGER NOT NULL
+);
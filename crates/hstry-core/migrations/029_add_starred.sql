@@ -0,0 +1,8 @@
+-- Starred conversations: `hstry star` sets the flag, `--starred` filters
+-- list and search on it, and prune and dedup leave starred conversations
+-- alone unless asked not to.
+
+ALTER TABLE conversations ADD COLUMN starred INTEGER NOT NULL DEFAULT 0;
+
+CREATE INDEX IF NOT EXISTS idx_conversations_starred ON conversations(starred)
+WHERE starred = 1;
@@ -0,0 +1,6 @@
+-- Titles written by `hstry title generate`. title_generated is 1 for a
+-- title given to an untitled conversation and 2 for one that replaced the
+-- adapter's title (`--force`). Syncs keep a generated title while the
+-- adapter reports none, and a forced one always.
+
+ALTER TABLE conversations ADD COLUMN title_generated INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,5 @@
+-- Token counts written by `hstry tokens backfill` for messages whose adapter
+-- reported none. Syncs keep an estimate while the adapter still reports no
+-- count and the content is unchanged.
+
+ALTER TABLE messages ADD COLUMN tokens_estimated INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,21 @@
+-- Copies of one session imported through different sources, such as a
+-- claude-code session that also appears in a claude.ai export. Found by
+-- `hstry link detect`, which matches external ids, platform ids and
+-- message text. Each pair is stored once, conversation_id < related_id.
+
+CREATE TABLE IF NOT EXISTS related_conversations (
+    conversation_id TEXT NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+    related_id TEXT NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+    -- external_id, platform_id or content
+    reason TEXT NOT NULL,
+    -- Similarity of the messages; 1 for id matches
+    score REAL NOT NULL,
+    detected_at INTEGER NOT NULL,
+    PRIMARY KEY (conversation_id, related_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_related_conversations_related
+ON related_conversations(related_id);
+
+-- External ids are matched across sources.
+CREATE INDEX IF NOT EXISTS idx_conv_external_id ON conversations(external_id);
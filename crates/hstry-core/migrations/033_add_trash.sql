@@ -0,0 +1,9 @@
+-- Trash: `hstry remove`, the TUI and the API set deleted_at instead of
+-- deleting. Trashed conversations stay out of lists, counts and search
+-- until `hstry trash restore` clears the mark; `hstry trash empty` and the
+-- service's `[trash] purge_after_days` delete them for good.
+
+ALTER TABLE conversations ADD COLUMN deleted_at INTEGER;
+
+CREATE INDEX IF NOT EXISTS idx_conversations_deleted_at ON conversations(deleted_at)
+WHERE deleted_at IS NOT NULL;
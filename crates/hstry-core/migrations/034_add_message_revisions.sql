@@ -0,0 +1,10 @@
+-- Message revisions: when a re-sync rewrites a message with different
+-- text, the old text is kept as a message_events row with kind 'edit' or
+-- 'regenerate' (see revisions.rs). Rows mirrored by
+-- `[storage.message_events] enabled` keep kind 'upsert'; only those are
+-- streamed over gRPC and compacted.
+
+ALTER TABLE message_events ADD COLUMN kind TEXT NOT NULL DEFAULT 'upsert';
+
+CREATE INDEX IF NOT EXISTS idx_msg_events_revisions ON message_events(conversation_id, idx, created_at)
+WHERE kind != 'upsert';
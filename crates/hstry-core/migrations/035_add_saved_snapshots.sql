@@ -0,0 +1,19 @@
+-- Saved snapshots: point-in-time copies of a conversation, taken by
+-- `hstry snapshot create` and before dedup, merge and segment rewrite or
+-- delete it; `hstry snapshot restore` rolls the conversation back.
+-- (conversation_snapshots is the one-row-per-conversation read cache of
+-- get_messages and cannot hold several copies.) No foreign key: a
+-- snapshot outlives the conversation so a removed one can be restored.
+
+CREATE TABLE IF NOT EXISTS saved_snapshots (
+    id TEXT PRIMARY KEY,
+    conversation_id TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    title TEXT,
+    message_count INTEGER NOT NULL,
+    payload_json TEXT NOT NULL,
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_saved_snapshots_conversation
+ON saved_snapshots(conversation_id, created_at DESC);
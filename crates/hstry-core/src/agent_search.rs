@@ -0,0 +1,381 @@
+//! Search results shaped for agents.
+//!
+//! Agents calling `search_history` over MCP have small context budgets, and
+//! raw hits spend it on tool output, injected system context and the same
+//! conversation matching many times. [`rank_for_agents`] drops that noise,
+//! keeps the best few hits per conversation, favours focused messages over
+//! very long ones, and cuts each hit to a short excerpt around the match.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::models::{MessageRole, SearchHit};
+
+/// Truncation and deduplication knobs.
+#[derive(Debug, Clone)]
+pub struct AgentSearchConfig {
+    /// Maximum characters of message text per hit.
+    pub excerpt_chars: usize,
+    /// Hits kept per conversation.
+    pub per_conversation: usize,
+    /// Fenced code blocks with more lines than this are replaced by a
+    /// one-line placeholder.
+    pub max_code_lines: usize,
+}
+
+impl Default for AgentSearchConfig {
+    fn default() -> Self {
+        Self {
+            excerpt_chars: 400,
+            per_conversation: 2,
+            max_code_lines: 12,
+        }
+    }
+}
+
+/// One hit in an agent-oriented result.
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentHit {
+    pub conversation_id: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub readable_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub source: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workspace: Option<String>,
+    pub message_idx: i32,
+    pub role: MessageRole,
+    pub excerpt: String,
+    /// Further matching messages in the same conversation that were folded
+    /// into this result.
+    #[serde(skip_serializing_if = "is_zero")]
+    pub more_matches: usize,
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+/// Detect system context (AGENTS.md, skill lists, system prompts) that
+/// harnesses inject into conversations.
+pub fn is_system_context(content: &str) -> bool {
+    // Strong markers - if any of these are present, it's system context
+    let strong_markers = [
+        "# AGENTS.md",
+        "# Agent Configuration",
+        "<available_skills>",
+        "Guidance for coding agents",
+        "<SYSTEM_PROMPT>",
+        "</SYSTEM_PROMPT>",
+    ];
+
+    for marker in &strong_markers {
+        if content.contains(marker) {
+            return true;
+        }
+    }
+
+    // Check for AGENTS.md file path pattern
+    if content.contains("AGENTS.md") && content.contains("instructions") {
+        return true;
+    }
+
+    false
+}
+
+/// Whether `content` is a machine-readable dump (tool output pasted or
+/// echoed into a message) rather than prose.
+pub fn is_tool_dump(content: &str) -> bool {
+    let trimmed = content.trim();
+    if (trimmed.starts_with('{') || trimmed.starts_with('['))
+        && trimmed.len() > 200
+        && serde_json::from_str::<serde_json::Value>(trimmed).is_ok()
+    {
+        return true;
+    }
+    // Mostly fenced code with little text around it.
+    let total = trimmed.len();
+    if total < 1_000 {
+        return false;
+    }
+    let mut fenced = 0;
+    let mut in_fence = false;
+    for line in trimmed.lines() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            fenced += line.len() + 1;
+        }
+    }
+    fenced * 10 >= total * 9
+}
+
+/// Filter, deduplicate, re-rank and excerpt `hits` (best first) for an agent.
+/// Returns at most `limit` hits.
+pub fn rank_for_agents(
+    hits: &[SearchHit],
+    config: &AgentSearchConfig,
+    limit: usize,
+) -> Vec<AgentHit> {
+    let mut candidates: Vec<(f32, &SearchHit)> = hits
+        .iter()
+        .filter(|hit| matches!(hit.role, MessageRole::User | MessageRole::Assistant))
+        .filter(|hit| !is_system_context(&hit.content) && !is_tool_dump(&hit.content))
+        .map(|hit| (agent_score(hit), hit))
+        .collect();
+    candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    // The same session can be stored under several sources; group by the
+    // harness's own id when there is one.
+    let mut per_conversation: HashMap<String, usize> = HashMap::new();
+    let mut folded: HashMap<String, usize> = HashMap::new();
+    let mut seen_text: HashSet<String> = HashSet::new();
+    let mut kept: Vec<(String, AgentHit)> = Vec::new();
+    for (_, hit) in candidates {
+        let key = hit
+            .external_id
+            .clone()
+            .unwrap_or_else(|| hit.conversation_id.to_string());
+        let excerpt = excerpt(hit, config);
+        // Identical text (a prompt pasted into several sessions) only once.
+        if !seen_text.insert(normalize(&excerpt)) {
+            continue;
+        }
+        let count = per_conversation.entry(key.clone()).or_default();
+        if *count >= config.per_conversation.max(1) {
+            *folded.entry(key).or_default() += 1;
+            continue;
+        }
+        *count += 1;
+        kept.push((
+            key,
+            AgentHit {
+                conversation_id: hit.conversation_id,
+                readable_id: hit.readable_id.clone(),
+                title: hit.title.clone(),
+                source: hit.source_id.clone(),
+                workspace: hit.workspace.clone(),
+                message_idx: hit.message_idx,
+                role: hit.role.clone(),
+                excerpt,
+                more_matches: 0,
+            },
+        ));
+    }
+
+    kept.truncate(limit);
+    // Report folded matches on the conversation's last kept hit.
+    let mut last_for_key: HashMap<&str, usize> = HashMap::new();
+    for (i, (key, _)) in kept.iter().enumerate() {
+        last_for_key.insert(key.as_str(), i);
+    }
+    let updates: Vec<(usize, usize)> = last_for_key
+        .into_iter()
+        .filter_map(|(key, i)| folded.get(key).map(|n| (i, *n)))
+        .collect();
+    for (i, n) in updates {
+        kept[i].1.more_matches = n;
+    }
+    kept.into_iter().map(|(_, hit)| hit).collect()
+}
+
+/// Search score adjusted for agents: very long messages rarely answer a
+/// question on their own, so they rank below focused ones.
+fn agent_score(hit: &SearchHit) -> f32 {
+    let len = hit.content.chars().count() as f32;
+    let length_penalty = if len > 2_000.0 {
+        1.0 + (len / 2_000.0).ln()
+    } else {
+        1.0
+    };
+    let role_weight = match hit.role {
+        MessageRole::User => 1.1,
+        _ => 1.0,
+    };
+    hit.score * role_weight / length_penalty
+}
+
+/// Up to `excerpt_chars` of the message, centred on the first matched term,
+/// with long code blocks collapsed and whitespace squeezed.
+fn excerpt(hit: &SearchHit, config: &AgentSearchConfig) -> String {
+    let text = collapse_code_blocks(&hit.content, config.max_code_lines);
+    let text = squeeze_whitespace(&text);
+    let max = config.excerpt_chars.max(1);
+    let chars: Vec<char> = text.chars().collect();
+    if chars.len() <= max {
+        return text;
+    }
+
+    let start = matched_term(&hit.snippet)
+        .and_then(|term| find_case_insensitive(&text, &term))
+        .map(|byte| text[..byte].chars().count())
+        .map(|at| at.saturating_sub(max / 3))
+        .unwrap_or(0)
+        .min(chars.len() - max);
+    let end = start + max;
+    let mut out = String::new();
+    if start > 0 {
+        out.push('…');
+    }
+    out.extend(&chars[start..end]);
+    if end < chars.len() {
+        out.push('…');
+    }
+    out
+}
+
+/// First `[term]` highlighted by the full-text snippet.
+fn matched_term(snippet: &str) -> Option<String> {
+    let start = snippet.find('[')? + 1;
+    let len = snippet[start..].find(']')?;
+    let term = snippet[start..start + len].trim();
+    (!term.is_empty()).then(|| term.to_string())
+}
+
+fn find_case_insensitive(haystack: &str, needle: &str) -> Option<usize> {
+    let needle = needle.to_lowercase();
+    haystack
+        .char_indices()
+        .map(|(i, _)| i)
+        .find(|&i| haystack[i..].to_lowercase().starts_with(&needle))
+}
+
+fn collapse_code_blocks(content: &str, max_lines: usize) -> String {
+    let mut out = String::with_capacity(content.len());
+    let mut block: Option<Vec<&str>> = None;
+    for line in content.lines() {
+        let is_fence = line.trim_start().starts_with("```");
+        match (&mut block, is_fence) {
+            (None, true) => block = Some(vec![line]),
+            (None, false) => {
+                out.push_str(line);
+                out.push('\n');
+            }
+            (Some(lines), false) => lines.push(line),
+            (Some(lines), true) => {
+                lines.push(line);
+                let body = lines.len() - 2;
+                if body > max_lines {
+                    out.push_str(&format!("[code block: {body} lines]\n"));
+                } else {
+                    for line in lines.iter() {
+                        out.push_str(line);
+                        out.push('\n');
+                    }
+                }
+                block = None;
+            }
+        }
+    }
+    // Unterminated fence: keep what fits.
+    if let Some(lines) = block {
+        for line in lines.iter().take(max_lines + 1) {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+fn squeeze_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn normalize(text: &str) -> String {
+    text.trim_matches('…').to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn hit(conv: Uuid, idx: i32, role: MessageRole, content: &str, score: f32) -> SearchHit {
+        SearchHit {
+            message_id: Uuid::new_v4(),
+            conversation_id: conv,
+            message_idx: idx,
+            role,
+            content: content.to_string(),
+            snippet: "[deploy]".to_string(),
+            created_at: None,
+            conv_created_at: Utc::now(),
+            conv_updated_at: None,
+            score,
+            source_id: "codex".to_string(),
+            external_id: None,
+            readable_id: None,
+            title: None,
+            workspace: None,
+            workspace_name: None,
+            source_adapter: "codex".to_string(),
+            source_path: None,
+            host: None,
+            occurrences: None,
+            conversation: None,
+            context: None,
+        }
+    }
+
+    #[test]
+    fn drops_noise_and_folds_repeated_conversations() {
+        let a = Uuid::new_v4();
+        let b = Uuid::new_v4();
+        let json_dump = format!("{{\"items\": [{}]}}", vec!["1"; 200].join(","));
+        let hits = vec![
+            hit(a, 0, MessageRole::User, "how do I deploy the api", 9.0),
+            hit(a, 1, MessageRole::Assistant, "run make deploy", 8.0),
+            hit(a, 2, MessageRole::Assistant, "deploy finished", 7.0),
+            hit(a, 3, MessageRole::Tool, "deploy log output", 10.0),
+            hit(b, 0, MessageRole::User, "# AGENTS.md deploy rules", 10.0),
+            hit(b, 1, MessageRole::Assistant, &json_dump, 10.0),
+            hit(b, 2, MessageRole::User, "how do I deploy the api", 6.0),
+        ];
+        let config = AgentSearchConfig::default();
+        let ranked = rank_for_agents(&hits, &config, 10);
+
+        let texts: Vec<_> = ranked.iter().map(|h| h.excerpt.as_str()).collect();
+        assert_eq!(texts, vec!["how do I deploy the api", "run make deploy"]);
+        assert_eq!(ranked[1].more_matches, 1);
+    }
+
+    #[test]
+    fn excerpt_centres_on_match_and_collapses_code() {
+        let code = format!("```\n{}```\n", "let x = 1;\n".repeat(30));
+        let content = format!(
+            "{}\n{code}then we deploy it {}",
+            "intro ".repeat(100),
+            "tail ".repeat(100)
+        );
+        let config = AgentSearchConfig {
+            excerpt_chars: 60,
+            ..AgentSearchConfig::default()
+        };
+        let h = hit(Uuid::new_v4(), 0, MessageRole::Assistant, &content, 1.0);
+        assert!(collapse_code_blocks(&content, 12).contains("[code block: 30 lines]"));
+        let text = excerpt(&h, &config);
+        assert!(text.contains("then we deploy it"));
+        assert!(!text.contains("let x"));
+        assert!(text.starts_with('…') && text.ends_with('…'));
+        assert_eq!(text.chars().count(), 62);
+    }
+
+    #[test]
+    fn long_messages_rank_below_focused_ones() {
+        let conv = Uuid::new_v4();
+        let long = hit(
+            conv,
+            0,
+            MessageRole::Assistant,
+            &"deploy ".repeat(2_000),
+            5.0,
+        );
+        let short = hit(conv, 1, MessageRole::Assistant, "deploy", 4.0);
+        assert!(agent_score(&short) > agent_score(&long));
+    }
+}
@@ -0,0 +1,79 @@
+//! Bearer tokens for `hstry-api`.
+//!
+//! Tokens are random strings handed out once by `hstry api token create`;
+//! the database only keeps their SHA-256, so a leaked database does not leak
+//! working credentials.
+
+use std::fmt::Write as _;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{Error, Result};
+
+/// Marks hstry tokens so they are recognisable in config files and logs.
+const TOKEN_PREFIX: &str = "hstry_";
+
+/// Characters of a token kept in clear text to tell tokens apart.
+const DISPLAY_PREFIX_LEN: usize = 12;
+
+/// What a token may do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiTokenScope {
+    /// Search and read conversations.
+    Read,
+    /// Everything `read` allows, plus ingest, deletes and remote sync pushes.
+    Ingest,
+}
+
+impl ApiTokenScope {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Read => "read",
+            Self::Ingest => "ingest",
+        }
+    }
+
+    /// Whether a token with this scope may make a request needing `needed`.
+    pub fn allows(self, needed: Self) -> bool {
+        self == Self::Ingest || needed == Self::Read
+    }
+}
+
+impl FromStr for ApiTokenScope {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "read" => Ok(Self::Read),
+            "ingest" => Ok(Self::Ingest),
+            other => Err(Error::Other(format!("Unknown API token scope: {other}"))),
+        }
+    }
+}
+
+/// Generate a new random token.
+pub fn generate_token() -> String {
+    format!(
+        "{TOKEN_PREFIX}{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+/// Hex SHA-256 of a token, as stored in `api_tokens.token_hash`.
+pub fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .fold(String::with_capacity(64), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+}
+
+/// Leading part of a token that is safe to show in `hstry api token list`.
+pub fn display_prefix(token: &str) -> &str {
+    token.get(..DISPLAY_PREFIX_LEN).unwrap_or(token)
+}
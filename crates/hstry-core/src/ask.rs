@@ -0,0 +1,301 @@
+//! Question answering over history (`hstry ask`).
+//!
+//! [`retrieve`] finds messages for a natural-language question: the full
+//! question rarely matches as one full-text query, so it searches for its
+//! keywords together and one by one and fuses the rankings. The best
+//! messages become numbered [`Citation`]s that [`ask`] hands to the
+//! `[ask]` model, which answers citing them as `[n]`.
+
+use std::collections::{BTreeSet, HashMap};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::agent_search::{AgentSearchConfig, rank_for_agents};
+use crate::config::AskConfig;
+use crate::db::{Database, SearchOptions};
+use crate::error::Result;
+use crate::llm::Endpoint;
+use crate::models::{MessageRole, SearchHit};
+
+const INSTRUCTIONS: &str = "You answer questions about the user's past conversations with AI \
+    assistants. Use only the numbered excerpts provided. Cite every excerpt you rely on as [n], \
+    e.g. [2] or [1][4]. If the excerpts do not answer the question, say so briefly.";
+
+/// Words that carry no search signal in a question.
+const STOPWORDS: &str = "\
+    a about all am an and any are as at be been but by can could did do does for \
+    from had has have how i if in into is it its last me my of on or our should so \
+    that the their them then there these this those to was we were what when where \
+    which who why will with would you your ago again before ever week month year \
+    yesterday today";
+
+/// One message given to the model as context.
+#[derive(Debug, Clone, Serialize)]
+pub struct Citation {
+    /// The `[n]` the answer refers to.
+    pub n: usize,
+    pub conversation_id: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub readable_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub source: String,
+    pub message_idx: i32,
+    pub role: MessageRole,
+    pub date: DateTime<Utc>,
+    pub excerpt: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Answer {
+    pub answer: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Context messages the answer cites, in citation order.
+    pub citations: Vec<Citation>,
+    /// Number of context messages the model was given.
+    pub passages: usize,
+}
+
+/// Search terms of a question: lowercased words without stopwords.
+pub fn keywords(question: &str) -> Vec<String> {
+    let mut seen = BTreeSet::new();
+    question
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .map(str::to_lowercase)
+        .filter(|word| {
+            word.chars().count() > 1 && !STOPWORDS.split_whitespace().any(|stop| stop == word)
+        })
+        .filter(|word| seen.insert(word.clone()))
+        .collect()
+}
+
+/// Messages matching `question`, best first. Hits matching all keywords
+/// rank highest, followed by those matching the most individual keywords.
+pub async fn retrieve(
+    db: &Database,
+    question: &str,
+    opts: &SearchOptions,
+    limit: usize,
+) -> Result<Vec<SearchHit>> {
+    let words = keywords(question);
+    if words.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut queries = vec![(words.join(" "), 2.0)];
+    if words.len() > 1 {
+        queries.extend(words.iter().map(|word| (word.clone(), 1.0)));
+    }
+
+    let per_query = i64::try_from(limit.max(1)).unwrap_or(i64::MAX);
+    let mut ranked = Vec::with_capacity(queries.len());
+    for (query, weight) in queries {
+        let hits = db
+            .search(
+                &query,
+                SearchOptions {
+                    limit: Some(per_query),
+                    offset: None,
+                    ..opts.clone()
+                },
+            )
+            .await?;
+        ranked.push((weight, hits));
+    }
+    Ok(fuse(ranked, limit))
+}
+
+/// Reciprocal rank fusion of several weighted result lists.
+fn fuse(ranked: Vec<(f32, Vec<SearchHit>)>, limit: usize) -> Vec<SearchHit> {
+    let mut fused: HashMap<Uuid, (f32, SearchHit)> = HashMap::new();
+    for (weight, hits) in ranked {
+        for (rank, hit) in hits.into_iter().enumerate() {
+            let score = weight / (60.0 + rank as f32);
+            fused.entry(hit.message_id).or_insert_with(|| (0.0, hit)).0 += score;
+        }
+    }
+    let mut hits: Vec<(f32, SearchHit)> = fused.into_values().collect();
+    hits.sort_by(|a, b| b.0.total_cmp(&a.0));
+    hits.into_iter()
+        .take(limit)
+        .map(|(score, mut hit)| {
+            hit.score = score;
+            hit
+        })
+        .collect()
+}
+
+/// Pick the context messages from `hits` (best first): noise is dropped,
+/// at most three messages per conversation are kept, each cut to
+/// `excerpt_chars`.
+pub fn passages(hits: &[SearchHit], config: &AskConfig) -> Vec<Citation> {
+    let agent_config = AgentSearchConfig {
+        excerpt_chars: config.excerpt_chars,
+        per_conversation: 3,
+        max_code_lines: 30,
+    };
+    let dates: HashMap<(Uuid, i32), DateTime<Utc>> = hits
+        .iter()
+        .map(|hit| {
+            (
+                (hit.conversation_id, hit.message_idx),
+                hit.created_at.unwrap_or(hit.conv_created_at),
+            )
+        })
+        .collect();
+    rank_for_agents(hits, &agent_config, config.passages.max(1))
+        .into_iter()
+        .enumerate()
+        .map(|(i, hit)| Citation {
+            n: i + 1,
+            date: dates
+                .get(&(hit.conversation_id, hit.message_idx))
+                .copied()
+                .unwrap_or_default(),
+            conversation_id: hit.conversation_id,
+            readable_id: hit.readable_id,
+            title: hit.title,
+            source: hit.source,
+            message_idx: hit.message_idx,
+            role: hit.role,
+            excerpt: hit.excerpt,
+        })
+        .collect()
+}
+
+/// The user message sent to the model.
+pub fn prompt(question: &str, passages: &[Citation]) -> String {
+    let mut prompt = format!("Question: {question}\n\nExcerpts:");
+    for passage in passages {
+        prompt.push_str(&format!(
+            "\n\n[{}] {} ({}, {}, {})\n{}",
+            passage.n,
+            passage.title.as_deref().unwrap_or("Untitled"),
+            passage.date.format("%Y-%m-%d"),
+            passage.source,
+            passage.role,
+            passage.excerpt
+        ));
+    }
+    prompt
+}
+
+/// Passages referenced as `[n]` (or `[n, m]`) in `answer`, in order of
+/// first reference.
+pub fn cited(answer: &str, passages: &[Citation]) -> Vec<Citation> {
+    let mut order = Vec::new();
+    let mut rest = answer;
+    while let Some(start) = rest.find('[') {
+        rest = &rest[start + 1..];
+        let Some(end) = rest.find(']') else {
+            break;
+        };
+        for part in rest[..end].split(',') {
+            if let Ok(n) = part.trim().parse::<usize>()
+                && !order.contains(&n)
+            {
+                order.push(n);
+            }
+        }
+        rest = &rest[end + 1..];
+    }
+    order
+        .into_iter()
+        .filter_map(|n| passages.iter().find(|passage| passage.n == n).cloned())
+        .collect()
+}
+
+/// Ask the model `question` with `passages` as context.
+pub async fn ask(endpoint: &Endpoint, question: &str, passages: Vec<Citation>) -> Result<Answer> {
+    let answer = endpoint
+        .chat(INSTRUCTIONS, &prompt(question, &passages))
+        .await?
+        .trim()
+        .to_string();
+    Ok(Answer {
+        citations: cited(&answer, &passages),
+        passages: passages.len(),
+        model: endpoint.model().map(str::to_string),
+        answer,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(conversation_id: Uuid, idx: i32, content: &str) -> SearchHit {
+        SearchHit {
+            message_id: Uuid::new_v4(),
+            conversation_id,
+            message_idx: idx,
+            role: MessageRole::Assistant,
+            content: content.to_string(),
+            snippet: String::new(),
+            created_at: None,
+            conv_created_at: Utc::now(),
+            conv_updated_at: None,
+            score: 1.0,
+            source_id: "codex".to_string(),
+            external_id: None,
+            readable_id: None,
+            title: Some("WAL fix".to_string()),
+            workspace: None,
+            workspace_name: None,
+            source_adapter: "codex".to_string(),
+            source_path: None,
+            host: None,
+            occurrences: None,
+            conversation: None,
+            context: None,
+        }
+    }
+
+    #[test]
+    fn keywords_drop_stopwords_and_duplicates() {
+        assert_eq!(
+            keywords("How did I fix the SQLite WAL issue last month? The WAL!"),
+            vec!["fix", "sqlite", "wal", "issue"]
+        );
+        assert!(keywords("what did we do?").is_empty());
+    }
+
+    #[test]
+    fn fusion_prefers_hits_found_by_several_queries() {
+        let conv = Uuid::new_v4();
+        let both = hit(conv, 0, "wal checkpoint fix");
+        let one = hit(conv, 1, "wal mode");
+        let fused = fuse(
+            vec![
+                (1.0, vec![one.clone(), both.clone()]),
+                (1.0, vec![both.clone()]),
+            ],
+            10,
+        );
+        assert_eq!(fused[0].message_id, both.message_id);
+        assert_eq!(fused.len(), 2);
+    }
+
+    #[test]
+    fn citations_follow_the_answer() {
+        let conv = Uuid::new_v4();
+        let config = AskConfig::default();
+        let hits = vec![
+            hit(conv, 0, "enable wal mode"),
+            hit(conv, 3, "run a checkpoint"),
+            hit(Uuid::new_v4(), 1, "unrelated"),
+        ];
+        let passages = passages(&hits, &config);
+        assert_eq!(passages.len(), 3);
+        assert!(prompt("how?", &passages).contains("[2] WAL fix ("));
+
+        let cited = cited(
+            "Checkpoint it [2], after enabling WAL [1, 2]. See [9].",
+            &passages,
+        );
+        let idx: Vec<_> = cited.iter().map(|c| (c.n, c.message_idx)).collect();
+        assert_eq!(idx, vec![(2, 3), (1, 0)]);
+    }
+}
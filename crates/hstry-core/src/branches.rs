@@ -0,0 +1,185 @@
+//! Message-level branches within one conversation.
+//!
+//! Regenerated answers and edited prompts turn a conversation into a tree:
+//! each message may name the message it continues from
+//! ([`Message::parent_message_id`]). A conversation where no message does is
+//! linear. In a tree, messages without a parent are roots, the *active*
+//! thread is the path ending at the last message (highest `idx`), and every
+//! other leaf ends an alternate branch.
+
+use std::collections::{HashMap, HashSet};
+
+use uuid::Uuid;
+
+use crate::models::Message;
+
+/// One root-to-leaf path through a conversation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Branch {
+    /// Message `idx`s from the root to this branch's leaf.
+    pub path: Vec<i32>,
+    /// Leading messages shared with an earlier branch (0 for the active
+    /// thread). `path[shared..]` is what this branch adds.
+    pub shared: usize,
+}
+
+impl Branch {
+    /// The `idx` this branch diverges after, if it shares any history.
+    pub fn fork_idx(&self) -> Option<i32> {
+        self.shared
+            .checked_sub(1)
+            .and_then(|i| self.path.get(i).copied())
+    }
+}
+
+/// Whether `messages` form a tree rather than a linear conversation.
+pub fn is_tree(messages: &[Message]) -> bool {
+    messages.iter().any(|m| m.parent_message_id.is_some())
+}
+
+/// All branches of a conversation, active thread first, then alternates in
+/// order of their leaf's `idx`. A linear conversation is a single branch.
+pub fn branches(messages: &[Message]) -> Vec<Branch> {
+    let mut ordered: Vec<&Message> = messages.iter().collect();
+    ordered.sort_by_key(|m| m.idx);
+    if !is_tree(messages) {
+        return vec![Branch {
+            path: ordered.iter().map(|m| m.idx).collect(),
+            shared: 0,
+        }];
+    }
+
+    let by_id: HashMap<Uuid, &Message> = ordered.iter().map(|m| (m.id, *m)).collect();
+    let has_children: HashSet<Uuid> = ordered
+        .iter()
+        .filter_map(|m| m.parent_message_id)
+        .filter(|parent| by_id.contains_key(parent))
+        .collect();
+
+    let mut leaves: Vec<&Message> = ordered
+        .iter()
+        .copied()
+        .filter(|m| !has_children.contains(&m.id))
+        .collect();
+    // Active thread first: it ends at the last message.
+    if let Some(last) = leaves.pop() {
+        leaves.insert(0, last);
+    }
+
+    let mut out: Vec<Branch> = Vec::with_capacity(leaves.len());
+    for leaf in leaves {
+        let path = path_to(leaf, &by_id);
+        let shared = out
+            .iter()
+            .map(|earlier| common_prefix(&earlier.path, &path))
+            .max()
+            .unwrap_or(0);
+        out.push(Branch { path, shared });
+    }
+    out
+}
+
+/// The active thread: messages from the root to the last message.
+pub fn active_thread(messages: Vec<Message>) -> Vec<Message> {
+    let Some(active) = branches(&messages).into_iter().next() else {
+        return messages;
+    };
+    let keep: HashSet<i32> = active.path.into_iter().collect();
+    let mut thread: Vec<Message> = messages
+        .into_iter()
+        .filter(|m| keep.contains(&m.idx))
+        .collect();
+    thread.sort_by_key(|m| m.idx);
+    thread
+}
+
+fn path_to(leaf: &Message, by_id: &HashMap<Uuid, &Message>) -> Vec<i32> {
+    let mut path = vec![leaf.idx];
+    let mut seen = HashSet::from([leaf.id]);
+    let mut current = leaf;
+    while let Some(parent) = current.parent_message_id.and_then(|id| by_id.get(&id)) {
+        if !seen.insert(parent.id) {
+            break;
+        }
+        path.push(parent.idx);
+        current = parent;
+    }
+    path.reverse();
+    path
+}
+
+fn common_prefix(a: &[i32], b: &[i32]) -> usize {
+    a.iter().zip(b).take_while(|(x, y)| x == y).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::MessageRole;
+
+    fn message(idx: i32, parent: Option<i32>, ids: &[Uuid]) -> Message {
+        Message {
+            id: ids[usize::try_from(idx).expect("idx")],
+            conversation_id: Uuid::nil(),
+            idx,
+            role: MessageRole::User,
+            content: format!("m{idx}"),
+            parts_json: serde_json::json!([]),
+            created_at: None,
+            model: None,
+            tokens: None,
+            cost_usd: None,
+            metadata: serde_json::json!({}),
+            sender: None,
+            provider: None,
+            harness: None,
+            client_id: None,
+            parent_message_id: parent.map(|p| ids[usize::try_from(p).expect("idx")]),
+        }
+    }
+
+    #[test]
+    fn regenerated_answer_forms_an_alternate_branch() {
+        let ids: Vec<Uuid> = (0..5).map(|_| Uuid::new_v4()).collect();
+        // 0 -> 1 (first answer) and 0 -> 2 (regenerated) -> 3 -> 4
+        let messages = vec![
+            message(0, None, &ids),
+            message(1, Some(0), &ids),
+            message(2, Some(0), &ids),
+            message(3, Some(2), &ids),
+            message(4, Some(3), &ids),
+        ];
+        let all = branches(&messages);
+        assert_eq!(
+            all,
+            vec![
+                Branch {
+                    path: vec![0, 2, 3, 4],
+                    shared: 0
+                },
+                Branch {
+                    path: vec![0, 1],
+                    shared: 1
+                },
+            ]
+        );
+        assert_eq!(all[1].fork_idx(), Some(0));
+
+        let thread: Vec<i32> = active_thread(messages).iter().map(|m| m.idx).collect();
+        assert_eq!(thread, vec![0, 2, 3, 4]);
+    }
+
+    #[test]
+    fn linear_conversation_is_one_branch() {
+        let ids: Vec<Uuid> = (0..3).map(|_| Uuid::new_v4()).collect();
+        let messages: Vec<Message> = (0..3).map(|i| message(i, None, &ids)).collect();
+        assert!(!is_tree(&messages));
+        assert_eq!(
+            branches(&messages),
+            vec![Branch {
+                path: vec![0, 1, 2],
+                shared: 0
+            }]
+        );
+    }
+}
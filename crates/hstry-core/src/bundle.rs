@@ -0,0 +1,101 @@
+//! Configuration bundles for setting up another machine.
+//!
+//! A bundle is a single TOML file holding the configuration (adapter repos,
+//! sources, adapters, service and search settings, ...) and the tag
+//! taxonomy. It never contains conversations: use remote sync for those.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::error::{Error, Result};
+
+/// Bundle format version written by [`ConfigBundle::to_toml`].
+pub const BUNDLE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigBundle {
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    /// Tag names, including tags not attached to any conversation yet.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub config: Config,
+}
+
+impl ConfigBundle {
+    /// Bundle `config` and `tags`. Paths under the home directory are stored
+    /// as `~/...` and the machine-specific sync device id is dropped.
+    pub fn new(config: &Config, tags: Vec<String>) -> Self {
+        let mut config = config.clone();
+        config.contract_home_paths();
+        config.sync.device_id = None;
+        Self {
+            version: BUNDLE_VERSION,
+            exported_at: Utc::now(),
+            tags,
+            config,
+        }
+    }
+
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string_pretty(self).map_err(|e| Error::Config(e.to_string()))
+    }
+
+    pub fn from_toml(content: &str) -> Result<Self> {
+        let bundle: Self = toml::from_str(content)
+            .map_err(|e| Error::Config(format!("Failed to parse config bundle: {e}")))?;
+        if bundle.version > BUNDLE_VERSION {
+            return Err(Error::Config(format!(
+                "Config bundle version {} is newer than supported ({BUNDLE_VERSION})",
+                bundle.version
+            )));
+        }
+        Ok(bundle)
+    }
+
+    /// The bundled config with `~` expanded for this machine.
+    pub fn config_for_this_machine(&self) -> Config {
+        let mut config = self.config.clone();
+        config.expand_paths();
+        config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_contracts_and_expands_home() {
+        let Some(home) = dirs::home_dir() else {
+            return;
+        };
+        let mut config = Config {
+            workspaces: vec![home.join("code").to_string_lossy().to_string()],
+            ..Config::default()
+        };
+        config.sync.device_id = Some("laptop".to_string());
+
+        let bundle = ConfigBundle::new(&config, vec!["keep".to_string()]);
+        assert_eq!(bundle.config.workspaces, vec!["~/code".to_string()]);
+        assert!(bundle.config.sync.device_id.is_none());
+
+        let parsed =
+            ConfigBundle::from_toml(&bundle.to_toml().expect("serialize")).expect("parse bundle");
+        assert_eq!(parsed.tags, vec!["keep".to_string()]);
+        assert_eq!(
+            parsed.config_for_this_machine().workspaces,
+            config.workspaces
+        );
+    }
+
+    #[test]
+    fn rejects_newer_versions() {
+        let mut bundle = ConfigBundle::new(&Config::default(), Vec::new());
+        bundle.version = BUNDLE_VERSION + 1;
+        let err = ConfigBundle::from_toml(&bundle.to_toml().expect("serialize"))
+            .expect_err("newer bundle");
+        assert!(err.to_string().contains("newer"));
+    }
+}
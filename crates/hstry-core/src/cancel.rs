@@ -0,0 +1,125 @@
+//! Cooperative cancellation for long-running operations (sync, import, index,
+//! dedup).
+//!
+//! A [`CancelToken`] is a cheap, cloneable flag. Work loops poll it between
+//! units of work and wrap in-flight transactions with [`CancelToken::run`]:
+//! when the token fires, the pending future is dropped, which drops its
+//! `sqlx::Transaction` and rolls it back instead of leaving a partial write.
+
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tokio::sync::Notify;
+
+use crate::error::{Error, Result};
+
+/// Exit status used when a second Ctrl-C forces the process down (128 + SIGINT).
+const FORCED_EXIT_CODE: i32 = 130;
+
+#[derive(Debug, Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+/// Shared cancellation flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    inner: Arc<Inner>,
+}
+
+impl CancelToken {
+    /// Create a token that is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation and wake every task waiting in [`Self::cancelled`].
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        self.inner.notify.notify_waiters();
+    }
+
+    /// Whether cancellation has been requested.
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Return [`Error::Cancelled`] if cancellation has been requested.
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            Err(Error::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Resolve once cancellation has been requested.
+    pub async fn cancelled(&self) {
+        loop {
+            let notified = self.inner.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// Drive `fut` to completion unless the token fires first.
+    ///
+    /// On cancellation the future is dropped without being polled again, so
+    /// an open transaction inside it is rolled back rather than committed.
+    pub async fn run<F: Future>(&self, fut: F) -> Result<F::Output> {
+        self.check()?;
+        tokio::select! {
+            biased;
+            () = self.cancelled() => Err(Error::Cancelled),
+            output = fut => Ok(output),
+        }
+    }
+
+    /// Create a token that fires on the first Ctrl-C.
+    ///
+    /// A second Ctrl-C exits immediately so a stuck operation can still be
+    /// interrupted. Must be called from within a Tokio runtime.
+    pub fn install_ctrl_c() -> Self {
+        let token = Self::new();
+        let handle = token.clone();
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_err() {
+                return;
+            }
+            tracing::warn!("Interrupt received, finishing current step (Ctrl-C again to force)");
+            handle.cancel();
+            if tokio::signal::ctrl_c().await.is_ok() {
+                std::process::exit(FORCED_EXIT_CODE);
+            }
+        });
+        token
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn run_completes_when_not_cancelled() {
+        let token = CancelToken::new();
+        assert_eq!(token.run(async { 7 }).await.ok(), Some(7));
+    }
+
+    #[tokio::test]
+    async fn run_drops_pending_future_on_cancel() {
+        let token = CancelToken::new();
+        let trigger = token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            trigger.cancel();
+        });
+        let result = token.run(std::future::pending::<()>()).await;
+        assert!(matches!(result, Err(Error::Cancelled)));
+        assert!(matches!(token.check(), Err(Error::Cancelled)));
+    }
+}
@@ -1,5 +1,6 @@
 //! Configuration types and loading for hstry.
 
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
@@ -14,8 +15,8 @@ pub const DEFAULT_ADAPTER_REPO: &str = "https://github.com/byteowlz/hstry";
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Config {
-    /// Path to the hstry database.
-    pub database: PathBuf,
+    /// Database location and at-rest encryption.
+    pub database: DatabaseConfig,
 
     /// Adapter directories to search for adapters.
     pub adapter_paths: Vec<PathBuf>,
@@ -24,15 +25,24 @@ pub struct Config {
     /// Each repo can provide different adapters.
     pub adapter_repos: Vec<AdapterRepo>,
 
-    /// JavaScript runtime preference: "bun", "deno", "node", or "auto".
+    /// JavaScript runtime preference: "auto", "bun", "deno", "node", or a
+    /// comma-separated priority list such as "bun,node".
     pub js_runtime: String,
 
+    /// Seconds an adapter process stays alive without requests before it is
+    /// shut down. Reusing processes keeps `hstry scan` and auto-detection
+    /// from starting a runtime per call; 0 starts one per call.
+    pub adapter_worker_idle_secs: u64,
+
     /// Embedding endpoint for semantic search (e.g., mmry's /v1/embeddings).
     pub embedding_endpoint: Option<String>,
 
     /// Workspace roots to scan recursively for session output.
     pub workspaces: Vec<String>,
 
+    /// How workspace paths are normalized before they are stored.
+    pub workspace_paths: WorkspacePathConfig,
+
     /// Adapter configuration overrides.
     pub adapters: Vec<AdapterConfig>,
 
@@ -57,174 +67,1410 @@ pub struct Config {
     /// Resume configuration for opening sessions in coding agents.
     pub resume: ResumeConfig,
 
-    /// Storage knobs (message_events log, indexer outbox, etc.).
-    #[serde(default)]
-    pub storage: StorageConfig,
+    /// Storage knobs (message_events log, indexer outbox, etc.).
+    #[serde(default)]
+    pub storage: StorageConfig,
+
+    /// Conversation retention policy applied by `hstry prune` and the service.
+    #[serde(default)]
+    pub retention: RetentionConfig,
+
+    /// How long removed conversations stay in the trash.
+    pub trash: TrashConfig,
+
+    /// Profile name used for per-user state such as the "last seen" read
+    /// watermark. Overridden by `HSTRY_PROFILE`.
+    pub profile: String,
+
+    /// Language of CLI and TUI messages, e.g. "de". Unset follows
+    /// `LC_ALL`, `LC_MESSAGES` or `LANG`; unknown languages fall back to
+    /// English.
+    pub locale: Option<String>,
+
+    /// Color and icon per source id or adapter name, shown next to the
+    /// source in CLI tables and the TUI.
+    pub badges: SourceBadges,
+
+    /// Command shortcuts expanded by the CLI, e.g.
+    /// `week = "list --after 7d"`. Built-in subcommands take precedence.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub aliases: BTreeMap<String, String>,
+
+    /// Named groups of sources (e.g. "work", "personal") with their own
+    /// sync priority, tags and retention limits.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub groups: BTreeMap<String, SourceGroup>,
+
+    /// What `hstry-mcp` exposes to the agents connected to it.
+    pub mcp: McpConfig,
+
+    /// Language model answering `hstry ask`.
+    pub ask: AskConfig,
+
+    /// Titles written by `hstry title generate`.
+    pub titles: TitleConfig,
+
+    /// Model providers shared by the LLM-powered features.
+    pub llm: LlmConfig,
+
+    /// Token prices per model, used by `hstry cost report` to estimate the
+    /// cost of conversations whose adapter recorded none.
+    pub pricing: Pricing,
+
+    /// Daily note summaries written by `hstry export --format daily-notes`
+    /// and, when enabled, by the service each night.
+    pub daily_notes: DailyNotesConfig,
+
+    /// Sources and workspaces whose message content is stored encrypted.
+    pub sensitive: SensitiveConfig,
+
+    /// Record of the reads served over `hstry-api` and `hstry-mcp`.
+    pub access_log: AccessLogConfig,
+
+    /// Redaction rules applied by `hstry export --share-safe`.
+    pub redact: RedactConfig,
+
+    /// Throttles for syncs, imports and indexing run with `--nice` and by
+    /// the service.
+    pub nice: NiceConfig,
+}
+
+/// Database location, at-rest protection and SQLite tuning.
+///
+/// Accepts either a bare path (`database = "~/.local/share/hstry/hstry.db"`)
+/// or a `[database]` table with `path`, tuning keys and an
+/// `[database.encryption]` sub-table. Serializes back to the bare path form
+/// unless something other than the path is configured.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatabaseConfig {
+    /// Path to the hstry database.
+    pub path: PathBuf,
+    /// At-rest encryption settings.
+    pub encryption: EncryptionConfig,
+    /// Connection pragmas.
+    pub tuning: TuningConfig,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self::from(default_database_path())
+    }
+}
+
+impl From<PathBuf> for DatabaseConfig {
+    fn from(path: PathBuf) -> Self {
+        Self {
+            path,
+            encryption: EncryptionConfig::default(),
+            tuning: TuningConfig::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct DatabaseTable {
+    path: PathBuf,
+    #[serde(flatten)]
+    tuning: TuningConfig,
+    encryption: EncryptionConfig,
+}
+
+impl Default for DatabaseTable {
+    fn default() -> Self {
+        Self {
+            path: default_database_path(),
+            tuning: TuningConfig::default(),
+            encryption: EncryptionConfig::default(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DatabaseRepr {
+    Path(PathBuf),
+    Table(DatabaseTable),
+}
+
+impl Serialize for DatabaseConfig {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        if self.encryption == EncryptionConfig::default() && self.tuning == TuningConfig::default()
+        {
+            self.path.serialize(serializer)
+        } else {
+            DatabaseTable {
+                path: self.path.clone(),
+                tuning: self.tuning.clone(),
+                encryption: self.encryption.clone(),
+            }
+            .serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for DatabaseConfig {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        Ok(match DatabaseRepr::deserialize(deserializer)? {
+            DatabaseRepr::Path(path) => Self::from(path),
+            DatabaseRepr::Table(DatabaseTable {
+                path,
+                tuning,
+                encryption,
+            }) => Self {
+                path,
+                encryption,
+                tuning,
+            },
+        })
+    }
+}
+
+/// SQLite tuning preset.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TuningProfile {
+    /// SQLite defaults with full fsync: smallest footprint, survives power
+    /// loss without losing committed transactions.
+    #[default]
+    Safe,
+    /// Large page cache and memory-mapped reads for multi-GB databases.
+    /// `synchronous = NORMAL` may lose the last transactions on power loss
+    /// (never on a process crash) but keeps the database consistent.
+    Fast,
+}
+
+/// `PRAGMA synchronous` level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SynchronousMode {
+    Off,
+    Normal,
+    Full,
+    Extra,
+}
+
+impl SynchronousMode {
+    /// Pragma value, e.g. `NORMAL`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Off => "OFF",
+            Self::Normal => "NORMAL",
+            Self::Full => "FULL",
+            Self::Extra => "EXTRA",
+        }
+    }
+}
+
+/// SQLite pragmas applied to every connection. Unset keys come from
+/// `profile`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TuningConfig {
+    /// Preset supplying every value not set explicitly.
+    pub profile: TuningProfile,
+    /// `PRAGMA cache_size`: pages when positive, KiB when negative.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_size: Option<i64>,
+    /// `PRAGMA mmap_size` in bytes (`0` disables memory-mapped I/O).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mmap_size: Option<u64>,
+    /// `PRAGMA synchronous`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub synchronous: Option<SynchronousMode>,
+    /// `PRAGMA wal_autocheckpoint` in pages (`0` disables automatic
+    /// checkpoints).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wal_autocheckpoint: Option<u32>,
+    /// How long a connection waits on a locked database (milliseconds).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub busy_timeout_ms: Option<u64>,
+}
+
+/// Concrete pragma values after applying a [`TuningProfile`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SqliteTuning {
+    pub cache_size: i64,
+    pub mmap_size: u64,
+    pub synchronous: SynchronousMode,
+    pub wal_autocheckpoint: u32,
+    pub busy_timeout_ms: u64,
+}
+
+impl TuningProfile {
+    /// Pragma values for this preset.
+    pub fn tuning(self) -> SqliteTuning {
+        match self {
+            Self::Safe => SqliteTuning {
+                cache_size: -2_000,
+                mmap_size: 0,
+                synchronous: SynchronousMode::Full,
+                wal_autocheckpoint: 1_000,
+                busy_timeout_ms: 30_000,
+            },
+            Self::Fast => SqliteTuning {
+                cache_size: -262_144,
+                mmap_size: 1 << 30,
+                synchronous: SynchronousMode::Normal,
+                wal_autocheckpoint: 10_000,
+                busy_timeout_ms: 30_000,
+            },
+        }
+    }
+}
+
+impl Default for SqliteTuning {
+    fn default() -> Self {
+        TuningProfile::default().tuning()
+    }
+}
+
+impl TuningConfig {
+    /// Resolve the profile and explicit overrides into pragma values.
+    pub fn resolve(&self) -> SqliteTuning {
+        let base = self.profile.tuning();
+        SqliteTuning {
+            cache_size: self.cache_size.unwrap_or(base.cache_size),
+            mmap_size: self.mmap_size.unwrap_or(base.mmap_size),
+            synchronous: self.synchronous.unwrap_or(base.synchronous),
+            wal_autocheckpoint: self.wal_autocheckpoint.unwrap_or(base.wal_autocheckpoint),
+            busy_timeout_ms: self.busy_timeout_ms.unwrap_or(base.busy_timeout_ms),
+        }
+    }
+}
+
+/// SQLCipher at-rest encryption.
+///
+/// Requires a build with the `sqlcipher` feature of `hstry-core`; opening an
+/// encrypted database with a plain SQLite build fails instead of silently
+/// writing plaintext. The key is read from `key_env` first and falls back to
+/// the output of `key_command`, which can query the OS keyring (for example
+/// `secret-tool lookup service hstry` or
+/// `security find-generic-password -s hstry -w`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EncryptionConfig {
+    /// Encrypt the database file with SQLCipher.
+    pub enabled: bool,
+    /// Environment variable holding the passphrase.
+    pub key_env: String,
+    /// Shell command whose trimmed stdout is the passphrase.
+    pub key_command: Option<String>,
+}
+
+impl Default for EncryptionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            key_env: format!("{}_DB_KEY", crate::env_prefix()),
+            key_command: None,
+        }
+    }
+}
+
+impl EncryptionConfig {
+    /// Resolve the passphrase, or `None` when encryption is disabled.
+    pub fn resolve_key(&self) -> Result<Option<String>> {
+        if !self.enabled {
+            return Ok(None);
+        }
+        if let Ok(key) = std::env::var(&self.key_env)
+            && !key.is_empty()
+        {
+            return Ok(Some(key));
+        }
+        let Some(command) = self.key_command.as_deref() else {
+            return Err(Error::Config(format!(
+                "database encryption is enabled but {} is not set and no key_command is configured",
+                self.key_env
+            )));
+        };
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stderr(std::process::Stdio::inherit())
+            .output()?;
+        if !output.status.success() {
+            return Err(Error::Config(format!(
+                "database key_command exited with {}",
+                output.status
+            )));
+        }
+        let key = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if key.is_empty() {
+            return Err(Error::Config(
+                "database key_command printed an empty key".to_string(),
+            ));
+        }
+        Ok(Some(key))
+    }
+}
+
+/// Storage-level knobs that control optional bookkeeping tables.
+///
+/// `message_events` is an append-only event log that mirrors every message
+/// upsert. It is required by event-stream consumers (e.g. real-time UI clients)
+/// but represents pure overhead for batch / search-only workloads. The default
+/// favours the latter: events are *off*, and any existing rows are subject to
+/// retention compaction during scheduled syncs.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StorageConfig {
+    /// Configuration for the `message_events` log table.
+    pub message_events: MessageEventsConfig,
+    /// Configuration for the indexer outbox + worker.
+    pub indexer_outbox: IndexerOutboxConfig,
+    /// Connection pool sizing for the writer and read-only pools.
+    pub pool: PoolConfig,
+    /// Write-ahead buffer for live ingestion.
+    pub ingest_buffer: IngestBufferConfig,
+    /// Queue between adapter parsing and database writes during sync.
+    pub ingest_queue: IngestQueueConfig,
+    /// Cold-storage database for archived conversations. Defaults to
+    /// `archive.db` next to the main database.
+    pub archive_path: Option<PathBuf>,
+}
+
+/// Batches high-frequency live ingest writes (see `ingest_buffer`).
+///
+/// When enabled, the HTTP ingest endpoints acknowledge writes once they are
+/// queued and journaled; they become visible after the next flush.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IngestBufferConfig {
+    /// Queue ingest writes instead of committing each request.
+    pub enabled: bool,
+    /// Flush the queue at least this often (milliseconds).
+    pub flush_interval_ms: u64,
+    /// Flush early once this many writes are queued.
+    pub max_pending: usize,
+    /// Mirror queued writes to an NDJSON journal next to the database so
+    /// they survive a crash.
+    pub journal: bool,
+}
+
+impl Default for IngestBufferConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            flush_interval_ms: 500,
+            max_pending: 1_000,
+            journal: true,
+        }
+    }
+}
+
+/// Queue between an adapter's parser and the sync writer (see
+/// `ingest_queue`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IngestQueueConfig {
+    /// Parsed batches waiting to be written before parsing pauses.
+    pub capacity: usize,
+    /// Attempts after the first when a batch write hits a locked database.
+    pub retries: u32,
+    /// Wait before the first retry (milliseconds); doubles per attempt.
+    pub retry_backoff_ms: u64,
+}
+
+impl Default for IngestQueueConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 4,
+            retries: 3,
+            retry_backoff_ms: 250,
+        }
+    }
+}
+
+/// SQLite connection pool sizing.
+///
+/// Long-running processes (the API server and the background service) can
+/// open a dedicated read-only pool so search and listing traffic never queues
+/// behind sync writes. Each read runs against its own WAL snapshot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PoolConfig {
+    /// Maximum connections in the read-write pool.
+    pub max_connections: u32,
+    /// Maximum connections in the read-only pool used by the API and
+    /// service. `0` routes reads through the writer pool.
+    pub read_connections: u32,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: 5,
+            read_connections: 8,
+        }
+    }
+}
+
+/// Toggles the `message_events` append-only log on a per-deployment basis.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MessageEventsConfig {
+    /// Append a row to `message_events` for every message upsert.
+    /// Default: `false` — non-event consumers pay no overhead (trx-aa3m).
+    pub enabled: bool,
+    /// Maximum age of a `message_events` row before it becomes eligible for
+    /// retention compaction. `0` disables age-based pruning.
+    pub max_age_days: u32,
+    /// Maximum number of `message_events` rows to retain per conversation.
+    /// `0` disables per-conversation pruning.
+    pub max_per_conversation: u32,
+    /// Run retention compaction at most this often (seconds). The compactor is
+    /// invoked from the service sync loop and respects this floor.
+    pub compaction_interval_secs: u64,
+}
+
+impl Default for MessageEventsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_age_days: 30,
+            max_per_conversation: 5_000,
+            compaction_interval_secs: 3_600,
+        }
+    }
+}
+
+/// Conversation retention policy.
+///
+/// A conversation is out of policy when its last activity is older than
+/// `max_age_days`, or when it falls outside the newest
+/// `max_conversations_per_source` of its source. Conversations carrying any
+/// of `exclude_tags` are never pruned and do not count towards the
+/// per-source limit, and neither are starred ones unless `include_starred`
+/// is set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionConfig {
+    /// Enforce the policy automatically from the background service.
+    /// `hstry prune` works regardless of this flag.
+    pub enabled: bool,
+    /// Maximum age (days since last update) of a conversation. `0` disables
+    /// age-based pruning.
+    pub max_age_days: u32,
+    /// Maximum number of conversations kept per source. `0` disables the
+    /// per-source limit.
+    pub max_conversations_per_source: u32,
+    /// Tags that protect a conversation from pruning (e.g. `keep`).
+    pub exclude_tags: Vec<String>,
+    /// Prune starred conversations too.
+    pub include_starred: bool,
+    /// What to do with conversations beyond policy: `delete` or `archive`.
+    pub action: RetentionAction,
+    /// Run automatic enforcement at most this often (seconds).
+    pub interval_secs: u64,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_age_days: 0,
+            max_conversations_per_source: 0,
+            exclude_tags: vec!["keep".to_string()],
+            include_starred: false,
+            action: RetentionAction::Delete,
+            interval_secs: 21_600,
+        }
+    }
+}
+
+impl RetentionConfig {
+    /// True when at least one limit is configured.
+    pub fn has_limits(&self) -> bool {
+        self.max_age_days > 0 || self.max_conversations_per_source > 0
+    }
+}
+
+/// Conversations removed with `hstry remove`, the TUI or the API wait in
+/// the trash until `hstry trash empty` or the service purges them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrashConfig {
+    /// Days a conversation stays in the trash before the service deletes
+    /// it for good. `0` keeps it until `hstry trash empty`.
+    pub purge_after_days: u32,
+}
+
+impl Default for TrashConfig {
+    fn default() -> Self {
+        Self {
+            purge_after_days: 30,
+        }
+    }
+}
+
+/// A named set of sources:
+///
+/// ```toml
+/// [groups.work]
+/// sources = ["codex-work", "claude-code"]
+/// priority = 10
+/// tags = ["work"]
+///
+/// [groups.work.retention]
+/// max_age_days = 730
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SourceGroup {
+    /// Source ids in this group. A source belongs to the first group (by
+    /// name) that lists it.
+    pub sources: Vec<String>,
+    /// Groups with a higher priority are synced first; ungrouped sources
+    /// count as 0.
+    pub priority: i32,
+    /// Tags added to every conversation synced from the group's sources.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Limits replacing `[retention]`'s for the group's sources.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retention: Option<GroupRetention>,
+}
+
+/// Per-group overrides of the `[retention]` limits. Unset keys fall back to
+/// `[retention]`; `0` disables the limit for the group.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GroupRetention {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_age_days: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_conversations_per_source: Option<u32>,
+}
+
+/// Retention limits for one set of sources, from [`Config::retention_scopes`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetentionScope {
+    pub max_age_days: u32,
+    pub max_conversations_per_source: u32,
+    /// Only these sources; every source when `None`.
+    pub only_sources: Option<Vec<String>>,
+    /// Sources left to another scope.
+    pub skip_sources: Vec<String>,
+    /// Starred conversations are candidates too.
+    pub include_starred: bool,
+}
+
+impl RetentionScope {
+    /// True when at least one limit is configured.
+    pub fn has_limits(&self) -> bool {
+        self.max_age_days > 0 || self.max_conversations_per_source > 0
+    }
+}
+
+/// Which conversations `hstry-api` and `hstry-mcp` hand out, shown by
+/// `hstry access log`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccessLogConfig {
+    /// Record searches and conversation reads.
+    pub enabled: bool,
+    /// Drop entries older than this many days when a server starts
+    /// (0 keeps them forever).
+    pub max_age_days: u32,
+}
+
+impl Default for AccessLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_age_days: 90,
+        }
+    }
+}
+
+/// Redaction rules for share-safe exports (`[redact]`).
+///
+/// Each pattern is a regular expression; when it has a capture group named
+/// `secret` only that group is replaced, otherwise the whole match is.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RedactConfig {
+    /// Also apply the built-in rules (API keys, tokens, private keys,
+    /// passwords and email addresses).
+    pub builtin: bool,
+    /// Additional patterns.
+    pub patterns: Vec<String>,
+    /// Text that replaces a redacted match.
+    pub replacement: String,
+}
+
+/// Throttles of nice mode (`[nice]`), used by `hstry sync`, `import` and
+/// `index` with `--nice` and by the service when
+/// `service.resources.qos = "background"`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NiceConfig {
+    /// Run every sync, import and index in nice mode, as if `--nice` were
+    /// given.
+    pub enabled: bool,
+    /// Cap on message content written per second, in KiB (0 = unlimited).
+    pub max_write_kib_per_sec: u64,
+    /// Pause after every committed batch, giving the CPU back to
+    /// foreground work.
+    pub batch_pause_ms: u64,
+    /// Lower the process's CPU and IO priority (`renice`, and `ionice` on
+    /// Linux). Adapters started afterwards inherit it.
+    pub low_priority: bool,
+}
+
+impl Default for NiceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_write_kib_per_sec: 4096,
+            batch_pause_ms: 100,
+            low_priority: true,
+        }
+    }
+}
+
+impl Default for RedactConfig {
+    fn default() -> Self {
+        Self {
+            builtin: true,
+            patterns: Vec::new(),
+            replacement: "[redacted]".to_string(),
+        }
+    }
+}
+
+/// Limits on what the MCP server shares. The top-level keys apply when
+/// `hstry-mcp` runs without `--scope`; named scopes restrict individual
+/// agents:
+///
+/// ```toml
+/// [mcp]
+/// max_content_chars = 4000
+///
+/// [mcp.scopes.client-a]
+/// sources = ["claude-code"]
+/// workspaces = ["~/clients/a"]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct McpConfig {
+    /// Source ids or adapter names to expose; empty exposes every source.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<String>,
+    /// Workspace directories (and everything below them) to expose; empty
+    /// exposes every workspace, including conversations without one.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub workspaces: Vec<String>,
+    /// Truncate message content beyond this many characters. `0` disables.
+    pub max_content_chars: usize,
+    /// Named scopes selected with `hstry-mcp --scope <name>`.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub scopes: BTreeMap<String, McpScope>,
+}
+
+/// One set of MCP limits; see [`McpConfig`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct McpScope {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub workspaces: Vec<String>,
+    pub max_content_chars: usize,
+}
+
+impl McpConfig {
+    /// The named scope, or the top-level limits when `name` is `None`.
+    pub fn scope(&self, name: Option<&str>) -> Result<McpScope> {
+        match name {
+            None => Ok(McpScope {
+                sources: self.sources.clone(),
+                workspaces: self.workspaces.clone(),
+                max_content_chars: self.max_content_chars,
+            }),
+            Some(name) => self
+                .scopes
+                .get(name)
+                .cloned()
+                .ok_or_else(|| Error::Config(format!("Unknown MCP scope: {name}"))),
+        }
+    }
+}
+
+impl McpScope {
+    /// Whether a conversation from `source_id` (an `adapter` source) in
+    /// `workspace` is visible in this scope.
+    pub fn allows(&self, source_id: &str, adapter: &str, workspace: Option<&str>) -> bool {
+        let source_ok = self.allows_source(source_id, adapter);
+        let workspace_ok = self.workspaces.is_empty()
+            || workspace.is_some_and(|workspace| {
+                let workspace = Path::new(workspace);
+                self.workspaces
+                    .iter()
+                    .any(|allowed| workspace.starts_with(Config::expand_path(allowed)))
+            });
+        source_ok && workspace_ok
+    }
+
+    /// Whether conversations from `source_id` can be visible at all.
+    pub fn allows_source(&self, source_id: &str, adapter: &str) -> bool {
+        self.sources.is_empty()
+            || self
+                .sources
+                .iter()
+                .any(|allowed| allowed == source_id || allowed == adapter)
+    }
+
+    /// `content` cut to `max_content_chars`, noting how much was hidden.
+    pub fn mask<'a>(&self, content: &'a str) -> std::borrow::Cow<'a, str> {
+        if self.max_content_chars == 0 {
+            return content.into();
+        }
+        match content.char_indices().nth(self.max_content_chars) {
+            Some((cut, _)) => {
+                let hidden = content[cut..].chars().count();
+                format!("{}… [{hidden} more characters hidden]", &content[..cut]).into()
+            }
+            None => content.into(),
+        }
+    }
+}
+
+/// Action applied to conversations that fall outside the retention policy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RetentionAction {
+    /// Permanently delete the conversation and its messages.
+    #[default]
+    Delete,
+    /// Move the conversation into the archive database.
+    Archive,
+}
+
+/// Configuration for the durable indexer outbox.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IndexerOutboxConfig {
+    /// Enqueue indexer jobs on every message upsert. When disabled, the
+    /// service falls back to the legacy "index everything that has no row
+    /// in the search index" sweep.
+    pub enabled: bool,
+    /// How many outbox jobs the dedicated worker drains per tick.
+    pub batch_size: usize,
+    /// Sleep between worker ticks (milliseconds).
+    pub poll_interval_ms: u64,
+}
+
+impl Default for IndexerOutboxConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            batch_size: 256,
+            poll_interval_ms: 750,
+        }
+    }
+}
+
+/// Resume configuration for opening sessions in coding agents.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResumeConfig {
+    /// Default agent to resume sessions in (e.g., "pi", "claude-code", "codex").
+    pub default_agent: String,
+
+    /// Per-agent resume configuration.
+    pub agents: std::collections::HashMap<String, AgentResumeConfig>,
+}
+
+/// Configuration for resuming sessions in a specific coding agent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentResumeConfig {
+    /// hstry export format name (must match an adapter name).
+    pub format: String,
+
+    /// Command template to launch the agent.
+    /// Placeholders: {session_path}, {session_id}, {workspace}
+    pub command: String,
+
+    /// Directory where the agent stores sessions natively.
+    /// Converted sessions are placed here so the agent discovers them.
+    pub session_dir: String,
+}
+
+impl Default for ResumeConfig {
+    fn default() -> Self {
+        let mut agents = std::collections::HashMap::new();
+        agents.insert(
+            "pi".to_string(),
+            AgentResumeConfig {
+                format: "pi".to_string(),
+                command: "pi --session {session_path}".to_string(),
+                session_dir: "~/.pi/agent/sessions".to_string(),
+            },
+        );
+        agents.insert(
+            "claude-code".to_string(),
+            AgentResumeConfig {
+                format: "claude-code".to_string(),
+                command: "claude --resume {session_id}".to_string(),
+                session_dir: "~/.claude/projects".to_string(),
+            },
+        );
+        agents.insert(
+            "codex".to_string(),
+            AgentResumeConfig {
+                format: "codex".to_string(),
+                command: "codex resume {session_id}".to_string(),
+                session_dir: "~/.codex/sessions".to_string(),
+            },
+        );
+        agents.insert(
+            "hermes".to_string(),
+            AgentResumeConfig {
+                format: "hermes".to_string(),
+                command: "hermes --session {session_id}".to_string(),
+                session_dir: "~/.hermes/sessions".to_string(),
+            },
+        );
+        agents.insert(
+            "opencode".to_string(),
+            AgentResumeConfig {
+                format: "opencode".to_string(),
+                command: "opencode".to_string(),
+                session_dir: "~/.local/share/opencode".to_string(),
+            },
+        );
+        agents.insert(
+            "goose".to_string(),
+            AgentResumeConfig {
+                format: "goose".to_string(),
+                command: "goose session resume {session_id}".to_string(),
+                session_dir: "~/.local/share/goose/sessions".to_string(),
+            },
+        );
+        Self {
+            default_agent: "pi".to_string(),
+            agents,
+        }
+    }
+}
+
+/// Search configuration for indexing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchConfig {
+    /// Reserved for an external index directory; the full-text index lives
+    /// in the database (SQLite FTS5).
+    #[serde(default)]
+    pub index_path: Option<PathBuf>,
+
+    /// Batch size for background indexing.
+    #[serde(default = "default_index_batch_size")]
+    pub index_batch_size: usize,
+
+    /// Optional re-ranking of the best hits.
+    #[serde(default)]
+    pub rerank: RerankConfig,
+
+    /// How full-text scores are weighted.
+    #[serde(default)]
+    pub ranking: RankingConfig,
+
+    /// How message text is split into searchable terms. Changing it
+    /// rebuilds the full-text index the next time the database is opened.
+    #[serde(default)]
+    pub tokenizer: SearchTokenizer,
+
+    /// Bring the index up to date at the end of a sync that wrote messages:
+    /// rebuild it when it is stale and merge the segments the sync added.
+    #[serde(default = "default_true")]
+    pub auto_index: bool,
+}
+
+/// Tokenizer of the natural-language full-text index (code search keeps its
+/// own identifier-aware tokenizer).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchTokenizer {
+    /// Words with English stemming: "running" finds "runs".
+    #[default]
+    Porter,
+    /// Words without stemming, diacritics folded. Suits languages that
+    /// separate words with spaces but are mangled by English stemming.
+    Unicode,
+    /// Overlapping three-character sequences: matches any substring, so
+    /// Chinese and Japanese text without spaces is searchable. Terms shorter
+    /// than three characters are matched by a scan instead.
+    Trigram,
+}
+
+impl SearchTokenizer {
+    /// The FTS5 `tokenize` option.
+    pub fn fts5_spec(self) -> &'static str {
+        match self {
+            Self::Porter => "porter",
+            Self::Unicode => "unicode61 remove_diacritics 2",
+            Self::Trigram => "trigram",
+        }
+    }
+}
+
+/// Question answering over history (`hstry ask`).
+///
+/// The model answers from the best `passages` messages found for the
+/// question and cites them. It is the `[llm]` provider chosen for `ask`,
+/// unless `endpoint` names an OpenAI-compatible `/v1/chat/completions` URL
+/// directly.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AskConfig {
+    pub endpoint: Option<String>,
+    /// Model name sent with each request.
+    pub model: Option<String>,
+    /// Environment variable holding a bearer token for the endpoint.
+    pub api_key_env: Option<String>,
+    /// Messages given to the model as context.
+    pub passages: usize,
+    /// Characters of each message in the context.
+    pub excerpt_chars: usize,
+    pub timeout_secs: u64,
+}
+
+impl Default for AskConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: None,
+            model: None,
+            api_key_env: None,
+            passages: 12,
+            excerpt_chars: 1_500,
+            timeout_secs: 120,
+        }
+    }
+}
+
+/// Titles for untitled conversations (`[titles]`).
+///
+/// `hstry title generate` derives them from the first user message, or asks
+/// a model with `--llm`: the `[llm]` provider chosen for `titles`, unless
+/// `endpoint` names an OpenAI-compatible `/v1/chat/completions` URL
+/// directly.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TitleConfig {
+    pub endpoint: Option<String>,
+    /// Model name sent with each request.
+    pub model: Option<String>,
+    /// Environment variable holding a bearer token for the endpoint.
+    pub api_key_env: Option<String>,
+    /// Longest title kept, in characters.
+    pub max_chars: usize,
+    /// Characters of each message shown to the model.
+    pub excerpt_chars: usize,
+    pub timeout_secs: u64,
+}
+
+impl Default for TitleConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: None,
+            model: None,
+            api_key_env: None,
+            max_chars: 72,
+            excerpt_chars: 2_000,
+            timeout_secs: 60,
+        }
+    }
+}
+
+/// Daily note convention of a notes vault.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DailyNoteStyle {
+    /// `<vault>/YYYY-MM-DD.md` with a `##` section.
+    #[default]
+    Obsidian,
+    /// `<vault>/journals/YYYY_MM_DD.md` with an outline block.
+    Logseq,
+}
+
+/// Daily note summaries (`[daily_notes]`).
+///
+/// Each day's conversations are summarized in a section of that day's note
+/// in `vault`. Rewriting a day replaces the section and leaves the rest of
+/// the note untouched.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DailyNotesConfig {
+    /// Write the previous day's section from the background service.
+    pub enabled: bool,
+    /// Obsidian vault or Logseq graph directory.
+    pub vault: Option<PathBuf>,
+    pub style: DailyNoteStyle,
+    /// Folder of daily notes inside the vault. Defaults to the vault root
+    /// for Obsidian and `journals` for Logseq.
+    pub folder: Option<String>,
+    /// chrono format of note file names, without `.md`. Defaults to
+    /// `%Y-%m-%d` for Obsidian and `%Y_%m_%d` for Logseq.
+    pub file_format: Option<String>,
+    /// Heading of the section hstry writes.
+    pub heading: String,
+    /// Link target of each conversation, with `{id}` and `{readable_id}`
+    /// placeholders (e.g. `http://localhost:8080/c/{id}`). Without it the
+    /// readable id is shown instead.
+    pub link: Option<String>,
+    /// Local hour after which the service writes the previous day.
+    pub run_at_hour: u32,
+}
+
+impl Default for DailyNotesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            vault: None,
+            style: DailyNoteStyle::Obsidian,
+            folder: None,
+            file_format: None,
+            heading: "AI conversations".to_string(),
+            link: None,
+            run_at_hour: 2,
+        }
+    }
+}
+
+/// Normalization of workspace paths (`[workspace_paths]`), so the same
+/// project synced on several machines is stored under one workspace.
+///
+/// Applied to every conversation as it is stored; `hstry workspace
+/// normalize` applies it to conversations stored before.
+///
+/// ```toml
+/// [workspace_paths]
+/// rewrites = [{ from = "/mnt/laptop/code", to = "~/code" }]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorkspacePathConfig {
+    /// Prefix rewrites, tried in order before anything else; the first
+    /// matching `from` is replaced with `to`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rewrites: Vec<WorkspaceRewrite>,
+    /// Move paths under another machine's home directory (`/home/<user>`,
+    /// `/Users/<user>`, `C:\Users\<user>`) under this machine's.
+    pub canonical_home: bool,
+    /// Resolve symlinks in workspaces that exist on this machine.
+    pub resolve_symlinks: bool,
+}
+
+impl Default for WorkspacePathConfig {
+    fn default() -> Self {
+        Self {
+            rewrites: Vec::new(),
+            canonical_home: true,
+            resolve_symlinks: true,
+        }
+    }
+}
+
+impl WorkspacePathConfig {
+    /// Store workspaces exactly as adapters report them.
+    pub fn disabled() -> Self {
+        Self {
+            rewrites: Vec::new(),
+            canonical_home: false,
+            resolve_symlinks: false,
+        }
+    }
+}
+
+/// One `[workspace_paths]` prefix rewrite.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkspaceRewrite {
+    pub from: String,
+    pub to: String,
+}
+
+/// Sensitive sources and workspaces (`[sensitive]`).
+///
+/// Messages of matching conversations are encrypted to the key created by
+/// `hstry sensitive init` before they are stored, and never reach the search
+/// index. Reading them needs `hstry sensitive unlock`; running processes
+/// lock again when the session is locked or expires. hstry-api, hstry-mcp
+/// and remote sync leave these conversations out unless `api` or `mcp`
+/// opts in, and then serve their messages only while unlocked.
+///
+/// ```toml
+/// [sensitive]
+/// sources = ["chatgpt-personal"]
+/// workspaces = ["~/clients/acme"]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SensitiveConfig {
+    /// Source ids whose conversations are sensitive.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<String>,
+    /// Workspace directories (and everything below them) whose
+    /// conversations are sensitive.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub workspaces: Vec<String>,
+    /// Environment variable holding the passphrase; prompted for when unset.
+    pub passphrase_env: String,
+    /// How long `hstry sensitive unlock` keeps the key available.
+    pub unlock_minutes: u32,
+    /// List sensitive conversations over hstry-api.
+    pub api: bool,
+    /// List sensitive conversations over hstry-mcp.
+    pub mcp: bool,
+}
+
+impl Default for SensitiveConfig {
+    fn default() -> Self {
+        Self {
+            sources: Vec::new(),
+            workspaces: Vec::new(),
+            passphrase_env: format!("{}_SENSITIVE_PASSPHRASE", crate::env_prefix()),
+            unlock_minutes: 30,
+            api: false,
+            mcp: false,
+        }
+    }
+}
+
+impl SensitiveConfig {
+    /// Whether a conversation from `source_id` in `workspace` is sensitive.
+    pub fn matches(&self, source_id: &str, workspace: Option<&str>) -> bool {
+        self.sources.iter().any(|source| source == source_id)
+            || workspace.is_some_and(|workspace| {
+                let workspace = Path::new(workspace);
+                self.workspaces
+                    .iter()
+                    .any(|dir| workspace.starts_with(Config::expand_path(dir)))
+            })
+    }
+
+    /// Whether any source or workspace is marked sensitive.
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty() && self.workspaces.is_empty()
+    }
+}
+
+/// API spoken by a model provider.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LlmProviderKind {
+    /// OpenAI-compatible `/chat/completions` (OpenAI, OpenRouter, vLLM,
+    /// llama.cpp, LM Studio, ...).
+    #[default]
+    Openai,
+    /// Ollama's native `/api/chat`.
+    Ollama,
+}
+
+/// A model provider under `[llm.providers.<name>]`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LlmProviderConfig {
+    pub kind: LlmProviderKind,
+    /// Base URL, e.g. `https://api.openai.com/v1` or
+    /// `http://localhost:11434`.
+    pub url: String,
+    /// Model used unless the feature picks another.
+    pub model: Option<String>,
+    /// Environment variable holding a bearer token.
+    pub api_key_env: Option<String>,
+    /// Requests per minute across all features; `0` is unlimited.
+    pub requests_per_minute: u32,
+    pub timeout_secs: u64,
+    /// USD per million input tokens, for cost accounting.
+    pub input_cost_per_mtok: f64,
+    /// USD per million output tokens.
+    pub output_cost_per_mtok: f64,
+}
+
+impl Default for LlmProviderConfig {
+    fn default() -> Self {
+        Self {
+            kind: LlmProviderKind::Openai,
+            url: String::new(),
+            model: None,
+            api_key_env: None,
+            requests_per_minute: 0,
+            timeout_secs: 120,
+            input_cost_per_mtok: 0.0,
+            output_cost_per_mtok: 0.0,
+        }
+    }
 }
 
-/// Storage-level knobs that control optional bookkeeping tables.
-///
-/// `message_events` is an append-only event log that mirrors every message
-/// upsert. It is required by event-stream consumers (e.g. real-time UI clients)
-/// but represents pure overhead for batch / search-only workloads. The default
-/// favours the latter: events are *off*, and any existing rows are subject to
-/// retention compaction during scheduled syncs.
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+/// Provider and model of one feature under `[llm.features]`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(default)]
-pub struct StorageConfig {
-    /// Configuration for the `message_events` log table.
-    pub message_events: MessageEventsConfig,
-    /// Configuration for the indexer outbox + worker.
-    pub indexer_outbox: IndexerOutboxConfig,
+pub struct LlmFeatureConfig {
+    /// Name in `[llm.providers]`; defaults to `[llm].default_provider`.
+    pub provider: Option<String>,
+    /// Overrides the provider's model.
+    pub model: Option<String>,
 }
 
-/// Toggles the `message_events` append-only log on a per-deployment basis.
+/// Model providers shared by the LLM-powered features (`[llm]`).
+///
+/// ```toml
+/// [llm]
+/// default_provider = "local"
+///
+/// [llm.providers.local]
+/// kind = "ollama"
+/// url = "http://localhost:11434"
+/// model = "qwen3:8b"
+///
+/// [llm.features.ask]
+/// provider = "openai"
+/// model = "gpt-5-mini"
+/// ```
+///
+/// A feature whose own section sets an `endpoint` (e.g. `[ask]`) uses that
+/// endpoint instead.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
-pub struct MessageEventsConfig {
-    /// Append a row to `message_events` for every message upsert.
-    /// Default: `false` — non-event consumers pay no overhead (trx-aa3m).
-    pub enabled: bool,
-    /// Maximum age of a `message_events` row before it becomes eligible for
-    /// retention compaction. `0` disables age-based pruning.
-    pub max_age_days: u32,
-    /// Maximum number of `message_events` rows to retain per conversation.
-    /// `0` disables per-conversation pruning.
-    pub max_per_conversation: u32,
-    /// Run retention compaction at most this often (seconds). The compactor is
-    /// invoked from the service sync loop and respects this floor.
-    pub compaction_interval_secs: u64,
+pub struct LlmConfig {
+    /// Provider for features without one in `features`.
+    pub default_provider: Option<String>,
+    /// Retries after a rate limit (HTTP 429), server error or connection
+    /// failure.
+    pub max_retries: u32,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub providers: BTreeMap<String, LlmProviderConfig>,
+    /// Per-feature choice, keyed by feature: `ask`, `rerank`.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub features: BTreeMap<String, LlmFeatureConfig>,
 }
 
-impl Default for MessageEventsConfig {
+impl Default for LlmConfig {
     fn default() -> Self {
         Self {
-            enabled: false,
-            max_age_days: 30,
-            max_per_conversation: 5_000,
-            compaction_interval_secs: 3_600,
+            default_provider: None,
+            max_retries: 3,
+            providers: BTreeMap::new(),
+            features: BTreeMap::new(),
         }
     }
 }
 
-/// Configuration for the durable indexer outbox.
+/// Which kind of service re-scores search hits.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RerankBackend {
+    /// A `/v1/rerank` endpoint (Cohere, Jina, Voyage, llama.cpp, vLLM,
+    /// Infinity, TEI) serving a cross-encoder model.
+    #[default]
+    CrossEncoder,
+    /// A chat model asked to score each hit: the `[llm]` provider chosen
+    /// for `rerank`, or an OpenAI-compatible `/v1/chat/completions`
+    /// `endpoint`.
+    Llm,
+}
+
+/// Re-ranking of search results (`[search.rerank]`).
+///
+/// Full-text scores favour exact term overlap; a cross-encoder or LLM reads
+/// the query and each hit together, which ranks natural-language queries far
+/// better. Only the best `candidates` hits are re-scored, and `hstry search
+/// --rerank` enables it for one search.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
-pub struct IndexerOutboxConfig {
-    /// Enqueue indexer jobs on every message upsert. When disabled, the
-    /// service falls back to the legacy "index everything that has no row
-    /// in the search index" sweep.
+pub struct RerankConfig {
+    /// Re-rank every search.
     pub enabled: bool,
-    /// How many outbox jobs the dedicated worker drains per tick.
-    pub batch_size: usize,
-    /// Sleep between worker ticks (milliseconds).
-    pub poll_interval_ms: u64,
+    pub backend: RerankBackend,
+    /// Full URL of the rerank or chat completions endpoint.
+    pub endpoint: Option<String>,
+    /// Model name sent with each request.
+    pub model: Option<String>,
+    /// Environment variable holding a bearer token for the endpoint.
+    pub api_key_env: Option<String>,
+    /// Number of top hits to re-score.
+    pub candidates: usize,
+    /// Characters of each hit sent to the model.
+    pub max_chars: usize,
+    pub timeout_secs: u64,
 }
 
-impl Default for IndexerOutboxConfig {
+impl Default for RerankConfig {
     fn default() -> Self {
         Self {
             enabled: false,
-            batch_size: 256,
-            poll_interval_ms: 750,
+            backend: RerankBackend::CrossEncoder,
+            endpoint: None,
+            model: None,
+            api_key_env: None,
+            candidates: 50,
+            max_chars: 2_000,
+            timeout_secs: 30,
         }
     }
 }
 
-/// Resume configuration for opening sessions in coding agents.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Weights applied to full-text scores (`[search.ranking]`).
+///
+/// A hit's relevance is multiplied by its field weight, a recency boost and
+/// its source's boost, so a recent session outranks a years-old chat with
+/// the same terms.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
-pub struct ResumeConfig {
-    /// Default agent to resume sessions in (e.g., "pi", "claude-code", "codex").
-    pub default_agent: String,
-
-    /// Per-agent resume configuration.
-    pub agents: std::collections::HashMap<String, AgentResumeConfig>,
-}
-
-/// Configuration for resuming sessions in a specific coding agent.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct AgentResumeConfig {
-    /// hstry export format name (must match an adapter name).
-    pub format: String,
-
-    /// Command template to launch the agent.
-    /// Placeholders: {session_path}, {session_id}, {workspace}
-    pub command: String,
-
-    /// Directory where the agent stores sessions natively.
-    /// Converted sessions are placed here so the agent discovers them.
-    pub session_dir: String,
+pub struct RankingConfig {
+    /// Weight of the terms' relevance in the message content.
+    pub content_weight: f64,
+    /// Added weight for query terms found in the conversation title, scaled
+    /// by the share of terms found.
+    pub title_weight: f64,
+    /// How much a message written just now is boosted: its score is
+    /// multiplied by up to `1 + recency_weight`. `0` disables the boost.
+    pub recency_weight: f64,
+    /// Age in days at which the recency boost has halved.
+    pub recency_half_life_days: f64,
+    /// Score multipliers by source id or adapter name, e.g.
+    /// `{ codex = 1.5, chatgpt = 0.8 }`.
+    pub sources: BTreeMap<String, f64>,
 }
 
-impl Default for ResumeConfig {
+impl Default for RankingConfig {
     fn default() -> Self {
-        let mut agents = std::collections::HashMap::new();
-        agents.insert(
-            "pi".to_string(),
-            AgentResumeConfig {
-                format: "pi".to_string(),
-                command: "pi --session {session_path}".to_string(),
-                session_dir: "~/.pi/agent/sessions".to_string(),
-            },
-        );
-        agents.insert(
-            "claude-code".to_string(),
-            AgentResumeConfig {
-                format: "claude-code".to_string(),
-                command: "claude --resume {session_id}".to_string(),
-                session_dir: "~/.claude/projects".to_string(),
-            },
-        );
-        agents.insert(
-            "codex".to_string(),
-            AgentResumeConfig {
-                format: "codex".to_string(),
-                command: "codex resume {session_id}".to_string(),
-                session_dir: "~/.codex/sessions".to_string(),
-            },
-        );
-        agents.insert(
-            "hermes".to_string(),
-            AgentResumeConfig {
-                format: "hermes".to_string(),
-                command: "hermes --session {session_id}".to_string(),
-                session_dir: "~/.hermes/sessions".to_string(),
-            },
-        );
-        agents.insert(
-            "opencode".to_string(),
-            AgentResumeConfig {
-                format: "opencode".to_string(),
-                command: "opencode".to_string(),
-                session_dir: "~/.local/share/opencode".to_string(),
-            },
-        );
-        agents.insert(
-            "goose".to_string(),
-            AgentResumeConfig {
-                format: "goose".to_string(),
-                command: "goose session resume {session_id}".to_string(),
-                session_dir: "~/.local/share/goose/sessions".to_string(),
-            },
-        );
         Self {
-            default_agent: "pi".to_string(),
-            agents,
+            content_weight: 1.0,
+            title_weight: 0.5,
+            recency_weight: 1.0,
+            recency_half_life_days: 90.0,
+            sources: BTreeMap::new(),
         }
     }
 }
 
-/// Search configuration for indexing.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SearchConfig {
-    /// Optional path to the Tantivy index directory.
-    #[serde(default)]
-    pub index_path: Option<PathBuf>,
+impl RankingConfig {
+    /// Weights that leave full-text scores as they are.
+    pub fn neutral() -> Self {
+        Self {
+            content_weight: 1.0,
+            title_weight: 0.0,
+            recency_weight: 0.0,
+            recency_half_life_days: 90.0,
+            sources: BTreeMap::new(),
+        }
+    }
 
-    /// Batch size for background indexing.
-    #[serde(default = "default_index_batch_size")]
-    pub index_batch_size: usize,
+    /// Whether the weights change any score.
+    pub fn is_neutral(&self) -> bool {
+        self.content_weight == 1.0
+            && self.title_weight == 0.0
+            && self.recency_weight == 0.0
+            && self.sources.is_empty()
+    }
 }
 
 fn default_index_batch_size() -> usize {
@@ -282,15 +1528,24 @@ impl Default for WebConfig {
     }
 }
 
-/// Configuration for a remote host (SSH-based sync).
+/// Configuration for a remote host (SSH or hstry-api based sync).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RemoteConfig {
     /// Unique name for this remote (e.g., "laptop", "server").
     pub name: String,
 
-    /// SSH host (e.g., "user@hostname", "hostname", or SSH config alias).
+    /// How to reach the remote.
+    #[serde(rename = "type", default)]
+    pub transport: RemoteTransport,
+
+    /// SSH host (e.g., "user@hostname", "hostname", or SSH config alias), or
+    /// the base URL of the peer's hstry-api for `type = "http"`.
     pub host: String,
 
+    /// Environment variable holding a bearer token for an `http` remote's API.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_env: Option<String>,
+
     /// Path to the hstry database on the remote (defaults to standard XDG path).
     #[serde(default)]
     pub database_path: Option<String>,
@@ -306,6 +1561,58 @@ pub struct RemoteConfig {
     /// Whether this remote is enabled.
     #[serde(default = "default_true")]
     pub enabled: bool,
+
+    /// How to settle conversations that changed on both machines.
+    #[serde(default)]
+    pub conflict_strategy: ConflictStrategy,
+}
+
+/// Transport used to reach a remote.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RemoteTransport {
+    /// Run `hstry` on the remote over SSH.
+    #[default]
+    Ssh,
+    /// Talk to the remote's hstry-api server over HTTP(S).
+    Http,
+}
+
+impl RemoteTransport {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Ssh => "ssh",
+            Self::Http => "http",
+        }
+    }
+}
+
+/// How a remote sync settles a conversation whose messages diverged on both
+/// sides. Conversations that only grew on one side are never conflicts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConflictStrategy {
+    /// Keep whichever copy has the newer `updated_at`.
+    #[default]
+    LastWriterWins,
+    /// Always keep the copy on this machine.
+    PreferLocal,
+    /// Always keep the copy from the remote.
+    PreferRemote,
+    /// Keep both: the incoming copy is stored as a separate conversation and
+    /// the conflict stays open for review.
+    Duplicate,
+}
+
+impl ConflictStrategy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::LastWriterWins => "last-writer-wins",
+            Self::PreferLocal => "prefer-local",
+            Self::PreferRemote => "prefer-remote",
+            Self::Duplicate => "duplicate",
+        }
+    }
 }
 
 /// Configuration for an adapter repository.
@@ -348,10 +1655,12 @@ pub enum AdapterRepoSource {
 
     /// Direct URL to a tarball or zip archive.
     Archive {
-        /// URL to the archive file (.tar.gz, .zip, .tgz).
+        /// URL to the archive file (.zip, .tar.gz, .tgz, .tar.zst, .tar), or
+        /// a `file://` path.
         url: String,
 
-        /// Path within the archive where adapters are located.
+        /// Path within the archive where adapters are located. A single
+        /// top-level directory wrapping the archive is looked through.
         #[serde(default = "default_adapters_path")]
         path: String,
     },
@@ -372,6 +1681,10 @@ impl AdapterRepoSource {
     }
 }
 
+fn default_database_path() -> PathBuf {
+    xdg_data_dir().join("hstry").join("hstry.db")
+}
+
 fn default_git_ref() -> String {
     format!("v{}", env!("CARGO_PKG_VERSION"))
 }
@@ -380,9 +1693,12 @@ fn default_adapters_path() -> String {
     "adapters".to_string()
 }
 
+fn default_profile() -> String {
+    "default".to_string()
+}
+
 impl Default for Config {
     fn default() -> Self {
-        let data_dir = xdg_data_dir().join("hstry");
         let config_dir = xdg_config_dir().join("hstry");
 
         // Include both user config adapters and system-wide adapters
@@ -408,7 +1724,7 @@ impl Default for Config {
         }
 
         Self {
-            database: data_dir.join("hstry.db"),
+            database: DatabaseConfig::default(),
             adapter_paths,
             adapter_repos: vec![AdapterRepo {
                 name: "official".to_string(),
@@ -420,8 +1736,10 @@ impl Default for Config {
                 enabled: true,
             }],
             js_runtime: "auto".to_string(),
+            adapter_worker_idle_secs: 30,
             embedding_endpoint: None,
             workspaces: Vec::new(),
+            workspace_paths: WorkspacePathConfig::default(),
             adapters: Vec::new(),
             service: ServiceConfig::default(),
             sources: Vec::new(),
@@ -431,6 +1749,23 @@ impl Default for Config {
             web: WebConfig::default(),
             resume: ResumeConfig::default(),
             storage: StorageConfig::default(),
+            retention: RetentionConfig::default(),
+            trash: TrashConfig::default(),
+            profile: default_profile(),
+            locale: None,
+            badges: SourceBadges::default(),
+            aliases: BTreeMap::new(),
+            groups: BTreeMap::new(),
+            mcp: McpConfig::default(),
+            ask: AskConfig::default(),
+            titles: TitleConfig::default(),
+            llm: LlmConfig::default(),
+            pricing: Pricing::default(),
+            daily_notes: DailyNotesConfig::default(),
+            sensitive: SensitiveConfig::default(),
+            access_log: AccessLogConfig::default(),
+            redact: RedactConfig::default(),
+            nice: NiceConfig::default(),
         }
     }
 }
@@ -440,6 +1775,10 @@ impl Default for SearchConfig {
         Self {
             index_path: None,
             index_batch_size: default_index_batch_size(),
+            rerank: RerankConfig::default(),
+            ranking: RankingConfig::default(),
+            tokenizer: SearchTokenizer::default(),
+            auto_index: true,
         }
     }
 }
@@ -481,15 +1820,62 @@ impl Default for SyncConfig {
 }
 
 impl Config {
-    /// Resolve Tantivy index path from config.
+    /// Resolve the external index path from config (see
+    /// [`SearchConfig::index_path`]).
     pub fn search_index_path(&self) -> PathBuf {
         if let Some(path) = &self.search.index_path {
             return path.clone();
         }
 
-        let base_dir = self.database.parent().unwrap_or_else(|| Path::new("."));
+        let base_dir = self
+            .database
+            .path
+            .parent()
+            .unwrap_or_else(|| Path::new("."));
         base_dir.join("index").join("search")
     }
+
+    /// Path of the ingest buffer journal, or `None` when journaling is off.
+    pub fn ingest_buffer_journal_path(&self) -> Option<PathBuf> {
+        self.storage.ingest_buffer.journal.then(|| {
+            self.database
+                .path
+                .parent()
+                .unwrap_or_else(|| Path::new("."))
+                .join("ingest-buffer.ndjson")
+        })
+    }
+
+    /// Resolve the archive database path from config.
+    pub fn archive_path(&self) -> PathBuf {
+        if let Some(path) = &self.storage.archive_path {
+            return path.clone();
+        }
+        self.database
+            .path
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .join("archive.db")
+    }
+
+    /// Directory for per-run adapter logs (`logs/adapters` next to the
+    /// database).
+    pub fn adapter_log_dir(&self) -> PathBuf {
+        self.database
+            .path
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .join("logs")
+            .join("adapters")
+    }
+
+    /// Active profile name: `HSTRY_PROFILE` if set, else the configured one.
+    pub fn active_profile(&self) -> String {
+        std::env::var(format!("{}_PROFILE", crate::env_prefix()))
+            .ok()
+            .filter(|value| !value.trim().is_empty())
+            .unwrap_or_else(|| self.profile.clone())
+    }
 }
 
 impl Config {
@@ -546,8 +1932,8 @@ impl Config {
         PathBuf::from(expanded)
     }
 
-    fn expand_paths(&mut self) {
-        self.database = Self::expand_path(&self.database.to_string_lossy());
+    pub(crate) fn expand_paths(&mut self) {
+        self.database.path = Self::expand_path(&self.database.path.to_string_lossy());
         self.adapter_paths = self
             .adapter_paths
             .iter()
@@ -568,6 +1954,9 @@ impl Config {
                     .to_string_lossy()
                     .to_string(),
                 auto_sync: source.auto_sync,
+                options: source.options.clone(),
+                adapter_version: source.adapter_version.clone(),
+                filter: source.filter.clone(),
             })
             .collect();
 
@@ -576,6 +1965,85 @@ impl Config {
             .storage_dir
             .as_ref()
             .map(|path| Self::expand_path(path).to_string_lossy().to_string());
+        self.daily_notes.vault = self
+            .daily_notes
+            .vault
+            .as_ref()
+            .map(|path| Self::expand_path(&path.to_string_lossy()));
+    }
+
+    /// Inverse of `expand_paths`: rewrite paths under the home directory as
+    /// `~/...` so the config can be used on another machine.
+    pub(crate) fn contract_home_paths(&mut self) {
+        let Some(home) = dirs::home_dir() else {
+            return;
+        };
+        let contract = |path: &str| -> String {
+            Path::new(path).strip_prefix(&home).map_or_else(
+                |_| path.to_string(),
+                |rest| Path::new("~").join(rest).to_string_lossy().to_string(),
+            )
+        };
+        self.database.path = PathBuf::from(contract(&self.database.path.to_string_lossy()));
+        self.adapter_paths = self
+            .adapter_paths
+            .iter()
+            .map(|p| PathBuf::from(contract(&p.to_string_lossy())))
+            .collect();
+        self.workspaces = self.workspaces.iter().map(|p| contract(p)).collect();
+        for source in &mut self.sources {
+            source.path = contract(&source.path);
+        }
+        self.web.storage_dir = self.web.storage_dir.as_deref().map(contract);
+        self.daily_notes.vault = self
+            .daily_notes
+            .vault
+            .as_ref()
+            .map(|path| PathBuf::from(contract(&path.to_string_lossy())));
+    }
+
+    /// Name and settings of the group `source_id` belongs to.
+    pub fn source_group(&self, source_id: &str) -> Option<(&str, &SourceGroup)> {
+        self.groups
+            .iter()
+            .find(|(_, group)| group.sources.iter().any(|id| id == source_id))
+            .map(|(name, group)| (name.as_str(), group))
+    }
+
+    /// Split `base` into the limits that apply to each group with a
+    /// retention override, followed by `base` itself for all other sources.
+    pub fn retention_scopes(&self, base: &RetentionConfig) -> Vec<RetentionScope> {
+        let mut scopes = Vec::new();
+        let mut overridden = Vec::new();
+        for (name, group) in &self.groups {
+            let Some(retention) = group.retention else {
+                continue;
+            };
+            let sources: Vec<String> = group
+                .sources
+                .iter()
+                .filter(|id| self.source_group(id).map(|(owner, _)| owner) == Some(name.as_str()))
+                .cloned()
+                .collect();
+            overridden.extend(sources.iter().cloned());
+            scopes.push(RetentionScope {
+                max_age_days: retention.max_age_days.unwrap_or(base.max_age_days),
+                max_conversations_per_source: retention
+                    .max_conversations_per_source
+                    .unwrap_or(base.max_conversations_per_source),
+                only_sources: Some(sources),
+                skip_sources: Vec::new(),
+                include_starred: base.include_starred,
+            });
+        }
+        scopes.push(RetentionScope {
+            max_age_days: base.max_age_days,
+            max_conversations_per_source: base.max_conversations_per_source,
+            only_sources: None,
+            skip_sources: overridden,
+            include_starred: base.include_starred,
+        });
+        scopes
     }
 
     /// Check whether a given adapter is enabled.
@@ -603,6 +2071,64 @@ pub struct SourceConfig {
     /// Whether to auto-sync this source.
     #[serde(default = "default_true")]
     pub auto_sync: bool,
+
+    /// Adapter-specific options passed through to the adapter on every parse
+    /// (e.g. `include_archived = false`). Keys are adapter-defined.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub options: BTreeMap<String, serde_json::Value>,
+
+    /// Sync with this adapter version (a snapshot recorded in the adapter
+    /// lockfile) instead of whatever is currently installed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub adapter_version: Option<String>,
+
+    /// Conversations to leave out of the database when syncing.
+    #[serde(default, skip_serializing_if = "SourceFilterConfig::is_empty")]
+    pub filter: SourceFilterConfig,
+}
+
+/// Which conversations a sync keeps (`[sources.filter]`). Conversations
+/// left out are never written, so they are not searchable either.
+///
+/// ```toml
+/// [[sources]]
+/// id = "claude-code"
+/// adapter = "claude-code"
+/// path = "~/.claude/projects"
+///
+/// [sources.filter]
+/// exclude = ["**/agent-*.jsonl"]
+/// exclude_workspaces = ["~/scratch"]
+/// min_messages = 2
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SourceFilterConfig {
+    /// Glob patterns for the session files to keep, relative to the source
+    /// path; empty keeps every file. `*` stops at `/`, `**` does not, and a
+    /// pattern without `/` matches the file name.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    /// Glob patterns for session files to skip, applied after `include`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+    /// Workspace directories (and everything below them) to keep; empty
+    /// keeps every workspace, including conversations without one.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub workspaces: Vec<String>,
+    /// Workspace directories (and everything below them) to skip.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub exclude_workspaces: Vec<String>,
+    /// Skip conversations with fewer user and assistant messages than this,
+    /// such as transcripts made only of tool output. `0` keeps all.
+    pub min_messages: usize,
+}
+
+impl SourceFilterConfig {
+    /// Whether the filter keeps every conversation.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
 }
 
 /// Configuration for a single adapter.
@@ -614,6 +2140,128 @@ pub struct AdapterConfig {
     /// Whether this adapter is enabled for imports.
     #[serde(default = "default_true")]
     pub enabled: bool,
+
+    /// Runtime(s) to run this adapter on, e.g. "node" or "bun,node".
+    /// Overrides the adapter manifest's requirement.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runtime: Option<String>,
+
+    /// Seconds any single call to this adapter may take before its process
+    /// is killed, replacing the defaults (30 for detection, 600 for parsing
+    /// and export). For streamed parses it bounds the wait for each
+    /// conversation. 0 removes the limit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+}
+
+/// Display badges keyed by source id or adapter name:
+///
+/// ```toml
+/// [badges]
+/// opencode = { icon = "🟢", color = "green" }
+/// chatgpt = { icon = "🔵", color = "blue" }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SourceBadges(pub BTreeMap<String, SourceBadge>);
+
+impl SourceBadges {
+    /// Badge for a source: an entry for the exact source id wins, then one
+    /// for its adapter, then one for the id without an auto-generated
+    /// `-<hex8>` suffix (auto-detected sources are named `<adapter>-<hash>`).
+    pub fn lookup(&self, source_id: &str, adapter: Option<&str>) -> Option<&SourceBadge> {
+        self.0
+            .get(source_id)
+            .or_else(|| adapter.and_then(|adapter| self.0.get(adapter)))
+            .or_else(|| {
+                source_id
+                    .rsplit_once('-')
+                    .filter(|(_, suffix)| {
+                        suffix.len() == 8 && suffix.chars().all(|c| c.is_ascii_hexdigit())
+                    })
+                    .and_then(|(base, _)| self.0.get(base))
+            })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// How one source is marked in lists.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SourceBadge {
+    /// Short prefix such as an emoji.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    /// Terminal color for the source name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<BadgeColor>,
+}
+
+/// Basic terminal colors, so badges look the same in the CLI and the TUI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BadgeColor {
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    Gray,
+}
+
+/// Token prices keyed by model name; a key ending in `*` prices every model
+/// starting with the rest of it:
+///
+/// ```toml
+/// [pricing]
+/// "gpt-5" = { input_per_1k = 0.00125, output_per_1k = 0.01 }
+/// "claude-opus-4*" = { input_per_1k = 0.015, output_per_1k = 0.075 }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Pricing(pub BTreeMap<String, ModelPrice>);
+
+impl Pricing {
+    /// Price for a model, matched case-insensitively: an exact entry wins,
+    /// then the longest matching `*` prefix.
+    pub fn lookup(&self, model: &str) -> Option<&ModelPrice> {
+        let model = model.to_lowercase();
+        if let Some((_, price)) = self.0.iter().find(|(key, _)| key.to_lowercase() == model) {
+            return Some(price);
+        }
+        self.0
+            .iter()
+            .filter_map(|(key, price)| {
+                let prefix = key.strip_suffix('*')?.to_lowercase();
+                model.starts_with(&prefix).then_some((prefix.len(), price))
+            })
+            .max_by_key(|(len, _)| *len)
+            .map(|(_, price)| price)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Price of one model in dollars per thousand tokens.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelPrice {
+    pub input_per_1k: f64,
+    pub output_per_1k: f64,
+}
+
+impl ModelPrice {
+    /// Cost in dollars of the given token counts.
+    pub fn cost(&self, tokens_in: i64, tokens_out: i64) -> f64 {
+        (tokens_in as f64 * self.input_per_1k + tokens_out as f64 * self.output_per_1k) / 1_000.0
+    }
 }
 
 /// Transport type for gRPC service.
@@ -660,6 +2308,10 @@ pub struct ServiceConfig {
     /// Resource controls for the sync loop (trx-z42c.7).
     #[serde(default)]
     pub resources: ResourceConfig,
+
+    /// Alerts about sources that keep failing to sync.
+    #[serde(default)]
+    pub notifications: NotificationConfig,
 }
 
 /// Per-source adaptive cadence configuration. The scheduler keeps a per-source
@@ -700,7 +2352,7 @@ pub struct ResourceConfig {
     /// Hard time budget for a single source sync (milliseconds). `0` disables.
     pub per_source_time_budget_ms: u64,
     /// Quality-of-service class for sync work: `interactive` runs at normal
-    /// priority, `background` yields to other work via larger sleeps.
+    /// priority, `background` runs in nice mode (see [`NiceConfig`]).
     pub qos: QosClass,
 }
 
@@ -722,6 +2374,47 @@ pub enum QosClass {
     Background,
 }
 
+/// Failure alerts sent by the service. A source is reported once when it
+/// reaches `after_failures` consecutive failed syncs, again when it reaches
+/// `escalate_after`, and once more when it recovers; alerts raised during
+/// the same sync cycle are sent as a single digest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationConfig {
+    /// Send failure alerts at all.
+    pub enabled: bool,
+    /// Consecutive failed syncs before a source is reported.
+    pub after_failures: u32,
+    /// Consecutive failed syncs before a second, escalated alert. `0`
+    /// disables escalation.
+    pub escalate_after: u32,
+    /// Report when a previously reported source syncs again.
+    pub recovery: bool,
+    /// Show a desktop notification (`notify-send` or `osascript`).
+    pub desktop: bool,
+    /// POST each digest as JSON to this URL.
+    pub webhook_url: Option<String>,
+    /// Mail each digest to this address through `sendmail_command`.
+    pub email_to: Option<String>,
+    /// Command that reads a complete message (headers included) on stdin.
+    pub sendmail_command: String,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            after_failures: 3,
+            escalate_after: 20,
+            recovery: true,
+            desktop: true,
+            webhook_url: None,
+            email_to: None,
+            sendmail_command: "sendmail -t".to_string(),
+        }
+    }
+}
+
 impl Default for ServiceConfig {
     fn default() -> Self {
         Self {
@@ -732,6 +2425,7 @@ impl Default for ServiceConfig {
             transport: ServiceTransport::Tcp,
             scheduler: SchedulerConfig::default(),
             resources: ResourceConfig::default(),
+            notifications: NotificationConfig::default(),
         }
     }
 }
@@ -40,8 +40,8 @@ mod default_config_tests {
     #[test]
     fn default_has_database_path() {
         let config = Config::default();
-        assert!(config.database.to_string_lossy().contains("hstry"));
-        assert!(config.database.to_string_lossy().ends_with(".db"));
+        assert!(config.database.path.to_string_lossy().contains("hstry"));
+        assert!(config.database.path.to_string_lossy().ends_with(".db"));
     }
 
     #[test]
@@ -93,10 +93,14 @@ mod adapter_enabled_tests {
         config.adapters.push(AdapterConfig {
             name: "disabled-adapter".to_string(),
             enabled: false,
+            runtime: None,
+            timeout_secs: None,
         });
         config.adapters.push(AdapterConfig {
             name: "enabled-adapter".to_string(),
             enabled: true,
+            runtime: None,
+            timeout_secs: None,
         });
 
         assert!(!config.adapter_enabled("disabled-adapter"));
@@ -112,24 +116,46 @@ mod search_index_path_tests {
 
     #[test]
     fn uses_explicit_path_when_set() {
-        let mut config = Config::default();
-        config.search = SearchConfig {
-            index_path: Some(PathBuf::from("/custom/index")),
-            index_batch_size: 500,
+        let config = Config {
+            search: SearchConfig {
+                index_path: Some(PathBuf::from("/custom/index")),
+                ..SearchConfig::default()
+            },
+            ..Default::default()
         };
         assert_eq!(config.search_index_path(), PathBuf::from("/custom/index"));
     }
 
     #[test]
     fn derives_from_database_path_when_not_set() {
-        let mut config = Config::default();
-        config.database = PathBuf::from("/data/hstry/hstry.db");
+        let mut config = Config {
+            database: PathBuf::from("/data/hstry/hstry.db").into(),
+            ..Default::default()
+        };
         config.search.index_path = None;
 
         let index_path = config.search_index_path();
         assert!(index_path.to_string_lossy().contains("search"));
         assert!(index_path.to_string_lossy().contains("index"));
     }
+
+    #[test]
+    fn archive_path_defaults_next_to_database() {
+        let mut config = Config {
+            database: PathBuf::from("/data/hstry/hstry.db").into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.archive_path(),
+            PathBuf::from("/data/hstry/archive.db")
+        );
+
+        config.storage.archive_path = Some(PathBuf::from("/cold/hstry-archive.db"));
+        assert_eq!(
+            config.archive_path(),
+            PathBuf::from("/cold/hstry-archive.db")
+        );
+    }
 }
 
 #[cfg(test)]
@@ -139,10 +165,12 @@ mod config_serialization_tests {
 
     #[test]
     fn toml_roundtrip() {
-        let mut config = Config::default();
-        config.database = PathBuf::from("/test/db.db");
-        config.js_runtime = "bun".to_string();
-        config.workspaces = vec!["~/projects".to_string()];
+        let config = Config {
+            database: PathBuf::from("/test/db.db").into(),
+            js_runtime: "bun".to_string(),
+            workspaces: vec!["~/projects".to_string()],
+            ..Default::default()
+        };
 
         let toml_str = toml::to_string(&config).unwrap_or_else(|err| panic!("serialize: {err}"));
         let parsed: Config =
@@ -211,17 +239,20 @@ mod adapter_repo_source_tests {
 
 #[cfg(test)]
 mod remote_config_tests {
-    use super::super::RemoteConfig;
+    use super::super::{ConflictStrategy, RemoteConfig, RemoteTransport};
 
     #[test]
     fn serde_roundtrip() {
         let remote = RemoteConfig {
             name: "laptop".to_string(),
+            transport: RemoteTransport::Ssh,
             host: "user@laptop.local".to_string(),
+            token_env: None,
             database_path: Some("/custom/path.db".to_string()),
             port: Some(2222),
             identity_file: Some("~/.ssh/id_ed25519".to_string()),
             enabled: true,
+            conflict_strategy: ConflictStrategy::Duplicate,
         };
 
         let json = serde_json::to_string(&remote).unwrap_or_else(|err| panic!("serialize: {err}"));
@@ -231,5 +262,364 @@ mod remote_config_tests {
         assert_eq!(parsed.name, remote.name);
         assert_eq!(parsed.host, remote.host);
         assert_eq!(parsed.port, remote.port);
+        assert_eq!(parsed.conflict_strategy, ConflictStrategy::Duplicate);
+    }
+
+    #[test]
+    fn conflict_strategy_defaults_to_last_writer_wins() {
+        let parsed: RemoteConfig = toml::from_str("name = \"box\"\nhost = \"box\"")
+            .unwrap_or_else(|err| panic!("deserialize: {err}"));
+        assert_eq!(parsed.conflict_strategy, ConflictStrategy::LastWriterWins);
+        let parsed: RemoteConfig =
+            toml::from_str("name = \"box\"\nhost = \"box\"\nconflict_strategy = \"prefer-remote\"")
+                .unwrap_or_else(|err| panic!("deserialize: {err}"));
+        assert_eq!(parsed.conflict_strategy, ConflictStrategy::PreferRemote);
+    }
+
+    #[test]
+    fn transport_defaults_to_ssh() {
+        let parsed: RemoteConfig = toml::from_str("name = \"box\"\nhost = \"box\"")
+            .unwrap_or_else(|err| panic!("deserialize: {err}"));
+        assert_eq!(parsed.transport, RemoteTransport::Ssh);
+        let parsed: RemoteConfig = toml::from_str(
+            "name = \"nas\"\ntype = \"http\"\nhost = \"https://nas:3000\"\ntoken_env = \"NAS_TOKEN\"",
+        )
+        .unwrap_or_else(|err| panic!("deserialize: {err}"));
+        assert_eq!(parsed.transport, RemoteTransport::Http);
+        assert_eq!(parsed.token_env.as_deref(), Some("NAS_TOKEN"));
+    }
+}
+
+#[cfg(test)]
+mod database_config_tests {
+    use super::super::{Config, EncryptionConfig};
+    use std::path::PathBuf;
+
+    #[test]
+    fn accepts_bare_path() {
+        let config: Config = toml::from_str(r#"database = "/data/hstry.db""#)
+            .unwrap_or_else(|err| panic!("deserialize: {err}"));
+        assert_eq!(config.database.path, PathBuf::from("/data/hstry.db"));
+        assert!(!config.database.encryption.enabled);
+    }
+
+    #[test]
+    fn accepts_table_with_encryption() {
+        let config: Config = toml::from_str(
+            r#"
+            [database]
+            path = "/data/hstry.db"
+
+            [database.encryption]
+            enabled = true
+            key_command = "echo secret"
+            "#,
+        )
+        .unwrap_or_else(|err| panic!("deserialize: {err}"));
+        assert_eq!(config.database.path, PathBuf::from("/data/hstry.db"));
+        assert!(config.database.encryption.enabled);
+        assert_eq!(config.database.encryption.key_env, "HSTRY_DB_KEY");
+
+        let toml_str = toml::to_string(&config).unwrap_or_else(|err| panic!("serialize: {err}"));
+        let parsed: Config =
+            toml::from_str(&toml_str).unwrap_or_else(|err| panic!("deserialize: {err}"));
+        assert_eq!(parsed.database, config.database);
+    }
+
+    #[test]
+    fn resolve_key_prefers_env_then_command() {
+        let encryption = EncryptionConfig {
+            enabled: true,
+            key_env: "HSTRY_TEST_DB_KEY".to_string(),
+            key_command: Some("printf ' from-command \\n'".to_string()),
+        };
+        temp_env::with_var("HSTRY_TEST_DB_KEY", Some("from-env"), || {
+            assert_eq!(
+                encryption.resolve_key().ok().flatten().as_deref(),
+                Some("from-env")
+            );
+        });
+        temp_env::with_var_unset("HSTRY_TEST_DB_KEY", || {
+            assert_eq!(
+                encryption.resolve_key().ok().flatten().as_deref(),
+                Some("from-command")
+            );
+        });
+    }
+
+    #[test]
+    fn resolve_key_is_none_when_disabled() {
+        let encryption = EncryptionConfig::default();
+        assert!(matches!(encryption.resolve_key(), Ok(None)));
+    }
+}
+
+#[cfg(test)]
+mod tuning_config_tests {
+    use super::super::{Config, SynchronousMode, TuningProfile};
+
+    #[test]
+    fn default_profile_is_safe() {
+        let config = Config::default();
+        assert_eq!(config.database.tuning.profile, TuningProfile::Safe);
+        assert_eq!(
+            config.database.tuning.resolve().synchronous,
+            SynchronousMode::Full
+        );
+    }
+
+    #[test]
+    fn explicit_values_override_profile() {
+        let config: Config = toml::from_str(
+            r#"
+            [database]
+            path = "/data/hstry.db"
+            profile = "fast"
+            cache_size = -4096
+            synchronous = "full"
+            "#,
+        )
+        .unwrap_or_else(|err| panic!("deserialize: {err}"));
+        let tuning = config.database.tuning.resolve();
+        let fast = TuningProfile::Fast.tuning();
+        assert_eq!(tuning.cache_size, -4096);
+        assert_eq!(tuning.synchronous, SynchronousMode::Full);
+        assert_eq!(tuning.mmap_size, fast.mmap_size);
+        assert_eq!(tuning.wal_autocheckpoint, fast.wal_autocheckpoint);
+
+        let toml_str = toml::to_string(&config).unwrap_or_else(|err| panic!("serialize: {err}"));
+        let parsed: Config =
+            toml::from_str(&toml_str).unwrap_or_else(|err| panic!("deserialize: {err}"));
+        assert_eq!(parsed.database, config.database);
+    }
+}
+
+mod retention_config_tests {
+    use super::super::{Config, RetentionAction, RetentionConfig};
+
+    #[test]
+    fn default_is_disabled_without_limits() {
+        let retention = RetentionConfig::default();
+        assert!(!retention.enabled);
+        assert!(!retention.has_limits());
+        assert_eq!(retention.exclude_tags, vec!["keep".to_string()]);
+        assert_eq!(retention.action, RetentionAction::Delete);
+    }
+
+    #[test]
+    fn parses_retention_table() {
+        let config: Config = toml::from_str(
+            r#"
+            [retention]
+            enabled = true
+            max_age_days = 90
+            max_conversations_per_source = 500
+            exclude_tags = ["pinned"]
+            action = "delete"
+            "#,
+        )
+        .unwrap_or_else(|err| panic!("deserialize: {err}"));
+        assert!(config.retention.enabled);
+        assert!(config.retention.has_limits());
+        assert_eq!(config.retention.max_age_days, 90);
+        assert_eq!(config.retention.max_conversations_per_source, 500);
+        assert_eq!(config.retention.exclude_tags, vec!["pinned".to_string()]);
+        assert_eq!(config.retention.interval_secs, 21_600);
+    }
+}
+
+#[cfg(test)]
+mod source_group_tests {
+    use super::super::{Config, RetentionConfig, RetentionScope};
+
+    #[test]
+    fn group_retention_overrides_base_for_its_sources() {
+        let config: Config = toml::from_str(
+            r#"
+            [groups.personal]
+            sources = ["chatgpt"]
+
+            [groups.work]
+            sources = ["codex", "claude-code"]
+            priority = 10
+            tags = ["work"]
+
+            [groups.work.retention]
+            max_age_days = 730
+            "#,
+        )
+        .unwrap_or_else(|err| panic!("deserialize: {err}"));
+        assert_eq!(
+            config.source_group("codex").map(|(name, _)| name),
+            Some("work")
+        );
+        assert_eq!(
+            config
+                .source_group("chatgpt")
+                .map(|(_, group)| group.priority),
+            Some(0)
+        );
+        assert!(config.source_group("opencode").is_none());
+
+        let base = RetentionConfig {
+            max_age_days: 90,
+            max_conversations_per_source: 500,
+            ..RetentionConfig::default()
+        };
+        let scopes = config.retention_scopes(&base);
+        assert_eq!(
+            scopes,
+            vec![
+                RetentionScope {
+                    max_age_days: 730,
+                    max_conversations_per_source: 500,
+                    only_sources: Some(vec!["codex".to_string(), "claude-code".to_string()]),
+                    skip_sources: Vec::new(),
+                    include_starred: false,
+                },
+                RetentionScope {
+                    max_age_days: 90,
+                    max_conversations_per_source: 500,
+                    only_sources: None,
+                    skip_sources: vec!["codex".to_string(), "claude-code".to_string()],
+                    include_starred: false,
+                },
+            ]
+        );
+    }
+}
+
+#[cfg(test)]
+mod badge_config_tests {
+    use super::super::{BadgeColor, Config};
+
+    #[test]
+    fn lookup_prefers_source_then_adapter_then_hashed_base() {
+        let config: Config = toml::from_str(
+            r#"
+            [badges]
+            opencode = { icon = "🟢", color = "green" }
+            work-chat = { color = "blue" }
+            "#,
+        )
+        .unwrap_or_else(|err| panic!("deserialize: {err}"));
+        let badges = &config.badges;
+
+        let exact = badges.lookup("work-chat", Some("opencode"));
+        assert_eq!(exact.and_then(|b| b.color), Some(BadgeColor::Blue));
+
+        let by_adapter = badges.lookup("laptop", Some("opencode"));
+        assert_eq!(by_adapter.and_then(|b| b.icon.as_deref()), Some("🟢"));
+
+        let by_base = badges.lookup("opencode-1a2b3c4d", None);
+        assert_eq!(by_base.and_then(|b| b.color), Some(BadgeColor::Green));
+
+        assert!(badges.lookup("opencode-notahash", None).is_none());
+        assert!(Config::default().badges.is_empty());
+    }
+}
+
+mod pricing_config_tests {
+    use super::super::Config;
+
+    #[test]
+    fn lookup_prefers_exact_then_longest_prefix() {
+        let config: Config = toml::from_str(
+            r#"
+            [pricing]
+            "gpt-5" = { input_per_1k = 0.00125, output_per_1k = 0.01 }
+            "gpt-*" = { input_per_1k = 0.002 }
+            "gpt-5-mini*" = { input_per_1k = 0.00025, output_per_1k = 0.002 }
+            "#,
+        )
+        .unwrap_or_else(|err| panic!("deserialize: {err}"));
+        let pricing = &config.pricing;
+
+        let input = |model: &str| pricing.lookup(model).map(|price| price.input_per_1k);
+        assert_eq!(input("GPT-5"), Some(0.00125));
+        assert_eq!(input("gpt-5-mini-2025"), Some(0.00025));
+        assert_eq!(input("gpt-4o"), Some(0.002));
+        assert_eq!(input("claude-sonnet-4"), None);
+        assert_eq!(pricing.lookup("gpt-4o").map(|p| p.output_per_1k), Some(0.0));
+        assert!(Config::default().pricing.is_empty());
+    }
+}
+
+mod source_options_tests {
+    use super::super::Config;
+
+    #[test]
+    fn source_options_round_trip_and_default_empty() {
+        let config: Config = toml::from_str(
+            r#"
+            [[sources]]
+            id = "chatgpt"
+            adapter = "chatgpt"
+            path = "/exports"
+            options = { account = "work", include_archived = false }
+
+            [[sources]]
+            id = "codex"
+            adapter = "codex"
+            path = "/codex"
+            "#,
+        )
+        .unwrap_or_else(|err| panic!("deserialize: {err}"));
+        let options = &config.sources[0].options;
+        assert_eq!(options["account"], "work");
+        assert_eq!(options["include_archived"], false);
+        assert!(config.sources[1].options.is_empty());
+
+        let toml_str = toml::to_string(&config).unwrap_or_else(|err| panic!("serialize: {err}"));
+        assert_eq!(toml_str.matches("options").count(), 1);
+        let parsed: Config =
+            toml::from_str(&toml_str).unwrap_or_else(|err| panic!("deserialize: {err}"));
+        assert_eq!(parsed.sources[0].options, *options);
+    }
+}
+
+#[cfg(test)]
+mod mcp_scope_tests {
+    use super::super::{Config, McpScope};
+
+    #[test]
+    fn named_scope_restricts_sources_and_workspaces() {
+        let config: Config = toml::from_str(
+            r#"
+            [mcp]
+            max_content_chars = 100
+
+            [mcp.scopes.client-a]
+            sources = ["claude-code"]
+            workspaces = ["/work/client-a"]
+            max_content_chars = 10
+            "#,
+        )
+        .unwrap_or_else(|err| panic!("deserialize: {err}"));
+
+        let open = config.mcp.scope(None).expect("default scope");
+        assert!(open.allows("codex", "codex", None));
+        assert_eq!(open.max_content_chars, 100);
+
+        let scope = config.mcp.scope(Some("client-a")).expect("named scope");
+        // Matches by source id or adapter name.
+        assert!(scope.allows("cc-laptop", "claude-code", Some("/work/client-a/api")));
+        assert!(!scope.allows("codex", "codex", Some("/work/client-a")));
+        // Workspace prefixes match whole path components only.
+        assert!(!scope.allows("cc-laptop", "claude-code", Some("/work/client-ab")));
+        assert!(!scope.allows("cc-laptop", "claude-code", None));
+
+        assert!(config.mcp.scope(Some("missing")).is_err());
+    }
+
+    #[test]
+    fn mask_truncates_on_char_boundaries() {
+        let scope = McpScope {
+            max_content_chars: 3,
+            ..McpScope::default()
+        };
+        assert_eq!(scope.mask("héllo wörld"), "hél… [8 more characters hidden]");
+        assert_eq!(scope.mask("abc"), "abc");
+        assert_eq!(McpScope::default().mask("unchanged"), "unchanged");
     }
 }
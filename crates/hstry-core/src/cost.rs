@@ -0,0 +1,153 @@
+//! Spend reports (`hstry cost report`).
+//!
+//! Adapters record a cost for some conversations only. [`report`] adds up
+//! the recorded costs and estimates the rest from the conversation's tokens
+//! and the `[pricing]` entry for its model.
+
+use std::collections::BTreeSet;
+
+use serde::Serialize;
+
+use crate::config::Pricing;
+use crate::db::{CostBreakdown, Database, StatsGroup};
+use crate::error::Result;
+
+/// Spend of one month, model or workspace.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CostLine {
+    /// Month, model or workspace; `None` for conversations without a model
+    /// and for the report total.
+    pub key: Option<String>,
+    pub conversations: i64,
+    pub tokens_in: i64,
+    pub tokens_out: i64,
+    /// Cost recorded by the adapters.
+    pub recorded_usd: f64,
+    /// Cost estimated from tokens for conversations without a recorded one.
+    pub estimated_usd: f64,
+    /// Conversations with tokens but neither a recorded cost nor a price.
+    pub unpriced: i64,
+}
+
+impl CostLine {
+    pub fn total_usd(&self) -> f64 {
+        self.recorded_usd + self.estimated_usd
+    }
+
+    fn add(&mut self, other: &Self) {
+        self.conversations += other.conversations;
+        self.tokens_in += other.tokens_in;
+        self.tokens_out += other.tokens_out;
+        self.recorded_usd += other.recorded_usd;
+        self.estimated_usd += other.estimated_usd;
+        self.unpriced += other.unpriced;
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CostReport {
+    /// Months oldest first; models and workspaces costliest first.
+    pub lines: Vec<CostLine>,
+    pub total: CostLine,
+    /// Models with unpriced conversations, to add to `[pricing]`.
+    pub unpriced_models: Vec<String>,
+}
+
+/// Spend per `group`, estimating missing costs with `pricing`.
+pub async fn report(db: &Database, pricing: &Pricing, group: StatsGroup) -> Result<CostReport> {
+    let rows = db.cost_breakdown(group).await?;
+    Ok(summarize(&rows, pricing, group))
+}
+
+fn summarize(rows: &[CostBreakdown], pricing: &Pricing, group: StatsGroup) -> CostReport {
+    let mut lines: Vec<CostLine> = Vec::new();
+    let mut unpriced_models = BTreeSet::new();
+    for row in rows {
+        let mut line = CostLine {
+            key: row.key.clone(),
+            conversations: row.conversations,
+            tokens_in: row.tokens_in,
+            tokens_out: row.tokens_out,
+            ..CostLine::default()
+        };
+        if row.recorded {
+            line.recorded_usd = row.cost_usd;
+        } else if let Some(price) = row.model.as_deref().and_then(|model| pricing.lookup(model)) {
+            line.estimated_usd = price.cost(row.tokens_in, row.tokens_out);
+        } else if row.tokens_in + row.tokens_out > 0 {
+            line.unpriced = row.conversations;
+            unpriced_models.extend(row.model.clone());
+        }
+        match lines.iter_mut().find(|existing| existing.key == line.key) {
+            Some(existing) => existing.add(&line),
+            None => lines.push(line),
+        }
+    }
+
+    if group == StatsGroup::Month {
+        lines.sort_by(|a, b| a.key.cmp(&b.key));
+    } else {
+        lines.sort_by(|a, b| {
+            b.total_usd()
+                .total_cmp(&a.total_usd())
+                .then_with(|| a.key.cmp(&b.key))
+        });
+    }
+    let mut total = CostLine::default();
+    for line in &lines {
+        total.add(line);
+    }
+    CostReport {
+        lines,
+        total,
+        unpriced_models: unpriced_models.into_iter().collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ModelPrice;
+
+    fn row(key: &str, model: Option<&str>, recorded: bool, cost_usd: f64) -> CostBreakdown {
+        CostBreakdown {
+            key: Some(key.to_string()),
+            model: model.map(str::to_string),
+            recorded,
+            conversations: 2,
+            tokens_in: 10_000,
+            tokens_out: 2_000,
+            cost_usd,
+        }
+    }
+
+    #[test]
+    fn missing_costs_are_estimated_from_prices() {
+        let pricing = Pricing(
+            [(
+                "gpt-5*".to_string(),
+                ModelPrice {
+                    input_per_1k: 0.001,
+                    output_per_1k: 0.01,
+                },
+            )]
+            .into(),
+        );
+        let rows = [
+            row("2026-09", Some("gpt-5-mini"), false, 0.0),
+            row("2026-09", Some("gpt-5"), true, 1.5),
+            row("2026-08", Some("local-llama"), false, 0.0),
+        ];
+        let report = summarize(&rows, &pricing, StatsGroup::Month);
+
+        let months: Vec<_> = report.lines.iter().map(|l| l.key.as_deref()).collect();
+        assert_eq!(months, vec![Some("2026-08"), Some("2026-09")]);
+        let september = &report.lines[1];
+        assert_eq!(september.conversations, 4);
+        assert!((september.recorded_usd - 1.5).abs() < 1e-9);
+        assert!((september.estimated_usd - 0.03).abs() < 1e-9);
+        assert_eq!(report.lines[0].unpriced, 2);
+        assert!((report.total.total_usd() - 1.53).abs() < 1e-9);
+        assert_eq!(report.unpriced_models, vec!["local-llama".to_string()]);
+    }
+}
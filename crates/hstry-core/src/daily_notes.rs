@@ -0,0 +1,342 @@
+//! Daily note summaries for Obsidian and Logseq vaults.
+//!
+//! [`write_day`] summarizes the conversations started on one local day
+//! (title, link, source, workspace and outcome) as a section of that day's
+//! note. The section sits under [`DailyNotesConfig::heading`]; writing the
+//! day again replaces it and leaves everything else in the note as it was.
+
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, TimeZone, Utc};
+use serde::Serialize;
+
+use crate::config::{DailyNoteStyle, DailyNotesConfig};
+use crate::db::{Database, ListConversationsOptions};
+use crate::error::Result;
+use crate::models::Conversation;
+use crate::pack::{one_line, outcome, truncate};
+
+/// Characters of an outcome shown in a note.
+const OUTCOME_CHARS: usize = 240;
+
+/// One conversation in a day's section.
+#[derive(Debug, Clone)]
+pub struct DayEntry {
+    pub conversation: Conversation,
+    pub message_count: i64,
+    /// Title, or the first user message when the conversation has none.
+    pub title: String,
+    pub outcome: Option<String>,
+}
+
+/// Result of writing one day.
+#[derive(Debug, Clone, Serialize)]
+pub struct DayNote {
+    pub date: NaiveDate,
+    pub path: PathBuf,
+    pub conversations: usize,
+}
+
+/// Path of the daily note for `day` in `vault`.
+pub fn note_path(config: &DailyNotesConfig, vault: &Path, day: NaiveDate) -> PathBuf {
+    let (folder, file_format) = match config.style {
+        DailyNoteStyle::Obsidian => ("", "%Y-%m-%d"),
+        DailyNoteStyle::Logseq => ("journals", "%Y_%m_%d"),
+    };
+    let folder = config.folder.as_deref().unwrap_or(folder);
+    let file_format = config.file_format.as_deref().unwrap_or(file_format);
+    let mut path = vault.to_path_buf();
+    if !folder.is_empty() {
+        path.push(folder);
+    }
+    path.push(format!("{}.md", day.format(file_format)));
+    path
+}
+
+/// Start and end of a local day, in UTC.
+pub fn day_bounds(day: NaiveDate) -> (DateTime<Utc>, DateTime<Utc>) {
+    let start = |day: NaiveDate| {
+        let midnight = day.and_time(NaiveTime::MIN);
+        Local
+            .from_local_datetime(&midnight)
+            .earliest()
+            .map(|dt| dt.with_timezone(&Utc))
+            .unwrap_or_else(|| Utc.from_utc_datetime(&midnight))
+    };
+    let next = day.succ_opt().unwrap_or(day);
+    (start(day), start(next))
+}
+
+/// Conversations started on `day` (local time), oldest first. Sensitive
+/// conversations are left out: notes are written in plaintext.
+pub async fn day_entries(
+    db: &Database,
+    day: NaiveDate,
+    source_id: Option<String>,
+    workspace: Option<String>,
+) -> Result<Vec<DayEntry>> {
+    let (start, end) = day_bounds(day);
+    let mut summaries = db
+        .list_conversation_summaries(ListConversationsOptions {
+            source_id,
+            workspace,
+            after: Some(start - chrono::Duration::seconds(1)),
+            before: Some(end),
+            exclude_sensitive: true,
+            ..Default::default()
+        })
+        .await?;
+    summaries.retain(|summary| summary.message_count > 0);
+    summaries.sort_by_key(|summary| summary.conversation.created_at);
+
+    let mut entries = Vec::with_capacity(summaries.len());
+    for summary in summaries {
+        let messages = db.get_messages(summary.conversation.id).await?;
+        let title = summary
+            .conversation
+            .title
+            .as_deref()
+            .or(summary.first_user_message.as_deref())
+            .map(|text| truncate(&one_line(text), 80))
+            .filter(|title| !title.is_empty())
+            .unwrap_or_else(|| "Untitled".to_string());
+        entries.push(DayEntry {
+            outcome: outcome(&messages).map(|text| truncate(&text, OUTCOME_CHARS)),
+            message_count: summary.message_count,
+            conversation: summary.conversation,
+            title,
+        });
+    }
+    Ok(entries)
+}
+
+/// The section for one day, ending in a newline.
+pub fn render_section(config: &DailyNotesConfig, entries: &[DayEntry]) -> String {
+    let (header, item, detail) = match config.style {
+        DailyNoteStyle::Obsidian => (format!("## {}\n\n", config.heading), "- ", "  - "),
+        DailyNoteStyle::Logseq => (format!("- ## {}\n", config.heading), "\t- ", "\t\t- "),
+    };
+    let mut out = header;
+    for entry in entries {
+        let conv = &entry.conversation;
+        let title = entry.title.replace(['[', ']'], "");
+        let readable = conv
+            .readable_id
+            .clone()
+            .unwrap_or_else(|| conv.id.to_string());
+        let _ = write!(
+            out,
+            "{item}{} ",
+            conv.created_at.with_timezone(&Local).format("%H:%M")
+        );
+        match &config.link {
+            Some(template) => {
+                let url = template
+                    .replace("{id}", &conv.id.to_string())
+                    .replace("{readable_id}", &readable);
+                let _ = write!(out, "**[{title}]({url})**");
+            }
+            None => {
+                let _ = write!(out, "**{title}** `{readable}`");
+            }
+        }
+        let _ = write!(out, " · {}", conv.source_id);
+        if let Some(workspace) = conv.workspace.as_deref().filter(|w| !w.is_empty()) {
+            let name = Path::new(workspace)
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or(workspace);
+            let _ = write!(out, " · `{name}`");
+        }
+        let _ = writeln!(out, " · {} messages", entry.message_count);
+        if let Some(outcome) = &entry.outcome {
+            let _ = writeln!(out, "{detail}{outcome}");
+        }
+    }
+    out
+}
+
+/// `note` with its hstry section replaced by `section`, or with `section`
+/// appended when the note has none yet.
+pub fn merge_section(config: &DailyNotesConfig, note: &str, section: &str) -> String {
+    let lines: Vec<&str> = note.split_inclusive('\n').collect();
+    let (marker, ends_section): (String, fn(&str) -> bool) = match config.style {
+        DailyNoteStyle::Obsidian => (format!("## {}", config.heading), |line| {
+            line.starts_with("# ") || line.starts_with("## ")
+        }),
+        DailyNoteStyle::Logseq => (format!("- ## {}", config.heading), |line| {
+            !line.trim().is_empty() && !line.starts_with([' ', '\t'])
+        }),
+    };
+    let start = lines.iter().position(|line| line.trim_end() == marker);
+    let Some(start) = start else {
+        if note.trim().is_empty() || note.trim() == "-" {
+            return section.to_string();
+        }
+        let mut out = note.to_string();
+        if !out.ends_with('\n') {
+            out.push('\n');
+        }
+        if config.style == DailyNoteStyle::Obsidian {
+            out.push('\n');
+        }
+        out.push_str(section);
+        return out;
+    };
+    let end = lines[start + 1..]
+        .iter()
+        .position(|line| ends_section(line))
+        .map_or(lines.len(), |offset| start + 1 + offset);
+
+    let mut out: String = lines[..start].concat();
+    out.push_str(section);
+    if end < lines.len() {
+        if config.style == DailyNoteStyle::Obsidian {
+            out.push('\n');
+        }
+        out.push_str(&lines[end..].concat());
+    }
+    out
+}
+
+/// Write the section for `day` into its note in `vault`, creating the note
+/// when it does not exist. Days without conversations are left alone.
+pub async fn write_day(
+    db: &Database,
+    config: &DailyNotesConfig,
+    vault: &Path,
+    day: NaiveDate,
+    source_id: Option<String>,
+    workspace: Option<String>,
+) -> Result<DayNote> {
+    let entries = day_entries(db, day, source_id, workspace).await?;
+    let path = note_path(config, vault, day);
+    if !entries.is_empty() {
+        let note = match std::fs::read_to_string(&path) {
+            Ok(note) => note,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err.into()),
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let section = render_section(config, &entries);
+        std::fs::write(&path, merge_section(config, &note, &section))?;
+    }
+    Ok(DayNote {
+        date: day,
+        path,
+        conversations: entries.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn entry(title: &str, outcome: Option<&str>) -> DayEntry {
+        DayEntry {
+            conversation: Conversation {
+                id: Uuid::new_v4(),
+                source_id: "codex".to_string(),
+                external_id: None,
+                readable_id: Some("swift-fox".to_string()),
+                platform_id: None,
+                title: Some(title.to_string()),
+                created_at: Utc::now(),
+                updated_at: None,
+                model: None,
+                provider: None,
+                workspace: Some("/home/me/src/hstry".to_string()),
+                tokens_in: None,
+                tokens_out: None,
+                cost_usd: None,
+                metadata: serde_json::json!({}),
+                harness: None,
+                version: 1,
+                message_count: 4,
+                parent_conversation_id: None,
+                parent_message_idx: None,
+                fork_type: None,
+            },
+            message_count: 4,
+            title: title.to_string(),
+            outcome: outcome.map(str::to_string),
+        }
+    }
+
+    fn config(style: DailyNoteStyle) -> DailyNotesConfig {
+        DailyNotesConfig {
+            style,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn note_paths_follow_each_convention() {
+        let day = NaiveDate::from_ymd_opt(2026, 3, 9).unwrap();
+        let vault = Path::new("/vault");
+        assert_eq!(
+            note_path(&config(DailyNoteStyle::Obsidian), vault, day),
+            Path::new("/vault/2026-03-09.md")
+        );
+        assert_eq!(
+            note_path(&config(DailyNoteStyle::Logseq), vault, day),
+            Path::new("/vault/journals/2026_03_09.md")
+        );
+        let custom = DailyNotesConfig {
+            folder: Some("Daily".to_string()),
+            file_format: Some("%d.%m.%Y".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            note_path(&custom, vault, day),
+            Path::new("/vault/Daily/09.03.2026.md")
+        );
+    }
+
+    #[test]
+    fn renders_links_and_outcomes() {
+        let mut config = config(DailyNoteStyle::Obsidian);
+        let entries = [entry("Fix [WAL]", Some("Enabled WAL mode."))];
+        let section = render_section(&config, &entries);
+        assert!(section.starts_with("## AI conversations\n\n- "));
+        assert!(section.contains("**Fix WAL** `swift-fox` · codex · `hstry` · 4 messages\n"));
+        assert!(section.ends_with("  - Enabled WAL mode.\n"));
+
+        config.link = Some("http://localhost:8080/c/{readable_id}".to_string());
+        let section = render_section(&config, &entries);
+        assert!(section.contains("**[Fix WAL](http://localhost:8080/c/swift-fox)**"));
+    }
+
+    #[test]
+    fn obsidian_section_is_appended_then_replaced() {
+        let config = config(DailyNoteStyle::Obsidian);
+        let note = "# Monday\n\nGym.\n";
+        let first = merge_section(&config, note, "## AI conversations\n\n- one\n");
+        assert_eq!(first, "# Monday\n\nGym.\n\n## AI conversations\n\n- one\n");
+
+        let edited = format!("{first}\n## Evening\n\nRead.\n");
+        let second = merge_section(&config, &edited, "## AI conversations\n\n- two\n");
+        assert_eq!(
+            second,
+            "# Monday\n\nGym.\n\n## AI conversations\n\n- two\n\n## Evening\n\nRead.\n"
+        );
+    }
+
+    #[test]
+    fn logseq_block_is_replaced_with_its_children() {
+        let config = config(DailyNoteStyle::Logseq);
+        assert_eq!(
+            merge_section(&config, "- \n", "- ## AI conversations\n\t- one\n"),
+            "- ## AI conversations\n\t- one\n"
+        );
+        let note = "- Standup\n- ## AI conversations\n\t- old\n\t\t- outcome\n- Later\n";
+        assert_eq!(
+            merge_section(&config, note, "- ## AI conversations\n\t- new\n"),
+            "- Standup\n- ## AI conversations\n\t- new\n- Later\n"
+        );
+    }
+}
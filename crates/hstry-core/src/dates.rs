@@ -0,0 +1,123 @@
+//! Date filters given on the command line and in API queries.
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::error::{Error, Result};
+
+fn unit_to_duration(n: i64, unit: &str) -> Option<Duration> {
+    match unit.trim_end_matches('s') {
+        "m" | "min" | "minute" => Some(Duration::minutes(n)),
+        "h" | "hr" | "hour" => Some(Duration::hours(n)),
+        "d" | "day" => Some(Duration::days(n)),
+        "w" | "wk" | "week" => Some(Duration::weeks(n)),
+        "mo" | "mon" | "month" => Some(Duration::days(n * 30)),
+        "y" | "yr" | "year" => Some(Duration::days(n * 365)),
+        _ => None,
+    }
+}
+
+/// Parse a natural-language or ISO date string into a `DateTime<Utc>`.
+///
+/// Supports:
+/// - ISO dates: "2026-03-01", "2026-03-01T10:00:00Z"
+/// - Relative: "today", "yesterday", "N days ago", "N weeks ago", "N months ago"
+/// - Without "ago": "1 week", "2 days", "3 hours"
+/// - Compact: "1w", "2d", "3h", "30m", "2mo", "1y"
+/// - Bare number: "2" (interpreted as N days ago)
+/// - Named: "last week", "last month"
+pub fn parse_date_filter(s: &str) -> Result<DateTime<Utc>> {
+    let lower = s.trim().to_lowercase();
+
+    // Handle relative dates that dateparser doesn't support
+    let now = Utc::now();
+    let start_of_day = |dt: DateTime<Utc>| -> Result<DateTime<Utc>> {
+        dt.date_naive()
+            .and_hms_opt(0, 0, 0)
+            .map(|value| value.and_utc())
+            .ok_or_else(|| Error::Other("Could not compute start-of-day timestamp".to_string()))
+    };
+
+    if lower == "today" {
+        return start_of_day(now);
+    }
+    if lower == "yesterday" {
+        return start_of_day(now - Duration::days(1));
+    }
+    if lower == "last week" {
+        return start_of_day(now - Duration::weeks(1));
+    }
+    if lower == "last month" {
+        return start_of_day(now - Duration::days(30));
+    }
+
+    // "N days/weeks/months ago" or just "N days/weeks" (with or without "ago")
+    let relative = lower.strip_suffix(" ago").unwrap_or(&lower);
+    let parts: Vec<&str> = relative.split_whitespace().collect();
+    if parts.len() == 2
+        && let Ok(n) = parts[0].parse::<i64>()
+        && let Some(d) = unit_to_duration(n, parts[1])
+    {
+        return Ok(now - d);
+    }
+
+    // Compact durations like "1w", "2d", "3h", "30m", "2mo", "1y"
+    if let Some(idx) = lower.find(|c: char| c.is_alphabetic())
+        && idx > 0
+        && let Ok(n) = lower[..idx].parse::<i64>()
+        && let Some(d) = unit_to_duration(n, &lower[idx..])
+    {
+        return Ok(now - d);
+    }
+
+    // Bare number → N days ago
+    if let Ok(n) = lower.parse::<i64>() {
+        return start_of_day(now - Duration::days(n));
+    }
+
+    // Fall back to dateparser for ISO dates and other formats
+    dateparser::parse(s)
+        .map(|dt| dt.with_timezone(&Utc))
+        .map_err(|e| Error::Config(format!("Could not parse date '{s}': {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_date_filter_accepts_relative_durations() {
+        let now = Utc::now();
+        let cases = [
+            ("2", Duration::days(2)),
+            ("1w", Duration::weeks(1)),
+            ("2d", Duration::days(2)),
+            ("3h", Duration::hours(3)),
+            ("30m", Duration::minutes(30)),
+            ("2mo", Duration::days(60)),
+            ("1y", Duration::days(365)),
+            ("1 week", Duration::weeks(1)),
+            ("2 days", Duration::days(2)),
+            ("3 hours ago", Duration::hours(3)),
+            ("2 weeks ago", Duration::weeks(2)),
+        ];
+        for (input, expected) in cases {
+            let parsed =
+                parse_date_filter(input).unwrap_or_else(|err| panic!("parse '{input}': {err}"));
+            // Bare numbers snap to start-of-day, so allow a full day of slack.
+            let delta = ((now - expected) - parsed).num_seconds().abs();
+            assert!(
+                delta <= 86_400,
+                "input '{input}' parsed too far from expected (delta {delta}s)"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_date_filter_accepts_rfc3339_and_rejects_garbage() {
+        assert_eq!(
+            parse_date_filter("2026-03-01T10:00:00Z").unwrap(),
+            "2026-03-01T10:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        assert!(parse_date_filter("someday soon").is_err());
+    }
+}
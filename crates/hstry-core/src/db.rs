@@ -1,21 +1,71 @@
 //! Database operations for hstry.
 
+use crate::api_token::ApiTokenScope;
+use crate::config::{
+    Config, IngestQueueConfig, PoolConfig, RankingConfig, RetentionScope, SearchTokenizer,
+    SensitiveConfig, SqliteTuning, WorkspacePathConfig,
+};
+use crate::decisions::{Decision, FoundDecision};
 use crate::error::{Error, Result};
+use crate::labels;
+use crate::llm::LlmUsage;
 use crate::models::{
-    Conversation, ConversationSnapshot, Message, MessageEvent, MessageRole, SearchHit, Source,
+    Conversation, ConversationSnapshot, Message, MessageContext, MessageEvent, MessageRole,
+    SearchHit, Source, ToolCall, ToolStatus,
 };
+use crate::revisions::{MessageRevision, RevisionKind};
+use crate::saved_search::{SavedFilters, SavedSearch};
 use crate::schema::SCHEMA;
+use crate::sensitive;
+use crate::throttle::Throttle;
+use crate::tool_calls::Extracted;
+use crate::workspaces::Workspace;
+use age::x25519::{Identity, Recipient};
 use chrono::Utc;
-use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
-use sqlx::{Row, SqlitePool};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions, SqliteSynchronous};
+use sqlx::{ConnectOptions, Connection, Row, SqlitePool};
+use std::collections::{HashMap, HashSet};
 use std::fmt::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, PoisonError, RwLock};
 use std::time::Duration;
 use tokio::sync::{Mutex, MutexGuard};
 use uuid::Uuid;
 
+/// `metadata` for a message upsert: the incoming metadata, keeping labels
+/// set on the stored row so re-imports do not drop them.
+macro_rules! merge_message_metadata {
+    () => {
+        "metadata = CASE \
+            WHEN NOT json_valid(messages.metadata) OR NOT json_valid(excluded.metadata) \
+                THEN excluded.metadata \
+            WHEN json_type(messages.metadata, '$.labels') = 'array' \
+                AND json_type(excluded.metadata) = 'object' \
+                THEN json_set(excluded.metadata, '$.labels', messages.metadata -> '$.labels') \
+            ELSE excluded.metadata END"
+    };
+}
+
+/// `tokens` for a message upsert: the incoming count, keeping an estimate
+/// from `hstry tokens backfill` while the adapter reports none and the
+/// content is unchanged.
+macro_rules! merge_message_tokens {
+    () => {
+        "tokens = CASE \
+            WHEN excluded.tokens IS NULL AND messages.tokens_estimated = 1 \
+                AND excluded.content = messages.content \
+                THEN messages.tokens \
+            ELSE excluded.tokens END, \
+        tokens_estimated = CASE \
+            WHEN excluded.tokens IS NULL AND messages.tokens_estimated = 1 \
+                AND excluded.content = messages.content \
+                THEN 1 \
+            ELSE 0 END"
+    };
+}
+
 /// Per-source purge counts returned by [`Database::purge_source`].
 #[derive(Debug, Clone, Copy, Default, serde::Serialize)]
 pub struct PurgeStats {
@@ -38,9 +88,87 @@ pub struct IndexerOutboxJob {
     pub attempts: i32,
 }
 
+/// Connection settings for [`Database::open_with`].
+#[derive(Debug, Clone)]
+pub struct DatabaseOptions {
+    /// Pool sizing. The read-only pool is only opened when
+    /// `pools.read_connections` is non-zero.
+    pub pools: PoolConfig,
+    /// SQLCipher passphrase. `None` opens the file as plain SQLite.
+    pub key: Option<String>,
+    /// Pragmas applied to every connection.
+    pub tuning: SqliteTuning,
+    /// Sources and workspaces whose messages are sealed.
+    pub sensitive: SensitiveConfig,
+    /// Weights applied to full-text search scores.
+    pub ranking: RankingConfig,
+    /// Tokenizer of the natural-language index; the index is rebuilt when
+    /// it was built with another one. `None` keeps the existing index.
+    pub tokenizer: Option<SearchTokenizer>,
+    /// Capacity and retries of the sync write queue.
+    pub ingest_queue: IngestQueueConfig,
+    /// How workspaces are normalized before they are stored.
+    pub workspace_paths: WorkspacePathConfig,
+}
+
+impl Default for DatabaseOptions {
+    fn default() -> Self {
+        Self {
+            pools: PoolConfig {
+                read_connections: 0,
+                ..PoolConfig::default()
+            },
+            key: None,
+            tuning: SqliteTuning::default(),
+            sensitive: SensitiveConfig::default(),
+            ranking: RankingConfig::default(),
+            tokenizer: None,
+            ingest_queue: IngestQueueConfig::default(),
+            workspace_paths: WorkspacePathConfig::disabled(),
+        }
+    }
+}
+
+impl DatabaseOptions {
+    /// Options for the configured database: pool sizing from
+    /// `[storage.pool]`, pragmas from `[database]` and the key from
+    /// `[database.encryption]`.
+    /// `read_pool` opts long-running processes into the read-only pool.
+    pub fn from_config(config: &Config, read_pool: bool) -> Result<Self> {
+        let mut pools = config.storage.pool.clone();
+        if !read_pool {
+            pools.read_connections = 0;
+        }
+        Ok(Self {
+            pools,
+            key: config.database.encryption.resolve_key()?,
+            tuning: config.database.tuning.resolve(),
+            sensitive: config.sensitive.clone(),
+            ranking: config.search.ranking.clone(),
+            tokenizer: Some(config.search.tokenizer),
+            ingest_queue: config.storage.ingest_queue.clone(),
+            workspace_paths: config.workspace_paths.clone(),
+        })
+    }
+}
+
+/// Where the key that opens sealed messages comes from.
+enum Unsealing {
+    Locked,
+    /// Handed over with [`Database::unlock_sensitive`].
+    Key(Identity),
+    /// The unlock session of the database at this path.
+    Session(PathBuf),
+}
+
 /// Database handle for hstry.
 pub struct Database {
     pool: SqlitePool,
+    /// Optional read-only pool for search and listing queries. When absent,
+    /// reads share the writer pool.
+    read_pool: Option<SqlitePool>,
+    /// Pragmas the connections were opened with; restored after bulk reseed.
+    tuning: SqliteTuning,
     /// Whether to append to `message_events` on every insert. See
     /// `StorageConfig::message_events::enabled` (trx-aa3m).
     message_events_enabled: AtomicBool,
@@ -50,6 +178,65 @@ pub struct Database {
     /// transactions avoids wasting the busy timeout on writer contention while
     /// retaining the pool's concurrent WAL readers.
     ingest_writer: Mutex<()>,
+    /// `[sensitive]` rules choosing the conversations whose messages are
+    /// sealed.
+    sensitive: SensitiveConfig,
+    /// Key new sensitive messages are sealed to; `None` until
+    /// `hstry sensitive init`.
+    sealing_key: RwLock<Option<Recipient>>,
+    /// Where the key that opens sealed messages on read comes from.
+    unsealing_key: RwLock<Unsealing>,
+    /// Nice-mode pacing of syncs and imports; `None` writes at full speed.
+    throttle: RwLock<Option<Arc<Throttle>>>,
+    /// `[search.ranking]` weights applied to full-text scores.
+    ranking: RankingConfig,
+    /// Tokenizer of `messages_fts`: the configured one until the schema is
+    /// checked, then the one the index was built with.
+    tokenizer: RwLock<Option<SearchTokenizer>>,
+    /// `[storage.ingest_queue]` settings for sync writes.
+    ingest_queue: IngestQueueConfig,
+    /// `[workspace_paths]` rules applied to stored workspaces.
+    workspace_paths: WorkspacePathConfig,
+}
+
+/// Refuse to continue when a key was supplied but SQLite lacks SQLCipher.
+///
+/// Plain SQLite ignores `PRAGMA key`, which would silently store chat history
+/// unencrypted. Probes an in-memory connection so the database file is never
+/// touched by a build that cannot encrypt it.
+async fn ensure_sqlcipher() -> Result<()> {
+    let mut conn = SqliteConnectOptions::from_str("sqlite::memory:")?
+        .connect()
+        .await?;
+    let version: Option<(String,)> = sqlx::query_as("PRAGMA cipher_version")
+        .fetch_optional(&mut conn)
+        .await?;
+    conn.close().await?;
+    match version {
+        Some((v,)) if !v.is_empty() => Ok(()),
+        _ => Err(Error::Config(
+            "database encryption is enabled but hstry was built without SQLCipher \
+             (rebuild with the `sqlcipher` feature)"
+                .to_string(),
+        )),
+    }
+}
+
+/// Whether the file at `path` is plain SQLite: empty or starting with the
+/// SQLite header. An SQLCipher database has no readable header.
+pub fn is_plain_sqlite(path: &Path) -> std::io::Result<bool> {
+    use std::io::Read as _;
+
+    let mut header = [0u8; 16];
+    let mut file = std::fs::File::open(path)?;
+    let mut read = 0;
+    while read < header.len() {
+        match file.read(&mut header[read..])? {
+            0 => break,
+            n => read += n,
+        }
+    }
+    Ok(read == 0 || header == *b"SQLite format 3\0")
 }
 
 /// Normalize a source path for consistent comparison.
@@ -61,6 +248,44 @@ fn normalize_source_path(path: Option<&String>) -> Option<String> {
 impl Database {
     /// Open or create a database at the given path.
     pub async fn open(path: &Path) -> Result<Self> {
+        Self::open_with(path, &DatabaseOptions::default()).await
+    }
+
+    /// Open the database described by `config`, applying `[database]` tuning
+    /// and encryption. Sensitive messages are readable while an
+    /// `hstry sensitive unlock` session is open.
+    pub async fn open_config(config: &Config) -> Result<Self> {
+        let db = Self::open_with(
+            &config.database.path,
+            &DatabaseOptions::from_config(config, false)?,
+        )
+        .await?;
+        db.follow_unlock_session(&config.database.path);
+        Ok(db)
+    }
+
+    /// Open or create the cold-storage archive database for `config`.
+    ///
+    /// The archive shares the main database's schema, encryption and tuning,
+    /// so archived conversations stay readable with the regular queries.
+    /// It shares the main database's sensitive key and unlock session too.
+    pub async fn open_archive(config: &Config) -> Result<Self> {
+        let db = Self::open_with(
+            &config.archive_path(),
+            &DatabaseOptions::from_config(config, false)?,
+        )
+        .await?;
+        db.follow_unlock_session(&config.database.path);
+        Ok(db)
+    }
+
+    /// Open or create a database with explicit pool sizing and encryption.
+    ///
+    /// When `pools.read_connections` is non-zero, a separate read-only pool is
+    /// opened after migrations so heavy search traffic does not compete with
+    /// sync writes for pool slots.
+    pub async fn open_with(path: &Path, opts: &DatabaseOptions) -> Result<Self> {
+        let pools = &opts.pools;
         let parent = path.parent().unwrap_or(Path::new("."));
         if !parent.exists() {
             std::fs::create_dir_all(parent)?;
@@ -69,31 +294,95 @@ impl Database {
         let options = SqliteConnectOptions::from_str(&format!("sqlite:{}", path.display()))?
             .create_if_missing(true)
             .journal_mode(sqlx::sqlite::SqliteJournalMode::Wal)
-            .busy_timeout(Duration::from_secs(30))
+            .busy_timeout(Duration::from_millis(opts.tuning.busy_timeout_ms))
+            .synchronous(SqliteSynchronous::from_str(
+                opts.tuning.synchronous.as_str(),
+            )?)
+            .pragma("cache_size", opts.tuning.cache_size.to_string())
+            .pragma("mmap_size", opts.tuning.mmap_size.to_string())
+            .pragma(
+                "wal_autocheckpoint",
+                opts.tuning.wal_autocheckpoint.to_string(),
+            )
             .foreign_keys(true);
+        if opts.key.is_some() {
+            ensure_sqlcipher().await?;
+        }
+        let options = match &opts.key {
+            // `key` must be the first statement on every connection; sqlx
+            // orders it ahead of the other pragmas.
+            Some(key) => options.pragma("key", format!("'{}'", key.replace('\'', "''"))),
+            None => options,
+        };
 
         let pool = SqlitePoolOptions::new()
-            .max_connections(5)
-            .connect_with(options)
+            .max_connections(pools.max_connections.max(1))
+            .connect_with(options.clone())
             .await?;
 
-        let db = Self {
+        let mut db = Self {
             pool,
+            read_pool: None,
+            tuning: opts.tuning,
             // Default off to preserve the trx-aa3m contract: non-event consumers
             // pay no overhead unless the operator opts in.
             message_events_enabled: AtomicBool::new(false),
             indexer_outbox_enabled: AtomicBool::new(false),
             ingest_writer: Mutex::new(()),
+            sensitive: opts.sensitive.clone(),
+            sealing_key: RwLock::new(None),
+            unsealing_key: RwLock::new(Unsealing::Locked),
+            throttle: RwLock::new(None),
+            ranking: opts.ranking.clone(),
+            tokenizer: RwLock::new(opts.tokenizer),
+            ingest_queue: opts.ingest_queue.clone(),
+            workspace_paths: opts.workspace_paths.clone(),
         };
         db.init().await?;
+        let recipient = db.sensitive_recipient().await?;
+        *db.sealing_key
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = recipient;
+
+        if pools.read_connections > 0 {
+            // The writer has already switched the file to WAL, so every
+            // read-only connection sees a consistent committed snapshot
+            // without blocking (or being blocked by) the writer.
+            let read_options = options.create_if_missing(false).read_only(true);
+            let read_pool = SqlitePoolOptions::new()
+                .max_connections(pools.read_connections)
+                .connect_with(read_options)
+                .await?;
+            db.read_pool = Some(read_pool);
+        }
         Ok(db)
     }
 
+    /// Pool used for read-only queries.
+    fn reader(&self) -> &SqlitePool {
+        self.read_pool.as_ref().unwrap_or(&self.pool)
+    }
+
+    /// Whether a dedicated read-only pool is open.
+    pub fn has_read_pool(&self) -> bool {
+        self.read_pool.is_some()
+    }
+
     /// Acquire the single-writer gate used by bulk ingestion transactions.
     pub(crate) async fn lock_ingest_writer(&self) -> MutexGuard<'_, ()> {
         self.ingest_writer.lock().await
     }
 
+    /// Capacity and retries for [`crate::ingest_queue`].
+    pub fn ingest_queue_config(&self) -> &IngestQueueConfig {
+        &self.ingest_queue
+    }
+
+    /// `workspace` as it is stored, after the `[workspace_paths]` rules.
+    pub fn normalize_workspace(&self, workspace: Option<&str>) -> Option<String> {
+        crate::workspace_paths::normalize_opt(&self.workspace_paths, workspace)
+    }
+
     /// Toggle the `message_events` append-only log at runtime (trx-aa3m).
     pub fn set_message_events_enabled(&self, enabled: bool) {
         self.message_events_enabled
@@ -106,6 +395,36 @@ impl Database {
             .store(enabled, Ordering::Relaxed);
     }
 
+    /// Pace syncs and imports with `throttle` from now on, or stop pacing
+    /// them with `None`.
+    pub fn set_throttle(&self, throttle: Option<Throttle>) {
+        *self
+            .throttle
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = throttle.map(Arc::new);
+    }
+
+    /// Wait as nice mode asks after a committed batch that wrote `bytes` of
+    /// message content. Returns at once without a throttle.
+    pub async fn pace_writes(&self, bytes: u64) {
+        let throttle = self
+            .throttle
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        if let Some(throttle) = throttle {
+            throttle.pace(bytes).await;
+        }
+    }
+
+    fn tokenizer_is_trigram(&self) -> bool {
+        *self
+            .tokenizer
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            == Some(SearchTokenizer::Trigram)
+    }
+
     /// Initialize schema and run migrations.
     async fn init(&self) -> Result<()> {
         sqlx::raw_sql(SCHEMA).execute(&self.pool).await?;
@@ -263,6 +582,94 @@ impl Database {
                 "013_indexer_outbox_and_events_retention.sql",
                 include_str!("../migrations/013_indexer_outbox_and_events_retention.sql"),
             ),
+            (
+                "014_add_ingested_at_and_read_state.sql",
+                include_str!("../migrations/014_add_ingested_at_and_read_state.sql"),
+            ),
+            (
+                "015_tool_calls_name_index.sql",
+                include_str!("../migrations/015_tool_calls_name_index.sql"),
+            ),
+            (
+                "016_add_message_parent.sql",
+                include_str!("../migrations/016_add_message_parent.sql"),
+            ),
+            (
+                "017_add_conversation_changed_at.sql",
+                include_str!("../migrations/017_add_conversation_changed_at.sql"),
+            ),
+            (
+                "018_add_remote_conflicts.sql",
+                include_str!("../migrations/018_add_remote_conflicts.sql"),
+            ),
+            (
+                "019_add_sync_locks.sql",
+                include_str!("../migrations/019_add_sync_locks.sql"),
+            ),
+            (
+                "020_add_api_tokens.sql",
+                include_str!("../migrations/020_add_api_tokens.sql"),
+            ),
+            (
+                "021_add_change_log.sql",
+                include_str!("../migrations/021_add_change_log.sql"),
+            ),
+            (
+                "022_add_llm_usage.sql",
+                include_str!("../migrations/022_add_llm_usage.sql"),
+            ),
+            (
+                "023_add_sensitive.sql",
+                include_str!("../migrations/023_add_sensitive.sql"),
+            ),
+            (
+                "024_add_access_log.sql",
+                include_str!("../migrations/024_add_access_log.sql"),
+            ),
+            (
+                "025_add_export_state.sql",
+                include_str!("../migrations/025_add_export_state.sql"),
+            ),
+            (
+                "026_add_saved_searches.sql",
+                include_str!("../migrations/026_add_saved_searches.sql"),
+            ),
+            (
+                "027_add_decisions.sql",
+                include_str!("../migrations/027_add_decisions.sql"),
+            ),
+            (
+                "028_add_workspaces.sql",
+                include_str!("../migrations/028_add_workspaces.sql"),
+            ),
+            (
+                "029_add_starred.sql",
+                include_str!("../migrations/029_add_starred.sql"),
+            ),
+            (
+                "030_add_generated_titles.sql",
+                include_str!("../migrations/030_add_generated_titles.sql"),
+            ),
+            (
+                "031_add_estimated_tokens.sql",
+                include_str!("../migrations/031_add_estimated_tokens.sql"),
+            ),
+            (
+                "032_add_related_conversations.sql",
+                include_str!("../migrations/032_add_related_conversations.sql"),
+            ),
+            (
+                "033_add_trash.sql",
+                include_str!("../migrations/033_add_trash.sql"),
+            ),
+            (
+                "034_add_message_revisions.sql",
+                include_str!("../migrations/034_add_message_revisions.sql"),
+            ),
+            (
+                "035_add_saved_snapshots.sql",
+                include_str!("../migrations/035_add_saved_snapshots.sql"),
+            ),
         ];
 
         for (filename, sql) in migrations {
@@ -398,6 +805,9 @@ impl Database {
 
     /// Close the database.
     pub async fn close(self) {
+        if let Some(read_pool) = &self.read_pool {
+            read_pool.close().await;
+        }
         self.pool.close().await;
     }
 
@@ -574,14 +984,27 @@ impl Database {
             readable_id = Some(self.assign_readable_id(conv.id).await?);
         }
 
+        // The COALESCE below stores it under the workspace it is an alias of.
+        let workspace = self.normalize_workspace(conv.workspace.as_deref());
         sqlx::query(
             r"
             INSERT INTO conversations (id, source_id, external_id, readable_id, platform_id, title, created_at, updated_at, model, provider, workspace, tokens_in, tokens_out, cost_usd, metadata, harness, version, message_count, parent_conversation_id, parent_message_idx, fork_type)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 0, 0, ?, ?, ?)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, COALESCE((SELECT w.path FROM workspaces w, json_each(w.aliases) a WHERE a.value = ? LIMIT 1), ?), ?, ?, ?, ?, ?, 0, 0, ?, ?, ?)
             ON CONFLICT(source_id, external_id) DO UPDATE SET
                 readable_id = COALESCE(excluded.readable_id, conversations.readable_id),
                 platform_id = COALESCE(NULLIF(excluded.platform_id, ''), NULLIF(conversations.platform_id, '')),
-                title = excluded.title,
+                title = CASE
+                    WHEN conversations.title_generated = 2
+                        OR (conversations.title_generated = 1 AND COALESCE(excluded.title, '') = '')
+                    THEN conversations.title
+                    ELSE excluded.title
+                END,
+                title_generated = CASE
+                    WHEN conversations.title_generated = 2
+                        OR COALESCE(excluded.title, '') IN ('', conversations.title)
+                    THEN conversations.title_generated
+                    ELSE 0
+                END,
                 updated_at = excluded.updated_at,
                 model = excluded.model,
                 provider = excluded.provider,
@@ -607,7 +1030,8 @@ impl Database {
         .bind(conv.updated_at.map(|dt| dt.timestamp()))
         .bind(&conv.model)
         .bind(&conv.provider)
-        .bind(&conv.workspace)
+        .bind(&workspace)
+        .bind(&workspace)
         .bind(conv.tokens_in)
         .bind(conv.tokens_out)
         .bind(conv.cost_usd)
@@ -626,7 +1050,7 @@ impl Database {
         &self,
         opts: ListConversationsOptions,
     ) -> Result<Vec<Conversation>> {
-        let mut sql = String::from("SELECT * FROM conversations WHERE 1=1");
+        let mut sql = String::from("SELECT * FROM conversations c WHERE 1=1");
 
         if opts.source_id.is_some() {
             sql.push_str(" AND (source_id = ? OR source_id LIKE ?)");
@@ -644,12 +1068,14 @@ impl Database {
         if opts.before.is_some() {
             sql.push_str(" AND created_at < ?");
         }
+        if opts.ingested_after.is_some() {
+            sql.push_str(" AND ingested_at > ?");
+        }
+        let attribute_binds = push_conversation_attribute_filters(&mut sql, &opts);
 
         sql.push_str(" ORDER BY COALESCE(updated_at, created_at) DESC");
 
-        if let Some(limit) = opts.limit {
-            let _ = write!(sql, " LIMIT {limit}");
-        }
+        push_limit_offset(&mut sql, opts.limit, opts.offset);
 
         let mut query = sqlx::query(&sql);
 
@@ -665,8 +1091,14 @@ impl Database {
         if let Some(before) = opts.before {
             query = query.bind(before.timestamp());
         }
+        if let Some(ingested_after) = opts.ingested_after {
+            query = query.bind(ingested_after.timestamp());
+        }
+        for value in attribute_binds {
+            query = query.bind(value);
+        }
 
-        let rows = query.fetch_all(&self.pool).await?;
+        let rows = query.fetch_all(self.reader()).await?;
 
         let mut convs = Vec::new();
         for row in rows {
@@ -681,7 +1113,7 @@ impl Database {
         opts: ListConversationsOptions,
     ) -> Result<Vec<ConversationPreview>> {
         let mut sql = String::from(
-            "SELECT c.*, (SELECT content FROM messages m WHERE m.conversation_id = c.id AND m.role = 'user' ORDER BY m.idx ASC LIMIT 1) AS first_user_message FROM conversations c WHERE 1=1",
+            "SELECT c.*, (SELECT content FROM messages m WHERE m.conversation_id = c.id AND m.role = 'user' ORDER BY m.idx ASC LIMIT 1) AS first_user_message, (SELECT display_name FROM workspaces w WHERE w.path = c.workspace) AS workspace_name FROM conversations c WHERE 1=1",
         );
 
         if opts.source_id.is_some() {
@@ -700,12 +1132,14 @@ impl Database {
         if opts.before.is_some() {
             sql.push_str(" AND c.created_at < ?");
         }
+        if opts.ingested_after.is_some() {
+            sql.push_str(" AND c.ingested_at > ?");
+        }
+        let attribute_binds = push_conversation_attribute_filters(&mut sql, &opts);
 
         sql.push_str(" ORDER BY COALESCE(c.updated_at, c.created_at) DESC");
 
-        if let Some(limit) = opts.limit {
-            let _ = write!(sql, " LIMIT {limit}");
-        }
+        push_limit_offset(&mut sql, opts.limit, opts.offset);
 
         let mut query = sqlx::query(&sql);
 
@@ -721,14 +1155,21 @@ impl Database {
         if let Some(before) = opts.before {
             query = query.bind(before.timestamp());
         }
+        if let Some(ingested_after) = opts.ingested_after {
+            query = query.bind(ingested_after.timestamp());
+        }
+        for value in attribute_binds {
+            query = query.bind(value);
+        }
 
-        let rows = query.fetch_all(&self.pool).await?;
+        let rows = query.fetch_all(self.reader()).await?;
 
         let mut previews = Vec::new();
         for row in rows {
             previews.push(ConversationPreview {
                 conversation: conversation_from_row(&row),
                 first_user_message: row.get("first_user_message"),
+                workspace_name: row.get("workspace_name"),
             });
         }
 
@@ -743,7 +1184,8 @@ impl Database {
         let mut sql = String::from(
             "SELECT c.*, \
              COALESCE(cs.message_count, (SELECT COUNT(*) FROM messages m WHERE m.conversation_id = c.id)) AS message_count, \
-             COALESCE(cs.first_user_message, (SELECT content FROM messages m WHERE m.conversation_id = c.id AND m.role = 'user' ORDER BY m.idx ASC LIMIT 1)) AS first_user_message \
+             COALESCE(cs.first_user_message, (SELECT content FROM messages m WHERE m.conversation_id = c.id AND m.role = 'user' ORDER BY m.idx ASC LIMIT 1)) AS first_user_message, \
+             (cs.conversation_id IS NULL OR cs.message_count != (SELECT COUNT(*) FROM messages m WHERE m.conversation_id = c.id)) AS summary_stale \
              FROM conversations c \
              LEFT JOIN conversation_summary_cache cs ON cs.conversation_id = c.id \
              WHERE 1=1",
@@ -765,12 +1207,14 @@ impl Database {
         if opts.before.is_some() {
             sql.push_str(" AND c.created_at < ?");
         }
+        if opts.ingested_after.is_some() {
+            sql.push_str(" AND c.ingested_at > ?");
+        }
+        let attribute_binds = push_conversation_attribute_filters(&mut sql, &opts);
 
         sql.push_str(" ORDER BY COALESCE(c.updated_at, c.created_at) DESC");
 
-        if let Some(limit) = opts.limit {
-            let _ = write!(sql, " LIMIT {limit}");
-        }
+        push_limit_offset(&mut sql, opts.limit, opts.offset);
 
         let mut query = sqlx::query(&sql);
 
@@ -786,14 +1230,21 @@ impl Database {
         if let Some(before) = opts.before {
             query = query.bind(before.timestamp());
         }
+        if let Some(ingested_after) = opts.ingested_after {
+            query = query.bind(ingested_after.timestamp());
+        }
+        for value in attribute_binds {
+            query = query.bind(value);
+        }
 
-        let rows = query.fetch_all(&self.pool).await?;
+        let rows = query.fetch_all(self.reader()).await?;
         let mut summaries = Vec::with_capacity(rows.len());
         for row in rows {
             summaries.push(ConversationSummary {
                 conversation: conversation_from_row(&row),
                 message_count: row.get::<i64, _>("message_count"),
                 first_user_message: row.get::<Option<String>, _>("first_user_message"),
+                stale: row.get::<bool, _>("summary_stale"),
             });
         }
 
@@ -867,15 +1318,46 @@ impl Database {
             query = query.bind(conversation_id);
         }
 
-        let row = query.fetch_optional(&self.pool).await?;
+        let row = query.fetch_optional(self.reader()).await?;
         Ok(row.map(|row| conversation_from_row(&row)))
     }
 
+    /// Conversations whose UUID, external ID or platform ID starts with
+    /// `prefix`, or whose readable ID is `prefix` plus more dash-separated
+    /// words. Newest first, at most `limit`.
+    pub async fn find_conversations_by_prefix(
+        &self,
+        prefix: &str,
+        limit: i64,
+    ) -> Result<Vec<Conversation>> {
+        if prefix.is_empty() {
+            return Ok(Vec::new());
+        }
+        // substr() instead of LIKE, so `%` and `_` in IDs match literally.
+        let rows = sqlx::query(
+            r"
+            SELECT * FROM conversations
+            WHERE substr(id, 1, length(?1)) = ?1
+               OR substr(external_id, 1, length(?1)) = ?1
+               OR substr(platform_id, 1, length(?1)) = ?1
+               OR substr(readable_id, 1, length(?2)) = ?2
+            ORDER BY created_at DESC
+            LIMIT ?3
+            ",
+        )
+        .bind(prefix)
+        .bind(format!("{prefix}-"))
+        .bind(limit)
+        .fetch_all(self.reader())
+        .await?;
+        Ok(rows.iter().map(conversation_from_row).collect())
+    }
+
     /// Get a conversation by ID.
     pub async fn get_conversation(&self, id: Uuid) -> Result<Option<Conversation>> {
         let row = sqlx::query("SELECT * FROM conversations WHERE id = ?")
             .bind(id.to_string())
-            .fetch_optional(&self.pool)
+            .fetch_optional(self.reader())
             .await?;
 
         match row {
@@ -884,6 +1366,43 @@ impl Database {
         }
     }
 
+    /// Get several conversations by ID. Unknown IDs are skipped.
+    pub async fn get_conversations(&self, ids: &[Uuid]) -> Result<Vec<Conversation>> {
+        let mut conversations = Vec::with_capacity(ids.len());
+        for chunk in ids.chunks(500) {
+            let placeholders = vec!["?"; chunk.len()].join(",");
+            let sql = format!("SELECT * FROM conversations WHERE id IN ({placeholders})");
+            let mut query = sqlx::query(&sql);
+            for id in chunk {
+                query = query.bind(id.to_string());
+            }
+            let rows = query.fetch_all(self.reader()).await?;
+            conversations.extend(rows.iter().map(conversation_from_row));
+        }
+        Ok(conversations)
+    }
+
+    /// Attach each hit's conversation (see [`SearchHit::conversation`]).
+    pub async fn expand_search_hits<'a>(
+        &self,
+        hits: impl IntoIterator<Item = &'a mut SearchHit>,
+    ) -> Result<()> {
+        let hits: Vec<&mut SearchHit> = hits.into_iter().collect();
+        let mut ids: Vec<Uuid> = hits.iter().map(|hit| hit.conversation_id).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        let conversations: HashMap<Uuid, Conversation> = self
+            .get_conversations(&ids)
+            .await?
+            .into_iter()
+            .map(|conv| (conv.id, conv))
+            .collect();
+        for hit in hits {
+            hit.conversation = conversations.get(&hit.conversation_id).cloned();
+        }
+        Ok(())
+    }
+
     /// Get conversation ID by source_id + external_id (or platform_id).
     pub async fn get_conversation_id(
         &self,
@@ -948,9 +1467,6 @@ impl Database {
     ///
     /// Uses the deterministic `adjective-noun` base from [`readable_id`],
     /// appending a `-N` suffix if the base already exists. The suffix search
-    /// starts at a UUID-derived offset so the final id is stable for a given
-    /// UUID regardless of ingestion order.
-
     /// starts at a UUID-derived offset so the final id is stable for a given
     /// UUID regardless of ingestion order.
     async fn assign_readable_id(&self, uuid: Uuid) -> Result<String> {
@@ -991,106 +1507,998 @@ impl Database {
 
     /// Get conversation count.
     pub async fn count_conversations(&self) -> Result<i64> {
-        let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM conversations")
-            .fetch_one(&self.pool)
-            .await?;
+        let count: (i64,) =
+            sqlx::query_as("SELECT COUNT(*) FROM conversations WHERE deleted_at IS NULL")
+                .fetch_one(self.reader())
+                .await?;
         Ok(count.0)
     }
 
-    /// Count conversations and messages for a specific source.
-    pub async fn count_source_data(&self, source_id: &str) -> Result<(i64, i64)> {
-        let conv_count: (i64,) =
-            sqlx::query_as("SELECT COUNT(*) FROM conversations WHERE source_id = ?")
-                .bind(source_id)
-                .fetch_one(&self.pool)
-                .await?;
-
-        let msg_count: (i64,) = sqlx::query_as(
-            "SELECT COUNT(*) FROM messages m
-             JOIN conversations c ON m.conversation_id = c.id
-             WHERE c.source_id = ?",
+    /// Conversation counts per source, for badges that shouldn't load the
+    /// conversations themselves. Sources without conversations are omitted.
+    pub async fn count_conversations_by_source(&self) -> Result<Vec<(String, i64)>> {
+        let rows: Vec<(String, i64)> = sqlx::query_as(
+            "SELECT source_id, COUNT(*) FROM conversations WHERE deleted_at IS NULL
+             GROUP BY source_id ORDER BY source_id",
         )
-        .bind(source_id)
-        .fetch_one(&self.pool)
+        .fetch_all(self.reader())
         .await?;
+        Ok(rows)
+    }
 
-        Ok((conv_count.0, msg_count.0))
+    /// Conversation counts per workspace. Conversations without a workspace
+    /// and trashed ones are omitted.
+    pub async fn count_conversations_by_workspace(&self) -> Result<Vec<(String, i64)>> {
+        let rows: Vec<(String, i64)> = sqlx::query_as(
+            "SELECT workspace, COUNT(*) FROM conversations
+             WHERE workspace IS NOT NULL AND workspace != '' AND deleted_at IS NULL
+             GROUP BY workspace ORDER BY workspace",
+        )
+        .fetch_all(self.reader())
+        .await?;
+        Ok(rows)
     }
 
-    /// Get detailed statistics per source.
-    pub async fn get_source_stats(&self) -> Result<Vec<SourceStats>> {
-        let rows = sqlx::query(
-            r"
-            SELECT
-                s.id as source_id,
-                s.adapter,
-                COUNT(DISTINCT c.id) as conversations,
-                COUNT(m.id) as messages,
-                MIN(c.created_at) as oldest,
-                MAX(c.created_at) as newest,
-                s.last_sync_at
-            FROM sources s
-            LEFT JOIN conversations c ON c.source_id = s.id
-            LEFT JOIN messages m ON m.conversation_id = c.id
-            GROUP BY s.id, s.adapter, s.last_sync_at
-            ORDER BY conversations DESC
-            ",
+    /// Apply the `[workspace_paths]` rules to the workspaces already stored
+    /// on conversations, decisions and workspace metadata. Returns the
+    /// workspaces that change; with `apply` false nothing is written.
+    pub async fn normalize_stored_workspaces(&self, apply: bool) -> Result<Vec<WorkspaceChange>> {
+        // Trashed conversations are rewritten too, so they match once restored.
+        let stored: Vec<(String, i64)> = sqlx::query_as(
+            "SELECT workspace, COUNT(*) FROM conversations
+             WHERE workspace IS NOT NULL AND workspace != ''
+             GROUP BY workspace ORDER BY workspace",
         )
-        .fetch_all(&self.pool)
+        .fetch_all(self.reader())
         .await?;
+        let changes: Vec<WorkspaceChange> = stored
+            .into_iter()
+            .filter_map(|(from, conversations)| {
+                let to = crate::workspace_paths::normalize(&self.workspace_paths, &from);
+                (to != from).then_some(WorkspaceChange {
+                    from,
+                    to,
+                    conversations,
+                })
+            })
+            .collect();
+        if !apply || changes.is_empty() {
+            return Ok(changes);
+        }
 
-        let mut stats = Vec::new();
-        for row in rows {
-            stats.push(SourceStats {
-                source_id: row.get("source_id"),
-                adapter: row.get("adapter"),
-                conversations: row.get::<i64, _>("conversations"),
-                messages: row.get::<i64, _>("messages"),
-                oldest: row
-                    .get::<Option<i64>, _>("oldest")
-                    .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0))
-                    .map(|dt| dt.with_timezone(&Utc)),
-                newest: row
-                    .get::<Option<i64>, _>("newest")
-                    .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0))
-                    .map(|dt| dt.with_timezone(&Utc)),
-                last_sync_at: row
-                    .get::<Option<i64>, _>("last_sync_at")
-                    .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0))
-                    .map(|dt| dt.with_timezone(&Utc)),
-            });
+        let mut tx = self.pool.begin().await?;
+        for change in &changes {
+            sqlx::query(
+                "UPDATE conversations SET workspace = ?, version = version + 1
+                 WHERE workspace = ?",
+            )
+            .bind(&change.to)
+            .bind(&change.from)
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query("UPDATE decisions SET workspace = ? WHERE workspace = ?")
+                .bind(&change.to)
+                .bind(&change.from)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("UPDATE OR IGNORE workspaces SET path = ? WHERE path = ?")
+                .bind(&change.to)
+                .bind(&change.from)
+                .execute(&mut *tx)
+                .await?;
         }
-        Ok(stats)
+        tx.commit().await?;
+        Ok(changes)
     }
 
-    /// Get activity stats (conversations per day/week/month).
-    pub async fn get_activity_stats(&self, days: i64) -> Result<ActivityStats> {
-        let cutoff = Utc::now() - chrono::Duration::days(days);
-        let cutoff_ts = cutoff.timestamp();
+    /// Every workspace that has conversations or stored metadata, by path.
+    pub async fn list_workspaces(&self) -> Result<Vec<Workspace>> {
+        let rows = sqlx::query(
+            "SELECT p.path AS path, w.display_name AS display_name, w.git_remote AS git_remote,
+                    w.aliases AS aliases, COALESCE(c.n, 0) AS conversations
+             FROM (SELECT workspace AS path FROM conversations
+                   WHERE workspace IS NOT NULL AND workspace != '' AND deleted_at IS NULL
+                   UNION SELECT path FROM workspaces) p
+             LEFT JOIN workspaces w ON w.path = p.path
+             LEFT JOIN (SELECT workspace, COUNT(*) AS n FROM conversations
+                        WHERE deleted_at IS NULL GROUP BY workspace) c
+                    ON c.workspace = p.path
+             ORDER BY p.path",
+        )
+        .fetch_all(self.reader())
+        .await?;
+        Ok(rows
+            .iter()
+            .map(|row| Workspace {
+                path: row.get("path"),
+                display_name: row.get("display_name"),
+                git_remote: row.get("git_remote"),
+                aliases: row
+                    .get::<Option<&str>, _>("aliases")
+                    .and_then(|aliases| serde_json::from_str(aliases).ok())
+                    .unwrap_or_default(),
+                conversations: row.get("conversations"),
+            })
+            .collect())
+    }
 
-        let today_count: (i64,) =
-            sqlx::query_as("SELECT COUNT(*) FROM conversations WHERE created_at >= ?")
-                .bind((Utc::now() - chrono::Duration::days(1)).timestamp())
-                .fetch_one(&self.pool)
-                .await?;
+    /// Display names of the workspaces that have one, by path.
+    pub async fn workspace_names(&self) -> Result<HashMap<String, String>> {
+        let rows: Vec<(String, String)> = sqlx::query_as(
+            "SELECT path, display_name FROM workspaces
+             WHERE display_name IS NOT NULL AND display_name != ''",
+        )
+        .fetch_all(self.reader())
+        .await?;
+        Ok(rows.into_iter().collect())
+    }
 
-        let week_count: (i64,) =
-            sqlx::query_as("SELECT COUNT(*) FROM conversations WHERE created_at >= ?")
-                .bind((Utc::now() - chrono::Duration::days(7)).timestamp())
-                .fetch_one(&self.pool)
+    /// Set the display name of the workspace at `path`; `None` clears it.
+    pub async fn rename_workspace(&self, path: &str, name: Option<&str>) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO workspaces (path, display_name, updated_at) VALUES (?, ?, ?)
+             ON CONFLICT(path) DO UPDATE SET display_name = excluded.display_name,
+             updated_at = excluded.updated_at",
+        )
+        .bind(path)
+        .bind(name)
+        .bind(Utc::now().timestamp())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Record the git remote of the checkout at `path`.
+    pub async fn set_workspace_git_remote(&self, path: &str, remote: &str) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO workspaces (path, git_remote, updated_at) VALUES (?, ?, ?)
+             ON CONFLICT(path) DO UPDATE SET git_remote = excluded.git_remote,
+             updated_at = excluded.updated_at",
+        )
+        .bind(path)
+        .bind(remote)
+        .bind(Utc::now().timestamp())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Move the conversations and decisions of workspace `from` into
+    /// `into`. `from` and its aliases become aliases of `into`, so later
+    /// syncs reporting `from` store under `into` too; `into` keeps its own
+    /// name and remote, taking `from`'s where it has none. Returns the
+    /// number of conversations moved.
+    pub async fn merge_workspaces(&self, from: &str, into: &str) -> Result<u64> {
+        if from == into {
+            return Err(Error::Other(format!(
+                "cannot merge workspace {from} into itself"
+            )));
+        }
+        let mut tx = self.pool.begin().await?;
+        let stored = |path: &str| {
+            sqlx::query_as::<_, (Option<String>, Option<String>, String)>(
+                "SELECT display_name, git_remote, aliases FROM workspaces WHERE path = ?",
+            )
+            .bind(path.to_string())
+        };
+        let source = stored(from).fetch_optional(&mut *tx).await?;
+        let target = stored(into).fetch_optional(&mut *tx).await?;
+
+        let parse =
+            |aliases: &str| -> Vec<String> { serde_json::from_str(aliases).unwrap_or_default() };
+        let mut aliases = target
+            .as_ref()
+            .map(|(_, _, aliases)| parse(aliases))
+            .unwrap_or_default();
+        aliases.push(from.to_string());
+        if let Some((_, _, source_aliases)) = &source {
+            aliases.extend(parse(source_aliases));
+        }
+        aliases.retain(|alias| alias != into);
+        aliases.sort();
+        aliases.dedup();
+
+        let (target_name, target_remote) = target
+            .map(|(name, remote, _)| (name, remote))
+            .unwrap_or_default();
+        let (source_name, source_remote) = source
+            .map(|(name, remote, _)| (name, remote))
+            .unwrap_or_default();
+
+        sqlx::query(
+            "INSERT INTO workspaces (path, display_name, git_remote, aliases, updated_at)
+             VALUES (?, ?, ?, ?, ?)
+             ON CONFLICT(path) DO UPDATE SET display_name = excluded.display_name,
+             git_remote = excluded.git_remote, aliases = excluded.aliases,
+             updated_at = excluded.updated_at",
+        )
+        .bind(into)
+        .bind(target_name.or(source_name))
+        .bind(target_remote.or(source_remote))
+        .bind(serde_json::to_string(&aliases)?)
+        .bind(Utc::now().timestamp())
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("DELETE FROM workspaces WHERE path = ?")
+            .bind(from)
+            .execute(&mut *tx)
+            .await?;
+        let moved = sqlx::query(
+            "UPDATE conversations SET workspace = ?, version = version + 1 WHERE workspace = ?",
+        )
+        .bind(into)
+        .bind(from)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        sqlx::query("UPDATE decisions SET workspace = ? WHERE workspace = ?")
+            .bind(into)
+            .bind(from)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(moved)
+    }
+
+    /// Count conversations created or updated after `since`.
+    pub async fn count_unread_since(&self, since: chrono::DateTime<Utc>) -> Result<i64> {
+        let count: (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM conversations WHERE COALESCE(updated_at, created_at) > ?",
+        )
+        .bind(since.timestamp())
+        .fetch_one(self.reader())
+        .await?;
+        Ok(count.0)
+    }
+
+    /// Whether any conversation was created or updated after `since`. Stops at
+    /// the first match, so it is cheaper than [`Self::count_unread_since`].
+    pub async fn has_new_since(&self, since: chrono::DateTime<Utc>) -> Result<bool> {
+        let row: Option<(i64,)> = sqlx::query_as(
+            "SELECT 1 FROM conversations WHERE COALESCE(updated_at, created_at) > ? LIMIT 1",
+        )
+        .bind(since.timestamp())
+        .fetch_optional(self.reader())
+        .await?;
+        Ok(row.is_some())
+    }
+
+    /// Sequence number of the newest `change_log` entry, or 0 when the log is
+    /// empty. Readers poll this to notice writes by other processes.
+    pub async fn latest_change_seq(&self) -> Result<i64> {
+        let (seq,): (i64,) = sqlx::query_as("SELECT COALESCE(MAX(seq), 0) FROM change_log")
+            .fetch_one(self.reader())
+            .await?;
+        Ok(seq)
+    }
+
+    /// `change_log` entries after `seq`, oldest first.
+    pub async fn changes_since(&self, seq: i64, limit: i64) -> Result<Vec<ChangeLogEntry>> {
+        let rows: Vec<(i64, String, String, String, i64)> = sqlx::query_as(
+            "SELECT seq, entity, entity_id, kind, changed_at FROM change_log \
+             WHERE seq > ? ORDER BY seq LIMIT ?",
+        )
+        .bind(seq)
+        .bind(limit)
+        .fetch_all(self.reader())
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(
+                |(seq, entity, entity_id, kind, changed_at)| ChangeLogEntry {
+                    seq,
+                    entity,
+                    entity_id,
+                    kind,
+                    changed_at: chrono::DateTime::from_timestamp(changed_at, 0)
+                        .unwrap_or_else(Utc::now),
+                },
+            )
+            .collect())
+    }
+
+    /// Drop `change_log` entries recorded before `before`. Returns the number
+    /// of entries removed.
+    pub async fn prune_change_log(&self, before: chrono::DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM change_log WHERE changed_at < ?")
+            .bind(before.timestamp())
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Read watermark for `profile`: conversations ingested after it are new.
+    /// The first call for a profile starts the watermark at the current time,
+    /// so an existing history is not reported as new all at once.
+    pub async fn last_seen_or_init(&self, profile: &str) -> Result<chrono::DateTime<Utc>> {
+        sqlx::query("INSERT OR IGNORE INTO read_state (profile, last_seen_at) VALUES (?, ?)")
+            .bind(profile)
+            .bind(Utc::now().timestamp())
+            .execute(&self.pool)
+            .await?;
+        let (ts,): (i64,) = sqlx::query_as("SELECT last_seen_at FROM read_state WHERE profile = ?")
+            .bind(profile)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(chrono::DateTime::from_timestamp(ts, 0).unwrap_or_else(Utc::now))
+    }
+
+    /// Advance the read watermark for `profile` to `seen_at`. The watermark
+    /// never moves backwards.
+    pub async fn set_last_seen(&self, profile: &str, seen_at: chrono::DateTime<Utc>) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO read_state (profile, last_seen_at) VALUES (?, ?) \
+             ON CONFLICT(profile) DO UPDATE SET last_seen_at = MAX(last_seen_at, excluded.last_seen_at)",
+        )
+        .bind(profile)
+        .bind(seen_at.timestamp())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Mark every conversation as read for `profile`.
+    pub async fn mark_all_read(&self, profile: &str) -> Result<()> {
+        self.set_last_seen(profile, Utc::now()).await
+    }
+
+    /// When `target` was last exported as `format` by an incremental export.
+    pub async fn export_mark(
+        &self,
+        target: &str,
+        format: &str,
+    ) -> Result<Option<chrono::DateTime<Utc>>> {
+        let row: Option<(i64,)> =
+            sqlx::query_as("SELECT exported_at FROM export_state WHERE target = ? AND format = ?")
+                .bind(target)
+                .bind(format)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(row.and_then(|(ts,)| chrono::DateTime::from_timestamp(ts, 0)))
+    }
+
+    /// Record that `target` holds every conversation as of `exported_at`.
+    pub async fn set_export_mark(
+        &self,
+        target: &str,
+        format: &str,
+        exported_at: chrono::DateTime<Utc>,
+    ) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO export_state (target, format, exported_at) VALUES (?, ?, ?) \
+             ON CONFLICT(target, format) DO UPDATE SET exported_at = excluded.exported_at",
+        )
+        .bind(target)
+        .bind(format)
+        .bind(exported_at.timestamp())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Store `search` under its name, replacing a search of the same name.
+    pub async fn save_search(&self, search: &SavedSearch) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO saved_searches (name, query, filters, created_at) VALUES (?, ?, ?, ?) \
+             ON CONFLICT(name) DO UPDATE SET query = excluded.query, filters = excluded.filters, \
+             created_at = excluded.created_at",
+        )
+        .bind(&search.name)
+        .bind(&search.query)
+        .bind(serde_json::to_string(&search.filters)?)
+        .bind(search.created_at.timestamp())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// The saved search called `name`.
+    pub async fn get_saved_search(&self, name: &str) -> Result<Option<SavedSearch>> {
+        let row = sqlx::query("SELECT * FROM saved_searches WHERE name = ?")
+            .bind(name)
+            .fetch_optional(self.reader())
+            .await?;
+        Ok(row.as_ref().map(saved_search_from_row))
+    }
+
+    /// Saved searches by name.
+    pub async fn list_saved_searches(&self) -> Result<Vec<SavedSearch>> {
+        let rows = sqlx::query("SELECT * FROM saved_searches ORDER BY name")
+            .fetch_all(self.reader())
+            .await?;
+        Ok(rows.iter().map(saved_search_from_row).collect())
+    }
+
+    /// Delete the saved search called `name`. Returns whether it existed.
+    pub async fn delete_saved_search(&self, name: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM saved_searches WHERE name = ?")
+            .bind(name)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Conversations in `workspace` (a `LIKE` pattern or an exact path; all
+    /// when `None`) never scanned for decisions or changed since their scan.
+    /// Sensitive conversations are never scanned: decisions are stored in
+    /// plaintext.
+    pub async fn conversations_to_scan_for_decisions(
+        &self,
+        workspace: Option<&str>,
+    ) -> Result<Vec<Conversation>> {
+        let mut sql = String::from(
+            "SELECT c.* FROM conversations c \
+             LEFT JOIN decision_scans s ON s.conversation_id = c.id \
+             WHERE (s.scanned_at IS NULL OR c.changed_at > s.scanned_at) \
+             AND c.sensitive = 0",
+        );
+        if let Some(workspace) = workspace {
+            if is_like_pattern(workspace) {
+                sql.push_str(" AND c.workspace LIKE ?");
+            } else {
+                sql.push_str(" AND c.workspace = ?");
+            }
+        }
+        let mut query = sqlx::query(&sql);
+        if let Some(workspace) = workspace {
+            query = query.bind(workspace);
+        }
+        let rows = query.fetch_all(self.reader()).await?;
+        Ok(rows.iter().map(conversation_from_row).collect())
+    }
+
+    /// Replace the stored decisions of `conv` with `found` and record the
+    /// scan.
+    pub async fn replace_decisions(
+        &self,
+        conv: &Conversation,
+        found: &[FoundDecision],
+    ) -> Result<()> {
+        let id = conv.id.to_string();
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM decisions WHERE conversation_id = ?")
+            .bind(&id)
+            .execute(&mut *tx)
+            .await?;
+        for decision in found {
+            sqlx::query(
+                "INSERT INTO decisions (conversation_id, message_id, workspace, text, decided_at) \
+                 VALUES (?, ?, ?, ?, ?)",
+            )
+            .bind(&id)
+            .bind(decision.message_id.to_string())
+            .bind(&conv.workspace)
+            .bind(&decision.text)
+            .bind(decision.decided_at.unwrap_or(conv.created_at).timestamp())
+            .execute(&mut *tx)
+            .await?;
+        }
+        sqlx::query(
+            "INSERT INTO decision_scans (conversation_id, scanned_at) VALUES (?, ?) \
+             ON CONFLICT(conversation_id) DO UPDATE SET scanned_at = excluded.scanned_at",
+        )
+        .bind(&id)
+        .bind(Utc::now().timestamp())
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Stored decisions in `workspace` (matched like
+    /// [`Self::conversations_to_scan_for_decisions`]), newest first.
+    pub async fn list_decisions(
+        &self,
+        workspace: Option<&str>,
+        limit: Option<usize>,
+    ) -> Result<Vec<Decision>> {
+        let mut sql = String::from(
+            "SELECT d.*, c.title FROM decisions d \
+             JOIN conversations c ON c.id = d.conversation_id WHERE 1=1",
+        );
+        if let Some(workspace) = workspace {
+            if is_like_pattern(workspace) {
+                sql.push_str(" AND d.workspace LIKE ?");
+            } else {
+                sql.push_str(" AND d.workspace = ?");
+            }
+        }
+        sql.push_str(" ORDER BY d.decided_at DESC, d.id DESC");
+        if let Some(limit) = limit {
+            let _ = write!(sql, " LIMIT {limit}");
+        }
+        let mut query = sqlx::query(&sql);
+        if let Some(workspace) = workspace {
+            query = query.bind(workspace);
+        }
+        let rows = query.fetch_all(self.reader()).await?;
+        Ok(rows.iter().map(decision_from_row).collect())
+    }
+
+    /// Ids of conversations ingested after `since`.
+    pub async fn new_conversation_ids(&self, since: chrono::DateTime<Utc>) -> Result<Vec<Uuid>> {
+        let rows: Vec<(String,)> =
+            sqlx::query_as("SELECT id FROM conversations WHERE ingested_at > ?")
+                .bind(since.timestamp())
+                .fetch_all(self.reader())
+                .await?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|(id,)| Uuid::parse_str(&id).ok())
+            .collect())
+    }
+
+    /// Ids of conversations inserted or changed at or after `since`.
+    /// Inclusive, so a caller resuming from a recorded timestamp never
+    /// misses a change made within the same second.
+    pub async fn changed_conversation_ids(
+        &self,
+        since: chrono::DateTime<Utc>,
+    ) -> Result<Vec<Uuid>> {
+        let rows: Vec<(String,)> =
+            sqlx::query_as("SELECT id FROM conversations WHERE changed_at >= ?")
+                .bind(since.timestamp())
+                .fetch_all(self.reader())
+                .await?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|(id,)| Uuid::parse_str(&id).ok())
+            .collect())
+    }
+
+    /// Store a remote sync conflict and return its row id.
+    pub async fn record_remote_conflict(&self, conflict: &RemoteConflict) -> Result<i64> {
+        let result = sqlx::query(
+            "INSERT INTO remote_conflicts (remote, source_id, external_id, title, \
+                 local_conversation_id, duplicate_conversation_id, local_updated_at, \
+                 remote_updated_at, strategy, resolution, detected_at, resolved_at) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&conflict.remote)
+        .bind(&conflict.source_id)
+        .bind(&conflict.external_id)
+        .bind(&conflict.title)
+        .bind(conflict.local_conversation_id.map(|id| id.to_string()))
+        .bind(conflict.duplicate_conversation_id.map(|id| id.to_string()))
+        .bind(conflict.local_updated_at.map(|dt| dt.timestamp()))
+        .bind(conflict.remote_updated_at.map(|dt| dt.timestamp()))
+        .bind(&conflict.strategy)
+        .bind(&conflict.resolution)
+        .bind(conflict.detected_at.timestamp())
+        .bind(conflict.resolved_at.map(|dt| dt.timestamp()))
+        .execute(&self.pool)
+        .await?;
+        Ok(result.last_insert_rowid())
+    }
+
+    /// Take the sync lock for `source_id` as `pid` on `host`. A lock whose
+    /// heartbeat is older than `stale_before` is taken over. Returns `None`
+    /// once the caller holds the lock, or the holder that kept it.
+    pub async fn try_lock_source_sync(
+        &self,
+        source_id: &str,
+        pid: u32,
+        host: &str,
+        stale_before: chrono::DateTime<Utc>,
+    ) -> Result<Option<SyncLock>> {
+        let now = Utc::now().timestamp();
+        let taken = sqlx::query(
+            "INSERT INTO sync_locks (source_id, pid, host, acquired_at, heartbeat_at) \
+             VALUES (?, ?, ?, ?, ?) \
+             ON CONFLICT(source_id) DO UPDATE SET pid = excluded.pid, host = excluded.host, \
+                 acquired_at = excluded.acquired_at, heartbeat_at = excluded.heartbeat_at \
+             WHERE sync_locks.heartbeat_at < ?",
+        )
+        .bind(source_id)
+        .bind(i64::from(pid))
+        .bind(host)
+        .bind(now)
+        .bind(now)
+        .bind(stale_before.timestamp())
+        .execute(&self.pool)
+        .await?
+        .rows_affected()
+            > 0;
+        if taken {
+            return Ok(None);
+        }
+        self.get_sync_lock(source_id).await
+    }
+
+    /// Current holder of a source's sync lock.
+    pub async fn get_sync_lock(&self, source_id: &str) -> Result<Option<SyncLock>> {
+        let row = sqlx::query(
+            "SELECT source_id, pid, host, acquired_at, heartbeat_at \
+             FROM sync_locks WHERE source_id = ?",
+        )
+        .bind(source_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(|row| SyncLock {
+            source_id: row.get("source_id"),
+            pid: u32::try_from(row.get::<i64, _>("pid")).unwrap_or_default(),
+            host: row.get("host"),
+            acquired_at: chrono::DateTime::from_timestamp(row.get("acquired_at"), 0)
+                .unwrap_or_default(),
+            heartbeat_at: chrono::DateTime::from_timestamp(row.get("heartbeat_at"), 0)
+                .unwrap_or_default(),
+        }))
+    }
+
+    /// Refresh the heartbeat of a sync lock held by `pid` on `host`.
+    pub async fn heartbeat_source_sync(&self, source_id: &str, pid: u32, host: &str) -> Result<()> {
+        sqlx::query(
+            "UPDATE sync_locks SET heartbeat_at = ? WHERE source_id = ? AND pid = ? AND host = ?",
+        )
+        .bind(Utc::now().timestamp())
+        .bind(source_id)
+        .bind(i64::from(pid))
+        .bind(host)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Release a sync lock held by `pid` on `host`.
+    pub async fn unlock_source_sync(&self, source_id: &str, pid: u32, host: &str) -> Result<()> {
+        sqlx::query("DELETE FROM sync_locks WHERE source_id = ? AND pid = ? AND host = ?")
+            .bind(source_id)
+            .bind(i64::from(pid))
+            .bind(host)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Drop a lock left behind by a holder known to be gone. Only deletes
+    /// the exact lock passed in, so a lock re-taken meanwhile survives.
+    pub async fn break_sync_lock(&self, lock: &SyncLock) -> Result<()> {
+        sqlx::query(
+            "DELETE FROM sync_locks \
+             WHERE source_id = ? AND pid = ? AND host = ? AND acquired_at = ?",
+        )
+        .bind(&lock.source_id)
+        .bind(i64::from(lock.pid))
+        .bind(&lock.host)
+        .bind(lock.acquired_at.timestamp())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Store a new API token by its hash.
+    pub async fn create_api_token(
+        &self,
+        name: &str,
+        scope: ApiTokenScope,
+        token_hash: &str,
+        prefix: &str,
+    ) -> Result<ApiToken> {
+        let created_at = Utc::now();
+        let result = sqlx::query(
+            "INSERT INTO api_tokens (name, token_hash, prefix, scope, created_at) \
+             VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(name)
+        .bind(token_hash)
+        .bind(prefix)
+        .bind(scope.as_str())
+        .bind(created_at.timestamp())
+        .execute(&self.pool)
+        .await?;
+        Ok(ApiToken {
+            id: result.last_insert_rowid(),
+            name: name.to_string(),
+            prefix: prefix.to_string(),
+            scope,
+            created_at,
+            last_used_at: None,
+            revoked_at: None,
+        })
+    }
+
+    /// Look up an unrevoked API token by its hash.
+    pub async fn find_api_token(&self, token_hash: &str) -> Result<Option<ApiToken>> {
+        let row =
+            sqlx::query("SELECT * FROM api_tokens WHERE token_hash = ? AND revoked_at IS NULL")
+                .bind(token_hash)
+                .fetch_optional(self.reader())
+                .await?;
+        row.as_ref().map(api_token_from_row).transpose()
+    }
+
+    /// Whether any unrevoked API token exists.
+    pub async fn has_api_tokens(&self) -> Result<bool> {
+        let row: (i64,) =
+            sqlx::query_as("SELECT COUNT(*) FROM api_tokens WHERE revoked_at IS NULL")
+                .fetch_one(self.reader())
                 .await?;
+        Ok(row.0 > 0)
+    }
+
+    /// API tokens, oldest first; revoked ones only when `include_revoked`.
+    pub async fn list_api_tokens(&self, include_revoked: bool) -> Result<Vec<ApiToken>> {
+        let sql = if include_revoked {
+            "SELECT * FROM api_tokens ORDER BY id"
+        } else {
+            "SELECT * FROM api_tokens WHERE revoked_at IS NULL ORDER BY id"
+        };
+        let rows = sqlx::query(sql).fetch_all(self.reader()).await?;
+        rows.iter().map(api_token_from_row).collect()
+    }
+
+    /// Record that an API token was just used.
+    pub async fn touch_api_token(&self, id: i64) -> Result<()> {
+        sqlx::query("UPDATE api_tokens SET last_used_at = ? WHERE id = ?")
+            .bind(Utc::now().timestamp())
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Revoke unrevoked API tokens matching `id_or_name` (a numeric id or a
+    /// token name). Returns how many were revoked.
+    pub async fn revoke_api_token(&self, id_or_name: &str) -> Result<u64> {
+        let result = sqlx::query(
+            "UPDATE api_tokens SET revoked_at = ? \
+             WHERE revoked_at IS NULL AND (CAST(id AS TEXT) = ? OR name = ?)",
+        )
+        .bind(Utc::now().timestamp())
+        .bind(id_or_name)
+        .bind(id_or_name)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Record a read served over `channel` (`api` or `mcp`) and the
+    /// conversations it returned.
+    pub async fn record_access(
+        &self,
+        channel: &str,
+        caller: Option<&str>,
+        action: &str,
+        query: Option<&str>,
+        conversation_ids: &[Uuid],
+    ) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO access_log \
+             (accessed_at, channel, caller, action, query, conversation_ids) \
+             VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(Utc::now().timestamp())
+        .bind(channel)
+        .bind(caller)
+        .bind(action)
+        .bind(query)
+        .bind(serde_json::to_string(conversation_ids)?)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Access log entries matching `opts`, newest first.
+    pub async fn list_access_log(&self, opts: &AccessLogOptions) -> Result<Vec<AccessEntry>> {
+        let mut sql = String::from("SELECT * FROM access_log WHERE 1=1");
+        let mut binds = Vec::new();
+        if let Some(channel) = &opts.channel {
+            sql.push_str(" AND channel = ?");
+            binds.push(channel.clone());
+        }
+        if let Some(caller) = &opts.caller {
+            sql.push_str(" AND caller = ?");
+            binds.push(caller.clone());
+        }
+        if let Some(id) = opts.conversation_id {
+            sql.push_str(" AND conversation_ids LIKE ?");
+            binds.push(format!("%\"{id}\"%"));
+        }
+        if let Some(after) = opts.after {
+            sql.push_str(" AND accessed_at >= ?");
+            binds.push(after.timestamp().to_string());
+        }
+        sql.push_str(" ORDER BY accessed_at DESC, id DESC");
+        if let Some(limit) = opts.limit {
+            sql.push_str(&format!(" LIMIT {limit}"));
+        }
+
+        let mut query = sqlx::query(&sql);
+        for bind in &binds {
+            query = query.bind(bind);
+        }
+        let rows = query.fetch_all(self.reader()).await?;
+        Ok(rows
+            .iter()
+            .map(|row| AccessEntry {
+                id: row.get("id"),
+                accessed_at: chrono::DateTime::from_timestamp(row.get("accessed_at"), 0)
+                    .unwrap_or_default(),
+                channel: row.get("channel"),
+                caller: row.get("caller"),
+                action: row.get("action"),
+                query: row.get("query"),
+                conversation_ids: serde_json::from_str(row.get("conversation_ids"))
+                    .unwrap_or_default(),
+            })
+            .collect())
+    }
+
+    /// Delete access log entries older than `max_age_days` (0 keeps them
+    /// all). Returns how many were removed.
+    pub async fn prune_access_log(&self, max_age_days: u32) -> Result<u64> {
+        if max_age_days == 0 {
+            return Ok(0);
+        }
+        let cutoff = Utc::now() - chrono::Duration::days(i64::from(max_age_days));
+        let result = sqlx::query("DELETE FROM access_log WHERE accessed_at < ?")
+            .bind(cutoff.timestamp())
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Close an open remote sync conflict with the given resolution.
+    pub async fn resolve_remote_conflict(&self, id: i64, resolution: &str) -> Result<()> {
+        sqlx::query(
+            "UPDATE remote_conflicts SET resolution = ?, resolved_at = ? \
+             WHERE id = ? AND resolved_at IS NULL",
+        )
+        .bind(resolution)
+        .bind(Utc::now().timestamp())
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Remote sync conflicts, newest first. Only open ones unless
+    /// `include_resolved` is set.
+    pub async fn list_remote_conflicts(
+        &self,
+        remote: Option<&str>,
+        include_resolved: bool,
+    ) -> Result<Vec<RemoteConflict>> {
+        let mut sql = String::from("SELECT * FROM remote_conflicts WHERE 1=1");
+        if remote.is_some() {
+            sql.push_str(" AND remote = ?");
+        }
+        if !include_resolved {
+            sql.push_str(" AND resolved_at IS NULL");
+        }
+        sql.push_str(" ORDER BY detected_at DESC, id DESC");
+
+        let mut query = sqlx::query(&sql);
+        if let Some(remote) = remote {
+            query = query.bind(remote);
+        }
+        let rows = query.fetch_all(self.reader()).await?;
+        let timestamp = |row: &sqlx::sqlite::SqliteRow, column: &str| {
+            row.get::<Option<i64>, _>(column)
+                .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0))
+        };
+        let uuid = |row: &sqlx::sqlite::SqliteRow, column: &str| {
+            row.get::<Option<String>, _>(column)
+                .and_then(|id| Uuid::parse_str(&id).ok())
+        };
+        Ok(rows
+            .iter()
+            .map(|row| RemoteConflict {
+                id: row.get("id"),
+                remote: row.get("remote"),
+                source_id: row.get("source_id"),
+                external_id: row.get("external_id"),
+                title: row.get("title"),
+                local_conversation_id: uuid(row, "local_conversation_id"),
+                duplicate_conversation_id: uuid(row, "duplicate_conversation_id"),
+                local_updated_at: timestamp(row, "local_updated_at"),
+                remote_updated_at: timestamp(row, "remote_updated_at"),
+                strategy: row.get("strategy"),
+                resolution: row.get("resolution"),
+                detected_at: timestamp(row, "detected_at").unwrap_or_else(Utc::now),
+                resolved_at: timestamp(row, "resolved_at"),
+            })
+            .collect())
+    }
+
+    /// Count conversations and messages for a specific source.
+    pub async fn count_source_data(&self, source_id: &str) -> Result<(i64, i64)> {
+        let conv_count: (i64,) =
+            sqlx::query_as("SELECT COUNT(*) FROM conversations WHERE source_id = ?")
+                .bind(source_id)
+                .fetch_one(&self.pool)
+                .await?;
+
+        let msg_count: (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM messages m
+             JOIN conversations c ON m.conversation_id = c.id
+             WHERE c.source_id = ?",
+        )
+        .bind(source_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok((conv_count.0, msg_count.0))
+    }
+
+    /// Get detailed statistics per source.
+    pub async fn get_source_stats(&self) -> Result<Vec<SourceStats>> {
+        let rows = sqlx::query(
+            r"
+            SELECT
+                s.id as source_id,
+                s.adapter,
+                COUNT(DISTINCT c.id) as conversations,
+                COUNT(m.id) as messages,
+                MIN(c.created_at) as oldest,
+                MAX(c.created_at) as newest,
+                s.last_sync_at
+            FROM sources s
+            LEFT JOIN conversations c ON c.source_id = s.id
+            LEFT JOIN messages m ON m.conversation_id = c.id
+            GROUP BY s.id, s.adapter, s.last_sync_at
+            ORDER BY conversations DESC
+            ",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut stats = Vec::new();
+        for row in rows {
+            stats.push(SourceStats {
+                source_id: row.get("source_id"),
+                adapter: row.get("adapter"),
+                conversations: row.get::<i64, _>("conversations"),
+                messages: row.get::<i64, _>("messages"),
+                oldest: row
+                    .get::<Option<i64>, _>("oldest")
+                    .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0))
+                    .map(|dt| dt.with_timezone(&Utc)),
+                newest: row
+                    .get::<Option<i64>, _>("newest")
+                    .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0))
+                    .map(|dt| dt.with_timezone(&Utc)),
+                last_sync_at: row
+                    .get::<Option<i64>, _>("last_sync_at")
+                    .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0))
+                    .map(|dt| dt.with_timezone(&Utc)),
+            });
+        }
+        Ok(stats)
+    }
+
+    /// Get activity stats (conversations per day/week/month).
+    pub async fn get_activity_stats(&self, days: i64) -> Result<ActivityStats> {
+        let cutoff = Utc::now() - chrono::Duration::days(days);
+        let cutoff_ts = cutoff.timestamp();
+
+        let today_count: (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM conversations WHERE created_at >= ? AND deleted_at IS NULL",
+        )
+        .bind((Utc::now() - chrono::Duration::days(1)).timestamp())
+        .fetch_one(&self.pool)
+        .await?;
 
-        let month_count: (i64,) =
-            sqlx::query_as("SELECT COUNT(*) FROM conversations WHERE created_at >= ?")
-                .bind((Utc::now() - chrono::Duration::days(30)).timestamp())
-                .fetch_one(&self.pool)
-                .await?;
+        let week_count: (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM conversations WHERE created_at >= ? AND deleted_at IS NULL",
+        )
+        .bind((Utc::now() - chrono::Duration::days(7)).timestamp())
+        .fetch_one(&self.pool)
+        .await?;
 
-        let period_count: (i64,) =
-            sqlx::query_as("SELECT COUNT(*) FROM conversations WHERE created_at >= ?")
-                .bind(cutoff_ts)
-                .fetch_one(&self.pool)
-                .await?;
+        let month_count: (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM conversations WHERE created_at >= ? AND deleted_at IS NULL",
+        )
+        .bind((Utc::now() - chrono::Duration::days(30)).timestamp())
+        .fetch_one(&self.pool)
+        .await?;
+
+        let period_count: (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM conversations WHERE created_at >= ? AND deleted_at IS NULL",
+        )
+        .bind(cutoff_ts)
+        .fetch_one(&self.pool)
+        .await?;
 
         Ok(ActivityStats {
             today: today_count.0,
@@ -1101,6 +2509,258 @@ impl Database {
         })
     }
 
+    /// Conversations created on each of the last `days` local days, oldest
+    /// first. Days without conversations are included with a count of 0.
+    pub async fn count_conversations_per_day(
+        &self,
+        days: i64,
+    ) -> Result<Vec<(chrono::NaiveDate, i64)>> {
+        use chrono::TimeZone;
+
+        let today = chrono::Local::now().date_naive();
+        let first = today - chrono::Duration::days(days.max(1) - 1);
+        let cutoff = chrono::Local
+            .from_local_datetime(&first.and_time(chrono::NaiveTime::MIN))
+            .earliest()
+            .map_or(0, |dt| dt.timestamp());
+        let rows: Vec<(String, i64)> = sqlx::query_as(
+            "SELECT date(created_at, 'unixepoch', 'localtime') AS day, COUNT(*)
+             FROM conversations WHERE created_at >= ? AND deleted_at IS NULL GROUP BY day",
+        )
+        .bind(cutoff)
+        .fetch_all(self.reader())
+        .await?;
+        let counts: HashMap<String, i64> = rows.into_iter().collect();
+        Ok(first
+            .iter_days()
+            .take_while(|day| *day <= today)
+            .map(|day| {
+                let count = counts
+                    .get(&day.format("%Y-%m-%d").to_string())
+                    .copied()
+                    .unwrap_or(0);
+                (day, count)
+            })
+            .collect())
+    }
+
+    /// Conversation counts per model, most used first. A conversation
+    /// without a model counts under the first model its messages name;
+    /// conversations with none at all are omitted.
+    pub async fn count_conversations_by_model(&self, limit: i64) -> Result<Vec<(String, i64)>> {
+        let rows: Vec<(String, i64)> = sqlx::query_as(
+            "SELECT model, COUNT(*) AS n FROM (
+                 SELECT COALESCE(NULLIF(c.model, ''), (
+                     SELECT m.model FROM messages m
+                     WHERE m.conversation_id = c.id AND m.model IS NOT NULL AND m.model != ''
+                     ORDER BY m.idx LIMIT 1
+                 )) AS model
+                 FROM conversations c
+             )
+             WHERE model IS NOT NULL
+             GROUP BY model ORDER BY n DESC, model LIMIT ?",
+        )
+        .bind(limit)
+        .fetch_all(self.reader())
+        .await?;
+        Ok(rows)
+    }
+
+    /// Token and cost totals over all conversations outside the trash. A
+    /// conversation without token counts or a cost counts the sums of its
+    /// messages'.
+    pub async fn get_usage_totals(&self) -> Result<UsageTotals> {
+        let sql = format!(
+            "SELECT
+                 COALESCE(SUM({CONVERSATION_TOKENS_IN_SQL}), 0),
+                 COALESCE(SUM({CONVERSATION_TOKENS_OUT_SQL}), 0),
+                 COALESCE(SUM(COALESCE(c.cost_usd, (
+                     SELECT SUM(m.cost_usd) FROM messages m WHERE m.conversation_id = c.id
+                 ))), 0.0)
+             FROM conversations c WHERE c.deleted_at IS NULL"
+        );
+        let (tokens_in, tokens_out, cost_usd): (i64, i64, f64) =
+            sqlx::query_as(&sql).fetch_one(self.reader()).await?;
+        Ok(UsageTotals {
+            tokens_in,
+            tokens_out,
+            cost_usd,
+        })
+    }
+
+    /// Conversations, messages, tokens and cost grouped by `group`. Sources
+    /// and workspaces come busiest first, months oldest first, and models
+    /// costliest first; `limit` caps the number of rows.
+    pub async fn stats_breakdown(
+        &self,
+        group: StatsGroup,
+        limit: Option<i64>,
+    ) -> Result<Vec<StatsBreakdown>> {
+        let (key, filter) = (group.key_sql(), group.filter_sql());
+        let order = match group {
+            StatsGroup::Source => "conversations DESC, key",
+            StatsGroup::Model => "cost_usd DESC, conversations DESC, key",
+            StatsGroup::Month => "key",
+            StatsGroup::Workspace => "conversations DESC, messages DESC, key",
+        };
+        let sql = format!(
+            "SELECT key, COUNT(*) AS conversations, SUM(messages) AS messages,
+                    SUM(tokens_in) AS tokens_in, SUM(tokens_out) AS tokens_out,
+                    SUM(cost_usd) AS cost_usd
+             FROM (
+                 SELECT {key} AS key,
+                        c.message_count AS messages,
+                        COALESCE({CONVERSATION_TOKENS_IN_SQL}, 0) AS tokens_in,
+                        COALESCE({CONVERSATION_TOKENS_OUT_SQL}, 0) AS tokens_out,
+                        COALESCE(c.cost_usd, (
+                            SELECT SUM(m.cost_usd) FROM messages m WHERE m.conversation_id = c.id
+                        ), 0.0) AS cost_usd
+                 FROM conversations c {filter}
+             )
+             GROUP BY key ORDER BY {order} LIMIT ?"
+        );
+        let rows: Vec<(Option<String>, i64, i64, i64, i64, f64)> = sqlx::query_as(&sql)
+            .bind(limit.unwrap_or(-1))
+            .fetch_all(self.reader())
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(
+                |(key, conversations, messages, tokens_in, tokens_out, cost_usd)| StatsBreakdown {
+                    key,
+                    conversations,
+                    messages,
+                    tokens_in,
+                    tokens_out,
+                    cost_usd,
+                },
+            )
+            .collect())
+    }
+
+    /// Token and recorded cost totals grouped by `group` and model, for
+    /// estimating the cost of conversations without one. Conversations
+    /// with a recorded cost and those without are kept in separate rows.
+    pub async fn cost_breakdown(&self, group: StatsGroup) -> Result<Vec<CostBreakdown>> {
+        let sql = format!(
+            "SELECT key, model, cost_usd IS NOT NULL AS recorded, COUNT(*),
+                    SUM(tokens_in), SUM(tokens_out), COALESCE(SUM(cost_usd), 0.0)
+             FROM (
+                 SELECT {key} AS key,
+                        {CONVERSATION_MODEL_SQL} AS model,
+                        COALESCE({CONVERSATION_TOKENS_IN_SQL}, 0) AS tokens_in,
+                        COALESCE({CONVERSATION_TOKENS_OUT_SQL}, 0) AS tokens_out,
+                        COALESCE(c.cost_usd, (
+                            SELECT SUM(m.cost_usd) FROM messages m WHERE m.conversation_id = c.id
+                        )) AS cost_usd
+                 FROM conversations c {filter}
+             )
+             GROUP BY key, model, recorded ORDER BY key, model",
+            key = group.key_sql(),
+            filter = group.filter_sql(),
+        );
+        let rows: Vec<(Option<String>, Option<String>, bool, i64, i64, i64, f64)> =
+            sqlx::query_as(&sql).fetch_all(self.reader()).await?;
+        Ok(rows
+            .into_iter()
+            .map(
+                |(key, model, recorded, conversations, tokens_in, tokens_out, cost_usd)| {
+                    CostBreakdown {
+                        key,
+                        model,
+                        recorded,
+                        conversations,
+                        tokens_in,
+                        tokens_out,
+                        cost_usd,
+                    }
+                },
+            )
+            .collect())
+    }
+
+    /// The breakdowns behind `hstry stats --detailed`, with at most
+    /// `top` models and workspaces.
+    pub async fn detailed_stats(&self, top: i64) -> Result<DetailedStats> {
+        let totals = self.get_usage_totals().await?;
+        let (conversations, messages): (i64, i64) = sqlx::query_as(
+            "SELECT COUNT(*), COALESCE(SUM(message_count), 0) FROM conversations
+                 WHERE deleted_at IS NULL",
+        )
+        .fetch_one(self.reader())
+        .await?;
+        Ok(DetailedStats {
+            totals,
+            avg_messages: if conversations == 0 {
+                0.0
+            } else {
+                messages as f64 / conversations as f64
+            },
+            by_source: self.stats_breakdown(StatsGroup::Source, None).await?,
+            by_model: self.stats_breakdown(StatsGroup::Model, Some(top)).await?,
+            by_month: self.stats_breakdown(StatsGroup::Month, None).await?,
+            top_workspaces: self
+                .stats_breakdown(StatsGroup::Workspace, Some(top))
+                .await?,
+        })
+    }
+
+    /// Store the usage records of model requests.
+    pub async fn record_llm_usage(&self, usage: &[LlmUsage]) -> Result<()> {
+        if usage.is_empty() {
+            return Ok(());
+        }
+        let mut tx = self.pool.begin().await?;
+        for record in usage {
+            sqlx::query(
+                "INSERT INTO llm_usage
+                     (created_at, feature, provider, model, tokens_in, tokens_out, cost_usd)
+                 VALUES (?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(record.at.timestamp())
+            .bind(&record.feature)
+            .bind(&record.provider)
+            .bind(&record.model)
+            .bind(record.tokens_in)
+            .bind(record.tokens_out)
+            .bind(record.cost_usd)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Model requests grouped by feature, provider and model, costliest
+    /// first.
+    pub async fn llm_usage_summary(&self) -> Result<Vec<LlmUsageSummary>> {
+        let rows: Vec<(String, String, Option<String>, i64, i64, i64, f64)> = sqlx::query_as(
+            "SELECT feature, provider, model, COUNT(*),
+                    SUM(tokens_in), SUM(tokens_out), SUM(cost_usd)
+             FROM llm_usage
+             GROUP BY feature, provider, model
+             ORDER BY SUM(cost_usd) DESC, COUNT(*) DESC, feature",
+        )
+        .fetch_all(self.reader())
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(
+                |(feature, provider, model, requests, tokens_in, tokens_out, cost_usd)| {
+                    LlmUsageSummary {
+                        feature,
+                        provider,
+                        model,
+                        requests,
+                        tokens_in,
+                        tokens_out,
+                        cost_usd,
+                    }
+                },
+            )
+            .collect())
+    }
+
     /// Delete a conversation and all its messages.
     pub async fn delete_conversation(&self, id: Uuid) -> Result<()> {
         let id_str = id.to_string();
@@ -1117,64 +2777,512 @@ impl Database {
         Ok(())
     }
 
-    /// Delete multiple conversations and all their associated data in a single transaction.
-    /// Much faster than calling `delete_conversation` in a loop because it avoids
-    /// per-row transaction overhead.
-    pub async fn delete_conversations_batch(&self, ids: &[Uuid]) -> Result<usize> {
-        if ids.is_empty() {
-            return Ok(0);
-        }
+    /// Delete multiple conversations and all their associated data in a single transaction.
+    /// Much faster than calling `delete_conversation` in a loop because it avoids
+    /// per-row transaction overhead.
+    pub async fn delete_conversations_batch(&self, ids: &[Uuid]) -> Result<usize> {
+        if ids.is_empty() {
+            return Ok(0);
+        }
+
+        let mut tx = self.pool.begin().await?;
+
+        // Process in chunks to stay within SQLite variable limits (max 999)
+        for chunk in ids.chunks(500) {
+            let placeholders: String = chunk.iter().map(|_| "?").collect::<Vec<_>>().join(",");
+
+            // Delete related tables first (message_events, snapshots, summary cache, messages)
+            let sql =
+                format!("DELETE FROM message_events WHERE conversation_id IN ({placeholders})");
+            let mut query = sqlx::query(&sql);
+            for id in chunk {
+                query = query.bind(id.to_string());
+            }
+            query.execute(&mut *tx).await?;
+
+            let sql = format!(
+                "DELETE FROM conversation_snapshots WHERE conversation_id IN ({placeholders})"
+            );
+            let mut query = sqlx::query(&sql);
+            for id in chunk {
+                query = query.bind(id.to_string());
+            }
+            query.execute(&mut *tx).await?;
+
+            let sql = format!(
+                "DELETE FROM conversation_summary_cache WHERE conversation_id IN ({placeholders})"
+            );
+            let mut query = sqlx::query(&sql);
+            for id in chunk {
+                query = query.bind(id.to_string());
+            }
+            query.execute(&mut *tx).await?;
+
+            let sql = format!("DELETE FROM messages WHERE conversation_id IN ({placeholders})");
+            let mut query = sqlx::query(&sql);
+            for id in chunk {
+                query = query.bind(id.to_string());
+            }
+            query.execute(&mut *tx).await?;
+
+            let sql = format!("DELETE FROM conversations WHERE id IN ({placeholders})");
+            let mut query = sqlx::query(&sql);
+            for id in chunk {
+                query = query.bind(id.to_string());
+            }
+            query.execute(&mut *tx).await?;
+        }
+
+        tx.commit().await?;
+        Ok(ids.len())
+    }
+
+    // =========================================================================
+    // Trash
+    // =========================================================================
+
+    /// Move conversations to the trash. They keep their messages but drop
+    /// out of lists, counts and search until restored. Returns the number
+    /// newly trashed.
+    pub async fn trash_conversations(&self, ids: &[Uuid]) -> Result<usize> {
+        self.set_deleted_at(ids, Some(Utc::now().timestamp())).await
+    }
+
+    /// Take conversations back out of the trash. Returns the number
+    /// restored.
+    pub async fn restore_conversations(&self, ids: &[Uuid]) -> Result<usize> {
+        self.set_deleted_at(ids, None).await
+    }
+
+    async fn set_deleted_at(&self, ids: &[Uuid], deleted_at: Option<i64>) -> Result<usize> {
+        let mut tx = self.pool.begin().await?;
+        let mut changed = 0;
+        for chunk in ids.chunks(500) {
+            let placeholders: String = chunk.iter().map(|_| "?").collect::<Vec<_>>().join(",");
+            // The version bump puts the change in the change log, so open
+            // TUIs drop or bring back the conversation.
+            let sql = format!(
+                "UPDATE conversations SET deleted_at = ?, version = version + 1 \
+                 WHERE id IN ({placeholders}) AND (deleted_at IS NULL) = ?"
+            );
+            let mut query = sqlx::query(&sql).bind(deleted_at);
+            for id in chunk {
+                query = query.bind(id.to_string());
+            }
+            let result = query.bind(deleted_at.is_some()).execute(&mut *tx).await?;
+            changed += usize::try_from(result.rows_affected()).unwrap_or(usize::MAX);
+        }
+        tx.commit().await?;
+        Ok(changed)
+    }
+
+    /// Conversations in the trash, most recently trashed first.
+    pub async fn list_trash(&self, limit: Option<i64>) -> Result<Vec<TrashedConversation>> {
+        let mut sql = String::from(
+            "SELECT * FROM conversations WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC",
+        );
+        push_limit_offset(&mut sql, limit, None);
+        let rows = sqlx::query(&sql).fetch_all(self.reader()).await?;
+        Ok(rows
+            .iter()
+            .map(|row| TrashedConversation {
+                conversation: conversation_from_row(row),
+                deleted_at: chrono::DateTime::from_timestamp(row.get("deleted_at"), 0)
+                    .unwrap_or_default(),
+            })
+            .collect())
+    }
+
+    /// When a conversation was moved to the trash, or `None` when it is not
+    /// in the trash.
+    pub async fn trashed_at(&self, id: Uuid) -> Result<Option<chrono::DateTime<Utc>>> {
+        let row: Option<(Option<i64>,)> =
+            sqlx::query_as("SELECT deleted_at FROM conversations WHERE id = ?")
+                .bind(id.to_string())
+                .fetch_optional(self.reader())
+                .await?;
+        Ok(row
+            .and_then(|(deleted_at,)| deleted_at)
+            .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0)))
+    }
+
+    /// Permanently delete the conversations in the trash, or only those
+    /// trashed before `before`. Returns the number deleted.
+    pub async fn purge_trash(&self, before: Option<chrono::DateTime<Utc>>) -> Result<usize> {
+        let rows: Vec<(String,)> = sqlx::query_as(
+            "SELECT id FROM conversations WHERE deleted_at IS NOT NULL AND deleted_at < ?",
+        )
+        .bind(before.map_or(i64::MAX, |before| before.timestamp()))
+        .fetch_all(&self.pool)
+        .await?;
+        let ids: Vec<Uuid> = rows
+            .iter()
+            .filter_map(|(id,)| Uuid::parse_str(id).ok())
+            .collect();
+        self.delete_conversations_batch(&ids).await
+    }
+
+    // =========================================================================
+    // Saved snapshots
+    // =========================================================================
+
+    /// Save a copy of a conversation, its messages and tool calls that
+    /// [`Database::restore_snapshot`] can roll it back to. `reason` is
+    /// "manual" for `hstry snapshot create`, otherwise the operation about
+    /// to change the conversation; of those, only the newest
+    /// [`AUTO_SNAPSHOTS_KEPT`] per conversation are kept. Returns `None`
+    /// for a missing or sensitive conversation, whose text must not be
+    /// stored in the clear.
+    pub async fn create_snapshot(
+        &self,
+        conversation_id: Uuid,
+        reason: &str,
+    ) -> Result<Option<SavedSnapshot>> {
+        let Some(conversation) = self.get_conversation(conversation_id).await? else {
+            return Ok(None);
+        };
+        let mut conn = self.pool.acquire().await?;
+        if !self
+            .sensitive_ids(&mut conn, &[conversation_id])
+            .await?
+            .is_empty()
+        {
+            return Ok(None);
+        }
+        drop(conn);
+        let messages = self.get_messages(conversation_id).await?;
+        let tool_calls = self.get_tool_calls(conversation_id).await?;
+        let snapshot = SavedSnapshot {
+            id: Uuid::new_v4(),
+            conversation_id,
+            reason: reason.to_string(),
+            title: conversation.title.clone(),
+            message_count: i64::try_from(messages.len()).unwrap_or(i64::MAX),
+            created_at: Utc::now(),
+        };
+        let payload = serde_json::to_string(&SnapshotPayload {
+            conversation,
+            messages,
+            tool_calls,
+        })?;
+        sqlx::query(
+            "INSERT INTO saved_snapshots \
+             (id, conversation_id, reason, title, message_count, payload_json, created_at) \
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(snapshot.id.to_string())
+        .bind(conversation_id.to_string())
+        .bind(reason)
+        .bind(&snapshot.title)
+        .bind(snapshot.message_count)
+        .bind(payload)
+        .bind(snapshot.created_at.timestamp())
+        .execute(&self.pool)
+        .await?;
+
+        if reason != "manual" {
+            sqlx::query(
+                "DELETE FROM saved_snapshots WHERE id IN ( \
+                     SELECT id FROM saved_snapshots \
+                     WHERE conversation_id = ? AND reason != 'manual' \
+                     ORDER BY created_at DESC, rowid DESC LIMIT -1 OFFSET ?)",
+            )
+            .bind(conversation_id.to_string())
+            .bind(AUTO_SNAPSHOTS_KEPT)
+            .execute(&self.pool)
+            .await?;
+        }
+        Ok(Some(snapshot))
+    }
+
+    /// [`Database::create_snapshot`] for each of `conversation_ids`.
+    /// Returns how many were saved.
+    pub async fn snapshot_conversations(
+        &self,
+        conversation_ids: &[Uuid],
+        reason: &str,
+    ) -> Result<usize> {
+        let mut saved = 0;
+        for id in conversation_ids {
+            saved += usize::from(self.create_snapshot(*id, reason).await?.is_some());
+        }
+        Ok(saved)
+    }
+
+    /// Saved snapshots, newest first; only those of `conversation_id` when
+    /// given.
+    pub async fn list_snapshots(
+        &self,
+        conversation_id: Option<Uuid>,
+        limit: Option<i64>,
+    ) -> Result<Vec<SavedSnapshot>> {
+        let mut sql = String::from(
+            "SELECT id, conversation_id, reason, title, message_count, created_at \
+             FROM saved_snapshots",
+        );
+        if conversation_id.is_some() {
+            sql.push_str(" WHERE conversation_id = ?");
+        }
+        sql.push_str(" ORDER BY created_at DESC, rowid DESC");
+        if let Some(limit) = limit {
+            let _ = write!(sql, " LIMIT {limit}");
+        }
+        let mut query = sqlx::query(&sql);
+        if let Some(id) = conversation_id {
+            query = query.bind(id.to_string());
+        }
+        let rows = query.fetch_all(&self.pool).await?;
+        Ok(rows
+            .iter()
+            .map(|row| SavedSnapshot {
+                id: Uuid::parse_str(row.get("id")).unwrap_or_default(),
+                conversation_id: Uuid::parse_str(row.get("conversation_id")).unwrap_or_default(),
+                reason: row.get("reason"),
+                title: row.get("title"),
+                message_count: row.get("message_count"),
+                created_at: chrono::DateTime::from_timestamp(row.get("created_at"), 0)
+                    .unwrap_or_default(),
+            })
+            .collect())
+    }
+
+    /// Roll a conversation back to a saved snapshot: its messages and tool
+    /// calls are replaced by the saved ones, and it leaves the trash. A
+    /// conversation deleted since is recreated. The state being replaced
+    /// is saved first, with reason "restore". Returns the conversation's id.
+    pub async fn restore_snapshot(&self, snapshot_id: Uuid) -> Result<Uuid> {
+        let payload: Option<(String,)> =
+            sqlx::query_as("SELECT payload_json FROM saved_snapshots WHERE id = ?")
+                .bind(snapshot_id.to_string())
+                .fetch_optional(&self.pool)
+                .await?;
+        let Some((payload,)) = payload else {
+            return Err(Error::NotFound(format!("snapshot '{snapshot_id}'")));
+        };
+        let SnapshotPayload {
+            conversation,
+            messages,
+            tool_calls,
+        } = serde_json::from_str(&payload)?;
+
+        // A sync may have brought a deleted conversation back under a new id.
+        let existing = match self.get_conversation(conversation.id).await? {
+            Some(existing) => Some(existing.id),
+            None => match &conversation.external_id {
+                Some(external_id) => {
+                    self.get_conversation_id(&conversation.source_id, external_id)
+                        .await?
+                }
+                None => None,
+            },
+        };
+        if let Some(id) = existing {
+            self.create_snapshot(id, "restore").await?;
+        }
+        let target = existing.unwrap_or(conversation.id);
+        let messages: Vec<Message> = messages
+            .into_iter()
+            .map(|msg| Message {
+                conversation_id: target,
+                ..msg
+            })
+            .collect();
+
+        let mut tx = self.pool.begin().await?;
+        if existing.is_none() {
+            self.upsert_conversation_in_tx(&mut tx, &conversation)
+                .await?;
+        }
+        sqlx::query("DELETE FROM messages WHERE conversation_id = ?")
+            .bind(target.to_string())
+            .execute(&mut *tx)
+            .await?;
+        self.bulk_insert_messages_in_tx(&mut tx, &messages).await?;
+        if self.sensitive_ids(&mut tx, &[target]).await?.is_empty() {
+            self.insert_tool_calls_in_tx(&mut tx, &tool_calls).await?;
+        }
+        sqlx::query("UPDATE conversations SET deleted_at = NULL WHERE id = ?")
+            .bind(target.to_string())
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        self.rebuild_conversation_summaries(&[target]).await?;
+        Ok(target)
+    }
+
+    // =========================================================================
+    // Generated titles
+    // =========================================================================
+
+    /// Store a title from `hstry title generate`. A title that `replaces`
+    /// the adapter's is kept on later syncs; otherwise a title the adapter
+    /// reports later takes over.
+    pub async fn set_generated_title(
+        &self,
+        conversation_id: Uuid,
+        title: &str,
+        replaces: bool,
+    ) -> Result<()> {
+        sqlx::query(
+            "UPDATE conversations SET title = ?, title_generated = ?, version = version + 1 WHERE id = ?",
+        )
+        .bind(title)
+        .bind(if replaces { 2 } else { 1 })
+        .bind(conversation_id.to_string())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    // =========================================================================
+    // Stars
+    // =========================================================================
 
-        let mut tx = self.pool.begin().await?;
+    /// Star or unstar a conversation. Returns false when it does not exist.
+    pub async fn set_conversation_starred(
+        &self,
+        conversation_id: Uuid,
+        starred: bool,
+    ) -> Result<bool> {
+        let result = sqlx::query("UPDATE conversations SET starred = ? WHERE id = ?")
+            .bind(starred)
+            .bind(conversation_id.to_string())
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
 
-        // Process in chunks to stay within SQLite variable limits (max 999)
-        for chunk in ids.chunks(500) {
-            let placeholders: String = chunk.iter().map(|_| "?").collect::<Vec<_>>().join(",");
+    /// Whether the conversation is starred.
+    pub async fn is_starred(&self, conversation_id: Uuid) -> Result<bool> {
+        let starred: Option<bool> =
+            sqlx::query_scalar("SELECT starred != 0 FROM conversations WHERE id = ?")
+                .bind(conversation_id.to_string())
+                .fetch_optional(self.reader())
+                .await?;
+        Ok(starred.unwrap_or(false))
+    }
 
-            // Delete related tables first (message_events, snapshots, summary cache, messages)
-            let sql =
-                format!("DELETE FROM message_events WHERE conversation_id IN ({placeholders})");
-            let mut query = sqlx::query(&sql);
-            for id in chunk {
-                query = query.bind(id.to_string());
-            }
-            query.execute(&mut *tx).await?;
+    /// Ids of all starred conversations.
+    pub async fn starred_conversation_ids(&self) -> Result<HashSet<Uuid>> {
+        let ids: Vec<String> = sqlx::query_scalar("SELECT id FROM conversations WHERE starred = 1")
+            .fetch_all(self.reader())
+            .await?;
+        Ok(ids
+            .iter()
+            .filter_map(|id| Uuid::parse_str(id).ok())
+            .collect())
+    }
 
-            let sql = format!(
-                "DELETE FROM conversation_snapshots WHERE conversation_id IN ({placeholders})"
-            );
-            let mut query = sqlx::query(&sql);
-            for id in chunk {
-                query = query.bind(id.to_string());
-            }
-            query.execute(&mut *tx).await?;
+    // =========================================================================
+    // Related conversations
+    // =========================================================================
 
+    /// Pairs of conversations from different sources with the same
+    /// external id (`reason` "external_id") or platform id ("platform_id"),
+    /// as `(a, b, reason)`. Ids shorter than `min_len` are too likely to
+    /// collide and are skipped.
+    pub async fn conversations_sharing_ids(
+        &self,
+        min_len: usize,
+    ) -> Result<Vec<(Uuid, Uuid, &'static str)>> {
+        let mut pairs = Vec::new();
+        for (column, reason) in [
+            ("external_id", "external_id"),
+            ("platform_id", "platform_id"),
+        ] {
             let sql = format!(
-                "DELETE FROM conversation_summary_cache WHERE conversation_id IN ({placeholders})"
+                r"
+                SELECT a.id, b.id
+                FROM conversations a
+                JOIN conversations b
+                  ON b.{column} = a.{column} AND b.source_id <> a.source_id AND b.id > a.id
+                WHERE length(a.{column}) >= ?
+                  AND a.deleted_at IS NULL AND b.deleted_at IS NULL
+                "
             );
-            let mut query = sqlx::query(&sql);
-            for id in chunk {
-                query = query.bind(id.to_string());
-            }
-            query.execute(&mut *tx).await?;
-
-            let sql = format!("DELETE FROM messages WHERE conversation_id IN ({placeholders})");
-            let mut query = sqlx::query(&sql);
-            for id in chunk {
-                query = query.bind(id.to_string());
-            }
-            query.execute(&mut *tx).await?;
+            let rows: Vec<(String, String)> = sqlx::query_as(&sql)
+                .bind(i64::try_from(min_len).unwrap_or(i64::MAX))
+                .fetch_all(self.reader())
+                .await?;
+            pairs.extend(rows.into_iter().filter_map(|(a, b)| {
+                Some((Uuid::parse_str(&a).ok()?, Uuid::parse_str(&b).ok()?, reason))
+            }));
+        }
+        Ok(pairs)
+    }
 
-            let sql = format!("DELETE FROM conversations WHERE id IN ({placeholders})");
-            let mut query = sqlx::query(&sql);
-            for id in chunk {
-                query = query.bind(id.to_string());
+    /// Record pairs of related conversations as `(a, b, reason, score)`.
+    /// Pairs already recorded keep their reason and score. Returns the
+    /// pairs added.
+    pub async fn add_related_conversations(
+        &self,
+        links: &[(Uuid, Uuid, &str, f32)],
+    ) -> Result<u64> {
+        let now = Utc::now().timestamp();
+        let mut tx = self.pool.begin().await?;
+        let mut added = 0;
+        for &(a, b, reason, score) in links {
+            if a == b {
+                continue;
             }
-            query.execute(&mut *tx).await?;
+            let result = sqlx::query(
+                r"
+                INSERT INTO related_conversations
+                    (conversation_id, related_id, reason, score, detected_at)
+                VALUES (?, ?, ?, ?, ?)
+                ON CONFLICT(conversation_id, related_id) DO NOTHING
+                ",
+            )
+            .bind(a.min(b).to_string())
+            .bind(a.max(b).to_string())
+            .bind(reason)
+            .bind(score)
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+            added += result.rows_affected();
         }
-
         tx.commit().await?;
-        Ok(ids.len())
+        Ok(added)
+    }
+
+    /// Conversations recorded as copies of `conversation_id` in other
+    /// sources, closest first.
+    pub async fn related_conversations(
+        &self,
+        conversation_id: Uuid,
+    ) -> Result<Vec<RelatedConversation>> {
+        let id = conversation_id.to_string();
+        let rows = sqlx::query(
+            r"
+            SELECT c.id, c.source_id, c.readable_id, c.title, r.reason, r.score
+            FROM related_conversations r
+            JOIN conversations c ON c.id = CASE
+                WHEN r.conversation_id = ? THEN r.related_id
+                ELSE r.conversation_id
+            END
+            WHERE r.conversation_id = ? OR r.related_id = ?
+            ORDER BY r.score DESC, c.source_id
+            ",
+        )
+        .bind(&id)
+        .bind(&id)
+        .bind(&id)
+        .fetch_all(self.reader())
+        .await?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| {
+                Some(RelatedConversation {
+                    id: Uuid::parse_str(row.get::<&str, _>("id")).ok()?,
+                    source_id: row.get("source_id"),
+                    readable_id: row.get("readable_id"),
+                    title: row.get("title"),
+                    reason: row.get("reason"),
+                    score: row.get("score"),
+                })
+            })
+            .collect())
     }
 
     // =========================================================================
@@ -1214,6 +3322,45 @@ impl Database {
         Ok(result.rows_affected() > 0)
     }
 
+    /// Add a tag to many conversations in one transaction. Returns how many
+    /// conversations did not have it yet.
+    pub async fn add_tag_to_conversations(
+        &self,
+        conversation_ids: &[Uuid],
+        tag: &str,
+    ) -> Result<u64> {
+        let tag = tag.trim().to_lowercase();
+        if tag.is_empty() || conversation_ids.is_empty() {
+            return Ok(0);
+        }
+
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("INSERT OR IGNORE INTO tags (name) VALUES (?)")
+            .bind(&tag)
+            .execute(&mut *tx)
+            .await?;
+        let tag_id: i64 = sqlx::query("SELECT id FROM tags WHERE name = ?")
+            .bind(&tag)
+            .fetch_one(&mut *tx)
+            .await?
+            .get("id");
+
+        let mut added = 0;
+        for chunk in conversation_ids.chunks(500) {
+            let values = vec!["(?, ?)"; chunk.len()].join(",");
+            let sql = format!(
+                "INSERT OR IGNORE INTO conversation_tags (conversation_id, tag_id) VALUES {values}"
+            );
+            let mut query = sqlx::query(&sql);
+            for id in chunk {
+                query = query.bind(id.to_string()).bind(tag_id);
+            }
+            added += query.execute(&mut *tx).await?.rows_affected();
+        }
+        tx.commit().await?;
+        Ok(added)
+    }
+
     /// Remove a tag from a conversation.
     pub async fn remove_conversation_tag(&self, conversation_id: Uuid, tag: &str) -> Result<bool> {
         let tag = tag.trim().to_lowercase();
@@ -1264,6 +3411,33 @@ impl Database {
             .collect())
     }
 
+    /// All tag names, including tags no conversation uses.
+    pub async fn list_tag_names(&self) -> Result<Vec<String>> {
+        let rows: Vec<(String,)> = sqlx::query_as("SELECT name FROM tags ORDER BY name")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.into_iter().map(|(name,)| name).collect())
+    }
+
+    /// Create tags that don't exist yet. Returns how many were created.
+    pub async fn create_tags(&self, tags: &[String]) -> Result<usize> {
+        let mut tx = self.pool.begin().await?;
+        let mut created = 0;
+        for tag in tags {
+            let tag = tag.trim().to_lowercase();
+            if tag.is_empty() {
+                continue;
+            }
+            let result = sqlx::query("INSERT OR IGNORE INTO tags (name) VALUES (?)")
+                .bind(&tag)
+                .execute(&mut *tx)
+                .await?;
+            created += usize::try_from(result.rows_affected()).unwrap_or(0);
+        }
+        tx.commit().await?;
+        Ok(created)
+    }
+
     /// Find conversations that have a specific tag.
     pub async fn find_conversations_by_tag(&self, tag: &str) -> Result<Vec<Conversation>> {
         let tag = tag.trim().to_lowercase();
@@ -1352,6 +3526,16 @@ impl Database {
         Ok(result)
     }
 
+    /// Change the external id a conversation is matched on during sync.
+    pub async fn set_conversation_external_id(&self, id: Uuid, external_id: &str) -> Result<()> {
+        sqlx::query("UPDATE conversations SET external_id = ?, version = version + 1 WHERE id = ?")
+            .bind(external_id)
+            .bind(id.to_string())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
     /// Update a conversation's updated_at timestamp.
     pub async fn update_conversation_updated_at(
         &self,
@@ -1455,6 +3639,325 @@ impl Database {
         Ok(())
     }
 
+    // =========================================================================
+    // Sensitive conversations
+    // =========================================================================
+
+    /// Public key sensitive messages are sealed to, if one was created.
+    async fn sensitive_recipient(&self) -> Result<Option<Recipient>> {
+        let recipient: Option<String> =
+            sqlx::query_scalar("SELECT recipient FROM sensitive_key WHERE id = 1")
+                .fetch_optional(&self.pool)
+                .await?;
+        recipient
+            .map(|recipient| {
+                Recipient::from_str(&recipient)
+                    .map_err(|e| Error::Other(format!("invalid sensitive key: {e}")))
+            })
+            .transpose()
+    }
+
+    /// Whether `hstry sensitive init` created a key for this database.
+    pub fn has_sensitive_key(&self) -> bool {
+        self.sealing_key
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_some()
+    }
+
+    /// Store the key sensitive messages are sealed to. A database has one
+    /// key for good, so this fails when it already exists.
+    pub async fn create_sensitive_key(&self, key: &sensitive::WrappedKey) -> Result<()> {
+        if self.sensitive_recipient().await?.is_some() {
+            return Err(Error::Config(
+                "this database already has a sensitive key".to_string(),
+            ));
+        }
+        sqlx::query(
+            "INSERT INTO sensitive_key (id, recipient, identity, created_at) VALUES (1, ?, ?, ?)",
+        )
+        .bind(key.recipient.to_string())
+        .bind(&key.identity)
+        .bind(Utc::now().timestamp())
+        .execute(&self.pool)
+        .await?;
+        *self
+            .sealing_key
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = Some(key.recipient.clone());
+        Ok(())
+    }
+
+    /// The passphrase-wrapped private key, if one was created.
+    pub async fn wrapped_sensitive_identity(&self) -> Result<Option<Vec<u8>>> {
+        Ok(
+            sqlx::query_scalar("SELECT identity FROM sensitive_key WHERE id = 1")
+                .fetch_optional(&self.pool)
+                .await?,
+        )
+    }
+
+    /// Use the same sensitive key as `other`, so conversations moved between
+    /// the two stay readable. Fails when this database has a different key.
+    pub async fn share_sensitive_key(&self, other: &Database) -> Result<()> {
+        let Some(recipient) = other.sensitive_recipient().await? else {
+            return Ok(());
+        };
+        if let Some(own) = self.sensitive_recipient().await? {
+            if own != recipient {
+                return Err(Error::Config(
+                    "the databases have different sensitive keys".to_string(),
+                ));
+            }
+            return Ok(());
+        }
+        let identity = other
+            .wrapped_sensitive_identity()
+            .await?
+            .unwrap_or_default();
+        self.create_sensitive_key(&sensitive::WrappedKey {
+            recipient,
+            identity,
+        })
+        .await
+    }
+
+    /// Decrypt sealed messages on read with `identity` from now on.
+    pub fn unlock_sensitive(&self, identity: Identity) {
+        *self
+            .unsealing_key
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = Unsealing::Key(identity);
+    }
+
+    /// Decrypt sealed messages on read while the `hstry sensitive unlock`
+    /// session of the database at `db_path` is open. The session is checked
+    /// on every read, so locking it or letting it expire also locks
+    /// long-running processes.
+    pub fn follow_unlock_session(&self, db_path: &Path) {
+        *self
+            .unsealing_key
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = Unsealing::Session(db_path.to_path_buf());
+    }
+
+    /// Whether sealed messages are decrypted on read.
+    pub fn is_unlocked(&self) -> bool {
+        self.unsealing_identity().is_some()
+    }
+
+    /// The key that opens sealed messages right now, if any.
+    fn unsealing_identity(&self) -> Option<Identity> {
+        match &*self
+            .unsealing_key
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+        {
+            Unsealing::Locked => None,
+            Unsealing::Key(identity) => Some(identity.clone()),
+            Unsealing::Session(db_path) => {
+                sensitive::read_session(db_path).map(|(identity, _)| identity)
+            }
+        }
+    }
+
+    /// Whether the conversation's messages are sealed.
+    pub async fn is_sensitive(&self, conversation_id: Uuid) -> Result<bool> {
+        let sensitive: Option<bool> =
+            sqlx::query_scalar("SELECT sensitive != 0 FROM conversations WHERE id = ?")
+                .bind(conversation_id.to_string())
+                .fetch_optional(self.reader())
+                .await?;
+        Ok(sensitive.unwrap_or(false))
+    }
+
+    /// Ids of all sensitive conversations.
+    pub async fn sensitive_conversation_ids(&self) -> Result<HashSet<Uuid>> {
+        let ids: Vec<String> =
+            sqlx::query_scalar("SELECT id FROM conversations WHERE sensitive = 1")
+                .fetch_all(self.reader())
+                .await?;
+        Ok(ids
+            .iter()
+            .filter_map(|id| Uuid::parse_str(id).ok())
+            .collect())
+    }
+
+    /// Seal the stored messages of conversations that `[sensitive]` matches
+    /// but that were written in plaintext, e.g. before the rule was added.
+    /// Plaintext copies of them (message events, tool calls, decisions,
+    /// saved snapshots) are deleted. Returns the number of conversations sealed.
+    pub async fn seal_sensitive_conversations(&self) -> Result<usize> {
+        if self.sensitive.is_empty() {
+            return Ok(0);
+        }
+        let rows =
+            sqlx::query("SELECT id, source_id, workspace FROM conversations WHERE sensitive = 0")
+                .fetch_all(&self.pool)
+                .await?;
+        let ids: Vec<Uuid> = rows
+            .iter()
+            .filter(|row| {
+                self.sensitive.matches(
+                    row.get::<&str, _>("source_id"),
+                    row.get::<Option<&str>, _>("workspace"),
+                )
+            })
+            .filter_map(|row| Uuid::parse_str(row.get::<&str, _>("id")).ok())
+            .collect();
+
+        for &id in &ids {
+            let rows = sqlx::query(
+                "SELECT id, content, parts_json FROM messages \
+                 WHERE conversation_id = ? AND sealed IS NULL",
+            )
+            .bind(id.to_string())
+            .fetch_all(&self.pool)
+            .await?;
+            let mut tx = self.pool.begin().await?;
+            self.sensitive_ids(&mut tx, &[id]).await?;
+            for row in rows {
+                let parts = row
+                    .get::<Option<String>, _>("parts_json")
+                    .and_then(|parts| serde_json::from_str(&parts).ok())
+                    .unwrap_or_else(|| serde_json::json!([]));
+                let sealed = self.seal_content(id, row.get("content"), &parts)?;
+                sqlx::query(
+                    "UPDATE messages SET content = '', parts_json = '[]', sealed = ? WHERE id = ?",
+                )
+                .bind(sealed)
+                .bind(row.get::<&str, _>("id"))
+                .execute(&mut *tx)
+                .await?;
+            }
+            sqlx::query("DELETE FROM message_events WHERE conversation_id = ?")
+                .bind(id.to_string())
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query(
+                "DELETE FROM tool_calls WHERE message_id IN \
+                 (SELECT id FROM messages WHERE conversation_id = ?)",
+            )
+            .bind(id.to_string())
+            .execute(&mut *tx)
+            .await?;
+            for table in ["decisions", "decision_scans", "saved_snapshots"] {
+                sqlx::query(&format!("DELETE FROM {table} WHERE conversation_id = ?"))
+                    .bind(id.to_string())
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            tx.commit().await?;
+            self.invalidate_conversation_snapshot(id).await?;
+            self.rebuild_conversation_summary(id).await?;
+        }
+        Ok(ids.len())
+    }
+
+    /// Which of `ids` are sensitive: conversations already marked, and those
+    /// `[sensitive]` matches, which are marked now.
+    async fn sensitive_ids(
+        &self,
+        conn: &mut sqlx::SqliteConnection,
+        ids: &[Uuid],
+    ) -> Result<HashSet<Uuid>> {
+        let mut sensitive = HashSet::new();
+        if ids.is_empty() || (self.sensitive.is_empty() && !self.has_sensitive_key()) {
+            return Ok(sensitive);
+        }
+        let mut newly_matched = Vec::new();
+        for chunk in ids.chunks(500) {
+            let placeholders = vec!["?"; chunk.len()].join(",");
+            let sql = format!(
+                "SELECT id, source_id, workspace, sensitive FROM conversations WHERE id IN ({placeholders})"
+            );
+            let mut query = sqlx::query(&sql);
+            for id in chunk {
+                query = query.bind(id.to_string());
+            }
+            for row in query.fetch_all(&mut *conn).await? {
+                let Ok(id) = Uuid::parse_str(row.get::<&str, _>("id")) else {
+                    continue;
+                };
+                if row.get::<i64, _>("sensitive") != 0 {
+                    sensitive.insert(id);
+                } else if self.sensitive.matches(
+                    row.get::<&str, _>("source_id"),
+                    row.get::<Option<&str>, _>("workspace"),
+                ) {
+                    sensitive.insert(id);
+                    newly_matched.push(id);
+                }
+            }
+        }
+        for id in newly_matched {
+            sqlx::query("UPDATE conversations SET sensitive = 1 WHERE id = ?")
+                .bind(id.to_string())
+                .execute(&mut *conn)
+                .await?;
+        }
+        Ok(sensitive)
+    }
+
+    /// The columns `msg` is stored with: its content and parts, or for a
+    /// sensitive conversation empty ones and both sealed.
+    fn stored_content(&self, msg: &Message, sensitive: bool) -> Result<StoredContent> {
+        let parts_json = normalize_parts_json(&msg.parts_json);
+        let content = project_content(&msg.content, &parts_json);
+        if !sensitive {
+            return Ok(StoredContent {
+                content,
+                parts_json: parts_json.to_string(),
+                sealed: None,
+            });
+        }
+        Ok(StoredContent {
+            sealed: Some(self.seal_content(msg.conversation_id, &content, &parts_json)?),
+            content: String::new(),
+            parts_json: "[]".to_string(),
+        })
+    }
+
+    /// Seal a message of the sensitive conversation `conversation_id`.
+    fn seal_content(
+        &self,
+        conversation_id: Uuid,
+        content: &str,
+        parts_json: &serde_json::Value,
+    ) -> Result<Vec<u8>> {
+        let recipient = self
+            .sealing_key
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+            .ok_or_else(|| {
+                Error::Config(format!(
+                    "conversation {conversation_id} is sensitive but there is no key to seal it \
+                     with; run `hstry sensitive init`"
+                ))
+            })?;
+        sensitive::seal(&recipient, content, parts_json)
+    }
+
+    /// Fill in a sealed message's content and parts, or show
+    /// [`sensitive::LOCKED_CONTENT`] while locked.
+    fn open_sealed(&self, message: &mut Message, sealed: &[u8]) {
+        match self
+            .unsealing_identity()
+            .map(|identity| sensitive::unseal(&identity, sealed))
+        {
+            Some(Ok((content, parts))) => {
+                message.content = content;
+                message.parts_json = parts;
+            }
+            Some(Err(err)) => {
+                tracing::warn!("Cannot open sealed message {}: {err}", message.id);
+                message.content = sensitive::LOCKED_CONTENT.to_string();
+            }
+            None => message.content = sensitive::LOCKED_CONTENT.to_string(),
+        }
+    }
+
     // =========================================================================
     // Messages
     // =========================================================================
@@ -1466,8 +3969,19 @@ impl Database {
     /// writes, no version bump). If the content differs, the existing message
     /// is updated. Returns `true` if a write actually occurred.
     pub async fn insert_message(&self, msg: &Message) -> Result<bool> {
-        let parts_json = normalize_parts_json(&msg.parts_json);
-        let content = project_content(&msg.content, &parts_json);
+        Ok(self.write_message(msg).await?.0)
+    }
+
+    /// [`Database::insert_message`], also returning the revision kept for
+    /// the text the message replaced.
+    async fn write_message(&self, msg: &Message) -> Result<(bool, Option<RevisionKind>)> {
+        let sensitive = {
+            let mut conn = self.pool.acquire().await?;
+            self.sensitive_ids(&mut conn, &[msg.conversation_id])
+                .await?
+                .contains(&msg.conversation_id)
+        };
+        let stored = self.stored_content(msg, sensitive)?;
         let sender_json = msg
             .sender
             .as_ref()
@@ -1483,43 +3997,60 @@ impl Database {
         .await?;
 
         let is_update = existing.is_some();
-        let parts_json_str = parts_json.to_string();
         if let Some(ref row) = existing {
             let existing_content: String = row.get("content");
             let existing_parts: String = row
                 .get::<Option<String>, _>("parts_json")
                 .unwrap_or_default();
-            // Content-hash idempotency: skip if content and parts match
-            if existing_content == content && existing_parts == parts_json_str {
-                return Ok(false);
+            // Content-hash idempotency: skip if content and parts match.
+            // Sealed messages cannot be compared and are always rewritten.
+            if stored.sealed.is_none()
+                && existing_content == stored.content
+                && existing_parts == stored.parts_json
+            {
+                return Ok((false, None));
             }
         }
+        let revision = if is_update && !sensitive {
+            let mut conn = self.pool.acquire().await?;
+            self.record_revisions(&mut conn, std::slice::from_ref(msg), &HashSet::new())
+                .await?
+                .pop()
+        } else {
+            None
+        };
 
-        sqlx::query(
+        sqlx::query(concat!(
             r"
-            INSERT INTO messages (id, conversation_id, idx, role, content, parts_json, created_at, model, tokens, cost_usd, metadata, sender_json, provider, harness, client_id)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            INSERT INTO messages (id, conversation_id, idx, role, content, parts_json, created_at, model, tokens, cost_usd, metadata, sender_json, provider, harness, client_id, parent_message_id, sealed)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             ON CONFLICT(conversation_id, idx) DO UPDATE SET
                 role = excluded.role,
                 content = excluded.content,
                 parts_json = excluded.parts_json,
+                sealed = excluded.sealed,
                 created_at = excluded.created_at,
                 model = excluded.model,
-                tokens = excluded.tokens,
+                ",
+                merge_message_tokens!(),
+                r",
                 cost_usd = excluded.cost_usd,
-                metadata = excluded.metadata,
+                ",
+                merge_message_metadata!(),
+                r",
                 sender_json = excluded.sender_json,
                 provider = excluded.provider,
                 harness = excluded.harness,
-                client_id = COALESCE(excluded.client_id, messages.client_id)
+                client_id = COALESCE(excluded.client_id, messages.client_id),
+                parent_message_id = excluded.parent_message_id
             ",
-        )
+        ))
         .bind(msg.id.to_string())
         .bind(msg.conversation_id.to_string())
         .bind(msg.idx)
         .bind(msg.role.to_string())
-        .bind(&content)
-        .bind(parts_json_str)
+        .bind(&stored.content)
+        .bind(&stored.parts_json)
         .bind(msg.created_at.map(|dt| dt.timestamp()))
         .bind(&msg.model)
         .bind(msg.tokens)
@@ -1529,6 +4060,8 @@ impl Database {
         .bind(&msg.provider)
         .bind(&msg.harness)
         .bind(&msg.client_id)
+        .bind(msg.parent_message_id.map(|id| id.to_string()))
+        .bind(&stored.sealed)
         .execute(&self.pool)
         .await?;
 
@@ -1549,7 +4082,8 @@ impl Database {
             .await?;
         }
 
-        if self.message_events_enabled.load(Ordering::Relaxed) {
+        // The event log keeps whole messages, so sealed ones stay out of it.
+        if self.message_events_enabled.load(Ordering::Relaxed) && stored.sealed.is_none() {
             self.insert_message_event(msg).await?;
         }
         if self.indexer_outbox_enabled.load(Ordering::Relaxed) {
@@ -1562,9 +4096,9 @@ impl Database {
             self.rebuild_conversation_summary(msg.conversation_id)
                 .await?;
         } else {
-            self.bump_conversation_summary(msg, &content).await?;
+            self.bump_conversation_summary(msg, &stored.content).await?;
         }
-        Ok(true)
+        Ok((true, revision))
     }
 
     /// Begin an explicit transaction. The caller must call `commit()` or `rollback()`.
@@ -1581,37 +4115,45 @@ impl Database {
         tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
         msg: &Message,
     ) -> Result<()> {
-        let parts_json = normalize_parts_json(&msg.parts_json);
-        let content = project_content(&msg.content, &parts_json);
+        let sensitive = self.sensitive_ids(tx, &[msg.conversation_id]).await?;
+        self.record_revisions(tx, std::slice::from_ref(msg), &sensitive)
+            .await?;
+        let stored = self.stored_content(msg, sensitive.contains(&msg.conversation_id))?;
         let sender_json = msg
             .sender
             .as_ref()
             .map(|s| serde_json::to_string(s).unwrap_or_default());
-        sqlx::query(
+        sqlx::query(concat!(
             r"
-            INSERT INTO messages (id, conversation_id, idx, role, content, parts_json, created_at, model, tokens, cost_usd, metadata, sender_json, provider, harness, client_id)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            INSERT INTO messages (id, conversation_id, idx, role, content, parts_json, created_at, model, tokens, cost_usd, metadata, sender_json, provider, harness, client_id, parent_message_id, sealed)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             ON CONFLICT(conversation_id, idx) DO UPDATE SET
                 role = excluded.role,
                 content = excluded.content,
                 parts_json = excluded.parts_json,
+                sealed = excluded.sealed,
                 created_at = excluded.created_at,
                 model = excluded.model,
-                tokens = excluded.tokens,
+                ",
+                merge_message_tokens!(),
+                r",
                 cost_usd = excluded.cost_usd,
-                metadata = excluded.metadata,
+                ",
+                merge_message_metadata!(),
+                r",
                 sender_json = excluded.sender_json,
                 provider = excluded.provider,
                 harness = excluded.harness,
-                client_id = COALESCE(excluded.client_id, messages.client_id)
+                client_id = COALESCE(excluded.client_id, messages.client_id),
+                parent_message_id = excluded.parent_message_id
             ",
-        )
+        ))
         .bind(msg.id.to_string())
         .bind(msg.conversation_id.to_string())
         .bind(msg.idx)
         .bind(msg.role.to_string())
-        .bind(&content)
-        .bind(parts_json.to_string())
+        .bind(&stored.content)
+        .bind(&stored.parts_json)
         .bind(msg.created_at.map(|dt| dt.timestamp()))
         .bind(&msg.model)
         .bind(msg.tokens)
@@ -1621,6 +4163,8 @@ impl Database {
         .bind(&msg.provider)
         .bind(&msg.harness)
         .bind(&msg.client_id)
+        .bind(msg.parent_message_id.map(|id| id.to_string()))
+        .bind(&stored.sealed)
         .execute(&mut **tx)
         .await?;
         Ok(())
@@ -1654,14 +4198,27 @@ impl Database {
             .or_else(|| readable_id_from_metadata(&conv.metadata))
             .or_else(|| Some(crate::readable_id::base_for(conv.id)));
 
+        // The COALESCE below stores it under the workspace it is an alias of.
+        let workspace = self.normalize_workspace(conv.workspace.as_deref());
         sqlx::query(
             r"
             INSERT INTO conversations (id, source_id, external_id, readable_id, platform_id, title, created_at, updated_at, model, provider, workspace, tokens_in, tokens_out, cost_usd, metadata, harness, version, message_count, parent_conversation_id, parent_message_idx, fork_type)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 0, 0, ?, ?, ?)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, COALESCE((SELECT w.path FROM workspaces w, json_each(w.aliases) a WHERE a.value = ? LIMIT 1), ?), ?, ?, ?, ?, ?, 0, 0, ?, ?, ?)
             ON CONFLICT(source_id, external_id) DO UPDATE SET
                 readable_id = COALESCE(excluded.readable_id, conversations.readable_id),
                 platform_id = COALESCE(NULLIF(excluded.platform_id, ''), NULLIF(conversations.platform_id, '')),
-                title = excluded.title,
+                title = CASE
+                    WHEN conversations.title_generated = 2
+                        OR (conversations.title_generated = 1 AND COALESCE(excluded.title, '') = '')
+                    THEN conversations.title
+                    ELSE excluded.title
+                END,
+                title_generated = CASE
+                    WHEN conversations.title_generated = 2
+                        OR COALESCE(excluded.title, '') IN ('', conversations.title)
+                    THEN conversations.title_generated
+                    ELSE 0
+                END,
                 updated_at = excluded.updated_at,
                 model = excluded.model,
                 provider = excluded.provider,
@@ -1687,7 +4244,8 @@ impl Database {
         .bind(conv.updated_at.map(|dt| dt.timestamp()))
         .bind(&conv.model)
         .bind(&conv.provider)
-        .bind(&conv.workspace)
+        .bind(&workspace)
+        .bind(&workspace)
         .bind(conv.tokens_in)
         .bind(conv.tokens_out)
         .bind(conv.cost_usd)
@@ -1762,22 +4320,194 @@ impl Database {
         Ok(())
     }
 
+    /// Conversations whose summary cache is missing or was built from a
+    /// different number of messages than are stored now, oldest cache first.
+    /// Messages bulk-inserted without `rebuild_conversation_summaries` leave
+    /// both the cache and the conversation's `message_count` behind, so the
+    /// stored messages are counted rather than trusting either.
+    pub async fn stale_conversation_summaries(&self, limit: i64) -> Result<Vec<Uuid>> {
+        let rows: Vec<(String,)> = sqlx::query_as(
+            r"
+            SELECT c.id
+            FROM conversations c
+            LEFT JOIN conversation_summary_cache cs ON cs.conversation_id = c.id
+            LEFT JOIN (
+                SELECT conversation_id, COUNT(*) AS n FROM messages GROUP BY conversation_id
+            ) m ON m.conversation_id = c.id
+            WHERE cs.conversation_id IS NULL
+               OR cs.message_count != COALESCE(m.n, 0)
+               OR c.message_count != COALESCE(m.n, 0)
+            ORDER BY COALESCE(cs.updated_at, 0) ASC
+            LIMIT ?
+            ",
+        )
+        .bind(limit)
+        .fetch_all(self.reader())
+        .await?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|(id,)| Uuid::parse_str(&id).ok())
+            .collect())
+    }
+
+    /// Rebuild up to `limit` stale summary caches. Returns how many were
+    /// refreshed.
+    pub async fn refresh_stale_summaries(&self, limit: i64) -> Result<usize> {
+        let stale = self.stale_conversation_summaries(limit).await?;
+        for id in &stale {
+            self.refresh_conversation_summary(*id).await?;
+        }
+        Ok(stale.len())
+    }
+
+    /// Rebuild the summary cache of `conversation_id` if it is stale.
+    /// Returns whether it was.
+    pub async fn refresh_summary_if_stale(&self, conversation_id: Uuid) -> Result<bool> {
+        let stale: Option<(i64,)> = sqlx::query_as(
+            r"
+            SELECT 1
+            FROM conversations c
+            LEFT JOIN conversation_summary_cache cs ON cs.conversation_id = c.id
+            WHERE c.id = ?
+              AND (cs.conversation_id IS NULL OR cs.message_count != c.message_count
+                   OR c.message_count != (SELECT COUNT(*) FROM messages m WHERE m.conversation_id = c.id))
+            ",
+        )
+        .bind(conversation_id.to_string())
+        .fetch_optional(self.reader())
+        .await?;
+        if stale.is_none() {
+            return Ok(false);
+        }
+        self.refresh_conversation_summary(conversation_id).await?;
+        Ok(true)
+    }
+
+    /// Rebuild one summary cache from the stored messages. When the
+    /// conversation's own `message_count` drifted too it is corrected,
+    /// bumping `version` so followers notice the new messages.
+    async fn refresh_conversation_summary(&self, conversation_id: Uuid) -> Result<()> {
+        self.rebuild_conversation_summary(conversation_id).await?;
+        sqlx::query(
+            r"
+            UPDATE conversations SET
+                message_count = (SELECT COUNT(*) FROM messages WHERE conversation_id = conversations.id),
+                version = version + 1
+            WHERE id = ?
+              AND message_count != (SELECT COUNT(*) FROM messages WHERE conversation_id = conversations.id)
+            ",
+        )
+        .bind(conversation_id.to_string())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
     /// Get messages for a conversation.
     pub async fn get_messages(&self, conversation_id: Uuid) -> Result<Vec<Message>> {
-        let rows = sqlx::query("SELECT * FROM messages WHERE conversation_id = ? ORDER BY idx")
-            .bind(conversation_id.to_string())
-            .fetch_all(&self.pool)
-            .await?;
+        self.get_messages_range(conversation_id, MessageRange::default())
+            .await
+    }
+
+    /// Get a slice of a conversation's messages, ordered by `idx`.
+    ///
+    /// The idx bounds are applied first; `offset`/`limit` then page through
+    /// the remaining rows.
+    pub async fn get_messages_range(
+        &self,
+        conversation_id: Uuid,
+        range: MessageRange,
+    ) -> Result<Vec<Message>> {
+        let mut sql = String::from("SELECT * FROM messages WHERE conversation_id = ?");
+        if range.from_idx.is_some() {
+            sql.push_str(" AND idx >= ?");
+        }
+        if range.to_idx.is_some() {
+            sql.push_str(" AND idx < ?");
+        }
+        sql.push_str(" ORDER BY idx");
+        push_limit_offset(&mut sql, range.limit, range.offset);
+
+        let mut query = sqlx::query(&sql).bind(conversation_id.to_string());
+        if let Some(from_idx) = range.from_idx {
+            query = query.bind(from_idx);
+        }
+        if let Some(to_idx) = range.to_idx {
+            query = query.bind(to_idx);
+        }
+        let rows = query.fetch_all(self.reader()).await?;
 
         let mut messages = Vec::new();
         for row in rows {
-            messages.push(message_from_row(&row));
+            let mut message = message_from_row(&row);
+            if let Some(sealed) = row.try_get::<Option<Vec<u8>>, _>("sealed").ok().flatten() {
+                self.open_sealed(&mut message, &sealed);
+            }
+            messages.push(message);
         }
         Ok(messages)
     }
 
-    /// Get messages with snapshot caching.
+    /// Up to `before` messages preceding and `after` messages following
+    /// the message at `idx` in its conversation.
+    pub async fn get_message_context(
+        &self,
+        conversation_id: Uuid,
+        idx: i32,
+        before: usize,
+        after: usize,
+    ) -> Result<MessageContext> {
+        let sql_before = "SELECT * FROM messages WHERE conversation_id = ? AND idx < ? \
+                          ORDER BY idx DESC LIMIT ?";
+        let sql_after = "SELECT * FROM messages WHERE conversation_id = ? AND idx > ? \
+                         ORDER BY idx LIMIT ?";
+        let mut context = MessageContext::default();
+        for (sql, limit, messages) in [
+            (sql_before, before, &mut context.before),
+            (sql_after, after, &mut context.after),
+        ] {
+            if limit == 0 {
+                continue;
+            }
+            let rows = sqlx::query(sql)
+                .bind(conversation_id.to_string())
+                .bind(idx)
+                .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+                .fetch_all(self.reader())
+                .await?;
+            for row in rows {
+                let mut message = message_from_row(&row);
+                if let Some(sealed) = row.try_get::<Option<Vec<u8>>, _>("sealed").ok().flatten() {
+                    self.open_sealed(&mut message, &sealed);
+                }
+                messages.push(message);
+            }
+        }
+        context.before.reverse();
+        Ok(context)
+    }
+
+    /// Attach the messages around each hit (see [`SearchHit::context`]).
+    pub async fn add_search_context<'a>(
+        &self,
+        hits: impl IntoIterator<Item = &'a mut SearchHit>,
+        messages: usize,
+    ) -> Result<()> {
+        for hit in hits {
+            hit.context = Some(
+                self.get_message_context(hit.conversation_id, hit.message_idx, messages, messages)
+                    .await?,
+            );
+        }
+        Ok(())
+    }
+
+    /// Get messages with snapshot caching. Sensitive conversations are never
+    /// cached, since a snapshot would store them in plaintext.
     pub async fn get_messages_cached(&self, conversation_id: Uuid) -> Result<Vec<Message>> {
+        if self.is_sensitive(conversation_id).await? {
+            return self.get_messages(conversation_id).await;
+        }
         let message_count = self
             .count_messages_for_conversation(conversation_id)
             .await?;
@@ -1803,7 +4533,7 @@ impl Database {
     ) -> Result<Vec<MessageEvent>> {
         let mut sql = String::from(
             "SELECT id, conversation_id, idx, payload_json, created_at, metadata \
-             FROM message_events WHERE conversation_id = ?",
+             FROM message_events WHERE conversation_id = ? AND kind = 'upsert'",
         );
 
         if after_idx.is_some() {
@@ -1860,14 +4590,221 @@ impl Database {
         Ok(count.0)
     }
 
+    /// Add `label` to a message, or remove it when the message already has
+    /// it. Returns the message's labels afterwards.
+    pub async fn toggle_message_label(&self, message_id: Uuid, label: &str) -> Result<Vec<String>> {
+        let row: Option<(String, String)> =
+            sqlx::query_as("SELECT conversation_id, metadata FROM messages WHERE id = ?")
+                .bind(message_id.to_string())
+                .fetch_optional(&self.pool)
+                .await?;
+        let Some((conversation_id, metadata)) = row else {
+            return Err(Error::NotFound(format!("message '{message_id}'")));
+        };
+        let mut metadata: serde_json::Value =
+            serde_json::from_str(&metadata).unwrap_or(serde_json::Value::Null);
+        labels::toggle(&mut metadata, label);
+
+        sqlx::query("UPDATE messages SET metadata = ? WHERE id = ?")
+            .bind(metadata.to_string())
+            .bind(message_id.to_string())
+            .execute(&self.pool)
+            .await?;
+        if let Ok(conversation_id) = Uuid::parse_str(&conversation_id) {
+            self.invalidate_conversation_snapshot(conversation_id)
+                .await?;
+        }
+        Ok(labels::labels(&metadata)
+            .into_iter()
+            .map(str::to_string)
+            .collect())
+    }
+
     /// Get message count.
     pub async fn count_messages(&self) -> Result<i64> {
         let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM messages")
-            .fetch_one(&self.pool)
+            .fetch_one(self.reader())
             .await?;
         Ok(count.0)
     }
 
+    /// Up to `limit` messages without a token count, with rowids above
+    /// `after_rowid`, in rowid order. With `reestimate`, messages whose
+    /// count was estimated by [`Database::set_estimated_tokens`] are
+    /// included too.
+    pub async fn messages_for_token_backfill(
+        &self,
+        source_id: Option<&str>,
+        reestimate: bool,
+        after_rowid: i64,
+        limit: i64,
+    ) -> Result<Vec<(i64, Message)>> {
+        let mut sql = String::from(
+            "SELECT m.rowid AS message_rowid, m.* FROM messages m
+             JOIN conversations c ON c.id = m.conversation_id
+             WHERE m.rowid > ? AND (m.tokens IS NULL OR (? AND m.tokens_estimated = 1))",
+        );
+        if source_id.is_some() {
+            sql.push_str(" AND c.source_id = ?");
+        }
+        sql.push_str(" ORDER BY m.rowid LIMIT ?");
+        let mut query = sqlx::query(&sql).bind(after_rowid).bind(reestimate);
+        if let Some(source_id) = source_id {
+            query = query.bind(source_id);
+        }
+        let rows = query.bind(limit).fetch_all(self.reader()).await?;
+
+        let mut messages = Vec::with_capacity(rows.len());
+        for row in rows {
+            let mut message = message_from_row(&row);
+            if let Some(sealed) = row.try_get::<Option<Vec<u8>>, _>("sealed").ok().flatten() {
+                self.open_sealed(&mut message, &sealed);
+            }
+            messages.push((row.get("message_rowid"), message));
+        }
+        Ok(messages)
+    }
+
+    /// Store estimated token counts. Counts reported by an adapter are never
+    /// replaced. Returns the number of messages updated.
+    pub async fn set_estimated_tokens(&self, counts: &[(Uuid, i64)]) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+        let mut updated = 0;
+        for (id, tokens) in counts {
+            updated += sqlx::query(
+                "UPDATE messages SET tokens = ?, tokens_estimated = 1
+                 WHERE id = ? AND (tokens IS NULL OR tokens_estimated = 1)",
+            )
+            .bind(tokens)
+            .bind(id.to_string())
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        }
+        tx.commit().await?;
+        Ok(updated)
+    }
+
+    // =========================================================================
+    // Message revisions
+    // =========================================================================
+
+    /// Write a message an adapter reports again, possibly with new text, and
+    /// return the kind of revision kept for the text it replaced. Every
+    /// write path keeps revisions, so a sync that re-imports an edited
+    /// conversation records them too; see [`crate::revisions`].
+    pub async fn apply_message_event(&self, msg: &Message) -> Result<Option<RevisionKind>> {
+        Ok(self.write_message(msg).await?.1)
+    }
+
+    /// Earlier versions of the messages in a conversation, by message and
+    /// then oldest first.
+    pub async fn message_revisions(&self, conversation_id: Uuid) -> Result<Vec<MessageRevision>> {
+        let rows = sqlx::query(
+            "SELECT idx, kind, payload_json, created_at FROM message_events \
+             WHERE conversation_id = ? AND kind != 'upsert' \
+             ORDER BY idx, created_at, rowid",
+        )
+        .bind(conversation_id.to_string())
+        .fetch_all(&self.pool)
+        .await?;
+        let mut revisions = Vec::with_capacity(rows.len());
+        for row in rows {
+            let Some(kind) = RevisionKind::parse(row.get("kind")) else {
+                continue;
+            };
+            let payload: serde_json::Value =
+                serde_json::from_str(row.get("payload_json")).unwrap_or_default();
+            revisions.push(MessageRevision {
+                idx: row.get("idx"),
+                kind,
+                role: MessageRole::from(payload["role"].as_str().unwrap_or_default()),
+                content: payload["content"].as_str().unwrap_or_default().to_string(),
+                model: payload["model"].as_str().map(str::to_string),
+                created_at: payload["created_at"]
+                    .as_i64()
+                    .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0)),
+                replaced_at: row
+                    .get::<Option<i64>, _>("created_at")
+                    .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0))
+                    .unwrap_or_default(),
+            });
+        }
+        Ok(revisions)
+    }
+
+    /// Keep the stored text of each message in `messages` that is about to
+    /// be overwritten by more than an append, and return the kinds kept.
+    /// Sealed messages and conversations in `sensitive` are skipped: their
+    /// text is never stored in the clear.
+    async fn record_revisions(
+        &self,
+        conn: &mut sqlx::SqliteConnection,
+        messages: &[Message],
+        sensitive: &HashSet<Uuid>,
+    ) -> Result<Vec<RevisionKind>> {
+        let mut by_conversation: HashMap<Uuid, Vec<&Message>> = HashMap::new();
+        for msg in messages {
+            if !sensitive.contains(&msg.conversation_id) {
+                by_conversation
+                    .entry(msg.conversation_id)
+                    .or_default()
+                    .push(msg);
+            }
+        }
+        let replaced_at = Utc::now().timestamp();
+        let mut recorded = Vec::new();
+        for (conversation_id, incoming) in by_conversation {
+            for chunk in incoming.chunks(500) {
+                let placeholders = vec!["?"; chunk.len()].join(",");
+                let sql = format!(
+                    "SELECT idx, role, content, model, created_at FROM messages \
+                     WHERE conversation_id = ? AND sealed IS NULL AND idx IN ({placeholders})"
+                );
+                let mut query = sqlx::query(&sql).bind(conversation_id.to_string());
+                for msg in chunk {
+                    query = query.bind(msg.idx);
+                }
+                let stored: HashMap<i32, sqlx::sqlite::SqliteRow> = query
+                    .fetch_all(&mut *conn)
+                    .await?
+                    .into_iter()
+                    .map(|row| (row.get("idx"), row))
+                    .collect();
+                for msg in chunk {
+                    let Some(row) = stored.get(&msg.idx) else {
+                        continue;
+                    };
+                    let role = MessageRole::from(row.get::<&str, _>("role"));
+                    let old: &str = row.get("content");
+                    let Some(kind) = RevisionKind::between(&role, old, &msg.content) else {
+                        continue;
+                    };
+                    let payload = serde_json::json!({
+                        "role": role,
+                        "content": old,
+                        "model": row.get::<Option<String>, _>("model"),
+                        "created_at": row.get::<Option<i64>, _>("created_at"),
+                    });
+                    sqlx::query(
+                        "INSERT INTO message_events (id, conversation_id, idx, payload_json, created_at, metadata, kind) \
+                         VALUES (?, ?, ?, ?, ?, '{}', ?)",
+                    )
+                    .bind(Uuid::new_v4().to_string())
+                    .bind(conversation_id.to_string())
+                    .bind(msg.idx)
+                    .bind(payload.to_string())
+                    .bind(replaced_at)
+                    .bind(kind.as_str())
+                    .execute(&mut *conn)
+                    .await?;
+                    recorded.push(kind);
+                }
+            }
+        }
+        Ok(recorded)
+    }
+
     // =========================================================================
     // Message Events + Snapshots
     // =========================================================================
@@ -2029,46 +4966,293 @@ impl Database {
         filename: Option<&str>,
         data: &[u8],
     ) -> Result<()> {
-        // Determine type from mime_type
-        let attachment_type = if mime_type.starts_with("image/") {
-            "image"
-        } else if mime_type.starts_with("audio/") {
-            "audio"
-        } else if mime_type.starts_with("video/") {
-            "video"
-        } else {
-            "file"
-        };
+        // Determine type from mime_type
+        let attachment_type = if mime_type.starts_with("image/") {
+            "image"
+        } else if mime_type.starts_with("audio/") {
+            "audio"
+        } else if mime_type.starts_with("video/") {
+            "video"
+        } else {
+            "file"
+        };
+
+        sqlx::query(
+            r"
+            INSERT INTO attachments (id, message_id, type, name, mime_type, content)
+            VALUES (?, ?, ?, ?, ?, ?)
+            ",
+        )
+        .bind(id)
+        .bind(message_id.to_string())
+        .bind(attachment_type)
+        .bind(filename)
+        .bind(mime_type)
+        .bind(data)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Get attachment binary data by ID.
+    pub async fn get_attachment(&self, id: &str) -> Result<Option<(String, Vec<u8>)>> {
+        let row = sqlx::query("SELECT mime_type, content FROM attachments WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.map(|row| {
+            let mime_type: String = row.get("mime_type");
+            let content: Vec<u8> = row.get("content");
+            (mime_type, content)
+        }))
+    }
+
+    /// Stored attachments of a conversation's messages, without their
+    /// content, in message order.
+    pub async fn list_conversation_attachments(
+        &self,
+        conversation_id: Uuid,
+    ) -> Result<Vec<AttachmentInfo>> {
+        let rows: Vec<(String, String, Option<String>, Option<String>)> = sqlx::query_as(
+            r"
+            SELECT a.id, a.message_id, a.name, a.mime_type
+            FROM attachments a
+            JOIN messages m ON m.id = a.message_id
+            WHERE m.conversation_id = ? AND a.content IS NOT NULL
+            ORDER BY m.idx, a.id
+            ",
+        )
+        .bind(conversation_id.to_string())
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|(id, message_id, name, mime_type)| {
+                Some(AttachmentInfo {
+                    id,
+                    message_id: Uuid::parse_str(&message_id).ok()?,
+                    name,
+                    mime_type,
+                })
+            })
+            .collect())
+    }
+
+    // =========================================================================
+    // Tool calls
+    // =========================================================================
+
+    /// Replace the tool calls of messages `from_idx..=to_idx` in each listed
+    /// conversation with `extracted.calls`, then apply `extracted.outcomes`
+    /// to whichever stored calls they answer.
+    ///
+    /// Calls are attached by `(conversation_id, idx)` rather than message id
+    /// because re-imports keep the id of the row already stored there.
+    /// Sensitive conversations keep no tool calls, since those hold message
+    /// content in plaintext.
+    pub async fn write_tool_calls_in_tx(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        cleared: &[(Uuid, i32, i32)],
+        extracted: &Extracted,
+    ) -> Result<()> {
+        for (conversation_id, from_idx, to_idx) in cleared {
+            sqlx::query(
+                "DELETE FROM tool_calls WHERE message_id IN \
+                 (SELECT id FROM messages WHERE conversation_id = ? AND idx BETWEEN ? AND ?)",
+            )
+            .bind(conversation_id.to_string())
+            .bind(from_idx)
+            .bind(to_idx)
+            .execute(&mut **tx)
+            .await?;
+        }
+
+        let mut conversation_ids: Vec<Uuid> = extracted
+            .calls
+            .iter()
+            .map(|call| call.conversation_id)
+            .collect();
+        conversation_ids.sort_unstable();
+        conversation_ids.dedup();
+        let sensitive = self.sensitive_ids(tx, &conversation_ids).await?;
+
+        for call in &extracted.calls {
+            if sensitive.contains(&call.conversation_id) {
+                continue;
+            }
+            sqlx::query(
+                "INSERT OR REPLACE INTO tool_calls \
+                 (id, message_id, tool_name, input, output, status, duration_ms) \
+                 SELECT ?, id, ?, ?, ?, ?, ? FROM messages WHERE conversation_id = ? AND idx = ?",
+            )
+            .bind(call.id.to_string())
+            .bind(&call.tool_name)
+            .bind(call.input.as_ref().map(ToString::to_string))
+            .bind(&call.output)
+            .bind(&call.status)
+            .bind(call.duration_ms)
+            .bind(call.conversation_id.to_string())
+            .bind(call.idx)
+            .execute(&mut **tx)
+            .await?;
+        }
+
+        for outcome in &extracted.outcomes {
+            sqlx::query(
+                "UPDATE tool_calls SET output = COALESCE(?, output), status = ?, \
+                 duration_ms = COALESCE(?, duration_ms) WHERE id = ?",
+            )
+            .bind(&outcome.output)
+            .bind(&outcome.status)
+            .bind(outcome.duration_ms)
+            .bind(outcome.id.to_string())
+            .execute(&mut **tx)
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Insert already-materialized tool calls (message ids must exist).
+    pub async fn insert_tool_calls_in_tx(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        calls: &[ToolCall],
+    ) -> Result<()> {
+        for call in calls {
+            sqlx::query(
+                "INSERT OR REPLACE INTO tool_calls \
+                 (id, message_id, tool_name, input, output, status, duration_ms) \
+                 VALUES (?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(call.id.to_string())
+            .bind(call.message_id.to_string())
+            .bind(&call.tool_name)
+            .bind(call.input.as_ref().map(ToString::to_string))
+            .bind(&call.output)
+            .bind(call.status.as_ref().map(ToolStatus::as_str))
+            .bind(call.duration_ms)
+            .execute(&mut **tx)
+            .await?;
+        }
+        Ok(())
+    }
 
-        sqlx::query(
-            r"
-            INSERT INTO attachments (id, message_id, type, name, mime_type, content)
-            VALUES (?, ?, ?, ?, ?, ?)
-            ",
+    /// Tool calls of a conversation, in message order.
+    pub async fn get_tool_calls(&self, conversation_id: Uuid) -> Result<Vec<ToolCall>> {
+        let rows = sqlx::query(
+            "SELECT t.* FROM tool_calls t JOIN messages m ON m.id = t.message_id \
+             WHERE m.conversation_id = ? ORDER BY m.idx, t.rowid",
         )
-        .bind(id)
-        .bind(message_id.to_string())
-        .bind(attachment_type)
-        .bind(filename)
-        .bind(mime_type)
-        .bind(data)
-        .execute(&self.pool)
+        .bind(conversation_id.to_string())
+        .fetch_all(self.reader())
         .await?;
-        Ok(())
+        Ok(rows.iter().map(row_to_tool_call).collect())
     }
 
-    /// Get attachment binary data by ID.
-    pub async fn get_attachment(&self, id: &str) -> Result<Option<(String, Vec<u8>)>> {
-        let row = sqlx::query("SELECT mime_type, content FROM attachments WHERE id = ?")
-            .bind(id)
-            .fetch_optional(&self.pool)
-            .await?;
+    /// Most recent tool calls across conversations, newest first.
+    pub async fn list_tool_calls(&self, opts: &ListToolCallsOptions) -> Result<Vec<ToolCallEntry>> {
+        let mut sql = String::from(
+            "SELECT t.*, m.conversation_id, m.created_at AS message_created_at, \
+                    c.title, c.source_id, c.workspace \
+             FROM tool_calls t \
+             JOIN messages m ON m.id = t.message_id \
+             JOIN conversations c ON c.id = m.conversation_id",
+        );
+        let binds = push_tool_call_filters(&mut sql, opts);
+        sql.push_str(
+            " ORDER BY COALESCE(m.created_at, c.created_at) DESC, m.idx DESC, t.rowid DESC",
+        );
+        push_limit_offset(&mut sql, opts.limit, None);
 
-        Ok(row.map(|row| {
-            let mime_type: String = row.get("mime_type");
-            let content: Vec<u8> = row.get("content");
-            (mime_type, content)
-        }))
+        let mut query = sqlx::query(&sql);
+        for value in binds {
+            query = query.bind(value);
+        }
+        let rows = query.fetch_all(self.reader()).await?;
+        Ok(rows
+            .iter()
+            .map(|row| ToolCallEntry {
+                tool_call: row_to_tool_call(row),
+                conversation_id: Uuid::parse_str(row.get::<&str, _>("conversation_id"))
+                    .unwrap_or_default(),
+                title: row.get("title"),
+                source_id: row.get("source_id"),
+                workspace: row.get("workspace"),
+                created_at: row
+                    .get::<Option<i64>, _>("message_created_at")
+                    .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0)),
+            })
+            .collect())
+    }
+
+    /// Per-tool usage counts, most used first. `opts.limit` caps the number
+    /// of tools returned.
+    pub async fn tool_usage_stats(&self, opts: &ListToolCallsOptions) -> Result<Vec<ToolUsage>> {
+        let mut sql = String::from(
+            "SELECT t.tool_name, COUNT(*) AS calls, \
+                    SUM(CASE WHEN t.status = 'error' THEN 1 ELSE 0 END) AS errors, \
+                    COUNT(DISTINCT m.conversation_id) AS conversations, \
+                    AVG(t.duration_ms) AS avg_duration_ms \
+             FROM tool_calls t \
+             JOIN messages m ON m.id = t.message_id \
+             JOIN conversations c ON c.id = m.conversation_id",
+        );
+        let binds = push_tool_call_filters(&mut sql, opts);
+        sql.push_str(" GROUP BY t.tool_name ORDER BY calls DESC, t.tool_name");
+        push_limit_offset(&mut sql, opts.limit, None);
+
+        let mut query = sqlx::query(&sql);
+        for value in binds {
+            query = query.bind(value);
+        }
+        let rows = query.fetch_all(self.reader()).await?;
+        Ok(rows
+            .iter()
+            .map(|row| ToolUsage {
+                tool_name: row.get("tool_name"),
+                calls: row.get("calls"),
+                errors: row.get("errors"),
+                conversations: row.get("conversations"),
+                avg_duration_ms: row.get("avg_duration_ms"),
+            })
+            .collect())
+    }
+
+    /// Re-derive every conversation's tool calls from stored message parts.
+    /// Used to populate the table for history ingested before tool calls
+    /// were recorded. Returns the number of calls stored.
+    pub async fn rebuild_tool_calls(&self) -> Result<usize> {
+        let ids: Vec<String> = sqlx::query_scalar("SELECT id FROM conversations")
+            .fetch_all(self.reader())
+            .await?;
+        let mut stored = 0usize;
+        for id in ids {
+            let Ok(conversation_id) = Uuid::parse_str(&id) else {
+                continue;
+            };
+            let messages = self.get_messages(conversation_id).await?;
+            let mut extracted = Extracted::default();
+            for msg in &messages {
+                extracted.extend(crate::tool_calls::extract(
+                    conversation_id,
+                    msg.idx,
+                    &msg.parts_json,
+                    None,
+                ));
+            }
+            if extracted.is_empty() {
+                continue;
+            }
+            stored += extracted.calls.len();
+            let _writer = self.lock_ingest_writer().await;
+            let mut tx = self.begin().await?;
+            self.write_tool_calls_in_tx(&mut tx, &[(conversation_id, 0, i32::MAX)], &extracted)
+                .await?;
+            tx.commit().await?;
+        }
+        Ok(stored)
     }
 
     // =========================================================================
@@ -2099,11 +5283,35 @@ impl Database {
     // =========================================================================
 
     /// Full-text search across messages with snippet and provenance.
+    ///
+    /// `label:<name>` terms in `query` keep messages carrying that label. A
+    /// query of only label terms lists the labelled messages, newest first.
     pub async fn search(&self, query: &str, opts: SearchOptions) -> Result<Vec<SearchHit>> {
-        let mode = opts.mode.resolve(query);
+        let (query, labels) = labels::split_query(query);
+        let mode = opts.mode.resolve(&query);
         let table = mode.table_name();
-        let query = sanitize_fts_query(query);
+        let (query, scanned) = if table == "messages_fts" && self.tokenizer_is_trigram() {
+            split_trigram_query(&query)
+        } else {
+            (query, Vec::new())
+        };
+        let full_text = !query.is_empty() || (labels.is_empty() && scanned.is_empty());
+        let query = sanitize_fts_query(&query);
 
+        let (snippet, score, from) = if full_text {
+            (
+                format!("snippet({table}, 0, '[', ']', '…', 12)"),
+                ranked_score(&format!("bm25({table})"), &query, &self.ranking),
+                format!("{table} JOIN messages m ON m.rowid = {table}.rowid"),
+            )
+        } else {
+            (
+                "substr(m.content, 1, 160)".to_string(),
+                RankedScore::plain("0.0"),
+                "messages m".to_string(),
+            )
+        };
+        let score_sql = &score.sql;
         let mut sql = format!(
             r"
             SELECT
@@ -2120,18 +5328,26 @@ impl Database {
                 c.readable_id AS readable_id,
                 c.title AS title,
                 c.workspace AS workspace,
+                w.display_name AS workspace_name,
                 s.adapter AS source_adapter,
                 s.path AS source_path,
-                snippet({table}, 0, '[', ']', '…', 12) AS snippet,
-                bm25({table}) AS score
-            FROM {table}
-            JOIN messages m ON m.rowid = {table}.rowid
+                {snippet} AS snippet,
+                {score_sql} AS score
+            FROM {from}
             JOIN conversations c ON c.id = m.conversation_id
             JOIN sources s ON s.id = c.source_id
-            WHERE {table} MATCH ?
+            LEFT JOIN workspaces w ON w.path = c.workspace
+            WHERE c.deleted_at IS NULL
             "
         );
 
+        if full_text {
+            let _ = write!(sql, " AND {table} MATCH ?");
+        }
+        for _ in &scanned {
+            sql.push_str(" AND instr(lower(m.content), ?) > 0");
+        }
+
         if opts.source_id.is_some() {
             sql.push_str(" AND (c.source_id = ? OR c.source_id LIKE ?)");
         }
@@ -2156,8 +5372,20 @@ impl Database {
         if opts.tag.is_some() {
             sql.push_str(" AND c.id IN (SELECT ct.conversation_id FROM conversation_tags ct JOIN tags t ON t.id = ct.tag_id WHERE t.name = ?)");
         }
+        if opts.starred {
+            sql.push_str(" AND c.starred = 1");
+        }
+        for _ in &labels {
+            sql.push_str(
+                " AND EXISTS (SELECT 1 FROM json_each(CASE WHEN json_valid(m.metadata) THEN m.metadata ELSE '{}' END, '$.labels') WHERE value = ?)",
+            );
+        }
 
-        sql.push_str(" ORDER BY score ASC");
+        if full_text {
+            sql.push_str(" ORDER BY score ASC");
+        } else {
+            sql.push_str(" ORDER BY m.created_at DESC, m.idx DESC");
+        }
 
         if let Some(limit) = opts.limit {
             let _ = write!(sql, " LIMIT {limit}");
@@ -2167,7 +5395,17 @@ impl Database {
         }
 
         let mut query_builder = sqlx::query(&sql);
-        query_builder = query_builder.bind(query);
+        // The score comes first in the statement, so its values are bound
+        // first.
+        for value in &score.binds {
+            query_builder = query_builder.bind(value);
+        }
+        if full_text {
+            query_builder = query_builder.bind(query);
+        }
+        for term in &scanned {
+            query_builder = query_builder.bind(term.to_lowercase());
+        }
 
         if let Some(ref source_id) = opts.source_id {
             query_builder = query_builder.bind(source_id).bind(format!("{source_id}-%"));
@@ -2193,8 +5431,11 @@ impl Database {
         if let Some(ref tag) = opts.tag {
             query_builder = query_builder.bind(tag.trim().to_lowercase());
         }
+        for label in &labels {
+            query_builder = query_builder.bind(label);
+        }
 
-        let rows = query_builder.fetch_all(&self.pool).await?;
+        let rows = query_builder.fetch_all(self.reader()).await?;
 
         let mut hits = Vec::new();
         for row in rows {
@@ -2225,10 +5466,13 @@ impl Database {
                 readable_id: row.get("readable_id"),
                 title: row.get("title"),
                 workspace: row.get("workspace"),
+                workspace_name: row.get("workspace_name"),
                 source_adapter: row.get("source_adapter"),
                 source_path: row.get("source_path"),
                 host: None,
                 occurrences: None,
+                conversation: None,
+                context: None,
             });
         }
 
@@ -2252,6 +5496,157 @@ impl Database {
         Ok(usize::try_from(messages_count.max(0)).unwrap_or(usize::MAX))
     }
 
+    /// Segment, document and size figures for both FTS5 search tables.
+    pub async fn search_index_status(&self) -> Result<SearchIndexStatus> {
+        let (messages,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM messages")
+            .fetch_one(&self.pool)
+            .await?;
+
+        let mut tables = Vec::new();
+        for table in ["messages_fts", "messages_code_fts"] {
+            // The structure record (rowid 10) lists the table's segments.
+            let structure: Option<Vec<u8>> =
+                sqlx::query_scalar(&format!("SELECT block FROM {table}_data WHERE id = 10"))
+                    .fetch_optional(&self.pool)
+                    .await?;
+            let (documents,): (i64,) =
+                sqlx::query_as(&format!("SELECT COUNT(*) FROM {table}_docsize"))
+                    .fetch_one(&self.pool)
+                    .await?;
+            let (size_bytes,): (i64,) = sqlx::query_as(&format!(
+                "SELECT COALESCE(SUM(length(block)), 0) FROM {table}_data"
+            ))
+            .fetch_one(&self.pool)
+            .await?;
+            tables.push(SearchTableStatus {
+                table: table.to_string(),
+                segments: structure
+                    .as_deref()
+                    .and_then(fts_segment_count)
+                    .unwrap_or(0),
+                documents,
+                size_bytes,
+            });
+        }
+
+        let tokenizer = *self
+            .tokenizer
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        Ok(SearchIndexStatus {
+            messages,
+            tokenizer,
+            tables,
+        })
+    }
+
+    /// Merge the segments of both FTS5 search tables into one each, which
+    /// keeps queries fast after many small writes.
+    pub async fn optimize_search_fts(&self) -> Result<()> {
+        sqlx::raw_sql("INSERT INTO messages_fts(messages_fts) VALUES('optimize')")
+            .execute(&self.pool)
+            .await?;
+        sqlx::raw_sql("INSERT INTO messages_code_fts(messages_code_fts) VALUES('optimize')")
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Space used inside the database file: pages in use and free, each
+    /// table with its indexes, and the attachment store. Table sizes need
+    /// SQLite's `dbstat` table and are left empty without it.
+    pub async fn database_size(&self) -> Result<DatabaseSize> {
+        let (page_size,): (i64,) = sqlx::query_as("PRAGMA page_size")
+            .fetch_one(self.reader())
+            .await?;
+        let (pages,): (i64,) = sqlx::query_as("PRAGMA page_count")
+            .fetch_one(self.reader())
+            .await?;
+        let (free_pages,): (i64,) = sqlx::query_as("PRAGMA freelist_count")
+            .fetch_one(self.reader())
+            .await?;
+        let (attachments, attachment_bytes): (i64, i64) =
+            sqlx::query_as("SELECT COUNT(*), COALESCE(SUM(length(content)), 0) FROM attachments")
+                .fetch_one(self.reader())
+                .await?;
+
+        let objects: Vec<(String, String, i64)> = match sqlx::query_as(
+            "SELECT COALESCE(m.tbl_name, s.name), COALESCE(m.type, 'table'), SUM(s.pgsize)
+             FROM dbstat s LEFT JOIN sqlite_master m ON m.name = s.name
+             GROUP BY s.name",
+        )
+        .fetch_all(self.reader())
+        .await
+        {
+            Ok(objects) => objects,
+            Err(err) => {
+                tracing::debug!("Table sizes unavailable: {err}");
+                Vec::new()
+            }
+        };
+        let mut sizes: HashMap<String, TableSize> = HashMap::new();
+        for (table, kind, bytes) in objects {
+            let size = sizes.entry(table.clone()).or_insert_with(|| TableSize {
+                name: table,
+                table_bytes: 0,
+                index_bytes: 0,
+            });
+            if kind == "index" {
+                size.index_bytes += bytes;
+            } else {
+                size.table_bytes += bytes;
+            }
+        }
+        let mut tables: Vec<TableSize> = sizes.into_values().collect();
+        tables.sort_by(|a, b| {
+            (b.table_bytes + b.index_bytes)
+                .cmp(&(a.table_bytes + a.index_bytes))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+
+        Ok(DatabaseSize {
+            bytes: page_size * pages,
+            free_bytes: page_size * free_pages,
+            tables,
+            attachments,
+            attachment_bytes,
+        })
+    }
+
+    /// Rewrite the database file without its free pages, then truncate the
+    /// write-ahead log, so space freed by deletes and prunes goes back to
+    /// the file system. Needs about as much free disk space as the database
+    /// and holds the write gate while it runs.
+    pub async fn vacuum(&self) -> Result<()> {
+        let _writer = self.lock_ingest_writer().await;
+        sqlx::raw_sql("VACUUM").execute(&self.pool).await?;
+        sqlx::raw_sql("PRAGMA wal_checkpoint(TRUNCATE)")
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Bring the search index up to date after writes: rebuild it when a
+    /// table indexes a different number of messages than are stored, else
+    /// do a bounded amount of segment merging. Returns whether it rebuilt.
+    pub async fn catch_up_search_fts(&self) -> Result<bool> {
+        // Leaf pages merged per table; small enough to run after every sync.
+        const MERGE_PAGES: usize = 64;
+
+        if self.search_index_status().await?.is_stale() {
+            self.rebuild_search_fts().await?;
+            return Ok(true);
+        }
+        for table in ["messages_fts", "messages_code_fts"] {
+            sqlx::raw_sql(&format!(
+                "INSERT INTO {table}({table}, rank) VALUES('merge', {MERGE_PAGES})"
+            ))
+            .execute(&self.pool)
+            .await?;
+        }
+        Ok(false)
+    }
+
     /// Optimized FTS schema initialization that checks integrity only when needed.
     async fn ensure_fts_schema_optimized(&self) -> Result<()> {
         let force_integrity_check = std::env::var("HSTRY_FTS_INTEGRITY_CHECK")
@@ -2294,17 +5689,43 @@ impl Database {
             .fetch_one(&self.pool)
             .await?;
 
-        self.ensure_fts_table(
-            "messages_fts",
+        let existing: Option<String> = sqlx::query_scalar(
+            "SELECT sql FROM sqlite_master WHERE type='table' AND name = 'messages_fts'",
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        let built_with = existing.as_deref().and_then(fts_tokenizer);
+        let configured = *self
+            .tokenizer
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        let tokenizer = configured.or(built_with).unwrap_or_default();
+        if let Some(previous) = built_with.filter(|previous| *previous != tokenizer) {
+            tracing::info!(
+                "Search tokenizer changed from {previous:?} to {tokenizer:?}; rebuilding the full-text index"
+            );
+        }
+        // Prefix indexes only help word tokenizers; trigrams match any
+        // substring already.
+        let prefix = match tokenizer {
+            SearchTokenizer::Trigram => "",
+            SearchTokenizer::Porter | SearchTokenizer::Unicode => ", prefix = '2 3 4'",
+        };
+        let create_sql = format!(
             r"
             CREATE VIRTUAL TABLE messages_fts USING fts5(
                 content,
                 content=messages,
                 content_rowid=rowid,
-                tokenize = 'porter',
-                prefix = '2 3 4'
+                tokenize = '{}'{prefix}
             );
             ",
+            tokenizer.fts5_spec()
+        );
+
+        self.ensure_fts_table(
+            "messages_fts",
+            &create_sql,
             &[
                 r"
                 CREATE TRIGGER messages_ai AFTER INSERT ON messages BEGIN
@@ -2328,9 +5749,16 @@ impl Database {
             &["messages_ai", "messages_ad", "messages_au"],
             run_integrity_check,
             messages_count.0,
-            |sql| sql.contains("tokenize = 'porter'") && sql.contains("prefix = '2 3 4'"),
+            |sql| {
+                fts_tokenizer(sql) == Some(tokenizer)
+                    && (tokenizer == SearchTokenizer::Trigram || sql.contains("prefix = '2 3 4'"))
+            },
         )
         .await?;
+        *self
+            .tokenizer
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = Some(tokenizer);
 
         self.ensure_fts_table(
             "messages_code_fts",
@@ -2626,7 +6054,8 @@ impl Database {
         if max_age_days > 0 {
             let cutoff = chrono::Utc::now().timestamp() - i64::from(max_age_days) * 86_400;
             let res = sqlx::query(
-                "DELETE FROM message_events WHERE created_at IS NOT NULL AND created_at < ?",
+                "DELETE FROM message_events \
+                 WHERE kind = 'upsert' AND created_at IS NOT NULL AND created_at < ?",
             )
             .bind(cutoff)
             .execute(&self.pool)
@@ -2649,6 +6078,7 @@ impl Database {
                                    ORDER BY COALESCE(created_at, 0) DESC, idx DESC
                                ) AS rn
                         FROM message_events
+                        WHERE kind = 'upsert'
                     )
                     WHERE rn > ?
                 )
@@ -2663,15 +6093,208 @@ impl Database {
         Ok(total)
     }
 
-    /// Count the number of `message_events` rows. Useful for diagnostics and
-    /// `hstry stats` style commands.
+    /// Count the number of `message_events` rows, not counting message
+    /// revisions. Useful for diagnostics and `hstry stats` style commands.
     pub async fn count_message_events(&self) -> Result<i64> {
-        let row: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM message_events")
-            .fetch_one(&self.pool)
-            .await?;
+        let row: (i64,) =
+            sqlx::query_as("SELECT COUNT(*) FROM message_events WHERE kind = 'upsert'")
+                .fetch_one(&self.pool)
+                .await?;
         Ok(row.0)
     }
 
+    // =========================================================================
+    // Archive transfer
+    // =========================================================================
+
+    /// Move conversations (with their messages, tool calls, tags, star and
+    /// source row) into `target`, then remove them from this database.
+    ///
+    /// Used for both archiving (hot -> archive) and unarchiving
+    /// (archive -> hot). A copy already present in `target` under the same id
+    /// or `source_id`/`external_id` is replaced. Ids that don't exist here
+    /// are skipped. Returns the number of conversations moved.
+    ///
+    /// Sensitive conversations need this database unlocked; they stay sealed
+    /// in `target`, which takes over the sensitive key if it has none.
+    pub async fn transfer_conversations(&self, target: &Database, ids: &[Uuid]) -> Result<usize> {
+        let mut moved = Vec::with_capacity(ids.len());
+        for &id in ids {
+            let Some(conv) = self.get_conversation(id).await? else {
+                continue;
+            };
+            let sensitive = self.is_sensitive(id).await?;
+            if sensitive {
+                if !self.is_unlocked() {
+                    return Err(Error::Config(format!(
+                        "conversation {id} is sensitive; run `hstry sensitive unlock` to move it"
+                    )));
+                }
+                target.share_sensitive_key(self).await?;
+            }
+            let starred = self.is_starred(id).await?;
+            let messages = self.get_messages(id).await?;
+            let tool_calls = self.get_tool_calls(id).await?;
+            let tags = self.get_conversation_tags(id).await?;
+
+            if target.get_source(&conv.source_id).await?.is_none()
+                && let Some(source) = self.get_source(&conv.source_id).await?
+            {
+                target.upsert_source(&source).await?;
+            }
+
+            let mut stale = vec![id];
+            if let Some(external_id) = conv.external_id.as_deref()
+                && let Some(existing) = target
+                    .get_conversation_id(&conv.source_id, external_id)
+                    .await?
+                && existing != id
+            {
+                stale.push(existing);
+            }
+            target.delete_conversations_batch(&stale).await?;
+
+            let mut tx = target.begin().await?;
+            target.upsert_conversation_in_tx(&mut tx, &conv).await?;
+            if sensitive {
+                sqlx::query("UPDATE conversations SET sensitive = 1 WHERE id = ?")
+                    .bind(id.to_string())
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            if starred {
+                sqlx::query("UPDATE conversations SET starred = 1 WHERE id = ?")
+                    .bind(id.to_string())
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            target
+                .bulk_insert_messages_in_tx(&mut tx, &messages)
+                .await?;
+            target.insert_tool_calls_in_tx(&mut tx, &tool_calls).await?;
+            tx.commit().await?;
+            for tag in &tags {
+                target.add_conversation_tag(id, tag).await?;
+            }
+            moved.push(id);
+        }
+
+        target.rebuild_conversation_summaries(&moved).await?;
+        self.delete_conversations_batch(&moved).await?;
+        Ok(moved.len())
+    }
+
+    // =========================================================================
+    // Conversation retention
+    // =========================================================================
+
+    /// Find conversations that fall outside a retention policy, oldest first.
+    ///
+    /// A conversation is a candidate when its last activity is older than
+    /// `max_age_days` (when > 0) or when it ranks beyond the newest
+    /// `max_per_source` conversations of its source (when > 0). Conversations
+    /// tagged with any of `exclude_tags`, and starred ones, are neither
+    /// returned nor counted towards the per-source limit.
+    pub async fn find_retention_candidates(
+        &self,
+        max_age_days: u32,
+        max_per_source: u32,
+        exclude_tags: &[String],
+    ) -> Result<Vec<Conversation>> {
+        self.find_retention_candidates_in(
+            &RetentionScope {
+                max_age_days,
+                max_conversations_per_source: max_per_source,
+                only_sources: None,
+                skip_sources: Vec::new(),
+                include_starred: false,
+            },
+            exclude_tags,
+        )
+        .await
+    }
+
+    /// [`Self::find_retention_candidates`] limited to the sources of `scope`;
+    /// starred conversations are candidates when `scope.include_starred`.
+    pub async fn find_retention_candidates_in(
+        &self,
+        scope: &RetentionScope,
+        exclude_tags: &[String],
+    ) -> Result<Vec<Conversation>> {
+        let max_age_days = scope.max_age_days;
+        let max_per_source = scope.max_conversations_per_source;
+        if !scope.has_limits() || scope.only_sources.as_ref().is_some_and(Vec::is_empty) {
+            return Ok(Vec::new());
+        }
+
+        let tags: Vec<String> = exclude_tags
+            .iter()
+            .map(|tag| tag.trim().to_lowercase())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+
+        let mut sql = String::from(
+            r"WITH eligible AS (
+                SELECT c.*,
+                       COALESCE(c.updated_at, c.created_at) AS last_active,
+                       ROW_NUMBER() OVER (
+                           PARTITION BY c.source_id
+                           ORDER BY COALESCE(c.updated_at, c.created_at) DESC, c.id
+                       ) AS source_rank
+                FROM conversations c
+                WHERE 1=1",
+        );
+        if !scope.include_starred {
+            sql.push_str(" AND c.starred = 0");
+        }
+        if !tags.is_empty() {
+            let placeholders = vec!["?"; tags.len()].join(",");
+            let _ = write!(
+                sql,
+                " AND NOT EXISTS (
+                    SELECT 1 FROM conversation_tags ct
+                    JOIN tags t ON t.id = ct.tag_id
+                    WHERE ct.conversation_id = c.id AND t.name IN ({placeholders})
+                )"
+            );
+        }
+        if let Some(only) = &scope.only_sources {
+            let placeholders = vec!["?"; only.len()].join(",");
+            let _ = write!(sql, " AND c.source_id IN ({placeholders})");
+        }
+        if !scope.skip_sources.is_empty() {
+            let placeholders = vec!["?"; scope.skip_sources.len()].join(",");
+            let _ = write!(sql, " AND c.source_id NOT IN ({placeholders})");
+        }
+        sql.push_str(
+            r")
+            SELECT * FROM eligible
+            WHERE (? > 0 AND last_active < ?) OR (? > 0 AND source_rank > ?)
+            ORDER BY last_active ASC",
+        );
+
+        let cutoff = chrono::Utc::now().timestamp() - i64::from(max_age_days) * 86_400;
+        let mut query = sqlx::query(&sql);
+        for tag in &tags {
+            query = query.bind(tag);
+        }
+        for source_id in scope.only_sources.iter().flatten() {
+            query = query.bind(source_id);
+        }
+        for source_id in &scope.skip_sources {
+            query = query.bind(source_id);
+        }
+        let rows = query
+            .bind(i64::from(max_age_days))
+            .bind(cutoff)
+            .bind(i64::from(max_per_source))
+            .bind(i64::from(max_per_source))
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.iter().map(conversation_from_row).collect())
+    }
+
     // =========================================================================
     // Indexer outbox (trx-z42c.5/.6)
     // =========================================================================
@@ -2843,6 +6466,7 @@ impl Database {
             return Ok(removed);
         }
 
+        self.create_snapshot(conversation_id, "dedup").await?;
         let mut tx = self.pool.begin().await?;
         for chunk in to_delete.chunks(500) {
             let placeholders: String = chunk.iter().map(|_| "?").collect::<Vec<_>>().join(",");
@@ -2890,7 +6514,135 @@ impl Database {
                     .await?;
             }
         }
-        Ok(total)
+        Ok(total)
+    }
+
+    /// Split a linear conversation into sessions. `starts` are the `idx`
+    /// values where the second, third, ... session begin (ascending); the
+    /// messages from each start up to the next move into a conversation of
+    /// their own, with external id `<external id>#<n>`. Returns those
+    /// conversations' ids.
+    ///
+    /// Moved messages get new ids derived from the old ones. A later sync
+    /// that puts the messages back into the original conversation can then
+    /// store them under their old ids, and splitting again replaces the
+    /// sessions' messages instead of duplicating them.
+    pub async fn split_conversation(
+        &self,
+        conversation_id: Uuid,
+        starts: &[i32],
+    ) -> Result<Vec<Uuid>> {
+        let Some(conv) = self.get_conversation(conversation_id).await? else {
+            return Err(Error::NotFound(format!("conversation '{conversation_id}'")));
+        };
+        let messages = self.get_messages(conversation_id).await?;
+        let base = conv
+            .external_id
+            .clone()
+            .unwrap_or_else(|| conversation_id.to_string());
+
+        let mut sessions = Vec::with_capacity(starts.len());
+        for (k, start) in starts.iter().enumerate() {
+            let end = starts.get(k + 1).copied().unwrap_or(i32::MAX);
+            let moved: Vec<&Message> = messages
+                .iter()
+                .filter(|m| m.idx >= *start && m.idx < end)
+                .collect();
+            let (Some(first), Some(last)) = (moved.first(), moved.last()) else {
+                continue;
+            };
+            let external_id = format!("{base}#{}", k + 2);
+            let id = self
+                .get_conversation_id(&conv.source_id, &external_id)
+                .await?
+                .unwrap_or_else(Uuid::new_v4);
+            self.upsert_conversation(&Conversation {
+                id,
+                external_id: Some(external_id),
+                readable_id: None,
+                platform_id: None,
+                title: None,
+                created_at: first.created_at.unwrap_or(conv.created_at),
+                updated_at: last.created_at,
+                tokens_in: None,
+                tokens_out: None,
+                cost_usd: None,
+                metadata: serde_json::json!({
+                    "segment_of": conversation_id.to_string(),
+                    "segment": k + 2,
+                }),
+                version: 0,
+                message_count: 0,
+                parent_conversation_id: None,
+                parent_message_idx: None,
+                fork_type: None,
+                ..conv.clone()
+            })
+            .await?;
+            sessions.push((id, moved));
+        }
+        if !sessions.is_empty() {
+            self.create_snapshot(conversation_id, "segment").await?;
+        }
+
+        let mut tx = self.pool.begin().await?;
+        for (session_id, moved) in &sessions {
+            sqlx::query("DELETE FROM messages WHERE conversation_id = ?")
+                .bind(session_id.to_string())
+                .execute(&mut *tx)
+                .await?;
+            for (new_idx, msg) in moved.iter().enumerate() {
+                let old_id = msg.id.to_string();
+                let new_id = Uuid::new_v5(&msg.id, b"segment").to_string();
+                sqlx::query(
+                    r"
+                    INSERT INTO messages (id, conversation_id, idx, role, content, parts_json, created_at, model, tokens, cost_usd, metadata, sender_json, provider, harness, client_id, parent_message_id, sealed)
+                    SELECT ?, ?, ?, role, content, parts_json, created_at, model, tokens, cost_usd, metadata, sender_json, provider, harness, client_id, NULL, sealed
+                    FROM messages WHERE id = ?
+                    ",
+                )
+                .bind(&new_id)
+                .bind(session_id.to_string())
+                .bind(i32::try_from(new_idx).unwrap_or(i32::MAX))
+                .bind(&old_id)
+                .execute(&mut *tx)
+                .await?;
+                for table in ["tool_calls", "attachments"] {
+                    sqlx::query(&format!(
+                        "UPDATE {table} SET message_id = ? WHERE message_id = ?"
+                    ))
+                    .bind(&new_id)
+                    .bind(&old_id)
+                    .execute(&mut *tx)
+                    .await?;
+                }
+                // Replaying the event would put the message back where it was.
+                sqlx::query("DELETE FROM message_events WHERE id = ?")
+                    .bind(&old_id)
+                    .execute(&mut *tx)
+                    .await?;
+                sqlx::query(
+                    "UPDATE message_events SET conversation_id = ?, idx = ? \
+                     WHERE conversation_id = ? AND idx = ? AND kind != 'upsert'",
+                )
+                .bind(session_id.to_string())
+                .bind(i32::try_from(new_idx).unwrap_or(i32::MAX))
+                .bind(conversation_id.to_string())
+                .bind(msg.idx)
+                .execute(&mut *tx)
+                .await?;
+                sqlx::query("DELETE FROM messages WHERE id = ?")
+                    .bind(&old_id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+        }
+        tx.commit().await?;
+
+        let mut affected: Vec<Uuid> = sessions.iter().map(|(id, _)| *id).collect();
+        affected.push(conversation_id);
+        self.rebuild_conversation_summaries(&affected).await?;
+        Ok(sessions.into_iter().map(|(id, _)| id).collect())
     }
 
     // =========================================================================
@@ -2963,7 +6715,7 @@ impl Database {
     }
 
     /// Recreate indexes dropped by [`Database::begin_bulk_reseed`] and
-    /// restore production PRAGMA settings.
+    /// restore the configured PRAGMA settings.
     pub async fn end_bulk_reseed(&self) -> Result<()> {
         sqlx::raw_sql(
             "CREATE INDEX IF NOT EXISTS idx_messages_conv_idx ON messages(conversation_id, idx); \
@@ -2973,13 +6725,59 @@ impl Database {
         )
         .execute(&self.pool)
         .await?;
-        sqlx::raw_sql("PRAGMA synchronous = NORMAL;")
-            .execute(&self.pool)
-            .await?;
+        sqlx::raw_sql(&format!(
+            "PRAGMA synchronous = {}; \
+             PRAGMA cache_size = {}; \
+             PRAGMA mmap_size = {};",
+            self.tuning.synchronous.as_str(),
+            self.tuning.cache_size,
+            self.tuning.mmap_size
+        ))
+        .execute(&self.pool)
+        .await?;
         sqlx::raw_sql("ANALYZE;").execute(&self.pool).await?;
         Ok(())
     }
 
+    /// Point messages at their parents by position: each
+    /// `(conversation_id, idx, parent_idx)` sets the message at `idx` to
+    /// continue from the one at `parent_idx`.
+    ///
+    /// Links go by position because a re-imported row keeps the id it was
+    /// first stored with, which need not match a freshly computed id.
+    pub async fn link_message_parents_in_tx(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        links: &[(Uuid, i32, i32)],
+    ) -> Result<()> {
+        for (conversation_id, idx, parent_idx) in links {
+            sqlx::query(
+                "UPDATE messages SET parent_message_id = \
+                 (SELECT p.id FROM messages p WHERE p.conversation_id = ?1 AND p.idx = ?3) \
+                 WHERE conversation_id = ?1 AND idx = ?2",
+            )
+            .bind(conversation_id.to_string())
+            .bind(idx)
+            .bind(parent_idx)
+            .execute(&mut **tx)
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Whether any message in the conversation records a parent, i.e. the
+    /// conversation has message-level branches.
+    pub async fn has_message_branches(&self, conversation_id: Uuid) -> Result<bool> {
+        let exists: bool = sqlx::query_scalar(
+            "SELECT EXISTS(SELECT 1 FROM messages \
+             WHERE conversation_id = ? AND parent_message_id IS NOT NULL)",
+        )
+        .bind(conversation_id.to_string())
+        .fetch_one(self.reader())
+        .await?;
+        Ok(exists)
+    }
+
     /// Bulk-insert many messages into the same transaction using multi-row
     /// `INSERT ... VALUES (...), (...), ...` statements. Falls back to per-row
     /// inserts when a chunk would exceed SQLite's bound-parameter limit.
@@ -2995,43 +6793,55 @@ impl Database {
         if messages.is_empty() {
             return Ok(());
         }
-        // 15 columns per row; SQLite default SQLITE_MAX_VARIABLE_NUMBER is
-        // 999 (250000 in newer builds, but stay conservative). 15 * 60 = 900.
-        const COLS: usize = 15;
-        const ROWS_PER_CHUNK: usize = 60;
+        // 17 columns per row; SQLite default SQLITE_MAX_VARIABLE_NUMBER is
+        // 999 (250000 in newer builds, but stay conservative). 17 * 55 = 935.
+        const COLS: usize = 17;
+        const ROWS_PER_CHUNK: usize = 55;
+
+        let mut conversation_ids: Vec<Uuid> =
+            messages.iter().map(|msg| msg.conversation_id).collect();
+        conversation_ids.sort_unstable();
+        conversation_ids.dedup();
+        let sensitive = self.sensitive_ids(tx, &conversation_ids).await?;
+        self.record_revisions(tx, messages, &sensitive).await?;
 
         for chunk in messages.chunks(ROWS_PER_CHUNK) {
             let mut sql = String::from(
-                "INSERT INTO messages (id, conversation_id, idx, role, content, parts_json, created_at, model, tokens, cost_usd, metadata, sender_json, provider, harness, client_id) VALUES ",
+                "INSERT INTO messages (id, conversation_id, idx, role, content, parts_json, created_at, model, tokens, cost_usd, metadata, sender_json, provider, harness, client_id, parent_message_id, sealed) VALUES ",
             );
             for i in 0..chunk.len() {
                 if i > 0 {
                     sql.push(',');
                 }
-                sql.push_str("(?,?,?,?,?,?,?,?,?,?,?,?,?,?,?)");
+                sql.push_str("(?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?)");
             }
             // Match the conflict resolution that insert_message_in_tx uses so
             // re-imports remain idempotent.
-            sql.push_str(
+            sql.push_str(concat!(
                 " ON CONFLICT(conversation_id, idx) DO UPDATE SET \
                   role = excluded.role, \
                   content = excluded.content, \
                   parts_json = excluded.parts_json, \
+                  sealed = excluded.sealed, \
                   created_at = excluded.created_at, \
                   model = excluded.model, \
-                  tokens = excluded.tokens, \
+                  ",
+                merge_message_tokens!(),
+                ", \
                   cost_usd = excluded.cost_usd, \
-                  metadata = excluded.metadata, \
+                  ",
+                merge_message_metadata!(),
+                ", \
                   sender_json = excluded.sender_json, \
                   provider = excluded.provider, \
                   harness = excluded.harness, \
-                  client_id = COALESCE(excluded.client_id, messages.client_id)",
-            );
+                  client_id = COALESCE(excluded.client_id, messages.client_id), \
+                  parent_message_id = excluded.parent_message_id",
+            ));
 
             let mut q = sqlx::query(&sql);
             for msg in chunk {
-                let parts_json = normalize_parts_json(&msg.parts_json);
-                let content = project_content(&msg.content, &parts_json);
+                let stored = self.stored_content(msg, sensitive.contains(&msg.conversation_id))?;
                 let sender_json = msg
                     .sender
                     .as_ref()
@@ -3041,8 +6851,8 @@ impl Database {
                     .bind(msg.conversation_id.to_string())
                     .bind(msg.idx)
                     .bind(msg.role.to_string())
-                    .bind(content)
-                    .bind(parts_json.to_string())
+                    .bind(stored.content)
+                    .bind(stored.parts_json)
                     .bind(msg.created_at.map(|dt| dt.timestamp()))
                     .bind(msg.model.clone())
                     .bind(msg.tokens)
@@ -3051,7 +6861,9 @@ impl Database {
                     .bind(sender_json)
                     .bind(msg.provider.clone())
                     .bind(msg.harness.clone())
-                    .bind(msg.client_id.clone());
+                    .bind(msg.client_id.clone())
+                    .bind(msg.parent_message_id.map(|id| id.to_string()))
+                    .bind(stored.sealed);
             }
             // Compile-time sanity check that we didn't drift COLS/ROWS
             // accidentally.
@@ -3062,6 +6874,13 @@ impl Database {
     }
 }
 
+/// Message columns as written by the insert paths.
+struct StoredContent {
+    content: String,
+    parts_json: String,
+    sealed: Option<Vec<u8>>,
+}
+
 /// Conversation preview with first user message.
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct ConversationPreview {
@@ -3069,6 +6888,9 @@ pub struct ConversationPreview {
     pub conversation: Conversation,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub first_user_message: Option<String>,
+    /// Display name given to the conversation's workspace.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workspace_name: Option<String>,
 }
 
 /// Conversation summary with message counts.
@@ -3077,6 +6899,127 @@ pub struct ConversationSummary {
     pub conversation: Conversation,
     pub message_count: i64,
     pub first_user_message: Option<String>,
+    /// The cached summary is missing or was built from a different number
+    /// of messages than are stored now; see
+    /// [`Database::refresh_stale_summaries`].
+    pub stale: bool,
+}
+
+/// A stored workspace renamed by [`Database::normalize_stored_workspaces`].
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct WorkspaceChange {
+    pub from: String,
+    pub to: String,
+    /// Conversations stored under `from`.
+    pub conversations: i64,
+}
+
+/// Health of the full-text index, see [`Database::search_index_status`].
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SearchIndexStatus {
+    /// Messages stored in the database.
+    pub messages: i64,
+    /// Tokenizer of the natural-language table.
+    pub tokenizer: Option<SearchTokenizer>,
+    pub tables: Vec<SearchTableStatus>,
+}
+
+impl SearchIndexStatus {
+    /// Whether a table indexes a different number of messages than are
+    /// stored, which `hstry index --rebuild` repairs.
+    pub fn is_stale(&self) -> bool {
+        self.tables
+            .iter()
+            .any(|table| table.documents != self.messages)
+    }
+}
+
+/// One FTS5 table of [`SearchIndexStatus`].
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SearchTableStatus {
+    pub table: String,
+    /// Index segments; [`Database::optimize_search_fts`] merges them.
+    pub segments: i64,
+    /// Messages in the index.
+    pub documents: i64,
+    /// Bytes of index data, without SQLite page overhead.
+    pub size_bytes: i64,
+}
+
+/// See [`Database::database_size`].
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct DatabaseSize {
+    /// Bytes of pages in the database file, excluding the write-ahead log.
+    pub bytes: i64,
+    /// Bytes of free pages that `VACUUM` gives back.
+    pub free_bytes: i64,
+    /// Largest first; FTS5 shadow tables are listed on their own.
+    pub tables: Vec<TableSize>,
+    pub attachments: i64,
+    /// Bytes of attachment content, part of the `attachments` table.
+    pub attachment_bytes: i64,
+}
+
+/// Pages used by one table and by its indexes.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct TableSize {
+    pub name: String,
+    pub table_bytes: i64,
+    pub index_bytes: i64,
+}
+
+/// See [`Database::list_conversation_attachments`].
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct AttachmentInfo {
+    pub id: String,
+    pub message_id: Uuid,
+    pub name: Option<String>,
+    pub mime_type: Option<String>,
+}
+
+/// A conversation in the trash; see [`Database::list_trash`].
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct TrashedConversation {
+    #[serde(flatten)]
+    pub conversation: Conversation,
+    pub deleted_at: chrono::DateTime<Utc>,
+}
+
+/// Automatic snapshots kept per conversation; see
+/// [`Database::create_snapshot`].
+pub const AUTO_SNAPSHOTS_KEPT: i64 = 5;
+
+/// A saved copy of a conversation; see [`Database::create_snapshot`].
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SavedSnapshot {
+    pub id: Uuid,
+    pub conversation_id: Uuid,
+    /// "manual", "restore", or the operation that took it ("dedup",
+    /// "merge", "segment").
+    pub reason: String,
+    pub title: Option<String>,
+    pub message_count: i64,
+    pub created_at: chrono::DateTime<Utc>,
+}
+
+/// What a saved snapshot stores.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SnapshotPayload {
+    conversation: Conversation,
+    messages: Vec<Message>,
+    tool_calls: Vec<ToolCall>,
+}
+
+/// See [`Database::related_conversations`].
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RelatedConversation {
+    pub id: Uuid,
+    pub source_id: String,
+    pub readable_id: Option<String>,
+    pub title: Option<String>,
+    /// How the copy was found: "external_id", "platform_id" or "content".
+    pub reason: String,
+    pub score: f32,
 }
 
 /// Statistics for a single source.
@@ -3101,13 +7044,309 @@ pub struct ActivityStats {
     pub period_days: i64,
 }
 
+/// Token and cost totals, see [`Database::get_usage_totals`].
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct UsageTotals {
+    pub tokens_in: i64,
+    pub tokens_out: i64,
+    pub cost_usd: f64,
+}
+
+/// How [`Database::stats_breakdown`] groups conversations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatsGroup {
+    Source,
+    /// The conversation's model, or the first model its messages name.
+    Model,
+    /// Local calendar month the conversation started in, as `YYYY-MM`.
+    Month,
+    /// Conversations without a workspace are omitted.
+    Workspace,
+}
+
+/// A conversation's model, or the first model its messages name.
+const CONVERSATION_MODEL_SQL: &str = "COALESCE(NULLIF(c.model, ''), (
+    SELECT m.model FROM messages m
+    WHERE m.conversation_id = c.id AND m.model IS NOT NULL AND m.model != ''
+    ORDER BY m.idx LIMIT 1
+))";
+
+/// A conversation's input tokens, or the sum of its non-assistant
+/// messages' token counts.
+const CONVERSATION_TOKENS_IN_SQL: &str = "COALESCE(c.tokens_in, (
+    SELECT SUM(m.tokens) FROM messages m
+    WHERE m.conversation_id = c.id AND m.role != 'assistant'
+))";
+
+/// A conversation's output tokens, or the sum of its assistant messages'
+/// token counts.
+const CONVERSATION_TOKENS_OUT_SQL: &str = "COALESCE(c.tokens_out, (
+    SELECT SUM(m.tokens) FROM messages m
+    WHERE m.conversation_id = c.id AND m.role = 'assistant'
+))";
+
+impl StatsGroup {
+    /// Grouping expression over `conversations c`.
+    fn key_sql(self) -> &'static str {
+        match self {
+            Self::Source => "c.source_id",
+            Self::Model => CONVERSATION_MODEL_SQL,
+            Self::Month => "strftime('%Y-%m', c.created_at, 'unixepoch', 'localtime')",
+            Self::Workspace => "c.workspace",
+        }
+    }
+
+    fn filter_sql(self) -> &'static str {
+        match self {
+            Self::Workspace => {
+                "WHERE c.deleted_at IS NULL AND c.workspace IS NOT NULL AND c.workspace != ''"
+            }
+            _ => "WHERE c.deleted_at IS NULL",
+        }
+    }
+}
+
+/// Totals for one group of conversations, see [`Database::stats_breakdown`].
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct StatsBreakdown {
+    /// Source id, model, month or workspace; `None` for conversations
+    /// without a model.
+    pub key: Option<String>,
+    pub conversations: i64,
+    pub messages: i64,
+    pub tokens_in: i64,
+    pub tokens_out: i64,
+    pub cost_usd: f64,
+}
+
+impl StatsBreakdown {
+    /// Average messages per conversation.
+    pub fn avg_messages(&self) -> f64 {
+        if self.conversations == 0 {
+            0.0
+        } else {
+            self.messages as f64 / self.conversations as f64
+        }
+    }
+}
+
+/// Conversations of one group and model, see [`Database::cost_breakdown`].
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct CostBreakdown {
+    pub key: Option<String>,
+    pub model: Option<String>,
+    /// Whether these conversations have a recorded cost.
+    pub recorded: bool,
+    pub conversations: i64,
+    pub tokens_in: i64,
+    pub tokens_out: i64,
+    /// Recorded cost; 0 when `recorded` is false.
+    pub cost_usd: f64,
+}
+
+/// See [`Database::detailed_stats`].
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct DetailedStats {
+    pub totals: UsageTotals,
+    /// Average messages per conversation.
+    pub avg_messages: f64,
+    pub by_source: Vec<StatsBreakdown>,
+    pub by_model: Vec<StatsBreakdown>,
+    pub by_month: Vec<StatsBreakdown>,
+    pub top_workspaces: Vec<StatsBreakdown>,
+}
+
+/// A read served over `hstry-api` or `hstry-mcp`, see
+/// [`Database::record_access`].
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct AccessEntry {
+    pub id: i64,
+    pub accessed_at: chrono::DateTime<Utc>,
+    /// `api` or `mcp`.
+    pub channel: String,
+    /// API token name or MCP scope; `None` for reads that needed neither.
+    pub caller: Option<String>,
+    /// What was read, e.g. `search` or `conversation`.
+    pub action: String,
+    /// Search query or request parameters.
+    pub query: Option<String>,
+    /// Conversations the read returned.
+    pub conversation_ids: Vec<Uuid>,
+}
+
+/// Filters for [`Database::list_access_log`].
+#[derive(Debug, Default, Clone)]
+pub struct AccessLogOptions {
+    pub channel: Option<String>,
+    pub caller: Option<String>,
+    /// Only reads that returned this conversation.
+    pub conversation_id: Option<Uuid>,
+    pub after: Option<chrono::DateTime<Utc>>,
+    pub limit: Option<i64>,
+}
+
+/// Model requests of one feature, provider and model, see
+/// [`Database::llm_usage_summary`].
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct LlmUsageSummary {
+    pub feature: String,
+    pub provider: String,
+    pub model: Option<String>,
+    pub requests: i64,
+    pub tokens_in: i64,
+    pub tokens_out: i64,
+    pub cost_usd: f64,
+}
+
 /// Options for listing conversations.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct ListConversationsOptions {
     pub source_id: Option<String>,
     pub workspace: Option<String>,
     pub after: Option<chrono::DateTime<Utc>>,
     pub before: Option<chrono::DateTime<Utc>>,
+    /// Only conversations first ingested into this database after this time.
+    pub ingested_after: Option<chrono::DateTime<Utc>>,
+    /// Model name, case-insensitive; may contain `%` wildcards.
+    pub model: Option<String>,
+    /// Provider name, case-insensitive; may contain `%` wildcards.
+    pub provider: Option<String>,
+    /// Harness name, case-insensitive; may contain `%` wildcards.
+    pub harness: Option<String>,
+    /// Only conversations with at least one message in this role.
+    pub role: Option<MessageRole>,
+    /// Leave out conversations whose messages are sealed.
+    pub exclude_sensitive: bool,
+    /// Only starred conversations.
+    pub starred: bool,
+    pub limit: Option<i64>,
+    /// Number of rows to skip (for pagination).
+    pub offset: Option<i64>,
+}
+
+/// Filters for [`Database::list_tool_calls`] and [`Database::tool_usage_stats`].
+#[derive(Debug, Default, Clone)]
+pub struct ListToolCallsOptions {
+    /// Exact tool name (case-insensitive).
+    pub tool_name: Option<String>,
+    pub source_id: Option<String>,
+    /// Substring match on the conversation workspace.
+    pub workspace: Option<String>,
+    /// `pending`, `running`, `success` or `error`.
+    pub status: Option<String>,
+    /// Only calls in conversations created after this time.
+    pub after: Option<chrono::DateTime<Utc>>,
+    pub limit: Option<i64>,
+}
+
+/// A tool call with the conversation it belongs to.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ToolCallEntry {
+    #[serde(flatten)]
+    pub tool_call: ToolCall,
+    pub conversation_id: Uuid,
+    pub title: Option<String>,
+    pub source_id: String,
+    pub workspace: Option<String>,
+    /// When the message holding the call was written, if known.
+    pub created_at: Option<chrono::DateTime<Utc>>,
+}
+
+/// A bearer token accepted by `hstry-api`. The token itself is never stored.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ApiToken {
+    pub id: i64,
+    pub name: String,
+    /// Leading characters of the token, to tell tokens apart.
+    pub prefix: String,
+    pub scope: ApiTokenScope,
+    pub created_at: chrono::DateTime<Utc>,
+    pub last_used_at: Option<chrono::DateTime<Utc>>,
+    pub revoked_at: Option<chrono::DateTime<Utc>>,
+}
+
+fn api_token_from_row(row: &sqlx::sqlite::SqliteRow) -> Result<ApiToken> {
+    let timestamp = |column: &str| {
+        row.get::<Option<i64>, _>(column)
+            .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0))
+    };
+    Ok(ApiToken {
+        id: row.get("id"),
+        name: row.get("name"),
+        prefix: row.get("prefix"),
+        scope: row.get::<String, _>("scope").parse()?,
+        created_at: timestamp("created_at").unwrap_or_default(),
+        last_used_at: timestamp("last_used_at"),
+        revoked_at: timestamp("revoked_at"),
+    })
+}
+
+/// One row of the `change_log` table.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ChangeLogEntry {
+    pub seq: i64,
+    /// `conversation` or `source`.
+    pub entity: String,
+    pub entity_id: String,
+    /// `insert`, `update` or `delete`.
+    pub kind: String,
+    pub changed_at: chrono::DateTime<Utc>,
+}
+
+/// A process holding the advisory sync lock for a source.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SyncLock {
+    pub source_id: String,
+    pub pid: u32,
+    pub host: String,
+    pub acquired_at: chrono::DateTime<Utc>,
+    pub heartbeat_at: chrono::DateTime<Utc>,
+}
+
+/// A conversation that changed on both sides of a remote sync.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RemoteConflict {
+    /// Row id; 0 for a conflict that has not been recorded yet.
+    pub id: i64,
+    pub remote: String,
+    /// Source holding the conversation in this database.
+    pub source_id: String,
+    pub external_id: String,
+    pub title: Option<String>,
+    pub local_conversation_id: Option<Uuid>,
+    /// Copy created by the duplicate strategy, if any.
+    pub duplicate_conversation_id: Option<Uuid>,
+    pub local_updated_at: Option<chrono::DateTime<Utc>>,
+    pub remote_updated_at: Option<chrono::DateTime<Utc>>,
+    /// Strategy that handled the conflict (see `ConflictStrategy`).
+    pub strategy: String,
+    /// `kept-local`, `kept-remote` or `duplicated`.
+    pub resolution: String,
+    pub detected_at: chrono::DateTime<Utc>,
+    /// `None` while the conflict is open for review.
+    pub resolved_at: Option<chrono::DateTime<Utc>>,
+}
+
+/// Aggregate usage of one tool.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ToolUsage {
+    pub tool_name: String,
+    pub calls: i64,
+    pub errors: i64,
+    pub conversations: i64,
+    pub avg_duration_ms: Option<f64>,
+}
+
+/// Slice of a conversation's messages for [`Database::get_messages_range`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MessageRange {
+    /// Only messages with `idx >= from_idx`.
+    pub from_idx: Option<i32>,
+    /// Only messages with `idx < to_idx`.
+    pub to_idx: Option<i32>,
+    /// Number of matching rows to skip.
+    pub offset: Option<i64>,
+    /// Maximum number of rows to return.
     pub limit: Option<i64>,
 }
 
@@ -3131,6 +7370,8 @@ pub struct SearchOptions {
     pub harness: Option<String>,
     /// Filter by conversation tag.
     pub tag: Option<String>,
+    /// Only messages of starred conversations.
+    pub starred: bool,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -3157,6 +7398,23 @@ impl SearchMode {
     }
 }
 
+/// Append `LIMIT`/`OFFSET` clauses. SQLite only accepts `OFFSET` after a
+/// `LIMIT`, so an offset without a limit uses `LIMIT -1` (unbounded).
+fn push_limit_offset(sql: &mut String, limit: Option<i64>, offset: Option<i64>) {
+    match (limit, offset) {
+        (Some(limit), Some(offset)) => {
+            let _ = write!(sql, " LIMIT {limit} OFFSET {offset}");
+        }
+        (Some(limit), None) => {
+            let _ = write!(sql, " LIMIT {limit}");
+        }
+        (None, Some(offset)) => {
+            let _ = write!(sql, " LIMIT -1 OFFSET {offset}");
+        }
+        (None, None) => {}
+    }
+}
+
 fn detect_search_mode(query: &str) -> SearchMode {
     let has_path = query.contains('/') || query.contains('\\');
     let has_scope = query.contains("::") || query.contains("->");
@@ -3174,6 +7432,67 @@ fn detect_search_mode(query: &str) -> SearchMode {
     }
 }
 
+/// Split `query` for a trigram index into the terms it can match and the
+/// ones shorter than three characters, which it cannot and which are found
+/// by scanning message content instead. Every trigram match is a substring
+/// match, so prefix stars are dropped.
+fn split_trigram_query(query: &str) -> (String, Vec<String>) {
+    let mut indexed = Vec::new();
+    let mut scanned = Vec::new();
+    for token in query.split_whitespace() {
+        let term = token.trim_end_matches('*');
+        match term.chars().count() {
+            0 => {}
+            1 | 2 => scanned.push(term.to_string()),
+            _ => indexed.push(term),
+        }
+    }
+    (indexed.join(" "), scanned)
+}
+
+/// The tokenizer a `messages_fts` definition was created with.
+fn fts_tokenizer(create_sql: &str) -> Option<SearchTokenizer> {
+    let (_, rest) = create_sql.split_once("tokenize")?;
+    let spec = rest.trim_start_matches([' ', '=']);
+    let spec = spec.trim_start_matches(['\'', '"']);
+    let spec = spec.split(['\'', '"']).next()?;
+    [
+        SearchTokenizer::Porter,
+        SearchTokenizer::Unicode,
+        SearchTokenizer::Trigram,
+    ]
+    .into_iter()
+    .find(|tokenizer| tokenizer.fts5_spec() == spec)
+}
+
+/// Number of segments listed in an FTS5 structure record: a 4-byte cookie,
+/// an optional 4-byte version marker, then the level and segment counts as
+/// varints.
+fn fts_segment_count(structure: &[u8]) -> Option<i64> {
+    let mut rest = structure.get(4..)?;
+    if rest.starts_with(&[0xff, 0x00, 0x00, 0x01]) {
+        rest = &rest[4..];
+    }
+    let (_levels, used) = sqlite_varint(rest)?;
+    let (segments, _) = sqlite_varint(rest.get(used..)?)?;
+    i64::try_from(segments).ok()
+}
+
+/// Decode an SQLite varint, returning the value and the bytes it took.
+fn sqlite_varint(bytes: &[u8]) -> Option<(u64, usize)> {
+    let mut value = 0u64;
+    for (idx, &byte) in bytes.iter().take(9).enumerate() {
+        if idx == 8 {
+            return Some(((value << 8) | u64::from(byte), 9));
+        }
+        value = (value << 7) | u64::from(byte & 0x7f);
+        if byte & 0x80 == 0 {
+            return Some((value, idx + 1));
+        }
+    }
+    None
+}
+
 fn sanitize_fts_query(query: &str) -> String {
     let query = query.trim();
     if query.is_empty() {
@@ -3205,6 +7524,93 @@ fn sanitize_fts_query(query: &str) -> String {
     terms.join(" ")
 }
 
+/// A score expression with the values bound to its placeholders, in order.
+struct RankedScore {
+    sql: String,
+    binds: Vec<String>,
+}
+
+impl RankedScore {
+    fn plain(sql: &str) -> Self {
+        Self {
+            sql: sql.to_string(),
+            binds: Vec::new(),
+        }
+    }
+}
+
+/// Weight the full-text score `base` (negative, lower is better) for `query`
+/// as `ranking` asks: by the content and title field weights, by
+/// `1 + recency_weight * h / (h + age_days)` with `h` the half-life (a
+/// hyperbolic decay, which needs no SQL math functions), and by the
+/// source's boost.
+fn ranked_score(base: &str, query: &str, ranking: &RankingConfig) -> RankedScore {
+    if ranking.is_neutral() {
+        return RankedScore::plain(base);
+    }
+    let mut binds = Vec::new();
+    let mut factors = Vec::new();
+
+    let mut terms: Vec<String> = query
+        .split_whitespace()
+        .map(|token| token.trim_matches(|c| c == '"' || c == '*').to_lowercase())
+        .filter(|term| !term.is_empty())
+        .collect();
+    let mut seen = HashSet::new();
+    terms.retain(|term| seen.insert(term.clone()));
+    let mut field = format!("{:?}", ranking.content_weight.max(0.0));
+    if ranking.title_weight > 0.0 && !terms.is_empty() {
+        let found: Vec<&str> = terms
+            .iter()
+            .map(|_| "(instr(lower(COALESCE(c.title, '')), ?) > 0)")
+            .collect();
+        let _ = write!(
+            field,
+            " + {:?} * ({}) / {}.0",
+            ranking.title_weight,
+            found.join(" + "),
+            terms.len()
+        );
+        binds.extend(terms);
+    }
+    factors.push(format!("({field})"));
+
+    if ranking.recency_weight > 0.0 && ranking.recency_half_life_days > 0.0 {
+        let half_life = ranking.recency_half_life_days;
+        factors.push(format!(
+            "(1.0 + {:?} * {half_life:?} / ({half_life:?} + MAX(0.0, \
+             (CAST(strftime('%s', 'now') AS INTEGER) \
+             - COALESCE(m.created_at, c.updated_at, c.created_at)) / 86400.0)))",
+            ranking.recency_weight
+        ));
+    }
+
+    let boosts: Vec<(&String, f64)> = ranking
+        .sources
+        .iter()
+        .filter(|(_, boost)| boost.is_finite())
+        .map(|(source, boost)| (source, boost.max(0.0)))
+        .collect();
+    if !boosts.is_empty() {
+        let mut case = String::from("(CASE");
+        for (source, boost) in boosts {
+            let _ = write!(
+                case,
+                " WHEN c.source_id = ? OR s.adapter = ? THEN {boost:?}"
+            );
+            binds.push(source.clone());
+            binds.push(source.clone());
+        }
+        case.push_str(" ELSE 1.0 END)");
+        factors.push(case);
+    }
+
+    RankedScore {
+        sql: format!("({base} * {})", factors.join(" * ")),
+        binds,
+    }
+}
+
 fn is_like_pattern(value: &str) -> bool {
     value.contains('%') || value.contains('_')
 }
@@ -3243,6 +7649,117 @@ fn conversation_from_row(row: &sqlx::sqlite::SqliteRow) -> Conversation {
     }
 }
 
+fn row_to_tool_call(row: &sqlx::sqlite::SqliteRow) -> ToolCall {
+    ToolCall {
+        id: Uuid::parse_str(row.get::<&str, _>("id")).unwrap_or_default(),
+        message_id: Uuid::parse_str(row.get::<&str, _>("message_id")).unwrap_or_default(),
+        tool_name: row.get("tool_name"),
+        input: row
+            .get::<Option<String>, _>("input")
+            .and_then(|s| serde_json::from_str(&s).ok()),
+        output: row.get("output"),
+        status: row
+            .get::<Option<String>, _>("status")
+            .as_deref()
+            .and_then(ToolStatus::from_db),
+        duration_ms: row.get("duration_ms"),
+    }
+}
+
+/// Append the `WHERE` clause for [`ListToolCallsOptions`]; returns the binds
+/// in order.
+/// Append the model, provider, harness and role filters of `opts` to a
+/// conversation query whose table is aliased `c`, returning their binds.
+fn push_conversation_attribute_filters(
+    sql: &mut String,
+    opts: &ListConversationsOptions,
+) -> Vec<String> {
+    let mut binds = Vec::new();
+    for (column, value) in [
+        ("model", &opts.model),
+        ("provider", &opts.provider),
+        ("harness", &opts.harness),
+    ] {
+        if let Some(value) = value {
+            if is_like_pattern(value) {
+                sql.push_str(&format!(" AND c.{column} LIKE ?"));
+            } else {
+                sql.push_str(&format!(" AND c.{column} = ? COLLATE NOCASE"));
+            }
+            binds.push(value.clone());
+        }
+    }
+    if let Some(role) = &opts.role {
+        sql.push_str(
+            " AND EXISTS (SELECT 1 FROM messages m WHERE m.conversation_id = c.id AND m.role = ?)",
+        );
+        binds.push(role.to_string());
+    }
+    if opts.exclude_sensitive {
+        sql.push_str(" AND c.sensitive = 0");
+    }
+    if opts.starred {
+        sql.push_str(" AND c.starred = 1");
+    }
+    sql.push_str(" AND c.deleted_at IS NULL");
+    binds
+}
+
+fn push_tool_call_filters(sql: &mut String, opts: &ListToolCallsOptions) -> Vec<String> {
+    let mut clauses = Vec::new();
+    let mut binds = Vec::new();
+    if let Some(name) = &opts.tool_name {
+        clauses.push("t.tool_name = ? COLLATE NOCASE");
+        binds.push(name.clone());
+    }
+    if let Some(source_id) = &opts.source_id {
+        clauses.push("c.source_id = ?");
+        binds.push(source_id.clone());
+    }
+    if let Some(workspace) = &opts.workspace {
+        clauses.push("c.workspace LIKE ?");
+        binds.push(format!("%{workspace}%"));
+    }
+    if let Some(status) = &opts.status {
+        clauses.push("t.status = ?");
+        binds.push(status.clone());
+    }
+    if let Some(after) = opts.after {
+        clauses.push("c.created_at >= CAST(? AS INTEGER)");
+        binds.push(after.timestamp().to_string());
+    }
+    if !clauses.is_empty() {
+        sql.push_str(" WHERE ");
+        sql.push_str(&clauses.join(" AND "));
+    }
+    binds
+}
+
+fn decision_from_row(row: &sqlx::sqlite::SqliteRow) -> Decision {
+    Decision {
+        id: row.get("id"),
+        conversation_id: Uuid::parse_str(row.get::<&str, _>("conversation_id")).unwrap_or_default(),
+        message_id: Uuid::parse_str(row.get::<&str, _>("message_id")).unwrap_or_default(),
+        workspace: row.get("workspace"),
+        title: row.get("title"),
+        text: row.get("text"),
+        decided_at: chrono::DateTime::from_timestamp(row.get::<i64, _>("decided_at"), 0)
+            .unwrap_or_else(Utc::now),
+    }
+}
+
+fn saved_search_from_row(row: &sqlx::sqlite::SqliteRow) -> SavedSearch {
+    let filters: SavedFilters =
+        serde_json::from_str(row.get::<&str, _>("filters")).unwrap_or_default();
+    SavedSearch {
+        name: row.get("name"),
+        query: row.get("query"),
+        filters,
+        created_at: chrono::DateTime::from_timestamp(row.get::<i64, _>("created_at"), 0)
+            .unwrap_or_else(Utc::now),
+    }
+}
+
 fn message_from_row(row: &sqlx::sqlite::SqliteRow) -> Message {
     Message {
         id: Uuid::parse_str(row.get::<&str, _>("id")).unwrap_or_default(),
@@ -3273,6 +7790,11 @@ fn message_from_row(row: &sqlx::sqlite::SqliteRow) -> Message {
         provider: row.try_get("provider").ok().flatten(),
         harness: row.try_get("harness").ok().flatten(),
         client_id: row.try_get("client_id").ok().flatten(),
+        parent_message_id: row
+            .try_get::<Option<String>, _>("parent_message_id")
+            .ok()
+            .flatten()
+            .and_then(|id| Uuid::parse_str(&id).ok()),
     }
 }
 
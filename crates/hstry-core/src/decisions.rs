@@ -0,0 +1,184 @@
+//! The decision log (`hstry decisions`).
+//!
+//! [`extract_decisions`] picks the sentences of a conversation that record a
+//! decision ("we decided to…", "let's use X instead of Y"). [`analyze`]
+//! stores them in the `decisions` table, scanning only conversations that
+//! are new or changed since their last scan, so the log and the knowledge
+//! pack can read them back per workspace.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::agent_search::{is_system_context, is_tool_dump};
+use crate::db::Database;
+use crate::error::Result;
+use crate::models::{Message, MessageRole};
+use crate::pack::{strip_code, truncate};
+
+/// Phrases that mark a sentence as a decision.
+const DECISION_MARKERS: &[&str] = &[
+    "decision:",
+    "decided to",
+    "we decided",
+    "decided on",
+    "settled on",
+    "let's go with",
+    "we'll go with",
+    "going with",
+    "let's use",
+    "we'll use",
+    "switched to",
+    "chose to",
+    "opted to",
+    "agreed to",
+    "from now on",
+];
+
+/// A decision found in a message, before it is stored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoundDecision {
+    pub message_id: Uuid,
+    pub decided_at: Option<DateTime<Utc>>,
+    pub text: String,
+}
+
+/// A stored decision.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Decision {
+    pub id: i64,
+    pub conversation_id: Uuid,
+    pub message_id: Uuid,
+    pub workspace: Option<String>,
+    /// Title of the conversation it was made in.
+    pub title: Option<String>,
+    pub text: String,
+    /// When the message was sent, or the conversation started if unknown.
+    pub decided_at: DateTime<Utc>,
+}
+
+/// Sentences from user and assistant messages that record a decision.
+pub fn extract_decisions(messages: &[Message]) -> Vec<FoundDecision> {
+    let mut decisions = Vec::new();
+    for message in messages {
+        if !matches!(message.role, MessageRole::User | MessageRole::Assistant)
+            || is_system_context(&message.content)
+            || is_tool_dump(&message.content)
+        {
+            continue;
+        }
+        let text = strip_code(&message.content);
+        for sentence in sentences(&text) {
+            if is_decision(&sentence.to_lowercase()) {
+                decisions.push(FoundDecision {
+                    message_id: message.id,
+                    decided_at: message.created_at,
+                    text: truncate(&sentence, 240),
+                });
+            }
+        }
+    }
+    decisions
+}
+
+fn is_decision(lower: &str) -> bool {
+    DECISION_MARKERS.iter().any(|marker| lower.contains(marker))
+        // "use X instead of Y", but not "instead of answering, ..."
+        || (lower.contains("instead of") && (lower.contains("use ") || lower.contains("using ")))
+}
+
+/// Scan the conversations in `workspace` (all of them when `None`) that are
+/// new or changed since their last scan and store their decisions,
+/// replacing the ones found before. Returns how many were scanned.
+pub async fn analyze(db: &Database, workspace: Option<&str>) -> Result<usize> {
+    let conversations = db.conversations_to_scan_for_decisions(workspace).await?;
+    for conv in &conversations {
+        let messages = db.get_messages(conv.id).await?;
+        let found = extract_decisions(&messages);
+        db.replace_decisions(conv, &found).await?;
+    }
+    Ok(conversations.len())
+}
+
+fn sentences(text: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    for line in text.lines() {
+        let line = line.trim().trim_start_matches(['-', '*', '#', '>', ' ']);
+        let mut current = String::new();
+        for ch in line.chars() {
+            current.push(ch);
+            if matches!(ch, '.' | '!' | '?') {
+                push_sentence(&mut out, &mut current);
+            }
+        }
+        push_sentence(&mut out, &mut current);
+    }
+    out
+}
+
+fn push_sentence(out: &mut Vec<String>, current: &mut String) {
+    let sentence = current.trim();
+    if sentence.len() > 10 {
+        out.push(sentence.to_string());
+    }
+    current.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(idx: i32, role: MessageRole, content: &str) -> Message {
+        Message {
+            id: Uuid::new_v4(),
+            conversation_id: Uuid::nil(),
+            idx,
+            role,
+            content: content.to_string(),
+            parts_json: serde_json::json!([]),
+            created_at: None,
+            model: None,
+            tokens: None,
+            cost_usd: None,
+            metadata: serde_json::json!({}),
+            sender: None,
+            provider: None,
+            harness: None,
+            client_id: None,
+            parent_message_id: None,
+        }
+    }
+
+    #[test]
+    fn extracts_decisions_outside_code() {
+        let messages = vec![
+            message(
+                0,
+                MessageRole::User,
+                "Options are sqlite or postgres. Let's go with sqlite for now.",
+            ),
+            message(
+                1,
+                MessageRole::Assistant,
+                "```\n// we decided to panic here\n```\nDone, I opted to keep the API unchanged.",
+            ),
+            message(
+                2,
+                MessageRole::User,
+                "Instead of asking, just run it. We should use tokio instead of async-std.",
+            ),
+            message(3, MessageRole::Tool, "decided to run tests"),
+        ];
+        let found = extract_decisions(&messages);
+        let texts: Vec<&str> = found.iter().map(|d| d.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            vec![
+                "Let's go with sqlite for now.",
+                "Done, I opted to keep the API unchanged.",
+                "We should use tokio instead of async-std.",
+            ]
+        );
+        assert_eq!(found[1].message_id, messages[1].id);
+    }
+}
@@ -26,6 +26,9 @@ pub enum Error {
     #[error("Remote error: {0}")]
     Remote(String),
 
+    #[error("Operation cancelled")]
+    Cancelled,
+
     #[error("{0}")]
     Other(String),
 }
@@ -0,0 +1,269 @@
+//! Native exporters for the universal formats (markdown, JSON, JSONL).
+//!
+//! These need no TypeScript adapter, so `hstry export` works with zero
+//! adapters installed. JSON and JSONL use the same camelCase shape adapters
+//! receive ([`ParsedConversation`]), so exports can be re-imported.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::str::FromStr;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use uuid::Uuid;
+
+use crate::error::{Error, Result};
+use crate::labels;
+use crate::models::{Conversation, Message};
+use crate::parsed::{ParsedConversation, ParsedMessage};
+
+/// Formats rendered natively by [`render`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Markdown,
+    Json,
+    /// One JSON conversation per line.
+    Jsonl,
+}
+
+impl ExportFormat {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Markdown => "markdown",
+            Self::Json => "json",
+            Self::Jsonl => "jsonl",
+        }
+    }
+
+    /// File extension for this format, without the dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Json => "json",
+            Self::Jsonl => "jsonl",
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            Self::Markdown => "text/markdown",
+            Self::Json => "application/json",
+            Self::Jsonl => "application/jsonl",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "markdown" | "md" => Ok(Self::Markdown),
+            "json" => Ok(Self::Json),
+            "jsonl" | "ndjson" => Ok(Self::Jsonl),
+            other => Err(Error::Other(format!("Unknown export format: {other}"))),
+        }
+    }
+}
+
+/// Convert a stored conversation and its messages to the export shape.
+/// Branch links survive as `parentIdx` when the parent is among `messages`.
+pub fn to_parsed(conversation: &Conversation, messages: Vec<Message>) -> ParsedConversation {
+    let positions: HashMap<Uuid, i32> = messages
+        .iter()
+        .enumerate()
+        .filter_map(|(pos, m)| Some((m.id, i32::try_from(pos).ok()?)))
+        .collect();
+    ParsedConversation {
+        external_id: conversation.external_id.clone(),
+        readable_id: conversation.readable_id.clone(),
+        title: conversation.title.clone(),
+        created_at: conversation.created_at.timestamp_millis(),
+        updated_at: conversation.updated_at.map(|dt| dt.timestamp_millis()),
+        model: conversation.model.clone(),
+        provider: conversation.provider.clone(),
+        workspace: conversation.workspace.clone(),
+        tokens_in: conversation.tokens_in,
+        tokens_out: conversation.tokens_out,
+        cost_usd: conversation.cost_usd,
+        messages: messages
+            .into_iter()
+            .map(|m| ParsedMessage {
+                role: m.role.to_string(),
+                content: m.content,
+                created_at: m.created_at.map(|dt| dt.timestamp_millis()),
+                model: m.model,
+                tokens: m.tokens,
+                cost_usd: m.cost_usd,
+                parts: Some(m.parts_json),
+                tool_calls: None,
+                metadata: Some(m.metadata),
+                parent_idx: m
+                    .parent_message_id
+                    .and_then(|parent| positions.get(&parent).copied()),
+            })
+            .collect(),
+        metadata: Some(conversation.metadata.clone()),
+        version: u64::try_from(conversation.version).ok(),
+        message_count: u32::try_from(conversation.message_count).ok(),
+        parent_external_id: None,
+        parent_message_idx: conversation.parent_message_idx,
+        fork_type: conversation.fork_type.clone(),
+        source_id: Some(conversation.source_id.clone()),
+    }
+}
+
+/// Render `conversations` as a single document in `format`. `pretty` only
+/// affects JSON; JSONL is always one compact object per line.
+pub fn render(
+    format: ExportFormat,
+    conversations: &[ParsedConversation],
+    pretty: bool,
+) -> Result<String> {
+    match format {
+        ExportFormat::Markdown => Ok(render_markdown(conversations)),
+        ExportFormat::Json if pretty => Ok(serde_json::to_string_pretty(conversations)?),
+        ExportFormat::Json => Ok(serde_json::to_string(conversations)?),
+        ExportFormat::Jsonl => {
+            let mut out = String::new();
+            for conv in conversations {
+                out.push_str(&serde_json::to_string(conv)?);
+                out.push('\n');
+            }
+            Ok(out)
+        }
+    }
+}
+
+/// Markdown transcript, matching what the bundled adapters produce. When
+/// messages carry labels, they are listed in YAML front matter and under
+/// each message's heading.
+pub fn render_markdown(conversations: &[ParsedConversation]) -> String {
+    let mut out = String::new();
+    let mut labelled: Vec<&str> = conversations
+        .iter()
+        .flat_map(|conv| &conv.messages)
+        .flat_map(message_labels)
+        .collect();
+    labelled.sort_unstable();
+    labelled.dedup();
+    if !labelled.is_empty() {
+        let _ = writeln!(out, "---\nlabels: [{}]\n---\n", labelled.join(", "));
+    }
+    for conv in conversations {
+        let _ = writeln!(
+            out,
+            "# {}\n",
+            conv.title.as_deref().unwrap_or("Conversation")
+        );
+        if let Some(created) = iso_millis(conv.created_at) {
+            let _ = writeln!(out, "- Created: {created}");
+        }
+        if let Some(updated) = conv.updated_at.and_then(iso_millis) {
+            let _ = writeln!(out, "- Updated: {updated}");
+        }
+        if let Some(workspace) = &conv.workspace {
+            let _ = writeln!(out, "- Workspace: {workspace}");
+        }
+        out.push('\n');
+
+        for msg in &conv.messages {
+            let _ = writeln!(out, "## {}", msg.role);
+            if let Some(at) = msg.created_at.and_then(iso_millis) {
+                let _ = writeln!(out, "_at {at}_");
+            }
+            let msg_labels = message_labels(msg);
+            if !msg_labels.is_empty() {
+                let shown: Vec<String> = msg_labels.into_iter().map(labels::display).collect();
+                let _ = writeln!(out, "_{}_", shown.join(" · "));
+            }
+            let _ = writeln!(out, "\n{}\n", msg.content);
+        }
+    }
+    let mut out = out.trim().to_string();
+    out.push('\n');
+    out
+}
+
+fn message_labels(msg: &ParsedMessage) -> Vec<&str> {
+    msg.metadata
+        .as_ref()
+        .map(labels::labels)
+        .unwrap_or_default()
+}
+
+fn iso_millis(ms: i64) -> Option<String> {
+    DateTime::<Utc>::from_timestamp_millis(ms)
+        .map(|dt| dt.to_rfc3339_opts(SecondsFormat::Millis, true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conversation() -> ParsedConversation {
+        ParsedConversation {
+            external_id: Some("ext-1".to_string()),
+            readable_id: None,
+            title: Some("Parser bug".to_string()),
+            created_at: 1_700_000_000_000,
+            updated_at: None,
+            model: None,
+            provider: None,
+            workspace: Some("/code/hstry".to_string()),
+            tokens_in: None,
+            tokens_out: None,
+            cost_usd: None,
+            messages: vec![ParsedMessage {
+                role: "user".to_string(),
+                content: "Why does it panic?".to_string(),
+                created_at: None,
+                model: None,
+                tokens: None,
+                cost_usd: None,
+                parts: None,
+                tool_calls: None,
+                metadata: None,
+                parent_idx: None,
+            }],
+            metadata: None,
+            version: None,
+            message_count: None,
+            parent_external_id: None,
+            parent_message_idx: None,
+            fork_type: None,
+            source_id: None,
+        }
+    }
+
+    #[test]
+    fn markdown_lists_metadata_and_messages() {
+        let md = render_markdown(&[conversation()]);
+        assert_eq!(
+            md,
+            "# Parser bug\n\n- Created: 2023-11-14T22:13:20.000Z\n- Workspace: /code/hstry\n\n## user\n\nWhy does it panic?\n"
+        );
+    }
+
+    #[test]
+    fn markdown_front_matter_lists_labels() {
+        let mut conv = conversation();
+        conv.messages[0].metadata = Some(serde_json::json!({"labels": ["bug", "keep"]}));
+        let md = render_markdown(&[conv]);
+        assert!(md.starts_with("---\nlabels: [bug, keep]\n---\n\n# Parser bug\n"));
+        assert!(md.contains("## user\n_🐛 bug · 📌 keep_\n"));
+    }
+
+    #[test]
+    fn jsonl_writes_one_parseable_line_per_conversation() {
+        let out =
+            render(ExportFormat::Jsonl, &[conversation(), conversation()], true).expect("render");
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let parsed: ParsedConversation = serde_json::from_str(lines[0]).expect("parse line");
+        assert_eq!(parsed.external_id.as_deref(), Some("ext-1"));
+        assert_eq!(
+            "ndjson".parse::<ExportFormat>().ok(),
+            Some(ExportFormat::Jsonl)
+        );
+    }
+}
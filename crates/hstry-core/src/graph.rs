@@ -0,0 +1,611 @@
+//! Conversation graph export (`hstry export --format graphml|dot`).
+//!
+//! Conversations become nodes linked by four kinds of edges:
+//!
+//! - `continuation`: a conversation continued or forked from another
+//!   (directed, child to parent).
+//! - `workspace`: a conversation to the workspace it ran in. Workspaces are
+//!   nodes of their own, so every conversation in one links to a shared hub.
+//! - `file`: a conversation to a file its tool calls touched. Only files
+//!   touched by at least two exported conversations are included.
+//! - `similar`: two conversations whose title and user prompts share
+//!   distinctive words (TF-IDF cosine similarity), weighted by similarity.
+//!
+//! GraphML opens in Gephi, yEd and Cytoscape; DOT renders with Graphviz.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write as _;
+
+use crate::models::Conversation;
+
+/// Characters of user prompt text per conversation used for similarity.
+const SIMILARITY_TEXT_LIMIT: usize = 4_000;
+
+/// Words that appear in more documents than this are too common to link
+/// conversations by, and would make pair scoring quadratic.
+const MAX_TERM_DOCUMENTS: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    GraphMl,
+    Dot,
+}
+
+impl GraphFormat {
+    /// Format for an export format name, if it names a graph format.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "graphml" => Some(Self::GraphMl),
+            "dot" => Some(Self::Dot),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::GraphMl => "graphml",
+            Self::Dot => "dot",
+        }
+    }
+}
+
+/// A conversation and what the graph needs to know about it.
+pub struct GraphConversation {
+    pub conversation: Conversation,
+    /// Paths touched by the conversation's tool calls.
+    pub files: Vec<String>,
+    /// User prompts, compared for similarity edges.
+    pub text: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct GraphOptions {
+    /// Minimum cosine similarity for a `similar` edge; 0 disables them.
+    pub min_similarity: f32,
+    /// Most `similar` edges kept per conversation.
+    pub similar_per_conversation: usize,
+}
+
+impl Default for GraphOptions {
+    fn default() -> Self {
+        Self {
+            min_similarity: 0.3,
+            similar_per_conversation: 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum NodeKind {
+    Conversation,
+    Workspace,
+    File,
+}
+
+impl NodeKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Conversation => "conversation",
+            Self::Workspace => "workspace",
+            Self::File => "file",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub id: String,
+    pub kind: NodeKind,
+    pub label: String,
+    /// Extra attributes (source, workspace, created, messages).
+    pub attributes: BTreeMap<&'static str, String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeKind {
+    Continuation,
+    Workspace,
+    File,
+    Similar,
+}
+
+impl EdgeKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Continuation => "continuation",
+            Self::Workspace => "workspace",
+            Self::File => "file",
+            Self::Similar => "similar",
+        }
+    }
+
+    fn directed(self) -> bool {
+        self == Self::Continuation
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Edge {
+    pub source: String,
+    pub target: String,
+    pub kind: EdgeKind,
+    pub weight: f32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Graph {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+}
+
+impl Graph {
+    pub fn render(&self, format: GraphFormat) -> String {
+        match format {
+            GraphFormat::GraphMl => self.to_graphml(),
+            GraphFormat::Dot => self.to_dot(),
+        }
+    }
+
+    pub fn to_graphml(&self) -> String {
+        let mut out = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n\
+             \x20 <key id=\"label\" for=\"node\" attr.name=\"label\" attr.type=\"string\"/>\n\
+             \x20 <key id=\"kind\" for=\"node\" attr.name=\"kind\" attr.type=\"string\"/>\n\
+             \x20 <key id=\"source\" for=\"node\" attr.name=\"source\" attr.type=\"string\"/>\n\
+             \x20 <key id=\"workspace\" for=\"node\" attr.name=\"workspace\" attr.type=\"string\"/>\n\
+             \x20 <key id=\"created\" for=\"node\" attr.name=\"created\" attr.type=\"string\"/>\n\
+             \x20 <key id=\"messages\" for=\"node\" attr.name=\"messages\" attr.type=\"long\"/>\n\
+             \x20 <key id=\"edge_kind\" for=\"edge\" attr.name=\"kind\" attr.type=\"string\"/>\n\
+             \x20 <key id=\"weight\" for=\"edge\" attr.name=\"weight\" attr.type=\"double\"/>\n\
+             \x20 <graph id=\"hstry\" edgedefault=\"undirected\">\n",
+        );
+        for node in &self.nodes {
+            let _ = writeln!(out, "    <node id=\"{}\">", xml_escape(&node.id));
+            let _ = writeln!(
+                out,
+                "      <data key=\"label\">{}</data>",
+                xml_escape(&node.label)
+            );
+            let _ = writeln!(
+                out,
+                "      <data key=\"kind\">{}</data>",
+                node.kind.as_str()
+            );
+            for (key, value) in &node.attributes {
+                let _ = writeln!(
+                    out,
+                    "      <data key=\"{key}\">{}</data>",
+                    xml_escape(value)
+                );
+            }
+            out.push_str("    </node>\n");
+        }
+        for edge in &self.edges {
+            let _ = writeln!(
+                out,
+                "    <edge source=\"{}\" target=\"{}\"{}>",
+                xml_escape(&edge.source),
+                xml_escape(&edge.target),
+                if edge.kind.directed() {
+                    " directed=\"true\""
+                } else {
+                    ""
+                }
+            );
+            let _ = writeln!(
+                out,
+                "      <data key=\"edge_kind\">{}</data>",
+                edge.kind.as_str()
+            );
+            let _ = writeln!(out, "      <data key=\"weight\">{:.3}</data>", edge.weight);
+            out.push_str("    </edge>\n");
+        }
+        out.push_str("  </graph>\n</graphml>\n");
+        out
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph hstry {\n  node [shape=box];\n");
+        for node in &self.nodes {
+            let shape = match node.kind {
+                NodeKind::Conversation => "box",
+                NodeKind::Workspace => "folder",
+                NodeKind::File => "note",
+            };
+            let _ = write!(
+                out,
+                "  \"{}\" [label=\"{}\", shape={shape}, kind=\"{}\"",
+                dot_escape(&node.id),
+                dot_escape(&node.label),
+                node.kind.as_str()
+            );
+            for (key, value) in &node.attributes {
+                let _ = write!(out, ", {key}=\"{}\"", dot_escape(value));
+            }
+            out.push_str("];\n");
+        }
+        for edge in &self.edges {
+            let style = match edge.kind {
+                EdgeKind::Continuation => "",
+                EdgeKind::Workspace | EdgeKind::File => ", dir=none, color=gray",
+                EdgeKind::Similar => ", dir=none, style=dashed",
+            };
+            let _ = writeln!(
+                out,
+                "  \"{}\" -> \"{}\" [kind=\"{}\", weight={:.3}{style}];",
+                dot_escape(&edge.source),
+                dot_escape(&edge.target),
+                edge.kind.as_str(),
+                edge.weight
+            );
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+/// Build the graph of `conversations`.
+pub fn build(conversations: &[GraphConversation], opts: &GraphOptions) -> Graph {
+    let mut graph = Graph::default();
+    let exported: HashSet<String> = conversations
+        .iter()
+        .map(|c| c.conversation.id.to_string())
+        .collect();
+
+    let mut workspaces: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    let mut files: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for entry in conversations {
+        let conv = &entry.conversation;
+        let id = conversation_node_id(&conv.id.to_string());
+        let mut attributes = BTreeMap::new();
+        attributes.insert("source", conv.source_id.clone());
+        if let Some(workspace) = &conv.workspace {
+            attributes.insert("workspace", workspace.clone());
+        }
+        attributes.insert("created", conv.created_at.to_rfc3339());
+        attributes.insert("messages", conv.message_count.to_string());
+        graph.nodes.push(Node {
+            id: id.clone(),
+            kind: NodeKind::Conversation,
+            label: conv
+                .title
+                .clone()
+                .filter(|title| !title.trim().is_empty())
+                .or_else(|| conv.readable_id.clone())
+                .unwrap_or_else(|| conv.id.to_string()),
+            attributes,
+        });
+
+        if let Some(parent) = &conv.parent_conversation_id
+            && exported.contains(parent)
+        {
+            graph.edges.push(Edge {
+                source: id.clone(),
+                target: conversation_node_id(parent),
+                kind: EdgeKind::Continuation,
+                weight: 1.0,
+            });
+        }
+        if let Some(workspace) = conv.workspace.as_deref().filter(|w| !w.is_empty()) {
+            workspaces.entry(workspace).or_default().push(id.clone());
+        }
+        let mut seen = HashSet::new();
+        for file in &entry.files {
+            let path = absolute_path(file, conv.workspace.as_deref());
+            if seen.insert(path.clone()) {
+                files.entry(path).or_default().push(id.clone());
+            }
+        }
+    }
+
+    for (workspace, members) in workspaces {
+        let node = format!("w:{workspace}");
+        graph.nodes.push(Node {
+            id: node.clone(),
+            kind: NodeKind::Workspace,
+            label: workspace.to_string(),
+            attributes: BTreeMap::new(),
+        });
+        for member in members {
+            graph.edges.push(Edge {
+                source: member,
+                target: node.clone(),
+                kind: EdgeKind::Workspace,
+                weight: 1.0,
+            });
+        }
+    }
+
+    for (path, members) in files.into_iter().filter(|(_, m)| m.len() > 1) {
+        let node = format!("f:{path}");
+        graph.nodes.push(Node {
+            id: node.clone(),
+            kind: NodeKind::File,
+            label: path,
+            attributes: BTreeMap::new(),
+        });
+        for member in members {
+            graph.edges.push(Edge {
+                source: member,
+                target: node.clone(),
+                kind: EdgeKind::File,
+                weight: 1.0,
+            });
+        }
+    }
+
+    if opts.min_similarity > 0.0 {
+        for (a, b, score) in similar_pairs(conversations, opts) {
+            graph.edges.push(Edge {
+                source: conversation_node_id(&conversations[a].conversation.id.to_string()),
+                target: conversation_node_id(&conversations[b].conversation.id.to_string()),
+                kind: EdgeKind::Similar,
+                weight: score,
+            });
+        }
+    }
+    graph
+}
+
+fn conversation_node_id(id: &str) -> String {
+    format!("c:{id}")
+}
+
+/// Resolve a relative tool path against the conversation's workspace so
+/// the same file matches across conversations.
+fn absolute_path(path: &str, workspace: Option<&str>) -> String {
+    match workspace {
+        Some(workspace) if !path.starts_with('/') && !path.starts_with('~') => {
+            let relative = path.trim_start_matches("./");
+            format!("{}/{relative}", workspace.trim_end_matches('/'))
+        }
+        _ => path.to_string(),
+    }
+}
+
+/// Lowercased words of three or more characters.
+fn terms(text: &str) -> Vec<String> {
+    let text: String = text.chars().take(SIMILARITY_TEXT_LIMIT).collect();
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= 3)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Conversation pairs `(a, b, similarity)` with `a < b`, keeping each
+/// conversation's best `similar_per_conversation` matches above the
+/// threshold.
+fn similar_pairs(
+    conversations: &[GraphConversation],
+    opts: &GraphOptions,
+) -> Vec<(usize, usize, f32)> {
+    let documents: Vec<HashMap<String, f32>> = conversations
+        .iter()
+        .map(|entry| {
+            let title = entry.conversation.title.as_deref().unwrap_or_default();
+            let mut counts: HashMap<String, f32> = HashMap::new();
+            for term in terms(&format!("{title}\n{}", entry.text)) {
+                *counts.entry(term).or_default() += 1.0;
+            }
+            counts
+        })
+        .collect();
+
+    let mut postings: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, document) in documents.iter().enumerate() {
+        for term in document.keys() {
+            postings.entry(term).or_default().push(i);
+        }
+    }
+    let count = documents.len() as f32;
+    let limit = (documents.len() / 2).clamp(2, MAX_TERM_DOCUMENTS);
+    // TF-IDF weights, normalised per document; terms found in a single
+    // document cannot link two of them and are dropped.
+    let vectors: Vec<HashMap<&str, f32>> = documents
+        .iter()
+        .map(|document| {
+            let mut vector: HashMap<&str, f32> = document
+                .iter()
+                .filter_map(|(term, tf)| {
+                    let df = postings[term.as_str()].len();
+                    (df > 1 && df <= limit)
+                        .then(|| (term.as_str(), (1.0 + tf.ln()) * (count / df as f32).ln()))
+                })
+                .collect();
+            let norm = vector.values().map(|w| w * w).sum::<f32>().sqrt();
+            if norm > 0.0 {
+                vector.values_mut().for_each(|w| *w /= norm);
+            }
+            vector
+        })
+        .collect();
+
+    let mut best: Vec<Vec<(usize, f32)>> = vec![Vec::new(); documents.len()];
+    for (i, vector) in vectors.iter().enumerate() {
+        let mut scores: HashMap<usize, f32> = HashMap::new();
+        for (term, weight) in vector {
+            for &j in &postings[term] {
+                if j != i
+                    && let Some(other) = vectors[j].get(term)
+                {
+                    *scores.entry(j).or_default() += weight * other;
+                }
+            }
+        }
+        let mut scores: Vec<(usize, f32)> = scores
+            .into_iter()
+            .filter(|(_, score)| *score >= opts.min_similarity)
+            .collect();
+        scores.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        scores.truncate(opts.similar_per_conversation);
+        best[i] = scores;
+    }
+
+    let mut pairs: BTreeMap<(usize, usize), f32> = BTreeMap::new();
+    for (i, matches) in best.into_iter().enumerate() {
+        for (j, score) in matches {
+            pairs.insert((i.min(j), i.max(j)), score);
+        }
+    }
+    pairs.into_iter().map(|((a, b), s)| (a, b, s)).collect()
+}
+
+fn xml_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            // Control characters other than tab and newline are not valid XML.
+            c if c.is_control() && c != '\t' && c != '\n' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn conversation(title: &str, workspace: Option<&str>) -> Conversation {
+        Conversation {
+            id: Uuid::new_v4(),
+            source_id: "codex".to_string(),
+            external_id: None,
+            readable_id: None,
+            platform_id: None,
+            title: Some(title.to_string()),
+            created_at: Utc::now(),
+            updated_at: None,
+            model: None,
+            provider: None,
+            workspace: workspace.map(str::to_string),
+            tokens_in: None,
+            tokens_out: None,
+            cost_usd: None,
+            metadata: serde_json::json!({}),
+            harness: None,
+            version: 1,
+            message_count: 2,
+            parent_conversation_id: None,
+            parent_message_idx: None,
+            fork_type: None,
+        }
+    }
+
+    fn entry(conversation: Conversation, files: &[&str], text: &str) -> GraphConversation {
+        GraphConversation {
+            conversation,
+            files: files.iter().map(|f| f.to_string()).collect(),
+            text: text.to_string(),
+        }
+    }
+
+    fn edges(graph: &Graph, kind: EdgeKind) -> usize {
+        graph.edges.iter().filter(|e| e.kind == kind).count()
+    }
+
+    #[test]
+    fn links_continuations_workspaces_and_shared_files() {
+        let parent = conversation("Set up sqlite", Some("/repo"));
+        let mut child = conversation("Continue sqlite", Some("/repo"));
+        child.parent_conversation_id = Some(parent.id.to_string());
+        let other = conversation("Unrelated", Some("/elsewhere"));
+        let graph = build(
+            &[
+                entry(parent, &["src/db.rs", "/repo/README.md"], ""),
+                entry(child, &["/repo/src/db.rs"], ""),
+                entry(other, &["/elsewhere/notes.txt"], ""),
+            ],
+            &GraphOptions::default(),
+        );
+
+        assert_eq!(edges(&graph, EdgeKind::Continuation), 1);
+        assert_eq!(edges(&graph, EdgeKind::Workspace), 3);
+        // Only the file both conversations touched becomes a node.
+        let files: Vec<_> = graph
+            .nodes
+            .iter()
+            .filter(|n| n.kind == NodeKind::File)
+            .map(|n| n.label.as_str())
+            .collect();
+        assert_eq!(files, vec!["/repo/src/db.rs"]);
+        assert_eq!(edges(&graph, EdgeKind::File), 2);
+    }
+
+    #[test]
+    fn similar_conversations_are_linked() {
+        let conversations = vec![
+            entry(
+                conversation("WAL checkpoint", None),
+                &[],
+                "sqlite wal checkpoint starvation under load",
+            ),
+            entry(
+                conversation("Checkpoint again", None),
+                &[],
+                "the wal checkpoint starvation is back",
+            ),
+            entry(
+                conversation("Landing page", None),
+                &[],
+                "make the hero section responsive",
+            ),
+            entry(
+                conversation("Pricing", None),
+                &[],
+                "add a pricing table to the landing page",
+            ),
+        ];
+        let graph = build(&conversations, &GraphOptions::default());
+        let similar: Vec<_> = graph
+            .edges
+            .iter()
+            .filter(|e| e.kind == EdgeKind::Similar)
+            .collect();
+        assert_eq!(similar.len(), 2);
+        assert!(similar.iter().all(|e| e.weight >= 0.3));
+
+        let off = GraphOptions {
+            min_similarity: 0.0,
+            ..GraphOptions::default()
+        };
+        assert_eq!(edges(&build(&conversations, &off), EdgeKind::Similar), 0);
+    }
+
+    #[test]
+    fn renders_escaped_graphml_and_dot() {
+        let mut graph = Graph::default();
+        graph.nodes.push(Node {
+            id: "c:1".to_string(),
+            kind: NodeKind::Conversation,
+            label: "Fix <a> & \"b\"".to_string(),
+            attributes: BTreeMap::new(),
+        });
+        graph.edges.push(Edge {
+            source: "c:1".to_string(),
+            target: "c:2".to_string(),
+            kind: EdgeKind::Continuation,
+            weight: 1.0,
+        });
+        let xml = graph.to_graphml();
+        assert!(xml.contains("Fix &lt;a&gt; &amp; &quot;b&quot;"));
+        assert!(xml.contains("directed=\"true\""));
+        let dot = graph.to_dot();
+        assert!(dot.contains("label=\"Fix <a> & \\\"b\\\"\""));
+        assert!(dot.contains("\"c:1\" -> \"c:2\" [kind=\"continuation\""));
+    }
+}
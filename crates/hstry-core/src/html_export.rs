@@ -0,0 +1,388 @@
+//! Native static HTML site export (`hstry export --format html`, and `e` in
+//! the TUI).
+//!
+//! Produces a self-contained directory that works from `file://`:
+//!
+//! ```text
+//! site/
+//!   index.html          conversation list + search box
+//!   search-index.js     search data (a script, so no fetch/CORS is needed)
+//!   assets/style.css
+//!   assets/search.js
+//!   c/<slug>.html       one page per conversation
+//! ```
+//!
+//! Markdown is rendered with pulldown-cmark; fenced code blocks are
+//! highlighted at export time with syntect so pages need no JS to read.
+
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+use serde::Serialize;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::html::highlighted_html_for_string;
+use syntect::parsing::SyntaxSet;
+
+use crate::error::Result;
+use crate::models::{Conversation, Message, MessageRole};
+
+/// Characters of message text per conversation kept in the search index.
+const SEARCH_TEXT_LIMIT: usize = 20_000;
+
+const STYLE_CSS: &str = include_str!("html_export/style.css");
+const SEARCH_JS: &str = include_str!("html_export/search.js");
+
+/// A conversation and the messages to render for it.
+pub struct SiteConversation {
+    pub conversation: Conversation,
+    pub messages: Vec<Message>,
+}
+
+#[derive(Serialize)]
+struct SearchEntry<'a> {
+    title: String,
+    href: String,
+    source: &'a str,
+    workspace: Option<&'a str>,
+    date: String,
+    text: String,
+}
+
+struct Highlighter {
+    syntaxes: SyntaxSet,
+    theme: Theme,
+}
+
+impl Highlighter {
+    fn new() -> Self {
+        let mut themes = ThemeSet::load_defaults();
+        let theme = themes.themes.remove("InspiredGitHub").unwrap_or_default();
+        Self {
+            syntaxes: SyntaxSet::load_defaults_newlines(),
+            theme,
+        }
+    }
+
+    fn code_block(&self, lang: &str, code: &str) -> String {
+        let syntax = self
+            .syntaxes
+            .find_syntax_by_token(lang)
+            .unwrap_or_else(|| self.syntaxes.find_syntax_plain_text());
+        highlighted_html_for_string(code, &self.syntaxes, syntax, &self.theme)
+            .unwrap_or_else(|_| format!("<pre><code>{}</code></pre>", escape(code)))
+    }
+}
+
+/// Write the static site for `conversations` into `output`.
+///
+/// Returns the number of conversation pages written.
+pub fn write_site(output: &Path, conversations: &[SiteConversation]) -> Result<usize> {
+    fs::create_dir_all(output.join("assets"))?;
+    fs::create_dir_all(output.join("c"))?;
+    fs::write(output.join("assets/style.css"), STYLE_CSS)?;
+    fs::write(output.join("assets/search.js"), SEARCH_JS)?;
+
+    let highlighter = Highlighter::new();
+    let mut used_slugs = HashSet::new();
+    let mut entries = Vec::with_capacity(conversations.len());
+    let mut rows = String::new();
+
+    for site_conv in conversations {
+        let conv = &site_conv.conversation;
+        let slug = unique_slug(conv, &mut used_slugs);
+        let href = format!("c/{slug}.html");
+        let title = conversation_title(site_conv);
+
+        fs::write(
+            output.join(&href),
+            render_conversation(site_conv, &title, &highlighter),
+        )?;
+
+        let date = conv.created_at.format("%Y-%m-%d").to_string();
+        let _ = write!(
+            rows,
+            r#"<li class="conv"><a href="{href}">{title}</a><span class="meta">{date} · {source}{workspace}</span></li>"#,
+            href = escape(&href),
+            title = escape(&title),
+            source = escape(&conv.source_id),
+            workspace = conv
+                .workspace
+                .as_deref()
+                .map(|ws| format!(" · {}", escape(ws)))
+                .unwrap_or_default(),
+        );
+        rows.push('\n');
+
+        entries.push(SearchEntry {
+            title,
+            href,
+            source: &conv.source_id,
+            workspace: conv.workspace.as_deref(),
+            date,
+            text: search_text(&site_conv.messages),
+        });
+    }
+
+    fs::write(
+        output.join("search-index.js"),
+        format!(
+            "window.HSTRY_INDEX = {};\n",
+            serde_json::to_string(&entries)?
+        ),
+    )?;
+    fs::write(
+        output.join("index.html"),
+        render_index(&rows, conversations.len()),
+    )?;
+
+    Ok(conversations.len())
+}
+
+fn render_index(rows: &str, count: usize) -> String {
+    format!(
+        r#"<!doctype html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>hstry export</title>
+<link rel="stylesheet" href="assets/style.css">
+</head>
+<body>
+<header><h1>hstry</h1><span class="meta">{count} conversation(s)</span></header>
+<main>
+<input id="search" type="search" placeholder="Search conversations…" autofocus>
+<ul id="results" class="convs"></ul>
+<ul id="all" class="convs">
+{rows}</ul>
+</main>
+<script src="search-index.js"></script>
+<script src="assets/search.js"></script>
+</body>
+</html>
+"#
+    )
+}
+
+fn render_conversation(site_conv: &SiteConversation, title: &str, hl: &Highlighter) -> String {
+    let conv = &site_conv.conversation;
+    let mut meta = vec![
+        conv.created_at.format("%Y-%m-%d %H:%M UTC").to_string(),
+        escape(&conv.source_id),
+    ];
+    if let Some(ws) = &conv.workspace {
+        meta.push(escape(ws));
+    }
+    if let Some(model) = &conv.model {
+        meta.push(escape(model));
+    }
+
+    let mut body = String::new();
+    for msg in &site_conv.messages {
+        let role = msg.role.to_string();
+        let mut header = escape(&role);
+        if let Some(model) = &msg.model {
+            let _ = write!(header, r#" <span class="meta">{}</span>"#, escape(model));
+        }
+        if let Some(at) = msg.created_at {
+            let _ = write!(
+                header,
+                r#" <span class="meta">{}</span>"#,
+                at.format("%Y-%m-%d %H:%M")
+            );
+        }
+        let content = render_markdown(&msg.content, hl);
+        if msg.role == MessageRole::Tool {
+            let _ = write!(
+                body,
+                r#"<section class="msg {role}"><details><summary>{header}</summary>{content}</details></section>"#
+            );
+        } else {
+            let _ = write!(
+                body,
+                r#"<section class="msg {role}"><h2>{header}</h2>{content}</section>"#
+            );
+        }
+        body.push('\n');
+    }
+
+    format!(
+        r#"<!doctype html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title}</title>
+<link rel="stylesheet" href="../assets/style.css">
+</head>
+<body>
+<header><a href="../index.html">← all conversations</a></header>
+<main>
+<h1>{title}</h1>
+<p class="meta">{meta}</p>
+{body}</main>
+</body>
+</html>
+"#,
+        title = escape(title),
+        meta = meta.join(" · "),
+    )
+}
+
+/// Render message markdown to HTML. Raw HTML in the source is escaped, since
+/// chat content is untrusted, and fenced code is highlighted.
+fn render_markdown(markdown: &str, hl: &Highlighter) -> String {
+    let mut events = Vec::new();
+    let mut code: Option<(String, String)> = None;
+
+    for event in Parser::new_ext(markdown, Options::all()) {
+        match event {
+            Event::Start(Tag::CodeBlock(kind)) => {
+                let lang = match kind {
+                    CodeBlockKind::Fenced(info) => info
+                        .split_whitespace()
+                        .next()
+                        .unwrap_or_default()
+                        .to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                code = Some((lang, String::new()));
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                if let Some((lang, text)) = code.take() {
+                    events.push(Event::Html(hl.code_block(&lang, &text).into()));
+                }
+            }
+            Event::Text(text) if code.is_some() => {
+                if let Some((_, buf)) = code.as_mut() {
+                    buf.push_str(&text);
+                }
+            }
+            Event::Html(html) | Event::InlineHtml(html) => events.push(Event::Text(html)),
+            Event::Start(Tag::Link {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }) if is_script_url(&dest_url) => events.push(Event::Start(Tag::Link {
+                link_type,
+                dest_url: "#".into(),
+                title,
+                id,
+            })),
+            // Keep only the alt text so pages never fetch remote images.
+            Event::Start(Tag::Image { .. }) | Event::End(TagEnd::Image) => {}
+            other => events.push(other),
+        }
+    }
+
+    let mut html = String::new();
+    pulldown_cmark::html::push_html(&mut html, events.into_iter());
+    html
+}
+
+fn is_script_url(url: &str) -> bool {
+    let url = url.trim_start().to_ascii_lowercase();
+    url.starts_with("javascript:") || url.starts_with("vbscript:") || url.starts_with("data:")
+}
+
+fn conversation_title(site_conv: &SiteConversation) -> String {
+    if let Some(title) = site_conv
+        .conversation
+        .title
+        .as_deref()
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+    {
+        return title.to_string();
+    }
+    site_conv
+        .messages
+        .iter()
+        .find(|m| m.role == MessageRole::User)
+        .and_then(|m| m.content.lines().map(str::trim).find(|l| !l.is_empty()))
+        .map(|line| line.chars().take(80).collect())
+        .unwrap_or_else(|| "Untitled conversation".to_string())
+}
+
+fn search_text(messages: &[Message]) -> String {
+    let mut text = String::new();
+    for msg in messages
+        .iter()
+        .filter(|m| matches!(m.role, MessageRole::User | MessageRole::Assistant))
+    {
+        if text.len() >= SEARCH_TEXT_LIMIT {
+            break;
+        }
+        text.push_str(&msg.content);
+        text.push('\n');
+    }
+    if text.len() > SEARCH_TEXT_LIMIT {
+        let mut end = SEARCH_TEXT_LIMIT;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+    }
+    text
+}
+
+fn unique_slug(conv: &Conversation, used: &mut HashSet<String>) -> String {
+    let base = conv
+        .readable_id
+        .clone()
+        .unwrap_or_else(|| conv.id.to_string());
+    let base: String = base
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    let mut slug = base.clone();
+    let mut n = 2;
+    while !used.insert(slug.clone()) {
+        slug = format!("{base}-{n}");
+        n += 1;
+    }
+    slug
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markdown_escapes_raw_html_and_highlights_code() {
+        let hl = Highlighter::new();
+        let html = render_markdown(
+            "<script>alert(1)</script>\n\n```rust\nfn main() {}\n```\n",
+            &hl,
+        );
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("&lt;script&gt;"));
+        assert!(html.contains("<pre style="));
+        assert!(html.contains("main"));
+    }
+}
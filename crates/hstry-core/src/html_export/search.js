@@ -0,0 +1,69 @@
+// Client-side search over window.HSTRY_INDEX (written by search-index.js).
+(function () {
+  "use strict";
+
+  var input = document.getElementById("search");
+  var results = document.getElementById("results");
+  var all = document.getElementById("all");
+  var index = (window.HSTRY_INDEX || []).map(function (entry) {
+    return {
+      entry: entry,
+      haystack: (entry.title + "\n" + (entry.workspace || "") + "\n" + entry.text).toLowerCase(),
+    };
+  });
+
+  function snippet(text, term) {
+    var at = text.toLowerCase().indexOf(term);
+    if (at < 0) {
+      return "";
+    }
+    var start = Math.max(0, at - 60);
+    return (start > 0 ? "…" : "") + text.slice(start, at + term.length + 100).replace(/\s+/g, " ") + "…";
+  }
+
+  function render(matches, terms) {
+    results.textContent = "";
+    matches.slice(0, 200).forEach(function (item) {
+      var entry = item.entry;
+      var li = document.createElement("li");
+      li.className = "conv";
+
+      var link = document.createElement("a");
+      link.href = entry.href;
+      link.textContent = entry.title;
+      li.appendChild(link);
+
+      var meta = document.createElement("span");
+      meta.className = "meta";
+      meta.textContent = entry.date + " · " + entry.source + (entry.workspace ? " · " + entry.workspace : "");
+      li.appendChild(meta);
+
+      var text = snippet(entry.text, terms[0]);
+      if (text) {
+        var snip = document.createElement("span");
+        snip.className = "snippet";
+        snip.textContent = text;
+        li.appendChild(snip);
+      }
+      results.appendChild(li);
+    });
+  }
+
+  input.addEventListener("input", function () {
+    var terms = input.value.toLowerCase().split(/\s+/).filter(Boolean);
+    if (terms.length === 0) {
+      results.textContent = "";
+      all.hidden = false;
+      return;
+    }
+    all.hidden = true;
+    render(
+      index.filter(function (item) {
+        return terms.every(function (term) {
+          return item.haystack.indexOf(term) >= 0;
+        });
+      }),
+      terms
+    );
+  });
+})();
@@ -0,0 +1,198 @@
+//! Translated user-facing strings for the CLI and TUI.
+//!
+//! Messages are Fluent files compiled in from `locales/<language>/hstry.ftl`.
+//! [`init`] picks the language once per process: the `locale` config key,
+//! else `LC_ALL`, `LC_MESSAGES` or `LANG`. [`tr!`] formats a message,
+//! falling back to English when the language lacks it.
+
+use std::sync::OnceLock;
+
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource};
+use unic_langid::LanguageIdentifier;
+
+pub use fluent_bundle::FluentValue;
+
+/// Compiled-in locales, English first.
+const LOCALES: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en/hstry.ftl")),
+    ("de", include_str!("../locales/de/hstry.ftl")),
+];
+
+/// Languages with a translation.
+pub fn available() -> impl Iterator<Item = &'static str> {
+    LOCALES.iter().map(|(lang, _)| *lang)
+}
+
+static ACTIVE: OnceLock<Translations> = OnceLock::new();
+
+/// Select the language for this process. The first call wins; formatting a
+/// message before any call selects the language from the environment.
+pub fn init(locale: Option<&str>) {
+    let _ = ACTIVE.set(Translations::new(locale.or(env_locale().as_deref())));
+}
+
+/// The selected language, e.g. "de".
+pub fn language() -> &'static str {
+    active().language
+}
+
+/// Format message `id` in the selected language. See [`tr!`].
+pub fn tr(id: &str, args: &[(&str, FluentValue<'_>)]) -> String {
+    active().format(id, args)
+}
+
+/// Format a message with named arguments:
+/// `tr!("tui-sorted-by", order = label)`.
+#[macro_export]
+macro_rules! tr {
+    ($id:literal) => {
+        $crate::i18n::tr($id, &[])
+    };
+    ($id:literal, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::i18n::tr(
+            $id,
+            &[$((stringify!($name), $crate::i18n::FluentValue::from($value))),+],
+        )
+    };
+}
+
+fn active() -> &'static Translations {
+    ACTIVE.get_or_init(|| Translations::new(env_locale().as_deref()))
+}
+
+fn env_locale() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())
+}
+
+/// The bundle for one language plus the English fallback.
+struct Translations {
+    language: &'static str,
+    /// The selected language first, then English unless it is English.
+    bundles: Vec<FluentBundle<FluentResource>>,
+}
+
+impl Translations {
+    fn new(locale: Option<&str>) -> Self {
+        let english = &LOCALES[0];
+        let selected = locale
+            .and_then(match_language)
+            .and_then(|language| LOCALES.iter().find(|(lang, _)| *lang == language))
+            .unwrap_or(english);
+        let mut bundles = vec![bundle(selected)];
+        if selected != english {
+            bundles.push(bundle(english));
+        }
+        Self {
+            language: selected.0,
+            bundles,
+        }
+    }
+
+    fn format(&self, id: &str, args: &[(&str, FluentValue<'_>)]) -> String {
+        let mut fluent_args = FluentArgs::new();
+        for (name, value) in args {
+            fluent_args.set(*name, value.clone());
+        }
+        for bundle in &self.bundles {
+            let Some(pattern) = bundle.get_message(id).and_then(|m| m.value()) else {
+                continue;
+            };
+            let mut errors = Vec::new();
+            let text = bundle.format_pattern(pattern, Some(&fluent_args), &mut errors);
+            if !errors.is_empty() {
+                tracing::debug!("formatting message {id}: {errors:?}");
+            }
+            return text.into_owned();
+        }
+        tracing::debug!("missing message {id}");
+        id.to_string()
+    }
+}
+
+/// The compiled-in language for a locale such as `de_DE.UTF-8` or `de-AT`.
+fn match_language(locale: &str) -> Option<&'static str> {
+    let tag = locale
+        .split(['.', '@'])
+        .next()
+        .unwrap_or_default()
+        .replace('_', "-");
+    let requested: LanguageIdentifier = tag.parse().ok()?;
+    available().find(|lang| *lang == requested.language.as_str())
+}
+
+/// The bundle for a [`LOCALES`] entry.
+fn bundle(&(language, source): &(&str, &str)) -> FluentBundle<FluentResource> {
+    // The compiled-in tags are valid; an invalid one would only lose
+    // language-specific plural and number rules.
+    let langid: LanguageIdentifier = language.parse().unwrap_or_default();
+    let mut bundle = FluentBundle::new_concurrent(vec![langid]);
+    // Unicode isolation marks show up as stray characters in terminals.
+    bundle.set_use_isolating(false);
+    let resource =
+        FluentResource::try_new(source.to_string()).unwrap_or_else(|(resource, errors)| {
+            tracing::warn!("errors in the {language} messages: {errors:?}");
+            resource
+        });
+    if let Err(errors) = bundle.add_resource(resource) {
+        tracing::warn!("duplicate {language} messages: {errors:?}");
+    }
+    bundle
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_in_the_selected_language_with_english_fallback() {
+        let de = Translations::new(Some("de_DE.UTF-8"));
+        assert_eq!(de.language, "de");
+        assert_eq!(
+            de.format("tui-sorted-by", &[("order", "Quelle".into())]),
+            "Sortiert nach: Quelle"
+        );
+        assert_eq!(de.format("no-such-message", &[]), "no-such-message");
+
+        let en = Translations::new(Some("fr_FR"));
+        assert_eq!(en.language, "en");
+        assert_eq!(
+            en.format(
+                "tui-found-results",
+                &[("count", 1.into()), ("scope", "local".into())]
+            ),
+            "Found 1 result (local)"
+        );
+        assert_eq!(
+            en.format(
+                "tui-found-results",
+                &[("count", 3.into()), ("scope", "local".into())]
+            ),
+            "Found 3 results (local)"
+        );
+        assert_eq!(Translations::new(Some("C")).language, "en");
+    }
+
+    #[test]
+    fn every_locale_translates_every_message() {
+        let ids: Vec<&str> = LOCALES[0]
+            .1
+            .lines()
+            .filter(|line| line.starts_with(|c: char| c.is_ascii_lowercase()))
+            .filter_map(|line| line.split_once(" = ").map(|(id, _)| id))
+            .collect();
+        assert!(ids.contains(&"tui-help-hint"));
+        for language in available().skip(1) {
+            let translated = Translations::new(Some(language));
+            for id in &ids {
+                assert!(
+                    translated.bundles[0].has_message(id),
+                    "{language} is missing {id}"
+                );
+            }
+        }
+    }
+}
@@ -10,8 +10,9 @@ use chrono::Utc;
 use uuid::Uuid;
 
 use crate::Database;
-use crate::parsed::ParsedConversation;
+use crate::parsed::{ParsedConversation, ParsedMessage};
 use crate::stable_message_id;
+use crate::tool_calls::{self, Extracted};
 
 #[derive(Debug, Clone, Default)]
 pub struct IngestOutcome {
@@ -23,6 +24,9 @@ pub struct IngestOutcome {
     pub updated: usize,
     /// Messages submitted in this batch (duplicates dedupe at the DB layer).
     pub messages: usize,
+    /// Bytes of message content submitted in this batch; what
+    /// [`Database::pace_writes`] is given.
+    pub bytes: u64,
     /// Conversation ids touched by this batch; pass to
     /// [`Database::rebuild_conversation_summaries`] once syncing completes.
     pub affected_conversation_ids: Vec<Uuid>,
@@ -40,6 +44,9 @@ pub async fn ingest_batch(
 
     let mut batch_convs: Vec<crate::models::Conversation> = Vec::new();
     let mut batch_msgs: Vec<crate::models::Message> = Vec::new();
+    let mut batch_tools = Extracted::default();
+    let mut tool_ranges: Vec<(Uuid, i32, i32)> = Vec::new();
+    let mut parent_links: Vec<(Uuid, i32, i32)> = Vec::new();
 
     // Track conversation ids chosen in this batch by external_id so duplicate
     // rows for the same conversation map to one hstry conversation id.
@@ -172,39 +179,22 @@ pub async fn ingest_batch(
             let Ok(idx) = i32::try_from(idx) else {
                 continue;
             };
-            let parts_json = msg.parts.clone().unwrap_or_else(|| serde_json::json!([]));
-            let role_str = msg.role.as_str();
-            // Stable, content-addressable message id (trx-hjjw.4): replays
-            // of the same data produce the same row id, so the existing
-            // ON CONFLICT clauses naturally dedupe.
-            let stable_id = stable_message_id(
+            batch_msgs.push(to_message(
                 source_id,
+                hstry_conv.id,
                 hstry_conv.external_id.as_deref(),
                 idx,
-                role_str,
-                &msg.content,
-                None,
-            );
-            let hstry_msg = crate::models::Message {
-                id: stable_id,
-                conversation_id: hstry_conv.id,
-                idx,
-                role: crate::models::MessageRole::from(role_str),
-                content: msg.content.clone(),
-                parts_json,
-                created_at: msg.created_at.and_then(|ts| {
-                    chrono::DateTime::from_timestamp_millis(ts).map(|dt| dt.with_timezone(&Utc))
-                }),
-                model: msg.model.clone(),
-                tokens: msg.tokens,
-                cost_usd: msg.cost_usd,
-                metadata: serde_json::Value::Object(serde_json::Map::default()),
-                sender: None,
-                provider: None,
-                harness: None,
-                client_id: None,
-            };
-            batch_msgs.push(hstry_msg);
+                msg,
+            ));
+            batch_tools.extend(extract_tool_calls(hstry_conv.id, idx, msg));
+            if let Some(parent_idx) = msg.parent_idx.filter(|p| *p != idx) {
+                parent_links.push((hstry_conv.id, idx, parent_idx));
+            }
+        }
+        if let Ok(count) = i32::try_from(conv.messages.len())
+            && count > 0
+        {
+            tool_ranges.push((hstry_conv.id, 0, count - 1));
         }
     }
 
@@ -222,7 +212,15 @@ pub async fn ingest_batch(
             db.upsert_conversation_in_tx(&mut tx, conv).await?;
         }
         db.bulk_insert_messages_in_tx(&mut tx, &batch_msgs).await?;
+        db.link_message_parents_in_tx(&mut tx, &parent_links)
+            .await?;
+        db.write_tool_calls_in_tx(&mut tx, &tool_ranges, &batch_tools)
+            .await?;
         outcome.messages += batch_msgs.len();
+        outcome.bytes += batch_msgs
+            .iter()
+            .map(|m| m.content.len() as u64)
+            .sum::<u64>();
 
         tx.commit().await?;
     }
@@ -265,13 +263,126 @@ pub async fn ingest_batch(
     Ok(outcome)
 }
 
+/// Convert a parsed message at position `idx` into a stored [`Message`].
+///
+/// [`Message`]: crate::models::Message
+fn to_message(
+    source_id: &str,
+    conversation_id: Uuid,
+    external_id: Option<&str>,
+    idx: i32,
+    msg: &ParsedMessage,
+) -> crate::models::Message {
+    let parts_json = msg.parts.clone().unwrap_or_else(|| serde_json::json!([]));
+    let role_str = msg.role.as_str();
+    // Stable, content-addressable message id (trx-hjjw.4): replays of the
+    // same data produce the same row id, so the existing ON CONFLICT clauses
+    // naturally dedupe.
+    let stable_id = stable_message_id(source_id, external_id, idx, role_str, &msg.content, None);
+    crate::models::Message {
+        id: stable_id,
+        conversation_id,
+        idx,
+        role: crate::models::MessageRole::from(role_str),
+        content: msg.content.clone(),
+        parts_json,
+        created_at: msg.created_at.and_then(|ts| {
+            chrono::DateTime::from_timestamp_millis(ts).map(|dt| dt.with_timezone(&Utc))
+        }),
+        model: msg.model.clone(),
+        tokens: msg.tokens,
+        cost_usd: msg.cost_usd,
+        metadata: serde_json::Value::Object(serde_json::Map::default()),
+        sender: None,
+        provider: None,
+        harness: None,
+        client_id: None,
+        parent_message_id: None,
+    }
+}
+
+fn extract_tool_calls(conversation_id: Uuid, idx: i32, msg: &ParsedMessage) -> Extracted {
+    let empty = serde_json::json!([]);
+    tool_calls::extract(
+        conversation_id,
+        idx,
+        msg.parts.as_ref().unwrap_or(&empty),
+        msg.tool_calls.as_deref(),
+    )
+}
+
+/// Append `messages` to an existing conversation identified by
+/// `source_id` + `external_id`, for live capture that streams new turns
+/// instead of re-sending the whole history.
+///
+/// Messages are numbered from `start_idx`, or after the current last message
+/// when `None`. Passing an explicit `start_idx` makes retries idempotent: the
+/// same position and content map to the same stable message id. Returns
+/// `None` when the conversation does not exist.
+pub async fn append_messages(
+    db: &Database,
+    source_id: &str,
+    external_id: &str,
+    messages: Vec<ParsedMessage>,
+    start_idx: Option<i32>,
+) -> Result<Option<IngestOutcome>> {
+    let Some(conversation_id) = db.get_conversation_id(source_id, external_id).await? else {
+        return Ok(None);
+    };
+
+    let _writer = db.lock_ingest_writer().await;
+    let start_idx = match start_idx {
+        Some(idx) => idx,
+        None => i32::try_from(db.count_messages_for_conversation(conversation_id).await?)?,
+    };
+    let mut batch_msgs = Vec::with_capacity(messages.len());
+    let mut batch_tools = Extracted::default();
+    let mut parent_links = Vec::new();
+    for (offset, msg) in messages.iter().enumerate() {
+        let idx = start_idx + i32::try_from(offset)?;
+        batch_msgs.push(to_message(
+            source_id,
+            conversation_id,
+            Some(external_id),
+            idx,
+            msg,
+        ));
+        batch_tools.extend(extract_tool_calls(conversation_id, idx, msg));
+        if let Some(parent_idx) = msg.parent_idx.filter(|p| *p != idx) {
+            parent_links.push((conversation_id, idx, parent_idx));
+        }
+    }
+
+    let mut tx = db.begin().await?;
+    db.bulk_insert_messages_in_tx(&mut tx, &batch_msgs).await?;
+    db.link_message_parents_in_tx(&mut tx, &parent_links)
+        .await?;
+    if let Some(last) = batch_msgs.last() {
+        db.write_tool_calls_in_tx(
+            &mut tx,
+            &[(conversation_id, start_idx, last.idx)],
+            &batch_tools,
+        )
+        .await?;
+    }
+    tx.commit().await?;
+
+    Ok(Some(IngestOutcome {
+        conversations: 1,
+        created: 0,
+        updated: 1,
+        messages: batch_msgs.len(),
+        bytes: batch_msgs.iter().map(|m| m.content.len() as u64).sum(),
+        affected_conversation_ids: vec![conversation_id],
+    }))
+}
+
 #[cfg(test)]
 mod tests {
     use anyhow::Result;
 
     use super::*;
     use crate::models::Source;
-    use crate::parsed::{ParsedConversation, ParsedMessage};
 
     fn parsed_conversation() -> ParsedConversation {
         ParsedConversation {
@@ -296,6 +407,7 @@ mod tests {
                 parts: None,
                 tool_calls: None,
                 metadata: None,
+                parent_idx: None,
             }],
             metadata: None,
             version: None,
@@ -303,6 +415,7 @@ mod tests {
             parent_external_id: None,
             parent_message_idx: None,
             fork_type: None,
+            source_id: None,
         }
     }
 
@@ -381,4 +494,134 @@ mod tests {
         );
         Ok(())
     }
+
+    #[tokio::test]
+    async fn append_messages_extends_existing_conversation() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("hstry-ingest-{}.db", Uuid::new_v4()));
+        let db = Database::open(&path).await?;
+        db.upsert_source(&Source {
+            id: "live".to_string(),
+            adapter: "live".to_string(),
+            path: None,
+            last_sync_at: None,
+            config: serde_json::json!({}),
+        })
+        .await?;
+        ingest_batch(&db, "live", vec![parsed_conversation()]).await?;
+
+        let reply = ParsedMessage {
+            role: "assistant".to_string(),
+            content: "hi there".to_string(),
+            ..parsed_conversation().messages[0].clone()
+        };
+        let outcome = append_messages(&db, "live", "progress-fixture", vec![reply.clone()], None)
+            .await?
+            .map(|outcome| outcome.messages);
+        assert_eq!(outcome, Some(1));
+
+        // Retrying at the same explicit position is idempotent.
+        append_messages(&db, "live", "progress-fixture", vec![reply], Some(1)).await?;
+        let conv_id = db.get_conversation_id("live", "progress-fixture").await?;
+        let messages = db.get_messages(conv_id.unwrap_or_default()).await?;
+        let contents: Vec<_> = messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, vec!["hello", "hi there"]);
+
+        let missing = append_messages(&db, "live", "nope", Vec::new(), None).await?;
+        assert!(missing.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn tool_calls_are_stored_and_resolved_across_appends() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("hstry-ingest-{}.db", Uuid::new_v4()));
+        let db = Database::open(&path).await?;
+        db.upsert_source(&Source {
+            id: "live".to_string(),
+            adapter: "live".to_string(),
+            path: None,
+            last_sync_at: None,
+            config: serde_json::json!({}),
+        })
+        .await?;
+
+        let mut conv = parsed_conversation();
+        conv.messages[0].parts = Some(serde_json::json!([
+            {"type": "tool_call", "id": "p1", "toolCallId": "t1", "name": "bash",
+             "input": {"command": "ls"}}
+        ]));
+        // Re-ingesting the same history must not duplicate calls.
+        ingest_batch(&db, "live", vec![conv.clone()]).await?;
+        ingest_batch(&db, "live", vec![conv]).await?;
+
+        let result = ParsedMessage {
+            role: "tool".to_string(),
+            content: "a.txt".to_string(),
+            parts: Some(serde_json::json!([
+                {"type": "tool_result", "id": "p2", "toolCallId": "t1", "output": "a.txt",
+                 "isError": true}
+            ])),
+            ..parsed_conversation().messages[0].clone()
+        };
+        append_messages(&db, "live", "progress-fixture", vec![result], None).await?;
+
+        let conv_id = db
+            .get_conversation_id("live", "progress-fixture")
+            .await?
+            .unwrap_or_default();
+        let calls = db.get_tool_calls(conv_id).await?;
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].tool_name, "bash");
+        assert_eq!(calls[0].output.as_deref(), Some("a.txt"));
+        assert_eq!(calls[0].status, Some(crate::models::ToolStatus::Error));
+
+        let usage = db
+            .tool_usage_stats(&crate::db::ListToolCallsOptions::default())
+            .await?;
+        assert_eq!(
+            (usage[0].tool_name.as_str(), usage[0].calls, usage[0].errors),
+            ("bash", 1, 1)
+        );
+        assert_eq!(db.rebuild_tool_calls().await?, 1);
+        assert_eq!(db.get_tool_calls(conv_id).await?.len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn parent_idx_links_branches_by_position() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("hstry-ingest-{}.db", Uuid::new_v4()));
+        let db = Database::open(&path).await?;
+        db.upsert_source(&Source {
+            id: "web".to_string(),
+            adapter: "web".to_string(),
+            path: None,
+            last_sync_at: None,
+            config: serde_json::json!({}),
+        })
+        .await?;
+
+        let mut conv = parsed_conversation();
+        let prompt = conv.messages[0].clone();
+        let answer = |content: &str| ParsedMessage {
+            role: "assistant".to_string(),
+            content: content.to_string(),
+            parent_idx: Some(0),
+            ..prompt.clone()
+        };
+        conv.messages = vec![prompt.clone(), answer("first try"), answer("regenerated")];
+        ingest_batch(&db, "web", vec![conv]).await?;
+
+        let conv_id = db
+            .get_conversation_id("web", "progress-fixture")
+            .await?
+            .unwrap_or_default();
+        assert!(db.has_message_branches(conv_id).await?);
+        let messages = db.get_messages(conv_id).await?;
+        assert_eq!(messages[2].parent_message_id, Some(messages[0].id));
+        let thread: Vec<_> = crate::branches::active_thread(messages)
+            .into_iter()
+            .map(|m| m.content)
+            .collect();
+        assert_eq!(thread, vec!["hello", "regenerated"]);
+        Ok(())
+    }
 }
@@ -0,0 +1,548 @@
+//! Write-ahead buffer for high-frequency live ingestion.
+//!
+//! Live recorders (the HTTP `/ingest` endpoints) can emit a write per tool
+//! event. Committing each one separately costs a transaction, a summary
+//! rebuild and a writer-lock handoff per event. [`IngestBuffer`] queues the
+//! writes in memory, mirrors them to an NDJSON journal so a crash loses
+//! nothing, and flushes them on an interval: conversation pushes are grouped
+//! per source into one [`ingest_batch`] transaction and consecutive message
+//! appends to the same conversation are coalesced into one
+//! [`append_messages`] call.
+
+use std::collections::{BTreeSet, HashMap};
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, Notify};
+use uuid::Uuid;
+
+use crate::Database;
+use crate::config::IngestBufferConfig;
+use crate::ingest::{append_messages, ingest_batch};
+use crate::models::Source;
+use crate::parsed::{ParsedConversation, ParsedMessage};
+
+/// One queued write, as accepted by the ingest endpoints.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BufferedWrite {
+    /// Full or partial conversations for `source` (see [`ingest_batch`]).
+    Conversations {
+        source: String,
+        /// Adapter label stored on a newly created source.
+        adapter: Option<String>,
+        conversations: Vec<ParsedConversation>,
+    },
+    /// Messages appended to an existing conversation (see [`append_messages`]).
+    Messages {
+        source: String,
+        external_id: String,
+        start_idx: Option<i32>,
+        messages: Vec<ParsedMessage>,
+    },
+}
+
+impl BufferedWrite {
+    fn source(&self) -> &str {
+        match self {
+            Self::Conversations { source, .. } | Self::Messages { source, .. } => source,
+        }
+    }
+}
+
+/// Totals for one [`IngestBuffer::flush`].
+#[derive(Debug, Clone, Default)]
+pub struct FlushOutcome {
+    /// Buffered writes drained.
+    pub writes: usize,
+    /// Conversations written (new or updated).
+    pub conversations: usize,
+    /// Messages written.
+    pub messages: usize,
+    /// Message appends dropped because their conversation does not exist.
+    pub orphaned: usize,
+}
+
+#[derive(Default)]
+struct State {
+    pending: Vec<BufferedWrite>,
+    /// Conversations written by a flush whose summary rebuild has not
+    /// succeeded yet.
+    stale_summaries: BTreeSet<Uuid>,
+}
+
+/// In-memory write queue with an optional NDJSON journal.
+pub struct IngestBuffer {
+    db: Arc<Database>,
+    state: Mutex<State>,
+    /// Serializes flushes so journal rewrites never interleave.
+    flushing: Mutex<()>,
+    journal: Option<PathBuf>,
+    max_pending: usize,
+    wake: Notify,
+}
+
+impl IngestBuffer {
+    /// Create a buffer, replaying any writes left in `journal` by a previous
+    /// process. Replayed writes are flushed on the next tick.
+    pub fn open(
+        db: Arc<Database>,
+        config: &IngestBufferConfig,
+        journal: Option<PathBuf>,
+    ) -> Result<Self> {
+        let pending = match journal.as_deref() {
+            Some(path) => read_journal(path)?,
+            None => Vec::new(),
+        };
+        if !pending.is_empty() {
+            tracing::info!("Replaying {} buffered ingest writes", pending.len());
+        }
+        Ok(Self {
+            db,
+            state: Mutex::new(State {
+                pending,
+                ..State::default()
+            }),
+            flushing: Mutex::new(()),
+            journal,
+            max_pending: config.max_pending.max(1),
+            wake: Notify::new(),
+        })
+    }
+
+    /// Queue a write. It is durable once this returns when a journal is
+    /// configured; it becomes visible to readers after the next flush.
+    pub async fn push(&self, write: BufferedWrite) -> Result<()> {
+        let mut state = self.state.lock().await;
+        if let Some(path) = self.journal.as_deref() {
+            append_journal(path, &write)?;
+        }
+        state.pending.push(write);
+        if state.pending.len() >= self.max_pending {
+            self.wake.notify_one();
+        }
+        Ok(())
+    }
+
+    /// Number of writes waiting for the next flush.
+    pub async fn pending(&self) -> usize {
+        self.state.lock().await.pending.len()
+    }
+
+    /// Commit every pending write.
+    ///
+    /// Each source's conversations and each run of appends commit in their
+    /// own transaction. On failure only the writes that did not commit are
+    /// put back at the front of the queue (and kept in the journal), so the
+    /// next flush retries them without repeating the others. Summaries of
+    /// the committed ones are rebuilt on a later flush.
+    pub async fn flush(&self) -> Result<FlushOutcome> {
+        let _flushing = self.flushing.lock().await;
+        let batch = {
+            let mut state = self.state.lock().await;
+            if state.pending.is_empty() && state.stale_summaries.is_empty() {
+                return Ok(FlushOutcome::default());
+            }
+            std::mem::take(&mut state.pending)
+        };
+        let mut committed = vec![false; batch.len()];
+        let mut affected = BTreeSet::new();
+        let result = self.commit(&batch, &mut committed, &mut affected).await;
+
+        let (outcome, stale) = {
+            let mut state = self.state.lock().await;
+            state.stale_summaries.extend(affected);
+            if committed.iter().any(|done| !done) {
+                let newer = std::mem::take(&mut state.pending);
+                state.pending = batch
+                    .into_iter()
+                    .zip(committed)
+                    .filter_map(|(write, done)| (!done).then_some(write))
+                    .chain(newer)
+                    .collect();
+            }
+            if let Some(path) = self.journal.as_deref() {
+                rewrite_journal(path, &state.pending)?;
+            }
+            (result?, std::mem::take(&mut state.stale_summaries))
+        };
+
+        // A failed rebuild is retried on the next flush.
+        if !stale.is_empty() {
+            let ids: Vec<Uuid> = stale.iter().copied().collect();
+            if let Err(err) = self.db.rebuild_conversation_summaries(&ids).await {
+                self.state.lock().await.stale_summaries.extend(stale);
+                return Err(err.into());
+            }
+        }
+        Ok(outcome)
+    }
+
+    /// Flush every `config.flush_interval_ms`, or sooner once
+    /// `config.max_pending` writes are queued.
+    pub fn spawn_flusher(
+        self: &Arc<Self>,
+        config: &IngestBufferConfig,
+    ) -> tokio::task::JoinHandle<()> {
+        let buffer = Arc::clone(self);
+        let interval = Duration::from_millis(config.flush_interval_ms.max(1));
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    () = tokio::time::sleep(interval) => {}
+                    () = buffer.wake.notified() => {}
+                }
+                if let Err(err) = buffer.flush().await {
+                    tracing::warn!("Ingest buffer flush failed: {err:#}");
+                }
+            }
+        })
+    }
+
+    /// Commit `batch`, marking the writes in `committed` as their
+    /// transactions commit and collecting the conversations they touched.
+    async fn commit(
+        &self,
+        batch: &[BufferedWrite],
+        committed: &mut [bool],
+        affected: &mut BTreeSet<Uuid>,
+    ) -> Result<FlushOutcome> {
+        let mut outcome = FlushOutcome {
+            writes: batch.len(),
+            ..FlushOutcome::default()
+        };
+
+        let mut adapters: HashMap<&str, Option<&str>> = HashMap::new();
+        for write in batch {
+            let adapter = match write {
+                BufferedWrite::Conversations { adapter, .. } => adapter.as_deref(),
+                BufferedWrite::Messages { .. } => None,
+            };
+            let entry = adapters.entry(write.source()).or_default();
+            if entry.is_none() {
+                *entry = adapter;
+            }
+        }
+        for (source_id, adapter) in &adapters {
+            self.touch_source(source_id, *adapter).await?;
+        }
+
+        // Conversations first so appends queued right after a new
+        // conversation find it.
+        let mut by_source: Vec<(&str, Vec<usize>, Vec<ParsedConversation>)> = Vec::new();
+        for (position, write) in batch.iter().enumerate() {
+            if let BufferedWrite::Conversations {
+                source,
+                conversations,
+                ..
+            } = write
+            {
+                match by_source.iter_mut().find(|(id, _, _)| *id == source) {
+                    Some((_, positions, convs)) => {
+                        positions.push(position);
+                        convs.extend(conversations.iter().cloned());
+                    }
+                    None => by_source.push((source, vec![position], conversations.clone())),
+                }
+            }
+        }
+        for (source_id, positions, conversations) in by_source {
+            let result = ingest_batch(&self.db, source_id, conversations).await?;
+            outcome.conversations += result.conversations;
+            outcome.messages += result.messages;
+            affected.extend(result.affected_conversation_ids);
+            for position in positions {
+                committed[position] = true;
+            }
+        }
+
+        for run in coalesce_appends(batch) {
+            match append_messages(
+                &self.db,
+                run.source,
+                run.external_id,
+                run.messages,
+                run.start_idx,
+            )
+            .await?
+            {
+                Some(result) => {
+                    outcome.messages += result.messages;
+                    affected.extend(result.affected_conversation_ids);
+                }
+                None => {
+                    tracing::warn!(
+                        "Dropping buffered messages for unknown conversation '{}' in source '{}'",
+                        run.external_id,
+                        run.source
+                    );
+                    outcome.orphaned += 1;
+                }
+            }
+            for position in run.writes {
+                committed[position] = true;
+            }
+        }
+        Ok(outcome)
+    }
+
+    async fn touch_source(&self, source_id: &str, adapter: Option<&str>) -> Result<()> {
+        let mut source = self
+            .db
+            .get_source(source_id)
+            .await?
+            .unwrap_or_else(|| Source {
+                id: source_id.to_string(),
+                adapter: adapter.unwrap_or(source_id).to_string(),
+                path: None,
+                last_sync_at: None,
+                config: serde_json::json!({}),
+            });
+        source.last_sync_at = Some(Utc::now());
+        self.db.upsert_source(&source).await?;
+        Ok(())
+    }
+}
+
+/// Consecutive appends to one conversation, merged into one call.
+struct AppendRun<'a> {
+    /// Positions in the batch of the writes merged into this run.
+    writes: Vec<usize>,
+    source: &'a str,
+    external_id: &'a str,
+    start_idx: Option<i32>,
+    messages: Vec<ParsedMessage>,
+}
+
+/// Merge consecutive appends to the same conversation. An explicit
+/// `start_idx` starts a new run so positioned writes keep their index.
+fn coalesce_appends(batch: &[BufferedWrite]) -> Vec<AppendRun<'_>> {
+    let mut runs: Vec<AppendRun<'_>> = Vec::new();
+    for (position, write) in batch.iter().enumerate() {
+        let BufferedWrite::Messages {
+            source,
+            external_id,
+            start_idx,
+            messages,
+        } = write
+        else {
+            continue;
+        };
+        if let Some(last) = runs.last_mut()
+            && last.source == source
+            && last.external_id == external_id
+            && start_idx.is_none()
+        {
+            last.writes.push(position);
+            last.messages.extend(messages.iter().cloned());
+            continue;
+        }
+        runs.push(AppendRun {
+            writes: vec![position],
+            source,
+            external_id,
+            start_idx: *start_idx,
+            messages: messages.clone(),
+        });
+    }
+    runs
+}
+
+fn read_journal(path: &Path) -> Result<Vec<BufferedWrite>> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err).with_context(|| format!("read {}", path.display())),
+    };
+    let mut writes = Vec::new();
+    for (line_no, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(line) {
+            Ok(write) => writes.push(write),
+            // A torn final line means the process died mid-append; that write
+            // was never acknowledged.
+            Err(err) => tracing::warn!(
+                "Skipping unreadable ingest journal line {} in {}: {err}",
+                line_no + 1,
+                path.display()
+            ),
+        }
+    }
+    Ok(writes)
+}
+
+fn append_journal(path: &Path, write: &BufferedWrite) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut line = serde_json::to_vec(write)?;
+    line.push(b'\n');
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("open {}", path.display()))?;
+    file.write_all(&line)?;
+    file.sync_data()?;
+    Ok(())
+}
+
+fn rewrite_journal(path: &Path, pending: &[BufferedWrite]) -> Result<()> {
+    if pending.is_empty() {
+        return match std::fs::remove_file(path) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        };
+    }
+    let tmp = path.with_extension("ndjson.tmp");
+    {
+        let mut file = std::fs::File::create(&tmp)?;
+        for write in pending {
+            serde_json::to_writer(&mut file, write)?;
+            file.write_all(b"\n")?;
+        }
+        file.sync_data()?;
+    }
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsed::ParsedConversation;
+
+    fn conversation(external_id: &str, content: &str) -> ParsedConversation {
+        serde_json::from_value(serde_json::json!({
+            "externalId": external_id,
+            "createdAt": 1_700_000_000_000_i64,
+            "messages": [{ "role": "user", "content": content }],
+        }))
+        .expect("parsed conversation")
+    }
+
+    fn message(content: &str) -> ParsedMessage {
+        serde_json::from_value(serde_json::json!({ "role": "assistant", "content": content }))
+            .expect("parsed message")
+    }
+
+    #[tokio::test]
+    async fn flush_commits_coalesced_writes_and_clears_journal() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let db = Arc::new(Database::open(&dir.path().join("hstry.db")).await?);
+        let journal = dir.path().join("ingest-buffer.ndjson");
+        let config = IngestBufferConfig::default();
+
+        let buffer = IngestBuffer::open(Arc::clone(&db), &config, Some(journal.clone()))?;
+        buffer
+            .push(BufferedWrite::Conversations {
+                source: "live".to_string(),
+                adapter: Some("octo".to_string()),
+                conversations: vec![conversation("s1", "hello")],
+            })
+            .await?;
+        for text in ["one", "two", "three"] {
+            buffer
+                .push(BufferedWrite::Messages {
+                    source: "live".to_string(),
+                    external_id: "s1".to_string(),
+                    start_idx: None,
+                    messages: vec![message(text)],
+                })
+                .await?;
+        }
+        assert_eq!(buffer.pending().await, 4);
+        assert_eq!(read_journal(&journal)?.len(), 4);
+
+        // A fresh buffer replays the journal, as after a crash.
+        let replayed = IngestBuffer::open(Arc::clone(&db), &config, Some(journal.clone()))?;
+        assert_eq!(replayed.pending().await, 4);
+
+        let outcome = replayed.flush().await?;
+        assert_eq!(outcome.writes, 4);
+        assert_eq!(outcome.orphaned, 0);
+        assert!(!journal.exists());
+
+        let conv_id = db
+            .get_conversation_id("live", "s1")
+            .await?
+            .expect("conversation written");
+        let messages = db.get_messages(conv_id).await?;
+        let contents: Vec<_> = messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, ["hello", "one", "two", "three"]);
+        assert_eq!(
+            db.get_source("live").await?.map(|s| s.adapter).as_deref(),
+            Some("octo")
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn failed_flush_requeues_only_uncommitted_writes() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let db = Arc::new(Database::open(&dir.path().join("hstry.db")).await?);
+        let journal = dir.path().join("ingest-buffer.ndjson");
+        let buffer = IngestBuffer::open(
+            Arc::clone(&db),
+            &IngestBufferConfig::default(),
+            Some(journal.clone()),
+        )?;
+        let append = |external_id: &str, text: &str| BufferedWrite::Messages {
+            source: "live".to_string(),
+            external_id: external_id.to_string(),
+            start_idx: None,
+            messages: vec![message(text)],
+        };
+        buffer
+            .push(BufferedWrite::Conversations {
+                source: "live".to_string(),
+                adapter: None,
+                conversations: vec![conversation("s1", "hello"), conversation("s2", "hi")],
+            })
+            .await?;
+        buffer.push(append("s1", "one")).await?;
+        buffer.push(append("s2", "boom")).await?;
+        buffer.push(append("s1", "two")).await?;
+
+        let mut tx = db.begin().await?;
+        sqlx::query(
+            "CREATE TRIGGER fail_boom BEFORE INSERT ON messages WHEN NEW.content = 'boom' \
+             BEGIN SELECT RAISE(ABORT, 'injected failure'); END",
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        assert!(buffer.flush().await.is_err());
+        assert_eq!(buffer.pending().await, 2);
+        assert_eq!(read_journal(&journal)?.len(), 2);
+
+        let mut tx = db.begin().await?;
+        sqlx::query("DROP TRIGGER fail_boom")
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        buffer.flush().await?;
+        assert_eq!(buffer.pending().await, 0);
+
+        for (external_id, expected) in [
+            ("s1", ["hello", "one", "two"].as_slice()),
+            ("s2", &["hi", "boom"]),
+        ] {
+            let conv_id = db
+                .get_conversation_id("live", external_id)
+                .await?
+                .expect("conversation written");
+            let messages = db.get_messages(conv_id).await?;
+            let contents: Vec<_> = messages.iter().map(|m| m.content.as_str()).collect();
+            assert_eq!(contents, expected);
+        }
+        Ok(())
+    }
+}
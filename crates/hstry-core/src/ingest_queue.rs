@@ -0,0 +1,211 @@
+//! Bounded queue between adapter parsing and database writes.
+//!
+//! Without a queue a sync alternates between reading a batch from the
+//! adapter and committing it, so the adapter sits idle for every
+//! transaction. [`ingest_queue`] hands the parsing side a sender for
+//! [`QueuedBatch`]es and returns an [`IngestWriter`] that drains them
+//! concurrently: each batch is committed with [`ingest_batch`] in its own
+//! transaction, writes that hit a locked database are retried with backoff,
+//! and a batch's cursor only counts once the batch committed. A sync that
+//! fails or is cancelled therefore resumes after the last committed batch.
+
+use std::time::Duration;
+
+use anyhow::Result;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::Database;
+use crate::config::IngestQueueConfig;
+use crate::ingest::{IngestOutcome, ingest_batch};
+use crate::parsed::ParsedConversation;
+
+/// Conversations parsed together, with the adapter cursor covering them.
+#[derive(Debug, Default)]
+pub struct QueuedBatch {
+    pub conversations: Vec<ParsedConversation>,
+    /// Becomes [`IngestTotals::cursor`] once the batch is committed.
+    pub cursor: Option<serde_json::Value>,
+}
+
+/// What an [`IngestWriter`] has committed so far.
+#[derive(Debug, Clone, Default)]
+pub struct IngestTotals {
+    /// Batches committed.
+    pub batches: usize,
+    /// Conversations written (new or updated).
+    pub conversations: usize,
+    /// Messages written.
+    pub messages: usize,
+    /// Conversations touched; pass to
+    /// [`Database::rebuild_conversation_summaries`] once the sync ends.
+    pub affected_conversation_ids: Vec<Uuid>,
+    /// Cursor of the last committed batch that carried one.
+    pub cursor: Option<serde_json::Value>,
+}
+
+/// Create a queue for one source with the database's
+/// `[storage.ingest_queue]` capacity. Dropping every sender ends the writer
+/// once the queued batches are written.
+pub fn ingest_queue(db: &Database) -> (mpsc::Sender<QueuedBatch>, IngestWriter) {
+    let config = db.ingest_queue_config().clone();
+    let (sender, receiver) = mpsc::channel(config.capacity.max(1));
+    (sender, IngestWriter { receiver, config })
+}
+
+/// Writing end of an [`ingest_queue`].
+pub struct IngestWriter {
+    receiver: mpsc::Receiver<QueuedBatch>,
+    config: IngestQueueConfig,
+}
+
+impl IngestWriter {
+    /// Commit queued batches for `source_id` until the senders are gone,
+    /// adding each to `totals` and calling `on_commit` after it. Waits as
+    /// nice mode asks between batches.
+    ///
+    /// `totals` is updated as batches land, so it holds what was committed
+    /// even when this returns an error or the future is dropped.
+    pub async fn run<F>(
+        mut self,
+        db: &Database,
+        source_id: &str,
+        totals: &mut IngestTotals,
+        mut on_commit: F,
+    ) -> Result<()>
+    where
+        F: FnMut(&IngestTotals),
+    {
+        while let Some(batch) = self.receiver.recv().await {
+            if batch.conversations.is_empty() {
+                if let Some(cursor) = batch.cursor {
+                    totals.cursor = Some(cursor);
+                }
+                continue;
+            }
+            let outcome =
+                write_with_retry(db, source_id, batch.conversations, &self.config).await?;
+            totals.batches += 1;
+            totals.conversations += outcome.conversations;
+            totals.messages += outcome.messages;
+            totals
+                .affected_conversation_ids
+                .extend(outcome.affected_conversation_ids);
+            if let Some(cursor) = batch.cursor {
+                totals.cursor = Some(cursor);
+            }
+            on_commit(totals);
+            db.pace_writes(outcome.bytes).await;
+        }
+        Ok(())
+    }
+}
+
+/// [`ingest_batch`], retried with doubling backoff while the database is
+/// locked by another writer. A failed attempt rolls back, so retrying
+/// writes the batch exactly once.
+pub async fn write_with_retry(
+    db: &Database,
+    source_id: &str,
+    conversations: Vec<ParsedConversation>,
+    config: &IngestQueueConfig,
+) -> Result<IngestOutcome> {
+    let mut backoff = Duration::from_millis(config.retry_backoff_ms);
+    for attempt in 1..=config.retries {
+        match ingest_batch(db, source_id, conversations.clone()).await {
+            Err(err) if is_locked(&err) => {
+                tracing::warn!(
+                    source = source_id,
+                    attempt,
+                    "database locked while writing a batch, retrying in {backoff:?}"
+                );
+                tokio::time::sleep(backoff).await;
+                backoff = backoff.saturating_mul(2);
+            }
+            result => return result,
+        }
+    }
+    ingest_batch(db, source_id, conversations).await
+}
+
+/// Whether `err` is SQLite reporting another connection holding the lock
+/// (`SQLITE_BUSY` or `SQLITE_LOCKED`, including extended codes).
+fn is_locked(err: &anyhow::Error) -> bool {
+    let sqlx_err =
+        err.downcast_ref::<sqlx::Error>()
+            .or_else(|| match err.downcast_ref::<crate::Error>() {
+                Some(crate::Error::Database(inner)) => Some(inner),
+                _ => None,
+            });
+    match sqlx_err {
+        Some(sqlx::Error::Database(db_err)) => db_err
+            .code()
+            .and_then(|code| code.parse::<i32>().ok())
+            .is_some_and(|code| matches!(code & 0xff, 5 | 6)),
+        Some(sqlx::Error::PoolTimedOut) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Source;
+
+    fn conversation(external_id: &str, content: &str) -> ParsedConversation {
+        serde_json::from_value(serde_json::json!({
+            "externalId": external_id,
+            "createdAt": 1_700_000_000_000_i64,
+            "messages": [{ "role": "user", "content": content }],
+        }))
+        .expect("parsed conversation")
+    }
+
+    #[tokio::test]
+    async fn writer_commits_batches_in_order_and_keeps_the_last_cursor() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let db = Database::open(&dir.path().join("hstry.db")).await?;
+        db.upsert_source(&Source {
+            id: "queued".to_string(),
+            adapter: "test".to_string(),
+            path: None,
+            last_sync_at: None,
+            config: serde_json::json!({}),
+        })
+        .await?;
+
+        let (sender, writer) = ingest_queue(&db);
+        let produce = async move {
+            for idx in 0..6 {
+                let batch = QueuedBatch {
+                    conversations: vec![conversation(&format!("c{idx}"), "hello")],
+                    cursor: (idx % 2 == 1).then(|| serde_json::json!({ "offset": idx })),
+                };
+                sender.send(batch).await.expect("writer running");
+            }
+            // A checkpoint without conversations still advances the cursor.
+            sender
+                .send(QueuedBatch {
+                    conversations: Vec::new(),
+                    cursor: Some(serde_json::json!({ "offset": 6 })),
+                })
+                .await
+                .expect("writer running");
+        };
+        let mut totals = IngestTotals::default();
+        let mut commits = Vec::new();
+        let write = writer.run(&db, "queued", &mut totals, |totals| {
+            commits.push(totals.conversations);
+        });
+        let ((), written) = tokio::join!(produce, write);
+        written?;
+
+        assert_eq!(commits, [1, 2, 3, 4, 5, 6]);
+        assert_eq!(totals.batches, 6);
+        assert_eq!(totals.messages, 6);
+        assert_eq!(totals.affected_conversation_ids.len(), 6);
+        assert_eq!(totals.cursor, Some(serde_json::json!({ "offset": 6 })));
+        assert!(db.get_conversation_id("queued", "c5").await?.is_some());
+        Ok(())
+    }
+}
@@ -0,0 +1,137 @@
+//! Quick labels on messages.
+//!
+//! A label is a short lowercase word kept in the message's metadata under
+//! `labels`. The TUI offers the [`QUICK_LABELS`] as one-key toggles; search
+//! narrows to labelled messages with `label:<name>` terms in the query.
+
+use serde_json::Value;
+
+/// A label the TUI can toggle with one key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuickLabel {
+    pub name: &'static str,
+    pub emoji: &'static str,
+    pub key: char,
+}
+
+pub const QUICK_LABELS: &[QuickLabel] = &[
+    QuickLabel {
+        name: "insight",
+        emoji: "💡",
+        key: 'i',
+    },
+    QuickLabel {
+        name: "bug",
+        emoji: "🐛",
+        key: 'b',
+    },
+    QuickLabel {
+        name: "keep",
+        emoji: "📌",
+        key: 'k',
+    },
+];
+
+/// Metadata key holding a message's labels.
+pub const METADATA_KEY: &str = "labels";
+
+/// Lowercased, trimmed label name.
+pub fn normalize(label: &str) -> String {
+    label.trim().to_lowercase()
+}
+
+/// Labels stored in a message's `metadata`, in the order they were added.
+pub fn labels(metadata: &Value) -> Vec<&str> {
+    metadata
+        .get(METADATA_KEY)
+        .and_then(Value::as_array)
+        .map(|labels| labels.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default()
+}
+
+/// Add `label` to `metadata`, or remove it when it is already there.
+/// Returns whether the label is set afterwards.
+pub fn toggle(metadata: &mut Value, label: &str) -> bool {
+    let label = normalize(label);
+    if !metadata.is_object() {
+        *metadata = Value::Object(serde_json::Map::new());
+    }
+    let Some(object) = metadata.as_object_mut() else {
+        return false;
+    };
+    let entry = object
+        .entry(METADATA_KEY)
+        .or_insert_with(|| Value::Array(Vec::new()));
+    if !entry.is_array() {
+        *entry = Value::Array(Vec::new());
+    }
+    let Some(labels) = entry.as_array_mut() else {
+        return false;
+    };
+    let before = labels.len();
+    labels.retain(|value| value.as_str() != Some(label.as_str()));
+    let set = labels.len() == before;
+    if set {
+        labels.push(Value::String(label));
+    }
+    if labels.is_empty() {
+        object.remove(METADATA_KEY);
+    }
+    set
+}
+
+/// `label` with its emoji in front when it is a quick label.
+pub fn display(label: &str) -> String {
+    match QUICK_LABELS.iter().find(|quick| quick.name == label) {
+        Some(quick) => format!("{} {label}", quick.emoji),
+        None => format!("#{label}"),
+    }
+}
+
+/// Split `label:<name>` terms out of a search query. Returns the rest of
+/// the query and the labels, normalized.
+pub fn split_query(query: &str) -> (String, Vec<String>) {
+    let mut rest = Vec::new();
+    let mut labels = Vec::new();
+    for term in query.split_whitespace() {
+        match term.strip_prefix("label:").map(normalize) {
+            Some(label) if !label.is_empty() => labels.push(label),
+            _ => rest.push(term),
+        }
+    }
+    (rest.join(" "), labels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toggling_adds_then_removes() {
+        let mut metadata = serde_json::json!({"cwd": "/tmp"});
+        assert!(toggle(&mut metadata, "Insight"));
+        assert!(toggle(&mut metadata, "bug"));
+        assert_eq!(labels(&metadata), ["insight", "bug"]);
+        assert!(!toggle(&mut metadata, "insight"));
+        assert!(!toggle(&mut metadata, "bug"));
+        assert_eq!(metadata, serde_json::json!({"cwd": "/tmp"}));
+
+        let mut null = Value::Null;
+        assert!(toggle(&mut null, "keep"));
+        assert_eq!(null, serde_json::json!({"labels": ["keep"]}));
+    }
+
+    #[test]
+    fn label_terms_are_split_from_queries() {
+        assert_eq!(
+            split_query("wal label:Insight mode label:"),
+            ("wal mode label:".to_string(), vec!["insight".to_string()])
+        );
+        assert_eq!(
+            split_query("label:bug"),
+            (String::new(), vec!["bug".to_string()])
+        );
+        assert_eq!(display("bug"), "🐛 bug");
+        assert_eq!(display("todo"), "#todo");
+    }
+}
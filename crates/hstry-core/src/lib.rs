@@ -4,23 +4,59 @@
 //! managing chat history from multiple AI sources (ChatGPT, Claude, Gemini,
 //! OpenCode, Cursor, etc.)
 
+pub mod agent_search;
+pub mod api_token;
+pub mod ask;
+pub mod branches;
+pub mod bundle;
+pub mod cancel;
 pub mod config;
+pub mod cost;
+pub mod daily_notes;
+pub mod dates;
 pub mod db;
+pub mod decisions;
 pub mod error;
+pub mod export;
+pub mod graph;
+pub mod html_export;
+pub mod i18n;
 pub mod ingest;
+pub mod ingest_buffer;
+pub mod ingest_queue;
+pub mod labels;
+pub mod links;
+pub mod llm;
 pub mod models;
+pub mod near_duplicates;
+pub mod pack;
 pub mod parsed;
 pub mod parts;
 pub mod paths;
 pub mod peek;
 pub mod readable_id;
 pub mod remote;
+pub mod rerank;
+pub mod revisions;
+pub mod saved_search;
 pub mod schema;
+pub mod search_groups;
+pub mod segment;
+pub mod sensitive;
 pub mod service;
+pub mod share;
+pub mod source_files;
+pub mod source_filter;
 pub mod source_registry;
+pub mod throttle;
+pub mod titles;
+pub mod tokens;
+pub mod tool_calls;
+pub mod workspace_paths;
+pub mod workspaces;
 
 pub use config::Config;
-pub use db::Database;
+pub use db::{Database, DatabaseOptions};
 pub use error::Error;
 pub use error::Result;
 
@@ -80,6 +116,11 @@ fn utf8_prefix(s: &str, max_bytes: usize) -> &str {
     &s[..end]
 }
 
+/// Returns the environment variable prefix for this application.
+pub fn env_prefix() -> String {
+    "HSTRY".to_string()
+}
+
 #[cfg(test)]
 mod lib_tests {
     use super::*;
@@ -88,7 +129,7 @@ mod lib_tests {
     fn utf8_prefix_handles_multibyte_at_boundary() {
         // 3-byte char '─' (U+2500) repeated; choose a length so 4096 lands
         // mid-character.
-        let s: String = std::iter::repeat('─').take(2000).collect();
+        let s: String = "─".repeat(2000);
         // 2000 * 3 = 6000 bytes. max_bytes 4096 lands inside a glyph.
         let p = utf8_prefix(&s, 4096);
         assert!(p.len() <= 4096);
@@ -102,8 +143,3 @@ mod lib_tests {
         assert_eq!(utf8_prefix("hello", 4096), "hello");
     }
 }
-
-/// Returns the environment variable prefix for this application.
-pub fn env_prefix() -> String {
-    "HSTRY".to_string()
-}
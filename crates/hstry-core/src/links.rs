@@ -0,0 +1,106 @@
+//! Copies of one conversation imported through different sources
+//! (`hstry link detect`).
+//!
+//! A session can reach the database more than once, for example from a
+//! claude-code log and again from a claude.ai export. Rather than deleting
+//! either copy, [`detect`] records them as related: first by a shared
+//! external or platform id, then by message text, using the same MinHash
+//! signatures as `hstry dedup --strategy fuzzy`. `hstry show` and the TUI
+//! list the other copies.
+
+use std::collections::HashSet;
+
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::cancel::CancelToken;
+use crate::db::{Database, ListConversationsOptions};
+use crate::error::Result;
+use crate::models::Conversation;
+use crate::near_duplicates::{self, Signature};
+
+/// Default similarity for linking conversations by content.
+pub const DEFAULT_THRESHOLD: f32 = 0.8;
+
+/// Shorter ids, like "1" or "main", match by accident.
+const MIN_ID_LEN: usize = 8;
+
+/// Outcome of [`detect`].
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DetectStats {
+    pub scanned: u64,
+    /// Pairs found by each method; a pair counts once, for the first.
+    pub external_id: u64,
+    pub platform_id: u64,
+    pub content: u64,
+    /// Pairs not recorded before (or that would be, in a dry run).
+    pub added: u64,
+}
+
+/// Find conversations from different sources that are copies of each
+/// other and record them. Pairs whose messages are at least `threshold`
+/// similar are linked by content. `dry_run` finds pairs without storing
+/// them. Stops between conversations when `cancel` fires.
+pub async fn detect(
+    db: &Database,
+    threshold: f32,
+    dry_run: bool,
+    cancel: &CancelToken,
+) -> Result<DetectStats> {
+    let mut stats = DetectStats::default();
+    let mut links: Vec<(Uuid, Uuid, &str, f32)> = Vec::new();
+    let mut linked = HashSet::new();
+
+    for (a, b, reason) in db.conversations_sharing_ids(MIN_ID_LEN).await? {
+        if !linked.insert((a.min(b), a.max(b))) {
+            continue;
+        }
+        match reason {
+            "external_id" => stats.external_id += 1,
+            _ => stats.platform_id += 1,
+        }
+        links.push((a, b, reason, 1.0));
+    }
+
+    let conversations = db
+        .list_conversations(ListConversationsOptions::default())
+        .await?;
+    let mut signatures = Vec::with_capacity(conversations.len());
+    for conversation in &conversations {
+        cancel.check()?;
+        let messages = db.get_messages(conversation.id).await?;
+        signatures.push(Signature::of_messages(&messages));
+        stats.scanned += 1;
+    }
+    for (a, b, similarity) in content_pairs(&conversations, &signatures, threshold) {
+        if linked.insert((a.min(b), a.max(b))) {
+            stats.content += 1;
+            links.push((a, b, "content", similarity));
+        }
+    }
+
+    stats.added = if dry_run {
+        let mut added = 0;
+        for &(a, b, _, _) in &links {
+            let known = db.related_conversations(a).await?;
+            added += u64::from(!known.iter().any(|related| related.id == b));
+        }
+        added
+    } else {
+        db.add_related_conversations(&links).await?
+    };
+    Ok(stats)
+}
+
+/// Pairs of conversations from different sources with similar messages.
+fn content_pairs(
+    conversations: &[Conversation],
+    signatures: &[Option<Signature>],
+    threshold: f32,
+) -> Vec<(Uuid, Uuid, f32)> {
+    near_duplicates::similar_pairs(signatures, threshold)
+        .into_iter()
+        .filter(|&(a, b, _)| conversations[a].source_id != conversations[b].source_id)
+        .map(|(a, b, similarity)| (conversations[a].id, conversations[b].id, similarity))
+        .collect()
+}
@@ -0,0 +1,510 @@
+//! Shared client for the model endpoints used by re-ranking and `hstry ask`.
+//!
+//! [`Endpoint::for_feature`] picks the `[llm]` provider and model configured
+//! for a feature, unless the feature's own section names an endpoint
+//! directly. Every endpoint spaces its requests to the provider's
+//! `requests_per_minute`, retries rate limits and server errors, and keeps a
+//! [`LlmUsage`] record per request; callers hand those to
+//! [`Database::record_llm_usage`](crate::db::Database::record_llm_usage) so
+//! `hstry stats` can report tokens and cost per feature.
+
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex, PoisonError};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+
+use crate::config::{LlmConfig, LlmProviderKind};
+use crate::error::{Error, Result};
+
+/// Longest wait between two attempts of a request.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Next free request slot per rate-limited provider, shared by every
+/// endpoint in the process.
+static NEXT_SLOT: LazyLock<Mutex<HashMap<String, Instant>>> = LazyLock::new(Default::default);
+
+/// Token usage and cost of one model request.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LlmUsage {
+    pub at: DateTime<Utc>,
+    pub feature: String,
+    pub provider: String,
+    pub model: Option<String>,
+    pub tokens_in: i64,
+    pub tokens_out: i64,
+    pub cost_usd: f64,
+}
+
+/// An endpoint set in a feature's own config section, e.g. `[ask]`.
+#[derive(Debug, Clone, Copy)]
+pub struct SectionEndpoint<'a> {
+    /// Section name for errors, e.g. `[ask]`.
+    pub section: &'a str,
+    pub url: Option<&'a str>,
+    pub model: Option<&'a str>,
+    pub api_key_env: Option<&'a str>,
+    pub timeout_secs: u64,
+}
+
+/// A model endpoint: URL, API kind, optional model name and bearer token.
+pub struct Endpoint {
+    url: String,
+    kind: LlmProviderKind,
+    model: Option<String>,
+    api_key: Option<String>,
+    client: reqwest::Client,
+    feature: String,
+    /// Provider name, or the URL of a section endpoint.
+    provider: String,
+    requests_per_minute: u32,
+    max_retries: u32,
+    /// USD per million input and output tokens.
+    pricing: (f64, f64),
+    usage: Mutex<Vec<LlmUsage>>,
+}
+
+impl Endpoint {
+    /// Endpoint for `feature`: `own` when its section sets a URL, otherwise
+    /// the `[llm]` provider chosen for the feature.
+    pub fn for_feature(llm: &LlmConfig, feature: &str, own: SectionEndpoint<'_>) -> Result<Self> {
+        if own.url.is_some_and(|url| !url.is_empty()) {
+            return Self::direct(llm, feature, own);
+        }
+        let choice = llm.features.get(feature);
+        let name = choice
+            .and_then(|choice| choice.provider.as_deref())
+            .or(llm.default_provider.as_deref())
+            .ok_or_else(|| {
+                Error::Config(format!(
+                    "{} endpoint is not set and [llm] has no provider for {feature}",
+                    own.section
+                ))
+            })?;
+        let provider = llm
+            .providers
+            .get(name)
+            .ok_or_else(|| Error::Config(format!("[llm.providers.{name}] is not defined")))?;
+        if provider.url.is_empty() {
+            return Err(Error::Config(format!("[llm.providers.{name}] has no url")));
+        }
+        let model = choice
+            .and_then(|choice| choice.model.as_deref())
+            .or(own.model)
+            .or(provider.model.as_deref());
+        Ok(Self {
+            url: chat_url(provider.kind, &provider.url),
+            kind: provider.kind,
+            model: model.map(str::to_string),
+            api_key: api_key(
+                &format!("[llm.providers.{name}]"),
+                provider.api_key_env.as_deref(),
+            )?,
+            client: client(provider.timeout_secs)?,
+            feature: feature.to_string(),
+            provider: name.to_string(),
+            requests_per_minute: provider.requests_per_minute,
+            max_retries: llm.max_retries,
+            pricing: (provider.input_cost_per_mtok, provider.output_cost_per_mtok),
+            usage: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Endpoint at the URL set in `own`, taking only retries from `[llm]`.
+    pub fn direct(llm: &LlmConfig, feature: &str, own: SectionEndpoint<'_>) -> Result<Self> {
+        let url = own
+            .url
+            .filter(|url| !url.is_empty())
+            .ok_or_else(|| Error::Config(format!("{} endpoint is not set", own.section)))?;
+        Ok(Self {
+            url: url.to_string(),
+            kind: LlmProviderKind::Openai,
+            model: own.model.map(str::to_string),
+            api_key: api_key(own.section, own.api_key_env)?,
+            client: client(own.timeout_secs)?,
+            feature: feature.to_string(),
+            provider: url.to_string(),
+            requests_per_minute: 0,
+            max_retries: llm.max_retries,
+            pricing: (0.0, 0.0),
+            usage: Mutex::new(Vec::new()),
+        })
+    }
+
+    pub fn model(&self) -> Option<&str> {
+        self.model.as_deref()
+    }
+
+    /// Usage records of the requests made since the last call.
+    pub fn take_usage(&self) -> Vec<LlmUsage> {
+        std::mem::take(&mut *self.usage.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// POST `body` (with `model` added when configured) and return the JSON
+    /// response. Rate limits, server errors and connection failures are
+    /// retried up to `[llm].max_retries` times.
+    pub async fn post(&self, mut body: serde_json::Value) -> Result<serde_json::Value> {
+        if let Some(model) = &self.model {
+            body["model"] = model.clone().into();
+        }
+        let mut attempt = 0;
+        loop {
+            self.wait_for_slot().await;
+            let mut request = self.client.post(&self.url).json(&body);
+            if let Some(key) = &self.api_key {
+                request = request.bearer_auth(key);
+            }
+            let response = match request.send().await {
+                Ok(response) => response,
+                Err(e) if attempt < self.max_retries && (e.is_timeout() || e.is_connect()) => {
+                    tokio::time::sleep(backoff(attempt, None)).await;
+                    attempt += 1;
+                    continue;
+                }
+                Err(e) => {
+                    return Err(Error::Remote(format!(
+                        "request to {} failed: {e}",
+                        self.url
+                    )));
+                }
+            };
+            let status = response.status();
+            if status.is_success() {
+                let value: serde_json::Value = response.json().await.map_err(|e| {
+                    Error::Remote(format!("invalid response from {}: {e}", self.url))
+                })?;
+                self.record(&value);
+                return Ok(value);
+            }
+            if attempt < self.max_retries
+                && (status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error())
+            {
+                let retry_after = response
+                    .headers()
+                    .get(reqwest::header::RETRY_AFTER)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.trim().parse::<u64>().ok());
+                tokio::time::sleep(backoff(attempt, retry_after)).await;
+                attempt += 1;
+                continue;
+            }
+            let text = response.text().await.unwrap_or_default();
+            return Err(Error::Remote(format!(
+                "{} answered {status}: {}",
+                self.url,
+                text.trim()
+            )));
+        }
+    }
+
+    /// Send one system and one user message to the chat endpoint and return
+    /// the reply text.
+    pub async fn chat(&self, system: &str, user: &str) -> Result<String> {
+        let messages = serde_json::json!([
+            {"role": "system", "content": system},
+            {"role": "user", "content": user},
+        ]);
+        let body = match self.kind {
+            LlmProviderKind::Openai => serde_json::json!({
+                "messages": messages,
+                "temperature": 0,
+            }),
+            LlmProviderKind::Ollama => serde_json::json!({
+                "messages": messages,
+                "stream": false,
+                "options": {"temperature": 0},
+            }),
+        };
+        let response = self.post(body).await?;
+        let reply = match self.kind {
+            LlmProviderKind::Openai => {
+                let response: ChatResponse = serde_json::from_value(response)?;
+                response
+                    .choices
+                    .into_iter()
+                    .next()
+                    .map(|choice| choice.message.content)
+            }
+            LlmProviderKind::Ollama => {
+                let response: OllamaResponse = serde_json::from_value(response)?;
+                response.message.map(|message| message.content)
+            }
+        };
+        reply.ok_or_else(|| Error::Remote(format!("{} returned no reply", self.url)))
+    }
+
+    /// Wait for this endpoint's next request slot under its provider's
+    /// `requests_per_minute`.
+    async fn wait_for_slot(&self) {
+        if self.requests_per_minute == 0 {
+            return;
+        }
+        let interval = Duration::from_secs(60) / self.requests_per_minute;
+        let slot = {
+            let mut slots = NEXT_SLOT.lock().unwrap_or_else(PoisonError::into_inner);
+            let now = Instant::now();
+            let slot = slots
+                .get(&self.provider)
+                .copied()
+                .filter(|slot| *slot > now)
+                .unwrap_or(now);
+            slots.insert(self.provider.clone(), slot + interval);
+            slot
+        };
+        tokio::time::sleep_until(slot).await;
+    }
+
+    fn record(&self, response: &serde_json::Value) {
+        let (tokens_in, tokens_out, reported_cost) = parse_usage(response);
+        let cost_usd = reported_cost.unwrap_or_else(|| {
+            (tokens_in as f64 * self.pricing.0 + tokens_out as f64 * self.pricing.1) / 1_000_000.0
+        });
+        self.usage
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(LlmUsage {
+                at: Utc::now(),
+                feature: self.feature.clone(),
+                provider: self.provider.clone(),
+                model: self.model.clone(),
+                tokens_in,
+                tokens_out,
+                cost_usd,
+            });
+    }
+}
+
+fn api_key(section: &str, api_key_env: Option<&str>) -> Result<Option<String>> {
+    api_key_env
+        .map(|var| {
+            std::env::var(var)
+                .map_err(|_| Error::Config(format!("{section} api_key_env {var} is not set")))
+        })
+        .transpose()
+}
+
+fn client(timeout_secs: u64) -> Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(timeout_secs.max(1)))
+        .build()
+        .map_err(|e| Error::Remote(format!("failed to build HTTP client: {e}")))
+}
+
+/// Chat URL of a provider's base URL.
+fn chat_url(kind: LlmProviderKind, base: &str) -> String {
+    let base = base.trim_end_matches('/');
+    let path = match kind {
+        LlmProviderKind::Openai => "/chat/completions",
+        LlmProviderKind::Ollama => "/api/chat",
+    };
+    if base.ends_with(path) {
+        base.to_string()
+    } else {
+        format!("{base}{path}")
+    }
+}
+
+/// Wait before retry `attempt` (0-based): the server's `Retry-After`
+/// seconds, or exponential backoff from half a second.
+fn backoff(attempt: u32, retry_after: Option<u64>) -> Duration {
+    retry_after
+        .map(Duration::from_secs)
+        .unwrap_or_else(|| Duration::from_millis(500) * 2u32.saturating_pow(attempt))
+        .min(MAX_BACKOFF)
+}
+
+/// Input and output tokens of a response, and its cost when the provider
+/// reports one. Understands OpenAI-style `usage`, Anthropic-style
+/// `input_tokens`/`output_tokens`, Cohere's `meta.billed_units` and
+/// Ollama's eval counts.
+fn parse_usage(response: &serde_json::Value) -> (i64, i64, Option<f64>) {
+    let int = |value: &serde_json::Value, keys: &[&str]| {
+        keys.iter()
+            .find_map(|key| value.get(key).and_then(serde_json::Value::as_i64))
+            .unwrap_or(0)
+    };
+    if let Some(usage) = response.get("usage").filter(|usage| usage.is_object()) {
+        return (
+            int(usage, &["prompt_tokens", "input_tokens", "total_tokens"]),
+            int(usage, &["completion_tokens", "output_tokens"]),
+            usage.get("cost").and_then(serde_json::Value::as_f64),
+        );
+    }
+    if let Some(billed) = response.pointer("/meta/billed_units") {
+        return (int(billed, &["input_tokens", "search_units"]), 0, None);
+    }
+    (
+        int(response, &["prompt_eval_count"]),
+        int(response, &["eval_count"]),
+        None,
+    )
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    choices: Vec<ChatChoice>,
+}
+
+#[derive(Deserialize)]
+struct ChatChoice {
+    message: ChatMessage,
+}
+
+#[derive(Deserialize)]
+struct ChatMessage {
+    #[serde(default)]
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct OllamaResponse {
+    message: Option<ChatMessage>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{LlmFeatureConfig, LlmProviderConfig};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn own(url: Option<&str>) -> SectionEndpoint<'_> {
+        SectionEndpoint {
+            section: "[ask]",
+            url,
+            model: Some("section-model"),
+            api_key_env: None,
+            timeout_secs: 5,
+        }
+    }
+
+    fn llm(url: &str, kind: LlmProviderKind) -> LlmConfig {
+        let mut llm = LlmConfig {
+            default_provider: Some("local".to_string()),
+            max_retries: 2,
+            ..Default::default()
+        };
+        llm.providers.insert(
+            "local".to_string(),
+            LlmProviderConfig {
+                kind,
+                url: url.to_string(),
+                model: Some("provider-model".to_string()),
+                input_cost_per_mtok: 1.0,
+                output_cost_per_mtok: 4.0,
+                ..Default::default()
+            },
+        );
+        llm
+    }
+
+    #[test]
+    fn features_resolve_provider_and_model() {
+        let mut config = llm("http://localhost:11434/", LlmProviderKind::Ollama);
+        let endpoint = Endpoint::for_feature(&config, "ask", own(None)).unwrap();
+        assert_eq!(endpoint.url, "http://localhost:11434/api/chat");
+        assert_eq!(endpoint.model(), Some("section-model"));
+
+        config.features.insert(
+            "ask".to_string(),
+            LlmFeatureConfig {
+                provider: None,
+                model: Some("feature-model".to_string()),
+            },
+        );
+        let endpoint = Endpoint::for_feature(&config, "ask", own(None)).unwrap();
+        assert_eq!(endpoint.model(), Some("feature-model"));
+
+        let endpoint =
+            Endpoint::for_feature(&config, "ask", own(Some("http://h/v1/chat/completions")))
+                .unwrap();
+        assert_eq!(endpoint.provider, "http://h/v1/chat/completions");
+        assert_eq!(endpoint.model(), Some("section-model"));
+
+        config.default_provider = None;
+        let err = Endpoint::for_feature(&config, "rerank", own(None)).err();
+        assert!(err.unwrap().to_string().contains("no provider for rerank"));
+    }
+
+    #[test]
+    fn chat_urls_and_backoff() {
+        assert_eq!(
+            chat_url(LlmProviderKind::Openai, "https://api.openai.com/v1"),
+            "https://api.openai.com/v1/chat/completions"
+        );
+        assert_eq!(
+            chat_url(LlmProviderKind::Openai, "http://h/v1/chat/completions"),
+            "http://h/v1/chat/completions"
+        );
+        assert_eq!(backoff(0, None), Duration::from_millis(500));
+        assert_eq!(backoff(2, None), Duration::from_secs(2));
+        assert_eq!(backoff(1, Some(7)), Duration::from_secs(7));
+        assert_eq!(backoff(30, None), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn parses_usage_shapes() {
+        let openai = serde_json::json!({"usage": {"prompt_tokens": 10, "completion_tokens": 3}});
+        assert_eq!(parse_usage(&openai), (10, 3, None));
+        let openrouter =
+            serde_json::json!({"usage": {"prompt_tokens": 1, "completion_tokens": 2, "cost": 0.5}});
+        assert_eq!(parse_usage(&openrouter), (1, 2, Some(0.5)));
+        let ollama = serde_json::json!({"prompt_eval_count": 7, "eval_count": 9});
+        assert_eq!(parse_usage(&ollama), (7, 9, None));
+        let cohere = serde_json::json!({"meta": {"billed_units": {"search_units": 1}}});
+        assert_eq!(parse_usage(&cohere), (1, 0, None));
+    }
+
+    /// Serve `responses` (status, body) in order, one per connection.
+    async fn serve(responses: Vec<(u16, &'static str)>) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            for (status, body) in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = vec![0; 8192];
+                let _ = socket.read(&mut buf).await;
+                let reply = format!(
+                    "HTTP/1.1 {status} X\r\nContent-Type: application/json\r\nRetry-After: 0\r\n\
+                     Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = socket.write_all(reply.as_bytes()).await;
+            }
+        });
+        format!("http://{addr}")
+    }
+
+    #[tokio::test]
+    async fn retries_rate_limits_and_records_usage() {
+        let url = serve(vec![
+            (429, "{}"),
+            (
+                200,
+                r#"{"choices":[{"message":{"content":"hi"}}],"usage":{"prompt_tokens":1000,"completion_tokens":500}}"#,
+            ),
+        ])
+        .await;
+        let endpoint =
+            Endpoint::for_feature(&llm(&url, LlmProviderKind::Openai), "ask", own(None)).unwrap();
+        assert_eq!(endpoint.chat("system", "user").await.unwrap(), "hi");
+
+        let usage = endpoint.take_usage();
+        assert_eq!(usage.len(), 1);
+        assert_eq!((usage[0].tokens_in, usage[0].tokens_out), (1000, 500));
+        assert!((usage[0].cost_usd - 0.003).abs() < 1e-12);
+        assert_eq!(usage[0].feature, "ask");
+        assert!(endpoint.take_usage().is_empty());
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_retries() {
+        let url = serve(vec![(503, "{}"), (503, "{}"), (503, "down")]).await;
+        let endpoint =
+            Endpoint::for_feature(&llm(&url, LlmProviderKind::Openai), "ask", own(None)).unwrap();
+        let err = endpoint.chat("system", "user").await.unwrap_err();
+        assert!(err.to_string().contains("503"));
+        assert!(endpoint.take_usage().is_empty());
+    }
+}
@@ -86,6 +86,10 @@ pub struct Message {
     /// Allows frontend to correlate provisional messages with persisted versions.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub client_id: Option<String>,
+    /// Message this one continues from when the conversation branches
+    /// (regenerations, edited prompts). `None` in linear conversations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_message_id: Option<Uuid>,
 }
 
 /// Message roles across different sources.
@@ -141,10 +145,33 @@ pub struct ToolCall {
 #[serde(rename_all = "lowercase")]
 pub enum ToolStatus {
     Pending,
+    Running,
     Success,
     Error,
 }
 
+impl ToolStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Running => "running",
+            Self::Success => "success",
+            Self::Error => "error",
+        }
+    }
+
+    /// Parse a stored status; unknown values yield `None`.
+    pub fn from_db(s: &str) -> Option<Self> {
+        match s {
+            "pending" => Some(Self::Pending),
+            "running" => Some(Self::Running),
+            "success" => Some(Self::Success),
+            "error" => Some(Self::Error),
+            _ => None,
+        }
+    }
+}
+
 /// An attachment to a message (file, image, code block).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Attachment {
@@ -231,6 +258,9 @@ pub struct SearchHit {
     pub readable_id: Option<String>,
     pub title: Option<String>,
     pub workspace: Option<String>,
+    /// Display name given to the workspace with `hstry workspace rename`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workspace_name: Option<String>,
     pub source_adapter: String,
     pub source_path: Option<String>,
     #[serde(default)]
@@ -238,6 +268,21 @@ pub struct SearchHit {
     /// Number of occurrences in the original result set (for compact mode)
     #[serde(default)]
     pub occurrences: Option<i32>,
+    /// The hit's conversation, when the search was expanded with it
+    /// (`--expand conversation`, `?expand=conversation`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conversation: Option<Conversation>,
+    /// Messages around the hit, when the search asked for context
+    /// (`--context N`, `?context=N`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<MessageContext>,
+}
+
+/// Messages just before and after a message, both in `idx` order.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MessageContext {
+    pub before: Vec<Message>,
+    pub after: Vec<Message>,
 }
 
 #[cfg(test)]
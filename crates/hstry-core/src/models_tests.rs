@@ -265,6 +265,7 @@ mod message_tests {
             provider: None,
             harness: None,
             client_id: None,
+            parent_message_id: None,
         };
 
         let json = serde_json::to_string(&msg).expect("serialize");
@@ -304,6 +305,7 @@ mod message_tests {
             provider: Some("anthropic".to_string()),
             harness: Some("pi".to_string()),
             client_id: None,
+            parent_message_id: None,
         };
 
         let json = serde_json::to_string(&msg).expect("serialize");
@@ -358,10 +360,13 @@ mod search_hit_tests {
             readable_id: Some("calm-owls".to_string()),
             title: Some("Q&A Session".to_string()),
             workspace: Some("/project".to_string()),
+            workspace_name: None,
             source_adapter: "opencode".to_string(),
             source_path: Some("/home/user/.opencode".to_string()),
             host: None,
             occurrences: None,
+            conversation: None,
+            context: None,
         };
 
         let json = serde_json::to_string(&hit).expect("serialize");
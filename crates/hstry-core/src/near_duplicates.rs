@@ -0,0 +1,177 @@
+//! Near-duplicate conversations (`hstry dedup --strategy fuzzy`).
+//!
+//! Each conversation gets a MinHash [`Signature`] of its three-word
+//! shingles; the share of matching values estimates the Jaccard similarity
+//! of two texts. Candidate pairs come from locality-sensitive hashing over
+//! bands of the signature, so conversations are never compared one by one.
+//! Bands make pairs well below ~0.6 similarity unlikely to be found, which
+//! is fine for spotting duplicates.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+use crate::models::Message;
+use crate::sensitive::LOCKED_CONTENT;
+
+/// MinHash values per signature.
+const SIGNATURE_LEN: usize = 64;
+
+/// Signature values hashed together per band; 16 bands of 4.
+const BAND_ROWS: usize = 4;
+
+/// Words per shingle.
+const SHINGLE_WORDS: usize = 3;
+
+/// Texts with fewer distinct shingles are too short to compare.
+const MIN_SHINGLES: usize = 5;
+
+/// MinHash signature of a text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature([u64; SIGNATURE_LEN]);
+
+impl Signature {
+    /// Signature of `text`, or `None` when it has too few words to compare.
+    pub fn new(text: &str) -> Option<Self> {
+        let words: Vec<String> = text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+        let shingles: HashSet<u64> = words.windows(SHINGLE_WORDS).map(hash).collect();
+        if shingles.len() < MIN_SHINGLES {
+            return None;
+        }
+        let mut mins = [u64::MAX; SIGNATURE_LEN];
+        for shingle in shingles {
+            for (seed, min) in (1u64..).zip(mins.iter_mut()) {
+                let value = mix(shingle.wrapping_add(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15)));
+                *min = (*min).min(value);
+            }
+        }
+        Some(Self(mins))
+    }
+
+    /// Signature of a conversation's messages. Sealed messages all read the
+    /// same and would make conversations look alike, so they are left out.
+    pub fn of_messages(messages: &[Message]) -> Option<Self> {
+        let text: Vec<&str> = messages
+            .iter()
+            .map(|m| m.content.as_str())
+            .filter(|content| *content != LOCKED_CONTENT)
+            .collect();
+        Self::new(&text.join("\n"))
+    }
+
+    /// Estimated Jaccard similarity of the two texts' shingles, 0 to 1.
+    pub fn similarity(&self, other: &Self) -> f32 {
+        let equal = self.0.iter().zip(&other.0).filter(|(a, b)| a == b).count();
+        equal as f32 / SIGNATURE_LEN as f32
+    }
+}
+
+fn hash<T: Hash + ?Sized>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The splitmix64 finalizer, one hash function per seed.
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// Pairs `(a, b, similarity)` of `signatures` with `a < b` and a
+/// similarity of at least `threshold`, most similar first. Texts without a
+/// signature match nothing.
+pub fn similar_pairs(signatures: &[Option<Signature>], threshold: f32) -> Vec<(usize, usize, f32)> {
+    let mut buckets: HashMap<(usize, u64), Vec<usize>> = HashMap::new();
+    for (i, signature) in signatures.iter().enumerate() {
+        let Some(signature) = signature else {
+            continue;
+        };
+        for (band, rows) in signature.0.chunks(BAND_ROWS).enumerate() {
+            buckets.entry((band, hash(rows))).or_default().push(i);
+        }
+    }
+
+    let mut seen = HashSet::new();
+    let mut pairs = Vec::new();
+    for members in buckets.values() {
+        for (n, &a) in members.iter().enumerate() {
+            for &b in &members[n + 1..] {
+                if !seen.insert((a, b)) {
+                    continue;
+                }
+                if let (Some(sa), Some(sb)) = (&signatures[a], &signatures[b]) {
+                    let similarity = sa.similarity(sb);
+                    if similarity >= threshold {
+                        pairs.push((a, b, similarity));
+                    }
+                }
+            }
+        }
+    }
+    pairs.sort_by(|x, y| y.2.total_cmp(&x.2).then((x.0, x.1).cmp(&(y.0, y.1))));
+    pairs
+}
+
+/// Groups of `0..count` joined by `pairs`, each in ascending order.
+/// Items paired with nothing form groups of one.
+pub fn clusters(count: usize, pairs: impl IntoIterator<Item = (usize, usize)>) -> Vec<Vec<usize>> {
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    let mut parent: Vec<usize> = (0..count).collect();
+    for (a, b) in pairs {
+        let (ra, rb) = (root(&mut parent, a), root(&mut parent, b));
+        parent[ra.max(rb)] = ra.min(rb);
+    }
+    let mut groups: Vec<Vec<usize>> = vec![Vec::new(); count];
+    for i in 0..count {
+        let group = root(&mut parent, i);
+        groups[group].push(i);
+    }
+    groups.retain(|group| !group.is_empty());
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn near_identical_texts_pair_up_and_short_ones_are_skipped() {
+        let base = "How do I parse a JSON file in Rust with serde and handle missing \
+                    fields gracefully? Use serde_json::from_reader with a BufReader, \
+                    derive Deserialize and mark optional fields with serde default.";
+        let edited = format!("{base} Thanks, that worked.");
+        let other = "Write a haiku about autumn leaves falling on a quiet mountain lake \
+                     while the sun sets behind the pine trees.";
+        let signatures = vec![
+            Signature::new(base),
+            Signature::new(other),
+            Signature::new(&edited),
+            Signature::new("hi there"),
+        ];
+        assert!(signatures[3].is_none());
+
+        let pairs = similar_pairs(&signatures, 0.7);
+        assert_eq!(pairs.len(), 1);
+        let (a, b, similarity) = pairs[0];
+        assert_eq!((a, b), (0, 2));
+        assert!(similarity >= 0.7);
+        assert_eq!(similar_pairs(&signatures[..2], 0.1), Vec::new());
+        assert_eq!(
+            clusters(5, [(3, 1), (1, 4)]),
+            vec![vec![0], vec![1, 3, 4], vec![2]]
+        );
+    }
+}
@@ -0,0 +1,442 @@
+//! Workspace knowledge packs (`hstry pack`).
+//!
+//! A pack is one markdown file (by default `AGENTS-HISTORY.md`) that gives a
+//! coding agent the history of a workspace: decisions made along the way
+//! (read from the [decision log](crate::decisions)),
+//! the most substantial conversations with their outcome and final code, and
+//! a one-line summary of every conversation. It starts with a [`PackHeader`]
+//! comment so `hstry pack` can tell whether it is still up to date.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::agent_search::{is_system_context, is_tool_dump};
+use crate::db::ConversationSummary;
+use crate::decisions::Decision;
+use crate::models::{Message, MessageRole};
+
+const HEADER_PREFIX: &str = "<!-- hstry-pack ";
+const HEADER_SUFFIX: &str = " -->";
+
+/// Size limits for a pack.
+#[derive(Debug, Clone)]
+pub struct PackConfig {
+    /// Conversations written out in full under "Top conversations".
+    pub top_conversations: usize,
+    pub max_decisions: usize,
+    /// Characters per entry in the conversation index.
+    pub summary_chars: usize,
+    /// Characters of the request and outcome of a top conversation.
+    pub excerpt_chars: usize,
+    pub snippets_per_conversation: usize,
+    /// Longer code blocks are left out.
+    pub max_snippet_lines: usize,
+}
+
+impl Default for PackConfig {
+    fn default() -> Self {
+        Self {
+            top_conversations: 10,
+            max_decisions: 30,
+            summary_chars: 160,
+            excerpt_chars: 600,
+            snippets_per_conversation: 2,
+            max_snippet_lines: 40,
+        }
+    }
+}
+
+/// First line of a pack, recording what it was built from.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PackHeader {
+    pub workspace: String,
+    pub generated_at: DateTime<Utc>,
+    pub conversations: usize,
+}
+
+impl PackHeader {
+    /// Read the header from an existing pack.
+    pub fn parse(pack: &str) -> Option<Self> {
+        let line = pack.lines().next()?;
+        let json = line
+            .strip_prefix(HEADER_PREFIX)?
+            .strip_suffix(HEADER_SUFFIX)?;
+        serde_json::from_str(json).ok()
+    }
+
+    fn render(&self) -> String {
+        let json = serde_json::to_string(self).unwrap_or_default();
+        format!("{HEADER_PREFIX}{json}{HEADER_SUFFIX}")
+    }
+}
+
+/// Everything a pack is rendered from.
+pub struct PackInput<'a> {
+    pub workspace: &'a str,
+    /// Every conversation in the workspace, newest first.
+    pub summaries: &'a [ConversationSummary],
+    /// Messages of the conversations returned by [`top_conversations`].
+    pub messages: &'a HashMap<Uuid, Vec<Message>>,
+    /// Stored decisions of the workspace, newest first.
+    pub decisions: &'a [Decision],
+    pub generated_at: DateTime<Utc>,
+}
+
+/// Conversations ranked for the "Top conversations" section: long ones
+/// first, discounted by age so the pack follows the current state of the
+/// project.
+pub fn top_conversations(
+    summaries: &[ConversationSummary],
+    now: DateTime<Utc>,
+    limit: usize,
+) -> Vec<Uuid> {
+    let mut scored: Vec<(f64, Uuid)> = summaries
+        .iter()
+        .map(|summary| {
+            let conv = &summary.conversation;
+            let last = conv.updated_at.unwrap_or(conv.created_at);
+            let age_days = (now - last).num_hours().max(0) as f64 / 24.0;
+            let size = ((summary.message_count.max(0) + 1) as f64).ln();
+            (size * 0.5f64.powf(age_days / 30.0), conv.id)
+        })
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    scored.into_iter().take(limit).map(|(_, id)| id).collect()
+}
+
+/// Render the pack markdown.
+pub fn render_pack(input: &PackInput<'_>, config: &PackConfig) -> String {
+    let header = PackHeader {
+        workspace: input.workspace.to_string(),
+        generated_at: input.generated_at,
+        conversations: input.summaries.len(),
+    };
+    let mut out = header.render();
+    let _ = write!(
+        out,
+        "\n# Project history: {}\n\n\
+         Generated by `hstry pack` on {} from {} conversations. Regenerate \
+         with `hstry pack --workspace {}`.\n",
+        input.workspace,
+        input
+            .generated_at
+            .to_rfc3339_opts(SecondsFormat::Secs, true),
+        input.summaries.len(),
+        input.workspace,
+    );
+
+    let by_id: HashMap<Uuid, &ConversationSummary> = input
+        .summaries
+        .iter()
+        .map(|summary| (summary.conversation.id, summary))
+        .collect();
+
+    // Decisions, newest first, each said once.
+    let mut seen = HashSet::new();
+    let decisions: Vec<&Decision> = input
+        .decisions
+        .iter()
+        .filter(|decision| seen.insert(decision.text.to_lowercase()))
+        .take(config.max_decisions)
+        .collect();
+    if !decisions.is_empty() {
+        out.push_str("\n## Decisions\n\n");
+        for decision in decisions {
+            let title = match by_id.get(&decision.conversation_id) {
+                Some(summary) => title(summary),
+                None => decision
+                    .title
+                    .as_deref()
+                    .map(one_line)
+                    .unwrap_or_else(|| "Untitled".to_string()),
+            };
+            let _ = writeln!(
+                out,
+                "- {} _({title}, {})_",
+                decision.text,
+                decision.decided_at.format("%Y-%m-%d")
+            );
+        }
+    }
+
+    let top = top_conversations(
+        input.summaries,
+        input.generated_at,
+        config.top_conversations,
+    );
+    if !top.is_empty() {
+        out.push_str("\n## Top conversations\n");
+        for id in top {
+            let (Some(summary), Some(messages)) = (by_id.get(&id), input.messages.get(&id)) else {
+                continue;
+            };
+            render_top_conversation(&mut out, summary, messages, config);
+        }
+    }
+
+    out.push_str("\n## All conversations\n\n");
+    for summary in input.summaries {
+        let conv = &summary.conversation;
+        let request = summary
+            .first_user_message
+            .as_deref()
+            .filter(|text| !is_system_context(text))
+            .map(|text| truncate(&one_line(text), config.summary_chars))
+            .unwrap_or_default();
+        let _ = write!(
+            out,
+            "- {} · **{}** ({}, {} messages)",
+            conv.created_at.format("%Y-%m-%d"),
+            title(summary),
+            conv.source_id,
+            summary.message_count
+        );
+        if !request.is_empty() {
+            let _ = write!(out, ": {request}");
+        }
+        out.push('\n');
+    }
+    out
+}
+
+fn render_top_conversation(
+    out: &mut String,
+    summary: &ConversationSummary,
+    messages: &[Message],
+    config: &PackConfig,
+) {
+    let conv = &summary.conversation;
+    let _ = writeln!(
+        out,
+        "\n### {}\n\n{} · {} · {} messages · `{}`",
+        title(summary),
+        conv.created_at.format("%Y-%m-%d"),
+        conv.source_id,
+        summary.message_count,
+        conv.readable_id
+            .clone()
+            .unwrap_or_else(|| conv.id.to_string())
+    );
+    if let Some(request) = prose(messages, MessageRole::User).next() {
+        let _ = writeln!(
+            out,
+            "\n**Request:** {}",
+            truncate(&one_line(&request.content), config.excerpt_chars)
+        );
+    }
+    if let Some(outcome) = outcome(messages) {
+        let _ = writeln!(
+            out,
+            "\n**Outcome:** {}",
+            truncate(&outcome, config.excerpt_chars)
+        );
+    }
+    let snippets = extract_snippets(messages, config.max_snippet_lines);
+    let skip = snippets
+        .len()
+        .saturating_sub(config.snippets_per_conversation);
+    for (lang, code) in snippets.into_iter().skip(skip) {
+        let _ = writeln!(out, "\n```{lang}\n{code}\n```");
+    }
+}
+
+/// Messages in `role` with prose, skipping injected context and tool dumps.
+fn prose(messages: &[Message], role: MessageRole) -> impl DoubleEndedIterator<Item = &Message> {
+    messages
+        .iter()
+        .filter(move |m| m.role == role)
+        .filter(|m| !m.content.trim().is_empty())
+        .filter(|m| !is_system_context(&m.content) && !is_tool_dump(&m.content))
+}
+
+/// The last assistant prose of a conversation on one line, code removed.
+pub fn outcome(messages: &[Message]) -> Option<String> {
+    prose(messages, MessageRole::Assistant)
+        .map(|m| one_line(&strip_code(&m.content)))
+        .rfind(|text| !text.is_empty())
+}
+
+/// Fenced code blocks from assistant messages, in order, as
+/// `(language, code)`. Blocks longer than `max_lines` or shorter than three
+/// lines are skipped.
+pub fn extract_snippets(messages: &[Message], max_lines: usize) -> Vec<(String, String)> {
+    let mut snippets = Vec::new();
+    for message in messages.iter().filter(|m| m.role == MessageRole::Assistant) {
+        let mut block: Option<(String, Vec<&str>)> = None;
+        for line in message.content.lines() {
+            let fence = line.trim_start().strip_prefix("```");
+            match (&mut block, fence) {
+                (None, Some(lang)) => block = Some((lang.trim().to_string(), Vec::new())),
+                (Some(_), Some(_)) => {
+                    let (lang, lines) = block.take().unwrap_or_default();
+                    if (3..=max_lines).contains(&lines.len()) {
+                        snippets.push((lang, lines.join("\n")));
+                    }
+                }
+                (Some((_, lines)), None) => lines.push(line),
+                (None, None) => {}
+            }
+        }
+    }
+    snippets
+}
+
+fn title(summary: &ConversationSummary) -> String {
+    summary
+        .conversation
+        .title
+        .as_deref()
+        .map(one_line)
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| "Untitled".to_string())
+}
+
+/// Drop fenced code blocks, keeping the prose around them.
+pub(crate) fn strip_code(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_fence = false;
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if !in_fence {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+pub(crate) fn one_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+pub(crate) fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let mut out: String = text.chars().take(max_chars).collect();
+    out.push('…');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Conversation;
+
+    fn message(idx: i32, role: MessageRole, content: &str) -> Message {
+        Message {
+            id: Uuid::new_v4(),
+            conversation_id: Uuid::nil(),
+            idx,
+            role,
+            content: content.to_string(),
+            parts_json: serde_json::json!([]),
+            created_at: None,
+            model: None,
+            tokens: None,
+            cost_usd: None,
+            metadata: serde_json::json!({}),
+            sender: None,
+            provider: None,
+            harness: None,
+            client_id: None,
+            parent_message_id: None,
+        }
+    }
+
+    fn summary(title: &str, message_count: i64, days_ago: i64) -> ConversationSummary {
+        let created_at = Utc::now() - chrono::Duration::days(days_ago);
+        ConversationSummary {
+            conversation: Conversation {
+                id: Uuid::new_v4(),
+                source_id: "codex".to_string(),
+                external_id: None,
+                readable_id: None,
+                platform_id: None,
+                title: Some(title.to_string()),
+                created_at,
+                updated_at: None,
+                model: None,
+                provider: None,
+                workspace: Some("/work/app".to_string()),
+                tokens_in: None,
+                tokens_out: None,
+                cost_usd: None,
+                metadata: serde_json::json!({}),
+                harness: None,
+                version: 1,
+                message_count,
+                parent_conversation_id: None,
+                parent_message_idx: None,
+                fork_type: None,
+            },
+            message_count,
+            first_user_message: Some(format!("Help with {title}")),
+            stale: false,
+        }
+    }
+
+    #[test]
+    fn snippets_skip_tiny_and_huge_blocks() {
+        let big = format!("```rust\n{}```", "let x = 1;\n".repeat(50));
+        let content = format!("```sh\nls\n```\n```rust\nfn a() {{\n    b();\n}}\n```\n{big}");
+        let messages = vec![message(0, MessageRole::Assistant, &content)];
+        assert_eq!(
+            extract_snippets(&messages, 40),
+            vec![("rust".to_string(), "fn a() {\n    b();\n}".to_string())]
+        );
+    }
+
+    #[test]
+    fn pack_round_trips_header_and_ranks_recent_long_conversations_first() {
+        let old_long = summary("old long", 400, 365);
+        let recent = summary("recent", 40, 1);
+        let summaries = vec![recent.clone(), old_long.clone()];
+        let now = Utc::now();
+        assert_eq!(
+            top_conversations(&summaries, now, 2),
+            vec![recent.conversation.id, old_long.conversation.id]
+        );
+
+        let mut messages = HashMap::new();
+        messages.insert(
+            recent.conversation.id,
+            vec![
+                message(0, MessageRole::User, "Add caching"),
+                message(1, MessageRole::Assistant, "We decided to cache in memory."),
+            ],
+        );
+        let decision = Decision {
+            id: 1,
+            conversation_id: recent.conversation.id,
+            message_id: messages[&recent.conversation.id][1].id,
+            workspace: Some("/work/app".to_string()),
+            title: Some("recent".to_string()),
+            text: "We decided to cache in memory.".to_string(),
+            decided_at: now,
+        };
+        let decisions = vec![decision.clone(), decision];
+        let input = PackInput {
+            workspace: "/work/app",
+            summaries: &summaries,
+            messages: &messages,
+            decisions: &decisions,
+            generated_at: now,
+        };
+        let pack = render_pack(&input, &PackConfig::default());
+        let header = PackHeader::parse(&pack).expect("header");
+        assert_eq!(header.workspace, "/work/app");
+        assert_eq!(header.conversations, 2);
+        assert_eq!(pack.matches("- We decided to cache in memory.").count(), 1);
+        assert!(pack.contains("- We decided to cache in memory. _(recent,"));
+        assert!(pack.contains("**Request:** Add caching"));
+        assert!(pack.contains("**old long** (codex, 400 messages): Help with old long"));
+    }
+}
@@ -37,6 +37,10 @@ pub struct ParsedConversation {
     /// Why this child was created: "fork", "thread", "resume".
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub fork_type: Option<String>,
+    /// Source id the conversation was exported from. Set by `hstry export`
+    /// so delta streams can be routed per source; ingest ignores it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_id: Option<String>,
 }
 
 /// Parsed message from an external source.
@@ -55,6 +59,12 @@ pub struct ParsedMessage {
     pub tool_calls: Option<Vec<ParsedToolCall>>,
     #[serde(default)]
     pub metadata: Option<serde_json::Value>,
+    /// Position in the conversation of the message this one continues from,
+    /// for conversations that branch (regenerations, edited prompts). When
+    /// any message sets it, messages without one are roots; otherwise the
+    /// conversation is linear.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_idx: Option<i32>,
 }
 
 /// Parsed tool call from an external source.
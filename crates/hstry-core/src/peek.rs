@@ -80,14 +80,12 @@ pub fn build_peek(conv: &Conversation, messages: &[Message], cfg: &PeekConfig) -
 
     for (i, msg) in messages.iter().enumerate() {
         match msg.role {
-            MessageRole::User => {
-                if has_text_content(msg) {
-                    counts.user += 1;
-                    if first_user_idx.is_none() {
-                        first_user_idx = Some(i);
-                    }
-                    last_user_idx = Some(i);
+            MessageRole::User if has_text_content(msg) => {
+                counts.user += 1;
+                if first_user_idx.is_none() {
+                    first_user_idx = Some(i);
                 }
+                last_user_idx = Some(i);
             }
             MessageRole::Assistant => {
                 counts.assistant += 1;
@@ -117,28 +115,18 @@ pub fn build_peek(conv: &Conversation, messages: &[Message], cfg: &PeekConfig) -
                 *tools.entry(name.clone()).or_insert(0) += 1;
 
                 let input = part.get("input");
+                touched_paths(&name, input, &mut files);
 
-                if name == "bash" {
-                    if let Some(cmd) = input
+                if name == "bash"
+                    && let Some(cmd) = input
                         .and_then(|v| v.get("command"))
                         .and_then(|v| v.as_str())
-                    {
-                        let truncated = truncate_chars(cmd.trim(), cfg.bash_sample_chars);
-                        scan_paths_into(cmd, &mut files);
-                        if !bash_seen.contains_key(&truncated) {
-                            bash_seen.insert(truncated.clone(), ());
-                            if bash_sample.len() < cfg.bash_sample_count {
-                                bash_sample.push(truncated);
-                            }
-                        }
-                    }
-                } else if let Some(obj) = input.and_then(|v| v.as_object()) {
-                    for key in ["file_path", "path", "filePath", "notebook_path"] {
-                        if let Some(p) = obj.get(key).and_then(|v| v.as_str())
-                            && !p.is_empty()
-                        {
-                            files.push(p.to_string());
-                            break;
+                {
+                    let truncated = truncate_chars(cmd.trim(), cfg.bash_sample_chars);
+                    if !bash_seen.contains_key(&truncated) {
+                        bash_seen.insert(truncated.clone(), ());
+                        if bash_sample.len() < cfg.bash_sample_count {
+                            bash_sample.push(truncated);
                         }
                     }
                 }
@@ -180,6 +168,28 @@ pub fn build_peek(conv: &Conversation, messages: &[Message], cfg: &PeekConfig) -
     }
 }
 
+/// Push the paths a tool call touches onto `out`: its file argument, or
+/// path tokens in a `bash` command. `name` is the lowercased tool name.
+pub fn touched_paths(name: &str, input: Option<&serde_json::Value>, out: &mut Vec<String>) {
+    if name == "bash" {
+        if let Some(cmd) = input
+            .and_then(|v| v.get("command"))
+            .and_then(|v| v.as_str())
+        {
+            scan_paths_into(cmd, out);
+        }
+    } else if let Some(obj) = input.and_then(|v| v.as_object()) {
+        for key in ["file_path", "path", "filePath", "notebook_path"] {
+            if let Some(p) = obj.get(key).and_then(|v| v.as_str())
+                && !p.is_empty()
+            {
+                out.push(p.to_string());
+                break;
+            }
+        }
+    }
+}
+
 fn has_text_content(msg: &Message) -> bool {
     // Adapters that emit parts (claude-code, pi, etc.) often pack tool results
     // into user messages with non-empty content (the tool's stdout). Those
@@ -351,6 +361,7 @@ mod tests {
             provider: None,
             harness: None,
             client_id: None,
+            parent_message_id: None,
         }
     }
 
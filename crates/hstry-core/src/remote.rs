@@ -1,24 +1,38 @@
-//! Remote sync functionality over SSH.
+//! Remote sync functionality over SSH or a peer's hstry-api.
 //!
-//! Provides fetching and bidirectional merging of hstry databases across machines.
+//! Provides fetching and bidirectional merging of hstry databases across machines,
+//! plus a delta pull that streams only changed conversations. `http` remotes
+//! only sync deltas, through the peer's `/sync` endpoints.
 
+use std::collections::{BTreeSet, HashMap};
 use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, SecondsFormat, Utc};
 use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt};
 use tokio::task::JoinSet;
 use uuid::Uuid;
 
-use crate::config::RemoteConfig;
-use crate::db::{Database, SearchOptions};
+use crate::config::{ConflictStrategy, RemoteConfig, RemoteTransport};
+use crate::db::{
+    Database, DatabaseOptions, ListConversationsOptions, RemoteConflict, SearchOptions,
+};
 use crate::error::{Error, Result};
-use crate::models::{Conversation, ConversationWithMessages, Message, SearchHit};
+use crate::export;
+use crate::ingest::ingest_batch;
+use crate::models::{
+    Conversation, ConversationWithMessages, Message, MessageWithExtras, SearchHit, Source,
+};
+use crate::parsed::ParsedConversation;
 
 /// Default remote database path (XDG standard).
 pub const DEFAULT_REMOTE_DB_PATH: &str = "~/.local/share/hstry/hstry.db";
 
+/// Conversations ingested per transaction during a delta pull.
+const DELTA_BATCH_SIZE: usize = 100;
+
 #[derive(Debug, Deserialize)]
 struct JsonResponse<T> {
     ok: bool,
@@ -51,7 +65,7 @@ pub struct FetchResult {
 }
 
 /// Result of a sync/merge operation.
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SyncResult {
     pub remote_name: String,
     pub conversations_added: usize,
@@ -59,6 +73,13 @@ pub struct SyncResult {
     pub messages_added: usize,
     pub sources_added: usize,
     pub direction: SyncDirection,
+    /// Bytes streamed from the remote by a delta pull.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytes_transferred: Option<u64>,
+    /// Conversations that diverged on both sides, as settled by the
+    /// remote's conflict strategy. Not yet recorded in any database.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<RemoteConflict>,
 }
 
 /// Sync direction.
@@ -267,8 +288,56 @@ pub fn cached_db_path(remote_name: &str) -> PathBuf {
     remote_cache_dir().join(format!("{remote_name}.db"))
 }
 
+/// Where the delta cursor for a remote is kept: the remote's clock at the
+/// start of its last successful delta pull.
+pub fn delta_cursor_path(remote_name: &str) -> PathBuf {
+    remote_cache_dir().join(format!("{remote_name}.cursor"))
+}
+
+/// Read the delta cursor for a remote, if a delta pull has completed before.
+pub fn read_delta_cursor(remote_name: &str) -> Option<DateTime<Utc>> {
+    read_cursor(&delta_cursor_path(remote_name))
+}
+
+fn write_delta_cursor(remote_name: &str, cursor: DateTime<Utc>) -> Result<()> {
+    write_cursor(&delta_cursor_path(remote_name), cursor)
+}
+
+fn read_cursor(path: &Path) -> Option<DateTime<Utc>> {
+    let raw = std::fs::read_to_string(path).ok()?;
+    DateTime::parse_from_rfc3339(raw.trim())
+        .ok()
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+fn write_cursor(path: &Path, cursor: DateTime<Utc>) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, cursor.to_rfc3339_opts(SecondsFormat::Secs, true))?;
+    Ok(())
+}
+
+/// Remote shell command for a delta pull. The first output line is the
+/// remote clock, taken before the export so nothing written during it is
+/// skipped next time; the rest is the JSONL export.
+fn delta_export_command(since: Option<DateTime<Utc>>) -> String {
+    let mut command = String::from("date -u +%Y-%m-%dT%H:%M:%SZ && hstry export --format jsonl");
+    if let Some(since) = since {
+        command.push_str(" --since ");
+        command.push_str(&since.to_rfc3339_opts(SecondsFormat::Secs, true));
+    }
+    command
+}
+
 /// Fetch a remote database to local cache.
 pub fn fetch_remote(config: &RemoteConfig) -> Result<FetchResult> {
+    if config.transport == RemoteTransport::Http {
+        return Err(Error::Remote(format!(
+            "'{}' is an http remote; fetching the whole database needs SSH (use `hstry remote sync`)",
+            config.name
+        )));
+    }
     let transport = SshTransport::from_config(config);
 
     // Test connection first
@@ -302,26 +371,311 @@ pub fn fetch_remote(config: &RemoteConfig) -> Result<FetchResult> {
     })
 }
 
+/// Which machine the conversations being merged in come from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeOrigin {
+    /// Local history merged into a copy of the remote database (push).
+    Local,
+    /// Remote history merged into the local database (pull).
+    Remote,
+}
+
+/// What a merge does with an incoming conversation that already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MergeAction {
+    Keep,
+    Replace,
+    Duplicate,
+}
+
+/// How two copies of a conversation relate, compared by role and content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Divergence {
+    Same,
+    IncomingAhead,
+    TargetAhead,
+    Diverged,
+}
+
+fn compare_threads(target: &[(String, &str)], incoming: &[(String, &str)]) -> Divergence {
+    let common = target
+        .iter()
+        .zip(incoming)
+        .take_while(|(a, b)| a == b)
+        .count();
+    match (common == target.len(), common == incoming.len()) {
+        (true, true) => Divergence::Same,
+        (true, false) => Divergence::IncomingAhead,
+        (false, true) => Divergence::TargetAhead,
+        (false, false) => Divergence::Diverged,
+    }
+}
+
+fn thread_of(messages: &[Message]) -> Vec<(String, &str)> {
+    messages
+        .iter()
+        .map(|m| (m.role.to_string(), m.content.as_str()))
+        .collect()
+}
+
+fn incoming_is_newer(incoming: Option<DateTime<Utc>>, target: Option<DateTime<Utc>>) -> bool {
+    match (incoming, target) {
+        (Some(new_ts), Some(old_ts)) => new_ts > old_ts,
+        (Some(_), None) => true,
+        (None, _) => false,
+    }
+}
+
+/// Decide what to do with an incoming copy of an existing conversation.
+/// Returns the action and whether the copies had diverged (a conflict).
+/// Copies that only grew on one side fast-forward without a conflict.
+fn plan_merge(
+    divergence: Divergence,
+    incoming_updated: Option<DateTime<Utc>>,
+    target_updated: Option<DateTime<Utc>>,
+    strategy: ConflictStrategy,
+    origin: MergeOrigin,
+) -> (MergeAction, bool) {
+    let newer = incoming_is_newer(incoming_updated, target_updated);
+    let action = |take: bool| {
+        if take {
+            MergeAction::Replace
+        } else {
+            MergeAction::Keep
+        }
+    };
+    match divergence {
+        Divergence::Same => (action(newer), false),
+        Divergence::IncomingAhead => (MergeAction::Replace, false),
+        Divergence::TargetAhead => (MergeAction::Keep, false),
+        Divergence::Diverged => {
+            let resolved = match strategy {
+                ConflictStrategy::LastWriterWins => action(newer),
+                ConflictStrategy::PreferLocal => action(origin == MergeOrigin::Local),
+                ConflictStrategy::PreferRemote => action(origin == MergeOrigin::Remote),
+                ConflictStrategy::Duplicate => MergeAction::Duplicate,
+            };
+            (resolved, true)
+        }
+    }
+}
+
+/// External id given to the incoming copy kept by the duplicate strategy.
+/// Stable per remote, so a conversation that keeps diverging updates one
+/// copy instead of piling up new ones.
+fn conflict_external_id(external_id: &str, remote_name: &str) -> String {
+    format!("{external_id}~conflict-{remote_name}")
+}
+
+/// Everything needed to record a conflict found while merging one
+/// conversation.
+struct ConflictSide<'a> {
+    remote_name: &'a str,
+    strategy: ConflictStrategy,
+    origin: MergeOrigin,
+    source_id: &'a str,
+    external_id: &'a str,
+    title: Option<&'a str>,
+}
+
+impl ConflictSide<'_> {
+    fn record(
+        &self,
+        action: MergeAction,
+        target: (Uuid, Option<DateTime<Utc>>),
+        incoming: (Uuid, Option<DateTime<Utc>>),
+        duplicate: Option<Uuid>,
+    ) -> RemoteConflict {
+        let (local, remote) = match self.origin {
+            MergeOrigin::Remote => (target, incoming),
+            MergeOrigin::Local => (incoming, target),
+        };
+        let target_is_local = self.origin == MergeOrigin::Remote;
+        let resolution = match action {
+            MergeAction::Duplicate => "duplicated",
+            MergeAction::Keep if target_is_local => "kept-local",
+            MergeAction::Replace if !target_is_local => "kept-local",
+            MergeAction::Keep | MergeAction::Replace => "kept-remote",
+        };
+        let now = Utc::now();
+        RemoteConflict {
+            id: 0,
+            remote: self.remote_name.to_string(),
+            source_id: self.source_id.to_string(),
+            external_id: self.external_id.to_string(),
+            title: self.title.map(str::to_string),
+            local_conversation_id: Some(local.0),
+            duplicate_conversation_id: duplicate,
+            local_updated_at: local.1,
+            remote_updated_at: remote.1,
+            strategy: self.strategy.as_str().to_string(),
+            resolution: resolution.to_string(),
+            detected_at: now,
+            resolved_at: (action != MergeAction::Duplicate).then_some(now),
+        }
+    }
+}
+
+/// Which copy to keep when settling an open conflict by hand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictChoice {
+    /// Keep the copy on this machine and drop the duplicate.
+    Local,
+    /// Keep the remote's copy in place of the local one.
+    Remote,
+    /// Keep both conversations.
+    Both,
+}
+
+impl ConflictChoice {
+    /// The side with the newer `updated_at`; the local copy wins ties.
+    pub fn newest(conflict: &RemoteConflict) -> Self {
+        if incoming_is_newer(conflict.remote_updated_at, conflict.local_updated_at) {
+            Self::Remote
+        } else {
+            Self::Local
+        }
+    }
+
+    fn resolution(self) -> &'static str {
+        match self {
+            Self::Local => "kept-local",
+            Self::Remote => "kept-remote",
+            Self::Both => "kept-both",
+        }
+    }
+}
+
+/// How the two copies in an open conflict differ.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConflictDiff {
+    pub local_messages: usize,
+    pub remote_messages: usize,
+    /// Messages both copies share before they diverge.
+    pub shared_messages: usize,
+    /// First message only the local copy has, as `(role, content)`.
+    pub local_first_change: Option<(String, String)>,
+    /// First message only the remote copy has, as `(role, content)`.
+    pub remote_first_change: Option<(String, String)>,
+}
+
+/// Compare the local conversation of a conflict with the duplicate copy the
+/// sync stored next to it. `None` when either copy is not in `db` (push
+/// conflicts keep their duplicate on the remote).
+pub async fn conflict_diff(
+    db: &Database,
+    conflict: &RemoteConflict,
+) -> Result<Option<ConflictDiff>> {
+    let (Some(local_id), Some(remote_id)) = (
+        conflict.local_conversation_id,
+        conflict.duplicate_conversation_id,
+    ) else {
+        return Ok(None);
+    };
+    let local = db.get_messages(local_id).await?;
+    let remote = db.get_messages(remote_id).await?;
+    let local_thread = thread_of(&local);
+    let remote_thread = thread_of(&remote);
+    let shared = local_thread
+        .iter()
+        .zip(&remote_thread)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let first_change = |thread: &[(String, &str)]| {
+        thread
+            .get(shared)
+            .map(|(role, content)| (role.clone(), (*content).to_string()))
+    };
+    Ok(Some(ConflictDiff {
+        local_messages: local.len(),
+        remote_messages: remote.len(),
+        shared_messages: shared,
+        local_first_change: first_change(&local_thread),
+        remote_first_change: first_change(&remote_thread),
+    }))
+}
+
+/// Settle an open conflict left by the duplicate strategy.
+///
+/// `Local` deletes the duplicate; `Remote` deletes the local copy and lets
+/// the duplicate take over its external id, so later syncs match it.
+pub async fn resolve_conflict(
+    db: &Database,
+    conflict: &RemoteConflict,
+    choice: ConflictChoice,
+) -> Result<()> {
+    if choice != ConflictChoice::Both {
+        let (Some(local_id), Some(duplicate_id)) = (
+            conflict.local_conversation_id,
+            conflict.duplicate_conversation_id,
+        ) else {
+            return Err(Error::Remote(format!(
+                "Conflict #{} has no duplicate in this database; resolve it on {}",
+                conflict.id, conflict.remote
+            )));
+        };
+        match choice {
+            ConflictChoice::Local => {
+                db.delete_conversations_batch(&[duplicate_id]).await?;
+            }
+            ConflictChoice::Remote => {
+                db.delete_conversations_batch(&[local_id]).await?;
+                db.set_conversation_external_id(duplicate_id, &conflict.external_id)
+                    .await?;
+            }
+            ConflictChoice::Both => {}
+        }
+    }
+    db.resolve_remote_conflict(conflict.id, choice.resolution())
+        .await
+}
+
 /// Merge conversations from a source database into a target database.
-/// Uses updated_at for conflict resolution (newer wins).
+///
+/// Sources are namespaced as `<namespace>:<source>`. Conversations that
+/// diverged on both sides are settled by `strategy` and returned in
+/// [`SyncResult::conflicts`] for the caller to record.
 pub async fn merge_databases(
     target: &Database,
     source_path: &Path,
-    remote_name: &str,
+    namespace: &str,
+    strategy: ConflictStrategy,
+    origin: MergeOrigin,
+) -> Result<SyncResult> {
+    merge_databases_with(
+        target,
+        source_path,
+        &DatabaseOptions::default(),
+        namespace,
+        strategy,
+        origin,
+    )
+    .await
+}
+
+/// [`merge_databases`] with the source database opened with `source_opts`,
+/// e.g. the key of an encrypted one.
+pub async fn merge_databases_with(
+    target: &Database,
+    source_path: &Path,
+    source_opts: &DatabaseOptions,
+    namespace: &str,
+    strategy: ConflictStrategy,
+    origin: MergeOrigin,
 ) -> Result<SyncResult> {
-    // Open the source database
-    let source = Database::open(source_path).await?;
+    let source = Database::open_with(source_path, source_opts).await?;
 
     let mut conversations_added = 0usize;
     let mut conversations_updated = 0usize;
     let mut messages_added = 0usize;
     let mut sources_added = 0usize;
+    let mut conflicts = Vec::new();
 
-    // Merge sources (prefixed with remote name to avoid conflicts)
+    // Merge sources (prefixed with the namespace to avoid conflicts)
     let remote_sources = source.list_sources().await?;
     for mut remote_source in remote_sources {
-        // Prefix source ID with remote name to namespace it
-        let namespaced_id = format!("{}:{}", remote_name, remote_source.id);
+        // Prefix source ID with the namespace
+        let namespaced_id = format!("{}:{}", namespace, remote_source.id);
         remote_source.id = namespaced_id;
 
         // Check if source already exists
@@ -332,19 +686,24 @@ pub async fn merge_databases(
         }
     }
 
-    // Get all conversations from source
+    // Get all conversations from source; sensitive ones are never merged
     let source_conversations = source
-        .list_conversations(crate::db::ListConversationsOptions::default())
+        .list_conversations(crate::db::ListConversationsOptions {
+            exclude_sensitive: true,
+            ..Default::default()
+        })
         .await?;
 
     // Collect conversations and messages to insert, then write in a single transaction
     let mut batch_convs: Vec<Conversation> = Vec::new();
     let mut batch_msgs: Vec<Message> = Vec::new();
+    let mut batch_links: Vec<(Uuid, i32, i32)> = Vec::new();
     let mut affected_ids: Vec<Uuid> = Vec::new();
 
     for conv in source_conversations {
         // Namespace the source_id
-        let namespaced_source_id = format!("{}:{}", remote_name, conv.source_id);
+        let namespaced_source_id = format!("{}:{}", namespace, conv.source_id);
+        let mut external_id = conv.external_id.clone();
 
         // Check if conversation already exists (by external_id within namespaced source)
         let existing_id = if let Some(ref external_id) = conv.external_id {
@@ -355,36 +714,80 @@ pub async fn merge_databases(
             None
         };
 
-        let (should_insert, conv_id) = if let Some(existing_uuid) = existing_id {
-            // Conversation exists, check if we should update
-            if let Some(existing_conv) = target.get_conversation(existing_uuid).await? {
-                // Compare updated_at timestamps (newer wins)
-                let should_update = match (conv.updated_at, existing_conv.updated_at) {
-                    (Some(new_ts), Some(old_ts)) => new_ts > old_ts,
-                    (Some(_), None) => true,
-                    (None, Some(_)) => false,
-                    (None, None) => conv.created_at > existing_conv.created_at,
-                };
-                if should_update {
-                    conversations_updated += 1;
-                    (true, existing_uuid)
-                } else {
-                    (false, existing_uuid)
+        let source_messages = source.get_messages(conv.id).await?;
+        let conv_id = match existing_id {
+            Some(existing_uuid) => match target.get_conversation(existing_uuid).await? {
+                None => Some(existing_uuid),
+                Some(existing_conv) => {
+                    let target_messages = target.get_messages(existing_uuid).await?;
+                    let divergence =
+                        compare_threads(&thread_of(&target_messages), &thread_of(&source_messages));
+                    let (action, conflicted) = plan_merge(
+                        divergence,
+                        conv.updated_at,
+                        existing_conv.updated_at,
+                        strategy,
+                        origin,
+                    );
+                    let conv_id = match action {
+                        MergeAction::Keep => None,
+                        MergeAction::Replace => {
+                            // The local copy diverged; keep it restorable.
+                            if conflicted {
+                                target.create_snapshot(existing_uuid, "merge").await?;
+                            }
+                            conversations_updated += 1;
+                            Some(existing_uuid)
+                        }
+                        MergeAction::Duplicate => {
+                            let ext = conv.external_id.as_deref().unwrap_or_default();
+                            let duplicate_ext = conflict_external_id(ext, namespace);
+                            let duplicate_id = target
+                                .get_conversation_id(&namespaced_source_id, &duplicate_ext)
+                                .await?;
+                            if duplicate_id.is_none() {
+                                conversations_added += 1;
+                            } else {
+                                conversations_updated += 1;
+                            }
+                            external_id = Some(duplicate_ext);
+                            Some(duplicate_id.unwrap_or_else(Uuid::new_v4))
+                        }
+                    };
+                    if conflicted {
+                        let side = ConflictSide {
+                            remote_name: namespace,
+                            strategy,
+                            origin,
+                            source_id: match origin {
+                                MergeOrigin::Remote => &namespaced_source_id,
+                                MergeOrigin::Local => &conv.source_id,
+                            },
+                            external_id: conv.external_id.as_deref().unwrap_or_default(),
+                            title: conv.title.as_deref(),
+                        };
+                        conflicts.push(side.record(
+                            action,
+                            (existing_uuid, existing_conv.updated_at),
+                            (conv.id, conv.updated_at),
+                            conv_id.filter(|_| action == MergeAction::Duplicate),
+                        ));
+                    }
+                    conv_id
                 }
-            } else {
-                (true, existing_uuid)
+            },
+            None => {
+                // New conversation
+                conversations_added += 1;
+                Some(Uuid::new_v4())
             }
-        } else {
-            // New conversation
-            conversations_added += 1;
-            (true, Uuid::new_v4())
         };
 
-        if should_insert {
+        if let Some(conv_id) = conv_id {
             let merged_conv = Conversation {
                 id: conv_id,
                 source_id: namespaced_source_id.clone(),
-                external_id: conv.external_id,
+                external_id,
                 readable_id: conv.readable_id,
                 platform_id: conv.platform_id,
                 title: conv.title,
@@ -409,8 +812,17 @@ pub async fn merge_databases(
             batch_convs.push(merged_conv);
 
             // Collect messages
-            let source_messages = source.get_messages(conv.id).await?;
+            let idx_by_id: HashMap<Uuid, i32> = source_messages
+                .iter()
+                .map(|msg| (msg.id, msg.idx))
+                .collect();
             for msg in source_messages {
+                if let Some(parent_idx) = msg
+                    .parent_message_id
+                    .and_then(|parent| idx_by_id.get(&parent))
+                {
+                    batch_links.push((conv_id, msg.idx, *parent_idx));
+                }
                 let merged_msg = Message {
                     id: Uuid::new_v4(),
                     conversation_id: conv_id,
@@ -427,6 +839,7 @@ pub async fn merge_databases(
                     provider: msg.provider,
                     harness: msg.harness,
                     client_id: msg.client_id,
+                    parent_message_id: None,
                 };
                 batch_msgs.push(merged_msg);
                 messages_added += 1;
@@ -443,6 +856,9 @@ pub async fn merge_databases(
         for msg in &batch_msgs {
             target.insert_message_in_tx(&mut tx, msg).await?;
         }
+        target
+            .link_message_parents_in_tx(&mut tx, &batch_links)
+            .await?;
         tx.commit().await?;
 
         // Rebuild caches outside the transaction
@@ -452,12 +868,14 @@ pub async fn merge_databases(
     source.close().await;
 
     Ok(SyncResult {
-        remote_name: remote_name.to_string(),
+        remote_name: namespace.to_string(),
         conversations_added,
         conversations_updated,
         messages_added,
         sources_added,
         direction: SyncDirection::Pull,
+        bytes_transferred: None,
+        conflicts,
     })
 }
 
@@ -470,14 +888,321 @@ pub async fn sync_from_remote(
     let fetch_result = fetch_remote(config)?;
 
     // Merge into local
-    let sync_result =
-        merge_databases(local_db, &fetch_result.local_cache_path, &config.name).await?;
+    let sync_result = merge_databases(
+        local_db,
+        &fetch_result.local_cache_path,
+        &config.name,
+        config.conflict_strategy,
+        MergeOrigin::Remote,
+    )
+    .await?;
 
     Ok((fetch_result, sync_result))
 }
 
-/// Push local database to remote and merge.
-pub async fn sync_to_remote(local_db_path: &Path, config: &RemoteConfig) -> Result<SyncResult> {
+/// Pull only what changed on the remote since the last delta pull.
+///
+/// Runs `hstry export --format jsonl --since <cursor>` over SSH and ingests
+/// the stream as it arrives instead of copying the whole remote database.
+/// Sources are namespaced with the remote name, as in [`merge_databases`].
+/// The cursor only advances once the stream has been fully ingested.
+pub async fn sync_from_remote_delta(
+    local_db: &Database,
+    config: &RemoteConfig,
+) -> Result<SyncResult> {
+    if config.transport == RemoteTransport::Http {
+        return sync_from_remote_http(local_db, config).await;
+    }
+    let transport = SshTransport::from_config(config);
+    transport.test_connection()?;
+
+    let mut ingest = DeltaIngest::new(
+        local_db,
+        &config.name,
+        config.conflict_strategy,
+        MergeOrigin::Remote,
+        SyncDirection::Pull,
+    );
+
+    let listing = transport.exec("hstry source list --json")?;
+    let response: JsonResponse<Vec<Source>> = serde_json::from_str(&listing)
+        .map_err(|e| Error::Remote(format!("Failed parsing remote sources: {e}")))?;
+    ingest
+        .add_sources(response.result.unwrap_or_default())
+        .await?;
+
+    let mut cmd = tokio::process::Command::from(transport.ssh_command());
+    cmd.arg(&config.host)
+        .arg(delta_export_command(read_delta_cursor(&config.name)))
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .kill_on_drop(true);
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| Error::Remote(format!("Failed to start ssh: {e}")))?;
+
+    // Drain stderr alongside stdout so a chatty remote cannot stall the pipe.
+    let mut stderr = child.stderr.take();
+    let stderr_task = tokio::spawn(async move {
+        let mut text = String::new();
+        if let Some(stderr) = stderr.as_mut() {
+            let _ = stderr.read_to_string(&mut text).await;
+        }
+        text
+    });
+
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| Error::Remote("ssh stdout unavailable".to_string()))?;
+    let mut lines = tokio::io::BufReader::new(stdout).lines();
+    while let Some(line) = lines.next_line().await? {
+        ingest.line(&line).await?;
+    }
+
+    let status = child
+        .wait()
+        .await
+        .map_err(|e| Error::Remote(format!("SSH failed: {e}")))?;
+    let stderr = stderr_task.await.unwrap_or_default();
+    if !status.success() {
+        return Err(Error::Remote(format!(
+            "Remote export failed: {}",
+            stderr.trim()
+        )));
+    }
+
+    let (result, started_at) = ingest.finish().await?;
+    let started_at =
+        started_at.ok_or_else(|| Error::Remote("Remote export produced no output".to_string()))?;
+    write_delta_cursor(&config.name, started_at)?;
+    Ok(result)
+}
+
+/// Ingests a delta export as it streams in: a clock header line, then one
+/// JSONL conversation per line. Conversations are batched per source and
+/// settled against existing copies with the given conflict strategy.
+struct DeltaIngest<'a> {
+    db: &'a Database,
+    /// Prefix for incoming source ids, and the name conflicts are filed under.
+    namespace: &'a str,
+    strategy: ConflictStrategy,
+    origin: MergeOrigin,
+    started_at: Option<DateTime<Utc>>,
+    bytes: u64,
+    pending: HashMap<String, Vec<ParsedConversation>>,
+    affected: BTreeSet<Uuid>,
+    result: SyncResult,
+}
+
+impl<'a> DeltaIngest<'a> {
+    fn new(
+        db: &'a Database,
+        namespace: &'a str,
+        strategy: ConflictStrategy,
+        origin: MergeOrigin,
+        direction: SyncDirection,
+    ) -> Self {
+        Self {
+            db,
+            namespace,
+            strategy,
+            origin,
+            started_at: None,
+            bytes: 0,
+            pending: HashMap::new(),
+            affected: BTreeSet::new(),
+            result: SyncResult {
+                remote_name: namespace.to_string(),
+                conversations_added: 0,
+                conversations_updated: 0,
+                messages_added: 0,
+                sources_added: 0,
+                direction,
+                bytes_transferred: None,
+                conflicts: Vec::new(),
+            },
+        }
+    }
+
+    /// Register the sender's sources under the namespace.
+    async fn add_sources(&mut self, sources: Vec<Source>) -> Result<()> {
+        for mut source in sources {
+            source.id = format!("{}:{}", self.namespace, source.id);
+            if self.db.get_source(&source.id).await?.is_none() {
+                self.db.upsert_source(&source).await?;
+                self.result.sources_added += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Feed one line of the export stream.
+    async fn line(&mut self, line: &str) -> Result<()> {
+        self.bytes += line.len() as u64 + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            return Ok(());
+        }
+        if self.started_at.is_none() {
+            let stamp = DateTime::parse_from_rfc3339(line)
+                .map_err(|e| Error::Remote(format!("Unexpected delta header '{line}': {e}")))?;
+            self.started_at = Some(stamp.with_timezone(&Utc));
+            return Ok(());
+        }
+        let conversation: ParsedConversation = serde_json::from_str(line)
+            .map_err(|e| Error::Remote(format!("Failed parsing remote export: {e}")))?;
+        self.conversation(conversation).await
+    }
+
+    async fn conversation(&mut self, conversation: ParsedConversation) -> Result<()> {
+        let source_id = conversation.source_id.clone().ok_or_else(|| {
+            Error::Remote(
+                "Remote export has no sourceId; upgrade hstry on the remote for delta sync"
+                    .to_string(),
+            )
+        })?;
+        let Some(conversation) = self.plan(&source_id, conversation).await? else {
+            return Ok(());
+        };
+        let batch = self.pending.entry(source_id.clone()).or_default();
+        batch.push(conversation);
+        if batch.len() >= DELTA_BATCH_SIZE {
+            let batch = std::mem::take(batch);
+            self.ingest(&source_id, batch).await?;
+        }
+        Ok(())
+    }
+
+    /// Apply the conflict strategy to one incoming conversation. Returns the
+    /// conversation to ingest (possibly renamed to a conflict copy), or
+    /// `None` when the existing copy is kept.
+    async fn plan(
+        &mut self,
+        incoming_source_id: &str,
+        mut conversation: ParsedConversation,
+    ) -> Result<Option<ParsedConversation>> {
+        let source_id = format!("{}:{incoming_source_id}", self.namespace);
+        let Some(external_id) = conversation.external_id.clone() else {
+            return Ok(Some(conversation));
+        };
+        let Some(existing_id) = self
+            .db
+            .get_conversation_id(&source_id, &external_id)
+            .await?
+        else {
+            return Ok(Some(conversation));
+        };
+        let Some(existing) = self.db.get_conversation(existing_id).await? else {
+            return Ok(Some(conversation));
+        };
+
+        let existing_messages = self.db.get_messages(existing_id).await?;
+        let incoming: Vec<(String, &str)> = conversation
+            .messages
+            .iter()
+            .map(|m| (m.role.clone(), m.content.as_str()))
+            .collect();
+        let divergence = compare_threads(&thread_of(&existing_messages), &incoming);
+        let incoming_updated = conversation
+            .updated_at
+            .and_then(DateTime::<Utc>::from_timestamp_millis);
+        let (action, conflicted) = plan_merge(
+            divergence,
+            incoming_updated,
+            existing.updated_at,
+            self.strategy,
+            self.origin,
+        );
+        if conflicted {
+            let side = ConflictSide {
+                remote_name: self.namespace,
+                strategy: self.strategy,
+                origin: self.origin,
+                source_id: &source_id,
+                external_id: &external_id,
+                title: conversation.title.as_deref(),
+            };
+            // The sender's own conversation id is not part of the export.
+            self.result.conflicts.push(side.record(
+                action,
+                (existing_id, existing.updated_at),
+                (Uuid::nil(), incoming_updated),
+                None,
+            ));
+        }
+        if conflicted && action == MergeAction::Replace {
+            self.db.create_snapshot(existing_id, "merge").await?;
+        }
+        Ok(match action {
+            MergeAction::Keep => None,
+            MergeAction::Replace => Some(conversation),
+            MergeAction::Duplicate => {
+                conversation.external_id = Some(conflict_external_id(&external_id, self.namespace));
+                conversation.readable_id = None;
+                Some(conversation)
+            }
+        })
+    }
+
+    async fn ingest(
+        &mut self,
+        incoming_source_id: &str,
+        batch: Vec<ParsedConversation>,
+    ) -> Result<()> {
+        let source_id = format!("{}:{incoming_source_id}", self.namespace);
+        let outcome = ingest_batch(self.db, &source_id, batch)
+            .await
+            .map_err(|e| {
+                Error::Remote(format!(
+                    "Failed ingesting delta from {}: {e}",
+                    self.namespace
+                ))
+            })?;
+        self.result.conversations_added += outcome.created;
+        self.result.conversations_updated += outcome.updated;
+        self.result.messages_added += outcome.messages;
+        self.affected.extend(outcome.affected_conversation_ids);
+        Ok(())
+    }
+
+    /// Ingest what is still batched and return the result, with the clock
+    /// from the stream header when there was one.
+    async fn finish(mut self) -> Result<(SyncResult, Option<DateTime<Utc>>)> {
+        for (source_id, batch) in std::mem::take(&mut self.pending) {
+            if !batch.is_empty() {
+                self.ingest(&source_id, batch).await?;
+            }
+        }
+        let affected: Vec<Uuid> = std::mem::take(&mut self.affected).into_iter().collect();
+        if !affected.is_empty() {
+            self.db.rebuild_conversation_summaries(&affected).await?;
+        }
+        for conflict in &mut self.result.conflicts {
+            if conflict.resolution == "duplicated" && conflict.duplicate_conversation_id.is_none() {
+                let duplicate_ext = conflict_external_id(&conflict.external_id, self.namespace);
+                conflict.duplicate_conversation_id = self
+                    .db
+                    .get_conversation_id(&conflict.source_id, &duplicate_ext)
+                    .await?;
+            }
+        }
+        if self.bytes > 0 {
+            self.result.bytes_transferred = Some(self.bytes);
+        }
+        Ok((self.result, self.started_at))
+    }
+}
+
+/// Push local database to remote and merge. `db_opts` opens the local
+/// database and the merged copy, so an encrypted history is uploaded
+/// encrypted with the same key.
+pub async fn sync_to_remote(
+    local_db_path: &Path,
+    db_opts: &DatabaseOptions,
+    config: &RemoteConfig,
+) -> Result<SyncResult> {
     let transport = SshTransport::from_config(config);
 
     // Test connection
@@ -500,13 +1225,7 @@ pub async fn sync_to_remote(local_db_path: &Path, config: &RemoteConfig) -> Resu
         transport.fetch_file(&expanded_path, &temp_db_path)?;
     }
 
-    // Open/create the temp database
-    let temp_db = Database::open(&temp_db_path).await?;
-
-    // Merge local into temp (with namespace "local" for tracking)
-    let sync_result = merge_databases(&temp_db, local_db_path, "local").await?;
-
-    temp_db.close().await;
+    let sync_result = merge_for_push(&temp_db_path, local_db_path, db_opts, config).await?;
 
     // Push back to remote
     transport.push_file(&temp_db_path, &expanded_path)?;
@@ -518,15 +1237,363 @@ pub async fn sync_to_remote(local_db_path: &Path, config: &RemoteConfig) -> Resu
         messages_added: sync_result.messages_added,
         sources_added: sync_result.sources_added,
         direction: SyncDirection::Push,
+        bytes_transferred: None,
+        // Duplicates made by a push live in the remote database.
+        conflicts: sync_result
+            .conflicts
+            .into_iter()
+            .map(|conflict| RemoteConflict {
+                remote: config.name.clone(),
+                duplicate_conversation_id: None,
+                ..conflict
+            })
+            .collect(),
     })
 }
 
+/// Merge the local database into `merged`, the fetched remote database or
+/// a new file, under the `local` namespace. Refuses to merge an encrypted
+/// history into a remote database that is not encrypted.
+async fn merge_for_push(
+    merged: &Path,
+    local_db_path: &Path,
+    db_opts: &DatabaseOptions,
+    config: &RemoteConfig,
+) -> Result<SyncResult> {
+    if db_opts.key.is_some() && merged.exists() && crate::db::is_plain_sqlite(merged)? {
+        return Err(Error::Remote(format!(
+            "The database on {} is not encrypted; refusing to push the encrypted local history into it",
+            config.name
+        )));
+    }
+    let merged_db = Database::open_with(merged, db_opts).await?;
+    let result = merge_databases_with(
+        &merged_db,
+        local_db_path,
+        db_opts,
+        "local",
+        config.conflict_strategy,
+        MergeOrigin::Local,
+    )
+    .await;
+    merged_db.close().await;
+    result
+}
+
+/// HTTP transport to a peer's hstry-api server, for `type = "http"` remotes.
+pub struct HttpTransport {
+    base_url: String,
+    token: Option<String>,
+    client: reqwest::Client,
+}
+
+impl HttpTransport {
+    /// Create a new HTTP transport from remote config, reading the bearer
+    /// token from `token_env`.
+    pub fn from_config(config: &RemoteConfig) -> Result<Self> {
+        let token = match &config.token_env {
+            Some(var) => Some(std::env::var(var).map_err(|_| {
+                Error::Config(format!("remote {} token_env {var} is not set", config.name))
+            })?),
+            None => None,
+        };
+        Ok(Self {
+            base_url: config.host.trim_end_matches('/').to_string(),
+            token: token.filter(|token| !token.is_empty()),
+            client: reqwest::Client::new(),
+        })
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let builder = self
+            .client
+            .request(method, format!("{}{path}", self.base_url));
+        match &self.token {
+            Some(token) => builder.bearer_auth(token),
+            None => builder,
+        }
+    }
+
+    async fn send(&self, builder: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        let response = builder
+            .send()
+            .await
+            .map_err(|e| Error::Remote(format!("HTTP request to {} failed: {e}", self.base_url)))?;
+        let status = response.status();
+        if status == reqwest::StatusCode::UNAUTHORIZED {
+            return Err(Error::Remote(format!(
+                "{} rejected the token; set `token` on the remote",
+                self.base_url
+            )));
+        }
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(Error::Remote(format!(
+                "{} returned {status}: {}",
+                self.base_url,
+                body.trim()
+            )));
+        }
+        Ok(response)
+    }
+
+    async fn get_json<T: serde::de::DeserializeOwned>(&self, path: &str) -> Result<T> {
+        self.send(self.request(reqwest::Method::GET, path))
+            .await?
+            .json()
+            .await
+            .map_err(|e| Error::Remote(format!("Failed parsing response from {path}: {e}")))
+    }
+
+    /// Test that the API answers and accepts the token. Asks for an empty
+    /// export, since `/health` does not check the token.
+    pub async fn test_connection(&self) -> Result<()> {
+        let now = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+        self.send(
+            self.request(reqwest::Method::GET, "/sync/export")
+                .query(&[("since", now)]),
+        )
+        .await
+        .map(|_| ())
+    }
+}
+
+/// One batch of conversations pushed to an hstry-api peer (`POST /sync/import`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PushBatch {
+    /// How the receiver settles conversations that diverged on both sides.
+    #[serde(default)]
+    pub strategy: ConflictStrategy,
+    /// The sender's sources, registered under the `local:` namespace.
+    pub sources: Vec<Source>,
+    pub conversations: Vec<ParsedConversation>,
+}
+
+/// Where the push cursor for an `http` remote is kept: this machine's clock
+/// at the start of its last successful push.
+fn push_cursor_path(remote_name: &str) -> PathBuf {
+    remote_cache_dir().join(format!("{remote_name}.push-cursor"))
+}
+
+/// Ids of conversations changed since `since`, or every conversation when
+/// there is no cursor yet. Sensitive conversations are never synced.
+pub async fn delta_conversation_ids(
+    db: &Database,
+    since: Option<DateTime<Utc>>,
+) -> Result<Vec<Uuid>> {
+    match since {
+        Some(since) => {
+            let sensitive = db.sensitive_conversation_ids().await?;
+            let mut ids = db.changed_conversation_ids(since).await?;
+            ids.retain(|id| !sensitive.contains(id));
+            Ok(ids)
+        }
+        None => Ok(db
+            .list_conversations(ListConversationsOptions {
+                exclude_sensitive: true,
+                ..Default::default()
+            })
+            .await?
+            .into_iter()
+            .map(|conversation| conversation.id)
+            .collect()),
+    }
+}
+
+/// Load a conversation in the export shape used by delta syncs. Sensitive
+/// conversations are treated as missing.
+pub async fn export_conversation(db: &Database, id: Uuid) -> Result<Option<ParsedConversation>> {
+    let Some(conversation) = db.get_conversation(id).await? else {
+        return Ok(None);
+    };
+    if db.is_sensitive(id).await? {
+        return Ok(None);
+    }
+    let messages = db.get_messages(id).await?;
+    Ok(Some(export::to_parsed(&conversation, messages)))
+}
+
+/// Pull what changed on an `http` remote since the last pull, streaming
+/// `GET /sync/export` from its hstry-api server. Same namespacing, conflict
+/// handling and cursor as [`sync_from_remote_delta`].
+pub async fn sync_from_remote_http(
+    local_db: &Database,
+    config: &RemoteConfig,
+) -> Result<SyncResult> {
+    let transport = HttpTransport::from_config(config)?;
+    let mut ingest = DeltaIngest::new(
+        local_db,
+        &config.name,
+        config.conflict_strategy,
+        MergeOrigin::Remote,
+        SyncDirection::Pull,
+    );
+    ingest
+        .add_sources(transport.get_json("/sources").await?)
+        .await?;
+
+    let mut request = transport.request(reqwest::Method::GET, "/sync/export");
+    if let Some(since) = read_delta_cursor(&config.name) {
+        request = request.query(&[("since", since.to_rfc3339_opts(SecondsFormat::Secs, true))]);
+    }
+    let mut response = transport.send(request).await?;
+    let mut buffer: Vec<u8> = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| Error::Remote(format!("Export stream from {} broke: {e}", config.name)))?
+    {
+        buffer.extend_from_slice(&chunk);
+        while let Some(end) = buffer.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=end).collect();
+            ingest.line(&String::from_utf8_lossy(&line)).await?;
+        }
+    }
+    if !buffer.is_empty() {
+        ingest.line(&String::from_utf8_lossy(&buffer)).await?;
+    }
+
+    let (result, started_at) = ingest.finish().await?;
+    let started_at =
+        started_at.ok_or_else(|| Error::Remote("Remote export produced no output".to_string()))?;
+    write_delta_cursor(&config.name, started_at)?;
+    Ok(result)
+}
+
+/// Push what changed locally since the last push to an `http` remote, in
+/// batches of `POST /sync/import`. History pulled from that same remote is
+/// not sent back to it.
+pub async fn sync_to_remote_http(local_db: &Database, config: &RemoteConfig) -> Result<SyncResult> {
+    let transport = HttpTransport::from_config(config)?;
+    let started_at = Utc::now();
+    let echo_prefix = format!("{}:", config.name);
+
+    let sources: Vec<Source> = local_db
+        .list_sources()
+        .await?
+        .into_iter()
+        .filter(|source| !source.id.starts_with(&echo_prefix))
+        .collect();
+    let since = read_cursor(&push_cursor_path(&config.name));
+    let ids = delta_conversation_ids(local_db, since).await?;
+
+    let mut result = SyncResult {
+        remote_name: config.name.clone(),
+        conversations_added: 0,
+        conversations_updated: 0,
+        messages_added: 0,
+        sources_added: 0,
+        direction: SyncDirection::Push,
+        bytes_transferred: Some(0),
+        conflicts: Vec::new(),
+    };
+    let mut batch = Vec::with_capacity(DELTA_BATCH_SIZE);
+    for (i, id) in ids.iter().enumerate() {
+        if let Some(conversation) = export_conversation(local_db, *id).await?
+            && !conversation
+                .source_id
+                .as_deref()
+                .is_some_and(|source_id| source_id.starts_with(&echo_prefix))
+        {
+            batch.push(conversation);
+        }
+        if batch.len() < DELTA_BATCH_SIZE && i + 1 < ids.len() {
+            continue;
+        }
+        let payload = serde_json::to_vec(&PushBatch {
+            strategy: config.conflict_strategy,
+            sources: sources.clone(),
+            conversations: std::mem::take(&mut batch),
+        })?;
+        let bytes = payload.len() as u64;
+        let pushed: SyncResult = transport
+            .send(
+                transport
+                    .request(reqwest::Method::POST, "/sync/import")
+                    .header(reqwest::header::CONTENT_TYPE, "application/json")
+                    .body(payload),
+            )
+            .await?
+            .json()
+            .await
+            .map_err(|e| Error::Remote(format!("Failed parsing push response: {e}")))?;
+        result.conversations_added += pushed.conversations_added;
+        result.conversations_updated += pushed.conversations_updated;
+        result.messages_added += pushed.messages_added;
+        result.sources_added += pushed.sources_added;
+        result.bytes_transferred = result.bytes_transferred.map(|total| total + bytes);
+        // Duplicates made by a push live in the remote database.
+        result
+            .conflicts
+            .extend(pushed.conflicts.into_iter().map(|conflict| RemoteConflict {
+                remote: config.name.clone(),
+                duplicate_conversation_id: None,
+                ..conflict
+            }));
+    }
+
+    write_cursor(&push_cursor_path(&config.name), started_at)?;
+    Ok(result)
+}
+
+/// Apply a batch pushed by a peer to this database, as the receiving side
+/// of [`sync_to_remote_http`]. Sources land under `local:`, like an SSH push.
+pub async fn apply_push_batch(db: &Database, batch: PushBatch) -> Result<SyncResult> {
+    let mut ingest = DeltaIngest::new(
+        db,
+        "local",
+        batch.strategy,
+        MergeOrigin::Local,
+        SyncDirection::Push,
+    );
+    ingest.add_sources(batch.sources).await?;
+    for conversation in batch.conversations {
+        ingest.conversation(conversation).await?;
+    }
+    let (result, _) = ingest.finish().await?;
+    Ok(result)
+}
+
+/// Pull from a remote over its configured transport. SSH remotes copy the
+/// whole database unless `delta` is set; `http` remotes always pull deltas.
+pub async fn pull(local_db: &Database, config: &RemoteConfig, delta: bool) -> Result<SyncResult> {
+    if delta || config.transport == RemoteTransport::Http {
+        sync_from_remote_delta(local_db, config).await
+    } else {
+        sync_from_remote(local_db, config)
+            .await
+            .map(|(_, sync)| sync)
+    }
+}
+
+/// Push to a remote over its configured transport. `db_opts` are the
+/// options `local_db` was opened with; see [`sync_to_remote`].
+pub async fn push(
+    local_db: &Database,
+    local_db_path: &Path,
+    db_opts: &DatabaseOptions,
+    config: &RemoteConfig,
+) -> Result<SyncResult> {
+    match config.transport {
+        RemoteTransport::Ssh => sync_to_remote(local_db_path, db_opts, config).await,
+        RemoteTransport::Http => sync_to_remote_http(local_db, config).await,
+    }
+}
+
 pub async fn search_remote(
     config: &RemoteConfig,
     query: &str,
     opts: &SearchOptions,
 ) -> Result<Vec<SearchHit>> {
-    let transport = SshTransport::from_config(config);
+    let tag_host = |hits: Vec<SearchHit>| -> Vec<SearchHit> {
+        hits.into_iter()
+            .map(|mut hit| {
+                hit.host = Some(config.name.clone());
+                hit
+            })
+            .collect()
+    };
     let input = RemoteSearchInput {
         query: query.to_string(),
         limit: opts.limit,
@@ -542,8 +1609,23 @@ pub async fn search_remote(
             .to_string(),
         ),
     };
+    if config.transport == RemoteTransport::Http {
+        let transport = HttpTransport::from_config(config)?;
+        let hits: Vec<SearchHit> = transport
+            .send(
+                transport
+                    .request(reqwest::Method::GET, "/search")
+                    .query(&input),
+            )
+            .await?
+            .json()
+            .await
+            .map_err(|e| Error::Remote(format!("Failed parsing remote response: {e}")))?;
+        return Ok(tag_host(hits));
+    }
+
+    let transport = SshTransport::from_config(config);
     let payload = serde_json::to_vec(&input)?;
-    let host_name = config.name.clone();
     let host = config.host.clone();
 
     let hits = tokio::task::spawn_blocking(move || {
@@ -594,13 +1676,7 @@ pub async fn search_remote(
     .await
     .map_err(|e| Error::Remote(format!("Remote search join error: {e}")))??;
 
-    Ok(hits
-        .into_iter()
-        .map(|mut hit| {
-            hit.host = Some(host_name.clone());
-            hit
-        })
-        .collect())
+    Ok(tag_host(hits))
 }
 
 pub async fn search_remotes(
@@ -632,6 +1708,9 @@ pub async fn show_remote(
     config: &RemoteConfig,
     conversation_id: &str,
 ) -> Result<ConversationWithMessages> {
+    if config.transport == RemoteTransport::Http {
+        return show_remote_http(config, conversation_id).await;
+    }
     let transport = SshTransport::from_config(config);
     let input = RemoteShowInput {
         id: conversation_id.to_string(),
@@ -691,6 +1770,49 @@ pub async fn show_remote(
     .map_err(|e| Error::Remote(format!("Remote show join error: {e}")))?
 }
 
+/// `show_remote` for an `http` remote. The API's conversation view carries
+/// plain messages, so tool calls and attachments come back empty.
+async fn show_remote_http(
+    config: &RemoteConfig,
+    conversation_id: &str,
+) -> Result<ConversationWithMessages> {
+    #[derive(Deserialize)]
+    struct ApiConversation {
+        #[serde(flatten)]
+        conversation: Conversation,
+        messages: Vec<Message>,
+    }
+
+    let transport = HttpTransport::from_config(config)?;
+    let path = format!("/conversations/{}", urlencode_path_segment(conversation_id));
+    let found: ApiConversation = transport.get_json(&path).await?;
+    Ok(ConversationWithMessages {
+        conversation: found.conversation,
+        messages: found
+            .messages
+            .into_iter()
+            .map(|message| MessageWithExtras {
+                message,
+                tool_calls: Vec::new(),
+                attachments: Vec::new(),
+            })
+            .collect(),
+    })
+}
+
+/// Percent-encode everything outside the URL path-segment safe set.
+fn urlencode_path_segment(segment: &str) -> String {
+    let mut encoded = String::with_capacity(segment.len());
+    for byte in segment.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+            encoded.push(char::from(byte));
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -701,19 +1823,323 @@ mod tests {
         assert!(path.to_string_lossy().contains("laptop.db"));
     }
 
+    #[test]
+    fn delta_command_passes_cursor_as_rfc3339() {
+        assert_eq!(
+            delta_export_command(None),
+            "date -u +%Y-%m-%dT%H:%M:%SZ && hstry export --format jsonl"
+        );
+        let since = DateTime::parse_from_rfc3339("2026-01-02T03:04:05Z")
+            .expect("timestamp")
+            .with_timezone(&Utc);
+        assert!(delta_export_command(Some(since)).ends_with("--since 2026-01-02T03:04:05Z"));
+    }
+
+    #[test]
+    fn only_diverged_threads_go_through_the_strategy() {
+        let thread = |items: &[&'static str]| -> Vec<(String, &'static str)> {
+            items.iter().map(|c| ("user".to_string(), *c)).collect()
+        };
+        let base = thread(&["a", "b"]);
+        assert_eq!(compare_threads(&base, &base), Divergence::Same);
+        assert_eq!(
+            compare_threads(&base, &thread(&["a", "b", "c"])),
+            Divergence::IncomingAhead
+        );
+        assert_eq!(
+            compare_threads(&base, &thread(&["a"])),
+            Divergence::TargetAhead
+        );
+        assert_eq!(
+            compare_threads(&base, &thread(&["a", "x"])),
+            Divergence::Diverged
+        );
+
+        let older = Some(Utc::now() - chrono::Duration::hours(1));
+        let newer = Some(Utc::now());
+        let plan = |divergence, strategy| {
+            plan_merge(divergence, older, newer, strategy, MergeOrigin::Remote)
+        };
+        // Growth on one side fast-forwards regardless of timestamps.
+        assert_eq!(
+            plan(Divergence::IncomingAhead, ConflictStrategy::PreferLocal),
+            (MergeAction::Replace, false)
+        );
+        assert_eq!(
+            plan(Divergence::Diverged, ConflictStrategy::LastWriterWins),
+            (MergeAction::Keep, true)
+        );
+        assert_eq!(
+            plan(Divergence::Diverged, ConflictStrategy::PreferRemote),
+            (MergeAction::Replace, true)
+        );
+        assert_eq!(
+            plan(Divergence::Diverged, ConflictStrategy::Duplicate),
+            (MergeAction::Duplicate, true)
+        );
+    }
+
+    fn parsed(external_id: &str, replies: &[&str]) -> ParsedConversation {
+        ParsedConversation {
+            external_id: Some(external_id.to_string()),
+            readable_id: None,
+            title: Some("Shared".to_string()),
+            created_at: 1_700_000_000_000,
+            updated_at: Some(1_700_000_100_000),
+            model: None,
+            provider: None,
+            workspace: None,
+            tokens_in: None,
+            tokens_out: None,
+            cost_usd: None,
+            messages: replies
+                .iter()
+                .map(|content| crate::parsed::ParsedMessage {
+                    role: "assistant".to_string(),
+                    content: (*content).to_string(),
+                    created_at: None,
+                    model: None,
+                    tokens: None,
+                    cost_usd: None,
+                    parts: None,
+                    tool_calls: None,
+                    metadata: None,
+                    parent_idx: None,
+                })
+                .collect(),
+            metadata: None,
+            version: None,
+            message_count: None,
+            parent_external_id: None,
+            parent_message_idx: None,
+            fork_type: None,
+            source_id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn duplicate_strategy_keeps_both_diverged_copies() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let remote_path = dir.path().join("remote.db");
+        let remote = Database::open(&remote_path).await.expect("open remote");
+        ingest_batch(
+            &remote,
+            "pi",
+            vec![
+                parsed("c1", &["hi", "remote edit"]),
+                parsed("c2", &["x", "y"]),
+            ],
+        )
+        .await
+        .expect("seed remote");
+        remote.close().await;
+
+        let local = Database::open(&dir.path().join("local.db"))
+            .await
+            .expect("open local");
+        ingest_batch(
+            &local,
+            "box:pi",
+            vec![parsed("c1", &["hi", "local edit"]), parsed("c2", &["x"])],
+        )
+        .await
+        .expect("seed local");
+
+        let result = merge_databases(
+            &local,
+            &remote_path,
+            "box",
+            ConflictStrategy::Duplicate,
+            MergeOrigin::Remote,
+        )
+        .await
+        .expect("merge");
+
+        // c2 only grew on the remote, so it fast-forwards without a conflict.
+        assert_eq!(result.conflicts.len(), 1);
+        let conflict = &result.conflicts[0];
+        assert_eq!(conflict.external_id, "c1");
+        assert_eq!(conflict.resolution, "duplicated");
+        assert!(conflict.resolved_at.is_none());
+        let duplicate = local
+            .get_conversation_id("box:pi", "c1~conflict-box")
+            .await
+            .expect("lookup")
+            .expect("duplicate stored");
+        assert_eq!(conflict.duplicate_conversation_id, Some(duplicate));
+
+        let id = local
+            .record_remote_conflict(conflict)
+            .await
+            .expect("record");
+        let open = local
+            .list_remote_conflicts(Some("box"), false)
+            .await
+            .expect("list");
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].id, id);
+        assert_eq!(open[0].duplicate_conversation_id, Some(duplicate));
+
+        let diff = conflict_diff(&local, &open[0])
+            .await
+            .expect("diff")
+            .expect("both copies local");
+        assert_eq!(diff.shared_messages, 1);
+        assert_eq!(
+            diff.remote_first_change,
+            Some(("assistant".to_string(), "remote edit".to_string()))
+        );
+
+        resolve_conflict(&local, &open[0], ConflictChoice::Remote)
+            .await
+            .expect("resolve");
+        assert!(
+            local
+                .get_conversation_id("box:pi", "c1~conflict-box")
+                .await
+                .expect("lookup")
+                .is_none()
+        );
+        assert_eq!(
+            local
+                .get_conversation_id("box:pi", "c1")
+                .await
+                .expect("lookup"),
+            Some(duplicate)
+        );
+        assert!(
+            local
+                .list_remote_conflicts(Some("box"), false)
+                .await
+                .expect("list")
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn push_batch_lands_under_local_namespace() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let db = Database::open(&dir.path().join("peer.db"))
+            .await
+            .expect("open peer");
+        let source = Source {
+            id: "pi".to_string(),
+            adapter: "pi".to_string(),
+            path: None,
+            last_sync_at: None,
+            config: serde_json::json!({}),
+        };
+        let with_source = |mut conversation: ParsedConversation| {
+            conversation.source_id = Some("pi".to_string());
+            conversation
+        };
+
+        let first = apply_push_batch(
+            &db,
+            PushBatch {
+                strategy: ConflictStrategy::Duplicate,
+                sources: vec![source.clone()],
+                conversations: vec![with_source(parsed("c1", &["hi", "one"]))],
+            },
+        )
+        .await
+        .expect("first push");
+        assert_eq!(first.sources_added, 1);
+        assert_eq!(first.conversations_added, 1);
+        assert!(db.get_source("local:pi").await.expect("source").is_some());
+
+        let second = apply_push_batch(
+            &db,
+            PushBatch {
+                strategy: ConflictStrategy::Duplicate,
+                sources: vec![source],
+                conversations: vec![with_source(parsed("c1", &["hi", "two"]))],
+            },
+        )
+        .await
+        .expect("second push");
+        assert_eq!(second.sources_added, 0);
+        assert_eq!(second.conflicts.len(), 1);
+        assert!(
+            db.get_conversation_id("local:pi", "c1~conflict-local")
+                .await
+                .expect("lookup")
+                .is_some()
+        );
+    }
+
     #[test]
     fn test_ssh_transport_command_building() {
         let config = RemoteConfig {
             name: "test".to_string(),
+            transport: RemoteTransport::Ssh,
             host: "user@example.com".to_string(),
+            token_env: None,
             database_path: None,
             port: Some(2222),
             identity_file: Some("~/.ssh/custom_key".to_string()),
             enabled: true,
+            conflict_strategy: ConflictStrategy::default(),
         };
 
         let transport = SshTransport::from_config(&config);
         assert_eq!(transport.host, "user@example.com");
         assert_eq!(transport.port, Some(2222));
     }
+
+    #[cfg(feature = "sqlcipher")]
+    #[tokio::test]
+    async fn ssh_push_keeps_an_encrypted_history_encrypted() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let db_opts = DatabaseOptions {
+            key: Some("secret".to_string()),
+            ..Default::default()
+        };
+        let local_path = dir.path().join("local.db");
+        let local = Database::open_with(&local_path, &db_opts)
+            .await
+            .expect("open local");
+        ingest_batch(&local, "pi", vec![parsed("c1", &["hi"])])
+            .await
+            .expect("seed local");
+        local.close().await;
+        let config = RemoteConfig {
+            name: "box".to_string(),
+            transport: RemoteTransport::Ssh,
+            host: "user@box".to_string(),
+            token_env: None,
+            database_path: None,
+            port: None,
+            identity_file: None,
+            enabled: true,
+            conflict_strategy: ConflictStrategy::default(),
+        };
+
+        let merged = dir.path().join("merged.db");
+        merge_for_push(&merged, &local_path, &db_opts, &config)
+            .await
+            .expect("merge");
+        assert!(!crate::db::is_plain_sqlite(&merged).expect("read header"));
+        let db = Database::open_with(&merged, &db_opts)
+            .await
+            .expect("open merged");
+        assert!(
+            db.get_conversation_id("local:pi", "c1")
+                .await
+                .expect("lookup")
+                .is_some()
+        );
+        db.close().await;
+
+        let plain = dir.path().join("plain.db");
+        Database::open(&plain)
+            .await
+            .expect("open plain")
+            .close()
+            .await;
+        let err = merge_for_push(&plain, &local_path, &db_opts, &config)
+            .await
+            .expect_err("refused");
+        assert!(err.to_string().contains("not encrypted"), "{err}");
+    }
 }
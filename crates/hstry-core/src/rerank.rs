@@ -0,0 +1,274 @@
+//! Re-ranking of search hits by a cross-encoder or an LLM.
+//!
+//! Full-text search returns hits ordered by term overlap. [`Reranker`] sends
+//! the query together with the best hits to a model that reads both and
+//! scores relevance, then reorders the hits by that score. See
+//! [`RerankConfig`] for the supported endpoints.
+
+use serde::Deserialize;
+
+use crate::config::{LlmConfig, RerankBackend, RerankConfig};
+use crate::error::{Error, Result};
+use crate::llm::{Endpoint, LlmUsage, SectionEndpoint};
+use crate::models::SearchHit;
+
+const LLM_INSTRUCTIONS: &str = "You judge how well passages from a chat history answer a \
+    search query. Reply with only a JSON array of integers from 0 (unrelated) to 10 (answers \
+    the query), one per passage, in the order given.";
+
+/// Client for the configured re-ranking endpoint.
+pub struct Reranker {
+    backend: RerankBackend,
+    endpoint: Endpoint,
+    candidates: usize,
+    max_chars: usize,
+}
+
+impl Reranker {
+    /// Cross-encoders need their own `endpoint`; the LLM backend falls back
+    /// to the `[llm]` provider for the `rerank` feature.
+    pub fn from_config(config: &RerankConfig, llm: &LlmConfig) -> Result<Self> {
+        let own = SectionEndpoint {
+            section: "[search.rerank]",
+            url: config.endpoint.as_deref(),
+            model: config.model.as_deref(),
+            api_key_env: config.api_key_env.as_deref(),
+            timeout_secs: config.timeout_secs,
+        };
+        let endpoint = match config.backend {
+            RerankBackend::CrossEncoder => Endpoint::direct(llm, "rerank", own)?,
+            RerankBackend::Llm => Endpoint::for_feature(llm, "rerank", own)?,
+        };
+        Ok(Self {
+            backend: config.backend,
+            endpoint,
+            candidates: config.candidates.max(1),
+            max_chars: config.max_chars.max(1),
+        })
+    }
+
+    /// Usage records of the requests made since the last call.
+    pub fn take_usage(&self) -> Vec<LlmUsage> {
+        self.endpoint.take_usage()
+    }
+
+    /// Number of hits [`Self::rerank`] keeps and re-scores.
+    pub fn candidates(&self) -> usize {
+        self.candidates
+    }
+
+    /// Keep the best [`Self::candidates`] of `hits` (best first) and reorder
+    /// them by model relevance. Each hit's `score` becomes the model score.
+    pub async fn rerank(&self, query: &str, hits: &mut Vec<SearchHit>) -> Result<()> {
+        hits.truncate(self.candidates);
+        if hits.len() < 2 {
+            return Ok(());
+        }
+        let documents: Vec<String> = hits
+            .iter()
+            .map(|hit| document(hit, self.max_chars))
+            .collect();
+        let scores = match self.backend {
+            RerankBackend::CrossEncoder => self.cross_encoder_scores(query, &documents).await?,
+            RerankBackend::Llm => self.llm_scores(query, &documents).await?,
+        };
+        apply_scores(hits, &scores);
+        Ok(())
+    }
+
+    async fn cross_encoder_scores(
+        &self,
+        query: &str,
+        documents: &[String],
+    ) -> Result<Vec<Option<f32>>> {
+        let body = serde_json::json!({
+            "query": query,
+            "documents": documents,
+            "top_n": documents.len(),
+        });
+        let response = self.endpoint.post(body).await?;
+        parse_rerank_response(response, documents.len())
+    }
+
+    async fn llm_scores(&self, query: &str, documents: &[String]) -> Result<Vec<Option<f32>>> {
+        let mut prompt = format!("Query: {query}\n\nPassages:");
+        for (i, document) in documents.iter().enumerate() {
+            prompt.push_str(&format!("\n\n[{}] {document}", i + 1));
+        }
+        let reply = self.endpoint.chat(LLM_INSTRUCTIONS, &prompt).await?;
+        parse_llm_scores(&reply, documents.len())
+    }
+}
+
+/// Text sent to the model for one hit: the conversation title, then the
+/// start of the message.
+fn document(hit: &SearchHit, max_chars: usize) -> String {
+    let mut text = match hit.title.as_deref().filter(|title| !title.is_empty()) {
+        Some(title) => format!("{title}\n{}", hit.content),
+        None => hit.content.clone(),
+    };
+    if let Some((cut, _)) = text.char_indices().nth(max_chars) {
+        text.truncate(cut);
+    }
+    text
+}
+
+#[derive(Deserialize)]
+struct RerankResult {
+    index: usize,
+    #[serde(alias = "score")]
+    relevance_score: f32,
+}
+
+/// Cohere and Jina wrap results in `results`, Voyage in `data`, TEI returns
+/// a bare array.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RerankResponse {
+    Results { results: Vec<RerankResult> },
+    Data { data: Vec<RerankResult> },
+    Bare(Vec<RerankResult>),
+}
+
+fn parse_rerank_response(response: serde_json::Value, count: usize) -> Result<Vec<Option<f32>>> {
+    let results = match serde_json::from_value(response)
+        .map_err(|e| Error::Remote(format!("unexpected re-ranking response: {e}")))?
+    {
+        RerankResponse::Results { results } => results,
+        RerankResponse::Data { data } => data,
+        RerankResponse::Bare(results) => results,
+    };
+    let mut scores = vec![None; count];
+    for result in results {
+        if let Some(score) = scores.get_mut(result.index) {
+            *score = Some(result.relevance_score);
+        }
+    }
+    Ok(scores)
+}
+
+/// Read the JSON array of 0-10 scores from an LLM reply, tolerating text or
+/// a code fence around it.
+fn parse_llm_scores(content: &str, count: usize) -> Result<Vec<Option<f32>>> {
+    let array = content
+        .find('[')
+        .zip(content.rfind(']'))
+        .filter(|(start, end)| start < end)
+        .map(|(start, end)| &content[start..=end])
+        .ok_or_else(|| Error::Remote(format!("LLM reply has no score array: {content}")))?;
+    let scores: Vec<f32> = serde_json::from_str(array)
+        .map_err(|e| Error::Remote(format!("invalid LLM scores {array}: {e}")))?;
+    if scores.len() != count {
+        return Err(Error::Remote(format!(
+            "LLM returned {} scores for {count} passages",
+            scores.len()
+        )));
+    }
+    Ok(scores.into_iter().map(|score| Some(score / 10.0)).collect())
+}
+
+/// Sort `hits` by their new scores, keeping the original order for ties.
+/// Hits the model did not score go last with the lowest score seen.
+fn apply_scores(hits: &mut Vec<SearchHit>, scores: &[Option<f32>]) {
+    let floor = scores
+        .iter()
+        .flatten()
+        .copied()
+        .fold(f32::INFINITY, f32::min);
+    let floor = if floor.is_finite() { floor } else { 0.0 };
+    let mut scored: Vec<(Option<f32>, SearchHit)> = hits
+        .drain(..)
+        .enumerate()
+        .map(|(i, hit)| (scores.get(i).copied().flatten(), hit))
+        .collect();
+    scored.sort_by(|a, b| match (a.0, b.0) {
+        (Some(a), Some(b)) => b.total_cmp(&a),
+        (a, b) => b.is_some().cmp(&a.is_some()),
+    });
+    hits.extend(scored.into_iter().map(|(score, mut hit)| {
+        hit.score = score.unwrap_or(floor);
+        hit
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::MessageRole;
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn hit(content: &str, score: f32) -> SearchHit {
+        SearchHit {
+            message_id: Uuid::new_v4(),
+            conversation_id: Uuid::new_v4(),
+            message_idx: 0,
+            role: MessageRole::User,
+            content: content.to_string(),
+            snippet: String::new(),
+            created_at: None,
+            conv_created_at: Utc::now(),
+            conv_updated_at: None,
+            score,
+            source_id: "codex".to_string(),
+            external_id: None,
+            readable_id: None,
+            title: None,
+            workspace: None,
+            workspace_name: None,
+            source_adapter: "codex".to_string(),
+            source_path: None,
+            host: None,
+            occurrences: None,
+            conversation: None,
+            context: None,
+        }
+    }
+
+    #[test]
+    fn parses_rerank_response_shapes() {
+        let cohere = serde_json::json!({
+            "results": [
+                {"index": 2, "relevance_score": 0.9},
+                {"index": 0, "relevance_score": 0.1},
+            ]
+        });
+        assert_eq!(
+            parse_rerank_response(cohere, 3).unwrap(),
+            vec![Some(0.1), None, Some(0.9)]
+        );
+        let tei = serde_json::json!([{"index": 1, "score": 2.5}, {"index": 7, "score": 1.0}]);
+        assert_eq!(
+            parse_rerank_response(tei, 2).unwrap(),
+            vec![None, Some(2.5)]
+        );
+        assert!(parse_rerank_response(serde_json::json!({"oops": 1}), 1).is_err());
+    }
+
+    #[test]
+    fn parses_llm_scores_inside_fences() {
+        let reply = "```json\n[3, 10, 0]\n```";
+        assert_eq!(
+            parse_llm_scores(reply, 3).unwrap(),
+            vec![Some(0.3), Some(1.0), Some(0.0)]
+        );
+        assert!(parse_llm_scores("[1, 2]", 3).is_err());
+        assert!(parse_llm_scores("no idea", 1).is_err());
+    }
+
+    #[test]
+    fn applies_scores_and_puts_unscored_hits_last() {
+        let mut hits = vec![hit("a", 9.0), hit("b", 8.0), hit("c", 7.0), hit("d", 6.0)];
+        apply_scores(&mut hits, &[Some(0.2), None, Some(0.8), Some(0.2)]);
+        let order: Vec<_> = hits.iter().map(|h| h.content.as_str()).collect();
+        assert_eq!(order, vec!["c", "a", "d", "b"]);
+        assert_eq!(hits[3].score, 0.2);
+    }
+
+    #[test]
+    fn documents_are_titled_and_truncated() {
+        let mut h = hit("héllo world", 1.0);
+        h.title = Some("Greeting".to_string());
+        assert_eq!(document(&h, 11), "Greeting\nhé");
+    }
+}
@@ -0,0 +1,93 @@
+//! Earlier versions of messages that were edited or regenerated.
+//!
+//! Adapters report a message again under the same index when its text
+//! changes: the user edited a prompt, or the assistant reply was
+//! regenerated. Before the new text overwrites the old, the write paths
+//! keep the old text as a revision in `message_events`, so
+//! `hstry show --history` and the TUI can show how the message changed.
+//! Text that only grew (a reply still streaming when it was first synced)
+//! is not a revision.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::models::MessageRole;
+
+/// How a message came to be replaced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RevisionKind {
+    /// Text written by a person (a prompt or system message) was changed.
+    Edit,
+    /// A reply was produced again.
+    Regenerate,
+}
+
+impl RevisionKind {
+    /// Stored in `message_events.kind`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Edit => "edit",
+            Self::Regenerate => "regenerate",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "edit" => Some(Self::Edit),
+            "regenerate" => Some(Self::Regenerate),
+            _ => None,
+        }
+    }
+
+    /// The revision made by replacing `old` with `new` in a message with
+    /// `role`, or `None` when no text was lost.
+    pub fn between(role: &MessageRole, old: &str, new: &str) -> Option<Self> {
+        if old.trim().is_empty() || new.starts_with(old) {
+            return None;
+        }
+        Some(match role {
+            MessageRole::User | MessageRole::System => Self::Edit,
+            _ => Self::Regenerate,
+        })
+    }
+}
+
+/// An earlier version of a message. See [`crate::Database::message_revisions`].
+#[derive(Debug, Clone, Serialize)]
+pub struct MessageRevision {
+    pub idx: i32,
+    pub kind: RevisionKind,
+    pub role: MessageRole,
+    pub content: String,
+    pub model: Option<String>,
+    /// When the replaced text was first written.
+    pub created_at: Option<DateTime<Utc>>,
+    /// When it was replaced.
+    pub replaced_at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn appended_text_is_not_a_revision() {
+        let role = MessageRole::Assistant;
+        assert_eq!(RevisionKind::between(&role, "Hello", "Hello"), None);
+        assert_eq!(RevisionKind::between(&role, "Hello", "Hello, world"), None);
+        assert_eq!(RevisionKind::between(&role, "", "Hello"), None);
+    }
+
+    #[test]
+    fn changed_text_is_an_edit_or_regeneration_by_role() {
+        assert_eq!(
+            RevisionKind::between(&MessageRole::User, "Fix the bug", "Fix the tests"),
+            Some(RevisionKind::Edit)
+        );
+        assert_eq!(
+            RevisionKind::between(&MessageRole::Assistant, "Try A", "Try B"),
+            Some(RevisionKind::Regenerate)
+        );
+    }
+}
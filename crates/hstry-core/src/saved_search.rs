@@ -0,0 +1,137 @@
+//! Named searches.
+//!
+//! `hstry search --save <name>` stores the query and its filters;
+//! `hstry search --saved <name>` and the TUI's Saved view run them again.
+//! Dates are kept as typed, so a saved `--after 1w` always means the last
+//! week.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::dates::parse_date_filter;
+use crate::db::{SearchMode, SearchOptions};
+use crate::error::{Error, Result};
+use crate::models::{MessageRole, SearchHit};
+
+/// A stored search.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedSearch {
+    pub name: String,
+    pub query: String,
+    pub filters: SavedFilters,
+    pub created_at: DateTime<Utc>,
+}
+
+/// The filters of a [`SavedSearch`], as given on the command line.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedFilters {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workspace: Option<String>,
+    /// `natural` or `code`; unset detects the mode from the query.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub harness: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub no_tools: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub starred: bool,
+}
+
+impl SavedSearch {
+    /// Search options for running the search now.
+    pub fn options(&self) -> Result<SearchOptions> {
+        let filters = &self.filters;
+        let mode = match filters.mode.as_deref() {
+            None | Some("auto") => SearchMode::Auto,
+            Some("natural") => SearchMode::NaturalLanguage,
+            Some("code") => SearchMode::Code,
+            Some(other) => {
+                return Err(Error::Config(format!(
+                    "saved search '{}' has unknown mode '{other}'",
+                    self.name
+                )));
+            }
+        };
+        Ok(SearchOptions {
+            source_id: filters.source.clone(),
+            workspace: filters.workspace.clone(),
+            mode,
+            after: filters
+                .after
+                .as_deref()
+                .map(parse_date_filter)
+                .transpose()?,
+            before: filters
+                .before
+                .as_deref()
+                .map(parse_date_filter)
+                .transpose()?,
+            // Several roles are filtered afterwards, see `keeps`.
+            role: match filters.roles.as_slice() {
+                [role] => Some(role.clone()),
+                _ => None,
+            },
+            model: filters.model.clone(),
+            harness: filters.harness.clone(),
+            tag: filters.tag.clone(),
+            starred: filters.starred,
+            ..Default::default()
+        })
+    }
+
+    /// Whether `hit` passes the filters [`Self::options`] cannot express.
+    pub fn keeps(&self, hit: &SearchHit) -> bool {
+        let filters = &self.filters;
+        (filters.roles.is_empty() || filters.roles.iter().any(|r| *r == hit.role.to_string()))
+            && !(filters.no_tools && hit.role == MessageRole::Tool)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn options_resolve_relative_dates_at_run_time() {
+        let saved = SavedSearch {
+            name: "rust-errors".to_string(),
+            query: "borrow checker".to_string(),
+            filters: SavedFilters {
+                mode: Some("code".to_string()),
+                roles: vec!["assistant".to_string()],
+                after: Some("1w".to_string()),
+                ..Default::default()
+            },
+            created_at: Utc::now(),
+        };
+        let opts = saved.options().unwrap();
+        assert_eq!(opts.mode, SearchMode::Code);
+        assert_eq!(opts.role.as_deref(), Some("assistant"));
+        let after = opts.after.unwrap();
+        assert!(
+            (Utc::now() - chrono::Duration::weeks(1) - after)
+                .num_seconds()
+                .abs()
+                < 5
+        );
+
+        let json = serde_json::to_value(&saved.filters).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"mode": "code", "roles": ["assistant"], "after": "1w"})
+        );
+    }
+}
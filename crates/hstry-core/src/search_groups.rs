@@ -0,0 +1,106 @@
+//! Search hits grouped by conversation.
+//!
+//! A long thread that keeps coming back to a topic can fill a whole page of
+//! results. [`group_by_conversation`] folds the hits into one entry per
+//! conversation, ranked by a combined score that rewards both a good best
+//! hit and many hits.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::SearchHit;
+
+/// Damping constant of the reciprocal-rank sum; the usual value for
+/// reciprocal rank fusion.
+const RANK_CONSTANT: f32 = 60.0;
+
+/// The hits of one conversation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationHits {
+    pub conversation_id: Uuid,
+    /// Sum of `1 / (60 + rank)` over the hits; higher is better.
+    pub score: f32,
+    pub hit_count: usize,
+    /// The hits, best first.
+    pub hits: Vec<SearchHit>,
+}
+
+impl ConversationHits {
+    /// The best hit, which carries the conversation's title and ids.
+    pub fn best(&self) -> &SearchHit {
+        &self.hits[0]
+    }
+}
+
+/// Group `hits`, given best first, by conversation. Groups come back best
+/// first.
+pub fn group_by_conversation(hits: Vec<SearchHit>) -> Vec<ConversationHits> {
+    let mut groups: Vec<ConversationHits> = Vec::new();
+    let mut positions: HashMap<Uuid, usize> = HashMap::new();
+    for (rank, hit) in hits.into_iter().enumerate() {
+        let score = 1.0 / (RANK_CONSTANT + rank as f32 + 1.0);
+        let position = *positions.entry(hit.conversation_id).or_insert_with(|| {
+            groups.push(ConversationHits {
+                conversation_id: hit.conversation_id,
+                score: 0.0,
+                hit_count: 0,
+                hits: Vec::new(),
+            });
+            groups.len() - 1
+        });
+        let group = &mut groups[position];
+        group.score += score;
+        group.hit_count += 1;
+        group.hits.push(hit);
+    }
+    // Stable, so ties keep the order of their best hits.
+    groups.sort_by(|a, b| b.score.total_cmp(&a.score));
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::MessageRole;
+    use chrono::Utc;
+
+    fn hit(conversation: u128, idx: i32) -> SearchHit {
+        SearchHit {
+            message_id: Uuid::new_v4(),
+            conversation_id: Uuid::from_u128(conversation),
+            message_idx: idx,
+            role: MessageRole::User,
+            content: String::new(),
+            snippet: String::new(),
+            created_at: None,
+            conv_created_at: Utc::now(),
+            conv_updated_at: None,
+            score: 0.0,
+            source_id: "codex".to_string(),
+            external_id: None,
+            readable_id: None,
+            title: None,
+            workspace: None,
+            workspace_name: None,
+            source_adapter: "codex".to_string(),
+            source_path: None,
+            host: None,
+            occurrences: None,
+            conversation: None,
+            context: None,
+        }
+    }
+
+    #[test]
+    fn many_hits_outrank_a_single_better_one() {
+        let groups =
+            group_by_conversation(vec![hit(1, 0), hit(2, 4), hit(2, 7), hit(3, 1), hit(2, 9)]);
+        let order: Vec<u128> = groups.iter().map(|g| g.conversation_id.as_u128()).collect();
+        assert_eq!(order, [2, 1, 3]);
+        assert_eq!(groups[0].hit_count, 3);
+        assert_eq!(groups[0].best().message_idx, 4);
+        assert_eq!(groups[1].hit_count, 1);
+    }
+}
@@ -0,0 +1,225 @@
+//! Splitting stream-logged conversations into sessions.
+//!
+//! Terminal-capture sources can log a whole day as one conversation.
+//! [`boundaries`] finds where the sessions in it start: at a user message
+//! after a long enough pause, or at one matching a prompt pattern.
+//! [`segment_source`] previews or applies the split for every conversation
+//! of a source through [`Database::split_conversation`].
+
+use chrono::{DateTime, Duration, Utc};
+use regex::Regex;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::db::{Database, ListConversationsOptions};
+use crate::error::{Error, Result};
+use crate::models::{Message, MessageRole};
+use crate::pack::{one_line, truncate};
+
+/// Where sessions start.
+#[derive(Debug, Clone)]
+pub struct SegmentRules {
+    /// A user message at least this long after the previous message starts
+    /// a session.
+    pub gap: Option<Duration>,
+    /// A user message matching any of these starts a session.
+    pub patterns: Vec<Regex>,
+    /// Sessions shorter than this are kept with the one before them.
+    pub min_messages: usize,
+}
+
+impl SegmentRules {
+    pub fn new(gap: Option<Duration>, patterns: &[String], min_messages: usize) -> Result<Self> {
+        let patterns = patterns
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern)
+                    .map_err(|e| Error::Config(format!("invalid pattern {pattern:?}: {e}")))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            gap,
+            patterns,
+            min_messages: min_messages.max(1),
+        })
+    }
+
+    fn starts_session(&self, previous: &Message, message: &Message) -> bool {
+        if message.role != MessageRole::User {
+            return false;
+        }
+        let paused = match (self.gap, previous.created_at, message.created_at) {
+            (Some(gap), Some(before), Some(at)) => at - before >= gap,
+            _ => false,
+        };
+        paused
+            || self
+                .patterns
+                .iter()
+                .any(|pattern| pattern.is_match(&message.content))
+    }
+}
+
+/// Positions in `messages` where the second, third, ... session start.
+pub fn boundaries(messages: &[Message], rules: &SegmentRules) -> Vec<usize> {
+    let mut starts = Vec::new();
+    let mut last = 0;
+    for (i, pair) in messages.windows(2).enumerate() {
+        let position = i + 1;
+        if position - last >= rules.min_messages && rules.starts_session(&pair[0], &pair[1]) {
+            starts.push(position);
+            last = position;
+        }
+    }
+    // A short tail stays with the session before it.
+    if messages.len() - last < rules.min_messages {
+        starts.pop();
+    }
+    starts
+}
+
+/// One session of a conversation.
+#[derive(Debug, Clone, Serialize)]
+pub struct Session {
+    pub messages: usize,
+    pub started_at: Option<DateTime<Utc>>,
+    pub ended_at: Option<DateTime<Utc>>,
+    /// The first user message, on one line.
+    pub first_prompt: Option<String>,
+}
+
+/// The sessions found in one conversation.
+#[derive(Debug, Clone, Serialize)]
+pub struct SegmentPlan {
+    pub conversation_id: Uuid,
+    pub readable_id: Option<String>,
+    pub title: Option<String>,
+    pub sessions: Vec<Session>,
+    /// Conversations the later sessions were moved into; empty on a dry run.
+    pub created: Vec<Uuid>,
+}
+
+/// Find the sessions in every conversation of `source_id` and, unless
+/// `dry_run`, split the conversations that have more than one.
+/// Conversations with branches are left alone.
+pub async fn segment_source(
+    db: &Database,
+    source_id: &str,
+    rules: &SegmentRules,
+    dry_run: bool,
+) -> Result<Vec<SegmentPlan>> {
+    let conversations = db
+        .list_conversations(ListConversationsOptions {
+            source_id: Some(source_id.to_string()),
+            ..Default::default()
+        })
+        .await?;
+    let mut plans = Vec::new();
+    for conv in conversations {
+        if db.has_message_branches(conv.id).await? {
+            continue;
+        }
+        let messages = db.get_messages(conv.id).await?;
+        let starts = boundaries(&messages, rules);
+        if starts.is_empty() {
+            continue;
+        }
+        let sessions = std::iter::once(0)
+            .chain(starts.iter().copied())
+            .zip(
+                starts
+                    .iter()
+                    .copied()
+                    .chain(std::iter::once(messages.len())),
+            )
+            .map(|(start, end)| session(&messages[start..end]))
+            .collect();
+        let created = if dry_run {
+            Vec::new()
+        } else {
+            let idx: Vec<i32> = starts.iter().map(|&i| messages[i].idx).collect();
+            db.split_conversation(conv.id, &idx).await?
+        };
+        plans.push(SegmentPlan {
+            conversation_id: conv.id,
+            readable_id: conv.readable_id,
+            title: conv.title,
+            sessions,
+            created,
+        });
+    }
+    Ok(plans)
+}
+
+fn session(messages: &[Message]) -> Session {
+    Session {
+        messages: messages.len(),
+        started_at: messages.iter().find_map(|m| m.created_at),
+        ended_at: messages.iter().rev().find_map(|m| m.created_at),
+        first_prompt: messages
+            .iter()
+            .find(|m| m.role == MessageRole::User && !m.content.trim().is_empty())
+            .map(|m| truncate(&one_line(&m.content), 80)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(idx: i32, role: MessageRole, content: &str, minute: i64) -> Message {
+        let start = DateTime::parse_from_rfc3339("2026-03-09T08:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        Message {
+            id: Uuid::new_v4(),
+            conversation_id: Uuid::nil(),
+            idx,
+            role,
+            content: content.to_string(),
+            parts_json: serde_json::json!([]),
+            created_at: Some(start + Duration::minutes(minute)),
+            model: None,
+            tokens: None,
+            cost_usd: None,
+            metadata: serde_json::json!({}),
+            sender: None,
+            provider: None,
+            harness: None,
+            client_id: None,
+            parent_message_id: None,
+        }
+    }
+
+    fn day() -> Vec<Message> {
+        use MessageRole::{Assistant, User};
+        vec![
+            message(0, User, "ls", 0),
+            message(1, Assistant, "src", 1),
+            message(2, User, "cargo test", 2),
+            // An assistant reply after a pause never starts a session.
+            message(3, Assistant, "ok", 60),
+            message(4, User, "git status", 180),
+            message(5, Assistant, "clean", 181),
+            message(6, User, "$ ssh prod", 182),
+            message(7, Assistant, "connected", 183),
+            message(8, User, "uptime", 400),
+        ]
+    }
+
+    #[test]
+    fn sessions_start_after_pauses_and_at_prompts() {
+        let messages = day();
+        let by_gap = SegmentRules::new(Some(Duration::minutes(30)), &[], 1).unwrap();
+        assert_eq!(boundaries(&messages, &by_gap), [4, 8]);
+
+        let by_both =
+            SegmentRules::new(Some(Duration::minutes(30)), &[r"^\$ ssh".to_string()], 2).unwrap();
+        // The one-message tail stays with the session before it.
+        assert_eq!(boundaries(&messages, &by_both), [4, 6]);
+
+        let by_pattern = SegmentRules::new(None, &[r"^\$ ".to_string()], 2).unwrap();
+        assert_eq!(boundaries(&messages, &by_pattern), [6]);
+        assert!(SegmentRules::new(None, &["(".to_string()], 1).is_err());
+    }
+}
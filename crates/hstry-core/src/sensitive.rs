@@ -0,0 +1,206 @@
+//! Encryption of sensitive conversations.
+//!
+//! Messages of conversations matched by [`SensitiveConfig`] are sealed
+//! before they are stored: content and parts are encrypted to the database's
+//! age X25519 key and the plaintext columns stay empty, so the full-text
+//! index never sees them. The private half of the key is stored in the
+//! database wrapped with a passphrase. `hstry sensitive unlock` unwraps it
+//! into a short-lived session file that
+//! [`Database::open_config`](crate::db::Database::open_config) picks up.
+//!
+//! [`SensitiveConfig`]: crate::config::SensitiveConfig
+
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use age::secrecy::{ExposeSecret, SecretString};
+use age::x25519::{Identity, Recipient};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::{Error, Result};
+
+/// Content of a sealed message read without the key.
+pub const LOCKED_CONTENT: &str = "[sensitive: run `hstry sensitive unlock` to read]";
+
+/// A new sensitive key: the public recipient messages are sealed to and the
+/// passphrase-wrapped identity that opens them.
+pub struct WrappedKey {
+    pub recipient: Recipient,
+    pub identity: Vec<u8>,
+}
+
+/// Generate a key pair whose identity is wrapped with `passphrase`.
+pub fn generate_key(passphrase: &str) -> Result<WrappedKey> {
+    generate_key_with_work_factor(passphrase, None)
+}
+
+/// [`generate_key`] with an explicit scrypt work factor (`2^log_n`); `None`
+/// targets about a second on this machine.
+fn generate_key_with_work_factor(passphrase: &str, log_n: Option<u8>) -> Result<WrappedKey> {
+    if passphrase.is_empty() {
+        return Err(Error::Config(
+            "the passphrase must not be empty".to_string(),
+        ));
+    }
+    let identity = Identity::generate();
+    let mut wrapper = age::scrypt::Recipient::new(SecretString::from(passphrase.to_string()));
+    if let Some(log_n) = log_n {
+        wrapper.set_work_factor(log_n);
+    }
+    let wrapped = age::encrypt(&wrapper, identity.to_string().expose_secret().as_bytes())
+        .map_err(|e| Error::Other(format!("failed to wrap sensitive key: {e}")))?;
+    Ok(WrappedKey {
+        recipient: identity.to_public(),
+        identity: wrapped,
+    })
+}
+
+/// Unwrap a stored identity with `passphrase`.
+pub fn unwrap_identity(wrapped: &[u8], passphrase: &str) -> Result<Identity> {
+    let unwrapper = age::scrypt::Identity::new(SecretString::from(passphrase.to_string()));
+    let secret = age::decrypt(&unwrapper, wrapped)
+        .map_err(|_| Error::Config("wrong passphrase for the sensitive key".to_string()))?;
+    parse_identity(&String::from_utf8_lossy(&secret))
+}
+
+fn parse_identity(text: &str) -> Result<Identity> {
+    Identity::from_str(text.trim()).map_err(|e| Error::Other(format!("invalid sensitive key: {e}")))
+}
+
+/// What a sealed message encrypts.
+#[derive(Serialize, Deserialize)]
+struct Sealed {
+    content: String,
+    parts: serde_json::Value,
+}
+
+/// Encrypt a message's content and parts to `recipient`.
+pub fn seal(recipient: &Recipient, content: &str, parts: &serde_json::Value) -> Result<Vec<u8>> {
+    let plaintext = serde_json::to_vec(&Sealed {
+        content: content.to_string(),
+        parts: parts.clone(),
+    })?;
+    age::encrypt(recipient, &plaintext)
+        .map_err(|e| Error::Other(format!("failed to seal message: {e}")))
+}
+
+/// Decrypt a sealed message into its content and parts.
+pub fn unseal(identity: &Identity, sealed: &[u8]) -> Result<(String, serde_json::Value)> {
+    let plaintext = age::decrypt(identity, sealed)
+        .map_err(|e| Error::Other(format!("failed to open sealed message: {e}")))?;
+    let sealed: Sealed = serde_json::from_slice(&plaintext)?;
+    Ok((sealed.content, sealed.parts))
+}
+
+/// An unlocked key and when it locks again.
+#[derive(Serialize, Deserialize)]
+struct Session {
+    expires_at: DateTime<Utc>,
+    identity: String,
+}
+
+/// Session file for the database at `db_path`: in the runtime directory
+/// (usually a tmpfs) when there is one, so it does not outlive a reboot.
+pub fn session_path(db_path: &Path) -> PathBuf {
+    let digest = Sha256::digest(db_path.to_string_lossy().as_bytes());
+    let name: String = digest[..8].iter().map(|b| format!("{b:02x}")).collect();
+    dirs::runtime_dir()
+        .map(|dir| dir.join("hstry"))
+        .unwrap_or_else(crate::paths::state_dir)
+        .join(format!("sensitive-{name}.session"))
+}
+
+/// Keep `identity` available to the database at `db_path` until
+/// `expires_at`. The file is readable by the current user only.
+pub fn write_session(
+    db_path: &Path,
+    identity: &Identity,
+    expires_at: DateTime<Utc>,
+) -> Result<PathBuf> {
+    let path = session_path(db_path);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let session = Session {
+        expires_at,
+        identity: identity.to_string().expose_secret().to_string(),
+    };
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let file = options.open(&path)?;
+    serde_json::to_writer(file, &session)?;
+    Ok(path)
+}
+
+/// The unlocked identity for the database at `db_path` and its expiry, or
+/// `None` when locked. An expired session file is removed.
+pub fn read_session(db_path: &Path) -> Option<(Identity, DateTime<Utc>)> {
+    let path = session_path(db_path);
+    let session: Session = serde_json::from_slice(&std::fs::read(&path).ok()?).ok()?;
+    if session.expires_at <= Utc::now() {
+        let _ = std::fs::remove_file(&path);
+        return None;
+    }
+    let identity = parse_identity(&session.identity).ok()?;
+    Some((identity, session.expires_at))
+}
+
+/// Lock the database at `db_path` again. Returns whether it was unlocked.
+pub fn clear_session(db_path: &Path) -> Result<bool> {
+    match std::fs::remove_file(session_path(db_path)) {
+        Ok(()) => Ok(true),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sealed_messages_open_with_the_unwrapped_key() {
+        let key = generate_key_with_work_factor("correct horse", Some(2)).unwrap();
+        let parts = serde_json::json!([{"type": "text", "text": "secret"}]);
+        let sealed = seal(&key.recipient, "secret", &parts).unwrap();
+        assert!(!String::from_utf8_lossy(&sealed).contains("secret"));
+
+        let identity = unwrap_identity(&key.identity, "correct horse").unwrap();
+        assert_eq!(
+            unseal(&identity, &sealed).unwrap(),
+            ("secret".to_string(), parts)
+        );
+        assert!(unwrap_identity(&key.identity, "wrong").is_err());
+        assert!(unseal(&Identity::generate(), &sealed).is_err());
+    }
+
+    #[test]
+    fn sessions_are_per_database_and_expire() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = dir.path().join("hstry.db");
+        let other = dir.path().join("other.db");
+        assert_ne!(session_path(&db), session_path(&other));
+
+        temp_env::with_var("XDG_RUNTIME_DIR", Some(dir.path()), || {
+            let identity = Identity::generate();
+            let until = Utc::now() + chrono::Duration::minutes(5);
+            write_session(&db, &identity, until).unwrap();
+            let (read, expires_at) = read_session(&db).unwrap();
+            assert_eq!(
+                read.to_public().to_string(),
+                identity.to_public().to_string()
+            );
+            assert_eq!(expires_at, until);
+            assert!(read_session(&other).is_none());
+
+            write_session(&db, &identity, Utc::now() - chrono::Duration::seconds(1)).unwrap();
+            assert!(read_session(&db).is_none());
+            assert!(!session_path(&db).exists());
+            assert!(!clear_session(&db).unwrap());
+        });
+    }
+}
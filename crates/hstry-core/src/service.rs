@@ -87,6 +87,7 @@ pub fn search_request_to_opts(request: &proto::SearchRequest) -> SearchOptions {
         } else {
             Some(request.tag.clone())
         },
+        starred: request.starred,
     }
 }
 
@@ -104,6 +105,7 @@ fn search_request_from_opts(query: &str, opts: &SearchOptions) -> proto::SearchR
         model: opts.model.clone().unwrap_or_default(),
         harness: opts.harness.clone().unwrap_or_default(),
         tag: opts.tag.clone().unwrap_or_default(),
+        starred: opts.starred,
     }
 }
 
@@ -262,6 +264,7 @@ pub fn hit_to_proto(hit: &SearchHit) -> proto::SearchHit {
         readable_id: hit.readable_id.clone().unwrap_or_default(),
         title: hit.title.clone().unwrap_or_default(),
         workspace: hit.workspace.clone().unwrap_or_default(),
+        workspace_name: hit.workspace_name.clone().unwrap_or_default(),
         source_adapter: hit.source_adapter.clone(),
         source_path: hit.source_path.clone().unwrap_or_default(),
         host: hit.host.clone().unwrap_or_default(),
@@ -301,6 +304,11 @@ pub fn hit_from_proto(hit: proto::SearchHit) -> SearchHit {
         } else {
             Some(hit.workspace)
         },
+        workspace_name: if hit.workspace_name.is_empty() {
+            None
+        } else {
+            Some(hit.workspace_name)
+        },
         source_adapter: hit.source_adapter,
         source_path: if hit.source_path.is_empty() {
             None
@@ -313,6 +321,8 @@ pub fn hit_from_proto(hit: proto::SearchHit) -> SearchHit {
             Some(hit.host)
         },
         occurrences: None,
+        conversation: None,
+        context: None,
     }
 }
 
@@ -390,6 +400,7 @@ pub fn message_from_proto(proto: proto::Message, conversation_id: Uuid) -> Messa
         provider: proto.provider,
         harness: proto.harness,
         client_id: proto.client_id,
+        parent_message_id: None,
     }
 }
 
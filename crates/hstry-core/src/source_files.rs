@@ -0,0 +1,185 @@
+//! File manifests for incremental sync.
+//!
+//! Before a sync the files under a source's path are listed with their size
+//! and modification time. Files matching the manifest stored by the last
+//! completed sync (under `files` in the source's config) are passed to the
+//! adapter as unchanged, so it can skip reading them. A file whose timestamp
+//! moved but whose size did not is hashed and compared with its stored
+//! SHA-256, so touching or copying a file does not force a reparse.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::Read as _;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Key of the manifest in `sources.config`.
+pub const MANIFEST_KEY: &str = "files";
+
+/// Size and timestamp of one file when it was last synced.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileState {
+    pub mtime_ms: i64,
+    pub size: u64,
+    /// Hex SHA-256 of the content. Only computed for files whose timestamp
+    /// changed while their size did not.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+/// File path to its state, for every file under a source path.
+pub type FileManifest = BTreeMap<String, FileState>;
+
+/// Result of [`scan`].
+#[derive(Debug, Clone, Default)]
+pub struct FileScan {
+    /// Manifest to store once the sync completes.
+    pub manifest: FileManifest,
+    /// Files whose content is the same as at the last completed sync.
+    pub unchanged: Vec<String>,
+}
+
+/// The manifest stored in a source's `config`, empty when there is none.
+pub fn stored_manifest(config: &serde_json::Value) -> FileManifest {
+    config
+        .get(MANIFEST_KEY)
+        .and_then(|files| serde_json::from_value(files.clone()).ok())
+        .unwrap_or_default()
+}
+
+/// List the files under `path` (or `path` itself when it is a file) and
+/// compare them with `previous`. Symlinks are not followed.
+pub fn scan(path: &Path, previous: &FileManifest) -> std::io::Result<FileScan> {
+    let mut files = Vec::new();
+    collect_files(path, &mut files)?;
+
+    let mut result = FileScan::default();
+    for file in files {
+        let metadata = std::fs::metadata(&file)?;
+        let mtime_ms = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |since| {
+                i64::try_from(since.as_millis()).unwrap_or(i64::MAX)
+            });
+        let mut state = FileState {
+            mtime_ms,
+            size: metadata.len(),
+            sha256: None,
+        };
+        let key = file.to_string_lossy().into_owned();
+        let unchanged = match previous.get(&key) {
+            Some(prev) if prev.size == state.size && prev.mtime_ms == state.mtime_ms => {
+                state.sha256.clone_from(&prev.sha256);
+                true
+            }
+            Some(prev) if prev.size == state.size => {
+                state.sha256 = Some(sha256_file(&file)?);
+                prev.sha256.is_some() && prev.sha256 == state.sha256
+            }
+            _ => false,
+        };
+        if unchanged {
+            result.unchanged.push(key.clone());
+        }
+        result.manifest.insert(key, state);
+    }
+    Ok(result)
+}
+
+fn collect_files(path: &Path, files: &mut Vec<std::path::PathBuf>) -> std::io::Result<()> {
+    let file_type = std::fs::symlink_metadata(path)?.file_type();
+    if file_type.is_file() {
+        files.push(path.to_path_buf());
+    } else if file_type.is_dir() {
+        for entry in std::fs::read_dir(path)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                collect_files(&entry.path(), files)?;
+            } else if file_type.is_file() {
+                files.push(entry.path());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn sha256_file(path: &Path) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .fold(String::with_capacity(64), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set_mtime(path: &Path, secs: u64) {
+        let file = std::fs::File::options()
+            .write(true)
+            .open(path)
+            .expect("open");
+        file.set_modified(UNIX_EPOCH + std::time::Duration::from_secs(secs))
+            .expect("set mtime");
+    }
+
+    #[test]
+    fn scan_reports_files_unchanged_by_timestamp_or_content() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let nested = dir.path().join("project");
+        std::fs::create_dir(&nested).expect("mkdir");
+        let same = nested.join("same.jsonl");
+        let touched = nested.join("touched.jsonl");
+        let edited = nested.join("edited.jsonl");
+        for (path, content) in [(&same, "one"), (&touched, "two"), (&edited, "three")] {
+            std::fs::write(path, content).expect("write");
+            set_mtime(path, 1_700_000_000);
+        }
+
+        let first = scan(dir.path(), &FileManifest::new()).expect("scan");
+        assert_eq!(first.manifest.len(), 3);
+        assert!(first.unchanged.is_empty());
+
+        // Same size, new timestamp: hashed, but there is no stored hash yet.
+        set_mtime(&touched, 1_700_000_100);
+        std::fs::write(&edited, "THREE").expect("write");
+        set_mtime(&edited, 1_700_000_100);
+        let second = scan(dir.path(), &first.manifest).expect("scan");
+        assert_eq!(second.unchanged, [same.to_string_lossy()]);
+
+        // Touched again: the stored hash shows the content did not change.
+        set_mtime(&touched, 1_700_000_200);
+        std::fs::write(&edited, "three").expect("write");
+        set_mtime(&edited, 1_700_000_200);
+        let third = scan(dir.path(), &second.manifest).expect("scan");
+        let mut unchanged = third.unchanged.clone();
+        unchanged.sort();
+        assert_eq!(
+            unchanged,
+            [same.to_string_lossy(), touched.to_string_lossy()]
+        );
+
+        let config = serde_json::json!({ MANIFEST_KEY: third.manifest });
+        assert_eq!(stored_manifest(&config), third.manifest);
+    }
+}
@@ -0,0 +1,248 @@
+//! Per-source filters applied while syncing.
+//!
+//! A source's `[sources.filter]` is mirrored into its stored config under
+//! `filter`. [`SourceFilter`] compiles it once per sync and decides, for
+//! every parsed conversation, whether it is written at all: by the session
+//! file it came from (`metadata.file`), by workspace, and by how many user
+//! and assistant messages it has.
+
+use std::path::Path;
+
+use regex::Regex;
+
+use crate::config::{Config, SourceFilterConfig};
+use crate::error::{Error, Result};
+use crate::parsed::ParsedConversation;
+
+/// Key of the filter in `sources.config`.
+pub const FILTER_KEY: &str = "filter";
+
+/// A compiled [`SourceFilterConfig`].
+#[derive(Debug, Clone, Default)]
+pub struct SourceFilter {
+    include: Vec<Glob>,
+    exclude: Vec<Glob>,
+    workspaces: Vec<String>,
+    exclude_workspaces: Vec<String>,
+    min_messages: usize,
+}
+
+impl SourceFilter {
+    pub fn new(config: &SourceFilterConfig) -> Result<Self> {
+        let globs = |patterns: &[String]| -> Result<Vec<Glob>> {
+            patterns.iter().map(|p| Glob::new(p)).collect()
+        };
+        Ok(Self {
+            include: globs(&config.include)?,
+            exclude: globs(&config.exclude)?,
+            workspaces: config.workspaces.clone(),
+            exclude_workspaces: config.exclude_workspaces.clone(),
+            min_messages: config.min_messages,
+        })
+    }
+
+    /// The filter stored in a source's `config`; keeps everything when there
+    /// is none.
+    pub fn from_source_config(config: &serde_json::Value) -> Result<Self> {
+        match config.get(FILTER_KEY) {
+            Some(filter) => {
+                let filter: SourceFilterConfig = serde_json::from_value(filter.clone())
+                    .map_err(|e| Error::Config(format!("invalid source filter: {e}")))?;
+                Self::new(&filter)
+            }
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Whether the filter keeps every conversation.
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty()
+            && self.exclude.is_empty()
+            && self.workspaces.is_empty()
+            && self.exclude_workspaces.is_empty()
+            && self.min_messages == 0
+    }
+
+    /// Whether `conversation`, parsed from the source at `root`, should be
+    /// written. File patterns only apply to conversations whose adapter
+    /// reports the file they came from.
+    pub fn keeps(&self, root: &Path, conversation: &ParsedConversation) -> bool {
+        if let Some(file) = conversation
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get("file"))
+            .and_then(|file| file.as_str())
+        {
+            let file = Path::new(file);
+            let relative = file.strip_prefix(root).unwrap_or(file).to_string_lossy();
+            if !self.include.is_empty() && !self.include.iter().any(|g| g.matches(&relative)) {
+                return false;
+            }
+            if self.exclude.iter().any(|g| g.matches(&relative)) {
+                return false;
+            }
+        }
+
+        let workspace = conversation.workspace.as_deref().map(Path::new);
+        let under = |dirs: &[String]| {
+            workspace.is_some_and(|workspace| {
+                dirs.iter()
+                    .any(|dir| workspace.starts_with(Config::expand_path(dir)))
+            })
+        };
+        if !self.workspaces.is_empty() && !under(&self.workspaces) {
+            return false;
+        }
+        if under(&self.exclude_workspaces) {
+            return false;
+        }
+
+        if self.min_messages > 0 {
+            let conversational = conversation
+                .messages
+                .iter()
+                .filter(|message| matches!(message.role.as_str(), "user" | "assistant"))
+                .count();
+            if conversational < self.min_messages {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// A glob pattern over `/`-separated paths.
+#[derive(Debug, Clone)]
+struct Glob {
+    regex: Regex,
+    /// The pattern has no `/`, so it matches the file name alone.
+    name_only: bool,
+}
+
+impl Glob {
+    fn new(pattern: &str) -> Result<Self> {
+        let mut regex = String::from("^");
+        let mut chars = pattern.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '*' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    if chars.peek() == Some(&'/') {
+                        chars.next();
+                        regex.push_str("(?:.*/)?");
+                    } else {
+                        regex.push_str(".*");
+                    }
+                }
+                '*' => regex.push_str("[^/]*"),
+                '?' => regex.push_str("[^/]"),
+                c => regex.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+        regex.push('$');
+        let regex = Regex::new(&regex)
+            .map_err(|e| Error::Config(format!("invalid file pattern {pattern:?}: {e}")))?;
+        Ok(Self {
+            regex,
+            name_only: !pattern.contains('/'),
+        })
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        let path = if self.name_only {
+            path.rsplit('/').next().unwrap_or(path)
+        } else {
+            path
+        };
+        self.regex.is_match(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conversation(file: &str, workspace: Option<&str>, roles: &[&str]) -> ParsedConversation {
+        let messages: Vec<_> = roles
+            .iter()
+            .map(|role| serde_json::json!({ "role": role, "content": "x" }))
+            .collect();
+        serde_json::from_value(serde_json::json!({
+            "createdAt": 1_700_000_000_000_i64,
+            "workspace": workspace,
+            "messages": messages,
+            "metadata": { "file": file },
+        }))
+        .expect("parsed conversation")
+    }
+
+    #[test]
+    fn filter_checks_files_workspaces_and_message_counts() {
+        let filter = SourceFilter::new(&SourceFilterConfig {
+            include: vec!["projects/**/*.jsonl".to_string()],
+            exclude: vec!["agent-*.jsonl".to_string()],
+            workspaces: vec!["/work".to_string()],
+            exclude_workspaces: vec!["/work/tests".to_string()],
+            min_messages: 2,
+        })
+        .expect("filter");
+        let root = Path::new("/data");
+        let chat = ["user", "assistant"];
+
+        assert!(filter.keeps(
+            root,
+            &conversation("/data/projects/a/s.jsonl", Some("/work/app"), &chat)
+        ));
+        assert!(filter.keeps(
+            root,
+            &conversation("/data/projects/s.jsonl", Some("/work"), &chat)
+        ));
+        // Outside `include`, or matching `exclude` by file name.
+        assert!(!filter.keeps(
+            root,
+            &conversation("/data/other/s.jsonl", Some("/work"), &chat)
+        ));
+        assert!(!filter.keeps(
+            root,
+            &conversation("/data/projects/a/agent-1.jsonl", Some("/work"), &chat)
+        ));
+        // Outside the workspace allow list, inside the deny list, or none.
+        assert!(!filter.keeps(
+            root,
+            &conversation("/data/projects/s.jsonl", Some("/home/me"), &chat)
+        ));
+        assert!(!filter.keeps(
+            root,
+            &conversation("/data/projects/s.jsonl", Some("/work/tests/unit"), &chat)
+        ));
+        assert!(!filter.keeps(root, &conversation("/data/projects/s.jsonl", None, &chat)));
+        // Tool output does not count towards `min_messages`.
+        assert!(!filter.keeps(
+            root,
+            &conversation(
+                "/data/projects/s.jsonl",
+                Some("/work"),
+                &["user", "tool", "tool"]
+            )
+        ));
+
+        assert!(SourceFilter::default().keeps(root, &conversation("/x", None, &[])));
+        assert!(
+            SourceFilter::new(&SourceFilterConfig::default())
+                .expect("filter")
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn filter_is_read_from_the_source_config() {
+        let config = serde_json::json!({ FILTER_KEY: { "exclude_workspaces": ["/tmp"] } });
+        let filter = SourceFilter::from_source_config(&config).expect("filter");
+        assert!(!filter.is_empty());
+        assert!(
+            SourceFilter::from_source_config(&serde_json::json!({}))
+                .expect("filter")
+                .is_empty()
+        );
+    }
+}
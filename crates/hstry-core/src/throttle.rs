@@ -0,0 +1,139 @@
+//! Nice mode: throttled background ingestion.
+//!
+//! A [`Throttle`] set with [`Database::set_throttle`] paces syncs and
+//! imports: after every committed batch they wait for the configured pause,
+//! and longer when the content written since the rate window opened is ahead
+//! of `max_write_kib_per_sec`. [`lower_priority`] renices the process so
+//! adapters and SQLite yield to foreground work.
+//!
+//! [`Database::set_throttle`]: crate::Database::set_throttle
+
+use std::process::{Command, Stdio};
+use std::sync::{Mutex, Once, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::config::NiceConfig;
+
+/// Pacing state shared by every batch written through one database handle.
+#[derive(Debug)]
+pub struct Throttle {
+    pause: Duration,
+    bytes_per_sec: u64,
+    window: Mutex<Window>,
+}
+
+#[derive(Debug)]
+struct Window {
+    started: Instant,
+    bytes: u64,
+}
+
+impl Throttle {
+    pub fn new(config: &NiceConfig) -> Self {
+        Self {
+            pause: Duration::from_millis(config.batch_pause_ms),
+            bytes_per_sec: config.max_write_kib_per_sec.saturating_mul(1024),
+            window: Mutex::new(Window {
+                started: Instant::now(),
+                bytes: 0,
+            }),
+        }
+    }
+
+    /// Wait after a batch that wrote `bytes` of content.
+    pub async fn pace(&self, bytes: u64) {
+        let delay = self.delay(bytes, Instant::now());
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// How long to wait at `now` after writing `bytes`. The rate window
+    /// starts over once the bytes in it have been paid for.
+    fn delay(&self, bytes: u64, now: Instant) -> Duration {
+        let mut window = self.window.lock().unwrap_or_else(PoisonError::into_inner);
+        if now.saturating_duration_since(window.started) >= self.time_for(window.bytes) {
+            window.started = now;
+            window.bytes = 0;
+        }
+        window.bytes += bytes;
+        let ahead = self
+            .time_for(window.bytes)
+            .saturating_sub(now.saturating_duration_since(window.started));
+        self.pause.max(ahead)
+    }
+
+    /// Time `bytes` take at the rate cap.
+    fn time_for(&self, bytes: u64) -> Duration {
+        if self.bytes_per_sec == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64(bytes as f64 / self.bytes_per_sec as f64)
+    }
+}
+
+static LOWER_PRIORITY: Once = Once::new();
+
+/// Lower this process's CPU priority (`renice`) and, on Linux, its IO
+/// priority to idle (`ionice`). Runs once per process; failures are logged
+/// and otherwise ignored.
+pub fn lower_priority() {
+    LOWER_PRIORITY.call_once(|| {
+        let pid = std::process::id().to_string();
+        run_quietly("renice", &["-n", "10", "-p", &pid]);
+        if cfg!(target_os = "linux") {
+            run_quietly("ionice", &["-c", "3", "-p", &pid]);
+        }
+    });
+}
+
+fn run_quietly(program: &str, args: &[&str]) {
+    let status = Command::new(program)
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => tracing::debug!("{program} exited with {status}"),
+        Err(err) => tracing::debug!("could not run {program}: {err}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn waits_for_the_pause_or_until_the_rate_catches_up() {
+        let throttle = Throttle::new(&NiceConfig {
+            max_write_kib_per_sec: 1024,
+            batch_pause_ms: 100,
+            ..Default::default()
+        });
+        let start = Instant::now();
+        // Small batches only pause.
+        assert_eq!(throttle.delay(1024, start), Duration::from_millis(100));
+        // 2 MiB at 1 MiB/s takes two seconds.
+        let later = start + Duration::from_millis(500);
+        assert_eq!(
+            throttle.delay(2 * 1024 * 1024, later),
+            Duration::from_secs(2)
+        );
+        // Writing again before then waits for the rest as well.
+        let delay = throttle.delay(0, later + Duration::from_millis(500));
+        assert_eq!(delay, Duration::from_millis(1500));
+        // Once paid for, the window starts over.
+        assert_eq!(
+            throttle.delay(1024, later + Duration::from_secs(2)),
+            Duration::from_millis(100)
+        );
+
+        let unlimited = Throttle::new(&NiceConfig {
+            max_write_kib_per_sec: 0,
+            batch_pause_ms: 0,
+            ..Default::default()
+        });
+        assert!(unlimited.delay(u64::MAX / 2, start).is_zero());
+    }
+}
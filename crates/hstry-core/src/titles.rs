@@ -0,0 +1,251 @@
+//! Titles for untitled conversations (`hstry title generate`).
+//!
+//! Many adapters import conversations without a title. [`heuristic`] takes
+//! one from the first user message that is neither agent instructions nor a
+//! compaction summary; [`generate`] asks the `[titles]` model instead, given
+//! the opening user message and reply.
+
+use crate::agent_search::is_system_context;
+use crate::config::TitleConfig;
+use crate::error::Result;
+use crate::llm::Endpoint;
+use crate::models::{Message, MessageRole};
+
+/// Leading messages of a conversation read to find its opening exchange.
+pub const OPENING_MESSAGES: i64 = 20;
+
+const INSTRUCTIONS: &str = "You write titles for conversations between a user and an AI \
+    assistant. Reply with the title only: at most eight words naming the task or topic, no \
+    quotes, no trailing period.";
+
+/// Requests that open a message without saying what it is about.
+const FILLER: &[&str] = &[
+    "can you please ",
+    "could you please ",
+    "please ",
+    "can you ",
+    "could you ",
+    "would you ",
+    "i want you to ",
+    "i need you to ",
+];
+
+/// Title from the first user message, or `None` when there is no usable
+/// one.
+pub fn heuristic(messages: &[Message], max_chars: usize) -> Option<String> {
+    let message = first_message(messages, MessageRole::User)?;
+    let line = first_prose_line(&message.content)?;
+    let mut title = line
+        .trim_start_matches(['#', '>', '-', '*', ' '])
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let lower = title.to_lowercase();
+    if let Some(rest) = FILLER
+        .iter()
+        .find(|filler| lower.starts_with(*filler))
+        .and_then(|filler| title.get(filler.len()..))
+    {
+        title = rest.to_string();
+    }
+    // Keep the first sentence.
+    if let Some(end) = [". ", "? ", "! "]
+        .iter()
+        .filter_map(|end| title.find(end))
+        .min()
+    {
+        title.truncate(end + 1);
+    }
+    tidy(&capitalize(&title), max_chars)
+}
+
+/// Ask the model for a title. `None` when the conversation has no user
+/// message or the model replies with nothing usable.
+pub async fn generate(
+    endpoint: &Endpoint,
+    messages: &[Message],
+    config: &TitleConfig,
+) -> Result<Option<String>> {
+    let Some(prompt) = prompt(messages, config.excerpt_chars) else {
+        return Ok(None);
+    };
+    let reply = endpoint.chat(INSTRUCTIONS, &prompt).await?;
+    let line = reply
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or_default();
+    let line = line.strip_prefix("Title:").unwrap_or(line);
+    Ok(tidy(
+        line.trim_matches(|c: char| {
+            matches!(c, '"' | '\'' | '*' | '#' | '“' | '”') || c.is_whitespace()
+        }),
+        config.max_chars,
+    ))
+}
+
+/// The opening user message and reply, each cut to `excerpt_chars`.
+fn prompt(messages: &[Message], excerpt_chars: usize) -> Option<String> {
+    let user = first_message(messages, MessageRole::User)?;
+    let mut prompt = format!("User:\n{}", excerpt(&user.content, excerpt_chars));
+    if let Some(reply) = messages
+        .iter()
+        .skip_while(|message| message.id != user.id)
+        .find(|message| {
+            message.role == MessageRole::Assistant && !message.content.trim().is_empty()
+        })
+    {
+        prompt.push_str(&format!(
+            "\n\nAssistant:\n{}",
+            excerpt(&reply.content, excerpt_chars)
+        ));
+    }
+    Some(prompt)
+}
+
+/// First non-empty message in `role` that is not agent instructions or a
+/// compaction summary.
+fn first_message(messages: &[Message], role: MessageRole) -> Option<&Message> {
+    messages.iter().find(|message| {
+        let content = message.content.trim();
+        message.role == role
+            && !content.is_empty()
+            && !is_system_context(content)
+            && !content.starts_with("The conversation history before this point was compacted")
+    })
+}
+
+/// First line outside code blocks that has some letters in it.
+fn first_prose_line(content: &str) -> Option<&str> {
+    let mut in_code = false;
+    for line in content.lines().map(str::trim) {
+        if line.starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        if !in_code && line.chars().any(char::is_alphabetic) {
+            return Some(line);
+        }
+    }
+    None
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    chars.next().map_or_else(String::new, |first| {
+        first.to_uppercase().chain(chars).collect()
+    })
+}
+
+/// `title` without trailing punctuation, cut at a word boundary to
+/// `max_chars`.
+fn tidy(title: &str, max_chars: usize) -> Option<String> {
+    let title = title
+        .trim()
+        .trim_end_matches(['.', ',', ':', ';'])
+        .trim_end();
+    if title.is_empty() {
+        return None;
+    }
+    if title.chars().count() <= max_chars {
+        return Some(title.to_string());
+    }
+    let cut: String = title.chars().take(max_chars.saturating_sub(1)).collect();
+    let cut = match cut.rfind(' ') {
+        Some(space) if space > cut.len() / 2 => &cut[..space],
+        _ => cut.as_str(),
+    };
+    Some(format!(
+        "{}…",
+        cut.trim_end_matches([' ', ',', ':', ';', '-'])
+    ))
+}
+
+fn excerpt(content: &str, chars: usize) -> String {
+    let content = content.trim();
+    if content.chars().count() <= chars {
+        content.to_string()
+    } else {
+        let mut cut: String = content.chars().take(chars).collect();
+        cut.push('…');
+        cut
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn message(idx: i32, role: MessageRole, content: &str) -> Message {
+        Message {
+            id: Uuid::new_v4(),
+            conversation_id: Uuid::nil(),
+            idx,
+            role,
+            content: content.to_string(),
+            parts_json: serde_json::json!([]),
+            created_at: None,
+            model: None,
+            tokens: None,
+            cost_usd: None,
+            metadata: serde_json::json!({}),
+            sender: None,
+            provider: None,
+            harness: None,
+            client_id: None,
+            parent_message_id: None,
+        }
+    }
+
+    #[test]
+    fn heuristic_uses_the_first_real_request() {
+        let messages = [
+            message(0, MessageRole::System, "You are a helpful assistant."),
+            message(
+                1,
+                MessageRole::User,
+                "# AGENTS.md\n\nGuidance for coding agents",
+            ),
+            message(
+                2,
+                MessageRole::User,
+                "```\nerror[E0502]: cannot borrow\n```\ncan you fix the borrow error in db.rs? It fails on CI.",
+            ),
+            message(3, MessageRole::Assistant, "Sure."),
+        ];
+        assert_eq!(
+            heuristic(&messages, 72).as_deref(),
+            Some("Fix the borrow error in db.rs?")
+        );
+        assert_eq!(
+            heuristic(&[message(0, MessageRole::Assistant, "Hello")], 72),
+            None
+        );
+    }
+
+    #[test]
+    fn long_titles_are_cut_at_a_word() {
+        let messages = [message(
+            0,
+            MessageRole::User,
+            "## Migrate the sync writer to the batched queue and keep the cursor updates in one transaction",
+        )];
+        let title = heuristic(&messages, 40).expect("title");
+        assert_eq!(title, "Migrate the sync writer to the batched…");
+        assert!(title.chars().count() <= 40);
+    }
+
+    #[test]
+    fn prompt_pairs_the_request_with_its_reply() {
+        let messages = [
+            message(0, MessageRole::User, "why is the WAL file growing?"),
+            message(1, MessageRole::Tool, "ls -la"),
+            message(2, MessageRole::Assistant, "Checkpoints are not running."),
+        ];
+        assert_eq!(
+            prompt(&messages, 10).as_deref(),
+            Some("User:\nwhy is the…\n\nAssistant:\nCheckpoint…")
+        );
+    }
+}
@@ -0,0 +1,164 @@
+//! Token counts for messages whose adapter reported none
+//! (`hstry tokens backfill`).
+//!
+//! Built with the `tiktoken` feature, a [`Tokenizer`] counts with OpenAI's
+//! BPE encodings; without it, or with `estimate`, it assumes about four
+//! characters per token. The counts are stored as estimates, which the
+//! stats and cost reports add up for conversations without token totals.
+
+use serde::Serialize;
+
+use crate::cancel::CancelToken;
+use crate::db::Database;
+use crate::error::{Error, Result};
+use crate::models::Message;
+use crate::sensitive::LOCKED_CONTENT;
+
+/// Tokenizer names accepted by [`Tokenizer::new`].
+pub const TOKENIZERS: &[&str] = &["estimate", "o200k_base", "cl100k_base"];
+
+/// Messages read and updated per batch.
+const BATCH: i64 = 500;
+
+pub enum Tokenizer {
+    /// About four characters per token.
+    Estimate,
+    #[cfg(feature = "tiktoken")]
+    Bpe {
+        name: &'static str,
+        bpe: Box<tiktoken_rs::CoreBPE>,
+    },
+}
+
+impl Tokenizer {
+    /// The most accurate tokenizer in this build.
+    pub fn default_name() -> &'static str {
+        if cfg!(feature = "tiktoken") {
+            "o200k_base"
+        } else {
+            "estimate"
+        }
+    }
+
+    pub fn new(name: &str) -> Result<Self> {
+        match name {
+            "estimate" => Ok(Self::Estimate),
+            #[cfg(feature = "tiktoken")]
+            "o200k_base" => Self::bpe("o200k_base", tiktoken_rs::o200k_base()),
+            #[cfg(feature = "tiktoken")]
+            "cl100k_base" => Self::bpe("cl100k_base", tiktoken_rs::cl100k_base()),
+            #[cfg(not(feature = "tiktoken"))]
+            "o200k_base" | "cl100k_base" => Err(Error::Config(format!(
+                "tokenizer {name} requires a build with the `tiktoken` feature of hstry-core"
+            ))),
+            _ => Err(Error::Config(format!(
+                "unknown tokenizer {name} (expected one of {})",
+                TOKENIZERS.join(", ")
+            ))),
+        }
+    }
+
+    #[cfg(feature = "tiktoken")]
+    fn bpe(
+        name: &'static str,
+        bpe: std::result::Result<tiktoken_rs::CoreBPE, anyhow::Error>,
+    ) -> Result<Self> {
+        bpe.map(|bpe| Self::Bpe {
+            name,
+            bpe: Box::new(bpe),
+        })
+        .map_err(|err| Error::Other(format!("loading tokenizer {name}: {err}")))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Estimate => "estimate",
+            #[cfg(feature = "tiktoken")]
+            Self::Bpe { name, .. } => name,
+        }
+    }
+
+    pub fn count(&self, text: &str) -> i64 {
+        let tokens = match self {
+            Self::Estimate => text.chars().count().div_ceil(4),
+            #[cfg(feature = "tiktoken")]
+            Self::Bpe { bpe, .. } => bpe.encode_ordinary(text).len(),
+        };
+        i64::try_from(tokens).unwrap_or(i64::MAX)
+    }
+
+    /// Tokens in a message's content; `None` while it is sealed and locked.
+    pub fn count_message(&self, message: &Message) -> Option<i64> {
+        (message.content != LOCKED_CONTENT).then(|| self.count(&message.content))
+    }
+}
+
+/// Outcome of [`backfill`].
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BackfillStats {
+    pub tokenizer: &'static str,
+    /// Messages given a count (or that would be, in a dry run).
+    pub messages: u64,
+    pub tokens: i64,
+    /// Sealed messages that could not be read.
+    pub locked: u64,
+}
+
+/// Estimate and store token counts for messages without one, optionally
+/// only those of `source_id`. `reestimate` also recounts earlier estimates;
+/// `dry_run` counts without storing. Stops between batches when `cancel`
+/// fires.
+pub async fn backfill(
+    db: &Database,
+    tokenizer: &Tokenizer,
+    source_id: Option<&str>,
+    reestimate: bool,
+    dry_run: bool,
+    cancel: &CancelToken,
+) -> Result<BackfillStats> {
+    let mut stats = BackfillStats {
+        tokenizer: tokenizer.name(),
+        ..BackfillStats::default()
+    };
+    let mut after_rowid = 0;
+    while !cancel.is_cancelled() {
+        let batch = db
+            .messages_for_token_backfill(source_id, reestimate, after_rowid, BATCH)
+            .await?;
+        let Some((last_rowid, _)) = batch.last() else {
+            break;
+        };
+        after_rowid = *last_rowid;
+
+        let mut counts = Vec::with_capacity(batch.len());
+        for (_, message) in &batch {
+            match tokenizer.count_message(message) {
+                Some(tokens) => {
+                    stats.tokens += tokens;
+                    counts.push((message.id, tokens));
+                }
+                None => stats.locked += 1,
+            }
+        }
+        if dry_run {
+            stats.messages += counts.len() as u64;
+        } else {
+            stats.messages += db.set_estimated_tokens(&counts).await?;
+        }
+    }
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimate_rounds_up_to_whole_tokens() {
+        let tokenizer = Tokenizer::new("estimate").expect("tokenizer");
+        assert_eq!(tokenizer.count(""), 0);
+        assert_eq!(tokenizer.count("hello"), 2);
+        assert_eq!(tokenizer.count("héllo wörld!"), 3);
+        assert!(Tokenizer::new("gpt2").is_err());
+    }
+}
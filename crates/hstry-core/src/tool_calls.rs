@@ -0,0 +1,250 @@
+//! Derive `tool_calls` rows from ingested messages.
+//!
+//! Adapters report tool use two ways: canonical `tool_call` / `tool_result`
+//! parts, or the older `toolCalls` array on a message. Parts win when a
+//! message has both. Calls are keyed by a stable id derived from the
+//! conversation and the adapter's tool call id, so a `tool_result` that
+//! arrives in a later message (or a later sync batch) updates the call it
+//! answers.
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::models::ToolCall;
+use crate::parsed::ParsedToolCall;
+use crate::parts::{Part, ToolStatus};
+
+/// A tool call to store for the message at `(conversation_id, idx)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewToolCall {
+    pub id: Uuid,
+    pub conversation_id: Uuid,
+    pub idx: i32,
+    pub tool_name: String,
+    pub input: Option<Value>,
+    pub output: Option<String>,
+    pub status: Option<String>,
+    pub duration_ms: Option<i64>,
+}
+
+/// Result for a call stored earlier, matched by [`NewToolCall::id`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolCallOutcome {
+    pub id: Uuid,
+    pub output: Option<String>,
+    pub status: String,
+    pub duration_ms: Option<i64>,
+}
+
+/// Tool calls and results found in one message.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Extracted {
+    pub calls: Vec<NewToolCall>,
+    pub outcomes: Vec<ToolCallOutcome>,
+}
+
+impl Extracted {
+    pub fn extend(&mut self, other: Self) {
+        self.calls.extend(other.calls);
+        self.outcomes.extend(other.outcomes);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.calls.is_empty() && self.outcomes.is_empty()
+    }
+}
+
+/// Stable row id for a tool call within a conversation.
+pub fn tool_call_id(conversation_id: Uuid, key: &str) -> Uuid {
+    Uuid::new_v5(
+        &crate::HSTRY_MSG_NAMESPACE,
+        format!("tool:{conversation_id}:{key}").as_bytes(),
+    )
+}
+
+/// Extract tool calls from the message at `idx` of `conversation_id`.
+pub fn extract(
+    conversation_id: Uuid,
+    idx: i32,
+    parts: &Value,
+    explicit: Option<&[ParsedToolCall]>,
+) -> Extracted {
+    let mut out = Extracted::default();
+    let parts: Vec<Part> = parts
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| serde_json::from_value(item.clone()).ok())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    for (n, part) in parts.iter().enumerate() {
+        match part {
+            Part::ToolCall {
+                tool_call_id: call_id,
+                name,
+                input,
+                status,
+                ..
+            } => out.calls.push(NewToolCall {
+                id: tool_call_id(conversation_id, &call_key(call_id, idx, n)),
+                conversation_id,
+                idx,
+                tool_name: name.clone(),
+                input: input.clone(),
+                output: None,
+                status: Some(status_str(*status).to_string()),
+                duration_ms: None,
+            }),
+            Part::ToolResult {
+                tool_call_id: call_id,
+                output,
+                is_error,
+                duration_ms,
+                ..
+            } if !call_id.is_empty() => out.outcomes.push(ToolCallOutcome {
+                id: tool_call_id(conversation_id, &call_key(call_id, idx, n)),
+                output: output.as_ref().map(output_text),
+                status: if *is_error { "error" } else { "success" }.to_string(),
+                duration_ms: duration_ms.and_then(|ms| i64::try_from(ms).ok()),
+            }),
+            _ => {}
+        }
+    }
+
+    if out.calls.is_empty() {
+        for (n, call) in explicit.unwrap_or_default().iter().enumerate() {
+            out.calls.push(NewToolCall {
+                id: tool_call_id(conversation_id, &format!("{idx}:explicit:{n}")),
+                conversation_id,
+                idx,
+                tool_name: call.tool_name.clone(),
+                input: call.input.clone(),
+                output: call.output.clone(),
+                status: call
+                    .status
+                    .as_deref()
+                    .map(|s| status_str(ToolStatus::parse(s)).to_string()),
+                duration_ms: call.duration_ms,
+            });
+        }
+    }
+    out
+}
+
+/// Group stored calls by the message they belong to, keeping order.
+pub fn by_message(calls: Vec<ToolCall>) -> HashMap<Uuid, Vec<ToolCall>> {
+    let mut grouped: HashMap<Uuid, Vec<ToolCall>> = HashMap::new();
+    for call in calls {
+        grouped.entry(call.message_id).or_default().push(call);
+    }
+    grouped
+}
+
+/// Convert a stored call back to the adapter-facing shape.
+pub fn to_parsed(call: &ToolCall) -> ParsedToolCall {
+    ParsedToolCall {
+        tool_name: call.tool_name.clone(),
+        input: call.input.clone(),
+        output: call.output.clone(),
+        status: call.status.as_ref().map(|s| s.as_str().to_string()),
+        duration_ms: call.duration_ms,
+    }
+}
+
+fn call_key(call_id: &str, idx: i32, n: usize) -> String {
+    if call_id.is_empty() {
+        format!("{idx}:part:{n}")
+    } else {
+        format!("call:{call_id}")
+    }
+}
+
+fn status_str(status: ToolStatus) -> &'static str {
+    match status {
+        ToolStatus::Pending => "pending",
+        ToolStatus::Running => "running",
+        ToolStatus::Success => "success",
+        ToolStatus::Error => "error",
+    }
+}
+
+fn output_text(output: &Value) -> String {
+    match output {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parts_pair_calls_with_later_results_and_win_over_explicit() {
+        let conv = Uuid::new_v4();
+        let call = extract(
+            conv,
+            0,
+            &serde_json::json!([
+                {"type": "text", "id": "p1", "text": "running it"},
+                {"type": "tool_call", "id": "p2", "toolCallId": "t1", "name": "bash",
+                 "input": {"command": "ls"}}
+            ]),
+            Some(&[ParsedToolCall {
+                tool_name: "ignored".to_string(),
+                input: None,
+                output: None,
+                status: None,
+                duration_ms: None,
+            }]),
+        );
+        assert_eq!(call.calls.len(), 1);
+        assert_eq!(call.calls[0].tool_name, "bash");
+        assert_eq!(call.calls[0].status.as_deref(), Some("pending"));
+
+        let result = extract(
+            conv,
+            1,
+            &serde_json::json!([
+                {"type": "tool_result", "id": "p3", "toolCallId": "t1",
+                 "output": "a.txt", "isError": true, "durationMs": 12}
+            ]),
+            None,
+        );
+        assert_eq!(
+            result.outcomes,
+            vec![ToolCallOutcome {
+                id: call.calls[0].id,
+                output: Some("a.txt".to_string()),
+                status: "error".to_string(),
+                duration_ms: Some(12),
+            }]
+        );
+    }
+
+    #[test]
+    fn explicit_tool_calls_are_used_without_parts() {
+        let conv = Uuid::new_v4();
+        let out = extract(
+            conv,
+            3,
+            &serde_json::json!([]),
+            Some(&[ParsedToolCall {
+                tool_name: "read_file".to_string(),
+                input: Some(serde_json::json!({"path": "a.rs"})),
+                output: Some("fn main() {}".to_string()),
+                status: Some("completed".to_string()),
+                duration_ms: Some(5),
+            }]),
+        );
+        assert_eq!(out.calls.len(), 1);
+        assert_eq!(out.calls[0].idx, 3);
+        assert_eq!(out.calls[0].status.as_deref(), Some("success"));
+        assert_eq!(out.calls[0].id, tool_call_id(conv, "3:explicit:0"));
+    }
+}
@@ -0,0 +1,181 @@
+//! Normalizing workspace paths.
+//!
+//! Adapters report the working directory a session ran in as they found
+//! it, so one project synced from a laptop and a workstation shows up as
+//! `/Users/me/code/app` and `/home/me/code/app`. [`normalize`] applies the
+//! `[workspace_paths]` rules: configured prefix rewrites first, then moving
+//! other machines' home directories under this one, then resolving symlinks
+//! for paths that exist here.
+
+use std::path::Path;
+
+use crate::config::{Config, WorkspacePathConfig};
+
+/// The workspace `config` stores `workspace` under. Blank workspaces are
+/// returned unchanged.
+pub fn normalize(config: &WorkspacePathConfig, workspace: &str) -> String {
+    normalize_with_home(config, workspace, dirs::home_dir().as_deref())
+}
+
+fn normalize_with_home(
+    config: &WorkspacePathConfig,
+    workspace: &str,
+    home: Option<&Path>,
+) -> String {
+    if workspace.trim().is_empty() {
+        return workspace.to_string();
+    }
+    let mut path = workspace.to_string();
+
+    for rewrite in &config.rewrites {
+        let from = Config::expand_path(&rewrite.from);
+        let from = from.to_string_lossy();
+        if let Some(rest) = strip_dir_prefix(&path, from.trim_end_matches(['/', '\\'])) {
+            let to = Config::expand_path(&rewrite.to);
+            path = format!("{}{rest}", to.to_string_lossy().trim_end_matches('/'));
+            break;
+        }
+    }
+
+    if config.canonical_home
+        && let Some(home) = home
+    {
+        path = rehome(&path, &home.to_string_lossy());
+    }
+
+    let trimmed = path.trim_end_matches('/');
+    if !trimmed.is_empty() {
+        path.truncate(trimmed.len());
+    }
+
+    if config.resolve_symlinks
+        && let Ok(resolved) = std::fs::canonicalize(&path)
+    {
+        path = resolved.to_string_lossy().into_owned();
+    }
+    path
+}
+
+/// The part of `path` after the directory `prefix`, starting with its
+/// separator; `Some("")` when `path` is `prefix` itself.
+fn strip_dir_prefix<'a>(path: &'a str, prefix: &str) -> Option<&'a str> {
+    let rest = path.strip_prefix(prefix)?;
+    (rest.is_empty() || rest.starts_with(['/', '\\'])).then_some(rest)
+}
+
+/// Replace a home directory of another machine (or `~`) at the start of
+/// `path` with `home`.
+fn rehome(path: &str, home: &str) -> String {
+    if let Some(rest) = strip_dir_prefix(path, "~") {
+        return format!("{home}{rest}");
+    }
+    if strip_dir_prefix(path, home).is_some() {
+        return path.to_string();
+    }
+
+    let (root_len, windows) = if let Some(rest) = path
+        .strip_prefix("/home/")
+        .or_else(|| path.strip_prefix("/Users/"))
+    {
+        (path.len() - rest.len(), false)
+    } else if path
+        .get(..9)
+        .is_some_and(|root| root.eq_ignore_ascii_case("C:\\Users\\"))
+    {
+        (9, true)
+    } else {
+        return path.to_string();
+    };
+    let user_and_rest = &path[root_len..];
+    let user_len = user_and_rest
+        .find(['/', '\\'])
+        .unwrap_or(user_and_rest.len());
+    if user_len == 0 {
+        return path.to_string();
+    }
+    let rest = &user_and_rest[user_len..];
+    let rest = if windows && !cfg!(windows) {
+        rest.replace('\\', "/")
+    } else {
+        rest.to_string()
+    };
+    format!("{home}{rest}")
+}
+
+/// [`normalize`] for an optional workspace.
+pub fn normalize_opt(config: &WorkspacePathConfig, workspace: Option<&str>) -> Option<String> {
+    workspace.map(|workspace| normalize(config, workspace))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::WorkspaceRewrite;
+
+    fn rules(rewrites: &[(&str, &str)]) -> WorkspacePathConfig {
+        WorkspacePathConfig {
+            rewrites: rewrites
+                .iter()
+                .map(|(from, to)| WorkspaceRewrite {
+                    from: (*from).to_string(),
+                    to: (*to).to_string(),
+                })
+                .collect(),
+            canonical_home: true,
+            resolve_symlinks: false,
+        }
+    }
+
+    #[test]
+    fn home_directories_of_other_machines_map_onto_this_one() {
+        let config = rules(&[]);
+        let home = Some(Path::new("/home/me"));
+        let normalize = |workspace: &str| normalize_with_home(&config, workspace, home);
+
+        assert_eq!(normalize("/Users/me/code/app"), "/home/me/code/app");
+        assert_eq!(normalize("/home/other/code/app/"), "/home/me/code/app");
+        assert_eq!(normalize("C:\\Users\\Me\\code\\app"), "/home/me/code/app");
+        assert_eq!(normalize("~/code"), "/home/me/code");
+        assert_eq!(normalize("/home/me"), "/home/me");
+        assert_eq!(normalize("/srv/app"), "/srv/app");
+        assert_eq!(normalize("/Users/"), "/Users");
+        assert_eq!(normalize(""), "");
+    }
+
+    #[test]
+    fn rewrites_apply_first_and_only_on_directory_boundaries() {
+        let config = rules(&[("/mnt/laptop/code", "/srv/code"), ("/srv/code", "/nope")]);
+        let home = Some(Path::new("/home/me"));
+
+        assert_eq!(
+            normalize_with_home(&config, "/mnt/laptop/code/app", home),
+            "/srv/code/app"
+        );
+        assert_eq!(
+            normalize_with_home(&config, "/mnt/laptop/codex", home),
+            "/mnt/laptop/codex"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_workspaces_resolve_to_their_target() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let target = dir.path().join("target");
+        std::fs::create_dir(&target).expect("mkdir");
+        let link = dir.path().join("link");
+        std::os::unix::fs::symlink(&target, &link).expect("symlink");
+
+        let config = WorkspacePathConfig {
+            canonical_home: false,
+            ..WorkspacePathConfig::default()
+        };
+        let resolved = normalize(&config, &link.to_string_lossy());
+        assert_eq!(
+            Path::new(&resolved),
+            std::fs::canonicalize(&target).expect("canonicalize")
+        );
+        // Paths that do not exist here are kept.
+        assert_eq!(normalize(&config, "/no/such/dir"), "/no/such/dir");
+    }
+}
@@ -0,0 +1,104 @@
+//! Workspaces as entities.
+//!
+//! Conversations carry the workspace path they ran in; the `workspaces`
+//! table adds what `hstry workspace` manages on top: a display name shown
+//! instead of the path, the git remote of the checkout, and aliases. A
+//! conversation reported under an alias is stored under the workspace's
+//! path, so `hstry workspace merge` keeps holding for later syncs.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+/// A workspace with its metadata and conversation count.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Workspace {
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_remote: Option<String>,
+    /// Other paths stored under this workspace.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    /// Conversations stored under `path`.
+    pub conversations: i64,
+}
+
+impl Workspace {
+    /// The display name, or the last component of the path.
+    pub fn label(&self) -> &str {
+        label(&self.path, self.display_name.as_deref())
+    }
+}
+
+/// `name` when set, else the last component of `path`.
+pub fn label<'a>(path: &'a str, name: Option<&'a str>) -> &'a str {
+    name.filter(|name| !name.is_empty()).unwrap_or_else(|| {
+        Path::new(path)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(path)
+    })
+}
+
+/// URL of the `origin` remote (or else the first remote) of the git
+/// checkout at `path`, read from `.git/config`.
+pub fn git_remote(path: &Path) -> Option<String> {
+    let config = std::fs::read_to_string(path.join(".git").join("config")).ok()?;
+    let mut remote: Option<&str> = None;
+    let mut first = None;
+    for line in config.lines().map(str::trim) {
+        if line.starts_with('[') {
+            remote = line
+                .strip_prefix("[remote \"")
+                .and_then(|rest| rest.strip_suffix("\"]"));
+            continue;
+        }
+        let Some(name) = remote else {
+            continue;
+        };
+        if let Some((key, value)) = line.split_once('=')
+            && key.trim() == "url"
+        {
+            let url = value.trim().to_string();
+            if name == "origin" {
+                return Some(url);
+            }
+            first.get_or_insert(url);
+        }
+    }
+    first
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn git_remote_prefers_origin() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        assert_eq!(git_remote(dir.path()), None);
+
+        std::fs::create_dir(dir.path().join(".git")).expect("mkdir");
+        std::fs::write(
+            dir.path().join(".git").join("config"),
+            "[core]\n\tbare = false\n\
+             [remote \"fork\"]\n\turl = git@example.com:me/app.git\n\
+             [remote \"origin\"]\n\turl = https://example.com/team/app.git\n\
+             \tfetch = +refs/heads/*:refs/remotes/origin/*\n",
+        )
+        .expect("write");
+        assert_eq!(
+            git_remote(dir.path()).as_deref(),
+            Some("https://example.com/team/app.git")
+        );
+    }
+
+    #[test]
+    fn label_falls_back_to_the_directory_name() {
+        assert_eq!(label("/home/me/code/app", None), "app");
+        assert_eq!(label("/home/me/code/app", Some("")), "app");
+        assert_eq!(label("/home/me/code/app", Some("App")), "App");
+    }
+}
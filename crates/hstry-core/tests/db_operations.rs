@@ -2,8 +2,15 @@
 
 use chrono::Utc;
 use hstry_core::Database;
-use hstry_core::db::{ListConversationsOptions, SearchMode, SearchOptions};
+use hstry_core::config::SensitiveConfig;
+use hstry_core::db::{
+    AUTO_SNAPSHOTS_KEPT, DatabaseOptions, ListConversationsOptions, MessageRange, SearchMode,
+    SearchOptions,
+};
+use hstry_core::llm::LlmUsage;
 use hstry_core::models::{Conversation, Message, MessageRole, Source};
+use hstry_core::revisions::RevisionKind;
+use hstry_core::sensitive::{LOCKED_CONTENT, WrappedKey, clear_session, write_session};
 use uuid::Uuid;
 
 fn temp_db_path() -> std::path::PathBuf {
@@ -382,6 +389,293 @@ async fn list_conversations_with_filters() {
     assert_eq!(convs.len(), 2);
 }
 
+#[tokio::test]
+async fn dashboard_aggregates_count_days_models_and_usage() {
+    let db_path = temp_db_path();
+    let db = Database::open(&db_path).await.expect("open db");
+    setup_source(&db).await;
+
+    let now = Utc::now();
+    let rows = [
+        (Some("gpt-5"), Some(100), Some(0.5), now),
+        (Some("gpt-5"), Some(50), None, now),
+        (None, None, None, now - chrono::Duration::days(40)),
+    ];
+    let mut ids = Vec::new();
+    for (i, (model, tokens, cost, created_at)) in rows.iter().enumerate() {
+        let conv = Conversation {
+            id: Uuid::new_v4(),
+            source_id: "test-source".to_string(),
+            external_id: Some(format!("ext-{i}")),
+            readable_id: None,
+            platform_id: None,
+            title: None,
+            created_at: *created_at,
+            updated_at: None,
+            model: model.map(str::to_string),
+            provider: None,
+            workspace: None,
+            tokens_in: *tokens,
+            tokens_out: tokens.map(|t| t * 2),
+            cost_usd: *cost,
+            metadata: serde_json::json!({}),
+            harness: None,
+            version: 0,
+            message_count: 0,
+            parent_conversation_id: None,
+            parent_message_idx: None,
+            fork_type: None,
+        };
+        db.upsert_conversation(&conv).await.expect("upsert");
+        ids.push(conv.id);
+    }
+    for (conversation_id, model, cost) in [
+        (ids[1], None, Some(0.25)),
+        (ids[2], Some("claude-opus-4"), Some(1.0)),
+    ] {
+        let message = Message {
+            id: Uuid::new_v4(),
+            conversation_id,
+            idx: 0,
+            role: MessageRole::Assistant,
+            content: "done".to_string(),
+            parts_json: serde_json::json!([]),
+            created_at: None,
+            model: model.map(str::to_string),
+            tokens: None,
+            cost_usd: cost,
+            metadata: serde_json::json!({}),
+            sender: None,
+            provider: None,
+            harness: None,
+            client_id: None,
+            parent_message_id: None,
+        };
+        db.insert_message(&message).await.expect("insert");
+    }
+
+    let days = db.count_conversations_per_day(30).await.expect("per day");
+    assert_eq!(days.len(), 30);
+    assert_eq!(days.iter().map(|(_, n)| n).sum::<i64>(), 2);
+    assert_eq!(days.last().map(|(_, n)| *n), Some(2));
+
+    let models = db.count_conversations_by_model(10).await.expect("models");
+    assert_eq!(
+        models,
+        vec![("gpt-5".to_string(), 2), ("claude-opus-4".to_string(), 1)]
+    );
+
+    let usage = db.get_usage_totals().await.expect("usage");
+    assert_eq!((usage.tokens_in, usage.tokens_out), (150, 300));
+    assert!((usage.cost_usd - 1.75).abs() < 1e-9);
+}
+
+#[tokio::test]
+async fn detailed_stats_break_usage_down_by_model_month_and_workspace() {
+    let db_path = temp_db_path();
+    let db = Database::open(&db_path).await.expect("open db");
+    setup_source(&db).await;
+
+    let now = Utc::now();
+    let rows = [
+        ("a", "/work/hstry", Some("gpt-5"), Some(100), Some(0.5), now),
+        ("b", "/work/hstry", Some("gpt-5"), Some(50), Some(0.25), now),
+        (
+            "c",
+            "/work/site",
+            None,
+            None,
+            None,
+            now - chrono::Duration::days(70),
+        ),
+    ];
+    for (external_id, workspace, model, tokens, cost, created_at) in rows {
+        let mut conv = conversation_in(external_id, workspace);
+        conv.model = model.map(str::to_string);
+        conv.tokens_in = tokens;
+        conv.cost_usd = cost;
+        conv.created_at = created_at;
+        db.upsert_conversation(&conv).await.expect("upsert");
+        for idx in 0..2 {
+            let message = Message {
+                id: Uuid::new_v4(),
+                conversation_id: conv.id,
+                idx,
+                role: MessageRole::User,
+                content: "hello".to_string(),
+                parts_json: serde_json::json!([]),
+                created_at: None,
+                model: None,
+                tokens: None,
+                cost_usd: None,
+                metadata: serde_json::json!({}),
+                sender: None,
+                provider: None,
+                harness: None,
+                client_id: None,
+                parent_message_id: None,
+            };
+            db.insert_message(&message).await.expect("insert");
+        }
+    }
+
+    let stats = db.detailed_stats(10).await.expect("stats");
+    assert_eq!(stats.totals.tokens_in, 150);
+    assert!((stats.avg_messages - 2.0).abs() < 1e-9);
+
+    let models: Vec<_> = stats
+        .by_model
+        .iter()
+        .map(|row| (row.key.as_deref(), row.conversations, row.tokens_in))
+        .collect();
+    assert_eq!(models, vec![(Some("gpt-5"), 2, 150), (None, 1, 0)]);
+    assert!((stats.by_model[0].cost_usd - 0.75).abs() < 1e-9);
+
+    let months: Vec<_> = stats.by_month.iter().map(|row| row.conversations).collect();
+    assert_eq!(months, vec![1, 2]);
+
+    let workspaces: Vec<_> = stats
+        .top_workspaces
+        .iter()
+        .map(|row| (row.key.as_deref(), row.messages))
+        .collect();
+    assert_eq!(
+        workspaces,
+        vec![(Some("/work/hstry"), 4), (Some("/work/site"), 2)]
+    );
+
+    assert_eq!(stats.by_source.len(), 1);
+    assert_eq!(stats.by_source[0].conversations, 3);
+}
+
+#[tokio::test]
+async fn llm_usage_is_summarized_per_feature_and_model() {
+    let db_path = temp_db_path();
+    let db = Database::open(&db_path).await.expect("open db");
+
+    let record = |feature: &str, model: &str, tokens_in, cost_usd| LlmUsage {
+        at: Utc::now(),
+        feature: feature.to_string(),
+        provider: "openai".to_string(),
+        model: Some(model.to_string()),
+        tokens_in,
+        tokens_out: 10,
+        cost_usd,
+    };
+    db.record_llm_usage(&[
+        record("ask", "gpt-5", 1000, 0.5),
+        record("ask", "gpt-5", 500, 0.25),
+        record("rerank", "gpt-5-mini", 200, 0.01),
+    ])
+    .await
+    .expect("record");
+    db.record_llm_usage(&[]).await.expect("record nothing");
+
+    let summary = db.llm_usage_summary().await.expect("summary");
+    assert_eq!(summary.len(), 2);
+    assert_eq!(summary[0].feature, "ask");
+    assert_eq!(summary[0].requests, 2);
+    assert_eq!((summary[0].tokens_in, summary[0].tokens_out), (1500, 20));
+    assert!((summary[0].cost_usd - 0.75).abs() < 1e-9);
+    assert_eq!(summary[1].model.as_deref(), Some("gpt-5-mini"));
+}
+
+#[tokio::test]
+async fn list_conversations_filters_by_model_provider_harness_and_role() {
+    let db_path = temp_db_path();
+    let db = Database::open(&db_path).await.expect("open db");
+    setup_source(&db).await;
+
+    let mut ids = Vec::new();
+    for (i, (model, harness)) in [
+        ("claude-sonnet-4", "claude-code"),
+        ("gpt-5", "codex"),
+        ("Claude-Opus-4", "claude-code"),
+    ]
+    .iter()
+    .enumerate()
+    {
+        let conv = Conversation {
+            id: Uuid::new_v4(),
+            source_id: "test-source".to_string(),
+            external_id: Some(format!("ext-{i}")),
+            readable_id: None,
+            platform_id: None,
+            title: Some(format!("Conv {i}")),
+            created_at: Utc::now(),
+            updated_at: None,
+            model: Some(model.to_string()),
+            provider: Some(if i == 1 { "openai" } else { "anthropic" }.to_string()),
+            workspace: None,
+            tokens_in: None,
+            tokens_out: None,
+            cost_usd: None,
+            metadata: serde_json::json!({}),
+            harness: Some(harness.to_string()),
+            version: 0,
+            message_count: 0,
+            parent_conversation_id: None,
+            parent_message_idx: None,
+            fork_type: None,
+        };
+        db.upsert_conversation(&conv).await.expect("upsert");
+        ids.push(conv.id);
+    }
+    let tool_result = Message {
+        id: Uuid::new_v4(),
+        conversation_id: ids[2],
+        idx: 0,
+        role: MessageRole::Tool,
+        content: "ok".to_string(),
+        parts_json: serde_json::json!([]),
+        created_at: None,
+        model: None,
+        tokens: None,
+        cost_usd: None,
+        metadata: serde_json::json!({}),
+        sender: None,
+        provider: None,
+        harness: None,
+        client_id: None,
+        parent_message_id: None,
+    };
+    db.insert_message(&tool_result).await.expect("insert");
+
+    let titles = |convs: Vec<Conversation>| {
+        let mut titles: Vec<_> = convs.into_iter().filter_map(|c| c.title).collect();
+        titles.sort();
+        titles
+    };
+    let convs = db
+        .list_conversations(ListConversationsOptions {
+            model: Some("%claude%".to_string()),
+            ..Default::default()
+        })
+        .await
+        .expect("list");
+    assert_eq!(titles(convs), vec!["Conv 0", "Conv 2"]);
+
+    let convs = db
+        .list_conversations(ListConversationsOptions {
+            provider: Some("OpenAI".to_string()),
+            ..Default::default()
+        })
+        .await
+        .expect("list");
+    assert_eq!(titles(convs), vec!["Conv 1"]);
+
+    let convs = db
+        .list_conversation_summaries(ListConversationsOptions {
+            harness: Some("claude-code".to_string()),
+            role: Some(MessageRole::Tool),
+            ..Default::default()
+        })
+        .await
+        .expect("list");
+    assert_eq!(convs.len(), 1);
+    assert_eq!(convs[0].conversation.id, ids[2]);
+}
+
 #[tokio::test]
 async fn list_conversations_serializes_timestamps() {
     // Regression for trx-70as: list output must include non-empty ISO timestamps.
@@ -593,6 +887,7 @@ async fn insert_message_creates_new() {
         provider: None,
         harness: None,
         client_id: None,
+        parent_message_id: None,
     };
 
     db.insert_message(&msg).await.expect("insert");
@@ -625,6 +920,7 @@ async fn insert_message_upserts_by_idx() {
         provider: None,
         harness: None,
         client_id: None,
+        parent_message_id: None,
     };
     db.insert_message(&msg_v1).await.expect("insert v1");
 
@@ -644,6 +940,7 @@ async fn insert_message_upserts_by_idx() {
         provider: None,
         harness: None,
         client_id: None,
+        parent_message_id: None,
     };
     db.insert_message(&msg_v2).await.expect("insert v2");
 
@@ -680,6 +977,7 @@ async fn get_messages_ordered_by_idx() {
             provider: None,
             harness: None,
             client_id: None,
+            parent_message_id: None,
         };
         db.insert_message(&msg).await.expect("insert");
     }
@@ -691,6 +989,90 @@ async fn get_messages_ordered_by_idx() {
     assert_eq!(messages[2].idx, 2);
 }
 
+#[tokio::test]
+async fn get_messages_range_pages_by_idx_and_offset() {
+    let db_path = temp_db_path();
+    let db = Database::open(&db_path).await.expect("open db");
+    let conv = setup_conversation(&db).await;
+
+    for idx in 0..10 {
+        let msg = Message {
+            id: Uuid::new_v4(),
+            conversation_id: conv.id,
+            idx,
+            role: MessageRole::User,
+            content: format!("Message {idx}"),
+            parts_json: serde_json::json!([]),
+            created_at: None,
+            model: None,
+            tokens: None,
+            cost_usd: None,
+            metadata: serde_json::json!({}),
+            sender: None,
+            provider: None,
+            harness: None,
+            client_id: None,
+            parent_message_id: None,
+        };
+        db.insert_message(&msg).await.expect("insert");
+    }
+
+    let idxs = |messages: Vec<Message>| messages.iter().map(|m| m.idx).collect::<Vec<_>>();
+
+    let page = db
+        .get_messages_range(
+            conv.id,
+            MessageRange {
+                offset: Some(4),
+                limit: Some(3),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("page");
+    assert_eq!(idxs(page), [4, 5, 6]);
+
+    let window = db
+        .get_messages_range(
+            conv.id,
+            MessageRange {
+                from_idx: Some(2),
+                to_idx: Some(8),
+                offset: Some(1),
+                limit: Some(2),
+            },
+        )
+        .await
+        .expect("window");
+    assert_eq!(idxs(window), [3, 4]);
+
+    let tail = db
+        .get_messages_range(
+            conv.id,
+            MessageRange {
+                from_idx: Some(8),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("tail");
+    assert_eq!(idxs(tail), [8, 9]);
+
+    let context = db
+        .get_message_context(conv.id, 5, 2, 3)
+        .await
+        .expect("context");
+    assert_eq!(idxs(context.before), [3, 4]);
+    assert_eq!(idxs(context.after), [6, 7, 8]);
+
+    let edge = db
+        .get_message_context(conv.id, 1, 3, 0)
+        .await
+        .expect("edge");
+    assert_eq!(idxs(edge.before), [0]);
+    assert!(edge.after.is_empty());
+}
+
 #[tokio::test]
 async fn count_messages_accurate() {
     let db_path = temp_db_path();
@@ -716,6 +1098,7 @@ async fn count_messages_accurate() {
             provider: None,
             harness: None,
             client_id: None,
+            parent_message_id: None,
         };
         db.insert_message(&msg).await.expect("insert");
     }
@@ -747,6 +1130,7 @@ async fn message_events_include_inserted_messages() {
         provider: None,
         harness: None,
         client_id: None,
+        parent_message_id: None,
     };
     db.insert_message(&msg).await.expect("insert");
 
@@ -782,6 +1166,7 @@ async fn list_conversation_summaries_uses_cache() {
         provider: None,
         harness: None,
         client_id: None,
+        parent_message_id: None,
     };
     db.insert_message(&msg).await.expect("insert");
 
@@ -797,6 +1182,142 @@ async fn list_conversation_summaries_uses_cache() {
     assert_eq!(summary.first_user_message.as_deref(), Some("First message"));
 }
 
+#[tokio::test]
+async fn stale_summaries_are_detected_and_refreshed() {
+    let db_path = temp_db_path();
+    let db = Database::open(&db_path).await.expect("open db");
+    let conv = setup_conversation(&db).await;
+    let message = |idx: i32, content: &str| Message {
+        id: Uuid::new_v4(),
+        conversation_id: conv.id,
+        idx,
+        role: MessageRole::User,
+        content: content.to_string(),
+        parts_json: serde_json::json!([]),
+        created_at: None,
+        model: None,
+        tokens: None,
+        cost_usd: None,
+        metadata: serde_json::json!({}),
+        sender: None,
+        provider: None,
+        harness: None,
+        client_id: None,
+        parent_message_id: None,
+    };
+    db.insert_message(&message(0, "First message"))
+        .await
+        .expect("insert");
+    assert!(!db.refresh_summary_if_stale(conv.id).await.expect("check"));
+
+    // Bulk inserts skip the cache until it is rebuilt.
+    let mut tx = db.begin().await.expect("begin");
+    db.insert_message_in_tx(&mut tx, &message(1, "Second message"))
+        .await
+        .expect("insert in tx");
+    tx.commit().await.expect("commit");
+
+    let summaries = db
+        .list_conversation_summaries(ListConversationsOptions::default())
+        .await
+        .expect("summaries");
+    assert!(summaries[0].stale);
+    assert_eq!(
+        db.stale_conversation_summaries(10).await.expect("stale"),
+        vec![conv.id]
+    );
+
+    assert_eq!(db.refresh_stale_summaries(10).await.expect("refresh"), 1);
+    assert!(
+        db.stale_conversation_summaries(10)
+            .await
+            .expect("stale")
+            .is_empty()
+    );
+    let summaries = db
+        .list_conversation_summaries(ListConversationsOptions::default())
+        .await
+        .expect("summaries");
+    assert!(!summaries[0].stale);
+    assert_eq!(summaries[0].message_count, 2);
+    let (_, message_count) = db
+        .get_conversation_version(conv.id)
+        .await
+        .expect("version")
+        .expect("conversation");
+    assert_eq!(message_count, 2);
+}
+
+#[tokio::test]
+async fn split_conversation_moves_sessions_and_survives_resync() {
+    let db_path = temp_db_path();
+    let db = Database::open(&db_path).await.expect("open db");
+    let conv = setup_conversation(&db).await;
+
+    let messages: Vec<Message> = ["ls", "src", "git status", "clean"]
+        .iter()
+        .enumerate()
+        .map(|(idx, content)| Message {
+            id: Uuid::new_v4(),
+            conversation_id: conv.id,
+            idx: i32::try_from(idx).unwrap(),
+            role: if idx % 2 == 0 {
+                MessageRole::User
+            } else {
+                MessageRole::Assistant
+            },
+            content: (*content).to_string(),
+            parts_json: serde_json::json!([]),
+            created_at: Some(Utc::now()),
+            model: None,
+            tokens: None,
+            cost_usd: None,
+            metadata: serde_json::json!({}),
+            sender: None,
+            provider: None,
+            harness: None,
+            client_id: None,
+            parent_message_id: None,
+        })
+        .collect();
+    for msg in &messages {
+        db.insert_message(msg).await.expect("insert");
+    }
+
+    let created = db.split_conversation(conv.id, &[2]).await.expect("split");
+    assert_eq!(created.len(), 1);
+    let session = db
+        .get_conversation(created[0])
+        .await
+        .expect("get")
+        .expect("session");
+    assert_eq!(session.external_id.as_deref(), Some("conv-for-messages#2"));
+    assert_eq!(session.metadata["segment_of"], conv.id.to_string());
+    assert_eq!(session.message_count, 2);
+    let moved = db.get_messages(session.id).await.expect("messages");
+    assert_eq!(
+        moved
+            .iter()
+            .map(|m| (m.idx, m.content.as_str()))
+            .collect::<Vec<_>>(),
+        [(0, "git status"), (1, "clean")]
+    );
+    assert_eq!(db.get_messages(conv.id).await.expect("messages").len(), 2);
+
+    // A sync that restores the full log, then splitting again, leaves one
+    // copy of each message.
+    for msg in &messages[2..] {
+        db.insert_message(msg).await.expect("resync");
+    }
+    let again = db.split_conversation(conv.id, &[2]).await.expect("split");
+    assert_eq!(again, created);
+    assert_eq!(
+        db.get_messages(session.id).await.expect("messages").len(),
+        2
+    );
+    assert_eq!(db.get_messages(conv.id).await.expect("messages").len(), 2);
+}
+
 // ============================================================================
 // Search Operations
 // ============================================================================
@@ -823,6 +1344,7 @@ async fn search_finds_matching_content() {
         provider: None,
         harness: None,
         client_id: None,
+        parent_message_id: None,
     };
     db.insert_message(&msg).await.expect("insert");
 
@@ -831,18 +1353,192 @@ async fn search_finds_matching_content() {
         mode: SearchMode::NaturalLanguage,
         ..Default::default()
     };
-    let hits = db.search("fox", opts).await.expect("search");
+    let mut hits = db.search("fox", opts).await.expect("search");
 
     assert_eq!(hits.len(), 1);
     assert!(hits[0].snippet.to_lowercase().contains("fox"));
+    assert!(hits[0].conversation.is_none());
+
+    db.expand_search_hits(&mut hits).await.expect("expand");
+    let expanded = hits[0].conversation.as_ref().expect("conversation");
+    assert_eq!(expanded.id, conv.id);
+    assert_eq!(expanded.title, conv.title);
 }
 
 #[tokio::test]
-async fn search_with_source_filter() {
+async fn trashed_conversations_leave_lists_and_search_until_restored() {
     let db_path = temp_db_path();
     let db = Database::open(&db_path).await.expect("open db");
+    let mut conv = setup_conversation(&db).await;
+    conv.workspace = Some("/work/zoo".to_string());
+    db.upsert_conversation(&conv).await.expect("set workspace");
+    let msg = Message {
+        id: Uuid::new_v4(),
+        conversation_id: conv.id,
+        idx: 0,
+        role: MessageRole::User,
+        content: "Where did the walrus go".to_string(),
+        parts_json: serde_json::json!([]),
+        created_at: Some(Utc::now()),
+        model: None,
+        tokens: None,
+        cost_usd: None,
+        metadata: serde_json::json!({}),
+        sender: None,
+        provider: None,
+        harness: None,
+        client_id: None,
+        parent_message_id: None,
+    };
+    db.insert_message(&msg).await.expect("insert");
+    let opts = || SearchOptions {
+        limit: Some(10),
+        mode: SearchMode::NaturalLanguage,
+        ..Default::default()
+    };
 
-    // Create two sources
+    assert_eq!(db.trash_conversations(&[conv.id]).await.expect("trash"), 1);
+    assert_eq!(db.trash_conversations(&[conv.id]).await.expect("trash"), 0);
+    assert!(db.trashed_at(conv.id).await.expect("trashed").is_some());
+    assert_eq!(db.count_conversations().await.expect("count"), 0);
+    assert!(
+        db.list_conversations(ListConversationsOptions::default())
+            .await
+            .expect("list")
+            .is_empty()
+    );
+    assert!(
+        db.search("walrus", opts())
+            .await
+            .expect("search")
+            .is_empty()
+    );
+    // Nor are they counted in workspace badges or stats.
+    assert!(
+        db.count_conversations_by_workspace()
+            .await
+            .expect("count")
+            .is_empty()
+    );
+    assert!(db.list_workspaces().await.expect("workspaces").is_empty());
+    assert_eq!(db.get_activity_stats(30).await.expect("activity").period, 0);
+    let stats = db.detailed_stats(10).await.expect("stats");
+    assert!(stats.by_source.is_empty());
+    assert!(stats.top_workspaces.is_empty());
+    let trash = db.list_trash(None).await.expect("trash list");
+    assert_eq!(trash.len(), 1);
+    assert_eq!(trash[0].conversation.id, conv.id);
+
+    // A re-sync of the source leaves it in the trash.
+    db.upsert_conversation(&conv).await.expect("upsert");
+    assert!(db.trashed_at(conv.id).await.expect("trashed").is_some());
+
+    assert_eq!(
+        db.restore_conversations(&[conv.id]).await.expect("restore"),
+        1
+    );
+    assert_eq!(db.count_conversations().await.expect("count"), 1);
+    assert_eq!(db.search("walrus", opts()).await.expect("search").len(), 1);
+    assert_eq!(
+        db.count_conversations_by_workspace().await.expect("count"),
+        [("/work/zoo".to_string(), 1)]
+    );
+
+    db.trash_conversations(&[conv.id]).await.expect("trash");
+    let an_hour_ago = Utc::now() - chrono::Duration::hours(1);
+    assert_eq!(db.purge_trash(Some(an_hour_ago)).await.expect("purge"), 0);
+    assert_eq!(db.purge_trash(None).await.expect("purge"), 1);
+    assert!(db.get_conversation(conv.id).await.expect("get").is_none());
+    assert!(db.list_trash(None).await.expect("trash list").is_empty());
+}
+
+#[tokio::test]
+async fn labels_survive_reimport_and_filter_search() {
+    let db_path = temp_db_path();
+    let db = Database::open(&db_path).await.expect("open db");
+    let conv = setup_conversation(&db).await;
+
+    let mut messages = Vec::new();
+    for (idx, content) in ["Enable WAL mode", "WAL checkpoints stall"]
+        .iter()
+        .enumerate()
+    {
+        let msg = Message {
+            id: Uuid::new_v4(),
+            conversation_id: conv.id,
+            idx: i32::try_from(idx).unwrap(),
+            role: MessageRole::Assistant,
+            content: (*content).to_string(),
+            parts_json: serde_json::json!([]),
+            created_at: Some(Utc::now()),
+            model: None,
+            tokens: None,
+            cost_usd: None,
+            metadata: serde_json::json!({"cwd": "/tmp"}),
+            sender: None,
+            provider: None,
+            harness: None,
+            client_id: None,
+            parent_message_id: None,
+        };
+        db.insert_message(&msg).await.expect("insert");
+        messages.push(msg);
+    }
+
+    let labels = db
+        .toggle_message_label(messages[1].id, "Bug")
+        .await
+        .expect("toggle");
+    assert_eq!(labels, ["bug"]);
+
+    // Re-importing an edited message keeps the label next to the new
+    // metadata.
+    let mut reimported = messages[1].clone();
+    reimported.content = "WAL checkpoints stall under load".to_string();
+    reimported.metadata = serde_json::json!({"cwd": "/srv"});
+    db.insert_message(&reimported).await.expect("reimport");
+    let stored = db.get_messages(conv.id).await.expect("messages");
+    assert_eq!(
+        stored[1].metadata,
+        serde_json::json!({"cwd": "/srv", "labels": ["bug"]})
+    );
+
+    let search = |query: &'static str| {
+        let db = &db;
+        async move {
+            db.search(query, SearchOptions::default())
+                .await
+                .expect("search")
+                .into_iter()
+                .map(|hit| hit.message_idx)
+                .collect::<Vec<_>>()
+        }
+    };
+    assert_eq!(search("wal").await.len(), 2);
+    assert_eq!(search("wal label:bug").await, [1]);
+    assert_eq!(search("label:bug").await, [1]);
+    assert!(search("label:insight").await.is_empty());
+
+    assert!(
+        db.toggle_message_label(messages[1].id, "bug")
+            .await
+            .expect("untoggle")
+            .is_empty()
+    );
+    assert!(search("label:bug").await.is_empty());
+    assert!(
+        db.toggle_message_label(Uuid::new_v4(), "bug")
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn search_with_source_filter() {
+    let db_path = temp_db_path();
+    let db = Database::open(&db_path).await.expect("open db");
+
+    // Create two sources
     let source1 = Source {
         id: "source-1".to_string(),
         adapter: "test".to_string(),
@@ -903,6 +1599,7 @@ async fn search_with_source_filter() {
             provider: None,
             harness: None,
             client_id: None,
+            parent_message_id: None,
         };
         db.insert_message(&msg).await.expect("insert");
     }
@@ -926,6 +1623,284 @@ async fn search_with_source_filter() {
     assert_eq!(filtered[0].source_id, "source-1");
 }
 
+#[tokio::test]
+async fn search_ranking_boosts_recent_titled_and_preferred_sources() {
+    use hstry_core::config::RankingConfig;
+
+    async fn open(path: &std::path::Path, ranking: RankingConfig) -> Database {
+        let options = DatabaseOptions {
+            ranking,
+            ..Default::default()
+        };
+        Database::open_with(path, &options).await.expect("open db")
+    }
+
+    let db_path = temp_db_path();
+    let db = open(&db_path, RankingConfig::neutral()).await;
+    for (source_id, title, days_ago) in [
+        ("codex", "Borrow checker fight", 1000),
+        ("chatgpt", "Unrelated", 2),
+    ] {
+        db.upsert_source(&Source {
+            id: source_id.to_string(),
+            adapter: source_id.to_string(),
+            path: None,
+            last_sync_at: None,
+            config: serde_json::json!({}),
+        })
+        .await
+        .expect("upsert source");
+        let created_at = Utc::now() - chrono::Duration::days(days_ago);
+        let conv = Conversation {
+            id: Uuid::new_v4(),
+            source_id: source_id.to_string(),
+            external_id: Some(format!("{source_id}-conv")),
+            readable_id: None,
+            platform_id: None,
+            title: Some(title.to_string()),
+            created_at,
+            updated_at: None,
+            model: None,
+            provider: None,
+            workspace: None,
+            tokens_in: None,
+            tokens_out: None,
+            cost_usd: None,
+            metadata: serde_json::json!({}),
+            harness: None,
+            version: 0,
+            message_count: 0,
+            parent_conversation_id: None,
+            parent_message_idx: None,
+            fork_type: None,
+        };
+        db.upsert_conversation(&conv).await.expect("upsert");
+        db.insert_message(&Message {
+            id: Uuid::new_v4(),
+            conversation_id: conv.id,
+            idx: 0,
+            role: MessageRole::User,
+            content: "the borrow checker rejects this".to_string(),
+            parts_json: serde_json::json!([]),
+            created_at: Some(created_at),
+            model: None,
+            tokens: None,
+            cost_usd: None,
+            metadata: serde_json::json!({}),
+            sender: None,
+            provider: None,
+            harness: None,
+            client_id: None,
+            parent_message_id: None,
+        })
+        .await
+        .expect("insert");
+    }
+    db.close().await;
+
+    let order = |hits: Vec<hstry_core::models::SearchHit>| -> Vec<String> {
+        hits.into_iter().map(|hit| hit.source_id).collect()
+    };
+    let search = |ranking: RankingConfig| {
+        let db_path = db_path.clone();
+        async move {
+            let db = open(&db_path, ranking).await;
+            let hits = db
+                .search("borrow checker", SearchOptions::default())
+                .await
+                .expect("search");
+            db.close().await;
+            hits
+        }
+    };
+
+    let recent = RankingConfig {
+        title_weight: 0.0,
+        ..Default::default()
+    };
+    assert_eq!(order(search(recent).await), ["chatgpt", "codex"]);
+
+    let titled = RankingConfig {
+        title_weight: 3.0,
+        recency_weight: 0.0,
+        ..Default::default()
+    };
+    assert_eq!(order(search(titled).await), ["codex", "chatgpt"]);
+
+    let mut preferred = RankingConfig::default();
+    preferred.sources.insert("chatgpt".to_string(), 0.1);
+    assert_eq!(order(search(preferred).await), ["codex", "chatgpt"]);
+}
+
+#[tokio::test]
+async fn search_tokenizer_change_rebuilds_the_index() {
+    use hstry_core::config::SearchTokenizer;
+
+    async fn open(path: &std::path::Path, tokenizer: Option<SearchTokenizer>) -> Database {
+        let options = DatabaseOptions {
+            tokenizer,
+            ..Default::default()
+        };
+        Database::open_with(path, &options).await.expect("open db")
+    }
+    async fn count(db: &Database, query: &str) -> usize {
+        let opts = SearchOptions {
+            mode: SearchMode::NaturalLanguage,
+            ..Default::default()
+        };
+        db.search(query, opts).await.expect("search").len()
+    }
+
+    let db_path = temp_db_path();
+    let db = open(&db_path, None).await;
+    let conv = setup_conversation(&db).await;
+    for (idx, content) in ["東京で寿司を食べました", "the tests are running"]
+        .into_iter()
+        .enumerate()
+    {
+        db.insert_message(&Message {
+            id: Uuid::new_v4(),
+            conversation_id: conv.id,
+            idx: idx as i32,
+            role: MessageRole::User,
+            content: content.to_string(),
+            parts_json: serde_json::json!([]),
+            created_at: None,
+            model: None,
+            tokens: None,
+            cost_usd: None,
+            metadata: serde_json::json!({}),
+            sender: None,
+            provider: None,
+            harness: None,
+            client_id: None,
+            parent_message_id: None,
+        })
+        .await
+        .expect("insert");
+    }
+    // Porter stems English but sees a run of kanji as one word.
+    assert_eq!(count(&db, "run").await, 1);
+    assert_eq!(count(&db, "寿司を食べ").await, 0);
+    db.close().await;
+
+    let db = open(&db_path, Some(SearchTokenizer::Trigram)).await;
+    assert_eq!(count(&db, "寿司を食べ").await, 1);
+    // Shorter than a trigram: found by scanning.
+    assert_eq!(count(&db, "東京").await, 1);
+    assert_eq!(count(&db, "東京 tests").await, 0);
+    assert_eq!(count(&db, "unn").await, 1);
+    db.close().await;
+
+    // Opening without a configured tokenizer keeps the trigram index.
+    let db = open(&db_path, None).await;
+    assert_eq!(count(&db, "寿司を食べ").await, 1);
+    db.close().await;
+
+    let db = open(&db_path, Some(SearchTokenizer::Unicode)).await;
+    assert_eq!(count(&db, "run").await, 0);
+    assert_eq!(count(&db, "running").await, 1);
+}
+
+#[tokio::test]
+async fn search_index_status_reports_segments_and_optimize_merges_them() {
+    let db_path = temp_db_path();
+    let db = Database::open(&db_path).await.expect("open db");
+    let conv = setup_conversation(&db).await;
+
+    // Each write commits its own index segment.
+    for idx in 0..3 {
+        let msg = Message {
+            id: Uuid::new_v4(),
+            conversation_id: conv.id,
+            idx,
+            role: MessageRole::User,
+            content: format!("segment number {idx}"),
+            parts_json: serde_json::json!([]),
+            created_at: None,
+            model: None,
+            tokens: None,
+            cost_usd: None,
+            metadata: serde_json::json!({}),
+            sender: None,
+            provider: None,
+            harness: None,
+            client_id: None,
+            parent_message_id: None,
+        };
+        db.insert_message(&msg).await.expect("insert");
+    }
+
+    let status = db.search_index_status().await.expect("status");
+    assert_eq!(status.messages, 3);
+    assert!(!status.is_stale());
+    assert_eq!(status.tables.len(), 2);
+    for table in &status.tables {
+        assert_eq!(table.documents, 3, "{}", table.table);
+        assert!(table.segments > 1, "{}", table.table);
+        assert!(table.size_bytes > 0, "{}", table.table);
+    }
+
+    db.optimize_search_fts().await.expect("optimize");
+    let status = db.search_index_status().await.expect("status");
+    for table in &status.tables {
+        assert_eq!(table.segments, 1, "{}", table.table);
+        assert_eq!(table.documents, 3, "{}", table.table);
+    }
+    let hits = db
+        .search("segment", SearchOptions::default())
+        .await
+        .expect("search");
+    assert_eq!(hits.len(), 3);
+}
+
+#[tokio::test]
+async fn catch_up_search_fts_rebuilds_a_stale_index() {
+    let db_path = temp_db_path();
+    let db = Database::open(&db_path).await.expect("open db");
+    let conv = setup_conversation(&db).await;
+    db.insert_message(&Message {
+        id: Uuid::new_v4(),
+        conversation_id: conv.id,
+        idx: 0,
+        role: MessageRole::User,
+        content: "caught up".to_string(),
+        parts_json: serde_json::json!([]),
+        created_at: None,
+        model: None,
+        tokens: None,
+        cost_usd: None,
+        metadata: serde_json::json!({}),
+        sender: None,
+        provider: None,
+        harness: None,
+        client_id: None,
+        parent_message_id: None,
+    })
+    .await
+    .expect("insert");
+    assert!(!db.catch_up_search_fts().await.expect("catch up"));
+
+    // Empty the index behind the database's back.
+    let pool = sqlx::SqlitePool::connect(&format!("sqlite://{}", db_path.display()))
+        .await
+        .expect("connect");
+    sqlx::query("INSERT INTO messages_fts(messages_fts) VALUES('delete-all')")
+        .execute(&pool)
+        .await
+        .expect("clear index");
+    pool.close().await;
+    assert!(db.search_index_status().await.expect("status").is_stale());
+
+    assert!(db.catch_up_search_fts().await.expect("catch up"));
+    assert!(!db.search_index_status().await.expect("status").is_stale());
+    let hits = db
+        .search("caught", SearchOptions::default())
+        .await
+        .expect("search");
+    assert_eq!(hits.len(), 1);
+}
+
 #[tokio::test]
 async fn search_mode_code_explicit() {
     let db_path = temp_db_path();
@@ -948,6 +1923,7 @@ async fn search_mode_code_explicit() {
         provider: None,
         harness: None,
         client_id: None,
+        parent_message_id: None,
     };
     db.insert_message(&msg).await.expect("insert");
 
@@ -1127,6 +2103,7 @@ async fn delete_conversation_removes_messages() {
         provider: None,
         harness: None,
         client_id: None,
+        parent_message_id: None,
     };
     db.insert_message(&msg).await.expect("insert msg");
 
@@ -1145,16 +2122,1672 @@ async fn delete_conversation_removes_messages() {
     assert!(messages.is_empty());
 }
 
-// ============================================================================
-// Database Lifecycle
-// ============================================================================
+#[tokio::test]
+async fn retention_candidates_respect_age_limit_and_exclude_tags() {
+    let db_path = temp_db_path();
+    let db = Database::open(&db_path).await.expect("open db");
+    let template = setup_conversation(&db).await;
+    db.delete_conversation(template.id)
+        .await
+        .expect("delete template");
+
+    let mut ids = Vec::new();
+    for (label, days_ago) in [("old", 100), ("middle", 2), ("new", 1)] {
+        let conv = Conversation {
+            id: Uuid::new_v4(),
+            external_id: Some(format!("retention-{label}")),
+            title: Some(label.to_string()),
+            created_at: Utc::now() - chrono::Duration::days(days_ago),
+            ..template.clone()
+        };
+        db.upsert_conversation(&conv).await.expect("upsert conv");
+        ids.push(conv.id);
+    }
+    let (old, middle) = (ids[0], ids[1]);
+
+    let by_age = db
+        .find_retention_candidates(30, 0, &[])
+        .await
+        .expect("age candidates");
+    assert_eq!(by_age.iter().map(|c| c.id).collect::<Vec<_>>(), vec![old]);
+
+    let by_count = db
+        .find_retention_candidates(0, 1, &[])
+        .await
+        .expect("count candidates");
+    assert_eq!(
+        by_count.iter().map(|c| c.id).collect::<Vec<_>>(),
+        vec![old, middle]
+    );
+
+    // Protected conversations are skipped and don't use up the per-source quota.
+    db.add_conversation_tag(old, "keep").await.expect("tag");
+    let protected = db
+        .find_retention_candidates(30, 2, &["Keep".to_string()])
+        .await
+        .expect("protected candidates");
+    assert!(protected.is_empty());
+
+    assert!(
+        db.find_retention_candidates(0, 0, &[])
+            .await
+            .expect("no limits")
+            .is_empty()
+    );
+}
 
 #[tokio::test]
-async fn database_creates_parent_directories() {
-    let mut path = std::env::temp_dir();
-    path.push(format!("hstry-nested/{}/test.db", Uuid::new_v4()));
+async fn starred_conversations_are_filtered_and_kept_from_retention() {
+    let db_path = temp_db_path();
+    let db = Database::open(&db_path).await.expect("open db");
+    setup_source(&db).await;
+    let conv = Conversation {
+        created_at: Utc::now() - chrono::Duration::days(100),
+        ..conversation_in("old", "/project")
+    };
+    db.upsert_conversation(&conv).await.expect("upsert conv");
+    assert!(!db.is_starred(conv.id).await.expect("is starred"));
 
-    let db = Database::open(&path).await.expect("open");
-    assert!(path.exists());
-    db.close().await;
+    assert!(
+        db.set_conversation_starred(conv.id, true)
+            .await
+            .expect("star")
+    );
+    assert!(
+        !db.set_conversation_starred(Uuid::new_v4(), true)
+            .await
+            .expect("star missing")
+    );
+    // Re-syncing the conversation keeps the star.
+    db.upsert_conversation(&conv).await.expect("re-upsert conv");
+    assert!(db.is_starred(conv.id).await.expect("is starred"));
+    assert_eq!(
+        db.starred_conversation_ids().await.expect("starred ids"),
+        std::collections::HashSet::from([conv.id])
+    );
+
+    let starred = db
+        .list_conversations(ListConversationsOptions {
+            starred: true,
+            ..Default::default()
+        })
+        .await
+        .expect("list starred");
+    assert_eq!(
+        starred.iter().map(|c| c.id).collect::<Vec<_>>(),
+        vec![conv.id]
+    );
+
+    assert!(
+        db.find_retention_candidates(30, 0, &[])
+            .await
+            .expect("candidates")
+            .is_empty()
+    );
+
+    db.set_conversation_starred(conv.id, false)
+        .await
+        .expect("unstar");
+    assert!(
+        db.list_conversations(ListConversationsOptions {
+            starred: true,
+            ..Default::default()
+        })
+        .await
+        .expect("list starred")
+        .is_empty()
+    );
+    assert_eq!(
+        db.find_retention_candidates(30, 0, &[])
+            .await
+            .expect("candidates")
+            .len(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn generated_titles_survive_syncs_until_the_adapter_provides_one() {
+    async fn title(db: &Database, id: Uuid) -> Option<String> {
+        db.get_conversation(id)
+            .await
+            .expect("get")
+            .expect("conversation")
+            .title
+    }
+
+    let db_path = temp_db_path();
+    let db = Database::open(&db_path).await.expect("open db");
+    let untitled = Conversation {
+        title: None,
+        ..setup_conversation(&db).await
+    };
+    db.upsert_conversation(&untitled)
+        .await
+        .expect("upsert conv");
+
+    db.set_generated_title(untitled.id, "Fix the WAL checkpoint", false)
+        .await
+        .expect("set title");
+    db.upsert_conversation(&untitled).await.expect("re-sync");
+    assert_eq!(
+        title(&db, untitled.id).await.as_deref(),
+        Some("Fix the WAL checkpoint")
+    );
+
+    // A title from the adapter takes over a generated one...
+    let titled = Conversation {
+        title: Some("WAL".to_string()),
+        ..untitled.clone()
+    };
+    db.upsert_conversation(&titled).await.expect("re-sync");
+    assert_eq!(title(&db, untitled.id).await.as_deref(), Some("WAL"));
+
+    // ...but not one that replaced it.
+    db.set_generated_title(untitled.id, "Fix the WAL checkpoint", true)
+        .await
+        .expect("set title");
+    db.upsert_conversation(&titled).await.expect("re-sync");
+    assert_eq!(
+        title(&db, untitled.id).await.as_deref(),
+        Some("Fix the WAL checkpoint")
+    );
+}
+
+#[tokio::test]
+async fn vacuum_reclaims_the_pages_a_delete_freed() {
+    let db_path = temp_db_path();
+    let db = Database::open(&db_path).await.expect("open db");
+    let conv = setup_conversation(&db).await;
+    let msg = Message {
+        id: Uuid::new_v4(),
+        conversation_id: conv.id,
+        idx: 0,
+        role: MessageRole::User,
+        content: "see attached".to_string(),
+        parts_json: serde_json::json!([]),
+        created_at: None,
+        model: None,
+        tokens: None,
+        cost_usd: None,
+        metadata: serde_json::json!({}),
+        sender: None,
+        provider: None,
+        harness: None,
+        client_id: None,
+        parent_message_id: None,
+    };
+    db.insert_message(&msg).await.expect("insert");
+    db.insert_attachment("att-1", msg.id, "image/png", None, &[7; 256 * 1024])
+        .await
+        .expect("attach");
+
+    let size = db.database_size().await.expect("size");
+    assert_eq!((size.attachments, size.attachment_bytes), (1, 256 * 1024));
+    let attachments = size
+        .tables
+        .iter()
+        .find(|table| table.name == "attachments")
+        .expect("attachments table");
+    assert!(attachments.table_bytes >= 256 * 1024);
+
+    db.delete_conversation(conv.id).await.expect("delete");
+    let freed = db.database_size().await.expect("size");
+    assert!(freed.free_bytes >= 256 * 1024);
+
+    db.vacuum().await.expect("vacuum");
+    let vacuumed = db.database_size().await.expect("size");
+    assert_eq!((vacuumed.free_bytes, vacuumed.attachments), (0, 0));
+    assert!(vacuumed.bytes < size.bytes);
+}
+
+#[tokio::test]
+async fn backfilled_token_counts_feed_usage_and_survive_resyncs() {
+    use hstry_core::cancel::CancelToken;
+    use hstry_core::tokens::{self, Tokenizer};
+
+    let db_path = temp_db_path();
+    let db = Database::open(&db_path).await.expect("open db");
+    let conv = setup_conversation(&db).await;
+    let message = |idx, role, content: &str, tokens| Message {
+        id: Uuid::new_v4(),
+        conversation_id: conv.id,
+        idx,
+        role,
+        content: content.to_string(),
+        parts_json: serde_json::json!([]),
+        created_at: None,
+        model: None,
+        tokens,
+        cost_usd: None,
+        metadata: serde_json::json!({}),
+        sender: None,
+        provider: None,
+        harness: None,
+        client_id: None,
+        parent_message_id: None,
+    };
+    let messages = vec![
+        message(0, MessageRole::User, "twelve chars", None),
+        message(1, MessageRole::Assistant, "eight ch", None),
+        message(
+            2,
+            MessageRole::Assistant,
+            "counted by the adapter",
+            Some(40),
+        ),
+    ];
+    let sync = |messages: Vec<Message>| {
+        let db = &db;
+        async move {
+            let mut tx = db.begin().await.expect("begin");
+            db.bulk_insert_messages_in_tx(&mut tx, &messages)
+                .await
+                .expect("insert");
+            tx.commit().await.expect("commit");
+        }
+    };
+    sync(messages.clone()).await;
+
+    let tokenizer = Tokenizer::new("estimate").expect("tokenizer");
+    let cancel = CancelToken::new();
+    let stats = tokens::backfill(&db, &tokenizer, None, false, false, &cancel)
+        .await
+        .expect("backfill");
+    assert_eq!((stats.messages, stats.tokens), (2, 5));
+    let usage = db.get_usage_totals().await.expect("usage");
+    assert_eq!((usage.tokens_in, usage.tokens_out), (3, 42));
+
+    // A re-sync without counts keeps the estimates for unchanged messages.
+    let mut resynced = messages;
+    resynced[1].content = "eight characters more".to_string();
+    sync(resynced).await;
+    let usage = db.get_usage_totals().await.expect("usage");
+    assert_eq!((usage.tokens_in, usage.tokens_out), (3, 40));
+
+    let again = tokens::backfill(&db, &tokenizer, None, false, false, &cancel)
+        .await
+        .expect("backfill");
+    assert_eq!((again.messages, again.tokens), (1, 6));
+}
+
+#[tokio::test]
+async fn retention_scope_limits_candidates_to_its_sources() {
+    use hstry_core::config::RetentionScope;
+
+    let db = Database::open(&temp_db_path()).await.expect("open db");
+    let template = setup_conversation(&db).await;
+    let other = Source {
+        id: "other-source".to_string(),
+        ..db.get_source(&template.source_id)
+            .await
+            .expect("get")
+            .expect("exists")
+    };
+    db.upsert_source(&other).await.expect("upsert source");
+    db.delete_conversation(template.id)
+        .await
+        .expect("delete template");
+    let mut ids = Vec::new();
+    for source_id in [&template.source_id, &other.id] {
+        let conv = Conversation {
+            id: Uuid::new_v4(),
+            source_id: source_id.clone(),
+            external_id: Some(format!("scoped-{source_id}")),
+            created_at: Utc::now() - chrono::Duration::days(100),
+            ..template.clone()
+        };
+        db.upsert_conversation(&conv).await.expect("upsert conv");
+        ids.push(conv.id);
+    }
+    let (in_template_source, in_other) = (ids[0], ids[1]);
+
+    let scope = |only: Option<Vec<String>>, skip: Vec<String>| RetentionScope {
+        max_age_days: 30,
+        max_conversations_per_source: 0,
+        only_sources: only,
+        skip_sources: skip,
+        include_starred: false,
+    };
+    let only_other = db
+        .find_retention_candidates_in(&scope(Some(vec![other.id.clone()]), Vec::new()), &[])
+        .await
+        .expect("candidates");
+    assert_eq!(
+        only_other.iter().map(|c| c.id).collect::<Vec<_>>(),
+        vec![in_other]
+    );
+
+    let rest = db
+        .find_retention_candidates_in(&scope(None, vec![other.id.clone()]), &[])
+        .await
+        .expect("candidates");
+    assert_eq!(
+        rest.iter().map(|c| c.id).collect::<Vec<_>>(),
+        vec![in_template_source]
+    );
+}
+
+#[tokio::test]
+async fn add_tag_to_conversations_counts_new_links() {
+    let db = Database::open(&temp_db_path()).await.expect("open db");
+    let conv = setup_conversation(&db).await;
+
+    assert_eq!(
+        db.add_tag_to_conversations(&[conv.id], "Work")
+            .await
+            .expect("tag"),
+        1
+    );
+    assert_eq!(
+        db.add_tag_to_conversations(&[conv.id], "work")
+            .await
+            .expect("tag"),
+        0
+    );
+    assert_eq!(
+        db.get_conversation_tags(conv.id).await.expect("tags"),
+        vec!["work".to_string()]
+    );
+}
+
+#[tokio::test]
+async fn badge_counts_group_by_source_and_workspace() {
+    let db = Database::open(&temp_db_path()).await.expect("open db");
+    let template = setup_conversation(&db).await;
+    let before = Utc::now() - chrono::Duration::hours(1);
+
+    for (external_id, workspace) in [("a", Some("/code/hstry")), ("b", None)] {
+        db.upsert_conversation(&Conversation {
+            id: Uuid::new_v4(),
+            external_id: Some(external_id.to_string()),
+            workspace: workspace.map(str::to_string),
+            created_at: before - chrono::Duration::days(1),
+            ..template.clone()
+        })
+        .await
+        .expect("upsert conv");
+    }
+
+    assert_eq!(
+        db.count_conversations_by_source().await.expect("by source"),
+        vec![("test-source".to_string(), 3)]
+    );
+    assert_eq!(
+        db.count_conversations_by_workspace()
+            .await
+            .expect("by workspace"),
+        vec![("/code/hstry".to_string(), 1)]
+    );
+
+    // Only the template conversation was created after `before`.
+    assert_eq!(db.count_unread_since(before).await.expect("unread"), 1);
+    assert!(db.has_new_since(before).await.expect("has new"));
+    let later = Utc::now() + chrono::Duration::minutes(1);
+    assert_eq!(db.count_unread_since(later).await.expect("unread"), 0);
+    assert!(!db.has_new_since(later).await.expect("has new"));
+}
+
+#[tokio::test]
+async fn change_log_records_conversation_writes() {
+    let db = Database::open(&temp_db_path()).await.expect("open db");
+    assert_eq!(db.latest_change_seq().await.expect("seq"), 0);
+
+    let conv = setup_conversation(&db).await;
+    let after_insert = db.latest_change_seq().await.expect("seq");
+    let changes = db.changes_since(0, 100).await.expect("changes");
+    let kinds: Vec<_> = changes
+        .iter()
+        .map(|change| (change.entity.as_str(), change.kind.as_str()))
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![("source", "insert"), ("conversation", "insert")]
+    );
+    assert_eq!(changes[1].entity_id, conv.id.to_string());
+
+    // Re-upserting the source does not log anything; updating and deleting
+    // the conversation does.
+    setup_source(&db).await;
+    db.upsert_conversation(&Conversation {
+        title: Some("Renamed".to_string()),
+        ..conv.clone()
+    })
+    .await
+    .expect("update conv");
+    db.delete_conversation(conv.id).await.expect("delete conv");
+    let changes = db.changes_since(after_insert, 100).await.expect("changes");
+    let kinds: Vec<_> = changes.iter().map(|change| change.kind.as_str()).collect();
+    assert_eq!(kinds, vec!["update", "delete"]);
+
+    let future = Utc::now() + chrono::Duration::minutes(1);
+    assert_eq!(db.prune_change_log(future).await.expect("prune"), 4);
+    assert!(db.changes_since(0, 100).await.expect("changes").is_empty());
+}
+
+#[tokio::test]
+async fn read_watermark_tracks_newly_ingested_conversations() {
+    let db = Database::open(&temp_db_path()).await.expect("open db");
+    let earlier = Utc::now() - chrono::Duration::minutes(5);
+    let template = setup_conversation(&db).await;
+    // An old conversation ingested just now still counts as new.
+    let old = Conversation {
+        id: Uuid::new_v4(),
+        external_id: Some("old".to_string()),
+        created_at: Utc::now() - chrono::Duration::days(30),
+        ..template.clone()
+    };
+    db.upsert_conversation(&old).await.expect("upsert conv");
+
+    let mut new_ids = db.new_conversation_ids(earlier).await.expect("new ids");
+    new_ids.sort();
+    let mut expected = vec![template.id, old.id];
+    expected.sort();
+    assert_eq!(new_ids, expected);
+
+    let listed = db
+        .list_conversations(ListConversationsOptions {
+            ingested_after: Some(Utc::now() + chrono::Duration::minutes(1)),
+            ..Default::default()
+        })
+        .await
+        .expect("list");
+    assert!(listed.is_empty());
+
+    // Re-syncing an existing conversation does not make it new again.
+    db.mark_all_read("work").await.expect("mark read");
+    let seen = db.last_seen_or_init("work").await.expect("last seen");
+    db.upsert_conversation(&old).await.expect("re-upsert conv");
+    assert!(
+        db.new_conversation_ids(seen)
+            .await
+            .expect("new ids")
+            .is_empty()
+    );
+
+    // The watermark never moves backwards, and profiles are independent.
+    db.set_last_seen("work", earlier)
+        .await
+        .expect("set last seen");
+    assert_eq!(db.last_seen_or_init("work").await.expect("last seen"), seen);
+    db.set_last_seen("home", earlier)
+        .await
+        .expect("set last seen");
+    assert_eq!(
+        db.last_seen_or_init("home")
+            .await
+            .expect("last seen")
+            .timestamp(),
+        earlier.timestamp()
+    );
+}
+
+#[tokio::test]
+async fn export_marks_are_kept_per_target_and_format() {
+    let db = Database::open(&temp_db_path()).await.expect("open db");
+    assert!(
+        db.export_mark("/notes", "markdown")
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    let first = chrono::DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+    let second = chrono::DateTime::from_timestamp(1_700_086_400, 0).unwrap();
+    db.set_export_mark("/notes", "markdown", first)
+        .await
+        .unwrap();
+    db.set_export_mark("/notes", "json", second).await.unwrap();
+    db.set_export_mark("/notes", "markdown", second)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        db.export_mark("/notes", "markdown").await.unwrap(),
+        Some(second)
+    );
+    assert_eq!(
+        db.export_mark("/notes", "json").await.unwrap(),
+        Some(second)
+    );
+    assert!(
+        db.export_mark("/other", "markdown")
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[tokio::test]
+async fn saved_searches_are_replaced_by_name_and_deleted() {
+    use hstry_core::saved_search::{SavedFilters, SavedSearch};
+
+    let db = Database::open(&temp_db_path()).await.expect("open db");
+    let mut search = SavedSearch {
+        name: "rust-errors".to_string(),
+        query: "borrow checker".to_string(),
+        filters: SavedFilters {
+            roles: vec!["assistant".to_string()],
+            after: Some("1w".to_string()),
+            ..Default::default()
+        },
+        created_at: chrono::Utc::now(),
+    };
+    db.save_search(&search).await.expect("save");
+    search.query = "lifetime".to_string();
+    db.save_search(&search).await.expect("replace");
+
+    let searches = db.list_saved_searches().await.expect("list");
+    assert_eq!(searches.len(), 1);
+    assert_eq!(searches[0].query, "lifetime");
+    assert_eq!(searches[0].filters, search.filters);
+
+    assert!(db.delete_saved_search("rust-errors").await.expect("delete"));
+    assert!(!db.delete_saved_search("rust-errors").await.expect("delete"));
+    assert!(
+        db.get_saved_search("rust-errors")
+            .await
+            .expect("get")
+            .is_none()
+    );
+}
+
+#[tokio::test]
+async fn decisions_are_stored_per_workspace_and_scanned_once() {
+    let db = Database::open(&temp_db_path()).await.expect("open db");
+    let mut conv = setup_conversation(&db).await;
+    conv.workspace = Some("/work/app".to_string());
+    db.upsert_conversation(&conv).await.expect("set workspace");
+    for (idx, content) in [
+        "Should we use postgres? Let's use sqlite instead of postgres.",
+        "Done. We decided to keep the schema in one file.",
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let msg = Message {
+            id: Uuid::new_v4(),
+            conversation_id: conv.id,
+            idx: idx as i32,
+            role: MessageRole::User,
+            content: content.to_string(),
+            parts_json: serde_json::json!([]),
+            created_at: Some(Utc::now()),
+            model: None,
+            tokens: None,
+            cost_usd: None,
+            metadata: serde_json::json!({}),
+            sender: None,
+            provider: None,
+            harness: None,
+            client_id: None,
+            parent_message_id: None,
+        };
+        db.insert_message(&msg).await.expect("insert msg");
+    }
+
+    let scanned = hstry_core::decisions::analyze(&db, Some("/work/app"))
+        .await
+        .expect("analyze");
+    assert_eq!(scanned, 1);
+    assert_eq!(
+        hstry_core::decisions::analyze(&db, None)
+            .await
+            .expect("analyze"),
+        0
+    );
+
+    let decisions = db.list_decisions(Some("%app%"), None).await.expect("list");
+    let texts: Vec<&str> = decisions.iter().map(|d| d.text.as_str()).collect();
+    assert_eq!(texts.len(), 2);
+    assert!(texts.contains(&"Let's use sqlite instead of postgres."));
+    assert_eq!(
+        decisions[0].title.as_deref(),
+        Some("Conversation for messages")
+    );
+    assert!(
+        db.list_decisions(Some("/work/other"), None)
+            .await
+            .expect("list")
+            .is_empty()
+    );
+
+    db.delete_conversation(conv.id).await.expect("delete");
+    assert!(
+        db.list_decisions(None, None)
+            .await
+            .expect("list")
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn sensitive_conversations_are_not_scanned_for_decisions() {
+    let db_path = temp_db_path();
+    let decision = |conversation_id, idx| Message {
+        id: Uuid::new_v4(),
+        conversation_id,
+        idx,
+        role: MessageRole::User,
+        content: "Let's use sqlite instead of postgres.".to_string(),
+        parts_json: serde_json::json!([]),
+        created_at: Some(Utc::now()),
+        model: None,
+        tokens: None,
+        cost_usd: None,
+        metadata: serde_json::json!({}),
+        sender: None,
+        provider: None,
+        harness: None,
+        client_id: None,
+        parent_message_id: None,
+    };
+
+    let plain = Database::open(&db_path).await.expect("open db");
+    let conv = setup_conversation(&plain).await;
+    plain
+        .insert_message(&decision(conv.id, 0))
+        .await
+        .expect("insert msg");
+    hstry_core::decisions::analyze(&plain, None)
+        .await
+        .expect("analyze");
+    assert_eq!(
+        plain.list_decisions(None, None).await.expect("list").len(),
+        1
+    );
+    plain.close().await;
+
+    let options = DatabaseOptions {
+        sensitive: SensitiveConfig {
+            sources: vec!["test-source".to_string()],
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let db = Database::open_with(&db_path, &options)
+        .await
+        .expect("open sensitive db");
+    db.create_sensitive_key(&WrappedKey {
+        recipient: age::x25519::Identity::generate().to_public(),
+        identity: Vec::new(),
+    })
+    .await
+    .expect("create key");
+    assert_eq!(db.seal_sensitive_conversations().await.expect("seal"), 1);
+    assert!(
+        db.list_decisions(None, None)
+            .await
+            .expect("list")
+            .is_empty()
+    );
+
+    db.insert_message(&decision(conv.id, 1))
+        .await
+        .expect("insert sealed");
+    assert_eq!(
+        hstry_core::decisions::analyze(&db, None)
+            .await
+            .expect("analyze"),
+        0
+    );
+    assert!(
+        db.list_decisions(None, None)
+            .await
+            .expect("list")
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn sealing_deletes_plaintext_snapshots() {
+    let db_path = temp_db_path();
+    let plain = Database::open(&db_path).await.expect("open db");
+    let conv = setup_conversation(&plain).await;
+    plain
+        .insert_message(&Message {
+            id: Uuid::new_v4(),
+            conversation_id: conv.id,
+            idx: 0,
+            role: MessageRole::User,
+            content: "quokka in a snapshot".to_string(),
+            parts_json: serde_json::json!([]),
+            created_at: Some(Utc::now()),
+            model: None,
+            tokens: None,
+            cost_usd: None,
+            metadata: serde_json::json!({}),
+            sender: None,
+            provider: None,
+            harness: None,
+            client_id: None,
+            parent_message_id: None,
+        })
+        .await
+        .expect("insert msg");
+    plain
+        .create_snapshot(conv.id, "manual")
+        .await
+        .expect("create")
+        .expect("snapshot");
+    plain.close().await;
+
+    let options = DatabaseOptions {
+        sensitive: SensitiveConfig {
+            sources: vec!["test-source".to_string()],
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let db = Database::open_with(&db_path, &options)
+        .await
+        .expect("open sensitive db");
+    db.create_sensitive_key(&WrappedKey {
+        recipient: age::x25519::Identity::generate().to_public(),
+        identity: Vec::new(),
+    })
+    .await
+    .expect("create key");
+    assert_eq!(db.seal_sensitive_conversations().await.expect("seal"), 1);
+    assert!(
+        db.list_snapshots(Some(conv.id), None)
+            .await
+            .expect("list")
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn transfer_conversations_moves_messages_and_tags() {
+    let db = Database::open(&temp_db_path()).await.expect("open db");
+    let archive = Database::open(&temp_db_path()).await.expect("open archive");
+    let conv = setup_conversation(&db).await;
+    for (idx, content) in ["archived quokka question", "archived answer"]
+        .into_iter()
+        .enumerate()
+    {
+        let msg = Message {
+            id: Uuid::new_v4(),
+            conversation_id: conv.id,
+            idx: idx as i32,
+            role: MessageRole::User,
+            content: content.to_string(),
+            parts_json: serde_json::json!([]),
+            created_at: Some(Utc::now()),
+            model: None,
+            tokens: None,
+            cost_usd: None,
+            metadata: serde_json::json!({}),
+            sender: None,
+            provider: None,
+            harness: None,
+            client_id: None,
+            parent_message_id: None,
+        };
+        db.insert_message(&msg).await.expect("insert msg");
+    }
+    db.add_conversation_tag(conv.id, "project-x")
+        .await
+        .expect("tag");
+
+    let moved = db
+        .transfer_conversations(&archive, &[conv.id, Uuid::new_v4()])
+        .await
+        .expect("archive");
+    assert_eq!(moved, 1);
+    assert!(db.get_conversation(conv.id).await.expect("get").is_none());
+    assert!(
+        db.search("quokka", SearchOptions::default())
+            .await
+            .expect("search hot")
+            .is_empty()
+    );
+
+    let archived = archive
+        .get_conversation(conv.id)
+        .await
+        .expect("get archived")
+        .expect("archived exists");
+    assert_eq!(archived.message_count, 2);
+    assert!(
+        archive
+            .get_source("test-source")
+            .await
+            .expect("source")
+            .is_some()
+    );
+    assert_eq!(
+        archive.get_conversation_tags(conv.id).await.expect("tags"),
+        vec!["project-x".to_string()]
+    );
+    let hits = archive
+        .search("quokka", SearchOptions::default())
+        .await
+        .expect("search archive");
+    assert_eq!(hits.len(), 1);
+
+    let restored = archive
+        .transfer_conversations(&db, &[conv.id])
+        .await
+        .expect("unarchive");
+    assert_eq!(restored, 1);
+    assert_eq!(db.get_messages(conv.id).await.expect("msgs").len(), 2);
+    assert!(
+        archive
+            .get_conversation(conv.id)
+            .await
+            .expect("get")
+            .is_none()
+    );
+}
+
+#[tokio::test]
+async fn sensitive_conversations_are_sealed_and_unindexed() {
+    let db_path = temp_db_path();
+    let message = |conversation_id: Uuid, idx: i32, content: &str| Message {
+        id: Uuid::new_v4(),
+        conversation_id,
+        idx,
+        role: MessageRole::User,
+        content: content.to_string(),
+        parts_json: serde_json::json!([]),
+        created_at: Some(Utc::now()),
+        model: None,
+        tokens: None,
+        cost_usd: None,
+        metadata: serde_json::json!({}),
+        sender: None,
+        provider: None,
+        harness: None,
+        client_id: None,
+        parent_message_id: None,
+    };
+
+    let plain = Database::open(&db_path).await.expect("open db");
+    let conv = setup_conversation(&plain).await;
+    plain
+        .insert_message(&message(conv.id, 0, "quokka before the rule"))
+        .await
+        .expect("insert plaintext");
+    plain.close().await;
+
+    let options = DatabaseOptions {
+        sensitive: SensitiveConfig {
+            sources: vec!["test-source".to_string()],
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let db = Database::open_with(&db_path, &options)
+        .await
+        .expect("open sensitive db");
+    let identity = age::x25519::Identity::generate();
+    db.create_sensitive_key(&WrappedKey {
+        recipient: identity.to_public(),
+        identity: Vec::new(),
+    })
+    .await
+    .expect("create key");
+    assert_eq!(db.seal_sensitive_conversations().await.expect("seal"), 1);
+    db.insert_message(&message(conv.id, 1, "quokka after the rule"))
+        .await
+        .expect("insert sealed");
+
+    assert!(db.is_sensitive(conv.id).await.expect("is sensitive"));
+    assert!(
+        db.search("quokka", SearchOptions::default())
+            .await
+            .expect("search")
+            .is_empty()
+    );
+    let locked = db.get_messages(conv.id).await.expect("locked messages");
+    assert_eq!(locked.len(), 2);
+    assert!(locked.iter().all(|msg| msg.content == LOCKED_CONTENT));
+
+    let listed = |exclude_sensitive| ListConversationsOptions {
+        exclude_sensitive,
+        ..Default::default()
+    };
+    assert_eq!(
+        db.list_conversations(listed(false))
+            .await
+            .expect("list")
+            .len(),
+        1
+    );
+    assert!(
+        db.list_conversations(listed(true))
+            .await
+            .expect("list")
+            .is_empty()
+    );
+
+    db.unlock_sensitive(identity.clone());
+    let contents: Vec<String> = db
+        .get_messages(conv.id)
+        .await
+        .expect("unlocked messages")
+        .into_iter()
+        .map(|msg| msg.content)
+        .collect();
+    assert_eq!(
+        contents,
+        ["quokka before the rule", "quokka after the rule"]
+    );
+
+    // A followed unlock session is checked on every read, so locking it
+    // locks a handle that is already open.
+    db.follow_unlock_session(&db_path);
+    assert!(!db.is_unlocked());
+    write_session(
+        &db_path,
+        &identity,
+        Utc::now() + chrono::Duration::minutes(5),
+    )
+    .expect("write session");
+    assert_eq!(
+        db.get_messages(conv.id).await.expect("unlocked messages")[1].content,
+        "quokka after the rule"
+    );
+    assert!(clear_session(&db_path).expect("lock"));
+    assert!(!db.is_unlocked());
+    assert_eq!(
+        db.get_messages(conv.id).await.expect("locked messages")[1].content,
+        LOCKED_CONTENT
+    );
+}
+
+// ============================================================================
+// Database Lifecycle
+// ============================================================================
+
+#[tokio::test]
+async fn database_creates_parent_directories() {
+    let mut path = std::env::temp_dir();
+    path.push(format!("hstry-nested/{}/test.db", Uuid::new_v4()));
+
+    let db = Database::open(&path).await.expect("open");
+    assert!(path.exists());
+    db.close().await;
+}
+
+#[tokio::test]
+async fn changed_conversation_ids_include_inserts_since_cursor() {
+    let db = Database::open(&temp_db_path()).await.expect("open db");
+    let earlier = Utc::now() - chrono::Duration::minutes(5);
+    let template = setup_conversation(&db).await;
+    let old = Conversation {
+        id: Uuid::new_v4(),
+        external_id: Some("old".to_string()),
+        created_at: Utc::now() - chrono::Duration::days(30),
+        ..template.clone()
+    };
+    db.upsert_conversation(&old).await.expect("upsert conv");
+
+    let mut changed = db
+        .changed_conversation_ids(earlier)
+        .await
+        .expect("changed ids");
+    changed.sort();
+    let mut expected = vec![template.id, old.id];
+    expected.sort();
+    assert_eq!(changed, expected);
+
+    let later = Utc::now() + chrono::Duration::minutes(1);
+    assert!(
+        db.changed_conversation_ids(later)
+            .await
+            .expect("changed ids")
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn sync_lock_is_exclusive_until_released_or_stale() {
+    let db = Database::open(&temp_db_path()).await.expect("open db");
+    let stale_before = Utc::now() - chrono::Duration::minutes(5);
+
+    assert!(
+        db.try_lock_source_sync("src", 100, "host-a", stale_before)
+            .await
+            .expect("lock")
+            .is_none()
+    );
+    let holder = db
+        .try_lock_source_sync("src", 200, "host-b", stale_before)
+        .await
+        .expect("lock")
+        .expect("held");
+    assert_eq!(holder.pid, 100);
+    assert_eq!(holder.host, "host-a");
+
+    // A heartbeat older than the cutoff lets another process take over.
+    let future_cutoff = Utc::now() + chrono::Duration::minutes(1);
+    assert!(
+        db.try_lock_source_sync("src", 200, "host-b", future_cutoff)
+            .await
+            .expect("lock")
+            .is_none()
+    );
+
+    // Releasing as the previous holder leaves the new lock in place.
+    db.unlock_source_sync("src", 100, "host-a")
+        .await
+        .expect("unlock");
+    assert_eq!(
+        db.get_sync_lock("src")
+            .await
+            .expect("get")
+            .expect("held")
+            .pid,
+        200
+    );
+    db.unlock_source_sync("src", 200, "host-b")
+        .await
+        .expect("unlock");
+    assert!(db.get_sync_lock("src").await.expect("get").is_none());
+}
+
+#[tokio::test]
+async fn api_tokens_are_found_by_hash_until_revoked() {
+    use hstry_core::api_token::{self, ApiTokenScope};
+
+    let db = Database::open(&temp_db_path()).await.expect("open db");
+    assert!(!db.has_api_tokens().await.expect("has tokens"));
+
+    let secret = api_token::generate_token();
+    let hash = api_token::hash_token(&secret);
+    let created = db
+        .create_api_token(
+            "laptop",
+            ApiTokenScope::Read,
+            &hash,
+            api_token::display_prefix(&secret),
+        )
+        .await
+        .expect("create");
+    assert!(db.has_api_tokens().await.expect("has tokens"));
+
+    let found = db
+        .find_api_token(&hash)
+        .await
+        .expect("find")
+        .expect("token");
+    assert_eq!(found.id, created.id);
+    assert_eq!(found.scope, ApiTokenScope::Read);
+    assert!(!found.scope.allows(ApiTokenScope::Ingest));
+    assert!(
+        db.find_api_token(&api_token::hash_token("hstry_wrong"))
+            .await
+            .expect("find")
+            .is_none()
+    );
+
+    assert_eq!(db.revoke_api_token("laptop").await.expect("revoke"), 1);
+    assert!(db.find_api_token(&hash).await.expect("find").is_none());
+    assert!(!db.has_api_tokens().await.expect("has tokens"));
+    assert_eq!(db.list_api_tokens(true).await.expect("list").len(), 1);
+}
+
+#[tokio::test]
+async fn access_log_filters_by_channel_caller_and_conversation() {
+    use hstry_core::db::AccessLogOptions;
+
+    let db = Database::open(&temp_db_path()).await.expect("open db");
+    let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+    db.record_access(
+        "api",
+        Some("laptop"),
+        "search",
+        Some("sqlite"),
+        &[first, second],
+    )
+    .await
+    .expect("record search");
+    db.record_access("mcp", None, "conversation", None, &[second])
+        .await
+        .expect("record read");
+
+    let all = db
+        .list_access_log(&AccessLogOptions::default())
+        .await
+        .expect("list");
+    assert_eq!(all.len(), 2);
+    assert_eq!(all[0].channel, "mcp");
+    assert_eq!(all[1].caller.as_deref(), Some("laptop"));
+    assert_eq!(all[1].query.as_deref(), Some("sqlite"));
+    assert_eq!(all[1].conversation_ids, vec![first, second]);
+
+    let by_conversation = |conversation_id| AccessLogOptions {
+        conversation_id: Some(conversation_id),
+        ..Default::default()
+    };
+    assert_eq!(
+        db.list_access_log(&by_conversation(second))
+            .await
+            .expect("list")
+            .len(),
+        2
+    );
+    assert_eq!(
+        db.list_access_log(&by_conversation(first))
+            .await
+            .expect("list")
+            .len(),
+        1
+    );
+    let by_caller = AccessLogOptions {
+        channel: Some("api".to_string()),
+        caller: Some("laptop".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(db.list_access_log(&by_caller).await.expect("list").len(), 1);
+
+    assert_eq!(db.prune_access_log(0).await.expect("prune"), 0);
+    assert_eq!(db.prune_access_log(30).await.expect("prune"), 0);
+}
+
+#[tokio::test]
+async fn conversations_are_found_by_id_prefix() {
+    let db_path = temp_db_path();
+    let db = Database::open(&db_path).await.expect("open db");
+    setup_source(&db).await;
+
+    let mut ids = Vec::new();
+    for (external_id, readable_id) in [
+        ("ses_abc_1", "swift-fox"),
+        ("ses_abc_2", "swift-fox-otter"),
+        ("other", "calm-owl"),
+    ] {
+        let conv = Conversation {
+            id: Uuid::new_v4(),
+            source_id: "test-source".to_string(),
+            external_id: Some(external_id.to_string()),
+            readable_id: Some(readable_id.to_string()),
+            platform_id: None,
+            title: None,
+            created_at: Utc::now(),
+            updated_at: None,
+            model: None,
+            provider: None,
+            workspace: None,
+            tokens_in: None,
+            tokens_out: None,
+            cost_usd: None,
+            metadata: serde_json::json!({}),
+            harness: None,
+            version: 0,
+            message_count: 0,
+            parent_conversation_id: None,
+            parent_message_idx: None,
+            fork_type: None,
+        };
+        db.upsert_conversation(&conv).await.expect("upsert");
+        ids.push(conv.id);
+    }
+
+    let found = |prefix: &'static str| {
+        let db = &db;
+        async move {
+            let mut found: Vec<Uuid> = db
+                .find_conversations_by_prefix(prefix, 10)
+                .await
+                .expect("find")
+                .into_iter()
+                .map(|conv| conv.id)
+                .collect();
+            found.sort();
+            found
+        }
+    };
+    let sorted = |mut ids: Vec<Uuid>| {
+        ids.sort();
+        ids
+    };
+
+    assert_eq!(found("ses_abc").await, sorted(vec![ids[0], ids[1]]));
+    assert_eq!(found("ses_abc_2").await, vec![ids[1]]);
+    // `_` is not a wildcard.
+    assert!(found("ses_ab_").await.is_empty());
+    assert_eq!(found("swift").await, sorted(vec![ids[0], ids[1]]));
+    assert_eq!(found("swift-fox").await, vec![ids[1]]);
+    assert_eq!(found("calm-owl").await, Vec::<Uuid>::new());
+    let uuid_prefix = ids[2].to_string()[..8].to_string();
+    let by_uuid = db
+        .find_conversations_by_prefix(&uuid_prefix, 10)
+        .await
+        .expect("find");
+    assert!(by_uuid.iter().any(|conv| conv.id == ids[2]));
+    assert!(found("").await.is_empty());
+}
+
+/// A conversation of `test-source` in `workspace`.
+fn conversation_in(external_id: &str, workspace: &str) -> Conversation {
+    Conversation {
+        id: Uuid::new_v4(),
+        source_id: "test-source".to_string(),
+        external_id: Some(external_id.to_string()),
+        readable_id: None,
+        platform_id: None,
+        title: None,
+        created_at: Utc::now(),
+        updated_at: None,
+        model: None,
+        provider: None,
+        workspace: Some(workspace.to_string()),
+        tokens_in: None,
+        tokens_out: None,
+        cost_usd: None,
+        metadata: serde_json::json!({}),
+        harness: None,
+        version: 0,
+        message_count: 0,
+        parent_conversation_id: None,
+        parent_message_idx: None,
+        fork_type: None,
+    }
+}
+
+#[tokio::test]
+async fn workspace_paths_apply_on_insert_and_to_stored_workspaces() {
+    use hstry_core::config::{WorkspacePathConfig, WorkspaceRewrite};
+
+    let db_path = temp_db_path();
+    let db = Database::open(&db_path).await.expect("open db");
+    setup_source(&db).await;
+    db.upsert_conversation(&conversation_in("old", "/mnt/laptop/code/app"))
+        .await
+        .expect("upsert");
+    db.upsert_conversation(&conversation_in("other", "/srv/other"))
+        .await
+        .expect("upsert");
+    db.close().await;
+
+    let options = DatabaseOptions {
+        workspace_paths: WorkspacePathConfig {
+            rewrites: vec![WorkspaceRewrite {
+                from: "/mnt/laptop/code".to_string(),
+                to: "/srv/code".to_string(),
+            }],
+            ..WorkspacePathConfig::disabled()
+        },
+        ..Default::default()
+    };
+    let db = Database::open_with(&db_path, &options)
+        .await
+        .expect("open db");
+    db.upsert_conversation(&conversation_in("new", "/mnt/laptop/code/app/"))
+        .await
+        .expect("upsert");
+
+    let preview = db
+        .normalize_stored_workspaces(false)
+        .await
+        .expect("preview");
+    assert_eq!(preview.len(), 1);
+    assert_eq!(
+        (preview[0].from.as_str(), preview[0].to.as_str()),
+        ("/mnt/laptop/code/app", "/srv/code/app")
+    );
+    assert_eq!(preview[0].conversations, 1);
+
+    let applied = db.normalize_stored_workspaces(true).await.expect("apply");
+    assert_eq!(applied, preview);
+    assert_eq!(
+        db.count_conversations_by_workspace().await.expect("count"),
+        vec![
+            ("/srv/code/app".to_string(), 2),
+            ("/srv/other".to_string(), 1)
+        ]
+    );
+    assert!(
+        db.normalize_stored_workspaces(false)
+            .await
+            .expect("preview")
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn workspaces_can_be_renamed_and_merged() {
+    let db_path = temp_db_path();
+    let db = Database::open(&db_path).await.expect("open db");
+    setup_source(&db).await;
+    let old = conversation_in("old", "/old/app");
+    db.upsert_conversation(&old).await.expect("upsert");
+    db.upsert_conversation(&conversation_in("current", "/srv/app"))
+        .await
+        .expect("upsert");
+    let msg = Message {
+        id: Uuid::new_v4(),
+        conversation_id: old.id,
+        idx: 0,
+        role: MessageRole::User,
+        content: "Where did the zebra go".to_string(),
+        parts_json: serde_json::json!([]),
+        created_at: Some(Utc::now()),
+        model: None,
+        tokens: None,
+        cost_usd: None,
+        metadata: serde_json::json!({}),
+        sender: None,
+        provider: None,
+        harness: None,
+        client_id: None,
+        parent_message_id: None,
+    };
+    db.insert_message(&msg).await.expect("insert");
+
+    db.rename_workspace("/srv/app", Some("App"))
+        .await
+        .expect("rename");
+    assert_eq!(
+        db.merge_workspaces("/old/app", "/srv/app")
+            .await
+            .expect("merge"),
+        1
+    );
+    assert!(db.merge_workspaces("/srv/app", "/srv/app").await.is_err());
+
+    // Later syncs reporting the merged path land in the target workspace.
+    db.upsert_conversation(&conversation_in("later", "/old/app"))
+        .await
+        .expect("upsert");
+    let workspaces = db.list_workspaces().await.expect("list");
+    assert_eq!(workspaces.len(), 1);
+    assert_eq!(workspaces[0].path, "/srv/app");
+    assert_eq!(workspaces[0].label(), "App");
+    assert_eq!(workspaces[0].aliases, ["/old/app"]);
+    assert_eq!(workspaces[0].conversations, 3);
+
+    let opts = SearchOptions {
+        limit: Some(10),
+        mode: SearchMode::NaturalLanguage,
+        ..Default::default()
+    };
+    let hits = db.search("zebra", opts).await.expect("search");
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].workspace.as_deref(), Some("/srv/app"));
+    assert_eq!(hits[0].workspace_name.as_deref(), Some("App"));
+
+    let previews = db
+        .list_conversation_previews(ListConversationsOptions::default())
+        .await
+        .expect("previews");
+    assert!(
+        previews
+            .iter()
+            .all(|preview| preview.workspace_name.as_deref() == Some("App"))
+    );
+
+    db.rename_workspace("/srv/app", None).await.expect("clear");
+    assert!(db.workspace_names().await.expect("names").is_empty());
+}
+
+#[tokio::test]
+async fn link_detect_relates_copies_across_sources() {
+    use hstry_core::cancel::CancelToken;
+    use hstry_core::links;
+
+    let db_path = temp_db_path();
+    let db = Database::open(&db_path).await.expect("open db");
+    setup_source(&db).await;
+    let web = Source {
+        id: "claude-web".to_string(),
+        adapter: "claude-web".to_string(),
+        path: None,
+        last_sync_at: None,
+        config: serde_json::json!({}),
+    };
+    db.upsert_source(&web).await.expect("upsert source");
+
+    let text = "How do I parse a JSON file in Rust with serde and handle missing \
+                fields gracefully? Use serde_json::from_reader with a BufReader, \
+                derive Deserialize and mark optional fields with serde default.";
+    let mut conversations = Vec::new();
+    for (source_id, external_id, content) in [
+        ("test-source", "session-0123456789", "A short question"),
+        ("claude-web", "session-0123456789", "A short answer"),
+        ("test-source", "log-1", text),
+        ("claude-web", "export-1", text),
+        ("claude-web", "export-2", text),
+    ] {
+        let conv = Conversation {
+            source_id: source_id.to_string(),
+            ..conversation_in(external_id, "/srv/app")
+        };
+        db.upsert_conversation(&conv).await.expect("upsert");
+        let msg = Message {
+            id: Uuid::new_v4(),
+            conversation_id: conv.id,
+            idx: 0,
+            role: MessageRole::User,
+            content: content.to_string(),
+            parts_json: serde_json::json!([]),
+            created_at: Some(Utc::now()),
+            model: None,
+            tokens: None,
+            cost_usd: None,
+            metadata: serde_json::json!({}),
+            sender: None,
+            provider: None,
+            harness: None,
+            client_id: None,
+            parent_message_id: None,
+        };
+        db.insert_message(&msg).await.expect("insert");
+        conversations.push(conv);
+    }
+
+    let cancel = CancelToken::new();
+    let stats = links::detect(&db, links::DEFAULT_THRESHOLD, true, &cancel)
+        .await
+        .expect("dry run");
+    assert_eq!((stats.external_id, stats.content, stats.added), (1, 2, 3));
+    assert!(
+        db.related_conversations(conversations[0].id)
+            .await
+            .expect("related")
+            .is_empty()
+    );
+
+    let stats = links::detect(&db, links::DEFAULT_THRESHOLD, false, &cancel)
+        .await
+        .expect("detect");
+    assert_eq!((stats.scanned, stats.added), (5, 3));
+    let related = db
+        .related_conversations(conversations[0].id)
+        .await
+        .expect("related");
+    assert_eq!(related.len(), 1);
+    assert_eq!(related[0].id, conversations[1].id);
+    assert_eq!(related[0].source_id, "claude-web");
+    assert_eq!(related[0].reason, "external_id");
+
+    // The two exports come from one source; only the log links to them.
+    let related = db
+        .related_conversations(conversations[2].id)
+        .await
+        .expect("related");
+    assert_eq!(related.len(), 2);
+    assert!(related.iter().all(|r| r.reason == "content"));
+    assert!(
+        db.related_conversations(conversations[3].id)
+            .await
+            .expect("related")
+            .iter()
+            .all(|r| r.id == conversations[2].id)
+    );
+
+    let again = links::detect(&db, links::DEFAULT_THRESHOLD, false, &cancel)
+        .await
+        .expect("detect again");
+    assert_eq!(again.added, 0);
+
+    db.delete_conversation(conversations[1].id)
+        .await
+        .expect("delete");
+    assert!(
+        db.related_conversations(conversations[0].id)
+            .await
+            .expect("related")
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn rewritten_messages_keep_their_earlier_text_as_revisions() {
+    let db_path = temp_db_path();
+    let db = Database::open(&db_path).await.expect("open db");
+    let conv = setup_conversation(&db).await;
+    let message = |idx, role, content: &str| Message {
+        id: Uuid::new_v4(),
+        conversation_id: conv.id,
+        idx,
+        role,
+        content: content.to_string(),
+        parts_json: serde_json::json!([]),
+        created_at: Some(Utc::now()),
+        model: Some("gpt-4o".to_string()),
+        tokens: None,
+        cost_usd: None,
+        metadata: serde_json::json!({}),
+        sender: None,
+        provider: None,
+        harness: None,
+        client_id: None,
+        parent_message_id: None,
+    };
+    db.insert_message(&message(0, MessageRole::User, "What is 2+2?"))
+        .await
+        .expect("insert");
+    db.insert_message(&message(1, MessageRole::Assistant, "It is 4"))
+        .await
+        .expect("insert");
+
+    // A reply that was still streaming when first synced only grew.
+    let grown = message(1, MessageRole::Assistant, "It is 4, since 2+2=4.");
+    assert_eq!(db.apply_message_event(&grown).await.expect("apply"), None);
+    let regenerated = message(1, MessageRole::Assistant, "Four.");
+    assert_eq!(
+        db.apply_message_event(&regenerated).await.expect("apply"),
+        Some(RevisionKind::Regenerate)
+    );
+
+    let mut tx = db.begin().await.expect("begin");
+    db.bulk_insert_messages_in_tx(&mut tx, &[message(0, MessageRole::User, "What is 3+3?")])
+        .await
+        .expect("bulk insert");
+    tx.commit().await.expect("commit");
+
+    let revisions = db.message_revisions(conv.id).await.expect("revisions");
+    let summary: Vec<_> = revisions
+        .iter()
+        .map(|r| (r.idx, r.kind, r.content.as_str()))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            (0, RevisionKind::Edit, "What is 2+2?"),
+            (1, RevisionKind::Regenerate, "It is 4, since 2+2=4."),
+        ]
+    );
+    assert_eq!(revisions[1].model.as_deref(), Some("gpt-4o"));
+
+    // Revisions are not part of the opt-in message event stream.
+    assert!(
+        db.get_message_events(conv.id, None, None, None)
+            .await
+            .expect("events")
+            .is_empty()
+    );
+    assert_eq!(db.count_message_events().await.expect("count"), 0);
+}
+
+#[tokio::test]
+async fn snapshots_roll_conversations_back() {
+    let db_path = temp_db_path();
+    let db = Database::open(&db_path).await.expect("open db");
+    let conv = setup_conversation(&db).await;
+    let message = |idx, content: &str| Message {
+        id: Uuid::new_v4(),
+        conversation_id: conv.id,
+        idx,
+        role: MessageRole::User,
+        content: content.to_string(),
+        parts_json: serde_json::json!([]),
+        created_at: Some(Utc::now()),
+        model: None,
+        tokens: None,
+        cost_usd: None,
+        metadata: serde_json::json!({}),
+        sender: None,
+        provider: None,
+        harness: None,
+        client_id: None,
+        parent_message_id: None,
+    };
+    let contents = |messages: Vec<Message>| -> Vec<String> {
+        messages.into_iter().map(|m| m.content).collect()
+    };
+    for (idx, content) in [(0, "first"), (1, "second")] {
+        db.insert_message(&message(idx, content))
+            .await
+            .expect("insert");
+    }
+    let snapshot = db
+        .create_snapshot(conv.id, "manual")
+        .await
+        .expect("create")
+        .expect("snapshot");
+    assert_eq!(snapshot.message_count, 2);
+
+    db.insert_message(&message(1, "rewritten"))
+        .await
+        .expect("rewrite");
+    db.insert_message(&message(2, "third")).await.expect("add");
+    assert_eq!(
+        db.restore_snapshot(snapshot.id).await.expect("restore"),
+        conv.id
+    );
+    assert_eq!(
+        contents(db.get_messages(conv.id).await.expect("messages")),
+        ["first", "second"]
+    );
+    // The state the restore replaced is kept as well.
+    let reasons: Vec<String> = db
+        .list_snapshots(Some(conv.id), None)
+        .await
+        .expect("list")
+        .into_iter()
+        .map(|s| s.reason)
+        .collect();
+    assert_eq!(reasons, ["restore", "manual"]);
+
+    // A deleted conversation is recreated.
+    db.delete_conversation(conv.id).await.expect("delete");
+    db.restore_snapshot(snapshot.id)
+        .await
+        .expect("restore deleted");
+    let restored = db
+        .get_conversation(conv.id)
+        .await
+        .expect("get")
+        .expect("recreated");
+    assert_eq!(restored.message_count, 2);
+    assert_eq!(
+        contents(db.get_messages(conv.id).await.expect("messages")),
+        ["first", "second"]
+    );
+
+    // Automatic snapshots are capped per conversation; manual ones stay.
+    for _ in 0..AUTO_SNAPSHOTS_KEPT + 2 {
+        db.create_snapshot(conv.id, "dedup")
+            .await
+            .expect("snapshot");
+    }
+    let snapshots = db.list_snapshots(Some(conv.id), None).await.expect("list");
+    let automatic = snapshots.iter().filter(|s| s.reason != "manual").count();
+    assert_eq!(i64::try_from(automatic).unwrap(), AUTO_SNAPSHOTS_KEPT);
+    assert!(snapshots.iter().any(|s| s.id == snapshot.id));
 }
@@ -75,6 +75,7 @@ fn make_message(conv_id: uuid::Uuid, idx: i32, role: MessageRole, content: &str)
         provider: None,
         harness: None,
         client_id: None,
+        parent_message_id: None,
     }
 }
 
@@ -194,6 +194,7 @@ async fn messages_persist_across_reopen() {
                 provider: Some("openai".to_string()),
                 harness: Some("pi".to_string()),
                 client_id: Some(format!("client-{}", idx)),
+                parent_message_id: None,
             };
             db.insert_message(&msg).await.expect("insert");
         }
@@ -301,6 +302,7 @@ async fn full_scenario_persists_across_multiple_reopens() {
                 provider: Some("anthropic".to_string()),
                 harness: Some("pi".to_string()),
                 client_id: Some(format!("round1-msg{}", idx)),
+                parent_message_id: None,
             };
             db.insert_message(&msg).await.expect("insert");
         }
@@ -356,6 +358,7 @@ async fn full_scenario_persists_across_multiple_reopens() {
                 provider: Some("anthropic".to_string()),
                 harness: Some("pi".to_string()),
                 client_id: Some(format!("round2-msg{}", idx)),
+                parent_message_id: None,
             };
             db.insert_message(&msg).await.expect("insert round 2");
         }
@@ -535,3 +538,103 @@ async fn partial_update_persists_across_reopen() {
         db.close().await;
     }
 }
+
+#[tokio::test]
+async fn read_pool_sees_committed_writes() {
+    let db_path = temp_db_path();
+    let opts = hstry_core::DatabaseOptions {
+        pools: hstry_core::config::PoolConfig {
+            max_connections: 2,
+            read_connections: 2,
+        },
+        ..Default::default()
+    };
+    let db = Database::open_with(&db_path, &opts).await.expect("open db");
+    assert!(db.has_read_pool());
+
+    let source = Source {
+        id: "read-pool-source".to_string(),
+        adapter: "test".to_string(),
+        path: None,
+        last_sync_at: None,
+        config: serde_json::json!({}),
+    };
+    db.upsert_source(&source).await.expect("upsert source");
+
+    let conv = Conversation {
+        id: Uuid::new_v4(),
+        source_id: "read-pool-source".to_string(),
+        external_id: Some("read-pool-conv".to_string()),
+        readable_id: None,
+        platform_id: None,
+        title: Some("Read pool".to_string()),
+        created_at: Utc::now(),
+        updated_at: None,
+        model: None,
+        provider: None,
+        workspace: None,
+        tokens_in: None,
+        tokens_out: None,
+        cost_usd: None,
+        metadata: serde_json::json!({}),
+        harness: None,
+        version: 0,
+        message_count: 0,
+        parent_conversation_id: None,
+        parent_message_idx: None,
+        fork_type: None,
+    };
+    db.upsert_conversation(&conv).await.expect("upsert");
+
+    let fetched = db
+        .get_conversation(conv.id)
+        .await
+        .expect("get")
+        .expect("visible through read pool");
+    assert_eq!(fetched.title, Some("Read pool".to_string()));
+    assert_eq!(db.count_conversations().await.expect("count"), 1);
+
+    db.close().await;
+}
+
+#[cfg(not(feature = "sqlcipher"))]
+#[tokio::test]
+async fn encryption_key_rejected_without_sqlcipher() {
+    let db_path = temp_db_path();
+    let opts = hstry_core::DatabaseOptions {
+        key: Some("secret".to_string()),
+        ..Default::default()
+    };
+    assert!(Database::open_with(&db_path, &opts).await.is_err());
+    assert!(!db_path.exists(), "plain build must not create the file");
+}
+
+#[cfg(feature = "sqlcipher")]
+#[tokio::test]
+async fn encrypted_database_requires_key() {
+    let db_path = temp_db_path();
+    let opts = hstry_core::DatabaseOptions {
+        key: Some("secret".to_string()),
+        ..Default::default()
+    };
+    {
+        let db = Database::open_with(&db_path, &opts).await.expect("open db");
+        let source = Source {
+            id: "cipher-source".to_string(),
+            adapter: "test".to_string(),
+            path: None,
+            last_sync_at: None,
+            config: serde_json::json!({}),
+        };
+        db.upsert_source(&source).await.expect("upsert source");
+        db.close().await;
+    }
+
+    assert!(Database::open(&db_path).await.is_err());
+
+    let db = Database::open_with(&db_path, &opts)
+        .await
+        .expect("reopen db");
+    assert!(db.get_source("cipher-source").await.expect("get").is_some());
+    db.close().await;
+}
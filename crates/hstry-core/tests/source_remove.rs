@@ -64,6 +64,7 @@ async fn remove_source_deletes_conversations_and_messages() -> Result<()> {
         provider: None,
         harness: None,
         client_id: None,
+        parent_message_id: None,
     };
     db.insert_message(&message).await?;
 
@@ -146,6 +147,7 @@ async fn search_returns_snippet_and_ids() -> Result<()> {
         provider: None,
         harness: None,
         client_id: None,
+        parent_message_id: None,
     };
     db.insert_message(&message).await?;
 
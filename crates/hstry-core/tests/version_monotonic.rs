@@ -57,6 +57,7 @@ fn test_message(conversation_id: Uuid, idx: i32) -> Message {
         provider: None,
         harness: None,
         client_id: None,
+        parent_message_id: None,
     }
 }
 
@@ -1,5 +1,6 @@
 use std::io::{self, Write};
 use std::path::PathBuf;
+use std::sync::Arc;
 
 use anyhow::Result;
 use clap::{Args, Parser};
@@ -11,7 +12,20 @@ use rmcp::{
     transport::io::stdio,
 };
 
-use hstry_core::Config;
+use hstry_core::agent_search::{self, AgentSearchConfig};
+use hstry_core::config::McpScope;
+use hstry_core::db::{DatabaseOptions, SearchOptions};
+use hstry_core::models::Conversation;
+use hstry_core::{Config, Database};
+
+/// Most hits `search_history` returns.
+const MAX_SEARCH_LIMIT: usize = 100;
+
+/// `ids` without duplicates, in first-seen order.
+fn unique_ids(ids: impl Iterator<Item = uuid::Uuid>) -> Vec<uuid::Uuid> {
+    let mut seen = std::collections::HashSet::new();
+    ids.filter(|id| seen.insert(*id)).collect()
+}
 
 fn main() {
     if let Err(err) = try_main() {
@@ -28,14 +42,35 @@ async fn try_main() -> Result<()> {
         .config
         .unwrap_or_else(Config::default_config_path);
     let config = Config::ensure_at(&config_path)?;
+    let scope = config.mcp.scope(cli.scope.as_deref())?;
+    // Sensitive conversations stay sealed unless `[sensitive] mcp` is set.
+    let db = if config.sensitive.mcp {
+        Database::open_config(&config).await?
+    } else {
+        Database::open_with(
+            &config.database.path,
+            &DatabaseOptions::from_config(&config, false)?,
+        )
+        .await?
+    };
+
+    if let Err(err) = db.prune_access_log(config.access_log.max_age_days).await {
+        let _ = writeln!(io::stderr(), "failed to prune the access log: {err}");
+    }
 
-    let server = McpServer::new(config);
+    let server = McpServer::new(config, Arc::new(db), scope, cli.scope);
     let transport = stdio();
 
-    server
+    let running = server
         .serve(transport)
         .await
         .map_err(|e| anyhow::anyhow!("MCP server error: {e}"))?;
+    // `serve` returns once the handshake is done; keep answering requests
+    // until the client disconnects.
+    running
+        .waiting()
+        .await
+        .map_err(|e| anyhow::anyhow!("MCP server error: {e}"))?;
 
     Ok(())
 }
@@ -45,6 +80,11 @@ async fn try_main() -> Result<()> {
 struct Cli {
     #[command(flatten)]
     common: CommonOpts,
+
+    /// Restrict sources, workspaces and message size to the named
+    /// `[mcp.scopes.<name>]` entry
+    #[arg(long, env = "HSTRY_MCP_SCOPE", value_name = "NAME")]
+    scope: Option<String>,
 }
 
 #[derive(Debug, Clone, Args)]
@@ -60,19 +100,106 @@ struct EchoRequest {
     message: String,
 }
 
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct SearchHistoryRequest {
+    #[schemars(description = "Full-text search query")]
+    query: String,
+    #[schemars(description = "Maximum number of hits (default 20, at most 100)")]
+    limit: Option<usize>,
+    #[schemars(
+        description = "\"agent\" (default): deduplicated excerpts without tool output or system context; \"raw\": every hit with its full-text snippet"
+    )]
+    mode: Option<SearchHistoryMode>,
+    #[schemars(description = "Agent mode: maximum characters per excerpt (default 400)")]
+    max_chars: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, Default, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "lowercase")]
+enum SearchHistoryMode {
+    #[default]
+    Agent,
+    Raw,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct GetConversationRequest {
+    #[schemars(description = "Conversation UUID or readable id from search_history")]
+    id: String,
+}
+
 #[derive(Clone)]
 struct McpServer {
     config: Config,
+    db: Arc<Database>,
+    /// Sources, workspaces and message size this server may expose.
+    scope: Arc<McpScope>,
+    /// Name of the scope, recorded as the caller in the access log.
+    scope_name: Option<String>,
     tool_router: ToolRouter<Self>,
 }
 
 impl McpServer {
-    fn new(config: Config) -> Self {
+    fn new(config: Config, db: Arc<Database>, scope: McpScope, scope_name: Option<String>) -> Self {
         Self {
             config,
+            db,
+            scope: Arc::new(scope),
+            scope_name,
             tool_router: Self::tool_router(),
         }
     }
+
+    /// Record a read in the access log when `[access_log]` is enabled.
+    async fn log_access(&self, action: &str, query: Option<&str>, ids: &[uuid::Uuid]) {
+        if !self.config.access_log.enabled {
+            return;
+        }
+        if let Err(err) = self
+            .db
+            .record_access("mcp", self.scope_name.as_deref(), action, query, ids)
+            .await
+        {
+            let _ = writeln!(
+                io::stderr(),
+                "failed to record {action} in the access log: {err}"
+            );
+        }
+    }
+
+    /// Find a conversation the scope allows. Conversations outside the scope,
+    /// and sensitive ones unless `[sensitive] mcp` is set, are reported as
+    /// missing so their existence is not revealed.
+    async fn visible_conversation(&self, id: &str) -> Result<Option<Conversation>> {
+        let conv = match uuid::Uuid::parse_str(id) {
+            Ok(uuid) => self.db.get_conversation(uuid).await?,
+            Err(_) => None,
+        };
+        let conv = match conv {
+            Some(conv) => Some(conv),
+            None => {
+                self.db
+                    .get_conversation_by_reference(None, Some(id), Some(id), None, None)
+                    .await?
+            }
+        };
+        let Some(conv) = conv else {
+            return Ok(None);
+        };
+        if !self.config.sensitive.mcp && self.db.is_sensitive(conv.id).await? {
+            return Ok(None);
+        }
+        let adapter = self
+            .db
+            .get_source(&conv.source_id)
+            .await?
+            .map(|source| source.adapter)
+            .unwrap_or_default();
+        Ok(self
+            .scope
+            .allows(&conv.source_id, &adapter, conv.workspace.as_deref())
+            .then_some(conv))
+    }
 }
 
 #[tool_router]
@@ -97,6 +224,146 @@ impl McpServer {
         tokio::task::yield_now().await;
         serde_json::to_string_pretty(&self.config.service).unwrap_or_else(|_| "{}".to_string())
     }
+
+    /// List the sources this server exposes
+    #[tool(description = "Lists the chat history sources available to search")]
+    async fn list_sources(&self) -> Result<String, String> {
+        let sources = self.db.list_sources().await.map_err(|e| e.to_string())?;
+        let visible: Vec<_> = sources
+            .iter()
+            .filter(|source| self.scope.allows_source(&source.id, &source.adapter))
+            .map(|source| serde_json::json!({ "id": source.id, "adapter": source.adapter }))
+            .collect();
+        serde_json::to_string_pretty(&visible).map_err(|e| e.to_string())
+    }
+
+    /// Search conversation history
+    #[tool(
+        description = "Searches chat history and returns matching messages with their conversation ids"
+    )]
+    async fn search_history(
+        &self,
+        Parameters(req): Parameters<SearchHistoryRequest>,
+    ) -> Result<String, String> {
+        let limit = req.limit.unwrap_or(20).clamp(1, MAX_SEARCH_LIMIT);
+        // Search each allowed source separately so a busy source outside the
+        // scope cannot crowd out the allowed ones, and over-fetch so enough
+        // hits remain after dropping other workspaces (and, in agent mode,
+        // noise).
+        let source_ids = if self.scope.sources.is_empty() {
+            vec![None]
+        } else {
+            self.db
+                .list_sources()
+                .await
+                .map_err(|e| e.to_string())?
+                .into_iter()
+                .filter(|source| self.scope.allows_source(&source.id, &source.adapter))
+                .map(|source| Some(source.id))
+                .collect()
+        };
+        let mut hits = Vec::new();
+        for source_id in source_ids {
+            let opts = SearchOptions {
+                source_id,
+                limit: Some((limit * 5) as i64),
+                ..Default::default()
+            };
+            hits.extend(
+                self.db
+                    .search(&req.query, opts)
+                    .await
+                    .map_err(|e| e.to_string())?,
+            );
+        }
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+        hits.retain(|hit| {
+            self.scope.allows(
+                &hit.source_id,
+                &hit.source_adapter,
+                hit.workspace.as_deref(),
+            )
+        });
+
+        if let SearchHistoryMode::Agent = req.mode.unwrap_or_default() {
+            let config = AgentSearchConfig {
+                excerpt_chars: req
+                    .max_chars
+                    .unwrap_or(AgentSearchConfig::default().excerpt_chars),
+                ..AgentSearchConfig::default()
+            };
+            let mut ranked = agent_search::rank_for_agents(&hits, &config, limit);
+            for hit in &mut ranked {
+                hit.excerpt = self.scope.mask(&hit.excerpt).into_owned();
+            }
+            let ids = unique_ids(ranked.iter().map(|hit| hit.conversation_id));
+            self.log_access("search", Some(&req.query), &ids).await;
+            return serde_json::to_string_pretty(&ranked).map_err(|e| e.to_string());
+        }
+
+        let ids = unique_ids(hits.iter().take(limit).map(|hit| hit.conversation_id));
+        self.log_access("search", Some(&req.query), &ids).await;
+        let results: Vec<_> = hits
+            .iter()
+            .take(limit)
+            .map(|hit| {
+                serde_json::json!({
+                    "conversation_id": hit.conversation_id,
+                    "readable_id": hit.readable_id,
+                    "title": hit.title,
+                    "source": hit.source_id,
+                    "workspace": hit.workspace,
+                    "message_idx": hit.message_idx,
+                    "role": hit.role,
+                    "created_at": hit.created_at,
+                    "snippet": self.scope.mask(&hit.snippet),
+                })
+            })
+            .collect();
+        serde_json::to_string_pretty(&results).map_err(|e| e.to_string())
+    }
+
+    /// Read one conversation
+    #[tool(description = "Returns a conversation's messages by id")]
+    async fn get_conversation(
+        &self,
+        Parameters(req): Parameters<GetConversationRequest>,
+    ) -> Result<String, String> {
+        let conv = self
+            .visible_conversation(&req.id)
+            .await
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Conversation not found: {}", req.id))?;
+        let messages = self
+            .db
+            .get_messages(conv.id)
+            .await
+            .map_err(|e| e.to_string())?;
+        self.log_access("conversation", None, &[conv.id]).await;
+        let messages: Vec<_> = messages
+            .iter()
+            .map(|message| {
+                serde_json::json!({
+                    "idx": message.idx,
+                    "role": message.role,
+                    "created_at": message.created_at,
+                    "content": self.scope.mask(&message.content),
+                })
+            })
+            .collect();
+        serde_json::to_string_pretty(&serde_json::json!({
+            "id": conv.id,
+            "readable_id": conv.readable_id,
+            "title": conv.title,
+            "source": conv.source_id,
+            "workspace": conv.workspace,
+            "created_at": conv.created_at,
+            "updated_at": conv.updated_at,
+            "messages": messages,
+        }))
+        .map_err(|e| e.to_string())
+    }
 }
 
 #[tool_handler(router = self.tool_router)]
@@ -3,14 +3,19 @@
 //! This crate provides the runtime for executing TypeScript adapters
 //! using Bun, Deno, or Node.js.
 
+pub mod logs;
+mod pool;
 pub mod runner;
 
 pub use runner::AdapterRequest;
 pub use runner::AdapterResponse;
 pub use runner::AdapterRunner;
+pub use runner::AdapterTimeout;
+pub use runner::ConversationStream;
 pub use runner::ExportConversation;
 pub use runner::ExportOptions;
 pub use runner::ExportResult;
 pub use runner::ParsedMessage;
 pub use runner::Runtime;
 pub use runner::RuntimeKind;
+pub use runner::StreamEvent;
@@ -0,0 +1,205 @@
+//! Per-run adapter log files.
+//!
+//! Each adapter call that does real work (parse, parseStream, export) or
+//! writes to stderr leaves a log under `<log_dir>/<adapter>/`, named
+//! `<unix-ms>-<seq>-<method>.log` so a plain sort is chronological. Only the
+//! newest [`MAX_LOGS_PER_ADAPTER`] files per adapter are kept. Errors
+//! from failed runs quote the last [`ERROR_STDERR_LINES`] lines of stderr
+//! and point at the full log.
+
+use std::fs::{self, OpenOptions};
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Log files kept per adapter; older ones are removed after each write.
+pub const MAX_LOGS_PER_ADAPTER: usize = 20;
+
+/// Lines of stderr quoted in adapter errors.
+pub const ERROR_STDERR_LINES: usize = 10;
+
+static SEQ: AtomicU32 = AtomicU32::new(0);
+
+/// One log file on disk.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct AdapterLog {
+    pub adapter: String,
+    pub method: String,
+    /// Unix milliseconds when the run finished.
+    pub timestamp_ms: i64,
+    pub path: PathBuf,
+    pub size_bytes: u64,
+}
+
+/// What a single adapter run produced.
+pub(crate) struct RunRecord<'a> {
+    pub adapter: &'a str,
+    pub method: &'a str,
+    pub path: Option<&'a str>,
+    pub status: &'a str,
+    pub stdout_bytes: usize,
+    pub stderr: &'a [u8],
+}
+
+/// Write `record` under `log_dir` and prune old logs for the adapter.
+/// Returns the new file's path.
+pub(crate) fn write_log(log_dir: &Path, record: &RunRecord<'_>) -> std::io::Result<PathBuf> {
+    let dir = log_dir.join(record.adapter);
+    fs::create_dir_all(&dir)?;
+
+    let now_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    let (path, mut file) = loop {
+        let seq = SEQ.fetch_add(1, Ordering::Relaxed) % 10_000;
+        let path = dir.join(format!("{now_ms:013}-{seq:04}-{}.log", record.method));
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => break (path, file),
+            Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => {}
+            Err(err) => return Err(err),
+        }
+    };
+
+    writeln!(file, "adapter: {}", record.adapter)?;
+    writeln!(file, "method: {}", record.method)?;
+    if let Some(input) = record.path {
+        writeln!(file, "path: {input}")?;
+    }
+    writeln!(file, "status: {}", record.status)?;
+    writeln!(file, "stdout: {} bytes", record.stdout_bytes)?;
+    writeln!(file, "--- stderr ---")?;
+    file.write_all(record.stderr)?;
+    drop(file);
+
+    prune(&dir, MAX_LOGS_PER_ADAPTER)?;
+    Ok(path)
+}
+
+fn prune(dir: &Path, keep: usize) -> std::io::Result<()> {
+    let mut files = log_files(dir)?;
+    if files.len() > keep {
+        files.sort();
+        for old in &files[..files.len() - keep] {
+            let _ = fs::remove_file(old);
+        }
+    }
+    Ok(())
+}
+
+fn log_files(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    Ok(fs::read_dir(dir)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "log"))
+        .collect())
+}
+
+/// The last `max_lines` non-blank lines of `stderr`, with an
+/// "(N earlier lines omitted)" marker when some were cut.
+pub fn stderr_tail(stderr: &[u8], max_lines: usize) -> String {
+    let text = String::from_utf8_lossy(stderr);
+    let lines: Vec<&str> = text
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.is_empty())
+        .collect();
+    let skipped = lines.len().saturating_sub(max_lines);
+    let mut tail = String::new();
+    if skipped > 0 {
+        tail.push_str(&format!("({skipped} earlier lines omitted)\n"));
+    }
+    tail.push_str(&lines[skipped..].join("\n"));
+    tail
+}
+
+/// List logs under `log_dir`, newest first, optionally for one adapter.
+pub fn list_logs(log_dir: &Path, adapter: Option<&str>) -> Vec<AdapterLog> {
+    let adapters: Vec<String> = match adapter {
+        Some(name) => vec![name.to_string()],
+        None => fs::read_dir(log_dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .filter(|entry| entry.path().is_dir())
+                    .filter_map(|entry| entry.file_name().to_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default(),
+    };
+
+    let mut logs = Vec::new();
+    for name in adapters {
+        for path in log_files(&log_dir.join(&name)).unwrap_or_default() {
+            let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            let mut fields = stem.splitn(3, '-');
+            let (Some(ts), Some(_seq), Some(method)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+            let Ok(timestamp_ms) = ts.parse() else {
+                continue;
+            };
+            logs.push(AdapterLog {
+                adapter: name.clone(),
+                method: method.to_string(),
+                timestamp_ms,
+                size_bytes: fs::metadata(&path).map(|m| m.len()).unwrap_or_default(),
+                path,
+            });
+        }
+    }
+    logs.sort_by(|a, b| b.path.file_name().cmp(&a.path.file_name()));
+    logs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(stderr: &[u8]) -> RunRecord<'_> {
+        RunRecord {
+            adapter: "chatgpt",
+            method: "parse",
+            path: Some("/exports"),
+            status: "exit status: 0",
+            stdout_bytes: 2,
+            stderr,
+        }
+    }
+
+    #[test]
+    fn writes_rotates_and_lists_newest_first() {
+        let dir = tempfile::TempDir::new().expect("tempdir");
+        let mut written = Vec::new();
+        for _ in 0..MAX_LOGS_PER_ADAPTER + 5 {
+            written.push(write_log(dir.path(), &record(b"warn: skipped file\n")).expect("write"));
+        }
+
+        let logs = list_logs(dir.path(), None);
+        assert_eq!(logs.len(), MAX_LOGS_PER_ADAPTER);
+        assert_eq!(logs[0].path, *written.last().expect("last"));
+        assert_eq!(logs[0].adapter, "chatgpt");
+        assert_eq!(logs[0].method, "parse");
+        assert!(!written[0].exists());
+
+        let content = fs::read_to_string(&logs[0].path).expect("read");
+        assert!(content.contains("path: /exports"));
+        assert!(content.ends_with("--- stderr ---\nwarn: skipped file\n"));
+        assert!(list_logs(dir.path(), Some("codex")).is_empty());
+    }
+
+    #[test]
+    fn stderr_tail_keeps_the_last_lines() {
+        assert_eq!(stderr_tail(b"", 3), "");
+        assert_eq!(stderr_tail(b"one\n\ntwo  \n", 3), "one\ntwo");
+        assert_eq!(
+            stderr_tail(b"1\n2\n3\n4\n5\n", 2),
+            "(3 earlier lines omitted)\n4\n5"
+        );
+    }
+}
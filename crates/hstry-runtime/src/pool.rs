@@ -0,0 +1,294 @@
+//! Persistent adapter processes.
+//!
+//! Starting a JS runtime costs more than most `info` and `detect` calls, so
+//! `hstry scan` and import auto-detection used to spend most of their time
+//! spawning processes. A [`WorkerPool`] keeps adapter processes running in
+//! worker mode (`HSTRY_WORKER=1`), where `runAdapter` reads one JSON-RPC
+//! request per line on stdin and answers each with one line on stdout:
+//!
+//! ```text
+//! -> {"jsonrpc":"2.0","id":1,"method":"detect","params":{"path":"/data"}}
+//! <- {"jsonrpc":"2.0","id":1,"result":0.9,"stderr":""}
+//! ```
+//!
+//! `stderr` carries the console output of that request so per-run logs
+//! keep working. Workers idle for longer than the pool's timeout, and
+//! workers whose `adapter.ts` changed since they started, are shut down.
+//! Adapters built before worker mode exit instead of answering; the pool
+//! remembers them and the runner spawns a process per call as before.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant, SystemTime};
+
+use serde::Deserialize;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout};
+
+use crate::runner::AdapterRequest;
+
+/// Idle workers kept per adapter; more are started while calls overlap and
+/// shut down when they finish.
+const MAX_IDLE_PER_ADAPTER: usize = 2;
+
+/// Idle adapter processes, per adapter file.
+pub(crate) struct WorkerPool {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    idle_timeout: Duration,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    idle: HashMap<PathBuf, Vec<Worker>>,
+    /// Adapters whose process exited instead of serving requests.
+    unsupported: HashSet<PathBuf>,
+    reaper_running: bool,
+}
+
+impl WorkerPool {
+    pub(crate) fn new(idle_timeout: Duration) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                idle_timeout,
+                state: Mutex::new(State::default()),
+            }),
+        }
+    }
+
+    /// Whether the adapter can run as a worker, as far as is known.
+    pub(crate) fn supports(&self, adapter_path: &Path) -> bool {
+        !self.inner.lock().unsupported.contains(adapter_path)
+    }
+
+    /// Stop trying to run the adapter as a worker.
+    pub(crate) fn mark_unsupported(&self, adapter_path: &Path) {
+        let mut state = self.inner.lock();
+        state.unsupported.insert(adapter_path.to_path_buf());
+        state.idle.remove(adapter_path);
+    }
+
+    /// Take an idle worker for the adapter, if one is still current.
+    pub(crate) fn checkout(&self, adapter_path: &Path) -> Option<Worker> {
+        let modified = modified(adapter_path);
+        let mut state = self.inner.lock();
+        let workers = state.idle.get_mut(adapter_path)?;
+        while let Some(worker) = workers.pop() {
+            if worker.last_used.elapsed() < self.inner.idle_timeout && worker.modified == modified {
+                return Some(worker);
+            }
+        }
+        None
+    }
+
+    /// Return a worker after a successful call.
+    pub(crate) fn checkin(&self, adapter_path: &Path, mut worker: Worker) {
+        worker.last_used = Instant::now();
+        let mut state = self.inner.lock();
+        let workers = state.idle.entry(adapter_path.to_path_buf()).or_default();
+        if workers.len() < MAX_IDLE_PER_ADAPTER {
+            workers.push(worker);
+        }
+        if !state.reaper_running {
+            state.reaper_running = true;
+            tokio::spawn(reap(Arc::downgrade(&self.inner)));
+        }
+    }
+}
+
+impl Inner {
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Shut down workers once they have been idle for the timeout. Stops when
+/// the pool is dropped or has no workers left.
+async fn reap(pool: Weak<Inner>) {
+    loop {
+        let Some(timeout) = pool.upgrade().map(|inner| inner.idle_timeout) else {
+            return;
+        };
+        tokio::time::sleep(timeout).await;
+        let Some(inner) = pool.upgrade() else {
+            return;
+        };
+        let mut state = inner.lock();
+        for workers in state.idle.values_mut() {
+            workers.retain(|worker| worker.last_used.elapsed() < timeout);
+        }
+        state.idle.retain(|_, workers| !workers.is_empty());
+        if state.idle.is_empty() {
+            state.reaper_running = false;
+            return;
+        }
+    }
+}
+
+fn modified(adapter_path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(adapter_path)
+        .and_then(|meta| meta.modified())
+        .ok()
+}
+
+/// A running adapter process in worker mode. Dropping it kills the process.
+pub(crate) struct Worker {
+    _child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: u64,
+    last_used: Instant,
+    /// `adapter.ts` modification time when the process started.
+    modified: Option<SystemTime>,
+}
+
+/// A worker's answer to one request.
+pub(crate) struct WorkerReply {
+    /// The adapter's response, or the message of the error it threw.
+    pub result: Result<serde_json::Value, String>,
+    /// Console output produced while handling the request.
+    pub stderr: String,
+    pub stdout_bytes: usize,
+}
+
+#[derive(Deserialize)]
+struct RpcReply {
+    id: Option<u64>,
+    #[serde(default)]
+    result: serde_json::Value,
+    error: Option<RpcError>,
+    #[serde(default)]
+    stderr: String,
+}
+
+#[derive(Deserialize)]
+struct RpcError {
+    message: String,
+}
+
+impl Worker {
+    /// Wrap a process spawned with `HSTRY_WORKER=1` and piped stdin/stdout.
+    pub(crate) fn new(mut child: Child, adapter_path: &Path) -> anyhow::Result<Self> {
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("Adapter stdin not captured"))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("Adapter stdout not captured"))?;
+        Ok(Self {
+            _child: child,
+            stdin,
+            stdout: BufReader::new(stdout),
+            next_id: 0,
+            last_used: Instant::now(),
+            modified: modified(adapter_path),
+        })
+    }
+
+    /// Whether the worker has answered a request before.
+    pub(crate) fn is_warm(&self) -> bool {
+        self.next_id > 0
+    }
+
+    /// Send `request` and wait for its reply. Fails when the process exits
+    /// or answers with something that is not a reply to it; the worker
+    /// should not be reused after that.
+    pub(crate) async fn request(
+        &mut self,
+        request: &AdapterRequest,
+    ) -> anyhow::Result<WorkerReply> {
+        let id = self.next_id + 1;
+        let mut message = serde_json::to_value(request)?;
+        message["jsonrpc"] = "2.0".into();
+        message["id"] = id.into();
+        let mut line = serde_json::to_vec(&message)?;
+        line.push(b'\n');
+        self.stdin.write_all(&line).await?;
+        self.stdin.flush().await?;
+
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            if self.stdout.read_until(b'\n', &mut buf).await? == 0 {
+                anyhow::bail!("Adapter worker exited");
+            }
+            // Lossy for the same reason as one-shot calls: adapters may emit
+            // WTF-8 for unpaired surrogates.
+            let text = String::from_utf8_lossy(&buf);
+            if text.trim().is_empty() {
+                continue;
+            }
+            let reply: RpcReply = serde_json::from_str(&text)
+                .map_err(|err| anyhow::anyhow!("Invalid adapter worker reply: {err}"))?;
+            if reply.id != Some(id) {
+                anyhow::bail!("Adapter worker replied out of order");
+            }
+            self.next_id = id;
+            return Ok(WorkerReply {
+                result: match reply.error {
+                    Some(error) => Err(error.message),
+                    None => Ok(reply.result),
+                },
+                stderr: reply.stderr,
+                stdout_bytes: buf.len(),
+            });
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    /// A worker that answers one request with `reply`, then exits.
+    fn fake_worker(reply: &str) -> Worker {
+        let child = tokio::process::Command::new("sh")
+            .arg("-c")
+            .arg(format!("read request; echo '{reply}'"))
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .expect("spawn sh");
+        Worker::new(child, Path::new("adapter.ts")).expect("worker")
+    }
+
+    fn detect() -> AdapterRequest {
+        AdapterRequest::Detect {
+            path: "/data".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn worker_returns_result_and_captured_output() {
+        let mut worker =
+            fake_worker(r#"{"jsonrpc":"2.0","id":1,"result":0.5,"stderr":"scanning"}"#);
+        assert!(!worker.is_warm());
+
+        let reply = worker.request(&detect()).await.expect("reply");
+        assert_eq!(reply.result.expect("result"), serde_json::json!(0.5));
+        assert_eq!(reply.stderr, "scanning");
+        assert!(worker.is_warm());
+
+        assert!(worker.request(&detect()).await.is_err(), "worker exited");
+    }
+
+    #[tokio::test]
+    async fn worker_reports_thrown_errors_and_rejects_stray_replies() {
+        let mut worker =
+            fake_worker(r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32603,"message":"boom"}}"#);
+        let reply = worker.request(&detect()).await.expect("reply");
+        assert_eq!(reply.result.expect_err("error"), "boom");
+
+        let mut worker = fake_worker(r#"{"jsonrpc":"2.0","id":7,"result":null}"#);
+        assert!(worker.request(&detect()).await.is_err());
+    }
+}
@@ -1,8 +1,53 @@
 //! Adapter runner - executes TypeScript adapters via JS runtime.
 
+use futures::stream::BoxStream;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use tokio::process::Command as AsyncCommand;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdout, Command as AsyncCommand};
+use tokio::task::JoinHandle;
+
+use crate::pool::{Worker, WorkerPool};
+
+/// How long an adapter worker process stays alive without requests.
+pub const DEFAULT_WORKER_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Time limit for `info` and `detect` calls.
+pub const DEFAULT_PROBE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Time limit for `parse`, `parseStream` and `export` calls. A streamed
+/// parse may run longer as long as conversations keep arriving: the limit
+/// applies to the wait for each one.
+pub const DEFAULT_WORK_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// An adapter call that ran past its time limit. The adapter process was
+/// killed.
+#[derive(Debug, thiserror::Error)]
+#[error(
+    "Adapter '{adapter}' timed out after {secs}s in {method}; raise timeout_secs for it under [[adapters]] if it needs longer{diagnostics}",
+    secs = .after.as_secs()
+)]
+pub struct AdapterTimeout {
+    pub adapter: String,
+    pub method: &'static str,
+    pub after: Duration,
+    /// What the adapter wrote to stderr before it was killed, and its log,
+    /// when they were captured.
+    pub diagnostics: String,
+}
+
+impl AdapterTimeout {
+    fn new(adapter_path: &Path, request: &AdapterRequest, after: Duration) -> Self {
+        Self {
+            adapter: adapter_name(adapter_path).to_string(),
+            method: request.method(),
+            after,
+            diagnostics: String::new(),
+        }
+    }
+}
 
 /// JavaScript runtime kind.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -12,6 +57,57 @@ pub enum RuntimeKind {
     Node,
 }
 
+impl RuntimeKind {
+    /// Every runtime, in `auto` preference order (fastest first).
+    pub const ALL: [Self; 3] = [Self::Bun, Self::Deno, Self::Node];
+
+    /// Lowercase name, also the binary name.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Bun => "bun",
+            Self::Deno => "deno",
+            Self::Node => "node",
+        }
+    }
+
+    /// Parse a single runtime name (case-insensitive).
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.name().eq_ignore_ascii_case(s.trim()))
+    }
+
+    /// Parse a priority list: `"auto"` (every runtime), one name, or a
+    /// comma-separated list such as `"bun,node"`.
+    pub fn parse_list(s: &str) -> Option<Vec<Self>> {
+        if s.trim().eq_ignore_ascii_case("auto") {
+            return Some(Self::ALL.to_vec());
+        }
+        let mut kinds = Vec::new();
+        for name in s.split(',') {
+            let kind = Self::parse(name)?;
+            if !kinds.contains(&kind) {
+                kinds.push(kind);
+            }
+        }
+        Some(kinds)
+    }
+
+    /// Whether the runtime's binary is on `PATH`.
+    pub fn is_installed(self) -> bool {
+        which::which(self.name()).is_ok()
+    }
+
+    /// Render kinds as `"bun or node"` for messages.
+    pub fn describe(kinds: &[Self]) -> String {
+        let names: Vec<&str> = kinds.iter().map(|kind| kind.name()).collect();
+        match names.split_last() {
+            Some((last, rest)) if !rest.is_empty() => format!("{} or {last}", rest.join(", ")),
+            _ => names.concat(),
+        }
+    }
+}
+
 /// JavaScript runtime to use for adapters.
 ///
 /// Stores the resolved absolute path to the binary so that daemon processes
@@ -25,18 +121,9 @@ pub struct Runtime {
 }
 
 impl Runtime {
-    /// Get the bare binary name for this runtime kind.
-    fn bare_binary(kind: RuntimeKind) -> &'static str {
-        match kind {
-            RuntimeKind::Bun => "bun",
-            RuntimeKind::Deno => "deno",
-            RuntimeKind::Node => "node",
-        }
-    }
-
     /// Resolve the absolute path to a runtime binary, falling back to the bare name.
     fn resolve_binary(kind: RuntimeKind) -> PathBuf {
-        let name = Self::bare_binary(kind);
+        let name = kind.name();
         which::which(name).unwrap_or_else(|_| PathBuf::from(name))
     }
 
@@ -64,31 +151,42 @@ impl Runtime {
 
     /// Detect the best available runtime.
     pub fn detect() -> Option<Self> {
-        // Try bun first (fastest)
-        if which::which("bun").is_ok() {
-            return Some(Self::from_kind(RuntimeKind::Bun));
-        }
-        // Then deno (good TS support)
-        if which::which("deno").is_ok() {
-            return Some(Self::from_kind(RuntimeKind::Deno));
-        }
-        // Finally node (most common)
-        if which::which("node").is_ok() {
-            return Some(Self::from_kind(RuntimeKind::Node));
-        }
-        None
+        // bun (fastest), then deno (good TS support), then node (most common)
+        Self::first_installed(&RuntimeKind::ALL)
     }
 
-    /// Parse runtime from string.
+    /// The first installed runtime in `kinds`.
+    pub fn first_installed(kinds: &[RuntimeKind]) -> Option<Self> {
+        kinds
+            .iter()
+            .copied()
+            .find(|kind| kind.is_installed())
+            .map(Self::from_kind)
+    }
+
+    /// Parse runtime from string: `"auto"`, a runtime name, or a
+    /// comma-separated priority list (first installed wins). A single name is
+    /// returned even when it is not installed, so the spawn error names it.
     pub fn parse(s: &str) -> Option<Self> {
-        match s.to_lowercase().as_str() {
-            "bun" => Some(Self::from_kind(RuntimeKind::Bun)),
-            "deno" => Some(Self::from_kind(RuntimeKind::Deno)),
-            "node" => Some(Self::from_kind(RuntimeKind::Node)),
-            "auto" => Self::detect(),
-            _ => None,
+        match RuntimeKind::parse_list(s)?.as_slice() {
+            [] => None,
+            [kind] if !s.trim().eq_ignore_ascii_case("auto") => Some(Self::from_kind(*kind)),
+            kinds => Self::first_installed(kinds),
         }
     }
+
+    /// Pick a runtime an adapter supports. `allowed` is tried in `preference`
+    /// order first, then in its own order, so a requirement can pull in a
+    /// runtime the preference list leaves out.
+    pub fn select(preference: &[RuntimeKind], allowed: &[RuntimeKind]) -> Option<Self> {
+        let ordered: Vec<RuntimeKind> = preference
+            .iter()
+            .filter(|kind| allowed.contains(kind))
+            .chain(allowed.iter().filter(|kind| !preference.contains(kind)))
+            .copied()
+            .collect();
+        Self::first_installed(&ordered)
+    }
 }
 
 impl std::str::FromStr for Runtime {
@@ -121,6 +219,8 @@ pub enum AdapterRequest {
     Parse { path: String, opts: ParseOptions },
     #[serde(rename = "parseStream")]
     ParseStream { path: String, opts: ParseOptions },
+    #[serde(rename = "parseNdjson")]
+    ParseNdjson { path: String, opts: ParseOptions },
     #[serde(rename = "export")]
     Export {
         conversations: Vec<ExportConversation>,
@@ -128,6 +228,30 @@ pub enum AdapterRequest {
     },
 }
 
+impl AdapterRequest {
+    /// Protocol method name, as sent in the `method` field.
+    pub fn method(&self) -> &'static str {
+        match self {
+            Self::Info => "info",
+            Self::Detect { .. } => "detect",
+            Self::Parse { .. } => "parse",
+            Self::ParseStream { .. } => "parseStream",
+            Self::ParseNdjson { .. } => "parseNdjson",
+            Self::Export { .. } => "export",
+        }
+    }
+
+    fn path(&self) -> Option<&str> {
+        match self {
+            Self::Detect { path }
+            | Self::Parse { path, .. }
+            | Self::ParseStream { path, .. }
+            | Self::ParseNdjson { path, .. } => Some(path),
+            Self::Info | Self::Export { .. } => None,
+        }
+    }
+}
+
 /// Parse options sent to adapter.
 #[derive(Debug, Default, Serialize)]
 pub struct ParseOptions {
@@ -143,6 +267,13 @@ pub struct ParseOptions {
     pub cursor: Option<serde_json::Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub batch_size: Option<usize>,
+    /// Per-source adapter options (`hstry source set-option`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub options: Option<serde_json::Value>,
+    /// Files under the source path that have not changed since the last
+    /// completed sync; adapters may skip reading them.
+    #[serde(rename = "unchangedFiles", skip_serializing_if = "Option::is_none")]
+    pub unchanged_files: Option<Vec<String>>,
 }
 
 /// Export options sent to adapter.
@@ -170,6 +301,39 @@ pub struct ParseStreamResult {
     pub done: Option<bool>,
 }
 
+/// One line of a `parseNdjson` response.
+///
+/// The adapter writes `{"conversation": …}` per conversation, `{"cursor": …}`
+/// once everything before it can be committed, and `{"done": true}` last. An
+/// `{"error": …}` line ends the stream early.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum NdjsonLine {
+    Conversation {
+        conversation: Box<ParsedConversation>,
+    },
+    Cursor {
+        cursor: serde_json::Value,
+    },
+    Done {
+        done: bool,
+    },
+    Error {
+        error: String,
+    },
+}
+
+/// Item yielded by [`AdapterRunner::parse_ndjson`].
+#[derive(Debug, Clone)]
+pub enum StreamEvent {
+    Conversation(Box<ParsedConversation>),
+    /// Resume point covering every conversation yielded before it.
+    Checkpoint(serde_json::Value),
+}
+
+/// Conversations streamed from a running adapter process.
+pub type ConversationStream = BoxStream<'static, anyhow::Result<StreamEvent>>;
+
 /// Export conversation input (matches TS types)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -233,21 +397,215 @@ pub enum AdapterResponse {
     Error { error: String },
 }
 
+/// Directory under an adapter path that holds per-version adapter snapshots.
+pub const ADAPTER_VERSIONS_DIR: &str = ".versions";
+
+/// Snapshot root for adapter `name` at `version` under `base`.
+///
+/// Snapshots mirror the adapter root (`<name>/adapter.ts` next to `types/`)
+/// so an adapter's relative `../types` imports resolve unchanged.
+pub fn versioned_adapter_dir(base: &Path, name: &str, version: &str) -> PathBuf {
+    base.join(ADAPTER_VERSIONS_DIR)
+        .join(format!("{name}@{version}"))
+}
+
+/// Read the version an adapter reports from `info()` without running it, by
+/// finding the first `version: '<x>'` property in the script.
+pub fn adapter_version(adapter_file: &Path) -> Option<String> {
+    let source = std::fs::read_to_string(adapter_file).ok()?;
+    let mut rest = source.as_str();
+    while let Some(pos) = rest.find("version") {
+        let preceded_by_ident = rest[..pos]
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_alphanumeric() || c == '_');
+        let after = rest[pos + "version".len()..].trim_start();
+        rest = &rest[pos + "version".len()..];
+        if preceded_by_ident {
+            continue;
+        }
+        let Some(value) = after.strip_prefix(':').map(str::trim_start) else {
+            continue;
+        };
+        let Some(quote) = value
+            .chars()
+            .next()
+            .filter(|c| matches!(c, '\'' | '"' | '`'))
+        else {
+            continue;
+        };
+        let value = &value[1..];
+        let version = &value[..value.find(quote)?];
+        let valid = !version.is_empty()
+            && version
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+' | '_'));
+        return valid.then(|| version.to_string());
+    }
+    None
+}
+
+/// Adapter name for an `<name>/adapter.ts` path (snapshots included).
+fn adapter_name(adapter_path: &Path) -> &str {
+    adapter_path
+        .parent()
+        .and_then(Path::file_name)
+        .and_then(|name| name.to_str())
+        .unwrap_or("unknown")
+}
+
 /// Runner for TypeScript adapters.
 pub struct AdapterRunner {
-    runtime: Runtime,
+    /// Default runtime; `None` when none of the preferred ones is installed.
+    runtime: Option<Runtime>,
+    preference: Vec<RuntimeKind>,
+    adapter_runtimes: HashMap<String, Vec<RuntimeKind>>,
     adapter_paths: Vec<PathBuf>,
+    log_dir: Option<PathBuf>,
+    /// Warm adapter processes; `None` spawns one process per call.
+    pool: Option<WorkerPool>,
+    /// Per-adapter time limits replacing the defaults; zero means none.
+    adapter_timeouts: HashMap<String, Duration>,
 }
 
 impl AdapterRunner {
     /// Create a new adapter runner.
     pub fn new(runtime: Runtime, adapter_paths: Vec<PathBuf>) -> Self {
         Self {
-            runtime,
+            preference: vec![runtime.kind],
+            runtime: Some(runtime),
+            adapter_runtimes: HashMap::new(),
             adapter_paths,
+            log_dir: None,
+            pool: Some(WorkerPool::new(DEFAULT_WORKER_IDLE_TIMEOUT)),
+            adapter_timeouts: HashMap::new(),
+        }
+    }
+
+    /// Create a runner for a machine where none of the `preference` runtimes
+    /// is installed. Finding and listing adapters still works; running one
+    /// fails with an error naming the missing runtimes.
+    pub fn without_runtime(preference: Vec<RuntimeKind>, adapter_paths: Vec<PathBuf>) -> Self {
+        Self {
+            runtime: None,
+            preference,
+            adapter_runtimes: HashMap::new(),
+            adapter_paths,
+            log_dir: None,
+            pool: Some(WorkerPool::new(DEFAULT_WORKER_IDLE_TIMEOUT)),
+            adapter_timeouts: HashMap::new(),
+        }
+    }
+
+    /// Runtime priority list used when an adapter cannot run on the default
+    /// runtime.
+    #[must_use]
+    pub fn with_runtime_preference(mut self, preference: Vec<RuntimeKind>) -> Self {
+        self.preference = preference;
+        self
+    }
+
+    /// Restrict adapter `name` to the given runtimes (a requirement from the
+    /// adapter manifest or a per-adapter override in the config).
+    #[must_use]
+    pub fn with_adapter_runtimes(mut self, name: &str, runtimes: Vec<RuntimeKind>) -> Self {
+        self.adapter_runtimes.insert(name.to_string(), runtimes);
+        self
+    }
+
+    /// The runtime adapter `name` runs on: the default runtime unless the
+    /// adapter is restricted to others.
+    pub fn runtime_for(&self, name: &str) -> anyhow::Result<Runtime> {
+        let Some(allowed) = self
+            .adapter_runtimes
+            .get(name)
+            .filter(|allowed| !allowed.is_empty())
+        else {
+            return self.runtime.clone().ok_or_else(|| self.missing_runtime());
+        };
+        if let Some(runtime) = self
+            .runtime
+            .as_ref()
+            .filter(|runtime| allowed.contains(&runtime.kind))
+        {
+            return Ok(runtime.clone());
+        }
+        Runtime::select(&self.preference, allowed).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Adapter '{name}' requires {}, but no such runtime is installed",
+                RuntimeKind::describe(allowed)
+            )
+        })
+    }
+
+    fn missing_runtime(&self) -> anyhow::Error {
+        if self.preference.is_empty() || self.preference == RuntimeKind::ALL {
+            anyhow::anyhow!("No JavaScript runtime found. Install bun, deno, or node.")
+        } else {
+            anyhow::anyhow!(
+                "None of the configured JavaScript runtimes ({}) is installed.",
+                RuntimeKind::describe(&self.preference)
+            )
+        }
+    }
+
+    /// Keep per-run adapter logs under `dir` (see [`crate::logs`]).
+    #[must_use]
+    pub fn with_log_dir(mut self, dir: PathBuf) -> Self {
+        self.log_dir = Some(dir);
+        self
+    }
+
+    /// Keep adapter processes alive between calls for `idle_timeout`, so
+    /// repeated calls skip starting the JS runtime. Zero spawns a fresh
+    /// process for every call.
+    #[must_use]
+    pub fn with_worker_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.pool = (!idle_timeout.is_zero()).then(|| WorkerPool::new(idle_timeout));
+        self
+    }
+
+    /// Limit every call to adapter `name` to `timeout` instead of the
+    /// per-method defaults. Zero removes the limit.
+    #[must_use]
+    pub fn with_adapter_timeout(mut self, name: &str, timeout: Duration) -> Self {
+        self.adapter_timeouts.insert(name.to_string(), timeout);
+        self
+    }
+
+    /// Time limit for `request` on the adapter at `adapter_path`, if any.
+    pub fn timeout_for(&self, adapter_path: &Path, request: &AdapterRequest) -> Option<Duration> {
+        let timeout = match self.adapter_timeouts.get(adapter_name(adapter_path)) {
+            Some(timeout) => *timeout,
+            None => match request {
+                AdapterRequest::Info | AdapterRequest::Detect { .. } => DEFAULT_PROBE_TIMEOUT,
+                _ => DEFAULT_WORK_TIMEOUT,
+            },
+        };
+        (!timeout.is_zero()).then_some(timeout)
+    }
+
+    /// Run `call` under the time limit for `request`. When it runs out,
+    /// `call` is dropped, which kills the adapter process it drives.
+    async fn limit<T>(
+        &self,
+        adapter_path: &Path,
+        request: &AdapterRequest,
+        call: impl Future<Output = anyhow::Result<T>>,
+    ) -> anyhow::Result<T> {
+        match self.timeout_for(adapter_path, request) {
+            Some(after) => tokio::time::timeout(after, call)
+                .await
+                .map_err(|_| AdapterTimeout::new(adapter_path, request, after))?,
+            None => call.await,
         }
     }
 
+    /// Where adapter logs are written, if logging is enabled.
+    pub fn log_dir(&self) -> Option<&Path> {
+        self.log_dir.as_deref()
+    }
+
     /// Find an adapter by name.
     pub fn find_adapter(&self, name: &str) -> Option<PathBuf> {
         for base_path in &self.adapter_paths {
@@ -260,6 +618,19 @@ impl AdapterRunner {
         None
     }
 
+    /// Find the snapshot of adapter `name` at `version` kept for pinned
+    /// sources (see [`versioned_adapter_dir`]).
+    pub fn find_adapter_version(&self, name: &str, version: &str) -> Option<PathBuf> {
+        self.adapter_paths
+            .iter()
+            .map(|base| {
+                versioned_adapter_dir(base, name, version)
+                    .join(name)
+                    .join("adapter.ts")
+            })
+            .find(|file| file.exists())
+    }
+
     /// List available adapters.
     pub fn list_adapters(&self) -> Vec<String> {
         let mut adapters = Vec::new();
@@ -283,30 +654,183 @@ impl AdapterRunner {
         adapters
     }
 
-    /// Call an adapter method.
+    /// Call an adapter method, on a pooled worker process when the adapter
+    /// supports it. Fails with [`AdapterTimeout`] when the call runs past
+    /// [`Self::timeout_for`]; dropping the returned future also kills the
+    /// adapter process.
     pub async fn call(
         &self,
         adapter_path: &Path,
         request: AdapterRequest,
     ) -> anyhow::Result<AdapterResponse> {
-        use tokio::io::AsyncWriteExt;
+        if let Some(pool) = &self.pool
+            && pool.supports(adapter_path)
+            && let Some(response) = self.call_worker(pool, adapter_path, &request).await?
+        {
+            return Ok(response);
+        }
+
+        let mut child = self.spawn(adapter_path, &request).await?;
+        // Read separately so a run killed at its time limit still has it.
+        let stderr = collect_stderr(&mut child);
+        let output = self
+            .limit(adapter_path, &request, async {
+                Ok(child.wait_with_output().await?)
+            })
+            .await;
+        let stderr = match stderr {
+            Some(task) => task.await.unwrap_or_default(),
+            None => Vec::new(),
+        };
+        let output = match output.map_err(|err| err.downcast::<AdapterTimeout>()) {
+            Ok(output) => output,
+            Err(Ok(mut timeout)) => {
+                let log_path = Self::write_log(
+                    self.log_dir.as_deref(),
+                    adapter_path,
+                    &request,
+                    &format!("killed after {}s", timeout.after.as_secs()),
+                    false,
+                    0,
+                    &stderr,
+                );
+                timeout.diagnostics = diagnostics(&stderr, log_path.as_deref());
+                return Err(timeout.into());
+            }
+            Err(Err(err)) => return Err(err),
+        };
+        let log_path = Self::write_log(
+            self.log_dir.as_deref(),
+            adapter_path,
+            &request,
+            &output.status.to_string(),
+            output.status.success(),
+            output.stdout.len(),
+            &stderr,
+        );
+        let log_hint = diagnostics(&stderr, log_path.as_deref());
 
-        let request_json = serde_json::to_string(&request)?;
+        if !output.status.success() {
+            anyhow::bail!("Adapter failed: {}{log_hint}", output.status);
+        }
 
-        let mut args = self
-            .runtime
-            .run_args()
-            .iter()
-            .map(ToString::to_string)
-            .collect::<Vec<_>>();
-        args.push(adapter_path.display().to_string());
+        // Use lossy conversion to handle adapters that emit invalid UTF-8
+        // (e.g., JSON files containing Unicode surrogate pairs in tool output
+        // that get re-encoded as WTF-8 by the JS runtime).
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let response: AdapterResponse = serde_json::from_str(&stdout)
+            .map_err(|err| anyhow::anyhow!("Invalid adapter response: {err}{log_hint}"))?;
+
+        Ok(match response {
+            AdapterResponse::Error { error } => AdapterResponse::Error {
+                error: format!("{error}{log_hint}"),
+            },
+            other => other,
+        })
+    }
+
+    /// Run `request` on a worker: an idle one, or a new one if none is.
+    /// Returns `None` when the adapter cannot run as a worker, after which
+    /// it is only run one process per call.
+    async fn call_worker(
+        &self,
+        pool: &WorkerPool,
+        adapter_path: &Path,
+        request: &AdapterRequest,
+    ) -> anyhow::Result<Option<AdapterResponse>> {
+        let mut worker = match pool.checkout(adapter_path) {
+            Some(worker) => worker,
+            None => self.spawn_worker(adapter_path)?,
+        };
+        let result = match self
+            .limit(adapter_path, request, worker.request(request))
+            .await
+        {
+            // An idle worker may have died in the meantime; try a new one.
+            Err(err) if worker.is_warm() && !err.is::<AdapterTimeout>() => {
+                tracing::debug!("Restarting adapter worker: {err}");
+                worker = self.spawn_worker(adapter_path)?;
+                self.limit(adapter_path, request, worker.request(request))
+                    .await
+            }
+            result => result,
+        };
+        let reply = match result {
+            Ok(reply) => reply,
+            // Dropping the worker kills it.
+            Err(err) if err.is::<AdapterTimeout>() => return Err(err),
+            // The adapter predates worker mode, or fails to start at all (a
+            // one-shot run then reports why).
+            Err(err) => {
+                tracing::debug!(
+                    "Adapter {} does not run as a worker: {err}",
+                    adapter_path.display()
+                );
+                pool.mark_unsupported(adapter_path);
+                return Ok(None);
+            }
+        };
+        pool.checkin(adapter_path, worker);
+
+        let log_path = Self::write_log(
+            self.log_dir.as_deref(),
+            adapter_path,
+            request,
+            if reply.result.is_ok() {
+                "worker: ok"
+            } else {
+                "worker: error"
+            },
+            reply.result.is_ok(),
+            reply.stdout_bytes,
+            reply.stderr.as_bytes(),
+        );
+        let log_hint = diagnostics(reply.stderr.as_bytes(), log_path.as_deref());
+
+        let result = match reply.result {
+            Ok(result) => result,
+            Err(error) => anyhow::bail!("Adapter failed: {error}{log_hint}"),
+        };
+        let response: AdapterResponse = serde_json::from_value(result)
+            .map_err(|err| anyhow::anyhow!("Invalid adapter response: {err}{log_hint}"))?;
+        Ok(Some(match response {
+            AdapterResponse::Error { error } => AdapterResponse::Error {
+                error: format!("{error}{log_hint}"),
+            },
+            other => other,
+        }))
+    }
+
+    fn spawn_worker(&self, adapter_path: &Path) -> anyhow::Result<Worker> {
+        let (mut cmd, kind) = self.command(adapter_path)?;
+        cmd.env("HSTRY_WORKER", "1");
+        cmd.stdin(std::process::Stdio::piped());
+        cmd.stderr(std::process::Stdio::null());
+        let child = cmd.spawn().map_err(|err| spawn_error(kind, err))?;
+        Worker::new(child, adapter_path)
+    }
+
+    /// Command that runs the adapter on its runtime, with stdout piped.
+    fn command(&self, adapter_path: &Path) -> anyhow::Result<(AsyncCommand, RuntimeKind)> {
+        let runtime = self.runtime_for(adapter_name(adapter_path))?;
+        let mut cmd = AsyncCommand::new(runtime.binary());
+        cmd.args(runtime.run_args());
+        cmd.arg(adapter_path);
+        cmd.stdout(std::process::Stdio::piped());
+        cmd.kill_on_drop(true);
+        Ok((cmd, runtime.kind))
+    }
+
+    /// Start the adapter process for `request` with stdout and stderr piped.
+    async fn spawn(&self, adapter_path: &Path, request: &AdapterRequest) -> anyhow::Result<Child> {
+        use tokio::io::AsyncWriteExt;
+
+        let request_json = serde_json::to_string(request)?;
+        let (mut cmd, kind) = self.command(adapter_path)?;
 
         // Use stdin for large requests (> 100KB) to avoid env var size limits
         let use_stdin = request_json.len() > 100_000;
 
-        let mut cmd = AsyncCommand::new(self.runtime.binary());
-        cmd.args(&args);
-
         if use_stdin {
             cmd.env("HSTRY_REQUEST_STDIN", "1");
             cmd.stdin(std::process::Stdio::piped());
@@ -314,30 +838,53 @@ impl AdapterRunner {
             cmd.env("HSTRY_REQUEST", &request_json);
         }
 
-        cmd.stdout(std::process::Stdio::piped());
         cmd.stderr(std::process::Stdio::piped());
 
-        let mut child = cmd.spawn()?;
+        let mut child = cmd.spawn().map_err(|err| spawn_error(kind, err))?;
 
         if use_stdin && let Some(mut stdin) = child.stdin.take() {
             stdin.write_all(request_json.as_bytes()).await?;
             stdin.shutdown().await?;
         }
+        Ok(child)
+    }
 
-        let output = child.wait_with_output().await?;
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            anyhow::bail!("Adapter failed: {stderr}");
+    /// Record a run in the adapter's log directory. Quiet info/detect probes
+    /// are skipped so auto-detection does not flood the logs.
+    fn write_log(
+        log_dir: Option<&Path>,
+        adapter_path: &Path,
+        request: &AdapterRequest,
+        status: &str,
+        success: bool,
+        stdout_bytes: usize,
+        stderr: &[u8],
+    ) -> Option<PathBuf> {
+        let log_dir = log_dir?;
+        let quiet_probe = matches!(
+            request,
+            AdapterRequest::Info | AdapterRequest::Detect { .. }
+        ) && success
+            && stderr.is_empty();
+        if quiet_probe {
+            return None;
+        }
+        let adapter = adapter_name(adapter_path);
+        let record = crate::logs::RunRecord {
+            adapter,
+            method: request.method(),
+            path: request.path(),
+            status,
+            stdout_bytes,
+            stderr,
+        };
+        match crate::logs::write_log(log_dir, &record) {
+            Ok(path) => Some(path),
+            Err(err) => {
+                tracing::debug!("Failed to write adapter log: {err}");
+                None
+            }
         }
-
-        // Use lossy conversion to handle adapters that emit invalid UTF-8
-        // (e.g., JSON files containing Unicode surrogate pairs in tool output
-        // that get re-encoded as WTF-8 by the JS runtime).
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let response: AdapterResponse = serde_json::from_str(&stdout)?;
-
-        Ok(response)
     }
 
     /// Get adapter info.
@@ -419,6 +966,95 @@ impl AdapterRunner {
         }
     }
 
+    /// Parse conversations as a stream: the adapter writes one NDJSON line
+    /// per conversation and they are yielded as they arrive, so memory stays
+    /// bounded by what the caller buffers. Returns `None` when the adapter
+    /// predates the `parseNdjson` method.
+    pub async fn parse_ndjson(
+        &self,
+        adapter_path: &Path,
+        path: &str,
+        opts: ParseOptions,
+    ) -> anyhow::Result<Option<ConversationStream>> {
+        let request = AdapterRequest::ParseNdjson {
+            path: path.to_string(),
+            opts,
+        };
+        let mut child = self.spawn(adapter_path, &request).await?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("Adapter stdout not captured"))?;
+        let stderr = collect_stderr(&mut child);
+        let mut state = NdjsonState {
+            lines: BufReader::new(stdout).lines(),
+            child,
+            stderr,
+            stdout_bytes: 0,
+            log_dir: self.log_dir.clone(),
+            timeout: self.timeout_for(adapter_path, &request),
+            adapter_path: adapter_path.to_path_buf(),
+            request,
+        };
+
+        // An adapter without `parseNdjson` answers with a single error line.
+        let first = state.next_line().await?;
+        if let Some(NdjsonLine::Error { error }) = &first
+            && (error.contains("parseNdjson")
+                || error.contains("Unknown method")
+                || error.contains("does not support"))
+        {
+            state.finish(false).await;
+            return Ok(None);
+        }
+
+        let stream = futures::stream::unfold(Some((state, first)), |current| async move {
+            let (mut state, mut pending) = current?;
+            loop {
+                let line = match pending.take() {
+                    Some(line) => Ok(Some(line)),
+                    None => state.next_line().await,
+                };
+                return match line {
+                    Ok(Some(NdjsonLine::Conversation { conversation })) => Some((
+                        Ok(StreamEvent::Conversation(conversation)),
+                        Some((state, None)),
+                    )),
+                    Ok(Some(NdjsonLine::Cursor { cursor })) => {
+                        Some((Ok(StreamEvent::Checkpoint(cursor)), Some((state, None))))
+                    }
+                    Ok(Some(NdjsonLine::Done { done: false })) => continue,
+                    Ok(Some(NdjsonLine::Done { done: true })) => match state.finish(false).await {
+                        (Some(status), hint) if !status.success() => {
+                            Some((Err(anyhow::anyhow!("Adapter failed: {status}{hint}")), None))
+                        }
+                        _ => None,
+                    },
+                    Ok(Some(NdjsonLine::Error { error })) => {
+                        let (_, hint) = state.finish(false).await;
+                        Some((Err(anyhow::anyhow!("Adapter error: {error}{hint}")), None))
+                    }
+                    Ok(None) => {
+                        let err = match state.finish(false).await {
+                            (Some(status), hint) if !status.success() => {
+                                anyhow::anyhow!("Adapter failed: {status}{hint}")
+                            }
+                            (_, hint) => {
+                                anyhow::anyhow!("Adapter stream ended before it was done{hint}")
+                            }
+                        };
+                        Some((Err(err), None))
+                    }
+                    Err(err) => {
+                        let (_, hint) = state.finish(true).await;
+                        Some((Err(anyhow::anyhow!("{err}{hint}")), None))
+                    }
+                };
+            }
+        });
+        Ok(Some(Box::pin(stream)))
+    }
+
     /// Export conversations to a format.
     pub async fn export(
         &self,
@@ -443,6 +1079,110 @@ impl AdapterRunner {
     }
 }
 
+fn spawn_error(kind: RuntimeKind, err: std::io::Error) -> anyhow::Error {
+    if err.kind() == std::io::ErrorKind::NotFound {
+        anyhow::anyhow!(
+            "JavaScript runtime '{}' not found. Install it or change js_runtime in the config.",
+            kind.name()
+        )
+    } else {
+        err.into()
+    }
+}
+
+/// Read the child's stderr to the end in the background.
+fn collect_stderr(child: &mut Child) -> Option<JoinHandle<Vec<u8>>> {
+    child.stderr.take().map(|mut stderr| {
+        tokio::spawn(async move {
+            let mut buf = Vec::new();
+            let _ = stderr.read_to_end(&mut buf).await;
+            buf
+        })
+    })
+}
+
+/// Suffix for an adapter error: the last lines of the run's stderr, one per
+/// line, and the path of its full log.
+fn diagnostics(stderr: &[u8], log_path: Option<&Path>) -> String {
+    let tail = crate::logs::stderr_tail(stderr, crate::logs::ERROR_STDERR_LINES);
+    let mut hint = String::new();
+    for line in tail.lines() {
+        hint.push_str("\n  ");
+        hint.push_str(line);
+    }
+    if let Some(path) = log_path {
+        hint.push_str(&format!("\n  (log: {})", path.display()));
+    }
+    hint
+}
+
+/// A running `parseNdjson` adapter process.
+struct NdjsonState {
+    lines: Lines<BufReader<ChildStdout>>,
+    child: Child,
+    stderr: Option<JoinHandle<Vec<u8>>>,
+    stdout_bytes: usize,
+    log_dir: Option<PathBuf>,
+    /// Longest wait for the next line.
+    timeout: Option<Duration>,
+    adapter_path: PathBuf,
+    request: AdapterRequest,
+}
+
+impl NdjsonState {
+    /// Next non-empty line, or `None` at end of output. Fails with
+    /// [`AdapterTimeout`] when no line arrives within the time limit.
+    async fn next_line(&mut self) -> anyhow::Result<Option<NdjsonLine>> {
+        loop {
+            let line = match self.timeout {
+                Some(after) => tokio::time::timeout(after, self.lines.next_line())
+                    .await
+                    .map_err(|_| AdapterTimeout::new(&self.adapter_path, &self.request, after))?,
+                None => self.lines.next_line().await,
+            };
+            let Some(line) = line? else {
+                return Ok(None);
+            };
+            self.stdout_bytes += line.len() + 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+            return serde_json::from_str(&line)
+                .map(Some)
+                .map_err(|err| anyhow::anyhow!("Invalid adapter stream line: {err}"));
+        }
+    }
+
+    /// Stop reading, reap the process (killing it first when `kill` is set),
+    /// and write its log. Returns the exit status, if the process could be
+    /// waited on, and a hint to append to errors.
+    async fn finish(mut self, kill: bool) -> (Option<std::process::ExitStatus>, String) {
+        drop(self.lines);
+        if kill {
+            let _ = self.child.start_kill();
+        }
+        let status = self.child.wait().await.ok();
+        let stderr = match self.stderr.take() {
+            Some(task) => task.await.unwrap_or_default(),
+            None => Vec::new(),
+        };
+        let log_path = status.as_ref().and_then(|status| {
+            AdapterRunner::write_log(
+                self.log_dir.as_deref(),
+                &self.adapter_path,
+                &self.request,
+                &status.to_string(),
+                status.success(),
+                self.stdout_bytes,
+                &stderr,
+            )
+        });
+        let failed = status.is_none_or(|status| !status.success());
+        let stderr = if failed { &stderr[..] } else { &[] };
+        (status, diagnostics(stderr, log_path.as_deref()))
+    }
+}
+
 #[cfg(test)]
 #[path = "runner_tests.rs"]
 mod tests;
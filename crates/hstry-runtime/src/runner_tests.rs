@@ -88,6 +88,24 @@ mod runtime_tests {
         assert_eq!(node.kind, RuntimeKind::Node);
         assert!("unknown".parse::<Runtime>().is_err());
     }
+
+    #[test]
+    fn parse_priority_list() {
+        assert_eq!(
+            RuntimeKind::parse_list(" Bun, node ,bun"),
+            Some(vec![RuntimeKind::Bun, RuntimeKind::Node])
+        );
+        assert_eq!(
+            RuntimeKind::parse_list("auto"),
+            Some(RuntimeKind::ALL.to_vec())
+        );
+        assert!(RuntimeKind::parse_list("bun,python").is_none());
+        assert_eq!(
+            RuntimeKind::describe(&[RuntimeKind::Bun, RuntimeKind::Deno, RuntimeKind::Node]),
+            "bun, deno or node"
+        );
+        assert_eq!(RuntimeKind::describe(&[RuntimeKind::Deno]), "deno");
+    }
 }
 
 #[cfg(test)]
@@ -124,6 +142,8 @@ mod adapter_request_tests {
                 include_attachments: false,
                 cursor: None,
                 batch_size: Some(50),
+                options: Some(serde_json::json!({"account": "work"})),
+                unchanged_files: Some(vec!["/data/old.jsonl".to_string()]),
             },
         };
         let json = serde_json::to_value(&req).expect("serialize");
@@ -136,6 +156,11 @@ mod adapter_request_tests {
                 .as_bool()
                 .unwrap_or(false)
         );
+        assert_eq!(json["params"]["opts"]["options"]["account"], "work");
+        assert_eq!(
+            json["params"]["opts"]["unchangedFiles"][0],
+            "/data/old.jsonl"
+        );
     }
 
     #[test]
@@ -178,6 +203,7 @@ mod adapter_request_tests {
                     parts: None,
                     tool_calls: None,
                     metadata: None,
+                    parent_idx: None,
                 },
                 ParsedMessage {
                     role: "assistant".to_string(),
@@ -189,6 +215,7 @@ mod adapter_request_tests {
                     parts: None,
                     tool_calls: None,
                     metadata: None,
+                    parent_idx: None,
                 },
             ],
             metadata: Some(serde_json::json!({"tags": ["test"]})),
@@ -442,6 +469,7 @@ mod parsed_conversation_tests {
             parent_external_id: None,
             parent_message_idx: None,
             fork_type: None,
+            source_id: None,
         };
         let json = serde_json::to_string(&conv).expect("serialize");
         assert!(
@@ -475,6 +503,7 @@ mod parsed_conversation_tests {
             parent_external_id: None,
             parent_message_idx: None,
             fork_type: None,
+            source_id: None,
         };
         let value = serde_json::to_value(&conv).expect("serialize");
         assert_eq!(value["version"], 10);
@@ -611,8 +640,12 @@ mod parsed_message_tests {
 
 #[cfg(test)]
 mod adapter_runner_tests {
-    use super::super::{AdapterRunner, Runtime, RuntimeKind};
-    use std::path::PathBuf;
+    use super::super::{
+        AdapterRequest, AdapterRunner, DEFAULT_PROBE_TIMEOUT, DEFAULT_WORK_TIMEOUT, ParseOptions,
+        Runtime, RuntimeKind, adapter_version, versioned_adapter_dir,
+    };
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
     use tempfile::TempDir;
 
     fn test_runtime() -> Runtime {
@@ -627,6 +660,63 @@ mod adapter_runner_tests {
         adapter_file
     }
 
+    #[test]
+    fn adapter_runtimes_keep_default_when_allowed() {
+        let runner = AdapterRunner::new(test_runtime(), vec![])
+            .with_adapter_runtimes("codex", vec![RuntimeKind::Node, RuntimeKind::Bun]);
+        let runtime = runner.runtime_for("codex").expect("runtime");
+        assert_eq!(runtime.kind, RuntimeKind::Bun);
+        let runtime = runner.runtime_for("chatgpt").expect("runtime");
+        assert_eq!(runtime.kind, RuntimeKind::Bun);
+    }
+
+    #[test]
+    fn timeouts_default_by_method_and_can_be_set_per_adapter() {
+        let runner = AdapterRunner::new(test_runtime(), vec![])
+            .with_adapter_timeout("chatgpt", Duration::from_secs(1800))
+            .with_adapter_timeout("gemini", Duration::ZERO);
+        let detect = AdapterRequest::Detect {
+            path: "/data".to_string(),
+        };
+        let parse = AdapterRequest::Parse {
+            path: "/data".to_string(),
+            opts: ParseOptions::default(),
+        };
+        let adapter = |name: &str| PathBuf::from(format!("/adapters/{name}/adapter.ts"));
+
+        let codex = adapter("codex");
+        assert_eq!(
+            runner.timeout_for(&codex, &detect),
+            Some(DEFAULT_PROBE_TIMEOUT)
+        );
+        assert_eq!(
+            runner.timeout_for(&codex, &parse),
+            Some(DEFAULT_WORK_TIMEOUT)
+        );
+        assert_eq!(
+            runner.timeout_for(&adapter("chatgpt"), &detect),
+            Some(Duration::from_secs(1800))
+        );
+        assert_eq!(
+            runner.timeout_for(Path::new(&adapter("gemini")), &parse),
+            None
+        );
+    }
+
+    #[test]
+    fn runner_without_runtime_lists_adapters_but_cannot_run_them() {
+        let dir = TempDir::new().expect("tempdir");
+        create_test_adapter(&dir, "codex");
+        let runner = AdapterRunner::without_runtime(vec![], vec![dir.path().to_path_buf()]);
+        assert_eq!(runner.list_adapters(), vec!["codex".to_string()]);
+        assert!(runner.find_adapter("codex").is_some());
+        let err = runner.runtime_for("codex").expect_err("no runtime");
+        assert!(
+            err.to_string().contains("No JavaScript runtime found"),
+            "{err}"
+        );
+    }
+
     #[test]
     fn find_adapter_finds_existing() {
         let dir = TempDir::new().expect("tempdir");
@@ -650,6 +740,39 @@ mod adapter_runner_tests {
         assert!(runner.find_adapter("nonexistent").is_none());
     }
 
+    #[test]
+    fn find_adapter_version_resolves_snapshots() {
+        let dir = TempDir::new().expect("tempdir");
+        create_test_adapter(&dir, "codex");
+        let snapshot = versioned_adapter_dir(dir.path(), "codex", "1.0.0").join("codex");
+        std::fs::create_dir_all(&snapshot).expect("create dir");
+        std::fs::write(snapshot.join("adapter.ts"), "// old").expect("write");
+
+        let runner = AdapterRunner::new(test_runtime(), vec![dir.path().to_path_buf()]);
+        assert_eq!(
+            runner.find_adapter_version("codex", "1.0.0"),
+            Some(snapshot.join("adapter.ts"))
+        );
+        assert!(runner.find_adapter_version("codex", "2.0.0").is_none());
+        assert_eq!(runner.list_adapters(), vec!["codex".to_string()]);
+    }
+
+    #[test]
+    fn adapter_version_reads_info_literal() {
+        let dir = TempDir::new().expect("tempdir");
+        let file = create_test_adapter(&dir, "codex");
+        std::fs::write(
+            &file,
+            "interface Meta { cli_version?: string; version?: string }\n\
+             const adapter = { info() { return { name: 'codex', version: '1.1.0' }; } };\n",
+        )
+        .expect("write");
+        assert_eq!(adapter_version(&file), Some("1.1.0".to_string()));
+
+        std::fs::write(&file, "const v = { version: '../x' };").expect("write");
+        assert_eq!(adapter_version(&file), None);
+    }
+
     #[test]
     fn list_adapters_finds_all() {
         let dir = TempDir::new().expect("tempdir");
@@ -714,3 +837,174 @@ mod adapter_runner_tests {
         assert!(!adapters.contains(&"invalid".to_string()));
     }
 }
+
+#[cfg(all(test, unix))]
+mod ndjson_stream_tests {
+    use super::super::{
+        AdapterRunner, AdapterTimeout, ParseOptions, Runtime, RuntimeKind, StreamEvent,
+    };
+    use futures::StreamExt;
+    use std::os::unix::fs::PermissionsExt;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    /// A runner whose "runtime" runs `adapter.ts` as a shell script, so the
+    /// stream protocol can be exercised without a JS runtime.
+    fn shell_runner(dir: &TempDir) -> AdapterRunner {
+        let shim = dir.path().join("shim");
+        std::fs::write(&shim, "#!/bin/sh\nshift\nexec /bin/sh \"$@\"\n").expect("write");
+        std::fs::set_permissions(&shim, std::fs::Permissions::from_mode(0o755)).expect("chmod");
+        let runtime = Runtime {
+            kind: RuntimeKind::Bun,
+            binary_path: shim,
+        };
+        AdapterRunner::new(runtime, vec![dir.path().to_path_buf()])
+    }
+
+    fn adapter(dir: &TempDir, name: &str, script: &str) -> std::path::PathBuf {
+        let adapter_dir = dir.path().join(name);
+        std::fs::create_dir_all(&adapter_dir).expect("create dir");
+        let file = adapter_dir.join("adapter.ts");
+        std::fs::write(&file, script).expect("write");
+        file
+    }
+
+    #[tokio::test]
+    async fn yields_conversations_and_checkpoints_in_order() {
+        let dir = TempDir::new().expect("tempdir");
+        let runner = shell_runner(&dir);
+        let path = adapter(
+            &dir,
+            "streaming",
+            r#"case "$HSTRY_REQUEST" in *parseNdjson*) ;; *) exit 1 ;; esac
+printf '%s\n' '{"conversation":{"externalId":"c1","createdAt":1,"messages":[]}}' '' \
+  '{"cursor":{"page":1}}' '{"conversation":{"externalId":"c2","createdAt":2,"messages":[]}}' \
+  '{"done":true}'
+"#,
+        );
+
+        let stream = runner
+            .parse_ndjson(&path, "/data", ParseOptions::default())
+            .await
+            .expect("spawn")
+            .expect("supported");
+        let events: Vec<StreamEvent> = stream.map(|event| event.expect("event")).collect().await;
+        assert_eq!(events.len(), 3);
+        assert!(
+            matches!(&events[0], StreamEvent::Conversation(c) if c.external_id.as_deref() == Some("c1"))
+        );
+        assert!(matches!(&events[1], StreamEvent::Checkpoint(cursor) if cursor["page"] == 1));
+        assert!(
+            matches!(&events[2], StreamEvent::Conversation(c) if c.external_id.as_deref() == Some("c2"))
+        );
+    }
+
+    #[tokio::test]
+    async fn older_adapters_fall_back_and_truncated_streams_fail() {
+        let dir = TempDir::new().expect("tempdir");
+        let runner = shell_runner(&dir);
+
+        let old = adapter(
+            &dir,
+            "old",
+            "echo '{\"error\":\"Unknown method: parseNdjson\"}'\nexit 1\n",
+        );
+        assert!(
+            runner
+                .parse_ndjson(&old, "/data", ParseOptions::default())
+                .await
+                .expect("spawn")
+                .is_none()
+        );
+
+        let crashing = adapter(
+            &dir,
+            "crashing",
+            "echo '{\"conversation\":{\"createdAt\":1,\"messages\":[]}}'\necho boom >&2\nexit 3\n",
+        );
+        let mut stream = runner
+            .parse_ndjson(&crashing, "/data", ParseOptions::default())
+            .await
+            .expect("spawn")
+            .expect("supported");
+        assert!(matches!(
+            stream.next().await,
+            Some(Ok(StreamEvent::Conversation(_)))
+        ));
+        let err = stream.next().await.expect("error").expect_err("failure");
+        assert!(err.to_string().contains("boom"), "{err}");
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn stuck_adapters_are_killed_at_their_time_limit() {
+        let dir = TempDir::new().expect("tempdir");
+        let hung = adapter(&dir, "hung", "exec sleep 10\n");
+        let stalled = adapter(
+            &dir,
+            "stalled",
+            "echo '{\"conversation\":{\"createdAt\":1,\"messages\":[]}}'\nexec sleep 10\n",
+        );
+        let limit = Duration::from_millis(200);
+
+        // Once on a pooled worker, once one process per call.
+        for runner in [
+            shell_runner(&dir),
+            shell_runner(&dir).with_worker_idle_timeout(Duration::ZERO),
+        ] {
+            let runner = runner.with_adapter_timeout("hung", limit);
+            let err = runner.detect(&hung, "/data").await.expect_err("timed out");
+            assert!(err.is::<AdapterTimeout>(), "{err}");
+        }
+
+        let runner = shell_runner(&dir).with_adapter_timeout("stalled", limit);
+        let mut stream = runner
+            .parse_ndjson(&stalled, "/data", ParseOptions::default())
+            .await
+            .expect("spawn")
+            .expect("supported");
+        assert!(matches!(
+            stream.next().await,
+            Some(Ok(StreamEvent::Conversation(_)))
+        ));
+        let err = stream.next().await.expect("error").expect_err("timed out");
+        assert!(err.to_string().contains("timed out"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn failures_quote_the_end_of_stderr_and_point_at_the_log() {
+        let dir = TempDir::new().expect("tempdir");
+        let log_dir = dir.path().join("logs");
+        let runner = shell_runner(&dir)
+            .with_worker_idle_timeout(Duration::ZERO)
+            .with_log_dir(log_dir.clone());
+
+        let failing = adapter(
+            &dir,
+            "failing",
+            "i=1\nwhile [ $i -le 15 ]; do echo \"step $i\" >&2; i=$((i + 1)); done\nexit 2\n",
+        );
+        let err = runner
+            .detect(&failing, "/data")
+            .await
+            .expect_err("failed")
+            .to_string();
+        assert!(err.contains("(5 earlier lines omitted)\n  step 6"), "{err}");
+        assert!(err.contains("step 15"), "{err}");
+        assert!(!err.contains("step 5\n"), "{err}");
+        assert!(
+            err.contains(&log_dir.join("failing").display().to_string()),
+            "{err}"
+        );
+
+        let hung = adapter(&dir, "hung", "echo 'reading export' >&2\nexec sleep 10\n");
+        let err = runner
+            .with_adapter_timeout("hung", Duration::from_millis(200))
+            .detect(&hung, "/data")
+            .await
+            .expect_err("timed out");
+        let timeout = err.downcast_ref::<AdapterTimeout>().expect("timeout");
+        assert!(timeout.diagnostics.contains("reading export"), "{err}");
+        assert_eq!(crate::logs::list_logs(&log_dir, Some("hung")).len(), 1);
+    }
+}
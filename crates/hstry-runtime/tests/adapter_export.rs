@@ -26,6 +26,7 @@ fn export_request_serializes() {
             parts: None,
             tool_calls: None,
             metadata: None,
+            parent_idx: None,
         }],
         metadata: None,
         version: None,
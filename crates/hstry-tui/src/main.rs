@@ -1,9 +1,11 @@
 #![allow(clippy::print_stdout, clippy::print_stderr)]
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::io::{self, Write};
 use std::path::PathBuf;
-use std::time::Duration;
+use std::sync::Arc;
+use std::sync::mpsc as std_mpsc;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 use chrono::Datelike;
@@ -11,6 +13,7 @@ use clap::{Args, Parser};
 use crossterm::{
     event::{
         self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, KeyModifiers,
+        MouseButton, MouseEvent, MouseEventKind,
     },
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
@@ -22,14 +25,26 @@ use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style, Stylize},
     text::{Line, Span},
-    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Sparkline, Wrap},
 };
 use uuid::Uuid;
 
 use hstry_core::{
     Config, Database,
-    db::ListConversationsOptions,
+    branches::{self, Branch},
+    config::{BadgeColor, RemoteConfig},
+    db::{
+        ActivityStats, ChangeLogEntry, ListConversationsOptions, MessageRange, SearchOptions,
+        SourceStats, UsageTotals,
+    },
+    export::{self, ExportFormat},
+    html_export,
+    labels::{self, QUICK_LABELS},
     models::{Conversation, Message, MessageRole, SearchHit, Source},
+    parts::Part,
+    revisions::{MessageRevision, RevisionKind},
+    saved_search::SavedSearch,
+    tr,
 };
 
 // =============================================================================
@@ -567,19 +582,13 @@ fn try_main() -> Result<()> {
         .config
         .unwrap_or_else(Config::default_config_path);
     let config = Config::ensure_at(&config_path)?;
+    hstry_core::i18n::init(config.locale.as_deref());
 
     // Create tokio runtime for async operations
     let rt = tokio::runtime::Runtime::new()?;
 
     // Open database
-    let db = rt.block_on(Database::open(&config.database))?;
-
-    // Load initial data
-    let sources = rt.block_on(db.list_sources())?;
-    let conversations = rt.block_on(db.list_conversations(ListConversationsOptions {
-        limit: None,
-        ..Default::default()
-    }))?;
+    let db = Arc::new(rt.block_on(Database::open_config(&config))?);
 
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -587,8 +596,11 @@ fn try_main() -> Result<()> {
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let mut app = App::new(config, db, sources, conversations);
-    let result = run_app(&mut terminal, &mut app, &rt);
+    let search_worker = SearchWorker::spawn(&rt, db.clone(), config.remotes.clone());
+    let loader = Loader::new(rt.handle().clone());
+    let mut app = App::new(config, db, search_worker, loader);
+    app.load_read_state(&rt);
+    let result = run_app(&mut terminal, &mut app);
 
     disable_raw_mode()?;
     execute!(
@@ -598,8 +610,20 @@ fn try_main() -> Result<()> {
     )?;
     terminal.show_cursor()?;
 
-    // Close database
-    rt.block_on(app.db.close());
+    // Anything ingested before this session started has now been seen; newer
+    // arrivals stay new for the next session.
+    let _ = rt.block_on(app.db.set_last_seen(&app.profile, app.session_started_at));
+
+    // Stop the search worker so it releases its database handle, then close
+    // the database. A load still in flight keeps its handle; the pool is
+    // then closed when the runtime shuts down.
+    let App {
+        db, search_worker, ..
+    } = app;
+    search_worker.shutdown(&rt);
+    if let Ok(db) = Arc::try_unwrap(db) {
+        rt.block_on(db.close());
+    }
 
     result
 }
@@ -640,6 +664,22 @@ enum AppMode {
         source_id: String,
         source_name: String,
     },
+    Export {
+        ids: Vec<Uuid>,
+        target: ExportTarget,
+        path: String,
+        cursor: usize,
+    },
+    Filter {
+        form: PanelFilters,
+        /// Index into [`FILTER_FIELDS`].
+        field: usize,
+    },
+    Stats,
+    /// Picking a quick label for the message at the top of the detail pane.
+    Label {
+        message_id: Uuid,
+    },
 }
 
 impl AppMode {
@@ -651,6 +691,10 @@ impl AppMode {
             AppMode::Sort => "SORT",
             AppMode::Delete { .. } => "DELETE",
             AppMode::DeleteSource { .. } => "DELETE SOURCE",
+            AppMode::Export { .. } => "EXPORT",
+            AppMode::Filter { .. } => "FILTER",
+            AppMode::Stats => "STATS",
+            AppMode::Label { .. } => "LABEL",
         }
     }
 
@@ -661,10 +705,309 @@ impl AppMode {
             AppMode::Help { .. } => Color::Yellow,
             AppMode::Sort => Color::Magenta,
             AppMode::Delete { .. } | AppMode::DeleteSource { .. } => Color::Red,
+            AppMode::Export { .. } => Color::Cyan,
+            AppMode::Filter { .. } => Color::Magenta,
+            AppMode::Stats => Color::Cyan,
+            AppMode::Label { .. } => Color::Yellow,
+        }
+    }
+}
+
+// =============================================================================
+// Export
+// =============================================================================
+
+/// Formats offered by the export dialog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExportTarget {
+    Markdown,
+    Json,
+    /// Static site directory, as written by `hstry export --format html`.
+    Html,
+}
+
+impl ExportTarget {
+    fn next(self) -> Self {
+        match self {
+            Self::Markdown => Self::Json,
+            Self::Json => Self::Html,
+            Self::Html => Self::Markdown,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Markdown => "Markdown",
+            Self::Json => "JSON",
+            Self::Html => "HTML site",
+        }
+    }
+
+    fn default_path(self) -> &'static str {
+        match self {
+            Self::Markdown => "hstry-export.md",
+            Self::Json => "hstry-export.json",
+            Self::Html => "hstry-export",
+        }
+    }
+
+    /// Native single-file format, or `None` for the HTML site.
+    fn format(self) -> Option<ExportFormat> {
+        match self {
+            Self::Markdown => Some(ExportFormat::Markdown),
+            Self::Json => Some(ExportFormat::Json),
+            Self::Html => None,
+        }
+    }
+}
+
+/// Write `ids` to `path` with the same renderers `hstry export` uses.
+/// Remote search hits are skipped since their messages are not stored
+/// locally.
+async fn run_export(
+    db: &Database,
+    ids: Vec<Uuid>,
+    target: ExportTarget,
+    path: PathBuf,
+) -> hstry_core::Result<String> {
+    let mut loaded = Vec::with_capacity(ids.len());
+    for id in ids {
+        let Some(conversation) = db.get_conversation(id).await? else {
+            continue;
+        };
+        let messages = db.get_messages(id).await?;
+        loaded.push((conversation, messages));
+    }
+    if loaded.is_empty() {
+        return Err(hstry_core::Error::NotFound(
+            "none of the selected conversations are stored locally".to_string(),
+        ));
+    }
+
+    let count = loaded.len();
+    // Rendering (syntax highlighting for HTML) is CPU-bound.
+    tokio::task::spawn_blocking(move || {
+        let written = match target.format() {
+            Some(format) => {
+                let parsed: Vec<_> = loaded
+                    .into_iter()
+                    .map(|(conversation, messages)| export::to_parsed(&conversation, messages))
+                    .collect();
+                if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(&path, export::render(format, &parsed, true)?)?;
+                path
+            }
+            None => {
+                let site: Vec<_> = loaded
+                    .into_iter()
+                    .map(|(conversation, messages)| html_export::SiteConversation {
+                        conversation,
+                        messages,
+                    })
+                    .collect();
+                html_export::write_site(&path, &site)?;
+                path.join("index.html")
+            }
+        };
+        Ok(format!(
+            "Exported {count} conversations to {}",
+            written.display()
+        ))
+    })
+    .await
+    .map_err(|e| hstry_core::Error::Other(e.to_string()))?
+}
+
+// =============================================================================
+// Filter Panel
+// =============================================================================
+
+/// Filters set in the filter panel (`f`). They narrow the conversations
+/// loaded from the database; the left pane filters apply on top.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct PanelFilters {
+    /// Conversations with at least one message in this role.
+    role: Option<MessageRole>,
+    model: String,
+    provider: String,
+    harness: String,
+    /// First day, `YYYY-MM-DD`.
+    from: String,
+    /// Last day, `YYYY-MM-DD`, inclusive.
+    to: String,
+}
+
+/// Role choices cycled by the panel's role field.
+const FILTER_ROLES: [Option<MessageRole>; 5] = [
+    None,
+    Some(MessageRole::User),
+    Some(MessageRole::Assistant),
+    Some(MessageRole::System),
+    Some(MessageRole::Tool),
+];
+
+const FILTER_FIELDS: [&str; 6] = ["Role", "Model", "Provider", "Harness", "From", "To"];
+
+impl PanelFilters {
+    fn is_active(&self) -> bool {
+        *self != Self::default()
+    }
+
+    /// Text of field `field`; the role field has none.
+    fn text_mut(&mut self, field: usize) -> Option<&mut String> {
+        match field {
+            1 => Some(&mut self.model),
+            2 => Some(&mut self.provider),
+            3 => Some(&mut self.harness),
+            4 => Some(&mut self.from),
+            5 => Some(&mut self.to),
+            _ => None,
+        }
+    }
+
+    fn value(&self, field: usize) -> String {
+        match field {
+            0 => self
+                .role
+                .as_ref()
+                .map_or_else(|| "any".to_string(), ToString::to_string),
+            1 => self.model.clone(),
+            2 => self.provider.clone(),
+            3 => self.harness.clone(),
+            4 => self.from.clone(),
+            _ => self.to.clone(),
+        }
+    }
+
+    fn cycle_role(&mut self, forward: bool) {
+        let at = FILTER_ROLES
+            .iter()
+            .position(|role| *role == self.role)
+            .unwrap_or(0);
+        let count = FILTER_ROLES.len();
+        let next = if forward {
+            (at + 1) % count
+        } else {
+            (at + count - 1) % count
+        };
+        self.role = FILTER_ROLES[next].clone();
+    }
+
+    /// The list query for these filters. Model, provider and harness match
+    /// as substrings.
+    fn options(&self) -> std::result::Result<ListConversationsOptions, String> {
+        let substring = |value: &str| {
+            let value = value.trim();
+            (!value.is_empty()).then(|| format!("%{value}%"))
+        };
+        let day = |value: &str, label: &str| {
+            let value = value.trim();
+            if value.is_empty() {
+                return Ok(None);
+            }
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .map(|date| Some(date.and_time(chrono::NaiveTime::MIN).and_utc()))
+                .map_err(|_| format!("{label} must be a date like 2025-01-31"))
+        };
+        let from = day(&self.from, "From")?;
+        let to = day(&self.to, "To")?;
+        Ok(ListConversationsOptions {
+            model: substring(&self.model),
+            provider: substring(&self.provider),
+            harness: substring(&self.harness),
+            role: self.role.clone(),
+            after: from.map(|start| start - chrono::Duration::seconds(1)),
+            before: to.map(|end| end + chrono::Duration::days(1)),
+            ..Default::default()
+        })
+    }
+
+    /// One-line description for the conversations pane title.
+    fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(role) = &self.role {
+            parts.push(format!("role={role}"));
+        }
+        for (label, value) in [
+            ("model", &self.model),
+            ("provider", &self.provider),
+            ("harness", &self.harness),
+        ] {
+            if !value.trim().is_empty() {
+                parts.push(format!("{label}~{}", value.trim()));
+            }
+        }
+        match (self.from.trim(), self.to.trim()) {
+            ("", "") => {}
+            (from, "") => parts.push(format!("from {from}")),
+            ("", to) => parts.push(format!("until {to}")),
+            (from, to) => parts.push(format!("{from}..{to}")),
         }
+        parts.join(", ")
+    }
+}
+
+/// External programs a conversation can be opened in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Viewer {
+    Pager,
+    Editor,
+}
+
+impl Viewer {
+    /// `$PAGER` / `$EDITOR` (`$VISUAL` first), with the usual fallbacks.
+    fn command(self) -> String {
+        let vars: &[&str] = match self {
+            Self::Pager => &["PAGER"],
+            Self::Editor => &["VISUAL", "EDITOR"],
+        };
+        vars.iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.trim().is_empty())
+            .unwrap_or_else(|| match self {
+                Self::Pager => "less".to_string(),
+                Self::Editor => "vi".to_string(),
+            })
     }
 }
 
+/// What to do with a conversation once it is rendered as markdown.
+#[derive(Debug, Clone)]
+enum MarkdownUse {
+    Copy,
+    /// Write it to `path` and open that in `viewer`.
+    Open {
+        viewer: Viewer,
+        path: PathBuf,
+    },
+}
+
+/// Run `command` (a shell snippet such as `code --wait`) on `path` with the
+/// terminal handed over, then take the terminal back.
+fn open_external<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    command: &str,
+    path: &std::path::Path,
+) -> Result<std::process::ExitStatus>
+where
+    B::Error: Send + Sync + 'static,
+{
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture)?;
+    let status = std::process::Command::new("sh")
+        .arg("-c")
+        .arg(format!("{command} \"$1\""))
+        .arg("sh")
+        .arg(path)
+        .status();
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)?;
+    terminal.clear()?;
+    Ok(status?)
+}
+
 // =============================================================================
 // Focus Pane
 // =============================================================================
@@ -690,13 +1033,13 @@ enum SortOrder {
 }
 
 impl SortOrder {
-    fn label(self) -> &'static str {
+    fn label(self) -> String {
         match self {
-            SortOrder::DateDesc => "Date (newest first)",
-            SortOrder::DateAsc => "Date (oldest first)",
-            SortOrder::TitleAsc => "Title (A-Z)",
-            SortOrder::TitleDesc => "Title (Z-A)",
-            SortOrder::SourceAsc => "Source",
+            SortOrder::DateDesc => tr!("tui-sort-date-desc"),
+            SortOrder::DateAsc => tr!("tui-sort-date-asc"),
+            SortOrder::TitleAsc => tr!("tui-sort-title-asc"),
+            SortOrder::TitleDesc => tr!("tui-sort-title-desc"),
+            SortOrder::SourceAsc => tr!("tui-sort-source"),
         }
     }
 
@@ -740,6 +1083,360 @@ impl SearchScope {
     }
 }
 
+// =============================================================================
+// Search Worker
+// =============================================================================
+
+/// Quiet period after the last keystroke before a live search runs.
+const SEARCH_DEBOUNCE: Duration = Duration::from_millis(200);
+
+struct SearchRequest {
+    generation: u64,
+    /// Empty to cancel whatever is pending or running.
+    query: String,
+    scope: SearchScope,
+    opts: SearchOptions,
+    immediate: bool,
+}
+
+struct SearchResponse {
+    generation: u64,
+    query: String,
+    scope: SearchScope,
+    result: std::result::Result<Vec<SearchHit>, String>,
+}
+
+/// Runs searches on the tokio runtime so typing never waits on a query.
+/// Requests are debounced, and a newer request cancels the one in flight;
+/// responses carry a generation so stale ones are dropped.
+struct SearchWorker {
+    requests: tokio::sync::mpsc::UnboundedSender<SearchRequest>,
+    responses: std_mpsc::Receiver<SearchResponse>,
+    generation: u64,
+    /// Generation of the newest request without a response yet.
+    pending: Option<u64>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl SearchWorker {
+    fn spawn(rt: &tokio::runtime::Runtime, db: Arc<Database>, remotes: Vec<RemoteConfig>) -> Self {
+        let (request_tx, request_rx) = tokio::sync::mpsc::unbounded_channel();
+        let (response_tx, response_rx) = std_mpsc::channel();
+        let task = rt.spawn(search_worker_loop(db, remotes, request_rx, response_tx));
+        Self {
+            requests: request_tx,
+            responses: response_rx,
+            generation: 0,
+            pending: None,
+            task,
+        }
+    }
+
+    /// Stop the worker, abandoning any running search.
+    fn shutdown(self, rt: &tokio::runtime::Runtime) {
+        self.task.abort();
+        let _ = rt.block_on(self.task);
+    }
+
+    fn submit(&mut self, query: String, scope: SearchScope, opts: SearchOptions, immediate: bool) {
+        self.generation += 1;
+        self.pending = Some(self.generation);
+        let _ = self.requests.send(SearchRequest {
+            generation: self.generation,
+            query,
+            scope,
+            opts,
+            immediate,
+        });
+    }
+
+    fn cancel(&mut self) {
+        self.generation += 1;
+        self.pending = None;
+        let _ = self.requests.send(SearchRequest {
+            generation: self.generation,
+            query: String::new(),
+            scope: SearchScope::Local,
+            opts: SearchOptions::default(),
+            immediate: true,
+        });
+    }
+
+    fn is_busy(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// The response to the newest request, once it has arrived.
+    fn poll(&mut self) -> Option<SearchResponse> {
+        let mut latest = None;
+        while let Ok(response) = self.responses.try_recv() {
+            if Some(response.generation) == self.pending {
+                latest = Some(response);
+            }
+        }
+        if latest.is_some() {
+            self.pending = None;
+        }
+        latest
+    }
+}
+
+async fn search_worker_loop(
+    db: Arc<Database>,
+    remotes: Vec<RemoteConfig>,
+    mut requests: tokio::sync::mpsc::UnboundedReceiver<SearchRequest>,
+    responses: std_mpsc::Sender<SearchResponse>,
+) {
+    let mut next = None;
+    loop {
+        let mut request = match next.take() {
+            Some(request) => request,
+            None => match requests.recv().await {
+                Some(request) => request,
+                None => return,
+            },
+        };
+
+        // Wait until typing pauses, always keeping only the newest query.
+        while !request.immediate {
+            match tokio::time::timeout(SEARCH_DEBOUNCE, requests.recv()).await {
+                Ok(Some(newer)) => request = newer,
+                Ok(None) => return,
+                Err(_) => break,
+            }
+        }
+        if request.query.is_empty() {
+            continue;
+        }
+
+        // Dropping the search future cancels it when a newer request arrives.
+        tokio::select! {
+            result = run_search(&db, &remotes, &request.query, request.scope, request.opts.clone()) => {
+                let _ = responses.send(SearchResponse {
+                    generation: request.generation,
+                    query: request.query,
+                    scope: request.scope,
+                    result: result.map_err(|e| e.to_string()),
+                });
+            }
+            newer = requests.recv() => match newer {
+                Some(newer) => next = Some(newer),
+                None => return,
+            },
+        }
+    }
+}
+
+async fn run_search(
+    db: &Database,
+    remotes: &[RemoteConfig],
+    query: &str,
+    scope: SearchScope,
+    opts: SearchOptions,
+) -> hstry_core::Result<Vec<SearchHit>> {
+    let mut results = Vec::new();
+    if scope != SearchScope::Remote {
+        let local =
+            if let Some(hits) = hstry_core::service::try_service_search(query, &opts).await? {
+                hits
+            } else {
+                db.search(query, opts.clone()).await?
+            };
+        results.extend(local);
+    }
+
+    if scope != SearchScope::Local {
+        let remote_hits = hstry_core::remote::search_remotes(remotes, query, &opts).await?;
+        results.extend(remote_hits);
+    }
+
+    results.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    Ok(results)
+}
+
+// =============================================================================
+// Background Loading
+// =============================================================================
+
+/// Where the detail pane scrolls once a message load lands.
+#[derive(Debug, Clone, Copy)]
+enum ScrollTo {
+    /// The first match of the last search, or the top.
+    FirstMatch,
+    Top,
+    Bottom,
+}
+
+enum LoadedMessages {
+    /// Every message of a branched conversation.
+    Tree(Vec<Message>),
+    /// One window of a linear conversation, or a whole remote one.
+    Window {
+        messages: Vec<Message>,
+        offset: i64,
+        total: Option<i64>,
+    },
+}
+
+struct LoadedData {
+    change_seq: Option<i64>,
+    sources: Vec<Source>,
+    conversations: Vec<Conversation>,
+}
+
+struct LoadedCounts {
+    by_source: Vec<(String, i64)>,
+    by_workspace: Vec<(String, i64)>,
+    workspace_names: HashMap<String, String>,
+    new_ids: Vec<Uuid>,
+    starred_ids: HashSet<Uuid>,
+}
+
+/// Days of history loaded for the statistics screen's sparkline, which
+/// shows as many of the latest as fit.
+const STATS_DAYS: i64 = 365;
+/// Rows in each top list of the statistics screen.
+const STATS_TOP: usize = 8;
+
+/// Figures shown by the statistics screen.
+struct DashboardStats {
+    conversations: i64,
+    messages: i64,
+    activity: ActivityStats,
+    per_day: Vec<(chrono::NaiveDate, i64)>,
+    sources: Vec<SourceStats>,
+    models: Vec<(String, i64)>,
+    workspaces: Vec<(String, i64)>,
+    usage: UsageTotals,
+}
+
+/// Why conversations and sources are being reloaded.
+enum Reload {
+    /// First load after start; opens the first conversation.
+    Startup,
+    /// Explicit refresh or a write; shows `status` once done.
+    Refresh { status: String },
+    /// The change log moved; keeps the selection.
+    Follow(FollowUp),
+    /// The filter panel changed; opens the first matching conversation.
+    Filters,
+}
+
+struct FollowUp {
+    selected: Option<Uuid>,
+    /// The selected conversation itself changed and must be reloaded.
+    selected_changed: bool,
+    /// Distinct conversations touched.
+    changed: usize,
+    detail_scroll: usize,
+}
+
+/// Results sent back by background loads.
+enum DataEvent {
+    Messages {
+        generation: u64,
+        scroll: ScrollTo,
+        result: std::result::Result<LoadedMessages, String>,
+    },
+    Data {
+        generation: u64,
+        reload: Reload,
+        result: std::result::Result<LoadedData, String>,
+    },
+    Counts(std::result::Result<LoadedCounts, String>),
+    Stats(std::result::Result<DashboardStats, String>),
+    Changes(std::result::Result<Vec<ChangeLogEntry>, String>),
+    /// A delete finished; the data is reloaded and `status` shown.
+    Written {
+        status: String,
+    },
+    Exported(std::result::Result<String, String>),
+    /// Markdown of a whole conversation.
+    Rendered {
+        purpose: MarkdownUse,
+        result: std::result::Result<String, String>,
+    },
+    MarkedRead(std::result::Result<(), String>),
+    SavedSearches(std::result::Result<Vec<SavedSearch>, String>),
+    /// A label was toggled; the message's labels afterwards.
+    Labelled {
+        message_id: Uuid,
+        label: String,
+        result: std::result::Result<Vec<String>, String>,
+    },
+    /// A conversation was starred or unstarred.
+    Starred {
+        conversation_id: Uuid,
+        starred: bool,
+        result: std::result::Result<(), String>,
+    },
+    /// Sources holding other copies of a conversation.
+    Related {
+        conversation_id: Uuid,
+        result: std::result::Result<Vec<String>, String>,
+    },
+    /// Earlier versions of a conversation's messages.
+    Revisions {
+        conversation_id: Uuid,
+        result: std::result::Result<Vec<MessageRevision>, String>,
+    },
+}
+
+/// Runs database and remote loads as tasks on the tokio runtime. Results
+/// come back over a channel and are applied between frames, so the UI
+/// keeps drawing and taking input while they run.
+struct Loader {
+    handle: tokio::runtime::Handle,
+    events_tx: std_mpsc::Sender<DataEvent>,
+    events: std_mpsc::Receiver<DataEvent>,
+}
+
+impl Loader {
+    fn new(handle: tokio::runtime::Handle) -> Self {
+        let (events_tx, events) = std_mpsc::channel();
+        Self {
+            handle,
+            events_tx,
+            events,
+        }
+    }
+
+    fn spawn<F>(&self, load: F)
+    where
+        F: std::future::Future<Output = DataEvent> + Send + 'static,
+    {
+        let events = self.events_tx.clone();
+        self.handle.spawn(async move {
+            let _ = events.send(load.await);
+        });
+    }
+
+    fn try_recv(&self) -> Option<DataEvent> {
+        self.events.try_recv().ok()
+    }
+}
+
+/// Panes waiting on a background load, shown as a marker in their titles.
+#[derive(Debug, Default)]
+struct Loading {
+    nav: bool,
+    list: bool,
+    detail: bool,
+    /// A change log poll is in flight.
+    changes: bool,
+    stats: bool,
+}
+
+impl Loading {
+    fn any(&self) -> bool {
+        self.nav || self.list || self.detail || self.changes || self.stats
+    }
+}
+
 // =============================================================================
 // Filter State
 // =============================================================================
@@ -748,6 +1445,8 @@ impl SearchScope {
 struct FilterState {
     source: Option<String>,
     source_adapter: Option<String>,
+    /// Name of a `[groups]` entry.
+    group: Option<String>,
     workspace: Option<String>,
     date_range: Option<(chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>)>,
 }
@@ -833,14 +1532,17 @@ enum LeftPaneView {
     Sources,
     Workspaces,
     Dates,
+    /// Searches saved with `hstry search --save`.
+    Saved,
 }
 
 impl LeftPaneView {
-    fn label(self) -> &'static str {
+    fn label(self) -> String {
         match self {
-            Self::Sources => "Sources",
-            Self::Workspaces => "Workspaces",
-            Self::Dates => "Dates",
+            Self::Sources => tr!("tui-view-sources"),
+            Self::Workspaces => tr!("tui-view-workspaces"),
+            Self::Dates => tr!("tui-view-dates"),
+            Self::Saved => tr!("tui-view-saved"),
         }
     }
 
@@ -848,7 +1550,8 @@ impl LeftPaneView {
         match self {
             Self::Sources => Self::Workspaces,
             Self::Workspaces => Self::Dates,
-            Self::Dates => Self::Sources,
+            Self::Dates => Self::Saved,
+            Self::Saved => Self::Sources,
         }
     }
 }
@@ -860,21 +1563,31 @@ impl LeftPaneView {
 #[derive(Debug, Clone)]
 enum NavItem {
     All,
+    /// A `[groups]` entry, followed by its adapters.
+    Group {
+        name: String,
+        source_ids: Vec<String>,
+    },
     Source {
         adapter: String,
         source_ids: Vec<String>,
+        /// Group section the item is listed under.
+        group: Option<String>,
     },
     Workspace(String),
     // Date grouping items
     DateYear(i32),          // Year (e.g., 2025)
     DateMonth(i32, u32),    // Year, Month (1-12)
     DateDay(i32, u32, u32), // Year, Month, Day
+    /// A saved search, by name.
+    Saved(String),
 }
 
 impl NavItem {
     fn label(&self) -> String {
         match self {
-            NavItem::All => "All Conversations".to_string(),
+            NavItem::All => tr!("tui-all-conversations"),
+            NavItem::Group { name, .. } => format!("# {name}"),
             NavItem::Source { adapter, .. } => adapter.clone(),
             NavItem::Workspace(ws) => format!("@ {ws}"),
             NavItem::DateYear(year) => year.to_string(),
@@ -890,47 +1603,92 @@ impl NavItem {
                 )
             }
             NavItem::DateDay(year, month, day) => format!("{month:02}/{day:02}/{year}"),
+            NavItem::Saved(name) => format!("/ {name}"),
         }
     }
 }
 
-fn build_source_nav_items(sources: &[Source]) -> Vec<NavItem> {
-    let mut grouped: HashMap<String, Vec<String>> = HashMap::new();
+/// Sources grouped by adapter: ungrouped sources first, then one section per
+/// `[groups]` entry (highest priority first).
+fn build_source_nav_items(sources: &[Source], config: &Config) -> Vec<NavItem> {
+    let mut grouped: BTreeMap<Option<&str>, Vec<&Source>> = BTreeMap::new();
     for source in sources {
-        grouped
-            .entry(source.adapter.clone())
-            .or_default()
-            .push(source.id.clone());
+        let group = config.source_group(&source.id).map(|(name, _)| name);
+        grouped.entry(group).or_default().push(source);
     }
 
-    let mut adapters: Vec<String> = grouped.keys().cloned().collect();
-    adapters.sort();
-
     let mut nav_items = vec![NavItem::All];
-    for adapter in adapters {
-        let mut ids = grouped.remove(&adapter).unwrap_or_default();
-        ids.sort();
-        nav_items.push(NavItem::Source {
-            adapter,
-            source_ids: ids,
+    if let Some(ungrouped) = grouped.remove(&None) {
+        nav_items.extend(adapter_nav_items(&ungrouped, None));
+    }
+    let mut groups: Vec<(&str, Vec<&Source>)> = grouped
+        .into_iter()
+        .filter_map(|(name, sources)| Some((name?, sources)))
+        .collect();
+    groups.sort_by_key(|(name, _)| {
+        std::cmp::Reverse(config.groups.get(*name).map_or(0, |group| group.priority))
+    });
+    for (name, sources) in groups {
+        let mut source_ids: Vec<String> = sources.iter().map(|s| s.id.clone()).collect();
+        source_ids.sort();
+        nav_items.push(NavItem::Group {
+            name: name.to_string(),
+            source_ids,
         });
+        nav_items.extend(adapter_nav_items(&sources, Some(name)));
     }
 
     nav_items
 }
 
+fn adapter_nav_items(sources: &[&Source], group: Option<&str>) -> Vec<NavItem> {
+    let mut by_adapter: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for source in sources {
+        by_adapter
+            .entry(source.adapter.as_str())
+            .or_default()
+            .push(source.id.clone());
+    }
+    by_adapter
+        .into_iter()
+        .map(|(adapter, mut source_ids)| {
+            source_ids.sort();
+            NavItem::Source {
+                adapter: adapter.to_string(),
+                source_ids,
+                group: group.map(str::to_string),
+            }
+        })
+        .collect()
+}
+
 // =============================================================================
 // App State
 // =============================================================================
 
+/// Messages loaded into the detail pane at once. Long agent sessions are
+/// paged through in windows of this size as the user scrolls.
+const MESSAGE_WINDOW: i64 = 200;
+
+/// How often to poll the change log for writes by the service or other
+/// processes.
+const CHANGE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Change log entries inspected per poll; a longer backlog is treated as
+/// touching every conversation.
+const CHANGE_BATCH: i64 = 500;
+
 struct App {
     config: Config,
-    db: Database,
+    db: Arc<Database>,
     mode: AppMode,
     focus: FocusPane,
     sort_order: SortOrder,
     sort_selection: usize,
     filter: FilterState,
+    panel_filters: PanelFilters,
+    /// Database query for `panel_filters`.
+    list_options: ListConversationsOptions,
     g_prefix: bool,
 
     // Data
@@ -938,10 +1696,58 @@ struct App {
     all_conversations: Vec<Conversation>,
     filtered_conversations: Vec<Conversation>,
     messages: Vec<Message>,
+    /// Position of `messages[0]` within the conversation.
+    message_offset: i64,
+    /// Total messages in the selected conversation.
+    message_total: i64,
+    /// Rendered line count of the loaded window.
+    message_line_count: usize,
+    folds: MessageFolds,
+    history: MessageHistory,
+    /// Every message of the selected conversation when it branches; empty
+    /// for linear conversations, which load in windows instead.
+    tree_messages: Vec<Message>,
+    branches: Vec<Branch>,
+    /// Branch shown in the right pane (0 is the active thread).
+    branch_index: usize,
     search_results: Vec<SearchHit>,
     show_search_results: bool,
     last_search_query: Option<String>,
     search_scope: SearchScope,
+    search_worker: SearchWorker,
+    saved_searches: Vec<SavedSearch>,
+    /// Saved search whose results are on screen (or on their way).
+    active_saved: Option<SavedSearch>,
+
+    // Background loads, what is still loading, and the newest request per
+    // kind so stale results are dropped
+    loader: Loader,
+    loading: Loading,
+    message_generation: u64,
+    data_generation: u64,
+
+    // Badge counts for the left pane, keyed by source id / workspace
+    source_counts: HashMap<String, i64>,
+    workspace_counts: HashMap<String, i64>,
+    // Display names from `hstry workspace rename`, keyed by path
+    workspace_names: HashMap<String, String>,
+    // Statistics screen, from its last load
+    stats: Option<DashboardStats>,
+    // Newest change log entry reflected on screen, and when we last polled
+    change_seq: i64,
+    last_change_check: Instant,
+    // Read state: the profile's watermark at session start, the conversations
+    // ingested after it, and when this session began
+    profile: String,
+    last_seen: DateTime<Utc>,
+    new_ids: HashSet<Uuid>,
+    session_started_at: DateTime<Utc>,
+    // Conversations starred with `*` or `hstry star`
+    starred_ids: HashSet<Uuid>,
+    // Sources with copies of the open conversation (`hstry link detect`)
+    related_sources: Option<(Uuid, Vec<String>)>,
+    // Conversations moved to the trash by the last `d`, restored by `u`
+    last_trashed: Vec<Uuid>,
 
     // Navigation items for left pane
     left_pane_view: LeftPaneView,
@@ -956,21 +1762,26 @@ struct App {
     // Right pane scroll
     detail_scroll: usize,
 
+    // Last left click, to recognise double clicks
+    last_click: Option<(Instant, u16, u16)>,
+
+    // Opened on first copy and kept: on X11 the copied text is only
+    // available while its owner is alive
+    clipboard: Option<arboard::Clipboard>,
+    // Conversation file to open once the event loop can hand over the
+    // terminal
+    pending_open: Option<(Viewer, PathBuf)>,
+
     // Status message
     status_message: String,
 }
 
 impl App {
-    fn new(
-        config: Config,
-        db: Database,
-        sources: Vec<Source>,
-        conversations: Vec<Conversation>,
-    ) -> Self {
+    fn new(config: Config, db: Arc<Database>, search_worker: SearchWorker, loader: Loader) -> Self {
         // Build navigation items for Sources view (default)
-        let nav_items = build_source_nav_items(&sources);
+        let nav_items = build_source_nav_items(&[], &config);
 
-        let filtered_conversations = conversations.clone();
+        let profile = config.active_profile();
 
         Self {
             config,
@@ -980,22 +1791,55 @@ impl App {
             sort_order: SortOrder::DateDesc,
             sort_selection: 0,
             filter: FilterState::default(),
+            panel_filters: PanelFilters::default(),
+            list_options: ListConversationsOptions::default(),
             g_prefix: false,
-            sources,
-            all_conversations: conversations,
-            filtered_conversations,
+            sources: Vec::new(),
+            all_conversations: Vec::new(),
+            filtered_conversations: Vec::new(),
             messages: Vec::new(),
+            message_offset: 0,
+            message_total: 0,
+            message_line_count: 0,
+            folds: MessageFolds::default(),
+            history: MessageHistory::default(),
+            tree_messages: Vec::new(),
+            branches: Vec::new(),
+            branch_index: 0,
             search_results: Vec::new(),
             show_search_results: false,
             last_search_query: None,
             search_scope: SearchScope::Local,
+            search_worker,
+            saved_searches: Vec::new(),
+            active_saved: None,
+            loader,
+            loading: Loading::default(),
+            message_generation: 0,
+            data_generation: 0,
+            source_counts: HashMap::new(),
+            workspace_counts: HashMap::new(),
+            workspace_names: HashMap::new(),
+            stats: None,
+            change_seq: 0,
+            last_change_check: Instant::now(),
+            profile,
+            last_seen: Utc::now(),
+            new_ids: HashSet::new(),
+            starred_ids: HashSet::new(),
+            related_sources: None,
+            last_trashed: Vec::new(),
+            session_started_at: Utc::now(),
             left_pane_view: LeftPaneView::Sources,
             nav_items,
             nav_selection: Selection::default(),
             expanded_dates: HashSet::new(),
             conv_selection: Selection::default(),
             detail_scroll: 0,
-            status_message: "Press ? for help, q to quit".to_string(),
+            last_click: None,
+            clipboard: None,
+            pending_open: None,
+            status_message: tr!("tui-help-hint"),
         }
     }
 
@@ -1019,6 +1863,26 @@ impl App {
         }
     }
 
+    /// The multi-selection, or the conversation under the cursor.
+    fn selected_conversation_ids(&self) -> Vec<Uuid> {
+        let id_at = |idx: usize| {
+            if self.show_search_results {
+                self.search_results.get(idx).map(|h| h.conversation_id)
+            } else {
+                self.filtered_conversations.get(idx).map(|c| c.id)
+            }
+        };
+        if self.conv_selection.has_selections() {
+            self.conv_selection
+                .selected_indices
+                .iter()
+                .filter_map(|&idx| id_at(idx))
+                .collect()
+        } else {
+            id_at(self.conv_selection.index).into_iter().collect()
+        }
+    }
+
     fn selected_conversation(&self) -> Option<&Conversation> {
         let conv_id = self.selected_conversation_id()?;
         self.all_conversations.iter().find(|c| c.id == conv_id)
@@ -1046,6 +1910,12 @@ impl App {
                         return false;
                     }
                 }
+                if let Some(ref group) = self.filter.group
+                    && self.config.source_group(&c.source_id).map(|(name, _)| name)
+                        != Some(group.as_str())
+                {
+                    return false;
+                }
                 if let Some(ref workspace) = self.filter.workspace
                     && c.workspace.as_ref() != Some(workspace)
                 {
@@ -1114,7 +1984,7 @@ impl App {
 
         match self.left_pane_view {
             LeftPaneView::Sources => {
-                self.nav_items = build_source_nav_items(&self.sources);
+                self.nav_items = build_source_nav_items(&self.sources, &self.config);
             }
             LeftPaneView::Workspaces => {
                 self.nav_items.push(NavItem::All);
@@ -1182,6 +2052,13 @@ impl App {
                     }
                 }
             }
+            LeftPaneView::Saved => {
+                self.nav_items = self
+                    .saved_searches
+                    .iter()
+                    .map(|search| NavItem::Saved(search.name.clone()))
+                    .collect();
+            }
         }
 
         // Reset selection if it's now out of bounds
@@ -1194,147 +2071,1085 @@ impl App {
         self.left_pane_view = self.left_pane_view.next();
         self.rebuild_nav_items();
         self.nav_selection.index = 0;
-        self.status_message = format!("View: {}", self.left_pane_view.label());
+        self.status_message = tr!("tui-view", view = self.left_pane_view.label());
+        if self.left_pane_view == LeftPaneView::Saved {
+            self.load_saved_searches();
+        }
+    }
+
+    /// Reload the searches listed in the Saved view.
+    fn load_saved_searches(&mut self) {
+        let db = self.db.clone();
+        self.loading.nav = true;
+        self.loader.spawn(async move {
+            DataEvent::SavedSearches(db.list_saved_searches().await.map_err(|e| e.to_string()))
+        });
+    }
+
+    /// Run the saved search called `name`; its hits replace the list.
+    fn run_saved_search(&mut self, name: &str) {
+        let Some(saved) = self.saved_searches.iter().find(|s| s.name == name).cloned() else {
+            return;
+        };
+        let mut opts = match saved.options() {
+            Ok(opts) => opts,
+            Err(e) => {
+                self.status_message = format!("Error in saved search '{name}': {e}");
+                return;
+            }
+        };
+        opts.limit = Some(100);
+        self.search_worker
+            .submit(saved.query.clone(), SearchScope::Local, opts, true);
+        self.status_message = format!("Searching \"{}\" ({name})", saved.query);
+        self.active_saved = Some(saved);
     }
 
-    fn load_messages(&mut self, rt: &tokio::runtime::Runtime) {
+    fn load_messages(&mut self) {
         if self.show_search_results
             && let Some(hit) = self.search_results.get(self.conv_selection.index)
             && let Some(host) = &hit.host
         {
-            if let Some(remote) = self.config.remotes.iter().find(|r| r.name == *host) {
-                match rt.block_on(hstry_core::remote::show_remote(
-                    remote,
-                    &hit.conversation_id.to_string(),
-                )) {
-                    Ok(details) => {
-                        self.messages = details.messages.into_iter().map(|m| m.message).collect();
-                        self.detail_scroll = 0;
-                        return;
-                    }
-                    Err(e) => {
-                        self.status_message = format!("Remote load error: {e}");
-                        self.messages.clear();
-                        return;
-                    }
-                }
-            }
-            self.status_message = format!("Remote '{host}' not found in config");
-            self.messages.clear();
+            let Some(remote) = self
+                .config
+                .remotes
+                .iter()
+                .find(|r| r.name == *host)
+                .cloned()
+            else {
+                self.status_message = format!("Remote '{host}' not found in config");
+                self.message_generation += 1;
+                self.loading.detail = false;
+                self.set_message_window(Vec::new(), 0, None);
+                return;
+            };
+            let id = hit.conversation_id.to_string();
+            self.request_messages(ScrollTo::Top, async move {
+                let details = hstry_core::remote::show_remote(&remote, &id)
+                    .await
+                    .map_err(|e| format!("Remote load error: {e}"))?;
+                Ok(LoadedMessages::Window {
+                    messages: details.messages.into_iter().map(|m| m.message).collect(),
+                    offset: 0,
+                    total: None,
+                })
+            });
             return;
         }
 
-        if let Some(conv_id) = self.selected_conversation_id() {
-            match rt.block_on(self.db.get_messages(conv_id)) {
-                Ok(msgs) => {
-                    self.messages = msgs;
-                    self.detail_scroll = self
-                        .last_search_query
-                        .as_deref()
-                        .and_then(|q| first_match_scroll(&self.messages, q))
-                        .unwrap_or(0);
+        if let Some(conversation_id) = self.selected_conversation_id() {
+            self.load_message_window(0, ScrollTo::FirstMatch);
+            let db = self.db.clone();
+            self.loader.spawn(async move {
+                let result = db
+                    .related_conversations(conversation_id)
+                    .await
+                    .map(|related| related.into_iter().map(|r| r.source_id).collect())
+                    .map_err(|e| e.to_string());
+                DataEvent::Related {
+                    conversation_id,
+                    result,
                 }
-                Err(e) => {
-                    self.status_message = format!("Error loading messages: {e}");
+            });
+            let db = self.db.clone();
+            self.loader.spawn(async move {
+                let result = db
+                    .message_revisions(conversation_id)
+                    .await
+                    .map_err(|e| e.to_string());
+                DataEvent::Revisions {
+                    conversation_id,
+                    result,
                 }
+            });
+        } else {
+            self.message_generation += 1;
+            self.loading.detail = false;
+            self.set_message_window(Vec::new(), 0, None);
+        }
+    }
+
+    fn set_message_window(&mut self, messages: Vec<Message>, offset: i64, total: Option<i64>) {
+        self.message_offset = offset;
+        self.message_total =
+            total.unwrap_or_else(|| offset + i64::try_from(messages.len()).unwrap_or(0));
+        self.messages = messages;
+        self.message_line_count = self.layout().lines.len();
+    }
+
+    fn layout(&self) -> MessageLayout {
+        layout_messages(&self.messages, &self.folds, &self.history, None)
+    }
+
+    /// Load the window of messages starting at `offset` for the selected
+    /// conversation, scrolling to `scroll` once it arrives.
+    fn load_message_window(&mut self, offset: i64, scroll: ScrollTo) {
+        let Some(conv_id) = self.selected_conversation_id() else {
+            return;
+        };
+        let db = self.db.clone();
+        self.request_messages(scroll, async move {
+            let loaded = async {
+                // Opening a conversation is a good moment to catch its
+                // summary cache up with messages added since.
+                db.refresh_summary_if_stale(conv_id).await?;
+                // Branched conversations load whole so their branches can be
+                // walked; they are rarely long enough to need windowing.
+                if db.has_message_branches(conv_id).await? {
+                    return hstry_core::Result::Ok(LoadedMessages::Tree(
+                        db.get_messages(conv_id).await?,
+                    ));
+                }
+                let total = db.count_messages_for_conversation(conv_id).await?;
+                let messages = db
+                    .get_messages_range(
+                        conv_id,
+                        MessageRange {
+                            offset: Some(offset),
+                            limit: Some(MESSAGE_WINDOW),
+                            ..Default::default()
+                        },
+                    )
+                    .await?;
+                Ok(LoadedMessages::Window {
+                    messages,
+                    offset,
+                    total: Some(total),
+                })
+            };
+            loaded
+                .await
+                .map_err(|e| format!("Error loading messages: {e}"))
+        });
+    }
+
+    /// Start a message load for the detail pane, superseding any earlier one.
+    fn request_messages<F>(&mut self, scroll: ScrollTo, load: F)
+    where
+        F: std::future::Future<Output = std::result::Result<LoadedMessages, String>>
+            + Send
+            + 'static,
+    {
+        self.message_generation += 1;
+        let generation = self.message_generation;
+        self.loading.detail = true;
+        self.loader.spawn(async move {
+            DataEvent::Messages {
+                generation,
+                scroll,
+                result: load.await,
             }
+        });
+    }
+
+    /// Show branch `index` of the loaded tree conversation.
+    fn show_branch(&mut self, index: usize) {
+        let Some(branch) = self.branches.get(index) else {
+            return;
+        };
+        let messages: Vec<Message> = branch
+            .path
+            .iter()
+            .filter_map(|idx| self.tree_messages.iter().find(|m| m.idx == *idx).cloned())
+            .collect();
+        self.branch_index = index;
+        self.set_message_window(messages, 0, None);
+    }
+
+    /// Cycle to the next (`forward`) or previous branch of a branched
+    /// conversation, keeping the scroll near where the branches diverge.
+    fn cycle_branch(&mut self, forward: bool) {
+        let count = self.branches.len();
+        if count < 2 {
+            self.status_message = "This conversation has no other branches".to_string();
+            return;
+        }
+        let next = if forward {
+            (self.branch_index + 1) % count
+        } else {
+            (self.branch_index + count - 1) % count
+        };
+        let shared = self.branches[next].shared;
+        self.show_branch(next);
+        let shared = shared.min(self.messages.len());
+        self.detail_scroll =
+            layout_messages(&self.messages[..shared], &self.folds, &self.history, None)
+                .lines
+                .len();
+        self.status_message = format!("Branch {}/{count}", next + 1);
+    }
+
+    /// Move to the next (`forward`) or previous message window, if any.
+    fn shift_message_window(&mut self, forward: bool) {
+        // Scrolling on while a window loads would queue the same shift again.
+        if self.loading.detail {
+            return;
+        }
+        let loaded = i64::try_from(self.messages.len()).unwrap_or(0);
+        let offset = if forward {
+            if self.message_offset + loaded >= self.message_total {
+                return;
+            }
+            self.message_offset + loaded
         } else {
-            self.messages.clear();
+            if self.message_offset == 0 {
+                return;
+            }
+            (self.message_offset - MESSAGE_WINDOW).max(0)
+        };
+        let scroll = if forward {
+            ScrollTo::Top
+        } else {
+            ScrollTo::Bottom
+        };
+        self.load_message_window(offset, scroll);
+    }
+
+    /// Line of the first match of the last search, unfolding the message
+    /// it is in.
+    fn first_match_scroll(&mut self) -> Option<usize> {
+        let query = self.last_search_query.clone()?;
+        let index = first_match_message(&self.messages, &query)?;
+        let message = &self.messages[index];
+        if self.folds.is_folded(message) {
+            self.folds.overrides.insert(message.id, false);
+            self.message_line_count = self.layout().lines.len();
+        }
+        first_match_scroll(&self.layout(), &query)
+    }
+
+    /// Scroll to the start of the next (`forward`) or previous message,
+    /// moving to the neighbouring window at either end.
+    fn jump_message(&mut self, forward: bool) {
+        let layout = self.layout();
+        let Some(current) = layout.block_at(self.detail_scroll) else {
+            return;
+        };
+        let current_start = layout.blocks[current].0;
+        let target = if forward {
+            layout.blocks.get(current + 1).map(|(start, _)| *start)
+        } else if self.detail_scroll > current_start {
+            Some(current_start)
+        } else {
+            current
+                .checked_sub(1)
+                .map(|previous| layout.blocks[previous].0)
+        };
+        match target {
+            Some(line) => self.detail_scroll = line,
+            None => self.shift_message_window(forward),
         }
     }
 
-    fn perform_search(&mut self, rt: &tokio::runtime::Runtime) {
-        if let AppMode::Search { ref query, .. } = self.mode {
-            if query.is_empty() {
+    /// Fold or unfold the message at the top of the detail pane. A run of
+    /// folded tool steps unfolds as a whole.
+    fn toggle_fold(&mut self) {
+        let layout = self.layout();
+        let Some(block) = layout.block_at(self.detail_scroll) else {
+            return;
+        };
+        let range = layout.blocks[block].1.clone();
+        let fold = range.len() == 1 && !self.folds.is_folded(&self.messages[range.start]);
+        for message in &self.messages[range.clone()] {
+            self.folds.overrides.insert(message.id, fold);
+        }
+        self.relayout(range.start);
+    }
+
+    /// Fold every loaded message, or unfold them all when all are folded.
+    fn toggle_fold_all(&mut self) {
+        let fold = self.messages.iter().any(|m| !self.folds.is_folded(m));
+        let current = self.current_message_index();
+        for message in &self.messages {
+            self.folds.overrides.insert(message.id, fold);
+        }
+        self.relayout(current);
+        self.status_message = if fold {
+            "Folded all messages".to_string()
+        } else {
+            "Unfolded all messages".to_string()
+        };
+    }
+
+    /// Switch between folding and showing tool calls and results by default.
+    fn toggle_tool_folding(&mut self) {
+        let current = self.current_message_index();
+        self.folds.expand_tools = !self.folds.expand_tools;
+        let tool_steps: Vec<Uuid> = self
+            .messages
+            .iter()
+            .filter(|m| tool_step_names(m).is_some())
+            .map(|m| m.id)
+            .collect();
+        for id in tool_steps {
+            self.folds.overrides.remove(&id);
+        }
+        self.relayout(current);
+        self.status_message = if self.folds.expand_tools {
+            "Showing tool calls".to_string()
+        } else {
+            "Folding tool calls".to_string()
+        };
+    }
+
+    /// Show or hide the earlier versions of edited and regenerated messages.
+    fn toggle_history(&mut self) {
+        if self.messages.iter().all(|m| self.history.of(m).is_empty()) {
+            self.status_message = "No earlier message versions".to_string();
+            return;
+        }
+        let current = self.current_message_index();
+        self.history.expanded = !self.history.expanded;
+        self.relayout(current);
+        self.status_message = if self.history.expanded {
+            "Showing earlier message versions".to_string()
+        } else {
+            "Hiding earlier message versions".to_string()
+        };
+    }
+
+    fn current_message_index(&self) -> usize {
+        let layout = self.layout();
+        layout
+            .block_at(self.detail_scroll)
+            .map(|block| layout.blocks[block].1.start)
+            .unwrap_or(0)
+    }
+
+    /// Recount lines after folds change, keeping message `index` in view.
+    fn relayout(&mut self, index: usize) {
+        let layout = self.layout();
+        self.message_line_count = layout.lines.len();
+        self.detail_scroll = layout.line_of(index).unwrap_or(0);
+    }
+
+    /// Hand the current query to the search worker. `immediate` skips the
+    /// typing debounce (Enter, scope changes).
+    fn submit_search(&mut self, immediate: bool) {
+        let AppMode::Search { ref query, .. } = self.mode else {
+            return;
+        };
+        let query = query.trim().to_string();
+        self.active_saved = None;
+        if query.is_empty() {
+            self.search_worker.cancel();
+            self.search_results.clear();
+            self.show_search_results = false;
+            self.last_search_query = None;
+            return;
+        }
+        if !immediate && self.last_search_query.as_deref() == Some(query.as_str()) {
+            // Typed back to the query on screen: drop anything newer in flight.
+            self.search_worker.cancel();
+            return;
+        }
+
+        let opts = SearchOptions {
+            limit: Some(100),
+            source_id: self.filter.source.clone(),
+            workspace: self.filter.workspace.clone(),
+            ..Default::default()
+        };
+        self.search_worker
+            .submit(query, self.search_scope, opts, immediate);
+    }
+
+    /// Apply the newest finished search, if any.
+    fn poll_search(&mut self) {
+        let Some(response) = self.search_worker.poll() else {
+            return;
+        };
+        match response.result {
+            Ok(mut results) => {
+                if let Some(saved) = &self.active_saved {
+                    results.retain(|hit| saved.keeps(hit));
+                }
+                self.search_results = results;
+                self.show_search_results = !self.search_results.is_empty();
+                self.last_search_query = Some(response.query);
+                self.conv_selection.index = 0;
+                self.status_message = match &self.active_saved {
+                    Some(saved) => tr!(
+                        "tui-found-results",
+                        count = self.search_results.len(),
+                        scope = saved.name.as_str(),
+                    ),
+                    None => tr!(
+                        "tui-found-results",
+                        count = self.search_results.len(),
+                        scope = response.scope.label(),
+                    ),
+                };
+            }
+            Err(e) => {
+                self.status_message = tr!("tui-search-error", error = e.to_string());
                 self.search_results.clear();
                 self.show_search_results = false;
                 self.last_search_query = None;
-                return;
             }
+        }
+    }
 
-            let opts = hstry_core::db::SearchOptions {
-                limit: Some(100),
-                source_id: self.filter.source.clone(),
-                workspace: self.filter.workspace.clone(),
-                ..Default::default()
+    /// Reload the per-source and per-workspace badge counts.
+    fn load_counts(&mut self) {
+        let db = self.db.clone();
+        let last_seen = self.last_seen;
+        self.loading.nav = true;
+        self.loader.spawn(async move {
+            let loaded = async {
+                hstry_core::Result::Ok(LoadedCounts {
+                    by_source: db.count_conversations_by_source().await?,
+                    by_workspace: db.count_conversations_by_workspace().await?,
+                    workspace_names: db.workspace_names().await?,
+                    new_ids: db.new_conversation_ids(last_seen).await?,
+                    starred_ids: db.starred_conversation_ids().await?,
+                })
             };
-            let search_scope = self.search_scope;
-            let config = &self.config;
-            let db = &self.db;
-            let query = query.clone();
-            let query_for_closure = query.clone();
-
-            let search = async move {
-                let query = query_for_closure;
-                let mut results = Vec::new();
-                if search_scope != SearchScope::Remote {
-                    let local = if let Some(hits) =
-                        hstry_core::service::try_service_search(&query, &opts).await?
-                    {
-                        hits
-                    } else {
-                        db.search(&query, opts.clone()).await?
-                    };
-                    results.extend(local);
-                }
-
-                if search_scope != SearchScope::Local {
-                    let remote_hits =
-                        hstry_core::remote::search_remotes(&config.remotes, &query, &opts).await?;
-                    results.extend(remote_hits);
-                }
+            DataEvent::Counts(loaded.await.map_err(|e| e.to_string()))
+        });
+    }
 
-                results.sort_by(|a, b| {
-                    b.score
-                        .partial_cmp(&a.score)
-                        .unwrap_or(std::cmp::Ordering::Equal)
-                });
-                Ok::<_, hstry_core::Error>(results)
+    /// Reload the figures of the statistics screen.
+    fn load_stats(&mut self) {
+        let db = self.db.clone();
+        self.loading.stats = true;
+        self.loader.spawn(async move {
+            let loaded = async {
+                let mut workspaces = db.count_conversations_by_workspace().await?;
+                workspaces.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+                workspaces.truncate(STATS_TOP);
+                hstry_core::Result::Ok(DashboardStats {
+                    conversations: db.count_conversations().await?,
+                    messages: db.count_messages().await?,
+                    activity: db.get_activity_stats(STATS_DAYS).await?,
+                    per_day: db.count_conversations_per_day(STATS_DAYS).await?,
+                    sources: db.get_source_stats().await?,
+                    models: db.count_conversations_by_model(STATS_TOP as i64).await?,
+                    workspaces,
+                    usage: db.get_usage_totals().await?,
+                })
             };
+            DataEvent::Stats(loaded.await.map_err(|e| e.to_string()))
+        });
+    }
 
-            match rt.block_on(search) {
-                Ok(results) => {
-                    self.search_results = results;
-                    self.show_search_results = !self.search_results.is_empty();
-                    self.last_search_query = Some(query.clone());
-                    self.conv_selection.index = 0;
-                    self.status_message = format!(
-                        "Found {} results ({})",
-                        self.search_results.len(),
-                        self.search_scope.label()
-                    );
+    /// Load the profile's read watermark. Called once at startup.
+    fn load_read_state(&mut self, rt: &tokio::runtime::Runtime) {
+        match rt.block_on(self.db.last_seen_or_init(&self.profile)) {
+            Ok(last_seen) => self.last_seen = last_seen,
+            Err(e) => self.status_message = format!("Error loading read state: {e}"),
+        }
+    }
+
+    fn mark_all_read(&mut self) {
+        let db = self.db.clone();
+        let profile = self.profile.clone();
+        self.loader.spawn(async move {
+            DataEvent::MarkedRead(db.mark_all_read(&profile).await.map_err(|e| e.to_string()))
+        });
+    }
+
+    /// Star the selected conversation, or unstar it when it is starred.
+    fn toggle_star(&mut self) {
+        let Some(conversation_id) = self.selected_conversation_id() else {
+            self.status_message = tr!("tui-no-conversation");
+            return;
+        };
+        let starred = !self.starred_ids.contains(&conversation_id);
+        let db = self.db.clone();
+        self.loader.spawn(async move {
+            let result = db
+                .set_conversation_starred(conversation_id, starred)
+                .await
+                .map(|_| ())
+                .map_err(|e| e.to_string());
+            DataEvent::Starred {
+                conversation_id,
+                starred,
+                result,
+            }
+        });
+    }
+
+    /// Ask which quick label to toggle on the message at the top of the
+    /// detail pane.
+    fn start_labelling(&mut self) {
+        match message_at_line(
+            &self.messages,
+            &self.folds,
+            &self.history,
+            self.detail_scroll,
+        ) {
+            Some(message) => {
+                self.mode = AppMode::Label {
+                    message_id: message.id,
+                };
+            }
+            None => self.status_message = "No message to label".to_string(),
+        }
+    }
+
+    fn toggle_label(&mut self, message_id: Uuid, label: &'static str) {
+        let db = self.db.clone();
+        self.loader.spawn(async move {
+            let result = db
+                .toggle_message_label(message_id, label)
+                .await
+                .map_err(|e| e.to_string());
+            DataEvent::Labelled {
+                message_id,
+                label: label.to_string(),
+                result,
+            }
+        });
+    }
+
+    /// Copy the message at the top of the detail pane.
+    fn yank_message(&mut self) {
+        let Some(message) = message_at_line(
+            &self.messages,
+            &self.folds,
+            &self.history,
+            self.detail_scroll,
+        ) else {
+            self.status_message = "No message to copy".to_string();
+            return;
+        };
+        let what = format!("{} message", message.role);
+        self.copy_to_clipboard(message.content.clone(), &what);
+    }
+
+    /// Render the selected conversation as markdown for `purpose`. The
+    /// branch on screen is used when the whole conversation is loaded;
+    /// otherwise it is read in the background first.
+    fn render_conversation(&mut self, purpose: MarkdownUse) {
+        let Some(conversation) = self.selected_conversation().cloned() else {
+            self.status_message = tr!("tui-no-conversation");
+            return;
+        };
+        let loaded = i64::try_from(self.messages.len()).unwrap_or(0);
+        let complete = !self.tree_messages.is_empty()
+            || (self.message_offset == 0 && loaded == self.message_total);
+        if complete && !self.loading.detail {
+            let parsed = export::to_parsed(&conversation, self.messages.clone());
+            let markdown = export::render_markdown(std::slice::from_ref(&parsed));
+            self.use_markdown(purpose, markdown);
+            return;
+        }
+
+        self.status_message = tr!("tui-loading-conversation");
+        let db = self.db.clone();
+        self.loader.spawn(async move {
+            let result = db
+                .get_messages(conversation.id)
+                .await
+                .map(|messages| {
+                    let parsed =
+                        export::to_parsed(&conversation, branches::active_thread(messages));
+                    export::render_markdown(std::slice::from_ref(&parsed))
+                })
+                .map_err(|e| format!("Error loading messages: {e}"));
+            DataEvent::Rendered { purpose, result }
+        });
+    }
+
+    /// Open the selected conversation in `viewer`.
+    fn open_conversation(&mut self, viewer: Viewer) {
+        let Some(conversation) = self.selected_conversation() else {
+            self.status_message = tr!("tui-no-conversation");
+            return;
+        };
+        let name = conversation
+            .readable_id
+            .clone()
+            .unwrap_or_else(|| conversation.id.to_string());
+        let path = std::env::temp_dir().join(format!("hstry-{name}.md"));
+        self.render_conversation(MarkdownUse::Open { viewer, path });
+    }
+
+    fn use_markdown(&mut self, purpose: MarkdownUse, markdown: String) {
+        match purpose {
+            MarkdownUse::Copy => self.copy_to_clipboard(markdown, "conversation"),
+            MarkdownUse::Open { viewer, path } => match std::fs::write(&path, markdown) {
+                Ok(()) => self.pending_open = Some((viewer, path)),
+                Err(e) => {
+                    self.status_message = format!("Failed to write {}: {e}", path.display());
                 }
+            },
+        }
+    }
+
+    fn copy_to_clipboard(&mut self, text: String, what: &str) {
+        let mut clipboard = match self.clipboard.take() {
+            Some(clipboard) => clipboard,
+            None => match arboard::Clipboard::new() {
+                Ok(clipboard) => clipboard,
                 Err(e) => {
-                    self.status_message = format!("Search error: {e}");
-                    self.search_results.clear();
-                    self.show_search_results = false;
-                    self.last_search_query = None;
+                    self.status_message = format!("Clipboard unavailable: {e}");
+                    return;
                 }
-            }
+            },
+        };
+        let chars = text.chars().count();
+        self.status_message = match clipboard.set_text(text) {
+            Ok(()) => format!("Copied {what} ({chars} characters)"),
+            Err(e) => format!("Copy failed: {e}"),
+        };
+        self.clipboard = Some(clipboard);
+    }
+
+    /// Export conversations in the background.
+    fn export_conversations(&mut self, ids: Vec<Uuid>, target: ExportTarget, path: PathBuf) {
+        let db = self.db.clone();
+        self.loader.spawn(async move {
+            DataEvent::Exported(
+                run_export(&db, ids, target, path)
+                    .await
+                    .map_err(|e| format!("Export failed: {e}")),
+            )
+        });
+    }
+
+    /// Move conversations to the trash in the background, then reload.
+    fn delete_conversations(&mut self, ids: Vec<Uuid>) {
+        let db = self.db.clone();
+        self.loading.list = true;
+        self.last_trashed.clone_from(&ids);
+        self.loader.spawn(async move {
+            let status = match db.trash_conversations(&ids).await {
+                Ok(trashed) => format!("Moved {trashed} conversation(s) to the trash (u to undo)"),
+                Err(e) => format!("Error deleting conversations: {e}"),
+            };
+            DataEvent::Written { status }
+        });
+    }
+
+    /// Restore the conversations trashed by the last delete.
+    fn undo_delete(&mut self) {
+        if self.last_trashed.is_empty() {
+            self.status_message = "Nothing to undo".to_string();
+            return;
         }
+        let ids = std::mem::take(&mut self.last_trashed);
+        let db = self.db.clone();
+        self.loading.list = true;
+        self.loader.spawn(async move {
+            let status = match db.restore_conversations(&ids).await {
+                Ok(restored) => format!("Restored {restored} conversation(s) from the trash"),
+                Err(e) => format!("Error restoring conversations: {e}"),
+            };
+            DataEvent::Written { status }
+        });
+    }
+
+    /// Remove a source and its conversations in the background, then reload.
+    fn delete_source(&mut self, source_id: String) {
+        let db = self.db.clone();
+        self.loading.list = true;
+        self.loader.spawn(async move {
+            let status = match db.remove_source(&source_id).await {
+                Ok(()) => format!("Deleted source '{source_id}'"),
+                Err(e) => format!("Error deleting source: {e}"),
+            };
+            DataEvent::Written { status }
+        });
     }
 
-    fn refresh_data(&mut self, rt: &tokio::runtime::Runtime) {
-        match rt.block_on(self.db.list_sources()) {
-            Ok(sources) => self.sources = sources,
-            Err(e) => self.status_message = format!("Error loading sources: {e}"),
+    /// Source name with its configured badge icon and color.
+    fn source_spans(&self, source_id: &str) -> Vec<Span<'static>> {
+        let adapter = self
+            .sources
+            .iter()
+            .find(|source| source.id == source_id)
+            .map(|source| source.adapter.as_str());
+        let Some(badge) = self.config.badges.lookup(source_id, adapter) else {
+            return vec![Span::raw(source_id.to_string())];
+        };
+        let style = badge.color.map_or_else(Style::default, |color| {
+            Style::default().fg(badge_color(color))
+        });
+        let mut spans = Vec::with_capacity(2);
+        if let Some(icon) = &badge.icon {
+            spans.push(Span::raw(format!("{icon} ")));
         }
+        spans.push(Span::styled(source_id.to_string(), style));
+        spans
+    }
 
-        match rt.block_on(self.db.list_conversations(ListConversationsOptions {
-            limit: None,
-            ..Default::default()
-        })) {
-            Ok(convs) => {
-                self.all_conversations = convs;
+    /// Badge count shown next to a left pane item, if it has one.
+    fn nav_count(&self, item: &NavItem) -> Option<i64> {
+        match item {
+            NavItem::All => Some(self.source_counts.values().sum()),
+            NavItem::Group { source_ids, .. } | NavItem::Source { source_ids, .. } => Some(
+                source_ids
+                    .iter()
+                    .filter_map(|id| self.source_counts.get(id))
+                    .sum(),
+            ),
+            NavItem::Workspace(ws) => self.workspace_counts.get(ws).copied(),
+            NavItem::DateYear(_)
+            | NavItem::DateMonth(_, _)
+            | NavItem::DateDay(_, _, _)
+            | NavItem::Saved(_) => None,
+        }
+    }
+
+    /// Every `CHANGE_CHECK_INTERVAL`, poll the change log in the background.
+    /// The result is handled by [`App::apply_changes`].
+    fn follow_changes(&mut self) {
+        if self.last_change_check.elapsed() < CHANGE_CHECK_INTERVAL
+            || self.loading.changes
+            || self.loading.list
+        {
+            return;
+        }
+        self.last_change_check = Instant::now();
+        self.loading.changes = true;
+        let db = self.db.clone();
+        let since = self.change_seq;
+        self.loader.spawn(async move {
+            DataEvent::Changes(
+                db.changes_since(since, CHANGE_BATCH)
+                    .await
+                    .map_err(|e| e.to_string()),
+            )
+        });
+    }
+
+    /// Reload when the change log moved, keeping the selected conversation.
+    /// While the user is searching, multi-selecting or in a dialog, only flag
+    /// the change in the status bar.
+    fn apply_changes(&mut self, changes: Vec<ChangeLogEntry>) {
+        if changes.is_empty() {
+            return;
+        }
+        if !matches!(self.mode, AppMode::Normal)
+            || self.show_search_results
+            || !self.conv_selection.selected_indices.is_empty()
+        {
+            self.status_message = "New conversations available - press r to refresh".to_string();
+            return;
+        }
+
+        let selected = self.selected_conversation_id();
+        let selected_changed = changes.len() as i64 >= CHANGE_BATCH
+            || selected.is_some_and(|id| {
+                let id = id.to_string();
+                changes
+                    .iter()
+                    .any(|change| change.entity == "conversation" && change.entity_id == id)
+            });
+        let changed: HashSet<&str> = changes
+            .iter()
+            .filter(|change| change.entity == "conversation")
+            .map(|change| change.entity_id.as_str())
+            .collect();
+        let follow_up = FollowUp {
+            selected,
+            selected_changed,
+            changed: changed.len(),
+            detail_scroll: self.detail_scroll,
+        };
+        self.reload(Reload::Follow(follow_up));
+    }
+
+    /// Apply filter panel values and reload the conversation list.
+    fn set_panel_filters(&mut self, filters: PanelFilters, options: ListConversationsOptions) {
+        self.panel_filters = filters;
+        self.list_options = options;
+        self.reload(Reload::Filters);
+    }
+
+    fn refresh_data(&mut self) {
+        self.reload(Reload::Refresh {
+            status: tr!("tui-data-refreshed"),
+        });
+    }
+
+    /// Reload sources, conversations and counts in the background.
+    fn reload(&mut self, reload: Reload) {
+        self.data_generation += 1;
+        let generation = self.data_generation;
+        self.loading.list = true;
+        self.load_counts();
+        let db = self.db.clone();
+        let options = self.list_options.clone();
+        self.loader.spawn(async move {
+            let loaded = async {
+                // The change log position is read first so writes that land
+                // while loading are picked up by the next poll.
+                let change_seq = db.latest_change_seq().await.ok();
+                let sources = db.list_sources().await?;
+                let conversations = db
+                    .list_conversations(ListConversationsOptions {
+                        limit: None,
+                        ..options
+                    })
+                    .await?;
+                hstry_core::Result::Ok(LoadedData {
+                    change_seq,
+                    sources,
+                    conversations,
+                })
+            };
+            DataEvent::Data {
+                generation,
+                reload,
+                result: loaded.await.map_err(|e| e.to_string()),
+            }
+        });
+    }
+
+    /// Apply every finished background load.
+    fn poll_loads(&mut self) {
+        while let Some(event) = self.loader.try_recv() {
+            self.apply_load(event);
+        }
+    }
+
+    fn apply_load(&mut self, event: DataEvent) {
+        match event {
+            DataEvent::Messages {
+                generation,
+                scroll,
+                result,
+            } => {
+                if generation != self.message_generation {
+                    return;
+                }
+                self.loading.detail = false;
+                match result {
+                    Ok(LoadedMessages::Tree(messages)) => {
+                        self.branches = branches::branches(&messages);
+                        self.tree_messages = messages;
+                        self.show_branch(0);
+                    }
+                    Ok(LoadedMessages::Window {
+                        messages,
+                        offset,
+                        total,
+                    }) => {
+                        self.tree_messages.clear();
+                        self.branches.clear();
+                        self.branch_index = 0;
+                        self.set_message_window(messages, offset, total);
+                    }
+                    Err(e) => {
+                        self.status_message = e;
+                        return;
+                    }
+                }
+                self.detail_scroll = match scroll {
+                    ScrollTo::FirstMatch => self.first_match_scroll().unwrap_or(0),
+                    ScrollTo::Top => 0,
+                    ScrollTo::Bottom => self.message_line_count.saturating_sub(1),
+                };
+            }
+            DataEvent::Data {
+                generation,
+                reload,
+                result,
+            } => {
+                if generation != self.data_generation {
+                    return;
+                }
+                self.loading.list = false;
+                let data = match result {
+                    Ok(data) => data,
+                    Err(e) => {
+                        self.status_message = format!("Error loading conversations: {e}");
+                        return;
+                    }
+                };
+                if let Some(seq) = data.change_seq {
+                    self.change_seq = seq;
+                }
+                self.sources = data.sources;
+                self.rebuild_nav_items();
+                self.all_conversations = data.conversations;
                 self.apply_filters();
                 self.show_search_results = false;
                 self.search_results.clear();
                 self.last_search_query = None;
-                self.status_message = "Data refreshed".to_string();
+                match reload {
+                    Reload::Startup => {
+                        if !self.filtered_conversations.is_empty() {
+                            self.load_messages();
+                        }
+                    }
+                    Reload::Refresh { status } => self.status_message = status,
+                    Reload::Filters => {
+                        self.status_message = if self.panel_filters.is_active() {
+                            format!(
+                                "{} conversations match the filters",
+                                self.filtered_conversations.len()
+                            )
+                        } else {
+                            "Filters cleared".to_string()
+                        };
+                        if !self.filtered_conversations.is_empty() {
+                            self.load_messages();
+                        }
+                    }
+                    Reload::Follow(follow_up) => self.finish_follow(follow_up),
+                }
+            }
+            DataEvent::Counts(result) => {
+                self.loading.nav = false;
+                match result {
+                    Ok(counts) => {
+                        self.source_counts = counts.by_source.into_iter().collect();
+                        self.workspace_counts = counts.by_workspace.into_iter().collect();
+                        self.workspace_names = counts.workspace_names;
+                        self.new_ids = counts.new_ids.into_iter().collect();
+                        self.starred_ids = counts.starred_ids;
+                    }
+                    Err(e) => self.status_message = format!("Error loading counts: {e}"),
+                }
+            }
+            DataEvent::Stats(result) => {
+                self.loading.stats = false;
+                match result {
+                    Ok(stats) => self.stats = Some(stats),
+                    Err(e) => self.status_message = format!("Error loading statistics: {e}"),
+                }
+            }
+            DataEvent::Changes(result) => {
+                self.loading.changes = false;
+                if let Ok(changes) = result {
+                    self.apply_changes(changes);
+                }
+            }
+            DataEvent::Written { status } => {
+                self.reload(Reload::Refresh { status });
             }
-            Err(e) => self.status_message = format!("Error loading conversations: {e}"),
+            DataEvent::Exported(result) => {
+                self.status_message = result.unwrap_or_else(|e| e);
+            }
+            DataEvent::Rendered { purpose, result } => match result {
+                Ok(markdown) => self.use_markdown(purpose, markdown),
+                Err(e) => self.status_message = e,
+            },
+            DataEvent::SavedSearches(result) => {
+                self.loading.nav = false;
+                match result {
+                    Ok(searches) => {
+                        self.saved_searches = searches;
+                        if self.left_pane_view == LeftPaneView::Saved {
+                            self.rebuild_nav_items();
+                            if self.saved_searches.is_empty() {
+                                self.status_message = "No saved searches; save one with `hstry search <query> --save <name>`".to_string();
+                            }
+                        }
+                    }
+                    Err(e) => self.status_message = format!("Error loading saved searches: {e}"),
+                }
+            }
+            DataEvent::MarkedRead(result) => match result {
+                Ok(()) => {
+                    self.last_seen = Utc::now();
+                    self.new_ids.clear();
+                    self.status_message = "Marked all conversations as read".to_string();
+                }
+                Err(e) => self.status_message = format!("Error marking read: {e}"),
+            },
+            DataEvent::Labelled {
+                message_id,
+                label,
+                result,
+            } => match result {
+                Ok(labels) => {
+                    let set = labels.contains(&label);
+                    for message in self
+                        .messages
+                        .iter_mut()
+                        .chain(self.tree_messages.iter_mut())
+                        .filter(|m| m.id == message_id)
+                    {
+                        set_message_labels(message, &labels);
+                    }
+                    self.message_line_count = self.layout().lines.len();
+                    let shown = labels::display(&label);
+                    self.status_message = if set {
+                        format!("Labelled message {shown}")
+                    } else {
+                        format!("Removed {shown}")
+                    };
+                }
+                Err(e) => self.status_message = format!("Error labelling message: {e}"),
+            },
+            DataEvent::Starred {
+                conversation_id,
+                starred,
+                result,
+            } => match result {
+                Ok(()) => {
+                    if starred {
+                        self.starred_ids.insert(conversation_id);
+                        self.status_message = "Starred conversation".to_string();
+                    } else {
+                        self.starred_ids.remove(&conversation_id);
+                        self.status_message = "Unstarred conversation".to_string();
+                    }
+                }
+                Err(e) => self.status_message = format!("Error starring conversation: {e}"),
+            },
+            DataEvent::Related {
+                conversation_id,
+                result,
+            } => match result {
+                Ok(mut sources) => {
+                    sources.sort();
+                    sources.dedup();
+                    self.related_sources = Some((conversation_id, sources));
+                }
+                Err(e) => self.status_message = format!("Error loading linked copies: {e}"),
+            },
+            DataEvent::Revisions {
+                conversation_id,
+                result,
+            } => match result {
+                Ok(revisions) => {
+                    let current = self.current_message_index();
+                    let shown = !revisions.is_empty();
+                    self.history.conversation_id = Some(conversation_id);
+                    self.history.revisions.clear();
+                    for revision in revisions {
+                        self.history
+                            .revisions
+                            .entry(revision.idx)
+                            .or_default()
+                            .push(revision);
+                    }
+                    if shown {
+                        self.relayout(current);
+                    }
+                }
+                Err(e) => self.status_message = format!("Error loading message history: {e}"),
+            },
         }
     }
+
+    /// Restore the selection after a reload caused by the change log.
+    fn finish_follow(&mut self, follow_up: FollowUp) {
+        if let Some(index) = follow_up.selected.and_then(|id| {
+            self.filtered_conversations
+                .iter()
+                .position(|conv| conv.id == id)
+        }) {
+            self.conv_selection.index = index;
+        }
+        if follow_up.selected_changed || self.selected_conversation_id() != follow_up.selected {
+            self.load_messages();
+        } else {
+            self.detail_scroll = follow_up.detail_scroll;
+        }
+        self.status_message = match follow_up.changed {
+            0 => "Sources updated".to_string(),
+            1 => "1 conversation updated".to_string(),
+            n => format!("{n} conversations updated"),
+        };
+    }
 }
 
 // =============================================================================
@@ -1392,25 +3207,45 @@ fn parse_key(key: &event::KeyEvent) -> KeyAction {
 // Event Loop
 // =============================================================================
 
-fn run_app<B: ratatui::backend::Backend>(
-    terminal: &mut Terminal<B>,
-    app: &mut App,
-    rt: &tokio::runtime::Runtime,
-) -> Result<()>
+fn run_app<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<()>
 where
     B::Error: Send + Sync + 'static,
 {
-    // Load initial messages if we have conversations
-    if !app.filtered_conversations.is_empty() {
-        app.load_messages(rt);
-    }
+    app.reload(Reload::Startup);
 
     loop {
+        app.follow_changes();
+        app.poll_search();
+        app.poll_loads();
+        if let Some((viewer, path)) = app.pending_open.take() {
+            let command = viewer.command();
+            app.status_message = match open_external(terminal, &command, &path) {
+                Ok(status) if status.success() => format!("Closed {}", path.display()),
+                Ok(status) => format!("{command} exited with {status}"),
+                Err(e) => format!("Failed to run {command}: {e}"),
+            };
+        }
         terminal.draw(|f| ui(f, app))?;
 
-        if event::poll(Duration::from_millis(100))?
-            && let Event::Key(key) = event::read()?
-        {
+        // Wake up sooner while a search or load is running so its results
+        // show as soon as they arrive.
+        let poll_timeout = if app.search_worker.is_busy() || app.loading.any() {
+            Duration::from_millis(20)
+        } else {
+            Duration::from_millis(100)
+        };
+        if !event::poll(poll_timeout)? {
+            continue;
+        }
+        let event = event::read()?;
+        if let Event::Mouse(mouse) = event {
+            if matches!(app.mode, AppMode::Normal) {
+                let size = terminal.size()?;
+                handle_mouse(app, mouse, Rect::new(0, 0, size.width, size.height));
+            }
+            continue;
+        }
+        if let Event::Key(key) = event {
             if key.kind != KeyEventKind::Press {
                 continue;
             }
@@ -1424,12 +3259,12 @@ where
 
             match &app.mode {
                 AppMode::Normal => {
-                    if handle_normal_mode(app, action, rt) {
+                    if handle_normal_mode(app, action) {
                         return Ok(());
                     }
                 }
                 AppMode::Search { .. } => {
-                    handle_search_mode(app, action, rt);
+                    handle_search_mode(app, action);
                 }
                 AppMode::Help { .. } => {
                     handle_help_mode(app, action);
@@ -1438,17 +3273,29 @@ where
                     handle_sort_mode(app, action);
                 }
                 AppMode::Delete { .. } => {
-                    handle_delete_mode(app, action, rt);
+                    handle_delete_mode(app, action);
                 }
                 AppMode::DeleteSource { .. } => {
-                    handle_delete_source_mode(app, action, rt);
+                    handle_delete_source_mode(app, action);
+                }
+                AppMode::Export { .. } => {
+                    handle_export_mode(app, action);
+                }
+                AppMode::Filter { .. } => {
+                    handle_filter_mode(app, action);
+                }
+                AppMode::Stats => {
+                    handle_stats_mode(app, action);
+                }
+                AppMode::Label { .. } => {
+                    handle_label_mode(app, action);
                 }
             }
         }
     }
 }
 
-fn handle_normal_mode(app: &mut App, action: KeyAction, rt: &tokio::runtime::Runtime) -> bool {
+fn handle_normal_mode(app: &mut App, action: KeyAction) -> bool {
     // Reset g_prefix on any non-g key
     let was_g_prefix = app.g_prefix;
     if !matches!(action, KeyAction::Char('g')) {
@@ -1467,6 +3314,7 @@ fn handle_normal_mode(app: &mut App, action: KeyAction, rt: &tokio::runtime::Run
             };
             app.search_results.clear();
             app.show_search_results = false;
+            app.last_search_query = None;
         }
         KeyAction::Char('x')
             if app.show_search_results => {
@@ -1474,8 +3322,21 @@ fn handle_normal_mode(app: &mut App, action: KeyAction, rt: &tokio::runtime::Run
                 app.search_results.clear();
                 app.last_search_query = None;
                 app.conv_selection.index = 0;
-                app.status_message = "Cleared search results".to_string();
+                app.status_message = tr!("tui-search-cleared");
             }
+        KeyAction::Char('f') => {
+            app.mode = AppMode::Filter {
+                form: app.panel_filters.clone(),
+                field: 0,
+            };
+        }
+        KeyAction::Char('F') if app.panel_filters.is_active() => {
+            app.set_panel_filters(PanelFilters::default(), ListConversationsOptions::default());
+        }
+        KeyAction::Char('S') => {
+            app.mode = AppMode::Stats;
+            app.load_stats();
+        }
         KeyAction::Char('s') => {
             app.mode = AppMode::Sort;
             app.sort_selection = SortOrder::all()
@@ -1489,6 +3350,7 @@ fn handle_normal_mode(app: &mut App, action: KeyAction, rt: &tokio::runtime::Run
                 if let Some(NavItem::Source {
                     adapter,
                     source_ids,
+                    ..
                 }) = app.nav_items.get(app.nav_selection.index)
                 {
                     if source_ids.len() == 1 {
@@ -1514,14 +3376,77 @@ fn handle_normal_mode(app: &mut App, action: KeyAction, rt: &tokio::runtime::Run
                 }
             }
         }
+        KeyAction::Char('y') => {
+            app.yank_message();
+        }
+        KeyAction::Char('Y') => {
+            app.render_conversation(MarkdownUse::Copy);
+        }
+        KeyAction::Char('m') => {
+            app.start_labelling();
+        }
+        KeyAction::Char('*') => {
+            app.toggle_star();
+        }
+        KeyAction::Char('u') => {
+            app.undo_delete();
+        }
+        KeyAction::Char('o') => {
+            app.open_conversation(Viewer::Pager);
+        }
+        KeyAction::Char('O') => {
+            app.open_conversation(Viewer::Editor);
+        }
+        KeyAction::Char('e') => {
+            let ids = app.selected_conversation_ids();
+            if ids.is_empty() {
+                app.status_message = "Nothing to export".to_string();
+            } else {
+                let target = ExportTarget::Markdown;
+                let path = target.default_path().to_string();
+                app.mode = AppMode::Export {
+                    ids,
+                    target,
+                    cursor: path.len(),
+                    path,
+                };
+            }
+        }
         KeyAction::Char('r') => {
-            app.refresh_data(rt);
+            app.refresh_data();
+        }
+        KeyAction::Char('R') => {
+            app.mark_all_read();
+        }
+        KeyAction::Char(']') => {
+            app.cycle_branch(true);
+        }
+        KeyAction::Char('n') => {
+            app.jump_message(true);
+        }
+        KeyAction::Char('p') => {
+            app.jump_message(false);
+        }
+        KeyAction::Char('z') => {
+            app.toggle_fold();
+        }
+        KeyAction::Char('Z') => {
+            app.toggle_fold_all();
+        }
+        KeyAction::Char('T') => {
+            app.toggle_tool_folding();
+        }
+        KeyAction::Char('H') => {
+            app.toggle_history();
+        }
+        KeyAction::Char('[') => {
+            app.cycle_branch(false);
         }
         KeyAction::Char('j') | KeyAction::Down => {
-            handle_navigation(app, NavDirection::Down, rt);
+            handle_navigation(app, NavDirection::Down);
         }
         KeyAction::Char('k') | KeyAction::Up => {
-            handle_navigation(app, NavDirection::Up, rt);
+            handle_navigation(app, NavDirection::Up);
         }
         KeyAction::Char('h') | KeyAction::Left => match app.focus {
             FocusPane::Middle => app.focus = FocusPane::Left,
@@ -1535,23 +3460,23 @@ fn handle_normal_mode(app: &mut App, action: KeyAction, rt: &tokio::runtime::Run
         },
         KeyAction::Char('g') => {
             if was_g_prefix {
-                handle_navigation(app, NavDirection::Top, rt);
+                handle_navigation(app, NavDirection::Top);
                 app.g_prefix = false;
             } else {
                 app.g_prefix = true;
             }
         }
         KeyAction::Char('G') | KeyAction::End => {
-            handle_navigation(app, NavDirection::Bottom, rt);
+            handle_navigation(app, NavDirection::Bottom);
         }
         KeyAction::Home => {
-            handle_navigation(app, NavDirection::Top, rt);
+            handle_navigation(app, NavDirection::Top);
         }
         KeyAction::PageDown => {
-            handle_navigation(app, NavDirection::PageDown, rt);
+            handle_navigation(app, NavDirection::PageDown);
         }
         KeyAction::PageUp => {
-            handle_navigation(app, NavDirection::PageUp, rt);
+            handle_navigation(app, NavDirection::PageUp);
         }
         KeyAction::ToggleSelect
             if app.focus == FocusPane::Middle => {
@@ -1578,18 +3503,28 @@ fn handle_normal_mode(app: &mut App, action: KeyAction, rt: &tokio::runtime::Run
                         NavItem::All => {
                             app.filter.source = None;
                             app.filter.source_adapter = None;
+                            app.filter.group = None;
+                            app.filter.workspace = None;
+                            app.filter.date_range = None;
+                        }
+                        NavItem::Group { name, .. } => {
+                            app.filter.source = None;
+                            app.filter.source_adapter = None;
+                            app.filter.group = Some(name.clone());
                             app.filter.workspace = None;
                             app.filter.date_range = None;
                         }
-                        NavItem::Source { adapter, .. } => {
+                        NavItem::Source { adapter, group, .. } => {
                             app.filter.source = None;
                             app.filter.source_adapter = Some(adapter.clone());
+                            app.filter.group.clone_from(group);
                             app.filter.workspace = None;
                             app.filter.date_range = None;
                         }
                         NavItem::Workspace(ws) => {
                             app.filter.source = None;
                             app.filter.source_adapter = None;
+                            app.filter.group = None;
                             app.filter.workspace = Some(ws.clone());
                             app.filter.date_range = None;
                         }
@@ -1603,6 +3538,12 @@ fn handle_normal_mode(app: &mut App, action: KeyAction, rt: &tokio::runtime::Run
                             app.rebuild_nav_items();
                             return false;
                         }
+                        NavItem::Saved(name) => {
+                            let name = name.clone();
+                            app.run_saved_search(&name);
+                            app.focus = FocusPane::Middle;
+                            return false;
+                        }
                         NavItem::DateDay(year, month, day) => {
                             // Filter to specific day
                             let start = NaiveDate::from_ymd_opt(*year, *month, *day)
@@ -1614,18 +3555,19 @@ fn handle_normal_mode(app: &mut App, action: KeyAction, rt: &tokio::runtime::Run
                             app.filter.date_range = start.zip(end);
                             app.filter.source = None;
                             app.filter.source_adapter = None;
+                            app.filter.group = None;
                             app.filter.workspace = None;
                         }
                     }
                     app.apply_filters();
                     app.focus = FocusPane::Middle;
                     if !app.filtered_conversations.is_empty() {
-                        app.load_messages(rt);
+                        app.load_messages();
                     }
                 }
             } else if app.focus == FocusPane::Middle {
                 // Load messages for selected conversation
-                app.load_messages(rt);
+                app.load_messages();
                 app.focus = FocusPane::Right;
             }
         }
@@ -1634,7 +3576,98 @@ fn handle_normal_mode(app: &mut App, action: KeyAction, rt: &tokio::runtime::Run
     false
 }
 
-#[derive(Debug, Clone, Copy)]
+/// Two clicks on the same cell within this interval open the entry.
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+/// Lines the right pane moves per scroll wheel step.
+const WHEEL_LINES: usize = 3;
+
+/// Rows each entry takes in the left and middle pane lists.
+const NAV_ITEM_HEIGHT: u16 = 1;
+const CONVERSATION_ITEM_HEIGHT: u16 = 2;
+
+/// Index of the list entry drawn at `row` of a list in `inner`. The panes
+/// render their lists from offset 0, so the list scrolls just far enough
+/// to keep `selected` on screen.
+fn list_entry_at(inner: Rect, row: u16, item_height: u16, selected: usize) -> Option<usize> {
+    if row < inner.y || row >= inner.bottom() {
+        return None;
+    }
+    let visible = usize::from((inner.height / item_height).max(1));
+    let first = (selected + 1).saturating_sub(visible);
+    Some(first + usize::from((row - inner.y) / item_height))
+}
+
+fn handle_mouse(app: &mut App, mouse: MouseEvent, area: Rect) {
+    let (panes, _) = screen_layout(area);
+    let position = ratatui::layout::Position::new(mouse.column, mouse.row);
+    let Some(pane) = panes.iter().position(|pane| pane.contains(position)) else {
+        return;
+    };
+    let pane_focus = [FocusPane::Left, FocusPane::Middle, FocusPane::Right][pane];
+    let inner = Block::default().borders(Borders::ALL).inner(panes[pane]);
+
+    match mouse.kind {
+        MouseEventKind::ScrollUp | MouseEventKind::ScrollDown => {
+            // Scroll the pane under the pointer without moving focus.
+            let direction = if mouse.kind == MouseEventKind::ScrollUp {
+                NavDirection::Up
+            } else {
+                NavDirection::Down
+            };
+            let focus = std::mem::replace(&mut app.focus, pane_focus);
+            let steps = if pane_focus == FocusPane::Right {
+                WHEEL_LINES
+            } else {
+                1
+            };
+            for _ in 0..steps {
+                handle_navigation(app, direction);
+            }
+            app.focus = focus;
+        }
+        MouseEventKind::Down(MouseButton::Left) => {
+            app.g_prefix = false;
+            app.focus = pane_focus;
+            let double = app.last_click.is_some_and(|(at, column, row)| {
+                at.elapsed() < DOUBLE_CLICK && column == mouse.column && row == mouse.row
+            });
+            app.last_click = (!double).then(|| (Instant::now(), mouse.column, mouse.row));
+
+            let entry = match pane_focus {
+                FocusPane::Left => {
+                    list_entry_at(inner, mouse.row, NAV_ITEM_HEIGHT, app.nav_selection.index)
+                        .filter(|&index| index < app.nav_items.len())
+                }
+                FocusPane::Middle => list_entry_at(
+                    inner,
+                    mouse.row,
+                    CONVERSATION_ITEM_HEIGHT,
+                    app.conv_selection.index,
+                )
+                .filter(|&index| index < app.active_list_len()),
+                FocusPane::Right => None,
+            };
+            let Some(index) = entry else {
+                return;
+            };
+            match pane_focus {
+                FocusPane::Left => app.nav_selection.index = index,
+                FocusPane::Middle if index != app.conv_selection.index => {
+                    app.conv_selection.index = index;
+                    app.load_messages();
+                }
+                _ => {}
+            }
+            if double {
+                handle_normal_mode(app, KeyAction::Select);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum NavDirection {
     Up,
     Down,
@@ -1644,7 +3677,7 @@ enum NavDirection {
     PageDown,
 }
 
-fn handle_navigation(app: &mut App, direction: NavDirection, rt: &tokio::runtime::Runtime) {
+fn handle_navigation(app: &mut App, direction: NavDirection) {
     let page_size = 20; // Approximate visible items
 
     match app.focus {
@@ -1675,33 +3708,51 @@ fn handle_navigation(app: &mut App, direction: NavDirection, rt: &tokio::runtime
             }
             // Load messages if selection changed
             if app.conv_selection.index != prev_index {
-                app.load_messages(rt);
+                app.load_messages();
             }
         }
         FocusPane::Right => match direction {
-            NavDirection::Up => {
-                app.detail_scroll = app.detail_scroll.saturating_sub(1);
+            NavDirection::Up | NavDirection::PageUp => {
+                if app.detail_scroll == 0 {
+                    app.shift_message_window(false);
+                } else {
+                    let step = if direction == NavDirection::Up {
+                        1
+                    } else {
+                        page_size
+                    };
+                    app.detail_scroll = app.detail_scroll.saturating_sub(step);
+                }
             }
-            NavDirection::Down => {
-                app.detail_scroll += 1;
+            NavDirection::Down | NavDirection::PageDown => {
+                let step = if direction == NavDirection::Down {
+                    1
+                } else {
+                    page_size
+                };
+                app.detail_scroll += step;
+                if app.detail_scroll >= app.message_line_count {
+                    app.shift_message_window(true);
+                }
             }
             NavDirection::Top => {
+                if app.message_offset > 0 {
+                    app.load_message_window(0, ScrollTo::Top);
+                }
                 app.detail_scroll = 0;
             }
             NavDirection::Bottom => {
-                app.detail_scroll = app.messages.len().saturating_sub(1);
-            }
-            NavDirection::PageUp => {
-                app.detail_scroll = app.detail_scroll.saturating_sub(page_size);
-            }
-            NavDirection::PageDown => {
-                app.detail_scroll += page_size;
+                let last = (app.message_total - 1).max(0) / MESSAGE_WINDOW * MESSAGE_WINDOW;
+                if last != app.message_offset {
+                    app.load_message_window(last, ScrollTo::Bottom);
+                }
+                app.detail_scroll = app.message_line_count.saturating_sub(1);
             }
         },
     }
 }
 
-fn handle_search_mode(app: &mut App, action: KeyAction, rt: &tokio::runtime::Runtime) {
+fn handle_search_mode(app: &mut App, action: KeyAction) {
     if let AppMode::Search {
         ref mut query,
         ref mut cursor,
@@ -1712,14 +3763,16 @@ fn handle_search_mode(app: &mut App, action: KeyAction, rt: &tokio::runtime::Run
                 app.mode = AppMode::Normal;
             }
             KeyAction::Select => {
-                app.perform_search(rt);
+                app.submit_search(true);
             }
             KeyAction::Backspace if *cursor > 0 => {
                 query.remove(*cursor - 1);
                 *cursor -= 1;
+                app.submit_search(false);
             }
             KeyAction::Delete if *cursor < query.len() => {
                 query.remove(*cursor);
+                app.submit_search(false);
             }
             KeyAction::Left => {
                 *cursor = cursor.saturating_sub(1);
@@ -1736,15 +3789,18 @@ fn handle_search_mode(app: &mut App, action: KeyAction, rt: &tokio::runtime::Run
             KeyAction::Char(c) => {
                 query.insert(*cursor, c);
                 *cursor += 1;
+                app.submit_search(false);
             }
             KeyAction::ToggleSelect => {
                 // Space key - insert space character in search mode
                 query.insert(*cursor, ' ');
                 *cursor += 1;
+                app.submit_search(false);
             }
             KeyAction::Tab => {
                 app.search_scope = app.search_scope.next();
                 app.status_message = format!("Search scope: {}", app.search_scope.label());
+                app.submit_search(true);
             }
             KeyAction::Down => {
                 // Navigate search results
@@ -1788,6 +3844,16 @@ fn handle_help_mode(app: &mut App, action: KeyAction) {
     }
 }
 
+fn handle_stats_mode(app: &mut App, action: KeyAction) {
+    match action {
+        KeyAction::Escape | KeyAction::Char('q' | 'S') => {
+            app.mode = AppMode::Normal;
+        }
+        KeyAction::Char('r') => app.load_stats(),
+        _ => {}
+    }
+}
+
 fn handle_sort_mode(app: &mut App, action: KeyAction) {
     match action {
         KeyAction::Escape => {
@@ -1805,77 +3871,185 @@ fn handle_sort_mode(app: &mut App, action: KeyAction) {
             app.sort_order = SortOrder::all()[app.sort_selection];
             app.apply_sort();
             app.mode = AppMode::Normal;
-            app.status_message = format!("Sorted by: {}", app.sort_order.label());
+            app.status_message = tr!("tui-sorted-by", order = app.sort_order.label());
+        }
+        KeyAction::Char(c) if c.is_ascii_digit() => {
+            if let Some(idx) = c.to_digit(10) {
+                let idx = idx as usize;
+                if idx > 0 && idx <= SortOrder::all().len() {
+                    app.sort_order = SortOrder::all()[idx - 1];
+                    app.apply_sort();
+                    app.mode = AppMode::Normal;
+                    app.status_message = tr!("tui-sorted-by", order = app.sort_order.label());
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn handle_export_mode(app: &mut App, action: KeyAction) {
+    let AppMode::Export {
+        ref ids,
+        ref mut target,
+        ref mut path,
+        ref mut cursor,
+    } = app.mode
+    else {
+        return;
+    };
+    match action {
+        KeyAction::Escape => {
+            app.mode = AppMode::Normal;
+        }
+        KeyAction::Tab => {
+            // Follow the format until the user edits the path.
+            let untouched = *path == target.default_path();
+            *target = target.next();
+            if untouched {
+                *path = target.default_path().to_string();
+                *cursor = path.len();
+            }
+        }
+        KeyAction::Select if !path.trim().is_empty() => {
+            let ids = ids.clone();
+            let target = *target;
+            let path = Config::expand_path(path.trim());
+            app.status_message = format!("Exporting {} conversations...", ids.len());
+            app.mode = AppMode::Normal;
+            app.conv_selection.deselect_all();
+            app.export_conversations(ids, target, path);
+        }
+        KeyAction::Backspace if *cursor > 0 => {
+            path.remove(*cursor - 1);
+            *cursor -= 1;
+        }
+        KeyAction::Delete if *cursor < path.len() => {
+            path.remove(*cursor);
+        }
+        KeyAction::Left => {
+            *cursor = cursor.saturating_sub(1);
+        }
+        KeyAction::Right => {
+            *cursor = (*cursor + 1).min(path.len());
+        }
+        KeyAction::Home => {
+            *cursor = 0;
+        }
+        KeyAction::End => {
+            *cursor = path.len();
+        }
+        KeyAction::Char(c) => {
+            path.insert(*cursor, c);
+            *cursor += 1;
+        }
+        KeyAction::ToggleSelect => {
+            path.insert(*cursor, ' ');
+            *cursor += 1;
+        }
+        _ => {}
+    }
+}
+
+fn handle_filter_mode(app: &mut App, action: KeyAction) {
+    let AppMode::Filter {
+        ref mut form,
+        ref mut field,
+    } = app.mode
+    else {
+        return;
+    };
+    let fields = FILTER_FIELDS.len();
+    match action {
+        KeyAction::Escape => {
+            app.mode = AppMode::Normal;
+        }
+        KeyAction::Tab | KeyAction::Down => {
+            *field = (*field + 1) % fields;
+        }
+        KeyAction::Up => {
+            *field = (*field + fields - 1) % fields;
+        }
+        KeyAction::Select => match form.options() {
+            Ok(options) => {
+                let form = form.clone();
+                app.mode = AppMode::Normal;
+                app.set_panel_filters(form, options);
+            }
+            Err(e) => app.status_message = e,
+        },
+        KeyAction::Left | KeyAction::Right | KeyAction::ToggleSelect if *field == 0 => {
+            form.cycle_role(!matches!(action, KeyAction::Left));
+        }
+        KeyAction::Delete => {
+            if let Some(text) = form.text_mut(*field) {
+                text.clear();
+            } else {
+                form.role = None;
+            }
+        }
+        KeyAction::Backspace => {
+            if let Some(text) = form.text_mut(*field) {
+                text.pop();
+            }
+        }
+        KeyAction::Char(c) => {
+            if let Some(text) = form.text_mut(*field) {
+                text.push(c);
+            }
+        }
+        KeyAction::ToggleSelect => {
+            if let Some(text) = form.text_mut(*field) {
+                text.push(' ');
+            }
+        }
+        _ => {}
+    }
+}
+
+fn handle_label_mode(app: &mut App, action: KeyAction) {
+    let AppMode::Label { message_id } = app.mode else {
+        return;
+    };
+    match action {
+        KeyAction::Escape | KeyAction::Char('q') => {
+            app.mode = AppMode::Normal;
         }
-        KeyAction::Char(c) if c.is_ascii_digit() => {
-            if let Some(idx) = c.to_digit(10) {
-                let idx = idx as usize;
-                if idx > 0 && idx <= SortOrder::all().len() {
-                    app.sort_order = SortOrder::all()[idx - 1];
-                    app.apply_sort();
-                    app.mode = AppMode::Normal;
-                    app.status_message = format!("Sorted by: {}", app.sort_order.label());
-                }
+        KeyAction::Char(c) => {
+            let digit = c.to_digit(10).and_then(|d| usize::try_from(d).ok());
+            let picked = QUICK_LABELS
+                .iter()
+                .enumerate()
+                .find(|(i, quick)| quick.key == c || digit == Some(i + 1));
+            if let Some((_, quick)) = picked {
+                app.mode = AppMode::Normal;
+                app.toggle_label(message_id, quick.name);
             }
         }
         _ => {}
     }
 }
 
-fn handle_delete_mode(app: &mut App, action: KeyAction, rt: &tokio::runtime::Runtime) {
+fn handle_delete_mode(app: &mut App, action: KeyAction) {
     match action {
         KeyAction::Escape | KeyAction::Char('n') => {
             app.mode = AppMode::Normal;
         }
         KeyAction::Char('y') => {
-            // Get conversations to delete
-            let to_delete: Vec<uuid::Uuid> = if app.conv_selection.has_selections() {
-                app.conv_selection
-                    .selected_indices
-                    .iter()
-                    .filter_map(|&idx| {
-                        if app.show_search_results {
-                            app.search_results.get(idx).map(|h| h.conversation_id)
-                        } else {
-                            app.filtered_conversations.get(idx).map(|c| c.id)
-                        }
-                    })
-                    .collect()
-            } else {
-                // Delete current selection
-                let idx = app.conv_selection.index;
-                if app.show_search_results {
-                    app.search_results
-                        .get(idx)
-                        .map(|h| vec![h.conversation_id])
-                        .unwrap_or_default()
-                } else {
-                    app.filtered_conversations
-                        .get(idx)
-                        .map(|c| vec![c.id])
-                        .unwrap_or_default()
-                }
-            };
-
-            let count = to_delete.len();
-            let mut deleted = 0;
-            for id in to_delete {
-                if rt.block_on(app.db.delete_conversation(id)).is_ok() {
-                    deleted += 1;
-                }
-            }
+            let to_delete = app.selected_conversation_ids();
 
-            app.status_message = format!("Deleted {deleted}/{count} conversations");
+            app.status_message =
+                format!("Moving {} conversations to the trash...", to_delete.len());
             app.mode = AppMode::Normal;
             app.conv_selection.deselect_all();
             app.conv_selection.index = 0;
-            app.refresh_data(rt);
+            app.delete_conversations(to_delete);
         }
         _ => {}
     }
 }
 
-fn handle_delete_source_mode(app: &mut App, action: KeyAction, rt: &tokio::runtime::Runtime) {
+fn handle_delete_source_mode(app: &mut App, action: KeyAction) {
     let source_id = if let AppMode::DeleteSource { source_id, .. } = &app.mode {
         source_id.clone()
     } else {
@@ -1887,19 +4061,13 @@ fn handle_delete_source_mode(app: &mut App, action: KeyAction, rt: &tokio::runti
             app.mode = AppMode::Normal;
         }
         KeyAction::Char('y') => {
-            match rt.block_on(app.db.remove_source(&source_id)) {
-                Ok(()) => {
-                    app.status_message = format!("Deleted source '{source_id}'");
-                    app.nav_selection.index = 0;
-                    app.filter.source = None;
-                    app.filter.source_adapter = None;
-                }
-                Err(e) => {
-                    app.status_message = format!("Error deleting source: {e}");
-                }
-            }
+            app.status_message = format!("Deleting source '{source_id}'...");
+            app.nav_selection.index = 0;
+            app.filter.source = None;
+            app.filter.source_adapter = None;
+            app.filter.group = None;
             app.mode = AppMode::Normal;
-            app.refresh_data(rt);
+            app.delete_source(source_id);
         }
         _ => {}
     }
@@ -1909,11 +4077,12 @@ fn handle_delete_source_mode(app: &mut App, action: KeyAction, rt: &tokio::runti
 // UI Rendering
 // =============================================================================
 
-fn ui(f: &mut Frame, app: &App) {
+/// Areas of the left, middle and right panes and of the status bar.
+fn screen_layout(area: Rect) -> ([Rect; 3], Rect) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([Constraint::Min(0), Constraint::Length(1)])
-        .split(f.area());
+        .split(area);
 
     let main_chunks = Layout::default()
         .direction(Direction::Horizontal)
@@ -1924,10 +4093,16 @@ fn ui(f: &mut Frame, app: &App) {
         ])
         .split(chunks[0]);
 
-    draw_left_pane(f, app, main_chunks[0]);
-    draw_middle_pane(f, app, main_chunks[1]);
-    draw_right_pane(f, app, main_chunks[2]);
-    draw_status_bar(f, app, chunks[1]);
+    ([main_chunks[0], main_chunks[1], main_chunks[2]], chunks[1])
+}
+
+fn ui(f: &mut Frame, app: &App) {
+    let ([left, middle, right], status) = screen_layout(f.area());
+
+    draw_left_pane(f, app, left);
+    draw_middle_pane(f, app, middle);
+    draw_right_pane(f, app, right);
+    draw_status_bar(f, app, status);
 
     // Draw modal overlays
     match &app.mode {
@@ -1938,10 +4113,27 @@ fn ui(f: &mut Frame, app: &App) {
         }
         AppMode::Delete { count } => draw_delete_overlay(f, *count),
         AppMode::DeleteSource { source_name, .. } => draw_delete_source_overlay(f, source_name),
+        AppMode::Export {
+            ids,
+            target,
+            path,
+            cursor,
+        } => draw_export_overlay(f, ids.len(), *target, path, *cursor),
+        AppMode::Filter { form, field } => draw_filter_overlay(f, form, *field),
+        AppMode::Stats => draw_stats_overlay(f, app),
+        AppMode::Label { message_id } => draw_label_overlay(f, app, *message_id),
         AppMode::Normal => {}
     }
 }
 
+/// Pane title with a marker while the pane waits on a background load.
+fn pane_title(title: String, activity: Option<&str>) -> String {
+    match activity {
+        Some(activity) => format!("{title}· {activity}… "),
+        None => title,
+    }
+}
+
 fn draw_left_pane(f: &mut Frame, app: &App, area: Rect) {
     let is_focused = app.focus == FocusPane::Left;
     let border_style = if is_focused {
@@ -1956,7 +4148,10 @@ fn draw_left_pane(f: &mut Frame, app: &App, area: Rect) {
     f.render_widget(Paragraph::new("").style(base_style), area);
 
     let block = Block::default()
-        .title(format!(" {} ", app.left_pane_view.label()))
+        .title(pane_title(
+            format!(" {} ", app.left_pane_view.label()),
+            app.loading.nav.then_some("loading"),
+        ))
         .borders(Borders::ALL)
         .border_style(border_style)
         .style(base_style);
@@ -1974,16 +4169,25 @@ fn draw_left_pane(f: &mut Frame, app: &App, area: Rect) {
                 NavItem::All => {
                     app.filter.source.is_none()
                         && app.filter.source_adapter.is_none()
+                        && app.filter.group.is_none()
                         && app.filter.workspace.is_none()
                         && app.filter.date_range.is_none()
                 }
-                NavItem::Source { adapter, .. } => {
+                NavItem::Group { name, .. } => {
+                    app.filter.group.as_ref() == Some(name) && app.filter.source_adapter.is_none()
+                }
+                NavItem::Source { adapter, group, .. } => {
                     app.filter.source_adapter.as_ref() == Some(adapter)
+                        && app.filter.group == *group
                 }
                 NavItem::Workspace(ws) => app.filter.workspace.as_ref() == Some(ws),
                 NavItem::DateYear(_) | NavItem::DateMonth(_, _) | NavItem::DateDay(_, _, _) => {
                     false
                 }
+                NavItem::Saved(name) => app
+                    .active_saved
+                    .as_ref()
+                    .is_some_and(|saved| saved.name == *name),
             };
 
             let style = if is_selected && is_focused {
@@ -1998,7 +4202,11 @@ fn draw_left_pane(f: &mut Frame, app: &App, area: Rect) {
 
             let prefix = match item {
                 NavItem::All => " * ",
-                NavItem::Source { .. } | NavItem::Workspace(_) => "   ",
+                NavItem::Source { group: Some(_), .. } => "     ",
+                NavItem::Group { .. }
+                | NavItem::Source { .. }
+                | NavItem::Workspace(_)
+                | NavItem::Saved(_) => "   ",
                 NavItem::DateYear(year) => {
                     let key = format!("year:{year}");
                     if app.expanded_dates.contains(&key) {
@@ -2018,7 +4226,29 @@ fn draw_left_pane(f: &mut Frame, app: &App, area: Rect) {
                 NavItem::DateDay(_, _, _) => "     ",
             };
 
-            ListItem::new(format!("{}{}", prefix, item.label())).style(style)
+            let name = match item {
+                NavItem::Source { adapter, .. } => {
+                    match app
+                        .config
+                        .badges
+                        .lookup(adapter, None)
+                        .and_then(|badge| badge.icon.as_deref())
+                    {
+                        Some(icon) => format!("{icon} {adapter}"),
+                        None => adapter.clone(),
+                    }
+                }
+                NavItem::Workspace(ws) => match app.workspace_names.get(ws) {
+                    Some(name) => format!("@ {name}"),
+                    None => item.label(),
+                },
+                _ => item.label(),
+            };
+            let label = match app.nav_count(item) {
+                Some(count) => format!("{prefix}{name} ({count})"),
+                None => format!("{prefix}{name}"),
+            };
+            ListItem::new(label).style(style)
         })
         .collect();
 
@@ -2041,9 +4271,23 @@ fn draw_middle_pane(f: &mut Frame, app: &App, area: Rect) {
 
     let title = if app.show_search_results && !app.search_results.is_empty() {
         format!(" Search Results ({}) ", app.search_results.len())
+    } else if app.panel_filters.is_active() {
+        format!(
+            " Conversations ({}) · {} ",
+            app.filtered_conversations.len(),
+            app.panel_filters.summary()
+        )
     } else {
         format!(" Conversations ({}) ", app.filtered_conversations.len())
     };
+    let activity = if app.loading.list {
+        Some("loading")
+    } else if app.search_worker.is_busy() {
+        Some("searching")
+    } else {
+        None
+    };
+    let title = pane_title(title, activity);
 
     let block = Block::default()
         .title(title)
@@ -2073,10 +4317,12 @@ fn draw_middle_pane(f: &mut Frame, app: &App, area: Rect) {
                 let title = hit.title.as_deref().unwrap_or("Untitled");
                 let snippet = &hit.snippet;
                 let host = hit.host.as_deref().unwrap_or("local");
-                let source = &hit.source_adapter;
+                let mut detail = vec![Span::raw("    ")];
+                detail.extend(app.source_spans(&hit.source_adapter));
+                detail.push(Span::raw(format!(" | {host} | {snippet}")));
                 ListItem::new(vec![
                     Line::from(title).style(style),
-                    Line::from(format!("    {source} | {host} | {snippet}")).fg(Color::DarkGray),
+                    Line::from(detail).fg(Color::DarkGray),
                 ])
             })
             .collect();
@@ -2106,11 +4352,27 @@ fn draw_middle_pane(f: &mut Frame, app: &App, area: Rect) {
                 let marker = if is_multi_selected { "[x] " } else { "    " };
                 let title = conv.title.as_deref().unwrap_or("Untitled");
                 let date = conv.created_at.format("%Y-%m-%d");
-                let source = &conv.source_id;
+                let mut title_line = vec![Span::raw(marker)];
+                if app.starred_ids.contains(&conv.id) {
+                    title_line.push(Span::styled("* ", Style::default().fg(Color::Yellow)));
+                }
+                if app.new_ids.contains(&conv.id) {
+                    title_line.push(Span::styled(
+                        "NEW ",
+                        Style::default().fg(Color::Green).bold(),
+                    ));
+                }
+                title_line.push(Span::raw(title.to_string()));
 
                 ListItem::new(vec![
-                    Line::from(format!("{marker}{title}")).style(style),
-                    Line::from(format!("      {date} | {source}")).fg(Color::DarkGray),
+                    Line::from(title_line).style(style),
+                    Line::from(
+                        [Span::raw(format!("      {date} | "))]
+                            .into_iter()
+                            .chain(app.source_spans(&conv.source_id))
+                            .collect::<Vec<_>>(),
+                    )
+                    .fg(Color::DarkGray),
                 ])
             })
             .collect();
@@ -2121,45 +4383,311 @@ fn draw_middle_pane(f: &mut Frame, app: &App, area: Rect) {
     }
 }
 
-fn build_message_lines(messages: &[Message], highlight: Option<&str>) -> Vec<Line<'static>> {
-    let mut lines: Vec<Line<'static>> = Vec::new();
-    for msg in messages {
-        let role_style = match msg.role {
-            MessageRole::User => Style::default().fg(Color::Green).bold(),
-            MessageRole::Assistant => Style::default().fg(Color::Blue).bold(),
-            MessageRole::System => Style::default().fg(Color::Yellow).bold(),
-            MessageRole::Tool => Style::default().fg(Color::Magenta).bold(),
-            MessageRole::Other => Style::default().fg(Color::Gray).bold(),
-        };
+fn badge_color(color: BadgeColor) -> Color {
+    match color {
+        BadgeColor::Red => Color::Red,
+        BadgeColor::Green => Color::Green,
+        BadgeColor::Yellow => Color::Yellow,
+        BadgeColor::Blue => Color::Blue,
+        BadgeColor::Magenta => Color::Magenta,
+        BadgeColor::Cyan => Color::Cyan,
+        BadgeColor::White => Color::White,
+        BadgeColor::Gray => Color::DarkGray,
+    }
+}
 
-        let role_label = match msg.role {
-            MessageRole::User => "USER",
-            MessageRole::Assistant => "ASSISTANT",
-            MessageRole::System => "SYSTEM",
-            MessageRole::Tool => "TOOL",
-            MessageRole::Other => "OTHER",
-        };
+/// Which messages the right pane shows collapsed to one line.
+#[derive(Debug, Default)]
+struct MessageFolds {
+    /// Messages folded (`true`) or unfolded by hand, overriding the default.
+    overrides: HashMap<Uuid, bool>,
+    /// Show tool calls and results expanded by default.
+    expand_tools: bool,
+}
+
+impl MessageFolds {
+    fn is_folded(&self, message: &Message) -> bool {
+        self.overrides
+            .get(&message.id)
+            .copied()
+            .unwrap_or_else(|| !self.expand_tools && tool_step_names(message).is_some())
+    }
+}
+
+/// Earlier versions of the open conversation's messages, marked after the
+/// role and listed under each message when `expanded` (`H`).
+#[derive(Debug, Default)]
+struct MessageHistory {
+    conversation_id: Option<Uuid>,
+    /// By message index, oldest first.
+    revisions: HashMap<i32, Vec<MessageRevision>>,
+    expanded: bool,
+}
+
+impl MessageHistory {
+    fn of(&self, message: &Message) -> &[MessageRevision] {
+        if self.conversation_id != Some(message.conversation_id) {
+            return &[];
+        }
+        self.revisions
+            .get(&message.idx)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+}
+
+/// Tool names of `message` when it is a tool step: a tool result, or an
+/// assistant turn that only calls tools.
+fn tool_step_names(message: &Message) -> Option<Vec<String>> {
+    let parts: Vec<Part> = message
+        .parts_json
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| serde_json::from_value(item.clone()).ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    let names = parts.iter().filter_map(|part| match part {
+        Part::ToolCall { name, .. } => Some(name.clone()),
+        Part::ToolResult { name, .. } => name.clone(),
+        _ => None,
+    });
+    match message.role {
+        MessageRole::Tool => Some(names.collect()),
+        MessageRole::Assistant
+            if message.content.trim().is_empty()
+                && parts
+                    .iter()
+                    .any(|part| matches!(part, Part::ToolCall { .. })) =>
+        {
+            Some(names.collect())
+        }
+        _ => None,
+    }
+}
+
+/// The right pane's rendering of a message list. Each block is one message,
+/// or a run of folded tool steps shown as a single summary line.
+struct MessageLayout {
+    lines: Vec<Line<'static>>,
+    /// First line and message indices of each block, in order.
+    blocks: Vec<(usize, std::ops::Range<usize>)>,
+}
+
+impl MessageLayout {
+    /// Index of the block shown at `line`; the last one past the end.
+    fn block_at(&self, line: usize) -> Option<usize> {
+        let after = self.blocks.partition_point(|(start, _)| *start <= line);
+        after.checked_sub(1)
+    }
+
+    /// First line of the block holding message `index`.
+    fn line_of(&self, index: usize) -> Option<usize> {
+        self.blocks
+            .iter()
+            .find(|(_, range)| range.contains(&index))
+            .map(|(start, _)| *start)
+    }
+}
 
-        lines.push(Line::from(Span::styled(
-            format!("[{role_label}]"),
-            role_style,
-        )));
+fn role_label(role: &MessageRole) -> (&'static str, Style) {
+    match role {
+        MessageRole::User => ("USER", Style::default().fg(Color::Green).bold()),
+        MessageRole::Assistant => ("ASSISTANT", Style::default().fg(Color::Blue).bold()),
+        MessageRole::System => ("SYSTEM", Style::default().fg(Color::Yellow).bold()),
+        MessageRole::Tool => ("TOOL", Style::default().fg(Color::Magenta).bold()),
+        MessageRole::Other => ("OTHER", Style::default().fg(Color::Gray).bold()),
+    }
+}
 
-        let content_lines = render_markdown(&msg.content, &msg.role, highlight);
-        lines.extend(content_lines);
+fn layout_messages(
+    messages: &[Message],
+    folds: &MessageFolds,
+    history: &MessageHistory,
+    highlight: Option<&str>,
+) -> MessageLayout {
+    let mut layout = MessageLayout {
+        lines: Vec::new(),
+        blocks: Vec::new(),
+    };
+    let mut i = 0;
+    while i < messages.len() {
+        let start = layout.lines.len();
+        let msg = &messages[i];
+        let folded = folds.is_folded(msg);
+        let tool_names = folded.then(|| tool_step_names(msg)).flatten();
+
+        if let Some(mut names) = tool_names {
+            // Gather the whole run of folded tool steps into one line.
+            let mut end = i + 1;
+            while let Some(next) = messages.get(end).filter(|m| folds.is_folded(m))
+                && let Some(more) = tool_step_names(next)
+            {
+                names.extend(more);
+                end += 1;
+            }
+            let mut unique: Vec<String> = Vec::new();
+            for name in names {
+                if !unique.contains(&name) {
+                    unique.push(name);
+                }
+            }
+            let steps = end - i;
+            let mut summary = format!("▸ {steps} tool step{}", if steps == 1 { "" } else { "s" });
+            if !unique.is_empty() {
+                summary.push_str(&format!(": {}", unique.join(", ")));
+            }
+            layout.lines.push(Line::from(Span::styled(
+                summary,
+                Style::default().fg(Color::Magenta),
+            )));
+            layout.lines.push(Line::from(""));
+            layout.blocks.push((start, i..end));
+            i = end;
+            continue;
+        }
 
-        lines.push(Line::from(""));
+        let (label, style) = role_label(&msg.role);
+        let revisions = history.of(msg);
+        let revised = (!revisions.is_empty()).then(|| {
+            Span::styled(
+                format!(" ↺{}", revisions.len()),
+                Style::default().fg(Color::DarkGray),
+            )
+        });
+        if folded {
+            let total = msg.content.lines().count();
+            let first = msg
+                .content
+                .lines()
+                .find(|line| !line.trim().is_empty())
+                .unwrap_or("")
+                .trim();
+            let mut preview: String = first.chars().take(80).collect();
+            if preview.len() < first.len() || total > 1 {
+                preview.push_str(&format!(" … ({total} lines)"));
+            }
+            let mut line = vec![Span::styled(format!("[{label}] ▸ "), style)];
+            if let Some(badges) = label_badges(msg) {
+                line.push(Span::styled(format!("{badges} "), Color::Yellow));
+            }
+            line.push(Span::styled(preview, Style::default().fg(Color::DarkGray)));
+            line.extend(revised);
+            layout.lines.push(Line::from(line));
+        } else {
+            let mut line = vec![Span::styled(format!("[{label}]"), style)];
+            if let Some(badges) = label_badges(msg) {
+                line.push(Span::styled(format!(" {badges}"), Color::Yellow));
+            }
+            line.extend(revised);
+            layout.lines.push(Line::from(line));
+            layout
+                .lines
+                .extend(render_markdown(&msg.content, &msg.role, highlight));
+            if history.expanded {
+                for revision in revisions {
+                    layout.lines.extend(revision_lines(revision));
+                }
+            }
+        }
+        layout.lines.push(Line::from(""));
+        layout.blocks.push((start, i..i + 1));
+        i += 1;
     }
+    layout
+}
+
+/// An earlier version of a message, dimmed under the current one.
+fn revision_lines(revision: &MessageRevision) -> Vec<Line<'static>> {
+    let verb = match revision.kind {
+        RevisionKind::Edit => "edited",
+        RevisionKind::Regenerate => "regenerated",
+    };
+    let dim = Style::default().fg(Color::DarkGray);
+    let mut lines = vec![
+        Line::from(""),
+        Line::from(Span::styled(
+            format!(
+                "↺ earlier version, {verb} {}",
+                revision.replaced_at.format("%Y-%m-%d %H:%M")
+            ),
+            dim.italic(),
+        )),
+    ];
+    lines.extend(
+        revision
+            .content
+            .lines()
+            .map(|line| Line::from(Span::styled(line.to_string(), dim))),
+    );
     lines
 }
 
-fn first_match_scroll(messages: &[Message], query: &str) -> Option<usize> {
+/// The labels of `message`, as shown after its role.
+fn label_badges(message: &Message) -> Option<String> {
+    let labels = labels::labels(&message.metadata);
+    if labels.is_empty() {
+        return None;
+    }
+    let badges: Vec<String> = labels
+        .into_iter()
+        .map(|label| {
+            QUICK_LABELS
+                .iter()
+                .find(|quick| quick.name == label)
+                .map_or_else(|| format!("#{label}"), |quick| quick.emoji.to_string())
+        })
+        .collect();
+    Some(badges.join(" "))
+}
+
+/// Replace the labels in `message`'s metadata.
+fn set_message_labels(message: &mut Message, labels: &[String]) {
+    if !message.metadata.is_object() {
+        message.metadata = serde_json::json!({});
+    }
+    if let Some(object) = message.metadata.as_object_mut() {
+        if labels.is_empty() {
+            object.remove(labels::METADATA_KEY);
+        } else {
+            object.insert(labels::METADATA_KEY.to_string(), serde_json::json!(labels));
+        }
+    }
+}
+
+/// The message at `line` of the layout, or the last one when `line` is
+/// past the end.
+fn message_at_line<'a>(
+    messages: &'a [Message],
+    folds: &MessageFolds,
+    history: &MessageHistory,
+    line: usize,
+) -> Option<&'a Message> {
+    let layout = layout_messages(messages, folds, history, None);
+    layout
+        .block_at(line)
+        .and_then(|block| messages.get(layout.blocks[block].1.start))
+}
+
+/// Index of the first message containing `query`.
+fn first_match_message(messages: &[Message], query: &str) -> Option<usize> {
+    let needle = query.trim().to_lowercase();
+    if needle.is_empty() {
+        return None;
+    }
+    messages
+        .iter()
+        .position(|message| message.content.to_lowercase().contains(&needle))
+}
+
+fn first_match_scroll(layout: &MessageLayout, query: &str) -> Option<usize> {
     let needle = query.trim().to_lowercase();
     if needle.is_empty() {
         return None;
     }
-    let lines = build_message_lines(messages, None);
-    for (idx, line) in lines.iter().enumerate() {
+    for (idx, line) in layout.lines.iter().enumerate() {
         if line
             .spans
             .iter()
@@ -2183,8 +4711,34 @@ fn draw_right_pane(f: &mut Frame, app: &App, area: Rect) {
 
     f.render_widget(Paragraph::new("").style(base_style), area);
 
+    let loaded = i64::try_from(app.messages.len()).unwrap_or(0);
+    let title = if app.branches.len() > 1 {
+        format!(
+            " Messages · branch {}/{} ([ ] to switch) ",
+            app.branch_index + 1,
+            app.branches.len()
+        )
+    } else if loaded > 0 && loaded < app.message_total {
+        format!(
+            " Messages {}-{} of {} ",
+            app.message_offset + 1,
+            app.message_offset + loaded,
+            app.message_total
+        )
+    } else {
+        " Messages ".to_string()
+    };
+    let title = match &app.related_sources {
+        Some((id, sources))
+            if !sources.is_empty() && app.selected_conversation_id() == Some(*id) =>
+        {
+            format!("{title}· also imported from {} ", sources.join(", "))
+        }
+        _ => title,
+    };
+    let title = pane_title(title, app.loading.detail.then_some("loading"));
     let block = Block::default()
-        .title(" Messages ")
+        .title(title)
         .borders(Borders::ALL)
         .border_style(border_style)
         .style(base_style);
@@ -2203,7 +4757,10 @@ fn draw_right_pane(f: &mut Frame, app: &App, area: Rect) {
                     conv.created_at.format("%Y-%m-%d %H:%M")
                 )),
                 if let Some(ws) = &conv.workspace {
-                    Line::from(format!("Workspace: {ws}"))
+                    match app.workspace_names.get(ws) {
+                        Some(name) => Line::from(format!("Workspace: {name} ({ws})")),
+                        None => Line::from(format!("Workspace: {ws}")),
+                    }
                 } else {
                     Line::from("")
                 },
@@ -2213,12 +4770,16 @@ fn draw_right_pane(f: &mut Frame, app: &App, area: Rect) {
                     Line::from("")
                 },
                 Line::from(""),
-                Line::from("No messages loaded. Press Enter to load.").fg(Color::DarkGray),
+                if app.loading.detail {
+                    Line::from("Loading messages...").fg(Color::DarkGray)
+                } else {
+                    Line::from("No messages loaded. Press Enter to load.").fg(Color::DarkGray)
+                },
             ];
             let paragraph = Paragraph::new(info).style(base_style);
             f.render_widget(paragraph, inner);
         } else {
-            let paragraph = Paragraph::new("No conversation selected")
+            let paragraph = Paragraph::new(tr!("tui-no-conversation"))
                 .fg(Color::DarkGray)
                 .style(base_style);
             f.render_widget(paragraph, inner);
@@ -2231,7 +4792,7 @@ fn draw_right_pane(f: &mut Frame, app: &App, area: Rect) {
     } else {
         None
     };
-    let lines = build_message_lines(&app.messages, highlight);
+    let lines = layout_messages(&app.messages, &app.folds, &app.history, highlight).lines;
 
     // Apply scroll offset
     let scroll_offset = app.detail_scroll.min(lines.len().saturating_sub(1));
@@ -2299,10 +4860,22 @@ fn draw_help_overlay(f: &mut Frame, scroll: usize) {
         Line::from("  Ctrl-d        Page down"),
         Line::from("  Ctrl-u        Page up"),
         Line::from("  Enter         Select/expand"),
+        Line::from("  Click         Focus pane and select entry"),
+        Line::from("  Double-click  Open entry"),
+        Line::from("  Wheel         Scroll the pane under the pointer"),
+        Line::from(""),
+        Line::from("MESSAGES").bold(),
+        Line::from(""),
+        Line::from("  n / p         Next/previous message"),
+        Line::from("  z             Fold/unfold current message"),
+        Line::from("  Z             Fold/unfold all messages"),
+        Line::from("  T             Show/fold tool calls and results"),
+        Line::from("  H             Show/hide earlier versions of edited messages"),
+        Line::from("  m             Label current message (💡 insight, 🐛 bug, 📌 keep)"),
         Line::from(""),
         Line::from("LEFT PANE").bold(),
         Line::from(""),
-        Line::from("  Tab           Cycle view (Sources/Workspaces/Dates)"),
+        Line::from("  Tab           Cycle view (Sources/Workspaces/Dates/Saved)"),
         Line::from("  Enter         Expand/collapse or filter"),
         Line::from(""),
         Line::from("SELECTION").bold(),
@@ -2315,14 +4888,23 @@ fn draw_help_overlay(f: &mut Frame, scroll: usize) {
         Line::from(""),
         Line::from("  /             Search"),
         Line::from("  s             Sort options"),
-        Line::from("  d             Delete selected"),
+        Line::from("  S             Statistics"),
+        Line::from("  f / F         Filter by role, model, provider, harness, dates / clear"),
+        Line::from("  d / u         Move selected to the trash / undo"),
+        Line::from("  e             Export selected"),
+        Line::from("  y             Copy current message"),
+        Line::from("  Y             Copy conversation as markdown"),
+        Line::from("  o / O         Open conversation in $PAGER / $EDITOR"),
         Line::from("  r             Refresh data"),
+        Line::from("  R             Mark all as read"),
+        Line::from("  *             Star/unstar conversation"),
+        Line::from("  [ / ]         Previous/next branch of a branched conversation"),
         Line::from("  ?             Toggle help"),
         Line::from("  q             Quit"),
         Line::from(""),
         Line::from("SEARCH MODE").bold(),
         Line::from(""),
-        Line::from("  Enter         Execute search"),
+        Line::from("  Enter         Search now (also runs as you type)"),
         Line::from("  Esc           Exit search input (keep results)"),
         Line::from("  Tab           Toggle search scope"),
         Line::from("  Up/Down       Navigate results"),
@@ -2426,6 +5008,307 @@ fn draw_search_overlay(f: &mut Frame, query: &str, cursor: usize, scope: SearchS
     f.render_widget(paragraph, inner);
 }
 
+fn draw_filter_overlay(f: &mut Frame, form: &PanelFilters, field: usize) {
+    let area = centered_rect(50, 40, f.area());
+
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Filter Conversations ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Magenta))
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let mut text = vec![Line::from("")];
+    for (i, label) in FILTER_FIELDS.iter().enumerate() {
+        let value = form.value(i);
+        let value = if i == 0 && field == 0 {
+            format!("◂ {value} ▸")
+        } else if i == field {
+            format!("{value}█")
+        } else {
+            value
+        };
+        let style = if i == field {
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        text.push(Line::from(vec![
+            Span::styled(format!("  {label:<10}"), style),
+            Span::raw(value),
+        ]));
+    }
+    text.push(Line::from(""));
+    text.push(
+        Line::from("  Model, provider and harness match substrings; dates are YYYY-MM-DD")
+            .fg(Color::DarkGray),
+    );
+    text.push(
+        Line::from("  Tab/↑↓ field · ←→ role · Del clear · Enter apply · Esc cancel")
+            .fg(Color::DarkGray),
+    );
+
+    f.render_widget(Paragraph::new(text), inner);
+}
+
+fn draw_stats_overlay(f: &mut Frame, app: &App) {
+    let area = centered_rect(90, 90, f.area());
+
+    f.render_widget(Clear, area);
+
+    let title = pane_title(
+        " Statistics (r refresh, q/Esc close) ".to_string(),
+        app.loading.stats.then_some("loading"),
+    );
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan))
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let Some(stats) = &app.stats else {
+        f.render_widget(Paragraph::new("  Loading…"), inner);
+        return;
+    };
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(4),
+            Constraint::Length(7),
+            Constraint::Min(0),
+        ])
+        .split(inner);
+
+    let label = Style::default().fg(Color::DarkGray);
+    let totals = vec![
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("  Conversations ", label),
+            Span::raw(format!("{:<10}", stats.conversations)),
+            Span::styled("Messages ", label),
+            Span::raw(format!("{:<10}", stats.messages)),
+            Span::styled("Today ", label),
+            Span::raw(format!("{:<6}", stats.activity.today)),
+            Span::styled("Week ", label),
+            Span::raw(format!("{:<6}", stats.activity.week)),
+            Span::styled("Month ", label),
+            Span::raw(stats.activity.month.to_string()),
+        ]),
+        Line::from(vec![
+            Span::styled("  Tokens in     ", label),
+            Span::raw(format!("{:<10}", compact_count(stats.usage.tokens_in))),
+            Span::styled("Out      ", label),
+            Span::raw(format!("{:<10}", compact_count(stats.usage.tokens_out))),
+            Span::styled("Cost ", label),
+            Span::raw(format!("${:.2}", stats.usage.cost_usd)),
+        ]),
+    ];
+    f.render_widget(Paragraph::new(totals), rows[0]);
+
+    let spark_block = Block::default().borders(Borders::ALL);
+    let days = usize::from(spark_block.inner(rows[1]).width);
+    let per_day = &stats.per_day[stats.per_day.len().saturating_sub(days)..];
+    let peak = per_day.iter().map(|(_, n)| *n).max().unwrap_or(0);
+    let since = per_day
+        .first()
+        .map(|(day, _)| day.format("%Y-%m-%d").to_string())
+        .unwrap_or_default();
+    let data: Vec<u64> = per_day
+        .iter()
+        .map(|(_, n)| u64::try_from(*n).unwrap_or(0))
+        .collect();
+    f.render_widget(
+        Sparkline::default()
+            .block(spark_block.title(format!(
+                " Conversations per day since {since} (peak {peak}) "
+            )))
+            .data(&data)
+            .style(Style::default().fg(Color::Green)),
+        rows[1],
+    );
+
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage(34),
+            Constraint::Percentage(33),
+            Constraint::Percentage(33),
+        ])
+        .split(rows[2]);
+    let workspaces: Vec<(String, i64)> = stats
+        .workspaces
+        .iter()
+        .map(|(path, n)| (shorten_path(path), *n))
+        .collect();
+    let sources: Vec<(String, i64)> = stats
+        .sources
+        .iter()
+        .take(STATS_TOP)
+        .map(|source| (source.source_id.clone(), source.conversations))
+        .collect();
+    for (area, title, items) in [
+        (columns[0], " Top models ", &stats.models),
+        (columns[1], " Top workspaces ", &workspaces),
+        (columns[2], " Sources ", &sources),
+    ] {
+        let block = Block::default().title(title).borders(Borders::ALL);
+        let width = usize::from(block.inner(area).width);
+        f.render_widget(Paragraph::new(bar_lines(items, width)).block(block), area);
+    }
+}
+
+/// One row per item: name, a bar scaled to the largest count, and the count.
+fn bar_lines(items: &[(String, i64)], width: usize) -> Vec<Line<'static>> {
+    if items.is_empty() {
+        return vec![Line::from(" none").fg(Color::DarkGray)];
+    }
+    let max = items.iter().map(|(_, n)| *n).max().unwrap_or(0).max(1);
+    let name_width = (width / 2).clamp(4, 28);
+    let bar_width = width.saturating_sub(name_width + 9);
+    items
+        .iter()
+        .map(|(name, n)| {
+            let len = usize::try_from(*n * bar_width as i64 / max).unwrap_or(0);
+            Line::from(vec![
+                Span::raw(format!(" {:<name_width$} ", truncate_str(name, name_width))),
+                Span::styled("█".repeat(len.max(1)), Style::default().fg(Color::Cyan)),
+                Span::raw(format!(" {n}")),
+            ])
+        })
+        .collect()
+}
+
+/// `1234567` as `1.2M`.
+fn compact_count(n: i64) -> String {
+    match n {
+        n if n >= 1_000_000 => format!("{:.1}M", n as f64 / 1_000_000.0),
+        n if n >= 1_000 => format!("{:.1}k", n as f64 / 1_000.0),
+        n => n.to_string(),
+    }
+}
+
+fn draw_export_overlay(
+    f: &mut Frame,
+    count: usize,
+    target: ExportTarget,
+    path: &str,
+    cursor: usize,
+) {
+    let area = centered_rect(60, 30, f.area());
+
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Export ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan))
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let formats: Vec<Span> = [
+        ExportTarget::Markdown,
+        ExportTarget::Json,
+        ExportTarget::Html,
+    ]
+    .into_iter()
+    .flat_map(|t| {
+        let style = if t == target {
+            Style::default().fg(Color::Black).bg(Color::Cyan)
+        } else {
+            Style::default().fg(Color::DarkGray)
+        };
+        [
+            Span::styled(format!(" {} ", t.label()), style),
+            Span::raw(" "),
+        ]
+    })
+    .collect();
+
+    let before_cursor = &path[..cursor];
+    let cursor_char = path.chars().nth(cursor).unwrap_or(' ');
+    let after_cursor = if cursor < path.len() {
+        &path[cursor + 1..]
+    } else {
+        ""
+    };
+
+    let text = vec![
+        Line::from(""),
+        Line::from(format!("Export {count} conversation(s)")).bold(),
+        Line::from(""),
+        Line::from(formats),
+        Line::from(""),
+        Line::from(vec![
+            Span::raw("Path: "),
+            Span::raw(before_cursor),
+            Span::styled(
+                cursor_char.to_string(),
+                Style::default().bg(Color::White).fg(Color::Black),
+            ),
+            Span::raw(after_cursor),
+        ]),
+        Line::from(""),
+        Line::from("Tab: format  Enter: export  Esc: cancel").fg(Color::DarkGray),
+    ];
+
+    let paragraph = Paragraph::new(text).alignment(ratatui::layout::Alignment::Center);
+    f.render_widget(paragraph, inner);
+}
+
+fn draw_label_overlay(f: &mut Frame, app: &App, message_id: Uuid) {
+    let area = centered_rect(40, 25, f.area());
+
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Label Message ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow))
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let current: Vec<&str> = app
+        .messages
+        .iter()
+        .find(|m| m.id == message_id)
+        .map(|m| labels::labels(&m.metadata))
+        .unwrap_or_default();
+    let mut text = vec![Line::from("")];
+    for (i, quick) in QUICK_LABELS.iter().enumerate() {
+        let marker = if current.contains(&quick.name) {
+            " * "
+        } else {
+            "   "
+        };
+        text.push(Line::from(vec![
+            Span::raw(marker),
+            Span::styled(
+                format!(" {} ", quick.key),
+                Style::default().fg(Color::Black).bg(Color::Yellow),
+            ),
+            Span::raw(format!(" {}. {} {}", i + 1, quick.emoji, quick.name)),
+        ]));
+    }
+    text.push(Line::from(""));
+    text.push(Line::from("Press again to remove · Esc to cancel").fg(Color::DarkGray));
+
+    f.render_widget(Paragraph::new(text), inner);
+}
+
 fn draw_delete_overlay(f: &mut Frame, count: usize) {
     let area = centered_rect(50, 20, f.area());
 
@@ -2444,7 +5327,7 @@ fn draw_delete_overlay(f: &mut Frame, count: usize) {
         Line::from(""),
         Line::from(format!("Delete {count} conversation(s)?")).bold(),
         Line::from(""),
-        Line::from("This action cannot be undone.").fg(Color::DarkGray),
+        Line::from("They move to the trash; u undoes.").fg(Color::DarkGray),
         Line::from(""),
         Line::from(vec![
             Span::styled(" y ", Style::default().fg(Color::Black).bg(Color::Red)),